@@ -0,0 +1,184 @@
+//! Generates the usage page enums in `src/page.rs` from the usage tables in `hut/`
+//!
+//! Each `hut/<page>.txt` file describes one usage page, line by line:
+//!
+//! * `enum <Name> <u8|u16>` - name and representation of the enum, before any usages
+//! * `/// <text>` - doc comment of the enum
+//! * `<ID>\t<Name>` - usage with a hexadecimal ID and its variant name
+//! * `<ID>\tReserved` or `<ID>-<ID>\tReserved` - reserved IDs, which have no variant
+//! * `# <text>` or a blank line - ignored
+//!
+//! Usages and reserved IDs are in ascending order, and usage `0` is the default variant. Reserved
+//! ranges may run past the representation, to the end of the page. Variant names are the names
+//! from the usage tables converted to identifiers: words capitalised, spaces and punctuation
+//! removed, and names starting with a digit transliterated.
+//!
+//! The enum of `hut/<page>.txt` is written to `$OUT_DIR/page/<page>.rs`.
+//!
+//! The tables are transcribed from the HID Usage Tables, version 1.4. They are not generated from
+//! the machine readable JSON release of the usage tables, which is not vendored here; when it is,
+//! this script should read it directly and the tables should shrink to the enum names and any
+//! variant names that differ from the converted usage names.
+
+use std::env;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const TABLES: &str = "hut";
+
+const DERIVES: &str = "#[derive(
+    Debug,
+    Copy,
+    Clone,
+    Eq,
+    PartialEq,
+    Ord,
+    PartialOrd,
+    Hash,
+    PrimitiveEnum,
+    IntoPrimitive,
+    FromPrimitive,
+    Default,
+)]";
+
+struct Page {
+    name: String,
+    repr: &'static str,
+    docs: Vec<String>,
+    usages: Vec<(u32, String)>,
+}
+
+fn main() {
+    println!("cargo:rerun-if-changed={TABLES}");
+
+    let out = PathBuf::from(env::var_os("OUT_DIR").unwrap()).join("page");
+    fs::create_dir_all(&out).unwrap();
+
+    let mut tables: Vec<_> = fs::read_dir(TABLES)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "txt"))
+        .collect();
+    tables.sort();
+
+    for table in tables {
+        let page = parse(&table);
+        let file = table.with_extension("rs");
+        fs::write(out.join(file.file_name().unwrap()), generate(&page)).unwrap();
+    }
+}
+
+fn parse(table: &Path) -> Page {
+    let text = fs::read_to_string(table).unwrap();
+    let mut page: Option<Page> = None;
+    //First ID not yet used by a usage or reserved range
+    let mut next_id = 0;
+
+    for (i, line) in text.lines().enumerate() {
+        let fail =
+            |message: &str| -> ! { panic!("{}:{}: {message}: {line:?}", table.display(), i + 1) };
+
+        if line.trim().is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        if let Some(header) = line.strip_prefix("enum ") {
+            if page.is_some() {
+                fail("more than one enum");
+            }
+            let (name, repr) = header
+                .split_once(' ')
+                .unwrap_or_else(|| fail("missing repr"));
+            let repr = match repr {
+                "u8" => "u8",
+                "u16" => "u16",
+                _ => fail("repr must be u8 or u16"),
+            };
+            page = Some(Page {
+                name: name.to_owned(),
+                repr,
+                docs: Vec::new(),
+                usages: Vec::new(),
+            });
+            continue;
+        }
+
+        let page = page
+            .as_mut()
+            .unwrap_or_else(|| fail("expected an enum line first"));
+
+        if let Some(doc) = line.strip_prefix("///") {
+            if !page.usages.is_empty() {
+                fail("doc comment after usages");
+            }
+            page.docs.push(doc.to_owned());
+            continue;
+        }
+
+        let (ids, name) = line
+            .split_once('\t')
+            .unwrap_or_else(|| fail("expected a tab separated ID and name"));
+        let (first, last) = ids.split_once('-').unwrap_or((ids, ids));
+        let parse_id = |id: &str| {
+            u32::from_str_radix(id, 16).unwrap_or_else(|_| fail("invalid hexadecimal ID"))
+        };
+        let (first, last) = (parse_id(first), parse_id(last));
+
+        if first < next_id || last < first {
+            fail("IDs out of order");
+        }
+        if last > 0xFFFF {
+            fail("ID does not fit in u16");
+        }
+        next_id = last + 1;
+
+        if name == "Reserved" {
+            continue;
+        }
+        if first != last {
+            fail("only reserved IDs can be ranges");
+        }
+        if page.repr == "u8" && first > 0xFF {
+            fail("ID does not fit in u8");
+        }
+        if !is_identifier(name) {
+            fail("name is not an identifier");
+        }
+        if page.usages.iter().any(|(_, n)| n == name) {
+            fail("duplicate name");
+        }
+        page.usages.push((first, name.to_owned()));
+    }
+
+    let page = page.unwrap_or_else(|| panic!("{}: missing enum line", table.display()));
+    if page.usages.first().map(|(id, _)| *id) != Some(0) {
+        panic!("{}: missing usage 0 for the default", table.display());
+    }
+    page
+}
+
+fn is_identifier(name: &str) -> bool {
+    name.starts_with(|c: char| c.is_ascii_alphabetic())
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+fn generate(page: &Page) -> String {
+    let mut code = String::new();
+    for doc in &page.docs {
+        writeln!(code, "///{doc}").unwrap();
+    }
+    writeln!(code, "{DERIVES}").unwrap();
+    writeln!(code, "#[repr({})]", page.repr).unwrap();
+    writeln!(code, "pub enum {} {{", page.name).unwrap();
+    //Hexadecimal digits of the representation, plus the 0x prefix
+    let width = if page.repr == "u8" { 4 } else { 6 };
+    for (i, (id, name)) in page.usages.iter().enumerate() {
+        if i == 0 {
+            writeln!(code, "    #[default]").unwrap();
+        }
+        writeln!(code, "    {name} = {id:#0width$X},").unwrap();
+    }
+    writeln!(code, "}}").unwrap();
+    code
+}
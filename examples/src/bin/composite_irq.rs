@@ -103,7 +103,8 @@ fn main() -> ! {
             usbd_human_interface_device::device::consumer::ConsumerControlInterface::default_config(),
         )
         //Build
-        .build(usb_alloc);
+        .build(usb_alloc)
+        .unwrap();
 
     //https://pid.codes
     let usb_dev = UsbDeviceBuilder::new(usb_alloc, UsbVidPid(0x1209, 0x0001))
@@ -161,7 +162,7 @@ fn main() -> ! {
                 let keys = get_keyboard_keys(key_pins);
 
                 let keyboard = composite.interface::<NKROBootKeyboardInterface<'_, _>, _>();
-                match keyboard.write_report(keys) {
+                match keyboard.write_report(&NKROBootKeyboardReport::new(keys)) {
                     Err(UsbHidError::WouldBlock) => {}
                     Err(UsbHidError::Duplicate) => {}
                     Ok(_) => {}
@@ -208,7 +209,7 @@ fn main() -> ! {
                 if last_consumer_report != consumer_report {
                     let consumer = composite.interface::<ConsumerControlInterface<'_, _>, _>();
                     match consumer.write_report(&consumer_report) {
-                        Err(UsbError::WouldBlock) => {}
+                        Err(UsbHidError::WouldBlock) => {}
                         Ok(_) => {
                             last_consumer_report = consumer_report;
                         }
@@ -263,7 +264,7 @@ fn USBCTRL_IRQ() {
         if usb_device.poll(&mut [composite]) {
             let keyboard = composite.interface::<NKROBootKeyboardInterface<'_, _>, _>();
             match keyboard.read_report() {
-                Err(UsbError::WouldBlock) => {}
+                Err(UsbHidError::WouldBlock) => {}
                 Err(e) => {
                     core::panic!("Failed to read keyboard report: {:?}", e)
                 }
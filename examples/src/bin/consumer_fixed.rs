@@ -60,7 +60,8 @@ fn main() -> ! {
         .add_interface(
             usbd_human_interface_device::device::consumer::ConsumerControlFixedInterface::default_config(),
         )
-        .build(&usb_bus);
+        .build(&usb_bus)
+        .unwrap();
     //https://pid.codes
     let mut usb_dev = UsbDeviceBuilder::new(&usb_bus, UsbVidPid(0x1209, 0x0001))
         .manufacturer("usbd-human-interface-device")
@@ -99,7 +100,7 @@ fn main() -> ! {
             let report = get_report(keys);
             if report != last {
                 match consumer.interface().write_report(&report) {
-                    Err(UsbError::WouldBlock) => {}
+                    Err(UsbHidError::WouldBlock) => {}
                     Ok(_) => {
                         last = report;
                     }
@@ -62,7 +62,8 @@ fn main() -> ! {
             usbd_human_interface_device::device::consumer::ConsumerControlInterface::default_config(
             ),
         )
-        .build(&usb_bus);
+        .build(&usb_bus)
+        .unwrap();
 
     //https://pid.codes
     let mut usb_dev = UsbDeviceBuilder::new(&usb_bus, UsbVidPid(0x1209, 0x0001))
@@ -102,7 +103,7 @@ fn main() -> ! {
             let report = get_report(keys);
             if report != last {
                 match consumer.interface().write_report(&report) {
-                    Err(UsbError::WouldBlock) => {}
+                    Err(UsbHidError::WouldBlock) => {}
                     Ok(_) => {
                         last = report;
                     }
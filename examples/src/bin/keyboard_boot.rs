@@ -61,7 +61,8 @@ fn main() -> ! {
         .add_interface(
             usbd_human_interface_device::device::keyboard::BootKeyboardInterface::default_config(),
         )
-        .build(&usb_bus);
+        .build(&usb_bus)
+        .unwrap();
 
     //https://pid.codes
     let mut usb_dev = UsbDeviceBuilder::new(&usb_bus, UsbVidPid(0x1209, 0x0001))
@@ -99,7 +100,10 @@ fn main() -> ! {
         if input_count_down.wait().is_ok() {
             let keys = get_keys(keys);
 
-            match keyboard.interface().write_report(keys) {
+            match keyboard
+                .interface()
+                .write_report(&BootKeyboardReport::new(keys))
+            {
                 Err(UsbHidError::WouldBlock) => {}
                 Err(UsbHidError::Duplicate) => {}
                 Ok(_) => {}
@@ -122,7 +126,7 @@ fn main() -> ! {
 
         if usb_dev.poll(&mut [&mut keyboard]) {
             match keyboard.interface().read_report() {
-                Err(UsbError::WouldBlock) => {
+                Err(UsbHidError::WouldBlock) => {
                     //do nothing
                 }
                 Err(e) => {
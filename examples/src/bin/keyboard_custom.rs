@@ -20,6 +20,7 @@ use usb_device::prelude::*;
 use usbd_human_interface_device::device::keyboard::{BootKeyboardReport, KeyboardLedsReport};
 use usbd_human_interface_device::hid_class::prelude::*;
 use usbd_human_interface_device::page::Keyboard;
+use usbd_human_interface_device::UsbHidError;
 
 use rp_pico as bsp;
 
@@ -103,15 +104,14 @@ fn main() -> ! {
         .add_interface(
             RawInterfaceBuilder::new(LOGITECH_GAMING_KEYBOARD_REPORT_DESCRIPTOR)
                 .description("Custom Keyboard")
-                .idle_default(500.millis())
-                .unwrap()
-                .in_endpoint(UsbPacketSize::Bytes8, 10.millis())
-                .unwrap()
-                .with_out_endpoint(UsbPacketSize::Bytes8, 100.millis())
-                .unwrap()
-                .build(),
+                .idle_default(IdleDuration::millis(500))
+                .in_endpoint(UsbPacketSize::Bytes8, PollInterval::millis(10))
+                .with_out_endpoint(UsbPacketSize::Bytes8, PollInterval::millis(100))
+                .build()
+                .unwrap(),
         )
-        .build(&usb_bus);
+        .build(&usb_bus)
+        .unwrap();
 
     //https://pid.codes
     let mut usb_dev = UsbDeviceBuilder::new(&usb_bus, UsbVidPid(0x1209, 0x0001))
@@ -166,7 +166,7 @@ fn main() -> ! {
                     .interface()
                     .write_report(&BootKeyboardReport::new(keys).pack().unwrap())
                 {
-                    Err(UsbError::WouldBlock) => {}
+                    Err(UsbHidError::WouldBlock) => {}
                     Ok(_) => {
                         last_keys = Some(keys);
                         idle_count_down = reset_idle(&timer, keyboard.interface().global_idle());
@@ -181,7 +181,7 @@ fn main() -> ! {
         if usb_dev.poll(&mut [&mut keyboard]) {
             let data = &mut [0];
             match keyboard.interface().read_report(data) {
-                Err(UsbError::WouldBlock) => {
+                Err(UsbHidError::WouldBlock) => {
                     //do nothing
                 }
                 Err(e) => {
@@ -94,7 +94,8 @@ mod app {
         .add_interface(
             usbd_human_interface_device::device::keyboard::NKROBootKeyboardInterface::default_config(),
         )
-        .build(usb_alloc);
+        .build(usb_alloc)
+        .unwrap();
 
         // https://pid.codes
         let usb_device = UsbDeviceBuilder::new(usb_alloc, UsbVidPid(0x1209, 0x0001))
@@ -144,13 +145,13 @@ mod app {
     )]
     fn write_keyboard(mut cx: write_keyboard::Context, scheduled: Instant) {
         cx.shared.keyboard.lock(|k| {
-            match k
-                .interface()
-                .write_report([if cx.local.key.is_low().unwrap() {
+            match k.interface().write_report(&NKROBootKeyboardReport::new([
+                if cx.local.key.is_low().unwrap() {
                     Keyboard::A
                 } else {
                     Keyboard::NoEventIndicated
-                }]) {
+                },
+            ])) {
                 Err(UsbHidError::WouldBlock) => {}
                 Err(UsbHidError::Duplicate) => {}
                 Ok(_) => {}
@@ -174,7 +175,7 @@ mod app {
             if usb_device.poll(&mut [keyboard]) {
                 let interface = keyboard.interface();
                 match interface.read_report() {
-                    Err(UsbError::WouldBlock) => {}
+                    Err(UsbHidError::WouldBlock) => {}
                     Err(e) => {
                         core::panic!("Failed to read keyboard report: {:?}", e)
                     }
@@ -56,7 +56,8 @@ fn main() -> ! {
         .add_interface(
             usbd_human_interface_device::device::mouse::WheelMouseInterface::default_config(),
         )
-        .build(&usb_bus);
+        .build(&usb_bus)
+        .unwrap();
 
     //https://pid.codes
     let mut usb_dev = UsbDeviceBuilder::new(&usb_bus, UsbVidPid(0x1209, 0x0001))
@@ -0,0 +1,9 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use usbd_human_interface_device::simulation::fuzz::handle_control;
+
+fuzz_target!(|input: &[u8]| {
+    let (setup, data) = input.split_at(input.len().min(8));
+    handle_control(setup, data);
+});
@@ -0,0 +1,352 @@
+//! HID report descriptor parsing
+//!
+//! Reduces a report descriptor to the fields of a single input, output or feature report, selected
+//! by report ID in descriptors with more than one. Defined
+//! in section 6.2.2 of [Device Class Definition for Human Interface Devices (Hid) Version
+//! 1.11](<https://www.usb.org/sites/default/files/hid1_11.pdf>)
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReportKind {
+    Input,
+    Output,
+    Feature,
+}
+
+impl ReportKind {
+    fn tag(self) -> u8 {
+        match self {
+            ReportKind::Input => 0x8,
+            ReportKind::Output => 0x9,
+            ReportKind::Feature => 0xB,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Field {
+    pub name: String,
+    /// Offset in bits from the start of the report, excluding the report ID
+    pub offset: usize,
+    pub size: usize,
+    pub count: usize,
+    pub signed: bool,
+    /// All `count` values are packed into a single integer field, e.g. a bitmap of buttons
+    pub bitmap: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Report {
+    pub id: Option<u8>,
+    pub bits: usize,
+    pub fields: Vec<Field>,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct Globals {
+    usage_page: u16,
+    logical_minimum: i32,
+    report_size: usize,
+    report_count: usize,
+    report_id: Option<u8>,
+}
+
+#[derive(Debug, Clone, Default)]
+struct Locals {
+    usages: Vec<(u16, u16)>,
+    usage_minimum: Option<(u16, u16)>,
+}
+
+const USAGE_PAGE_GENERIC_DESKTOP: u16 = 0x01;
+const USAGE_PAGE_KEYBOARD: u16 = 0x07;
+const USAGE_PAGE_LEDS: u16 = 0x08;
+const USAGE_PAGE_BUTTON: u16 = 0x09;
+const USAGE_PAGE_CONSUMER: u16 = 0x0C;
+
+/// Parses `descriptor`, returning the layout of the `kind` report with ID `report_id`
+///
+/// `report_id` may be `None` if the descriptor has only one `kind` report.
+pub fn parse(descriptor: &[u8], kind: ReportKind, report_id: Option<u8>) -> Result<Report, String> {
+    let mut globals = Globals::default();
+    let mut stack = Vec::new();
+    let mut locals = Locals::default();
+    let mut report: Option<Report> = None;
+    let mut offset = 0;
+    let mut i = 0;
+
+    while i < descriptor.len() {
+        let prefix = descriptor[i];
+        if prefix == 0xFE {
+            return Err(format!("long items are not supported, at byte {i}"));
+        }
+        let len = match prefix & 0x3 {
+            3 => 4,
+            n => n as usize,
+        };
+        let data = descriptor
+            .get(i + 1..i + 1 + len)
+            .ok_or_else(|| format!("truncated item at byte {i}"))?;
+        let unsigned = data
+            .iter()
+            .rev()
+            .fold(0_u32, |acc, &b| (acc << 8) | u32::from(b));
+        let signed = match len {
+            0 => 0,
+            1 => i32::from(data[0] as i8),
+            2 => i32::from(unsigned as u16 as i16),
+            _ => unsigned as i32,
+        };
+        let tag = prefix >> 4;
+
+        match (prefix >> 2) & 0x3 {
+            // Main
+            0 => {
+                let selected = report_id.is_none_or(|id| globals.report_id == Some(id));
+                if tag == kind.tag() && selected {
+                    if globals.report_size == 0 || globals.report_count == 0 {
+                        return Err(format!(
+                            "main item without report size or count at byte {i}"
+                        ));
+                    }
+                    let report = match &mut report {
+                        Some(r) if r.id != globals.report_id => {
+                            return Err(format!(
+                                "descriptor has {kind:?} reports with more than one report id, \
+                                 select one with `{kind:?}(id)`"
+                            ));
+                        }
+                        Some(r) => r,
+                        None => report.insert(Report {
+                            id: globals.report_id,
+                            bits: 0,
+                            fields: Vec::new(),
+                        }),
+                    };
+                    let constant = unsigned & 0x1 != 0;
+                    let variable = unsigned & 0x2 != 0;
+                    if !constant {
+                        add_fields(report, &globals, &locals, variable, offset);
+                    }
+                    offset += globals.report_size * globals.report_count;
+                    report.bits = offset;
+                }
+                locals = Locals::default();
+            }
+            // Global
+            1 => match tag {
+                0x0 => globals.usage_page = unsigned as u16,
+                0x1 => globals.logical_minimum = signed,
+                0x7 => globals.report_size = unsigned as usize,
+                0x8 => {
+                    let id = u8::try_from(unsigned)
+                        .map_err(|_| format!("report id out of range at byte {i}"))?;
+                    globals.report_id = Some(id);
+                }
+                0x9 => globals.report_count = unsigned as usize,
+                0xA => stack.push(globals),
+                0xB => {
+                    globals = stack
+                        .pop()
+                        .ok_or_else(|| format!("pop without push at byte {i}"))?;
+                }
+                _ => {}
+            },
+            // Local
+            2 => {
+                let usage = if len == 4 {
+                    ((unsigned >> 16) as u16, unsigned as u16)
+                } else {
+                    (globals.usage_page, unsigned as u16)
+                };
+                match tag {
+                    0x0 => locals.usages.push(usage),
+                    0x1 => locals.usage_minimum = Some(usage),
+                    _ => {}
+                }
+            }
+            _ => return Err(format!("reserved item type at byte {i}")),
+        }
+        i += 1 + len;
+    }
+
+    report.ok_or_else(|| match report_id {
+        Some(id) => format!("descriptor has no {kind:?} items with report id {id}"),
+        None => format!("descriptor has no {kind:?} items"),
+    })
+}
+
+fn add_fields(
+    report: &mut Report,
+    globals: &Globals,
+    locals: &Locals,
+    variable: bool,
+    offset: usize,
+) {
+    let signed = globals.logical_minimum < 0;
+    let size = globals.report_size;
+    let count = globals.report_count;
+
+    if variable && count > 1 && size > 1 && locals.usages.len() == count {
+        // One field per usage, e.g. X and Y
+        for (n, usage) in locals.usages.iter().enumerate() {
+            let name = unique_name(report, usage_name(*usage, globals.usage_page));
+            report.fields.push(Field {
+                name,
+                offset: offset + n * size,
+                size,
+                count: 1,
+                signed,
+                bitmap: false,
+            });
+        }
+        return;
+    }
+
+    let usage = locals
+        .usages
+        .first()
+        .or(locals.usage_minimum.as_ref())
+        .copied()
+        .unwrap_or((globals.usage_page, 0));
+    let name = unique_name(report, usage_name(usage, globals.usage_page));
+    report.fields.push(Field {
+        name,
+        offset,
+        size,
+        count,
+        signed,
+        bitmap: variable && size == 1 && count > 1 && count <= 32,
+    });
+}
+
+fn usage_name((page, id): (u16, u16), global_page: u16) -> String {
+    let name = match (page, id) {
+        (USAGE_PAGE_GENERIC_DESKTOP, 0x30) => "x",
+        (USAGE_PAGE_GENERIC_DESKTOP, 0x31) => "y",
+        (USAGE_PAGE_GENERIC_DESKTOP, 0x32) => "z",
+        (USAGE_PAGE_GENERIC_DESKTOP, 0x33) => "rx",
+        (USAGE_PAGE_GENERIC_DESKTOP, 0x34) => "ry",
+        (USAGE_PAGE_GENERIC_DESKTOP, 0x35) => "rz",
+        (USAGE_PAGE_GENERIC_DESKTOP, 0x36) => "slider",
+        (USAGE_PAGE_GENERIC_DESKTOP, 0x37) => "dial",
+        (USAGE_PAGE_GENERIC_DESKTOP, 0x38) => "wheel",
+        (USAGE_PAGE_GENERIC_DESKTOP, 0x39) => "hat_switch",
+        (USAGE_PAGE_CONSUMER, 0x238) => "pan",
+        (USAGE_PAGE_KEYBOARD, 0xE0..=0xE7) => "modifiers",
+        (USAGE_PAGE_KEYBOARD, _) => "keys",
+        (USAGE_PAGE_LEDS, _) => "leds",
+        (USAGE_PAGE_BUTTON, _) => "buttons",
+        (USAGE_PAGE_CONSUMER, _) => "consumer",
+        _ if page != global_page => return usage_name((global_page, id), global_page),
+        _ => "field",
+    };
+    name.into()
+}
+
+fn unique_name(report: &Report, name: String) -> String {
+    let taken = |n: &str| report.fields.iter().any(|f| f.name == n);
+    if !taken(&name) {
+        return name;
+    }
+    (2..)
+        .map(|n| format!("{name}_{n}"))
+        .find(|n| !taken(n))
+        .unwrap()
+}
+
+#[cfg(test)]
+mod test {
+    use crate::descriptor::{parse, Field, ReportKind};
+
+    #[rustfmt::skip]
+    const BOOT_KEYBOARD: &[u8] = &[
+        0x05, 0x01, 0x09, 0x06, 0xA1, 0x01,
+        0x75, 0x01, 0x95, 0x08, 0x05, 0x07, 0x19, 0xE0, 0x29, 0xE7, 0x15, 0x00, 0x25, 0x01, 0x81, 0x02,
+        0x75, 0x08, 0x95, 0x01, 0x81, 0x01,
+        0x75, 0x01, 0x95, 0x05, 0x05, 0x08, 0x19, 0x01, 0x29, 0x05, 0x91, 0x02,
+        0x75, 0x03, 0x95, 0x01, 0x91, 0x01,
+        0x75, 0x08, 0x95, 0x06, 0x15, 0x00, 0x26, 0xFF, 0x00, 0x05, 0x07, 0x19, 0x00, 0x2A, 0xFF, 0x00, 0x81, 0x00,
+        0xC0,
+    ];
+
+    #[test]
+    fn boot_keyboard_input() {
+        let report = parse(BOOT_KEYBOARD, ReportKind::Input, None).unwrap();
+        assert_eq!(report.id, None);
+        assert_eq!(report.bits, 64);
+        assert_eq!(
+            report.fields,
+            vec![
+                Field {
+                    name: "modifiers".into(),
+                    offset: 0,
+                    size: 1,
+                    count: 8,
+                    signed: false,
+                    bitmap: true,
+                },
+                Field {
+                    name: "keys".into(),
+                    offset: 16,
+                    size: 8,
+                    count: 6,
+                    signed: false,
+                    bitmap: false,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn boot_keyboard_output() {
+        let report = parse(BOOT_KEYBOARD, ReportKind::Output, None).unwrap();
+        assert_eq!(report.bits, 8);
+        assert_eq!(report.fields.len(), 1);
+        assert_eq!(report.fields[0].name, "leds");
+        assert!(report.fields[0].bitmap);
+    }
+
+    #[test]
+    fn signed_axes_with_report_id() {
+        #[rustfmt::skip]
+        let descriptor = [
+            0x05, 0x01, 0x09, 0x02, 0xA1, 0x01, 0x85, 0x02,
+            0x75, 0x08, 0x95, 0x02, 0x09, 0x30, 0x09, 0x31, 0x15, 0x81, 0x25, 0x7F, 0x81, 0x06,
+            0xC0,
+        ];
+        let report = parse(&descriptor, ReportKind::Input, None).unwrap();
+        assert_eq!(report.id, Some(2));
+        assert_eq!(report.fields[0].name, "x");
+        assert_eq!(report.fields[1].name, "y");
+        assert_eq!(report.fields[1].offset, 8);
+        assert!(report.fields[1].signed);
+    }
+
+    #[test]
+    fn multiple_report_ids_selected_by_id() {
+        #[rustfmt::skip]
+        let descriptor = [
+            0x05, 0x01, 0x85, 0x01, 0x75, 0x08, 0x95, 0x01, 0x09, 0x30, 0x81, 0x02,
+            0x85, 0x02, 0x75, 0x10, 0x95, 0x02, 0x09, 0x31, 0x09, 0x32, 0x81, 0x02,
+            0x85, 0x01, 0x75, 0x08, 0x95, 0x01, 0x09, 0x38, 0x81, 0x02,
+        ];
+        assert!(parse(&descriptor, ReportKind::Input, None).is_err());
+        assert!(parse(&descriptor, ReportKind::Input, Some(3)).is_err());
+
+        let first = parse(&descriptor, ReportKind::Input, Some(1)).unwrap();
+        assert_eq!(first.id, Some(1));
+        assert_eq!(first.bits, 16);
+        assert_eq!(first.fields[0].name, "x");
+        assert_eq!(
+            (first.fields[1].name.as_str(), first.fields[1].offset),
+            ("wheel", 8)
+        );
+
+        let second = parse(&descriptor, ReportKind::Input, Some(2)).unwrap();
+        assert_eq!(second.bits, 32);
+        assert_eq!(second.fields[0].name, "y");
+        assert_eq!(
+            (second.fields[1].name.as_str(), second.fields[1].offset),
+            ("z", 16)
+        );
+    }
+}
@@ -0,0 +1,345 @@
+//! Procedural macros for [usbd-human-interface-device](https://docs.rs/usbd-human-interface-device)
+//!
+//! Usually used through the `macros` feature of `usbd-human-interface-device`, rather than as a
+//! direct dependency.
+
+use proc_macro::TokenStream;
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use quote::{format_ident, quote};
+use syn::parse::{Parse, ParseStream};
+use syn::punctuated::Punctuated;
+use syn::{
+    bracketed, parenthesized, parse_macro_input, token, Attribute, Ident, LitInt, Token, Visibility,
+};
+
+use descriptor::{Field, ReportKind};
+
+mod descriptor;
+
+struct HidReportInput {
+    attrs: Vec<Attribute>,
+    vis: Visibility,
+    name: Ident,
+    kind: ReportKind,
+    report_id: Option<u8>,
+    descriptor: Vec<u8>,
+}
+
+impl Parse for HidReportInput {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let attrs = input.call(Attribute::parse_outer)?;
+        let vis = input.parse()?;
+        input.parse::<Token![struct]>()?;
+        let name = input.parse()?;
+
+        let (kind, report_id) = if input.parse::<Option<Token![:]>>()?.is_some() {
+            let kind: Ident = input.parse()?;
+            let kind = match kind.to_string().as_str() {
+                "Input" => ReportKind::Input,
+                "Output" => ReportKind::Output,
+                "Feature" => ReportKind::Feature,
+                _ => {
+                    return Err(syn::Error::new(
+                        kind.span(),
+                        "expected `Input`, `Output` or `Feature`",
+                    ))
+                }
+            };
+            let report_id = if input.peek(token::Paren) {
+                let content;
+                parenthesized!(content in input);
+                Some(content.parse::<LitInt>()?.base10_parse::<u8>()?)
+            } else {
+                None
+            };
+            (kind, report_id)
+        } else {
+            (ReportKind::Input, None)
+        };
+
+        input.parse::<Token![=]>()?;
+        let content;
+        bracketed!(content in input);
+        let descriptor = Punctuated::<LitInt, Token![,]>::parse_terminated(&content)?
+            .iter()
+            .map(LitInt::base10_parse::<u8>)
+            .collect::<syn::Result<_>>()?;
+        input.parse::<Option<Token![;]>>()?;
+
+        Ok(Self {
+            attrs,
+            vis,
+            name,
+            kind,
+            report_id,
+            descriptor,
+        })
+    }
+}
+
+/// Generates a report struct from a HID report descriptor
+///
+/// The descriptor is parsed at compile time, producing a struct with a public field for each data
+/// item of the selected report kind (`Input` if omitted) and `pack`/`unpack` methods that convert
+/// to and from the report bytes. The struct also implements `packed_struct::PackedStruct`, so the
+/// crate using the macro must depend on `packed_struct`. Attributes, such as doc comments or
+/// further derives, are applied to the struct ahead of the generated
+/// `#[derive(Clone, Copy, Debug, PartialEq, Eq)]`.
+///
+/// In descriptors with more than one report ID, the report is selected by ID after the kind, such
+/// as `struct ConsumerReport: Input(2) = [...]`, and one struct is generated per report.
+///
+/// Fields are named after their usage, such as `x`, `wheel` or `buttons`. A field is an array if
+/// the item has a report count greater than one, except for single bit variable items, such as
+/// buttons, which are packed into one integer bitmap.
+///
+/// ```
+/// use usbd_human_interface_device_macros::hid_report;
+///
+/// hid_report! {
+///     /// Three button mouse
+///     pub struct MouseReport = [
+///         0x05, 0x01, 0x09, 0x02, 0xA1, 0x01, 0x09, 0x01, 0xA1, 0x00,
+///         0x95, 0x03, 0x75, 0x01, 0x05, 0x09, 0x19, 0x01, 0x29, 0x03,
+///         0x15, 0x00, 0x25, 0x01, 0x81, 0x02, 0x95, 0x01, 0x75, 0x05,
+///         0x81, 0x01, 0x75, 0x08, 0x95, 0x02, 0x05, 0x01, 0x09, 0x30,
+///         0x09, 0x31, 0x15, 0x81, 0x25, 0x7F, 0x81, 0x06, 0xC0, 0xC0,
+///     ];
+/// }
+///
+/// let report = MouseReport { buttons: 0b101, x: -1, y: 2 };
+/// assert_eq!(report.pack(), [0x05, 0xFF, 0x02]);
+/// assert_eq!(MouseReport::unpack(&[0x05, 0xFF, 0x02]), Some(report));
+/// ```
+///
+/// ```
+/// use packed_struct::PackedStruct;
+/// use usbd_human_interface_device_macros::hid_report;
+///
+/// // Keyboard LEDs with report ID 1 and a consumer control with report ID 2
+/// hid_report! {
+///     #[derive(Hash)]
+///     struct LedsReport: Output(1) = [
+///         0x05, 0x01, 0x09, 0x06, 0xA1, 0x01, 0x85, 0x01, 0x05, 0x08,
+///         0x19, 0x01, 0x29, 0x05, 0x15, 0x00, 0x25, 0x01, 0x75, 0x01,
+///         0x95, 0x05, 0x91, 0x02, 0x95, 0x03, 0x91, 0x01, 0xC0, 0x05,
+///         0x0C, 0x09, 0x01, 0xA1, 0x01, 0x85, 0x02, 0x15, 0x00, 0x26,
+///         0xFF, 0x03, 0x75, 0x10, 0x95, 0x01, 0x19, 0x00, 0x2A, 0xFF,
+///         0x03, 0x81, 0x00, 0xC0,
+///     ];
+/// }
+/// hid_report! {
+///     struct ConsumerReport: Input(2) = [
+///         0x05, 0x01, 0x09, 0x06, 0xA1, 0x01, 0x85, 0x01, 0x05, 0x08,
+///         0x19, 0x01, 0x29, 0x05, 0x15, 0x00, 0x25, 0x01, 0x75, 0x01,
+///         0x95, 0x05, 0x91, 0x02, 0x95, 0x03, 0x91, 0x01, 0xC0, 0x05,
+///         0x0C, 0x09, 0x01, 0xA1, 0x01, 0x85, 0x02, 0x15, 0x00, 0x26,
+///         0xFF, 0x03, 0x75, 0x10, 0x95, 0x01, 0x19, 0x00, 0x2A, 0xFF,
+///         0x03, 0x81, 0x00, 0xC0,
+///     ];
+/// }
+///
+/// assert_eq!(LedsReport { leds: 0b00010 }.pack(), [0x01, 0x02]);
+/// let consumer = ConsumerReport { consumer: 0xE9 };
+/// assert_eq!(PackedStruct::pack(&consumer), Ok([0x02, 0xE9, 0x00]));
+/// assert_eq!(ConsumerReport::unpack(&[0x01, 0xE9, 0x00]), None);
+/// ```
+#[proc_macro]
+pub fn hid_report(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as HidReportInput);
+    match expand(input) {
+        Ok(tokens) => tokens.into(),
+        Err(e) => e.to_compile_error().into(),
+    }
+}
+
+fn expand(input: HidReportInput) -> syn::Result<TokenStream2> {
+    let HidReportInput {
+        attrs,
+        vis,
+        name,
+        kind,
+        report_id,
+        descriptor,
+    } = input;
+
+    let report = descriptor::parse(&descriptor, kind, report_id)
+        .map_err(|e| syn::Error::new(name.span(), format!("invalid report descriptor: {e}")))?;
+
+    let id_bits = if report.id.is_some() { 8 } else { 0 };
+    let size = (report.bits + id_bits).div_ceil(8);
+
+    let mut fields = Vec::new();
+    let mut defaults = Vec::new();
+    let mut packs = Vec::new();
+    let mut unpacks = Vec::new();
+
+    for field in &report.fields {
+        let ident = format_ident!("{}", field.name.as_str());
+        let (bits, count) = if field.bitmap {
+            (field.size * field.count, 1)
+        } else {
+            (field.size, field.count)
+        };
+        let ty = value_type(field, bits)?;
+        let offset = field.offset + id_bits;
+        let to_raw = to_raw(field, bits);
+        let from_raw = from_raw(field, bits);
+        let zero = if bits == 1 { quote!(false) } else { quote!(0) };
+
+        if count == 1 {
+            fields.push(quote!(pub #ident: #ty));
+            defaults.push(quote!(#ident: #zero));
+            packs.push(quote! {
+                let v = self.#ident;
+                Self::put(&mut data, #offset, #bits, #to_raw);
+            });
+            unpacks.push(quote! {
+                #ident: {
+                    let raw = Self::get(data, #offset, #bits);
+                    #from_raw
+                }
+            });
+        } else {
+            fields.push(quote!(pub #ident: [#ty; #count]));
+            defaults.push(quote!(#ident: [#zero; #count]));
+            packs.push(quote! {
+                for (n, &v) in self.#ident.iter().enumerate() {
+                    Self::put(&mut data, #offset + n * #bits, #bits, #to_raw);
+                }
+            });
+            unpacks.push(quote! {
+                #ident: core::array::from_fn(|n| {
+                    let raw = Self::get(data, #offset + n * #bits, #bits);
+                    #from_raw
+                })
+            });
+        }
+    }
+
+    let (id_const, write_id, check_id) = match report.id {
+        Some(id) => (
+            quote!(Some(#id)),
+            quote!(data[0] = #id;),
+            quote! {
+                if data[0] != #id {
+                    return None;
+                }
+            },
+        ),
+        None => (quote!(None), quote!(), quote!()),
+    };
+
+    Ok(quote! {
+        #(#attrs)*
+        #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+        #vis struct #name {
+            #(#fields,)*
+        }
+
+        impl Default for #name {
+            fn default() -> Self {
+                Self {
+                    #(#defaults,)*
+                }
+            }
+        }
+
+        impl #name {
+            pub const REPORT_ID: Option<u8> = #id_const;
+            /// Report length in bytes, including the report ID
+            pub const SIZE: usize = #size;
+
+            pub fn pack(&self) -> [u8; #size] {
+                let mut data = [0_u8; #size];
+                #write_id
+                #(#packs)*
+                data
+            }
+
+            /// Returns `None` if `data` is too short or has a different report ID
+            pub fn unpack(data: &[u8]) -> Option<Self> {
+                if data.len() < #size {
+                    return None;
+                }
+                #check_id
+                Some(Self {
+                    #(#unpacks,)*
+                })
+            }
+
+            fn put(data: &mut [u8], offset: usize, bits: usize, value: u32) {
+                for i in 0..bits {
+                    if (value >> i) & 1 != 0 {
+                        data[(offset + i) / 8] |= 1 << ((offset + i) % 8);
+                    }
+                }
+            }
+
+            fn get(data: &[u8], offset: usize, bits: usize) -> u32 {
+                (0..bits).fold(0, |acc, i| {
+                    acc | (u32::from((data[(offset + i) / 8] >> ((offset + i) % 8)) & 1) << i)
+                })
+            }
+        }
+
+        impl ::packed_struct::PackedStruct for #name {
+            type ByteArray = [u8; #size];
+
+            fn pack(&self) -> ::packed_struct::PackingResult<[u8; #size]> {
+                Ok(#name::pack(self))
+            }
+
+            fn unpack(src: &[u8; #size]) -> ::packed_struct::PackingResult<Self> {
+                #name::unpack(src).ok_or(::packed_struct::PackingError::InvalidValue)
+            }
+        }
+    })
+}
+
+fn value_type(field: &Field, bits: usize) -> syn::Result<TokenStream2> {
+    let width = match bits {
+        1 => return Ok(quote!(bool)),
+        2..=8 => 8_u8,
+        9..=16 => 16,
+        17..=32 => 32,
+        _ => {
+            return Err(syn::Error::new(
+                Span::call_site(),
+                format!("field `{}` is wider than 32 bits", field.name),
+            ))
+        }
+    };
+    let prefix = if field.signed { "i" } else { "u" };
+    let ty = format_ident!("{}{}", prefix, width);
+    Ok(quote!(#ty))
+}
+
+fn to_raw(field: &Field, bits: usize) -> TokenStream2 {
+    if field.signed && bits > 1 {
+        quote!(v as u32)
+    } else {
+        quote!(u32::from(v))
+    }
+}
+
+fn from_raw(field: &Field, bits: usize) -> TokenStream2 {
+    match bits {
+        1 => quote!(raw != 0),
+        32 if field.signed => quote!(raw as i32),
+        17..=31 if field.signed => {
+            let shift = 32 - bits;
+            quote!(((raw << #shift) as i32) >> #shift)
+        }
+        _ if field.signed => {
+            let shift = 32 - bits;
+            let ty = value_type(field, bits).unwrap();
+            quote!((((raw << #shift) as i32) >> #shift) as #ty)
+        }
+        32 => quote!(raw),
+        _ => {
+            let ty = value_type(field, bits).unwrap();
+            quote!(raw as #ty)
+        }
+    }
+}
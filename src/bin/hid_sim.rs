@@ -0,0 +1,201 @@
+//! Replays a recorded host trace against one of the crate's devices
+//!
+//! ```text
+//! hid-sim <device> <trace> [--devnum <n>] [--interface-only]
+//! ```
+//!
+//! `device` is one of `keyboard`, `nkro-keyboard`, `mouse`, `wheel-mouse`, `consumer`,
+//! `gamepad` or `joystick`. `trace` is either a text trace or a usbmon pcap capture, see
+//! [`usbd_human_interface_device::simulation::Trace`]. Exits with status 1 if any response differs
+//! from the trace.
+
+use std::process::ExitCode;
+
+use usbd_human_interface_device::device::consumer::ConsumerControl;
+use usbd_human_interface_device::device::gamepad::Gamepad;
+use usbd_human_interface_device::device::joystick::Joystick;
+use usbd_human_interface_device::device::keyboard::{BootKeyboard, NKROBootKeyboard};
+use usbd_human_interface_device::device::mouse::{BootMouse, WheelMouse};
+use usbd_human_interface_device::prelude::*;
+use usbd_human_interface_device::simulation::{
+    device_builder, replay, Compare, ReplayReport, SimBus, Trace,
+};
+use usbd_human_interface_device::usb_device::bus::UsbBusAllocator;
+use usbd_human_interface_device::usb_device::class::UsbClass;
+use usbd_human_interface_device::usb_device::prelude::*;
+
+const USAGE: &str =
+    "usage: hid-sim <keyboard|nkro-keyboard|mouse|wheel-mouse|consumer|gamepad|joystick> <trace> [--devnum <n>] [--interface-only]";
+
+struct Args {
+    device: String,
+    trace: String,
+    devnum: Option<u8>,
+    compare: Compare,
+}
+
+fn parse_args() -> Option<Args> {
+    let mut args = std::env::args().skip(1);
+    let device = args.next()?;
+    let trace = args.next()?;
+    let mut devnum = None;
+    let mut compare = Compare::All;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--devnum" => devnum = Some(args.next()?.parse().ok()?),
+            "--interface-only" => compare = Compare::InterfaceRequests,
+            _ => return None,
+        }
+    }
+    Some(Args {
+        device,
+        trace,
+        devnum,
+        compare,
+    })
+}
+
+#[cfg(not(feature = "usb-device-03"))]
+fn build_device(usb_alloc: &UsbBusAllocator<SimBus>) -> UsbDevice<'_, SimBus> {
+    device_builder(usb_alloc, 64)
+        .manufacturer("usbd-human-interface-device")
+        .product("hid-sim")
+        .build()
+}
+
+#[cfg(feature = "usb-device-03")]
+fn build_device(usb_alloc: &UsbBusAllocator<SimBus>) -> UsbDevice<'_, SimBus> {
+    device_builder(usb_alloc, 64)
+        .strings(&[StringDescriptors::default()
+            .manufacturer("usbd-human-interface-device")
+            .product("hid-sim")])
+        .expect("Invalid string descriptors")
+        .build()
+}
+
+fn run<C: UsbClass<SimBus>>(
+    usb_alloc: &UsbBusAllocator<SimBus>,
+    mut class: C,
+    trace: &Trace,
+    compare: Compare,
+) -> ReplayReport {
+    let mut usb_dev = build_device(usb_alloc);
+    replay(&mut usb_dev, &mut [&mut class], trace, compare)
+}
+
+fn main() -> ExitCode {
+    let Some(args) = parse_args() else {
+        eprintln!("{USAGE}");
+        return ExitCode::from(2);
+    };
+
+    let capture = match std::fs::read(&args.trace) {
+        Ok(capture) => capture,
+        Err(e) => {
+            eprintln!("Failed to read {}: {e}", args.trace);
+            return ExitCode::from(2);
+        }
+    };
+    let trace = match std::str::from_utf8(&capture) {
+        Ok(text) => Trace::parse_text(text),
+        Err(_) => Trace::from_usbmon_pcap(&capture, args.devnum),
+    };
+    let trace = match trace {
+        Ok(trace) => trace,
+        Err(e) => {
+            eprintln!("Failed to parse {}: {e:?}", args.trace);
+            return ExitCode::from(2);
+        }
+    };
+
+    let usb_alloc = UsbBusAllocator::new(SimBus::new());
+    let builder = UsbHidClassBuilder::new();
+    let report = match args.device.as_str() {
+        "keyboard" => {
+            let class = builder.add_interface(BootKeyboard::default_config());
+            run(
+                &usb_alloc,
+                class.build(&usb_alloc).unwrap(),
+                &trace,
+                args.compare,
+            )
+        }
+        "nkro-keyboard" => {
+            let class = builder.add_interface(NKROBootKeyboard::default_config());
+            run(
+                &usb_alloc,
+                class.build(&usb_alloc).unwrap(),
+                &trace,
+                args.compare,
+            )
+        }
+        "mouse" => {
+            let class = builder.add_interface(BootMouse::default_config());
+            run(
+                &usb_alloc,
+                class.build(&usb_alloc).unwrap(),
+                &trace,
+                args.compare,
+            )
+        }
+        "wheel-mouse" => {
+            let class = builder.add_interface(WheelMouse::default_config());
+            run(
+                &usb_alloc,
+                class.build(&usb_alloc).unwrap(),
+                &trace,
+                args.compare,
+            )
+        }
+        "consumer" => {
+            let class = builder.add_interface(ConsumerControl::default_config());
+            run(
+                &usb_alloc,
+                class.build(&usb_alloc).unwrap(),
+                &trace,
+                args.compare,
+            )
+        }
+        "gamepad" => {
+            let class = builder.add_interface(Gamepad::default_config());
+            run(
+                &usb_alloc,
+                class.build(&usb_alloc).unwrap(),
+                &trace,
+                args.compare,
+            )
+        }
+        "joystick" => {
+            let class = builder.add_interface(Joystick::default_config());
+            run(
+                &usb_alloc,
+                class.build(&usb_alloc).unwrap(),
+                &trace,
+                args.compare,
+            )
+        }
+        _ => {
+            eprintln!("{USAGE}");
+            return ExitCode::from(2);
+        }
+    };
+
+    for mismatch in &report.mismatches {
+        let transfer = &trace.transfers[mismatch.index];
+        println!(
+            "transfer {} {:02x?}: expected {:02x?}, got {:02x?}",
+            mismatch.index, transfer.setup, mismatch.expected, mismatch.actual
+        );
+    }
+    println!(
+        "{} transfers, {} mismatches",
+        report.transfers,
+        report.mismatches.len()
+    );
+
+    if report.mismatches.is_empty() {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
@@ -0,0 +1,258 @@
+//! Report generators for validating end-to-end connectivity during bring-up
+//!
+//! Each generator is driven by calling its `tick` method every 1ms/at 1kHz alongside the
+//! interface's own `tick()`. Generators only advance once a report has been accepted by the
+//! interface, so they are paced by the endpoint polling interval.
+//!
+//! Requires the `demo` feature.
+
+use usb_device::bus::UsbBus;
+
+use crate::device::gamepad::{GamepadInterface, GamepadReport, HatSwitch};
+use crate::device::keyboard::{
+    BootKeyboardInterface, BootKeyboardReport, NKROBootKeyboardInterface, NKROBootKeyboardReport,
+};
+use crate::device::mouse::{BootMouseInterface, BootMouseReport};
+use crate::page::Keyboard;
+use crate::UsbHidError;
+
+/// Moves the mouse cursor around a square
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MouseSquare {
+    side: u16,
+    speed: i8,
+    step: u32,
+}
+
+impl MouseSquare {
+    /// Square with sides of `side` reports, moving `speed` units per report
+    pub const fn new(side: u16, speed: i8) -> Self {
+        Self {
+            side,
+            speed,
+            step: 0,
+        }
+    }
+
+    /// The next report, advancing the generator
+    pub fn next_report(&mut self) -> BootMouseReport {
+        let side = u32::from(self.side.max(1));
+        let (x, y) = match (self.step / side) % 4 {
+            0 => (self.speed, 0),
+            1 => (0, self.speed),
+            2 => (self.speed.saturating_neg(), 0),
+            _ => (0, self.speed.saturating_neg()),
+        };
+        self.step = (self.step + 1) % (side * 4);
+        BootMouseReport { buttons: 0, x, y }
+    }
+
+    pub fn tick<B: UsbBus>(
+        &mut self,
+        mouse: &BootMouseInterface<'_, B>,
+    ) -> Result<(), UsbHidError> {
+        let mut next = *self;
+        mouse.write_report(&next.next_report())?;
+        *self = next;
+        Ok(())
+    }
+}
+
+impl Default for MouseSquare {
+    fn default() -> Self {
+        Self::new(100, 2)
+    }
+}
+
+/// Presses each gamepad button in turn, then points the hat switch in each direction in turn
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct GamepadSweep {
+    period: u16,
+    step: u32,
+}
+
+impl GamepadSweep {
+    const BUTTONS: u32 = 8;
+    const DIRECTIONS: u32 = 8;
+
+    /// Hold each button and hat switch direction for `period` reports
+    pub const fn new(period: u16) -> Self {
+        Self { period, step: 0 }
+    }
+
+    /// The next report, advancing the generator
+    pub fn next_report(&mut self) -> GamepadReport {
+        let period = u32::from(self.period.max(1));
+        let index = self.step / period;
+        let mut report = GamepadReport::default();
+        if index < Self::BUTTONS {
+            report.buttons = 1 << index;
+        } else {
+            report.hat = HatSwitch::from((index - Self::BUTTONS) as u8);
+        }
+        self.step = (self.step + 1) % (period * (Self::BUTTONS + Self::DIRECTIONS));
+        report
+    }
+
+    pub fn tick<B: UsbBus>(
+        &mut self,
+        gamepad: &GamepadInterface<'_, B>,
+    ) -> Result<(), UsbHidError> {
+        let mut next = *self;
+        gamepad.write_report(&next.next_report())?;
+        *self = next;
+        Ok(())
+    }
+}
+
+impl Default for GamepadSweep {
+    fn default() -> Self {
+        Self::new(25)
+    }
+}
+
+/// Repeatedly types a pattern of keys, pressing and releasing each key in turn
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct KeyboardTyping<'k> {
+    pattern: &'k [Keyboard],
+    period: u16,
+    index: usize,
+    pressed: bool,
+    elapsed: u16,
+}
+
+impl<'k> KeyboardTyping<'k> {
+    /// Types "hello " followed by a backspace for each character typed
+    pub const DEFAULT_PATTERN: &'static [Keyboard] = &[
+        Keyboard::H,
+        Keyboard::E,
+        Keyboard::L,
+        Keyboard::L,
+        Keyboard::O,
+        Keyboard::Space,
+        Keyboard::DeleteBackspace,
+        Keyboard::DeleteBackspace,
+        Keyboard::DeleteBackspace,
+        Keyboard::DeleteBackspace,
+        Keyboard::DeleteBackspace,
+        Keyboard::DeleteBackspace,
+    ];
+
+    /// Hold each key of `pattern` for `period` ticks, then release it for `period` ticks
+    pub const fn new(pattern: &'k [Keyboard], period: u16) -> Self {
+        Self {
+            pattern,
+            period,
+            index: 0,
+            pressed: false,
+            elapsed: 0,
+        }
+    }
+
+    /// The key currently pressed, [`Keyboard::NoEventIndicated`] between key presses
+    pub fn current_key(&self) -> Keyboard {
+        match self.pattern.get(self.index) {
+            Some(&k) if self.pressed => k,
+            _ => Keyboard::NoEventIndicated,
+        }
+    }
+
+    fn advance(&mut self) {
+        self.elapsed += 1;
+        if self.elapsed >= self.period {
+            self.elapsed = 0;
+            if self.pressed {
+                self.index = (self.index + 1) % self.pattern.len().max(1);
+            }
+            self.pressed = !self.pressed;
+        }
+    }
+
+    fn write(&mut self, result: Result<(), UsbHidError>) -> Result<(), UsbHidError> {
+        match result {
+            Ok(()) | Err(UsbHidError::Duplicate) => {
+                self.advance();
+                Ok(())
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    pub fn tick<B: UsbBus>(
+        &mut self,
+        keyboard: &BootKeyboardInterface<'_, B>,
+    ) -> Result<(), UsbHidError> {
+        let report = BootKeyboardReport::new([self.current_key()]);
+        self.write(keyboard.write_report(&report))
+    }
+
+    pub fn tick_nkro<B: UsbBus>(
+        &mut self,
+        keyboard: &NKROBootKeyboardInterface<'_, B>,
+    ) -> Result<(), UsbHidError> {
+        let report = NKROBootKeyboardReport::new([self.current_key()]);
+        self.write(keyboard.write_report(&report))
+    }
+}
+
+impl Default for KeyboardTyping<'static> {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_PATTERN, 50)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::demo::{GamepadSweep, KeyboardTyping, MouseSquare};
+    use crate::page::{HatSwitch, Keyboard};
+
+    #[test]
+    fn mouse_square_returns_to_start() {
+        let mut square = MouseSquare::new(3, 5);
+        let (x, y) = (0..12).fold((0_i32, 0_i32), |(x, y), _| {
+            let r = square.next_report();
+            (x + i32::from(r.x), y + i32::from(r.y))
+        });
+        assert_eq!((x, y), (0, 0));
+        assert_eq!(square, MouseSquare::new(3, 5));
+    }
+
+    #[test]
+    fn gamepad_sweep_buttons_then_hat() {
+        let mut sweep = GamepadSweep::new(2);
+        let reports: [_; 32] = core::array::from_fn(|_| sweep.next_report());
+        assert_eq!(reports[0].buttons, 0x01);
+        assert_eq!(reports[1].buttons, 0x01);
+        assert_eq!(reports[15].buttons, 0x80);
+        assert!(reports[..16].iter().all(|r| r.hat == HatSwitch::Null));
+        assert_eq!(reports[16].hat, HatSwitch::N);
+        assert_eq!(reports[31].hat, HatSwitch::NW);
+        assert!(reports[16..].iter().all(|r| r.buttons == 0));
+        assert_eq!(sweep, GamepadSweep::new(2));
+    }
+
+    #[test]
+    fn keyboard_typing_presses_and_releases() {
+        let mut typing = KeyboardTyping::new(&[Keyboard::A, Keyboard::B], 2);
+        let keys: [Keyboard; 10] = core::array::from_fn(|_| {
+            let k = typing.current_key();
+            typing.advance();
+            k
+        });
+        assert_eq!(
+            keys,
+            [
+                Keyboard::NoEventIndicated,
+                Keyboard::NoEventIndicated,
+                Keyboard::A,
+                Keyboard::A,
+                Keyboard::NoEventIndicated,
+                Keyboard::NoEventIndicated,
+                Keyboard::B,
+                Keyboard::B,
+                Keyboard::NoEventIndicated,
+                Keyboard::NoEventIndicated,
+            ]
+        );
+    }
+}
@@ -0,0 +1,324 @@
+//!HID controllers with only buttons, such as dance pads, arcade sticks and button boxes
+//!
+//! The raw state of the buttons is written on every scan with [`ButtonPadInterface::write_scan`],
+//! which debounces each button and sends a report when the debounced state changes.
+use core::cell::RefCell;
+
+use crate::hid_class::descriptor::HidProtocol;
+use core::default::Default;
+use delegate::delegate;
+use packed_struct::prelude::*;
+use usb_device::bus::{InterfaceNumber, StringIndex, UsbBus};
+use usb_device::class_prelude::{DescriptorWriter, EndpointAddress};
+
+use crate::hid_class::prelude::*;
+use crate::interface::duration::PollInterval;
+use crate::interface::latency::LatencyHistogram;
+use crate::interface::raw::{RawInterface, RawInterfaceConfig};
+use crate::interface::{
+    DefaultConfig, HidDescriptorBodyBytes, InterfaceClass, WrappedInterface, WrappedInterfaceConfig,
+};
+use crate::UsbHidError;
+
+/// Number of buttons of a [`ButtonPadReport`]
+pub const BUTTON_PAD_BUTTONS: usize = 32;
+
+/// Controller with 32 buttons and no axes
+///
+/// * Bytes 0-3 - Buttons 1 to 32
+#[rustfmt::skip]
+pub const BUTTON_PAD_REPORT_DESCRIPTOR: &[u8] = &[
+    0x05, 0x01,        // Usage Page (Generic Desktop),
+    0x09, 0x04,        // Usage (Joystick),
+    0xA1, 0x01,        // Collection (Application),
+    0x05, 0x09,        //   Usage Page (Buttons),
+    0x19, 0x01,        //   Usage Minimum (1),
+    0x29, 0x20,        //   Usage Maximum (32),
+    0x15, 0x00,        //   Logical Minimum (0),
+    0x25, 0x01,        //   Logical Maximum (1),
+    0x75, 0x01,        //   Report Size (1),
+    0x95, 0x20,        //   Report Count (32),
+    0x81, 0x02,        //   Input (Data, Variable, Absolute),
+    0xC0,              // End Collection
+];
+
+/// Buttons 1 to 32 in bits 0 to 31
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Default, PackedStruct)]
+#[packed_struct(endian = "lsb", size_bytes = "4")]
+pub struct ButtonPadReport {
+    #[packed_field]
+    pub buttons: u32,
+}
+
+/// Configuration of the [`ButtonPadInterface`], set on [`WrappedInterfaceConfig::config`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ButtonPadConfig {
+    /// Consecutive scans a button must differ from its debounced state for the change to be
+    /// reported, `0` or `1` reports every change immediately
+    pub debounce_scans: u8,
+}
+
+impl Default for ButtonPadConfig {
+    /// Five scans, 5ms when scanning every 1ms
+    fn default() -> Self {
+        Self { debounce_scans: 5 }
+    }
+}
+
+/// Per button debouncing of raw button states
+///
+/// A button's debounced state changes once its raw state has differed for `scans` consecutive
+/// scans, a bounce back restarts the count. Buttons are debounced independently, so a bouncing
+/// button does not delay the others.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Debouncer {
+    scans: u8,
+    state: u32,
+    counts: [u8; BUTTON_PAD_BUTTONS],
+}
+
+impl Debouncer {
+    pub fn new(scans: u8) -> Self {
+        Self {
+            scans,
+            state: 0,
+            counts: [0; BUTTON_PAD_BUTTONS],
+        }
+    }
+
+    /// Updates the debounced state with the raw state of one scan, returning the debounced state
+    pub fn update(&mut self, raw: u32) -> u32 {
+        let changed = raw ^ self.state;
+        for (i, count) in self.counts.iter_mut().enumerate() {
+            if changed & (1 << i) == 0 {
+                *count = 0;
+                continue;
+            }
+            *count = count.saturating_add(1);
+            if *count >= self.scans {
+                self.state ^= 1 << i;
+                *count = 0;
+            }
+        }
+        self.state
+    }
+
+    pub fn state(&self) -> u32 {
+        self.state
+    }
+
+    pub fn reset(&mut self) {
+        self.state = 0;
+        self.counts = [0; BUTTON_PAD_BUTTONS];
+    }
+}
+
+struct ScanState {
+    debouncer: Debouncer,
+    /// Debounced state last sent to the host, `None` until the first report is sent
+    sent: Option<u32>,
+}
+
+pub struct ButtonPadInterface<'a, B: UsbBus> {
+    inner: RawInterface<'a, B>,
+    scan: RefCell<ScanState>,
+}
+
+/// Button pad interface, see [`ButtonPadInterface`]
+pub type ButtonPad<'a, B> = ButtonPadInterface<'a, B>;
+
+impl<'a, B: UsbBus> ButtonPadInterface<'a, B> {
+    delegate! {
+        to self.inner {
+            pub fn protocol(&self) -> HidProtocol;
+            pub fn report_latency(&self) -> LatencyHistogram;
+            pub fn clear_report_latency(&self);
+            pub fn host_stalls(&self) -> u32;
+            pub fn host_stalled(&self) -> bool;
+            pub fn write_ready(&self) -> bool;
+        }
+    }
+
+    /// Writes a report of the given button states, without debouncing
+    pub fn write_report(&self, report: &ButtonPadReport) -> Result<(), UsbHidError> {
+        let data = report.pack().map_err(|e| {
+            error!("Error packing ButtonPadReport: {:?}", e);
+            UsbHidError::SerializationError
+        })?;
+        self.inner.write_report(&data).map(|_| ())
+    }
+
+    /// Debounces the raw button states of one scan, bit 0 being button 1, and writes a report if
+    /// the debounced state differs from the last report sent
+    ///
+    /// Call at a regular interval, such as every 1ms. Returns [`UsbHidError::Duplicate`] if the
+    /// debounced state has not changed. A report that could not be written is retried on the next
+    /// scan.
+    pub fn write_scan(&self, raw: u32) -> Result<(), UsbHidError> {
+        let mut scan = self.scan.borrow_mut();
+        let buttons = scan.debouncer.update(raw);
+        if scan.sent == Some(buttons) {
+            return Err(UsbHidError::Duplicate);
+        }
+        self.write_report(&ButtonPadReport { buttons })?;
+        scan.sent = Some(buttons);
+        Ok(())
+    }
+
+    /// The debounced button states
+    pub fn buttons(&self) -> u32 {
+        self.scan.borrow().debouncer.state()
+    }
+
+    pub fn default_config() -> WrappedInterfaceConfig<Self, RawInterfaceConfig<'a>, ButtonPadConfig>
+    {
+        Self::default_config_with_descriptor(BUTTON_PAD_REPORT_DESCRIPTOR)
+    }
+
+    /// Default configuration with an alternative report descriptor
+    ///
+    /// The descriptor must describe reports with the same layout as [`ButtonPadReport`], such as
+    /// fewer buttons followed by padding
+    pub fn default_config_with_descriptor(
+        report_descriptor: &'a [u8],
+    ) -> WrappedInterfaceConfig<Self, RawInterfaceConfig<'a>, ButtonPadConfig> {
+        WrappedInterfaceConfig::new(
+            RawInterfaceBuilder::new(report_descriptor)
+                .description("Button Pad")
+                .in_endpoint(UsbPacketSize::Bytes8, PollInterval::millis(1))
+                .without_out_endpoint()
+                .build()
+                .unwrap(),
+            ButtonPadConfig::default(),
+        )
+    }
+}
+
+impl<'a, B: UsbBus + 'a> DefaultConfig<'a, B> for ButtonPadInterface<'a, B> {
+    type Config = WrappedInterfaceConfig<Self, RawInterfaceConfig<'a>, ButtonPadConfig>;
+
+    fn default_config() -> Self::Config {
+        Self::default_config()
+    }
+}
+
+impl<'a, B: UsbBus> InterfaceClass<'a> for ButtonPadInterface<'a, B> {
+    delegate! {
+        to self.inner{
+           fn report_descriptor(&self) -> &'_ [u8];
+           fn hid_descriptor_body(&self) -> HidDescriptorBodyBytes;
+           fn class_descriptor(&self, descriptor_type: u8, index: u8) -> Option<&'_ [u8]>;
+           fn take_wakeup_request(&self) -> bool;
+           fn id(&self) -> InterfaceNumber;
+           fn write_descriptors(&self, writer: &mut DescriptorWriter) -> usb_device::Result<()>;
+           fn get_string(&self, index: StringIndex, _lang_id: u16) -> Option<&'_ str>;
+           fn set_report(&mut self, data: &[u8]) -> usb_device::Result<()>;
+           fn get_report(&mut self, data: &mut [u8]) -> usb_device::Result<usize>;
+           fn get_report_ack(&mut self) -> usb_device::Result<()>;
+           fn set_idle(&mut self, report_id: u8, value: u8);
+           fn get_idle(&self, report_id: u8) -> u8;
+           fn set_protocol(&mut self, protocol: HidProtocol) -> usb_device::Result<()>;
+           fn get_protocol(&self) -> HidProtocol;
+           fn out_endpoint_address(&self) -> Option<EndpointAddress>;
+           fn in_endpoint_address(&self) -> EndpointAddress;
+           fn endpoint_in_complete(&self, addr: EndpointAddress);
+           fn endpoint_out(&mut self);
+        }
+    }
+
+    fn reset(&mut self) {
+        self.inner.reset();
+        //Resend the current state to the reset host
+        self.scan.get_mut().sent = None;
+    }
+}
+
+impl<'a, B: UsbBus> WrappedInterface<'a, B, RawInterface<'a, B>, ButtonPadConfig>
+    for ButtonPadInterface<'a, B>
+{
+    fn new(interface: RawInterface<'a, B>, config: ButtonPadConfig) -> Self {
+        Self {
+            inner: interface,
+            scan: RefCell::new(ScanState {
+                debouncer: Debouncer::new(config.debounce_scans),
+                sent: None,
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use packed_struct::PackedStruct;
+
+    use crate::device::button_pad::{ButtonPadReport, Debouncer};
+
+    #[test]
+    fn pack_report() {
+        let report = ButtonPadReport {
+            buttons: 0x8000_0001,
+        };
+        assert_eq!(report.pack().unwrap(), [0x01, 0x00, 0x00, 0x80]);
+    }
+
+    #[test]
+    fn buttons_debounced_independently() {
+        let mut debouncer = Debouncer::new(3);
+        assert_eq!(debouncer.update(0b01), 0);
+        assert_eq!(debouncer.update(0b11), 0);
+        assert_eq!(debouncer.update(0b11), 0b01);
+        // Button 2 bounces, restarting its count
+        assert_eq!(debouncer.update(0b01), 0b01);
+        assert_eq!(debouncer.update(0b11), 0b01);
+        assert_eq!(debouncer.update(0b11), 0b01);
+        assert_eq!(debouncer.update(0b11), 0b11);
+        // Release
+        assert_eq!(debouncer.update(0b00), 0b11);
+        assert_eq!(debouncer.update(0b00), 0b11);
+        assert_eq!(debouncer.update(0b00), 0b00);
+
+        let mut debouncer = Debouncer::new(0);
+        assert_eq!(debouncer.update(0x8000_0000), 0x8000_0000);
+        debouncer.reset();
+        assert_eq!(debouncer.state(), 0);
+    }
+
+    #[cfg(feature = "simulation")]
+    #[test]
+    fn debounced_changes_written() {
+        use usb_device::bus::UsbBusAllocator;
+
+        use crate::device::button_pad::{ButtonPad, ButtonPadConfig};
+        use crate::prelude::*;
+        use crate::simulation::{device_builder, SimBus, SimHost};
+        use crate::UsbHidError;
+
+        let usb_alloc = UsbBusAllocator::new(SimBus::new());
+        let mut config = ButtonPad::default_config();
+        config.config = ButtonPadConfig { debounce_scans: 2 };
+        let mut class = UsbHidClassBuilder::new()
+            .add_interface(config)
+            .build(&usb_alloc)
+            .unwrap();
+        let mut usb_dev = device_builder(&usb_alloc, 8).build();
+        let host = SimHost::enumerate(&mut usb_dev, &mut [&mut class]).unwrap();
+
+        class
+            .interface::<ButtonPad<'_, _>, _>()
+            .write_scan(0)
+            .unwrap();
+        assert_eq!(
+            host.read_report(&mut usb_dev, &mut [&mut class], 0),
+            Some(vec![0, 0, 0, 0])
+        );
+
+        let pad = class.interface::<ButtonPad<'_, _>, _>();
+        assert!(matches!(pad.write_scan(0b100), Err(UsbHidError::Duplicate)));
+        pad.write_scan(0b100).unwrap();
+        assert_eq!(pad.buttons(), 0b100);
+        assert_eq!(
+            host.read_report(&mut usb_dev, &mut [&mut class], 0),
+            Some(vec![0b100, 0, 0, 0])
+        );
+    }
+}
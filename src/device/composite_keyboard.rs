@@ -0,0 +1,531 @@
+//!Keyboards with consumer and system controls on a single interface
+//!
+//! The keyboard, consumer control and system control reports are multiplexed with report IDs, so
+//! media and power keys take no additional interface or endpoint. The keyboard remains boot
+//! compatible, in boot protocol only keyboard reports are sent, without a report ID.
+use core::cell::Cell;
+
+use crate::hid_class::descriptor::{HidProtocol, ReportType};
+use delegate::delegate;
+use packed_struct::prelude::*;
+use usb_device::bus::{InterfaceNumber, StringIndex, UsbBus};
+use usb_device::class_prelude::{DescriptorWriter, EndpointAddress};
+use usb_device::UsbError;
+
+use crate::device::consumer::MultipleConsumerReport;
+use crate::device::keyboard::{
+    BootKeyboardReport, KeyboardConfig, KeyboardLedsReport, LedsReceiver, LedsReportStatistics,
+    BOOT_KEYBOARD_REPORT_DESCRIPTOR,
+};
+use crate::hid_class::prelude::*;
+use crate::interface::duration::PollInterval;
+use crate::interface::latency::LatencyHistogram;
+use crate::interface::packet::MAX_CONTROL_REPORT_SIZE;
+use crate::interface::raw::{RawInterface, RawInterfaceConfig};
+use crate::interface::{
+    DefaultConfig, HidDescriptorBodyBytes, InterfaceClass, WrappedInterface, WrappedInterfaceConfig,
+};
+use crate::page::Desktop;
+use crate::UsbHidError;
+
+pub const KEYBOARD_REPORT_ID: u8 = 0x01;
+pub const CONSUMER_REPORT_ID: u8 = 0x02;
+pub const SYSTEM_REPORT_ID: u8 = 0x03;
+
+/// Boot keyboard with consumer and system controls
+///
+/// * Report ID 1, Input - [`BootKeyboardReport`]
+/// * Report ID 1, Output - [`KeyboardLedsReport`]
+/// * Report ID 2, Input - [`MultipleConsumerReport`]
+/// * Report ID 3, Input - [`SystemControlReport`]
+#[rustfmt::skip]
+pub const COMPOSITE_KEYBOARD_REPORT_DESCRIPTOR: &[u8] = &[
+    0x05, 0x01,        // Usage Page (Generic Desktop),
+    0x09, 0x06,        // Usage (Keyboard),
+    0xA1, 0x01,        // Collection (Application),
+    0x85, 0x01,        //   Report ID (1),
+    0x75, 0x01,        //   Report Size (1),
+    0x95, 0x08,        //   Report Count (8),
+    0x05, 0x07,        //   Usage Page (Key Codes),
+    0x19, 0xE0,        //   Usage Minimum (224),
+    0x29, 0xE7,        //   Usage Maximum (231),
+    0x15, 0x00,        //   Logical Minimum (0),
+    0x25, 0x01,        //   Logical Maximum (1),
+    0x81, 0x02,        //   Input (Data, Variable, Absolute), ;Modifier byte
+    0x95, 0x01,        //   Report Count (1),
+    0x75, 0x08,        //   Report Size (8),
+    0x81, 0x01,        //   Input (Constant), ;Reserved byte
+    0x95, 0x05,        //   Report Count (5),
+    0x75, 0x01,        //   Report Size (1),
+    0x05, 0x08,        //   Usage Page (LEDs),
+    0x19, 0x01,        //   Usage Minimum (1),
+    0x29, 0x05,        //   Usage Maximum (5),
+    0x91, 0x02,        //   Output (Data, Variable, Absolute), ;LED report
+    0x95, 0x01,        //   Report Count (1),
+    0x75, 0x03,        //   Report Size (3),
+    0x91, 0x01,        //   Output (Constant), ;LED report padding
+    0x95, 0x06,        //   Report Count (6),
+    0x75, 0x08,        //   Report Size (8),
+    0x15, 0x00,        //   Logical Minimum (0),
+    0x26, 0xFF, 0x00,  //   Logical Maximum(255),
+    0x05, 0x07,        //   Usage Page (Key Codes),
+    0x19, 0x00,        //   Usage Minimum (0),
+    0x2A, 0xFF, 0x00,  //   Usage Maximum (255),
+    0x81, 0x00,        //   Input (Data, Array),
+    0xC0,              // End Collection
+    0x05, 0x0C,        // Usage Page (Consumer),
+    0x09, 0x01,        // Usage (Consumer Control),
+    0xA1, 0x01,        // Collection (Application),
+    0x85, 0x02,        //   Report ID (2),
+    0x75, 0x10,        //   Report Size(16)
+    0x95, 0x04,        //   Report Count(4)
+    0x15, 0x00,        //   Logical Minimum(0)
+    0x26, 0x9C, 0x02,  //   Logical Maximum(0x029C)
+    0x19, 0x00,        //   Usage Minimum(0)
+    0x2A, 0x9C, 0x02,  //   Usage Maximum(0x029C)
+    0x81, 0x00,        //   Input (Data, Array),
+    0xC0,              // End Collection
+    0x05, 0x01,        // Usage Page (Generic Desktop),
+    0x09, 0x80,        // Usage (System Control),
+    0xA1, 0x01,        // Collection (Application),
+    0x85, 0x03,        //   Report ID (3),
+    0x75, 0x08,        //   Report Size (8),
+    0x95, 0x01,        //   Report Count (1),
+    0x16, 0x81, 0x00,  //   Logical Minimum (0x81),
+    0x26, 0x8F, 0x00,  //   Logical Maximum (0x8F),
+    0x19, 0x81,        //   Usage Minimum (System Power Down),
+    0x29, 0x8F,        //   Usage Maximum (System Warm Restart),
+    0x81, 0x00,        //   Input (Data, Array),
+    0xC0,              // End Collection
+];
+
+/// Report of the pressed system control, [`Desktop::Undefined`] if none
+///
+/// Only the system controls [`Desktop::SystemPowerDown`] to [`Desktop::SystemWarmRestart`] are
+/// described by [`COMPOSITE_KEYBOARD_REPORT_DESCRIPTOR`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Default, PackedStruct)]
+#[packed_struct(endian = "lsb", size_bytes = "1")]
+pub struct SystemControlReport {
+    #[packed_field(ty = "enum", element_size_bytes = "1")]
+    pub usage: Desktop,
+}
+
+/// Last report written with each report ID, for `GET_REPORT` requests
+#[derive(Clone, Copy, Debug, Default)]
+struct LastReports {
+    keyboard: [u8; 8],
+    consumer: [u8; 8],
+    system: [u8; 1],
+}
+
+/// Interface multiplexing a boot keyboard, consumer control and system control with report IDs
+///
+/// Unlike [`BootKeyboardInterface`](crate::device::keyboard::BootKeyboardInterface) reports are
+/// not managed, each write is sent to the host and HID idle is not supported.
+///
+/// LED reports are held in the inner interface's output report queue of `OUT_QUEUE` reports, see
+/// [`CompositeKeyboardInterface::read_report`], and input reports in its input report queue of
+/// `IN_QUEUE` reports.
+pub struct CompositeKeyboardInterface<
+    'a,
+    B: UsbBus,
+    const OUT_QUEUE: usize = 0,
+    const IN_QUEUE: usize = 0,
+> {
+    inner: RawInterface<'a, B, OUT_QUEUE, IN_QUEUE>,
+    config: KeyboardConfig,
+    leds: LedsReceiver,
+    last_reports: Cell<LastReports>,
+    input_report_sent: bool,
+}
+
+/// Composite keyboard interface, see [`CompositeKeyboardInterface`]
+pub type CompositeKeyboard<'a, B, const OUT_QUEUE: usize = 0, const IN_QUEUE: usize = 0> =
+    CompositeKeyboardInterface<'a, B, OUT_QUEUE, IN_QUEUE>;
+
+impl<'a, B: UsbBus, const OUT_QUEUE: usize, const IN_QUEUE: usize>
+    CompositeKeyboardInterface<'a, B, OUT_QUEUE, IN_QUEUE>
+{
+    delegate! {
+        to self.inner {
+            pub fn protocol(&self) -> HidProtocol;
+            pub fn report_latency(&self) -> LatencyHistogram;
+            pub fn clear_report_latency(&self);
+            pub fn host_stalls(&self) -> u32;
+            pub fn host_stalled(&self) -> bool;
+            pub fn write_ready(&self) -> bool;
+            pub fn flush(&self) -> Result<(), UsbHidError>;
+            pub fn report_layout(&self) -> HidProtocol;
+        }
+    }
+
+    pub fn write_keyboard(&self, report: &BootKeyboardReport) -> Result<(), UsbHidError> {
+        let data = pack(report, "BootKeyboardReport")?;
+        let mut last_reports = self.last_reports.get();
+        last_reports.keyboard = data;
+        self.last_reports.set(last_reports);
+
+        match self.report_layout() {
+            HidProtocol::Boot => self.write(&data),
+            HidProtocol::Report => self.write_with_id(KEYBOARD_REPORT_ID, &data),
+        }
+    }
+
+    /// Writes a consumer control report, discarded in boot protocol
+    pub fn write_consumer(&self, report: &MultipleConsumerReport) -> Result<(), UsbHidError> {
+        let data = pack(report, "MultipleConsumerReport")?;
+        let mut last_reports = self.last_reports.get();
+        last_reports.consumer = data;
+        self.last_reports.set(last_reports);
+        self.write_with_id(CONSUMER_REPORT_ID, &data)
+    }
+
+    /// Writes a system control report, discarded in boot protocol
+    pub fn write_system(&self, report: &SystemControlReport) -> Result<(), UsbHidError> {
+        let data = pack(report, "SystemControlReport")?;
+        let mut last_reports = self.last_reports.get();
+        last_reports.system = data;
+        self.last_reports.set(last_reports);
+        self.write_with_id(SYSTEM_REPORT_ID, &data)
+    }
+
+    /// The last LED report received from the host, [`UsbHidError::WouldBlock`] if it has already
+    /// been read
+    ///
+    /// With an output report queue, see
+    /// [`RawInterfaceBuilder::out_report_queue`](crate::interface::raw::RawInterfaceBuilder::out_report_queue),
+    /// each LED report received is read in turn, oldest first.
+    pub fn read_report(&self) -> Result<KeyboardLedsReport, UsbHidError> {
+        if OUT_QUEUE > 0 {
+            let mut data = [0; 1];
+            self.inner.read_queued_packet(&mut data)?;
+            return KeyboardLedsReport::unpack(&data).map_err(|e| {
+                error!("Error unpacking KeyboardLedsReport: {:?}", e);
+                UsbHidError::SerializationError
+            });
+        }
+        match self.receive_leds() {
+            Ok(()) | Err(UsbHidError::WouldBlock) => self.leds.read(),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// The LED state, if it changed since the last call
+    ///
+    /// LED reports are received while the [`UsbHidClass`](crate::hid_class::UsbHidClass) is
+    /// polled, whether sent to the OUT endpoint or with a `SET_REPORT` request.
+    pub fn leds_changed(&self) -> Option<KeyboardLedsReport> {
+        self.leds.changed()
+    }
+
+    /// The LED state set by the last LED report received from the host
+    pub fn leds(&self) -> KeyboardLedsReport {
+        self.leds.leds()
+    }
+
+    /// Statistics about LED output reports received from the host
+    pub fn leds_report_statistics(&self) -> LedsReportStatistics {
+        self.leds.statistics()
+    }
+
+    fn receive_leds(&self) -> Result<(), UsbHidError> {
+        //Like SET_REPORT, longer OUT packets are rejected with a ReportTooLong error
+        let mut data = [0; MAX_CONTROL_REPORT_SIZE];
+        let n = self.inner.read_unqueued_packet(&mut data)?;
+        Ok(self.receive_leds_report(&data[..n])?)
+    }
+
+    fn receive_leds_report(&self, data: &[u8]) -> usb_device::Result<()> {
+        let leds = match (self.report_layout(), data) {
+            (HidProtocol::Boot, leds) => leds,
+            (HidProtocol::Report, [KEYBOARD_REPORT_ID, leds @ ..]) => leds,
+            (HidProtocol::Report, _) => {
+                warn!("Unexpected output report: {:X?}", data);
+                return Err(UsbError::ParseError);
+            }
+        };
+        let leds = self.leds.receive(leds, self.config.leds_parsing)?;
+        if OUT_QUEUE > 0 {
+            let data = leds.pack().map_err(|_| UsbError::ParseError)?;
+            self.inner.queue_output_report(&data)?;
+        }
+        Ok(())
+    }
+
+    fn write_with_id<const N: usize>(
+        &self,
+        report_id: u8,
+        data: &[u8; N],
+    ) -> Result<(), UsbHidError> {
+        if self.report_layout() == HidProtocol::Boot {
+            return Ok(());
+        }
+        let mut buffer = [0; 9];
+        buffer[0] = report_id;
+        buffer[1..=N].copy_from_slice(data);
+        self.write(&buffer[..=N])
+    }
+
+    fn write(&self, data: &[u8]) -> Result<(), UsbHidError> {
+        self.inner.write_report(data).map(|_| ())
+    }
+}
+
+impl<'a, B: UsbBus> CompositeKeyboardInterface<'a, B> {
+    pub fn default_config() -> WrappedInterfaceConfig<Self, RawInterfaceConfig<'a>, KeyboardConfig>
+    {
+        WrappedInterfaceConfig::new(
+            RawInterfaceBuilder::new(COMPOSITE_KEYBOARD_REPORT_DESCRIPTOR)
+                .boot_device(InterfaceProtocol::Keyboard)
+                .boot_report_descriptor(BOOT_KEYBOARD_REPORT_DESCRIPTOR)
+                .description("Keyboard")
+                .in_endpoint(UsbPacketSize::Bytes16, PollInterval::millis(10))
+                .with_out_endpoint(UsbPacketSize::Bytes8, PollInterval::millis(100))
+                .build()
+                .unwrap(),
+            KeyboardConfig::default(),
+        )
+    }
+}
+
+impl<'a, B: UsbBus + 'a> DefaultConfig<'a, B> for CompositeKeyboardInterface<'a, B> {
+    type Config = WrappedInterfaceConfig<Self, RawInterfaceConfig<'a>, KeyboardConfig>;
+
+    fn default_config() -> Self::Config {
+        Self::default_config()
+    }
+}
+
+fn pack<R: PackedStruct>(report: &R, name: &str) -> Result<R::ByteArray, UsbHidError> {
+    report.pack().map_err(|e| {
+        error!("Error packing {}: {:?}", name, e);
+        UsbHidError::SerializationError
+    })
+}
+
+impl<'a, B: UsbBus, const OUT_QUEUE: usize, const IN_QUEUE: usize> InterfaceClass<'a>
+    for CompositeKeyboardInterface<'a, B, OUT_QUEUE, IN_QUEUE>
+{
+    delegate! {
+        to self.inner{
+           fn report_descriptor(&self) -> &'_ [u8];
+           fn hid_descriptor_body(&self) -> HidDescriptorBodyBytes;
+           fn class_descriptor(&self, descriptor_type: u8, index: u8) -> Option<&'_ [u8]>;
+           fn take_wakeup_request(&self) -> bool;
+           fn id(&self) -> InterfaceNumber;
+           fn write_descriptors(&self, writer: &mut DescriptorWriter) -> usb_device::Result<()>;
+           fn get_string(&self, index: StringIndex, _lang_id: u16) -> Option<&'_ str>;
+           fn get_report(&mut self, data: &mut [u8]) -> usb_device::Result<usize>;
+           fn set_idle(&mut self, report_id: u8, value: u8);
+           fn get_idle(&self, report_id: u8) -> u8;
+           fn set_protocol(&mut self, protocol: HidProtocol) -> usb_device::Result<()>;
+           fn get_protocol(&self) -> HidProtocol;
+           fn out_endpoint_address(&self) -> Option<EndpointAddress>;
+           fn in_endpoint_address(&self) -> EndpointAddress;
+           fn endpoint_in_complete(&self, addr: EndpointAddress);
+        }
+    }
+
+    fn set_report(&mut self, data: &[u8]) -> usb_device::Result<()> {
+        self.receive_leds_report(data)
+    }
+
+    fn endpoint_out(&mut self) {
+        self.receive_leds().ok();
+    }
+
+    fn reset(&mut self) {
+        self.inner.reset();
+        self.last_reports.set(LastReports::default());
+        self.input_report_sent = false;
+    }
+
+    fn get_report_by_id(
+        &mut self,
+        report_type: ReportType,
+        report_id: u8,
+        data: &mut [u8],
+    ) -> usb_device::Result<usize> {
+        let last_reports = self.last_reports.get();
+        let mut buffer = [report_id, 0, 0, 0, 0, 0, 0, 0, 0];
+        let len = match (report_type, self.report_layout(), report_id) {
+            (ReportType::Input, HidProtocol::Boot, _) => {
+                buffer[..8].copy_from_slice(&last_reports.keyboard);
+                8
+            }
+            (ReportType::Input, HidProtocol::Report, KEYBOARD_REPORT_ID) => {
+                buffer[1..].copy_from_slice(&last_reports.keyboard);
+                9
+            }
+            (ReportType::Input, HidProtocol::Report, CONSUMER_REPORT_ID) => {
+                buffer[1..].copy_from_slice(&last_reports.consumer);
+                9
+            }
+            (ReportType::Input, HidProtocol::Report, SYSTEM_REPORT_ID) => {
+                buffer[1..2].copy_from_slice(&last_reports.system);
+                2
+            }
+            _ => {
+                warn!(
+                    "Unsupported GetReport type: {:?}, ID: {:X}",
+                    report_type, report_id
+                );
+                return Err(UsbError::Unsupported);
+            }
+        };
+        let data = data.get_mut(..len).ok_or(UsbError::BufferOverflow)?;
+        data.copy_from_slice(&buffer[..len]);
+        self.input_report_sent = true;
+        Ok(len)
+    }
+
+    fn get_report_ack(&mut self) -> usb_device::Result<()> {
+        if self.input_report_sent {
+            self.input_report_sent = false;
+            Ok(())
+        } else {
+            self.inner.get_report_ack()
+        }
+    }
+}
+
+impl<'a, B: UsbBus, const OUT_QUEUE: usize, const IN_QUEUE: usize>
+    WrappedInterface<'a, B, RawInterface<'a, B, OUT_QUEUE, IN_QUEUE>, KeyboardConfig>
+    for CompositeKeyboardInterface<'a, B, OUT_QUEUE, IN_QUEUE>
+{
+    fn new(interface: RawInterface<'a, B, OUT_QUEUE, IN_QUEUE>, config: KeyboardConfig) -> Self {
+        Self {
+            inner: interface,
+            config,
+            leds: Default::default(),
+            last_reports: Default::default(),
+            input_report_sent: false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use packed_struct::PackedStruct;
+
+    use crate::device::composite_keyboard::*;
+
+    #[test]
+    fn pack_system_report() {
+        let report = SystemControlReport {
+            usage: Desktop::SystemSleep,
+        };
+        assert_eq!(report.pack().unwrap(), [0x82]);
+    }
+
+    #[cfg(feature = "simulation")]
+    #[test]
+    fn reports_multiplexed_by_id() {
+        use usb_device::bus::UsbBusAllocator;
+
+        use crate::page::{Consumer, Keyboard};
+        use crate::simulation::{control_transfer, device_builder, Response, SimBus};
+
+        let usb_alloc = UsbBusAllocator::new(SimBus::new());
+        let mut keyboard = UsbHidClassBuilder::new()
+            .add_interface(CompositeKeyboard::default_config())
+            .build(&usb_alloc)
+            .unwrap();
+        let mut usb_dev = device_builder(&usb_alloc, 64).build();
+
+        let interface = keyboard.interface::<CompositeKeyboard<'_, _>, _>();
+        interface
+            .write_keyboard(&BootKeyboardReport::new([Keyboard::A]))
+            .unwrap();
+        assert_eq!(
+            usb_dev.bus().host_take_in(1).unwrap(),
+            [0x01, 0x00, 0x00, 0x04, 0x00, 0x00, 0x00, 0x00, 0x00]
+        );
+        interface
+            .write_consumer(&MultipleConsumerReport {
+                codes: [
+                    Consumer::Mute,
+                    Consumer::Unassigned,
+                    Consumer::Unassigned,
+                    Consumer::Unassigned,
+                ],
+            })
+            .unwrap();
+        assert_eq!(
+            usb_dev.bus().host_take_in(1).unwrap(),
+            [0x02, 0xE2, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00]
+        );
+        interface
+            .write_system(&SystemControlReport {
+                usage: Desktop::SystemPowerDown,
+            })
+            .unwrap();
+        assert_eq!(usb_dev.bus().host_take_in(1).unwrap(), [0x03, 0x81]);
+
+        // GET_REPORT (Input) consumer control
+        assert_eq!(
+            control_transfer(
+                &mut usb_dev,
+                &mut [&mut keyboard],
+                [0xA1, 0x01, 0x02, 0x01, 0x00, 0x00, 0x09, 0x00],
+                &[]
+            ),
+            Response::Data(vec![0x02, 0xE2, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00])
+        );
+
+        // SET_REPORT (Output) LEDs
+        assert_eq!(
+            control_transfer(
+                &mut usb_dev,
+                &mut [&mut keyboard],
+                [0x21, 0x09, 0x01, 0x02, 0x00, 0x00, 0x02, 0x00],
+                &[0x01, 0x02]
+            ),
+            Response::Ack
+        );
+        let interface = keyboard.interface::<CompositeKeyboard<'_, _>, _>();
+        assert!(interface.read_report().unwrap().caps_lock);
+    }
+
+    #[cfg(feature = "simulation")]
+    #[test]
+    fn boot_protocol_sends_keyboard_only() {
+        use usb_device::bus::UsbBusAllocator;
+
+        use crate::page::Keyboard;
+        use crate::simulation::{control_transfer, device_builder, Response, SimBus};
+
+        let usb_alloc = UsbBusAllocator::new(SimBus::new());
+        let mut keyboard = UsbHidClassBuilder::new()
+            .add_interface(CompositeKeyboard::default_config())
+            .build(&usb_alloc)
+            .unwrap();
+        let mut usb_dev = device_builder(&usb_alloc, 64).build();
+
+        // SET_PROTOCOL (Boot)
+        assert_eq!(
+            control_transfer(
+                &mut usb_dev,
+                &mut [&mut keyboard],
+                [0x21, 0x0B, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00],
+                &[]
+            ),
+            Response::Ack
+        );
+
+        let interface = keyboard.interface::<CompositeKeyboard<'_, _>, _>();
+        interface
+            .write_system(&SystemControlReport {
+                usage: Desktop::SystemSleep,
+            })
+            .unwrap();
+        assert!(usb_dev.bus().host_take_in(1).is_none());
+        interface
+            .write_keyboard(&BootKeyboardReport::new([Keyboard::B]))
+            .unwrap();
+        assert_eq!(
+            usb_dev.bus().host_take_in(1).unwrap(),
+            [0x00, 0x00, 0x05, 0x00, 0x00, 0x00, 0x00, 0x00]
+        );
+    }
+}
@@ -1,16 +1,18 @@
 //!HID consumer control devices
 
 use delegate::delegate;
-use fugit::ExtU32;
-use log::error;
 use packed_struct::prelude::*;
 use usb_device::class_prelude::*;
-use usb_device::{Result, UsbError};
+use usb_device::Result;
 
 use crate::hid_class::prelude::*;
+use crate::interface::duration::PollInterval;
 use crate::interface::raw::{RawInterface, RawInterfaceConfig};
-use crate::interface::{InterfaceClass, WrappedInterface, WrappedInterfaceConfig};
+use crate::interface::{
+    DefaultConfig, HidDescriptorBodyBytes, InterfaceClass, WrappedInterface, WrappedInterfaceConfig,
+};
 use crate::page::Consumer;
+use crate::UsbHidError;
 
 ///Consumer control report descriptor - Four `u16` consumer control usage codes as an array (8 bytes)
 #[rustfmt::skip]
@@ -95,32 +97,58 @@ pub struct ConsumerControlInterface<'a, B: UsbBus> {
     inner: RawInterface<'a, B>,
 }
 
+/// Multiple code consumer control interface, see [`ConsumerControlInterface`]
+pub type ConsumerControl<'a, B> = ConsumerControlInterface<'a, B>;
+
 impl<'a, B: UsbBus> ConsumerControlInterface<'a, B> {
-    pub fn write_report(&self, report: &MultipleConsumerReport) -> usb_device::Result<usize> {
+    pub fn write_report(
+        &self,
+        report: &MultipleConsumerReport,
+    ) -> core::result::Result<usize, UsbHidError> {
         let data = report.pack().map_err(|e| {
             error!("Error packing MultipleConsumerReport: {:?}", e);
-            UsbError::ParseError
+            UsbHidError::SerializationError
         })?;
         self.inner.write_report(&data)
     }
 
     pub fn default_config() -> WrappedInterfaceConfig<Self, RawInterfaceConfig<'a>> {
+        Self::default_config_with_descriptor(MULTIPLE_CODE_REPORT_DESCRIPTOR)
+    }
+
+    /// Default configuration with an alternative report descriptor
+    ///
+    /// The descriptor must describe reports with the same layout as [`MultipleConsumerReport`]
+    pub fn default_config_with_descriptor(
+        report_descriptor: &'a [u8],
+    ) -> WrappedInterfaceConfig<Self, RawInterfaceConfig<'a>> {
         WrappedInterfaceConfig::new(
-            RawInterfaceBuilder::new(MULTIPLE_CODE_REPORT_DESCRIPTOR)
+            RawInterfaceBuilder::new(report_descriptor)
                 .description("Consumer Control")
-                .in_endpoint(UsbPacketSize::Bytes8, 50.millis())
-                .unwrap()
+                .in_endpoint(UsbPacketSize::Bytes8, PollInterval::millis(50))
                 .without_out_endpoint()
-                .build(),
+                .build()
+                .unwrap(),
             (),
         )
     }
 }
 
+impl<'a, B: UsbBus + 'a> DefaultConfig<'a, B> for ConsumerControlInterface<'a, B> {
+    type Config = WrappedInterfaceConfig<Self, RawInterfaceConfig<'a>>;
+
+    fn default_config() -> Self::Config {
+        Self::default_config()
+    }
+}
+
 impl<'a, B: UsbBus> InterfaceClass<'a> for ConsumerControlInterface<'a, B> {
     delegate! {
         to self.inner{
            fn report_descriptor(&self) -> &'_ [u8];
+           fn hid_descriptor_body(&self) -> HidDescriptorBodyBytes;
+           fn class_descriptor(&self, descriptor_type: u8, index: u8) -> Option<&'_ [u8]>;
+           fn take_wakeup_request(&self) -> bool;
            fn id(&self) -> InterfaceNumber;
            fn write_descriptors(&self, writer: &mut DescriptorWriter) -> usb_device::Result<()>;
            fn get_string(&self, index: StringIndex, _lang_id: u16) -> Option<&'_ str>;
@@ -130,8 +158,12 @@ impl<'a, B: UsbBus> InterfaceClass<'a> for ConsumerControlInterface<'a, B> {
            fn get_report_ack(&mut self) -> Result<()>;
            fn set_idle(&mut self, report_id: u8, value: u8);
            fn get_idle(&self, report_id: u8) -> u8;
-           fn set_protocol(&mut self, protocol: HidProtocol);
+           fn set_protocol(&mut self, protocol: HidProtocol) -> usb_device::Result<()>;
            fn get_protocol(&self) -> HidProtocol;
+           fn out_endpoint_address(&self) -> Option<EndpointAddress>;
+           fn in_endpoint_address(&self) -> EndpointAddress;
+           fn endpoint_in_complete(&self, addr: EndpointAddress);
+           fn endpoint_out(&mut self);
         }
     }
 }
@@ -148,32 +180,58 @@ pub struct ConsumerControlFixedInterface<'a, B: UsbBus> {
     inner: RawInterface<'a, B>,
 }
 
+/// Fixed function consumer control interface, see [`ConsumerControlFixedInterface`]
+pub type ConsumerControlFixed<'a, B> = ConsumerControlFixedInterface<'a, B>;
+
 impl<'a, B: UsbBus> ConsumerControlFixedInterface<'a, B> {
-    pub fn write_report(&self, report: &FixedFunctionReport) -> usb_device::Result<usize> {
+    pub fn write_report(
+        &self,
+        report: &FixedFunctionReport,
+    ) -> core::result::Result<usize, UsbHidError> {
         let data = report.pack().map_err(|e| {
             error!("Error packing MultipleConsumerReport: {:?}", e);
-            UsbError::ParseError
+            UsbHidError::SerializationError
         })?;
         self.inner.write_report(&data)
     }
 
     pub fn default_config() -> WrappedInterfaceConfig<Self, RawInterfaceConfig<'a>> {
+        Self::default_config_with_descriptor(FIXED_FUNCTION_REPORT_DESCRIPTOR)
+    }
+
+    /// Default configuration with an alternative report descriptor
+    ///
+    /// The descriptor must describe reports with the same layout as [`FixedFunctionReport`]
+    pub fn default_config_with_descriptor(
+        report_descriptor: &'a [u8],
+    ) -> WrappedInterfaceConfig<Self, RawInterfaceConfig<'a>> {
         WrappedInterfaceConfig::new(
-            RawInterfaceBuilder::new(FIXED_FUNCTION_REPORT_DESCRIPTOR)
+            RawInterfaceBuilder::new(report_descriptor)
                 .description("Consumer Control")
-                .in_endpoint(UsbPacketSize::Bytes8, 50.millis())
-                .unwrap()
+                .in_endpoint(UsbPacketSize::Bytes8, PollInterval::millis(50))
                 .without_out_endpoint()
-                .build(),
+                .build()
+                .unwrap(),
             (),
         )
     }
 }
 
+impl<'a, B: UsbBus + 'a> DefaultConfig<'a, B> for ConsumerControlFixedInterface<'a, B> {
+    type Config = WrappedInterfaceConfig<Self, RawInterfaceConfig<'a>>;
+
+    fn default_config() -> Self::Config {
+        Self::default_config()
+    }
+}
+
 impl<'a, B: UsbBus> InterfaceClass<'a> for ConsumerControlFixedInterface<'a, B> {
     delegate! {
         to self.inner{
            fn report_descriptor(&self) -> &'_ [u8];
+           fn hid_descriptor_body(&self) -> HidDescriptorBodyBytes;
+           fn class_descriptor(&self, descriptor_type: u8, index: u8) -> Option<&'_ [u8]>;
+           fn take_wakeup_request(&self) -> bool;
            fn id(&self) -> InterfaceNumber;
            fn write_descriptors(&self, writer: &mut DescriptorWriter) -> usb_device::Result<()>;
            fn get_string(&self, index: StringIndex, _lang_id: u16) -> Option<&'_ str>;
@@ -183,8 +241,12 @@ impl<'a, B: UsbBus> InterfaceClass<'a> for ConsumerControlFixedInterface<'a, B>
            fn get_report_ack(&mut self) -> Result<()>;
            fn set_idle(&mut self, report_id: u8, value: u8);
            fn get_idle(&self, report_id: u8) -> u8;
-           fn set_protocol(&mut self, protocol: HidProtocol);
+           fn set_protocol(&mut self, protocol: HidProtocol) -> usb_device::Result<()>;
            fn get_protocol(&self) -> HidProtocol;
+           fn out_endpoint_address(&self) -> Option<EndpointAddress>;
+           fn in_endpoint_address(&self) -> EndpointAddress;
+           fn endpoint_in_complete(&self, addr: EndpointAddress);
+           fn endpoint_out(&mut self);
         }
     }
 }
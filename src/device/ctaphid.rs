@@ -0,0 +1,661 @@
+//!FIDO2 CTAP-HID transport
+//!
+//! Implements the message framing of the
+//! [CTAP HID protocol](https://fidoalliance.org/specs/fido-v2.1-ps-20210615/fido-client-to-authenticator-protocol-v2.1-ps-20210615.html#usb)
+//! on top of the 64 byte reports of [`FIDO_REPORT_DESCRIPTOR`]:
+//!
+//! * Requests split across initialization and continuation packets are reassembled, and
+//!   complete `CTAPHID_CBOR`, `CTAPHID_MSG`, `CTAPHID_WINK`, `CTAPHID_CANCEL` and vendor
+//!   requests are handed to the application by [`CtapHidInterface::read_message`]
+//! * `CTAPHID_INIT` allocates channels and `CTAPHID_PING` is echoed without involving the
+//!   application
+//! * Framing errors, and requests from other channels while a transaction is in progress, are
+//!   answered with `CTAPHID_ERROR`
+//!
+//! Responses are queued with [`CtapHidInterface::respond`] and sent, along with the replies
+//! generated by the interface, by [`CtapHidInterface::flush`], which should be called
+//! regularly. The interface has no clock, so incomplete requests are not timed out.
+use core::cell::RefCell;
+
+use delegate::delegate;
+use heapless::{Deque, Vec};
+use usb_device::bus::{InterfaceNumber, StringIndex, UsbBus};
+use usb_device::class_prelude::{DescriptorWriter, EndpointAddress};
+
+use crate::device::fido::FIDO_REPORT_DESCRIPTOR;
+use crate::hid_class::descriptor::HidProtocol;
+use crate::hid_class::prelude::*;
+use crate::interface::duration::PollInterval;
+use crate::interface::raw::{RawInterface, RawInterfaceConfig};
+use crate::interface::{
+    DefaultConfig, HidDescriptorBodyBytes, InterfaceClass, WrappedInterface, WrappedInterfaceConfig,
+};
+use crate::UsbHidError;
+
+pub const CTAPHID_PACKET_SIZE: usize = 64;
+const INIT_DATA_SIZE: usize = CTAPHID_PACKET_SIZE - 7;
+const CONT_DATA_SIZE: usize = CTAPHID_PACKET_SIZE - 5;
+/// Largest message an initialization packet and 128 continuation packets can carry
+pub const CTAPHID_MAX_MESSAGE_SIZE: usize = INIT_DATA_SIZE + 128 * CONT_DATA_SIZE;
+
+pub const CTAPHID_BROADCAST_CID: u32 = 0xFFFF_FFFF;
+pub const CTAPHID_PROTOCOL_VERSION: u8 = 2;
+
+/// The authenticator implements `CTAPHID_WINK`
+pub const CAPABILITY_WINK: u8 = 0x01;
+/// The authenticator implements `CTAPHID_CBOR`
+pub const CAPABILITY_CBOR: u8 = 0x04;
+/// The authenticator does not implement `CTAPHID_MSG`
+pub const CAPABILITY_NMSG: u8 = 0x08;
+
+const CMD_PING: u8 = 0x01;
+const CMD_MSG: u8 = 0x03;
+const CMD_LOCK: u8 = 0x04;
+const CMD_INIT: u8 = 0x06;
+const CMD_WINK: u8 = 0x08;
+const CMD_CBOR: u8 = 0x10;
+const CMD_CANCEL: u8 = 0x11;
+const CMD_KEEPALIVE: u8 = 0x3B;
+const CMD_ERROR: u8 = 0x3F;
+const CMD_VENDOR_FIRST: u8 = 0x40;
+
+/// CTAP-HID command, without the initialization packet flag
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Command {
+    Ping,
+    Msg,
+    Lock,
+    Init,
+    Wink,
+    Cbor,
+    Cancel,
+    Keepalive,
+    Error,
+    /// Vendor specific command, `0x40` to `0x7F`
+    Vendor(u8),
+    Unknown(u8),
+}
+
+impl From<u8> for Command {
+    fn from(value: u8) -> Self {
+        match value {
+            CMD_PING => Command::Ping,
+            CMD_MSG => Command::Msg,
+            CMD_LOCK => Command::Lock,
+            CMD_INIT => Command::Init,
+            CMD_WINK => Command::Wink,
+            CMD_CBOR => Command::Cbor,
+            CMD_CANCEL => Command::Cancel,
+            CMD_KEEPALIVE => Command::Keepalive,
+            CMD_ERROR => Command::Error,
+            CMD_VENDOR_FIRST..=0x7F => Command::Vendor(value),
+            _ => Command::Unknown(value),
+        }
+    }
+}
+
+impl From<Command> for u8 {
+    fn from(command: Command) -> Self {
+        match command {
+            Command::Ping => CMD_PING,
+            Command::Msg => CMD_MSG,
+            Command::Lock => CMD_LOCK,
+            Command::Init => CMD_INIT,
+            Command::Wink => CMD_WINK,
+            Command::Cbor => CMD_CBOR,
+            Command::Cancel => CMD_CANCEL,
+            Command::Keepalive => CMD_KEEPALIVE,
+            Command::Error => CMD_ERROR,
+            Command::Vendor(value) | Command::Unknown(value) => value & 0x7F,
+        }
+    }
+}
+
+/// Error code of a `CTAPHID_ERROR` response
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[repr(u8)]
+pub enum CtapHidError {
+    InvalidCommand = 0x01,
+    InvalidParameter = 0x02,
+    InvalidLength = 0x03,
+    InvalidSequence = 0x04,
+    MessageTimeout = 0x05,
+    ChannelBusy = 0x06,
+    LockRequired = 0x0A,
+    InvalidChannel = 0x0B,
+    Other = 0x7F,
+}
+
+/// Status of a `CTAPHID_KEEPALIVE` response
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[repr(u8)]
+pub enum KeepaliveStatus {
+    Processing = 1,
+    UserPresenceNeeded = 2,
+}
+
+/// A complete request for the application
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct CtapHidMessage<const N: usize> {
+    pub cid: u32,
+    pub command: Command,
+    pub data: Vec<u8, N>,
+}
+
+/// Configuration of [`CtapHidInterface`]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct CtapHidConfig {
+    /// Major, minor and build version reported in `CTAPHID_INIT` responses
+    pub device_version: [u8; 3],
+    /// `CAPABILITY_*` flags, requests for commands the application does not implement are
+    /// rejected by the interface
+    pub capabilities: u8,
+}
+
+impl Default for CtapHidConfig {
+    fn default() -> Self {
+        Self {
+            device_version: [1, 0, 0],
+            capabilities: CAPABILITY_CBOR | CAPABILITY_NMSG,
+        }
+    }
+}
+
+struct Receive<const N: usize> {
+    cid: u32,
+    command: u8,
+    len: usize,
+    seq: u8,
+    data: Vec<u8, N>,
+}
+
+struct Transmit<const N: usize> {
+    cid: u32,
+    command: u8,
+    data: Vec<u8, N>,
+    offset: usize,
+    /// Sequence number of the next continuation packet, `None` until the initialization packet
+    /// is sent
+    seq: Option<u8>,
+}
+
+impl<const N: usize> Transmit<N> {
+    fn packet(&self) -> [u8; CTAPHID_PACKET_SIZE] {
+        let mut packet = [0; CTAPHID_PACKET_SIZE];
+        packet[..4].copy_from_slice(&self.cid.to_be_bytes());
+        let (header, size) = match self.seq {
+            None => {
+                packet[4] = self.command | 0x80;
+                packet[5..7].copy_from_slice(&(self.data.len() as u16).to_be_bytes());
+                (7, INIT_DATA_SIZE)
+            }
+            Some(seq) => {
+                packet[4] = seq;
+                (5, CONT_DATA_SIZE)
+            }
+        };
+        let end = self.data.len().min(self.offset + size);
+        packet[header..header + end - self.offset].copy_from_slice(&self.data[self.offset..end]);
+        packet
+    }
+
+    /// Moves past the last packet, returning `true` once all the data has been sent
+    fn advance(&mut self) -> bool {
+        let size = match self.seq {
+            None => INIT_DATA_SIZE,
+            Some(_) => CONT_DATA_SIZE,
+        };
+        self.offset += size;
+        self.seq = Some(self.seq.map_or(0, |seq| seq + 1));
+        self.offset >= self.data.len()
+    }
+}
+
+struct State<const N: usize> {
+    next_cid: u32,
+    rx: Option<Receive<N>>,
+    tx: Option<Transmit<N>>,
+    errors: Deque<(u32, CtapHidError), 4>,
+    /// Channel of the request being processed by the application
+    busy: Option<u32>,
+}
+
+impl<const N: usize> Default for State<N> {
+    fn default() -> Self {
+        Self {
+            next_cid: 1,
+            rx: None,
+            tx: None,
+            errors: Deque::new(),
+            busy: None,
+        }
+    }
+}
+
+impl<const N: usize> State<N> {
+    fn allocate_cid(&mut self) -> u32 {
+        let cid = self.next_cid;
+        self.next_cid = match cid.wrapping_add(1) {
+            0 | CTAPHID_BROADCAST_CID => 1,
+            next => next,
+        };
+        cid
+    }
+
+    fn error(&mut self, cid: u32, error: CtapHidError) {
+        warn!("CTAPHID error {:?} on channel {:X}", error, cid);
+        if self.errors.push_back((cid, error)).is_err() {
+            error!("CTAPHID error queue full, dropping {:?}", error);
+        }
+    }
+
+    fn reply(&mut self, cid: u32, command: u8, data: &[u8]) {
+        if self.tx.is_some() {
+            self.error(cid, CtapHidError::ChannelBusy);
+            return;
+        }
+        match Vec::from_slice(data) {
+            Ok(data) => {
+                self.tx = Some(Transmit {
+                    cid,
+                    command,
+                    data,
+                    offset: 0,
+                    seq: None,
+                })
+            }
+            Err(_) => self.error(cid, CtapHidError::Other),
+        }
+    }
+}
+
+/// FIDO2 authenticator interface with messages of up to `N` bytes
+pub struct CtapHidInterface<'a, B: UsbBus, const N: usize> {
+    inner: RawInterface<'a, B>,
+    config: CtapHidConfig,
+    state: RefCell<State<N>>,
+}
+
+/// CTAP-HID interface with the default CTAP2 maximum message size of 1024 bytes, see
+/// [`CtapHidInterface`]
+pub type CtapHid<'a, B> = CtapHidInterface<'a, B, 1024>;
+
+impl<'a, B: UsbBus, const N: usize> CtapHidInterface<'a, B, N> {
+    delegate! {
+        to self.inner {
+            pub fn host_stalls(&self) -> u32;
+            pub fn host_stalled(&self) -> bool;
+            pub fn write_ready(&self) -> bool;
+        }
+    }
+
+    /// Reads packets from the host until a request for the application is complete
+    ///
+    /// Returns [`UsbHidError::WouldBlock`] when no request is complete. Until the request is
+    /// answered with [`Self::respond`], requests on other channels are rejected as busy, and
+    /// only `CTAPHID_CANCEL` and `CTAPHID_INIT` are accepted on the request's channel.
+    pub fn read_message(&self) -> Result<CtapHidMessage<N>, UsbHidError> {
+        let mut packet = [0; CTAPHID_PACKET_SIZE];
+        loop {
+            let len = self.inner.read_report(&mut packet)?;
+            if len < 7 {
+                warn!("Short CTAPHID packet: {} bytes", len);
+                continue;
+            }
+            if let Some(message) = self.process(&packet) {
+                return Ok(message);
+            }
+        }
+    }
+
+    /// Queues the response to the request on channel `cid`
+    ///
+    /// Returns [`UsbHidError::WouldBlock`] if the previous response has not yet been sent.
+    pub fn respond(&self, cid: u32, command: Command, data: &[u8]) -> Result<(), UsbHidError> {
+        let mut state = self.state.borrow_mut();
+        if state.tx.is_some() {
+            return Err(UsbHidError::WouldBlock);
+        }
+        if data.len() > CTAPHID_MAX_MESSAGE_SIZE {
+            return Err(UsbHidError::SerializationError);
+        }
+        state.tx = Some(Transmit {
+            cid,
+            command: command.into(),
+            data: Vec::from_slice(data).map_err(|_| UsbHidError::SerializationError)?,
+            offset: 0,
+            seq: None,
+        });
+        if state.busy == Some(cid) {
+            state.busy = None;
+        }
+        Ok(())
+    }
+
+    /// Sends a `CTAPHID_KEEPALIVE` on channel `cid` while the application processes a request
+    pub fn keepalive(&self, cid: u32, status: KeepaliveStatus) -> Result<(), UsbHidError> {
+        if self.state.borrow().tx.is_some() {
+            return Err(UsbHidError::WouldBlock);
+        }
+        self.write_packet(cid, CMD_KEEPALIVE, &[status as u8])
+    }
+
+    /// Sends queued error replies and responses
+    ///
+    /// Returns [`UsbHidError::WouldBlock`] until every queued packet has been sent.
+    pub fn flush(&self) -> Result<(), UsbHidError> {
+        let mut state = self.state.borrow_mut();
+        while let Some(&(cid, error)) = state.errors.front() {
+            self.write_packet(cid, CMD_ERROR, &[error as u8])?;
+            state.errors.pop_front();
+        }
+        while let Some(tx) = state.tx.as_mut() {
+            self.inner.write_report(&tx.packet())?;
+            if tx.advance() {
+                state.tx = None;
+            }
+        }
+        Ok(())
+    }
+
+    fn write_packet(&self, cid: u32, command: u8, data: &[u8]) -> Result<(), UsbHidError> {
+        let mut packet = [0; CTAPHID_PACKET_SIZE];
+        packet[..4].copy_from_slice(&cid.to_be_bytes());
+        packet[4] = command | 0x80;
+        packet[5..7].copy_from_slice(&(data.len() as u16).to_be_bytes());
+        packet[7..7 + data.len()].copy_from_slice(data);
+        self.inner.write_report(&packet).map(|_| ())
+    }
+
+    fn process(&self, packet: &[u8; CTAPHID_PACKET_SIZE]) -> Option<CtapHidMessage<N>> {
+        let mut state = self.state.borrow_mut();
+        let cid = u32::from_be_bytes([packet[0], packet[1], packet[2], packet[3]]);
+
+        if packet[4] & 0x80 == 0 {
+            let rx = match state.rx.as_mut() {
+                Some(rx) if rx.cid == cid => rx,
+                // Continuation packets of other channels and of aborted requests are ignored
+                _ => return None,
+            };
+            if packet[4] != rx.seq {
+                state.rx = None;
+                state.error(cid, CtapHidError::InvalidSequence);
+                return None;
+            }
+            rx.seq += 1;
+            let end = (rx.len - rx.data.len()).min(CONT_DATA_SIZE);
+            rx.data.extend_from_slice(&packet[5..5 + end]).ok()?;
+        } else {
+            let command = packet[4] & 0x7F;
+            let len = usize::from(u16::from_be_bytes([packet[5], packet[6]]));
+
+            if command == CMD_INIT {
+                self.init(&mut state, cid, len, &packet[7..]);
+                return None;
+            }
+            if cid == 0 || cid == CTAPHID_BROADCAST_CID {
+                state.error(cid, CtapHidError::InvalidChannel);
+                return None;
+            }
+            if let Some(rx) = &state.rx {
+                let error = if rx.cid == cid {
+                    state.rx = None;
+                    CtapHidError::InvalidSequence
+                } else {
+                    CtapHidError::ChannelBusy
+                };
+                state.error(cid, error);
+                return None;
+            }
+            if command == CMD_CANCEL {
+                return (state.busy == Some(cid)).then(|| CtapHidMessage {
+                    cid,
+                    command: Command::Cancel,
+                    data: Vec::new(),
+                });
+            }
+            if state.busy.is_some() {
+                state.error(cid, CtapHidError::ChannelBusy);
+                return None;
+            }
+            if len > N.min(CTAPHID_MAX_MESSAGE_SIZE) {
+                state.error(cid, CtapHidError::InvalidLength);
+                return None;
+            }
+
+            let mut data = Vec::new();
+            data.extend_from_slice(&packet[7..7 + len.min(INIT_DATA_SIZE)])
+                .ok()?;
+            state.rx = Some(Receive {
+                cid,
+                command,
+                len,
+                seq: 0,
+                data,
+            });
+        }
+
+        match state.rx.take() {
+            Some(rx) if rx.data.len() == rx.len => self.dispatch(&mut state, rx),
+            rx => {
+                state.rx = rx;
+                None
+            }
+        }
+    }
+
+    fn init(&self, state: &mut State<N>, cid: u32, len: usize, data: &[u8]) {
+        if cid == 0 {
+            state.error(cid, CtapHidError::InvalidChannel);
+            return;
+        }
+        if len != 8 {
+            state.error(cid, CtapHidError::InvalidLength);
+            return;
+        }
+        let new_cid = if cid == CTAPHID_BROADCAST_CID {
+            state.allocate_cid()
+        } else {
+            // Resynchronise the channel, abandoning any transaction in progress
+            if state.rx.as_ref().is_some_and(|rx| rx.cid == cid) {
+                state.rx = None;
+            }
+            if state.busy == Some(cid) {
+                state.busy = None;
+            }
+            cid
+        };
+
+        let mut response = [0; 17];
+        response[..8].copy_from_slice(&data[..8]);
+        response[8..12].copy_from_slice(&new_cid.to_be_bytes());
+        response[12] = CTAPHID_PROTOCOL_VERSION;
+        response[13..16].copy_from_slice(&self.config.device_version);
+        response[16] = self.config.capabilities;
+        state.reply(cid, CMD_INIT, &response);
+    }
+
+    fn dispatch(&self, state: &mut State<N>, rx: Receive<N>) -> Option<CtapHidMessage<N>> {
+        let capabilities = self.config.capabilities;
+        let supported = match rx.command {
+            CMD_PING => {
+                state.reply(rx.cid, CMD_PING, &rx.data);
+                return None;
+            }
+            CMD_CBOR => capabilities & CAPABILITY_CBOR != 0,
+            CMD_MSG => capabilities & CAPABILITY_NMSG == 0,
+            CMD_WINK => capabilities & CAPABILITY_WINK != 0,
+            CMD_VENDOR_FIRST..=0x7F => true,
+            _ => false,
+        };
+        if !supported {
+            state.error(rx.cid, CtapHidError::InvalidCommand);
+            return None;
+        }
+        state.busy = Some(rx.cid);
+        Some(CtapHidMessage {
+            cid: rx.cid,
+            command: rx.command.into(),
+            data: rx.data,
+        })
+    }
+
+    pub fn default_config() -> WrappedInterfaceConfig<Self, RawInterfaceConfig<'a>, CtapHidConfig> {
+        Self::config(CtapHidConfig::default())
+    }
+
+    pub fn config(
+        config: CtapHidConfig,
+    ) -> WrappedInterfaceConfig<Self, RawInterfaceConfig<'a>, CtapHidConfig> {
+        WrappedInterfaceConfig::new(
+            RawInterfaceBuilder::new(FIDO_REPORT_DESCRIPTOR)
+                .description("FIDO2 Authenticator")
+                .in_endpoint(UsbPacketSize::Bytes64, PollInterval::millis(5))
+                .with_out_endpoint(UsbPacketSize::Bytes64, PollInterval::millis(5))
+                .build()
+                .unwrap(),
+            config,
+        )
+    }
+}
+
+impl<'a, B: UsbBus + 'a, const N: usize> DefaultConfig<'a, B> for CtapHidInterface<'a, B, N> {
+    type Config = WrappedInterfaceConfig<Self, RawInterfaceConfig<'a>, CtapHidConfig>;
+
+    fn default_config() -> Self::Config {
+        Self::default_config()
+    }
+}
+
+impl<'a, B: UsbBus, const N: usize> InterfaceClass<'a> for CtapHidInterface<'a, B, N> {
+    delegate! {
+        to self.inner{
+           fn report_descriptor(&self) -> &'_ [u8];
+           fn hid_descriptor_body(&self) -> HidDescriptorBodyBytes;
+           fn class_descriptor(&self, descriptor_type: u8, index: u8) -> Option<&'_ [u8]>;
+           fn take_wakeup_request(&self) -> bool;
+           fn id(&self) -> InterfaceNumber;
+           fn write_descriptors(&self, writer: &mut DescriptorWriter) -> usb_device::Result<()>;
+           fn get_string(&self, index: StringIndex, _lang_id: u16) -> Option<&'_ str>;
+           fn set_report(&mut self, data: &[u8]) -> usb_device::Result<()>;
+           fn get_report(&mut self, data: &mut [u8]) -> usb_device::Result<usize>;
+           fn get_report_ack(&mut self) -> usb_device::Result<()>;
+           fn set_idle(&mut self, report_id: u8, value: u8);
+           fn get_idle(&self, report_id: u8) -> u8;
+           fn set_protocol(&mut self, protocol: HidProtocol) -> usb_device::Result<()>;
+           fn get_protocol(&self) -> HidProtocol;
+           fn out_endpoint_address(&self) -> Option<EndpointAddress>;
+           fn in_endpoint_address(&self) -> EndpointAddress;
+           fn endpoint_in_complete(&self, addr: EndpointAddress);
+           fn endpoint_out(&mut self);
+        }
+    }
+
+    fn reset(&mut self) {
+        self.inner.reset();
+        let state = self.state.get_mut();
+        *state = State {
+            next_cid: state.next_cid,
+            ..Default::default()
+        };
+    }
+}
+
+impl<'a, B: UsbBus, const N: usize> WrappedInterface<'a, B, RawInterface<'a, B>, CtapHidConfig>
+    for CtapHidInterface<'a, B, N>
+{
+    fn new(interface: RawInterface<'a, B>, config: CtapHidConfig) -> Self {
+        Self {
+            inner: interface,
+            config,
+            state: RefCell::new(State::default()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::device::ctaphid::*;
+
+    #[test]
+    fn command_round_trip() {
+        for value in 0..0x80 {
+            assert_eq!(u8::from(Command::from(value)), value);
+        }
+        assert_eq!(Command::from(0x10), Command::Cbor);
+        assert_eq!(Command::from(0x41), Command::Vendor(0x41));
+    }
+
+    #[test]
+    fn max_message_size() {
+        assert_eq!(CTAPHID_MAX_MESSAGE_SIZE, 7609);
+    }
+
+    #[cfg(feature = "simulation")]
+    #[test]
+    fn init_and_reassemble_cbor() {
+        use usb_device::bus::UsbBusAllocator;
+
+        use crate::simulation::{device_builder, SimBus};
+
+        fn packet(header: &[u8], data: &[u8]) -> [u8; CTAPHID_PACKET_SIZE] {
+            let mut packet = [0; CTAPHID_PACKET_SIZE];
+            packet[..header.len()].copy_from_slice(header);
+            packet[header.len()..header.len() + data.len()].copy_from_slice(data);
+            packet
+        }
+
+        let usb_alloc = UsbBusAllocator::new(SimBus::new());
+        let fido = UsbHidClassBuilder::new()
+            .add_interface(CtapHid::default_config())
+            .build(&usb_alloc)
+            .unwrap();
+        let usb_dev = device_builder(&usb_alloc, 64).build();
+        let bus = usb_dev.bus();
+        let interface = fido.interface::<CtapHid<'_, _>, _>();
+
+        // CTAPHID_INIT on the broadcast channel
+        let nonce = [1, 2, 3, 4, 5, 6, 7, 8];
+        bus.host_out(
+            1,
+            &packet(&[0xFF, 0xFF, 0xFF, 0xFF, 0x86, 0x00, 0x08], &nonce),
+        );
+        assert!(matches!(
+            interface.read_message(),
+            Err(UsbHidError::WouldBlock)
+        ));
+        interface.flush().unwrap();
+        let response = bus.host_take_in(1).unwrap();
+        assert_eq!(response[..7], [0xFF, 0xFF, 0xFF, 0xFF, 0x86, 0x00, 0x11]);
+        assert_eq!(response[7..15], nonce);
+        assert_eq!(response[15..19], [0x00, 0x00, 0x00, 0x01]);
+        assert_eq!(response[23], CAPABILITY_CBOR | CAPABILITY_NMSG);
+
+        // CTAPHID_CBOR of 100 bytes across two packets
+        let request: std::vec::Vec<u8> = (0..100).collect();
+        bus.host_out(1, &packet(&[0, 0, 0, 1, 0x90, 0x00, 100], &request[..57]));
+        bus.host_out(1, &packet(&[0, 0, 0, 1, 0x00], &request[57..]));
+        let message = interface.read_message().unwrap();
+        assert_eq!(message.cid, 1);
+        assert_eq!(message.command, Command::Cbor);
+        assert_eq!(message.data, request[..]);
+
+        // Another channel is busy until the response is queued
+        bus.host_out(1, &packet(&[0, 0, 0, 2, 0x81, 0x00, 0x01], &[0xAA]));
+        assert!(interface.read_message().is_err());
+        interface.flush().unwrap();
+        assert_eq!(
+            bus.host_take_in(1).unwrap()[..8],
+            [0, 0, 0, 2, 0xBF, 0x00, 0x01, 0x06]
+        );
+
+        interface.respond(1, Command::Cbor, &request).unwrap();
+        assert!(matches!(interface.flush(), Err(UsbHidError::WouldBlock)));
+        let first = bus.host_take_in(1).unwrap();
+        assert_eq!(first[..7], [0, 0, 0, 1, 0x90, 0x00, 100]);
+        interface.flush().unwrap();
+        let second = bus.host_take_in(1).unwrap();
+        assert_eq!(second[..5], [0, 0, 0, 1, 0x00]);
+        assert_eq!(second[5..48], request[57..]);
+    }
+}
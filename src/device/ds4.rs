@@ -0,0 +1,391 @@
+//!HID gamepads with the report layout of the DualShock 4
+//!
+//! The input report follows the USB input report of the DualShock 4, including the motion sensor
+//! and touchpad fields, so host software that already reads DualShock 4 reports can read the
+//! device. Only the input report and the output report carrying rumble and light bar settings are
+//! described. The vendor defined feature reports, such as the motion sensor calibration, are not
+//! included, host drivers matching the device by vendor and product ID may request them.
+use crate::hid_class::descriptor::HidProtocol;
+use core::default::Default;
+use delegate::delegate;
+use packed_struct::prelude::*;
+use packed_struct::PackingResult;
+use usb_device::bus::{InterfaceNumber, StringIndex, UsbBus};
+use usb_device::class_prelude::{DescriptorWriter, EndpointAddress};
+
+use crate::hid_class::prelude::*;
+use crate::interface::duration::PollInterval;
+use crate::interface::latency::LatencyHistogram;
+use crate::interface::raw::{RawInterface, RawInterfaceConfig};
+use crate::interface::{
+    DefaultConfig, HidDescriptorBodyBytes, InterfaceClass, WrappedInterface, WrappedInterfaceConfig,
+};
+use crate::page::HatSwitch;
+use crate::UsbHidError;
+
+pub const DS4_INPUT_REPORT_ID: u8 = 0x01;
+pub const DS4_OUTPUT_REPORT_ID: u8 = 0x05;
+
+/// DualShock 4 compatible gamepad, report ID 1 input and report ID 5 output reports
+///
+/// Input report, following the report ID, see [`DS4GamepadReport`]:
+/// * Bytes 0-3 - Left stick X and Y, right stick X and Y, `0` to `255`, `128` when centred
+/// * Byte 4 - Hat switch, `0` (up) to `7` (up left) clockwise in 45° steps, `8` when centred,
+///   and buttons 1 to 4
+/// * Byte 5 and bits 0-1 of byte 6 - Buttons 5 to 14
+/// * Bits 2-7 of byte 6 - Report counter
+/// * Bytes 7-8 - Left and right triggers, `0` to `255`
+/// * Bytes 9-62 - Vendor defined, timestamp, motion sensors, battery and touchpad
+///
+/// Output report, following the report ID:
+/// * Bytes 0-30 - Vendor defined, rumble magnitudes and light bar colour
+#[rustfmt::skip]
+pub const DS4_REPORT_DESCRIPTOR: &[u8] = &[
+    0x05, 0x01,        // Usage Page (Generic Desktop),
+    0x09, 0x05,        // Usage (Game Pad),
+    0xA1, 0x01,        // Collection (Application),
+    0x85, 0x01,        //   Report ID (1),
+    0x09, 0x30,        //   Usage (X),
+    0x09, 0x31,        //   Usage (Y),
+    0x09, 0x32,        //   Usage (Z),
+    0x09, 0x35,        //   Usage (Rz),
+    0x15, 0x00,        //   Logical Minimum (0),
+    0x26, 0xFF, 0x00,  //   Logical Maximum (255),
+    0x75, 0x08,        //   Report Size (8),
+    0x95, 0x04,        //   Report Count (4),
+    0x81, 0x02,        //   Input (Data, Variable, Absolute),
+    0x09, 0x39,        //   Usage (Hat Switch),
+    0x15, 0x00,        //   Logical Minimum (0),
+    0x25, 0x07,        //   Logical Maximum (7),
+    0x35, 0x00,        //   Physical Minimum (0),
+    0x46, 0x3B, 0x01,  //   Physical Maximum (315),
+    0x65, 0x14,        //   Unit (Degrees),
+    0x75, 0x04,        //   Report Size (4),
+    0x95, 0x01,        //   Report Count (1),
+    0x81, 0x42,        //   Input (Data, Variable, Absolute, Null State),
+    0x65, 0x00,        //   Unit (None),
+    0x05, 0x09,        //   Usage Page (Buttons),
+    0x19, 0x01,        //   Usage Minimum (1),
+    0x29, 0x0E,        //   Usage Maximum (14),
+    0x15, 0x00,        //   Logical Minimum (0),
+    0x25, 0x01,        //   Logical Maximum (1),
+    0x75, 0x01,        //   Report Size (1),
+    0x95, 0x0E,        //   Report Count (14),
+    0x81, 0x02,        //   Input (Data, Variable, Absolute),
+    0x06, 0x00, 0xFF,  //   Usage Page (FF00h),
+    0x09, 0x20,        //   Usage (20h),
+    0x75, 0x06,        //   Report Size (6),
+    0x95, 0x01,        //   Report Count (1),
+    0x15, 0x00,        //   Logical Minimum (0),
+    0x25, 0x7F,        //   Logical Maximum (127),
+    0x81, 0x02,        //   Input (Data, Variable, Absolute),
+    0x05, 0x01,        //   Usage Page (Generic Desktop),
+    0x09, 0x33,        //   Usage (Rx),
+    0x09, 0x34,        //   Usage (Ry),
+    0x15, 0x00,        //   Logical Minimum (0),
+    0x26, 0xFF, 0x00,  //   Logical Maximum (255),
+    0x75, 0x08,        //   Report Size (8),
+    0x95, 0x02,        //   Report Count (2),
+    0x81, 0x02,        //   Input (Data, Variable, Absolute),
+    0x06, 0x00, 0xFF,  //   Usage Page (FF00h),
+    0x09, 0x21,        //   Usage (21h),
+    0x95, 0x36,        //   Report Count (54),
+    0x81, 0x02,        //   Input (Data, Variable, Absolute),
+    0x85, 0x05,        //   Report ID (5),
+    0x09, 0x22,        //   Usage (22h),
+    0x95, 0x1F,        //   Report Count (31),
+    0x91, 0x02,        //   Output (Data, Variable, Absolute),
+    0xC0,              // End Collection
+];
+
+/// Centre position of the sticks of a [`DS4GamepadReport`]
+pub const DS4_STICK_CENTRE: u8 = 0x80;
+
+/// Touchpad contact of a [`DS4GamepadReport`]
+///
+/// `x` is `0` to `1919` and `y` is `0` to `941`, from the top left corner of the touchpad.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Default)]
+pub struct DS4TouchPoint {
+    pub touching: bool,
+    /// Identifies the contact while it touches the touchpad, `0` to `127`
+    pub id: u8,
+    pub x: u16,
+    pub y: u16,
+}
+
+impl PackedStruct for DS4TouchPoint {
+    type ByteArray = [u8; 4];
+
+    fn pack(&self) -> PackingResult<[u8; 4]> {
+        if self.id > 0x7F || self.x > 0xFFF || self.y > 0xFFF {
+            return Err(PackingError::BitsError);
+        }
+        let not_touching = if self.touching { 0 } else { 0x80 };
+        Ok([
+            not_touching | self.id,
+            self.x as u8,
+            (self.x >> 8) as u8 | (self.y << 4) as u8,
+            (self.y >> 4) as u8,
+        ])
+    }
+
+    fn unpack(src: &[u8; 4]) -> PackingResult<Self> {
+        Ok(Self {
+            touching: src[0] & 0x80 == 0,
+            id: src[0] & 0x7F,
+            x: u16::from(src[1]) | u16::from(src[2] & 0x0F) << 8,
+            y: u16::from(src[2] >> 4) | u16::from(src[3]) << 4,
+        })
+    }
+}
+
+/// Input report of a DualShock 4 compatible gamepad, excluding the report ID
+///
+/// The default has centred sticks, no buttons pressed and no touchpad contacts. Motion sensor
+/// values are in the units of the DualShock 4, and hosts may expect calibration feature reports
+/// to interpret them.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, PackedStruct)]
+#[packed_struct(endian = "lsb", bit_numbering = "msb0", size_bytes = "63")]
+pub struct DS4GamepadReport {
+    #[packed_field(bytes = "0")]
+    pub left_x: u8,
+    #[packed_field(bytes = "1")]
+    pub left_y: u8,
+    #[packed_field(bytes = "2")]
+    pub right_x: u8,
+    #[packed_field(bytes = "3")]
+    pub right_y: u8,
+    #[packed_field(bits = "32")]
+    pub triangle: bool,
+    #[packed_field(bits = "33")]
+    pub circle: bool,
+    #[packed_field(bits = "34")]
+    pub cross: bool,
+    #[packed_field(bits = "35")]
+    pub square: bool,
+    #[packed_field(bits = "36:39", ty = "enum")]
+    pub hat: HatSwitch,
+    #[packed_field(bits = "40")]
+    pub r3: bool,
+    #[packed_field(bits = "41")]
+    pub l3: bool,
+    #[packed_field(bits = "42")]
+    pub options: bool,
+    #[packed_field(bits = "43")]
+    pub share: bool,
+    #[packed_field(bits = "44")]
+    pub r2: bool,
+    #[packed_field(bits = "45")]
+    pub l2: bool,
+    #[packed_field(bits = "46")]
+    pub r1: bool,
+    #[packed_field(bits = "47")]
+    pub l1: bool,
+    /// Incremented with every report, wrapping at 64
+    #[packed_field(bits = "48:53")]
+    pub counter: u8,
+    #[packed_field(bits = "54")]
+    pub touchpad_click: bool,
+    #[packed_field(bits = "55")]
+    pub ps: bool,
+    #[packed_field(bytes = "7")]
+    pub left_trigger: u8,
+    #[packed_field(bytes = "8")]
+    pub right_trigger: u8,
+    /// Time of the report, in units of 5.33µs
+    #[packed_field(bytes = "9:10")]
+    pub timestamp: u16,
+    #[packed_field(bytes = "11")]
+    pub temperature: u8,
+    #[packed_field(bytes = "12:17")]
+    pub gyro: [i16; 3],
+    #[packed_field(bytes = "18:23")]
+    pub accel: [i16; 3],
+    #[packed_field(bytes = "24:28")]
+    pub reserved: [u8; 5],
+    /// Battery level in bits 0-3, and bit 4 set while the cable is connected
+    #[packed_field(bytes = "29")]
+    pub battery: u8,
+    #[packed_field(bytes = "30:31")]
+    pub reserved2: [u8; 2],
+    /// Number of touchpad packets, `1` to report [`Self::touch_points`]
+    #[packed_field(bytes = "32")]
+    pub touch_packets: u8,
+    /// Timestamp of the touchpad packet
+    #[packed_field(bytes = "33")]
+    pub touch_timestamp: u8,
+    #[packed_field(bytes = "34:41")]
+    pub touch_points: [DS4TouchPoint; 2],
+    #[packed_field(bytes = "42:62")]
+    pub reserved3: [u8; 21],
+}
+
+impl Default for DS4GamepadReport {
+    fn default() -> Self {
+        Self {
+            left_x: DS4_STICK_CENTRE,
+            left_y: DS4_STICK_CENTRE,
+            right_x: DS4_STICK_CENTRE,
+            right_y: DS4_STICK_CENTRE,
+            triangle: false,
+            circle: false,
+            cross: false,
+            square: false,
+            hat: HatSwitch::Null,
+            r3: false,
+            l3: false,
+            options: false,
+            share: false,
+            r2: false,
+            l2: false,
+            r1: false,
+            l1: false,
+            counter: 0,
+            touchpad_click: false,
+            ps: false,
+            left_trigger: 0,
+            right_trigger: 0,
+            timestamp: 0,
+            temperature: 0,
+            gyro: [0; 3],
+            accel: [0; 3],
+            reserved: [0; 5],
+            battery: 0,
+            reserved2: [0; 2],
+            touch_packets: 1,
+            touch_timestamp: 0,
+            touch_points: [DS4TouchPoint::default(); 2],
+            reserved3: [0; 21],
+        }
+    }
+}
+
+pub struct DS4GamepadInterface<'a, B: UsbBus> {
+    inner: RawInterface<'a, B>,
+}
+
+/// DualShock 4 compatible gamepad interface, see [`DS4GamepadInterface`]
+pub type DS4Gamepad<'a, B> = DS4GamepadInterface<'a, B>;
+
+impl<'a, B: UsbBus> DS4GamepadInterface<'a, B> {
+    delegate! {
+        to self.inner {
+            pub fn protocol(&self) -> HidProtocol;
+            pub fn report_latency(&self) -> LatencyHistogram;
+            pub fn clear_report_latency(&self);
+            pub fn host_stalls(&self) -> u32;
+            pub fn host_stalled(&self) -> bool;
+            pub fn write_ready(&self) -> bool;
+            /// Reads an output report, including the [`DS4_OUTPUT_REPORT_ID`] report ID
+            pub fn read_report(&self, data: &mut [u8]) -> Result<usize, UsbHidError>;
+        }
+    }
+
+    /// Writes an input report, prefixed with [`DS4_INPUT_REPORT_ID`]
+    pub fn write_report(&self, report: &DS4GamepadReport) -> Result<(), UsbHidError> {
+        let data = report.pack().map_err(|e| {
+            error!("Error packing DS4GamepadReport: {:?}", e);
+            UsbHidError::SerializationError
+        })?;
+        let mut buffer = [0; 64];
+        buffer[0] = DS4_INPUT_REPORT_ID;
+        buffer[1..].copy_from_slice(&data);
+        self.inner.write_report(&buffer).map(|_| ())
+    }
+
+    /// Configuration with the endpoints of the DualShock 4
+    pub fn default_config() -> WrappedInterfaceConfig<Self, RawInterfaceConfig<'a>> {
+        WrappedInterfaceConfig::new(
+            RawInterfaceBuilder::new(DS4_REPORT_DESCRIPTOR)
+                .in_endpoint(UsbPacketSize::Bytes64, PollInterval::millis(5))
+                .with_out_endpoint(UsbPacketSize::Bytes64, PollInterval::millis(5))
+                .build()
+                .unwrap(),
+            (),
+        )
+    }
+}
+
+impl<'a, B: UsbBus + 'a> DefaultConfig<'a, B> for DS4GamepadInterface<'a, B> {
+    type Config = WrappedInterfaceConfig<Self, RawInterfaceConfig<'a>>;
+
+    fn default_config() -> Self::Config {
+        Self::default_config()
+    }
+}
+
+impl<'a, B: UsbBus> InterfaceClass<'a> for DS4GamepadInterface<'a, B> {
+    delegate! {
+        to self.inner{
+           fn report_descriptor(&self) -> &'_ [u8];
+           fn hid_descriptor_body(&self) -> HidDescriptorBodyBytes;
+           fn class_descriptor(&self, descriptor_type: u8, index: u8) -> Option<&'_ [u8]>;
+           fn take_wakeup_request(&self) -> bool;
+           fn id(&self) -> InterfaceNumber;
+           fn write_descriptors(&self, writer: &mut DescriptorWriter) -> usb_device::Result<()>;
+           fn get_string(&self, index: StringIndex, _lang_id: u16) -> Option<&'_ str>;
+           fn reset(&mut self);
+           fn set_report(&mut self, data: &[u8]) -> usb_device::Result<()>;
+           fn get_report(&mut self, data: &mut [u8]) -> usb_device::Result<usize>;
+           fn get_report_ack(&mut self) -> usb_device::Result<()>;
+           fn set_idle(&mut self, report_id: u8, value: u8);
+           fn get_idle(&self, report_id: u8) -> u8;
+           fn set_protocol(&mut self, protocol: HidProtocol) -> usb_device::Result<()>;
+           fn get_protocol(&self) -> HidProtocol;
+           fn out_endpoint_address(&self) -> Option<EndpointAddress>;
+           fn in_endpoint_address(&self) -> EndpointAddress;
+           fn endpoint_in_complete(&self, addr: EndpointAddress);
+           fn endpoint_out(&mut self);
+        }
+    }
+}
+
+impl<'a, B: UsbBus> WrappedInterface<'a, B, RawInterface<'a, B>> for DS4GamepadInterface<'a, B> {
+    fn new(interface: RawInterface<'a, B>, _: ()) -> Self {
+        Self { inner: interface }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use packed_struct::PackedStruct;
+
+    use crate::device::ds4::{DS4GamepadReport, DS4TouchPoint};
+    use crate::page::HatSwitch;
+
+    #[test]
+    fn pack_report() {
+        let data = DS4GamepadReport::default().pack().unwrap();
+        assert_eq!(
+            data[..9],
+            [0x80, 0x80, 0x80, 0x80, 0x08, 0x00, 0x00, 0x00, 0x00]
+        );
+        assert_eq!(data[32..42], [0x01, 0x00, 0x80, 0, 0, 0, 0x80, 0, 0, 0]);
+
+        let report = DS4GamepadReport {
+            cross: true,
+            hat: HatSwitch::E,
+            l1: true,
+            ps: true,
+            counter: 0x3F,
+            right_trigger: 0xFF,
+            timestamp: 0x1234,
+            gyro: [-1, 0, 1],
+            touch_points: [
+                DS4TouchPoint {
+                    touching: true,
+                    id: 5,
+                    x: 1919,
+                    y: 941,
+                },
+                DS4TouchPoint::default(),
+            ],
+            ..Default::default()
+        };
+        let data = report.pack().unwrap();
+        assert_eq!(data[4..11], [0x22, 0x01, 0xFD, 0x00, 0xFF, 0x34, 0x12]);
+        assert_eq!(data[12..18], [0xFF, 0xFF, 0x00, 0x00, 0x01, 0x00]);
+        assert_eq!(data[34..38], [0x05, 0x7F, 0xD7, 0x3A]);
+        assert_eq!(DS4GamepadReport::unpack(&data).unwrap(), report);
+    }
+}
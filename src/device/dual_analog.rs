@@ -0,0 +1,258 @@
+//!HID gamepads with the dual analog layout of common console controllers
+//!
+//! Buttons are numbered in the order of the W3C Gamepad API standard mapping, which SDL also
+//! follows for unknown HID gamepads, so controllers get a sensible default button mapping without
+//! per-OS quirks. The directional pad is the hat switch, and the triggers are analog axes as well
+//! as buttons.
+use crate::hid_class::descriptor::HidProtocol;
+use core::default::Default;
+use delegate::delegate;
+use packed_struct::prelude::*;
+use usb_device::bus::{InterfaceNumber, StringIndex, UsbBus};
+use usb_device::class_prelude::{DescriptorWriter, EndpointAddress};
+
+use crate::hid_class::prelude::*;
+use crate::interface::duration::PollInterval;
+use crate::interface::latency::LatencyHistogram;
+use crate::interface::raw::{RawInterface, RawInterfaceConfig};
+use crate::interface::{
+    DefaultConfig, HidDescriptorBodyBytes, InterfaceClass, WrappedInterface, WrappedInterfaceConfig,
+};
+use crate::page::HatSwitch;
+use crate::UsbHidError;
+
+/// Dual analog gamepad with 14 buttons, two sticks, two analog triggers and a hat switch
+///
+/// * Bits 0-13 - Buttons 1 to 14, see [`DualAnalogButton`]
+/// * Bytes 2-9 - Left stick X and Y, right stick X and Y as X, Y, Z and Rz, little endian
+///   `-32767` to `32767`
+/// * Bytes 10-11 - Left and right triggers as Rx and Ry, `0` to `255`
+/// * Byte 12 - Hat switch, `0` (up) to `7` (up left) clockwise in 45° steps, `8` when centred
+#[rustfmt::skip]
+pub const DUAL_ANALOG_GAMEPAD_REPORT_DESCRIPTOR: &[u8] = &[
+    0x05, 0x01,        // Usage Page (Generic Desktop),
+    0x09, 0x05,        // Usage (Game Pad),
+    0xA1, 0x01,        // Collection (Application),
+    0x05, 0x09,        //   Usage Page (Buttons),
+    0x19, 0x01,        //   Usage Minimum (1),
+    0x29, 0x0E,        //   Usage Maximum (14),
+    0x15, 0x00,        //   Logical Minimum (0),
+    0x25, 0x01,        //   Logical Maximum (1),
+    0x75, 0x01,        //   Report Size (1),
+    0x95, 0x0E,        //   Report Count (14),
+    0x81, 0x02,        //   Input (Data, Variable, Absolute),
+    0x95, 0x02,        //   Report Count (2),
+    0x81, 0x01,        //   Input (Constant),
+    0x05, 0x01,        //   Usage Page (Generic Desktop),
+    0x09, 0x30,        //   Usage (X),
+    0x09, 0x31,        //   Usage (Y),
+    0x09, 0x32,        //   Usage (Z),
+    0x09, 0x35,        //   Usage (Rz),
+    0x16, 0x01, 0x80,  //   Logical Minimum (-32767),
+    0x26, 0xFF, 0x7F,  //   Logical Maximum (32767),
+    0x75, 0x10,        //   Report Size (16),
+    0x95, 0x04,        //   Report Count (4),
+    0x81, 0x02,        //   Input (Data, Variable, Absolute),
+    0x09, 0x33,        //   Usage (Rx),
+    0x09, 0x34,        //   Usage (Ry),
+    0x15, 0x00,        //   Logical Minimum (0),
+    0x26, 0xFF, 0x00,  //   Logical Maximum (255),
+    0x75, 0x08,        //   Report Size (8),
+    0x95, 0x02,        //   Report Count (2),
+    0x81, 0x02,        //   Input (Data, Variable, Absolute),
+    0x09, 0x39,        //   Usage (Hat Switch),
+    0x15, 0x00,        //   Logical Minimum (0),
+    0x25, 0x07,        //   Logical Maximum (7),
+    0x35, 0x00,        //   Physical Minimum (0),
+    0x46, 0x3B, 0x01,  //   Physical Maximum (315),
+    0x65, 0x14,        //   Unit (Degrees),
+    0x75, 0x04,        //   Report Size (4),
+    0x95, 0x01,        //   Report Count (1),
+    0x81, 0x42,        //   Input (Data, Variable, Absolute, Null State),
+    0x65, 0x00,        //   Unit (None),
+    0x81, 0x01,        //   Input (Constant),
+    0xC0,              // End Collection
+];
+
+/// Buttons of a [`DualAnalogGamepadReport`], named by position, in the order of the W3C Gamepad
+/// API standard mapping
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[repr(u8)]
+pub enum DualAnalogButton {
+    /// A on Xbox controllers, Cross on PlayStation controllers
+    South = 0,
+    East = 1,
+    West = 2,
+    North = 3,
+    LeftShoulder = 4,
+    RightShoulder = 5,
+    /// Pressed state of the left trigger, also reported as the [`DualAnalogGamepadReport::left_trigger`] axis
+    LeftTrigger = 6,
+    /// Pressed state of the right trigger, also reported as the [`DualAnalogGamepadReport::right_trigger`] axis
+    RightTrigger = 7,
+    Select = 8,
+    Start = 9,
+    LeftStick = 10,
+    RightStick = 11,
+    Home = 12,
+    /// Additional button, such as share or capture
+    Misc = 13,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Default, PackedStruct)]
+#[packed_struct(endian = "lsb", size_bytes = "13")]
+pub struct DualAnalogGamepadReport {
+    #[packed_field]
+    pub buttons: u16,
+    #[packed_field]
+    pub left_x: i16,
+    #[packed_field]
+    pub left_y: i16,
+    #[packed_field]
+    pub right_x: i16,
+    #[packed_field]
+    pub right_y: i16,
+    #[packed_field]
+    pub left_trigger: u8,
+    #[packed_field]
+    pub right_trigger: u8,
+    #[packed_field(ty = "enum", element_size_bytes = "1")]
+    pub hat: HatSwitch,
+}
+
+impl DualAnalogGamepadReport {
+    pub fn set_button(&mut self, button: DualAnalogButton, pressed: bool) {
+        let mask = 1 << button as u8;
+        if pressed {
+            self.buttons |= mask;
+        } else {
+            self.buttons &= !mask;
+        }
+    }
+
+    pub fn button(&self, button: DualAnalogButton) -> bool {
+        self.buttons & (1 << button as u8) != 0
+    }
+}
+
+pub struct DualAnalogGamepadInterface<'a, B: UsbBus> {
+    inner: RawInterface<'a, B>,
+}
+
+/// Dual analog gamepad interface, see [`DualAnalogGamepadInterface`]
+pub type DualAnalogGamepad<'a, B> = DualAnalogGamepadInterface<'a, B>;
+
+impl<'a, B: UsbBus> DualAnalogGamepadInterface<'a, B> {
+    delegate! {
+        to self.inner {
+            pub fn protocol(&self) -> HidProtocol;
+            pub fn report_latency(&self) -> LatencyHistogram;
+            pub fn clear_report_latency(&self);
+            pub fn host_stalls(&self) -> u32;
+            pub fn host_stalled(&self) -> bool;
+            pub fn write_ready(&self) -> bool;
+        }
+    }
+
+    pub fn write_report(&self, report: &DualAnalogGamepadReport) -> Result<(), UsbHidError> {
+        let data = report.pack().map_err(|e| {
+            error!("Error packing DualAnalogGamepadReport: {:?}", e);
+            UsbHidError::SerializationError
+        })?;
+        self.inner.write_report(&data).map(|_| ())
+    }
+
+    pub fn default_config() -> WrappedInterfaceConfig<Self, RawInterfaceConfig<'a>> {
+        Self::default_config_with_descriptor(DUAL_ANALOG_GAMEPAD_REPORT_DESCRIPTOR)
+    }
+
+    /// Default configuration with an alternative report descriptor
+    ///
+    /// The descriptor must describe reports with the same layout as [`DualAnalogGamepadReport`]
+    pub fn default_config_with_descriptor(
+        report_descriptor: &'a [u8],
+    ) -> WrappedInterfaceConfig<Self, RawInterfaceConfig<'a>> {
+        WrappedInterfaceConfig::new(
+            RawInterfaceBuilder::new(report_descriptor)
+                .description("Gamepad")
+                .in_endpoint(UsbPacketSize::Bytes16, PollInterval::millis(4))
+                .without_out_endpoint()
+                .build()
+                .unwrap(),
+            (),
+        )
+    }
+}
+
+impl<'a, B: UsbBus + 'a> DefaultConfig<'a, B> for DualAnalogGamepadInterface<'a, B> {
+    type Config = WrappedInterfaceConfig<Self, RawInterfaceConfig<'a>>;
+
+    fn default_config() -> Self::Config {
+        Self::default_config()
+    }
+}
+
+impl<'a, B: UsbBus> InterfaceClass<'a> for DualAnalogGamepadInterface<'a, B> {
+    delegate! {
+        to self.inner{
+           fn report_descriptor(&self) -> &'_ [u8];
+           fn hid_descriptor_body(&self) -> HidDescriptorBodyBytes;
+           fn class_descriptor(&self, descriptor_type: u8, index: u8) -> Option<&'_ [u8]>;
+           fn take_wakeup_request(&self) -> bool;
+           fn id(&self) -> InterfaceNumber;
+           fn write_descriptors(&self, writer: &mut DescriptorWriter) -> usb_device::Result<()>;
+           fn get_string(&self, index: StringIndex, _lang_id: u16) -> Option<&'_ str>;
+           fn reset(&mut self);
+           fn set_report(&mut self, data: &[u8]) -> usb_device::Result<()>;
+           fn get_report(&mut self, data: &mut [u8]) -> usb_device::Result<usize>;
+           fn get_report_ack(&mut self) -> usb_device::Result<()>;
+           fn set_idle(&mut self, report_id: u8, value: u8);
+           fn get_idle(&self, report_id: u8) -> u8;
+           fn set_protocol(&mut self, protocol: HidProtocol) -> usb_device::Result<()>;
+           fn get_protocol(&self) -> HidProtocol;
+           fn out_endpoint_address(&self) -> Option<EndpointAddress>;
+           fn in_endpoint_address(&self) -> EndpointAddress;
+           fn endpoint_in_complete(&self, addr: EndpointAddress);
+           fn endpoint_out(&mut self);
+        }
+    }
+}
+
+impl<'a, B: UsbBus> WrappedInterface<'a, B, RawInterface<'a, B>>
+    for DualAnalogGamepadInterface<'a, B>
+{
+    fn new(interface: RawInterface<'a, B>, _: ()) -> Self {
+        Self { inner: interface }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use packed_struct::PackedStruct;
+
+    use crate::device::dual_analog::{DualAnalogButton, DualAnalogGamepadReport};
+    use crate::page::HatSwitch;
+
+    #[test]
+    fn pack_report() {
+        let mut report = DualAnalogGamepadReport {
+            left_x: -32767,
+            right_y: 0x1234,
+            left_trigger: 0xFF,
+            hat: HatSwitch::E,
+            ..Default::default()
+        };
+        report.set_button(DualAnalogButton::South, true);
+        report.set_button(DualAnalogButton::Misc, true);
+        report.set_button(DualAnalogButton::Start, true);
+        report.set_button(DualAnalogButton::Start, false);
+        assert!(report.button(DualAnalogButton::Misc));
+        assert!(!report.button(DualAnalogButton::Start));
+
+        assert_eq!(
+            report.pack().unwrap(),
+            [0x01, 0x20, 0x01, 0x80, 0x00, 0x00, 0x00, 0x00, 0x34, 0x12, 0xFF, 0x00, 0x02]
+        );
+        assert_eq!(DualAnalogGamepadReport::default().pack().unwrap()[12], 0x08);
+    }
+}
@@ -0,0 +1,375 @@
+//!Environmental temperature and humidity sensor
+//!
+//! A sensor collection of two sensors on the HID Sensors usage page, each with its own report ID
+//! for its [`SensorProperties`] feature report and its input report. Hosts with native HID sensor
+//! support, such as the Windows Sensor API, expose them as separate temperature and humidity
+//! sensors.
+use core::cell::Cell;
+use core::default::Default;
+
+use delegate::delegate;
+use packed_struct::prelude::*;
+use usb_device::bus::{InterfaceNumber, StringIndex, UsbBus};
+use usb_device::class_prelude::{DescriptorWriter, EndpointAddress};
+use usb_device::UsbError;
+
+use crate::device::sensor::{
+    data_field, sensor_collection, usage, Field, SensorEvent, SensorProperties, SensorState,
+    END_COLLECTION, SENSOR_PROPERTIES_DESCRIPTOR, SENSOR_STATE_EVENT_DESCRIPTOR,
+};
+use crate::hid_class::descriptor::{concat_descriptor, descriptor_len, HidProtocol, ReportType};
+use crate::hid_class::prelude::*;
+use crate::interface::duration::PollInterval;
+use crate::interface::feature::{pack_with_id, FeatureResponder};
+use crate::interface::latency::LatencyHistogram;
+use crate::interface::raw::{RawInterface, RawInterfaceConfig};
+use crate::interface::{
+    DefaultConfig, HidDescriptorBodyBytes, InterfaceClass, WrappedInterface, WrappedInterfaceConfig,
+};
+use crate::UsbHidError;
+
+pub const TEMPERATURE_REPORT_ID: u8 = 0x01;
+pub const HUMIDITY_REPORT_ID: u8 = 0x02;
+
+const SENSOR_COLLECTION: &[u8] = &[0x05, 0x20, 0x09, 0x01, 0xA1, 0x01];
+
+const ENVIRONMENTAL_FRAGMENTS: &[&[u8]] = &[
+    // Usage Page (Sensors), Usage (Sensor Collection), Collection (Application)
+    SENSOR_COLLECTION,
+    &[0x85, TEMPERATURE_REPORT_ID],
+    &sensor_collection(usage::ENVIRONMENTAL_TEMPERATURE),
+    SENSOR_PROPERTIES_DESCRIPTOR,
+    SENSOR_STATE_EVENT_DESCRIPTOR,
+    &data_field(usage::TEMPERATURE, Field::I16, -2),
+    END_COLLECTION,
+    &[0x85, HUMIDITY_REPORT_ID],
+    &sensor_collection(usage::ENVIRONMENTAL_HUMIDITY),
+    SENSOR_PROPERTIES_DESCRIPTOR,
+    SENSOR_STATE_EVENT_DESCRIPTOR,
+    &data_field(usage::RELATIVE_HUMIDITY, Field::U16, -2),
+    END_COLLECTION,
+    END_COLLECTION,
+];
+const ENVIRONMENTAL_DESCRIPTOR: [u8; descriptor_len(ENVIRONMENTAL_FRAGMENTS)] =
+    concat_descriptor(ENVIRONMENTAL_FRAGMENTS);
+
+/// Temperature sensor with report ID [`TEMPERATURE_REPORT_ID`] and humidity sensor with report ID
+/// [`HUMIDITY_REPORT_ID`], see [`TemperatureReport`] and [`HumidityReport`]
+pub const ENVIRONMENTAL_SENSOR_REPORT_DESCRIPTOR: &[u8] = &ENVIRONMENTAL_DESCRIPTOR;
+
+/// A sensor of [`EnvironmentalSensorInterface`]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum EnvironmentalSensor {
+    Temperature,
+    Humidity,
+}
+
+impl EnvironmentalSensor {
+    pub const fn report_id(self) -> u8 {
+        match self {
+            EnvironmentalSensor::Temperature => TEMPERATURE_REPORT_ID,
+            EnvironmentalSensor::Humidity => HUMIDITY_REPORT_ID,
+        }
+    }
+
+    pub const fn from_report_id(report_id: u8) -> Option<Self> {
+        match report_id {
+            TEMPERATURE_REPORT_ID => Some(EnvironmentalSensor::Temperature),
+            HUMIDITY_REPORT_ID => Some(EnvironmentalSensor::Humidity),
+            _ => None,
+        }
+    }
+
+    const fn index(self) -> usize {
+        match self {
+            EnvironmentalSensor::Temperature => 0,
+            EnvironmentalSensor::Humidity => 1,
+        }
+    }
+}
+
+/// Temperature in hundredths of a degree Celsius
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Default, PackedStruct)]
+#[packed_struct(endian = "lsb", size_bytes = "4")]
+pub struct TemperatureReport {
+    #[packed_field(ty = "enum", element_size_bytes = "1")]
+    pub state: SensorState,
+    #[packed_field(ty = "enum", element_size_bytes = "1")]
+    pub event: SensorEvent,
+    #[packed_field]
+    pub temperature: i16,
+}
+
+/// Relative humidity in hundredths of a percent
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Default, PackedStruct)]
+#[packed_struct(endian = "lsb", size_bytes = "4")]
+pub struct HumidityReport {
+    #[packed_field(ty = "enum", element_size_bytes = "1")]
+    pub state: SensorState,
+    #[packed_field(ty = "enum", element_size_bytes = "1")]
+    pub event: SensorEvent,
+    #[packed_field]
+    pub humidity: u16,
+}
+
+/// Configuration of [`EnvironmentalSensorInterface`]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct EnvironmentalSensorConfig {
+    /// The same report descriptor as the inner [`RawInterfaceConfig`]
+    pub report_descriptor: &'static [u8],
+    /// Properties of both sensors until the host sets them
+    pub properties: SensorProperties,
+}
+
+/// Temperature and humidity sensor
+pub struct EnvironmentalSensorInterface<'a, B: UsbBus> {
+    inner: RawInterface<'a, B>,
+    config: EnvironmentalSensorConfig,
+    properties: Cell<[SensorProperties; 2]>,
+    feature_responder: FeatureResponder,
+}
+
+impl<'a, B: UsbBus> EnvironmentalSensorInterface<'a, B> {
+    delegate! {
+        to self.inner {
+            pub fn protocol(&self) -> HidProtocol;
+            pub fn report_latency(&self) -> LatencyHistogram;
+            pub fn clear_report_latency(&self);
+            pub fn host_stalls(&self) -> u32;
+            pub fn host_stalled(&self) -> bool;
+            pub fn write_ready(&self) -> bool;
+        }
+    }
+
+    /// Properties of `sensor` last set by the host
+    pub fn properties(&self, sensor: EnvironmentalSensor) -> SensorProperties {
+        self.properties.get()[sensor.index()]
+    }
+
+    /// Sets the sensor state of `sensor` reported in its feature report
+    pub fn set_sensor_state(&self, sensor: EnvironmentalSensor, state: SensorState) {
+        let mut properties = self.properties.get();
+        properties[sensor.index()].sensor_state = state;
+        self.properties.set(properties);
+    }
+
+    pub fn write_temperature(&self, report: &TemperatureReport) -> Result<(), UsbHidError> {
+        let data = report.pack().map_err(|e| {
+            error!("Error packing TemperatureReport: {:?}", e);
+            UsbHidError::SerializationError
+        })?;
+        self.write_report_with_id(TEMPERATURE_REPORT_ID, &data)
+    }
+
+    pub fn write_humidity(&self, report: &HumidityReport) -> Result<(), UsbHidError> {
+        let data = report.pack().map_err(|e| {
+            error!("Error packing HumidityReport: {:?}", e);
+            UsbHidError::SerializationError
+        })?;
+        self.write_report_with_id(HUMIDITY_REPORT_ID, &data)
+    }
+
+    fn write_report_with_id(&self, report_id: u8, data: &[u8; 4]) -> Result<(), UsbHidError> {
+        let mut buffer = [0; 5];
+        buffer[0] = report_id;
+        buffer[1..].copy_from_slice(data);
+        self.inner.write_report(&buffer).map(|_| ())
+    }
+
+    pub fn default_config(
+    ) -> WrappedInterfaceConfig<Self, RawInterfaceConfig<'a>, EnvironmentalSensorConfig> {
+        WrappedInterfaceConfig::new(
+            RawInterfaceBuilder::new(ENVIRONMENTAL_SENSOR_REPORT_DESCRIPTOR)
+                .description("Environmental Sensor")
+                .in_endpoint(UsbPacketSize::Bytes8, PollInterval::millis(10))
+                .without_out_endpoint()
+                .build()
+                .unwrap(),
+            EnvironmentalSensorConfig {
+                report_descriptor: ENVIRONMENTAL_SENSOR_REPORT_DESCRIPTOR,
+                properties: SensorProperties::default(),
+            },
+        )
+    }
+}
+
+impl<'a, B: UsbBus + 'a> DefaultConfig<'a, B> for EnvironmentalSensorInterface<'a, B> {
+    type Config = WrappedInterfaceConfig<Self, RawInterfaceConfig<'a>, EnvironmentalSensorConfig>;
+
+    fn default_config() -> Self::Config {
+        Self::default_config()
+    }
+}
+
+impl<'a, B: UsbBus> InterfaceClass<'a> for EnvironmentalSensorInterface<'a, B> {
+    delegate! {
+        to self.inner{
+           fn report_descriptor(&self) -> &'_ [u8];
+           fn hid_descriptor_body(&self) -> HidDescriptorBodyBytes;
+           fn class_descriptor(&self, descriptor_type: u8, index: u8) -> Option<&'_ [u8]>;
+           fn take_wakeup_request(&self) -> bool;
+           fn id(&self) -> InterfaceNumber;
+           fn write_descriptors(&self, writer: &mut DescriptorWriter) -> usb_device::Result<()>;
+           fn get_string(&self, index: StringIndex, _lang_id: u16) -> Option<&'_ str>;
+           fn get_report(&mut self, data: &mut [u8]) -> usb_device::Result<usize>;
+           fn set_idle(&mut self, report_id: u8, value: u8);
+           fn get_idle(&self, report_id: u8) -> u8;
+           fn set_protocol(&mut self, protocol: HidProtocol) -> usb_device::Result<()>;
+           fn get_protocol(&self) -> HidProtocol;
+           fn out_endpoint_address(&self) -> Option<EndpointAddress>;
+           fn in_endpoint_address(&self) -> EndpointAddress;
+           fn endpoint_in_complete(&self, addr: EndpointAddress);
+           fn endpoint_out(&mut self);
+        }
+    }
+
+    fn static_report_descriptor(&self) -> Option<&'static [u8]> {
+        Some(self.config.report_descriptor)
+    }
+
+    fn reset(&mut self) {
+        self.inner.reset();
+        self.properties.set([self.config.properties; 2]);
+        self.feature_responder.reset();
+    }
+
+    /// Sets the [`SensorProperties`] feature report of the sensor with the leading report ID
+    fn set_report(&mut self, data: &[u8]) -> usb_device::Result<()> {
+        let (report_id, data) = data.split_first().ok_or(UsbError::ParseError)?;
+        let sensor = EnvironmentalSensor::from_report_id(*report_id).ok_or_else(|| {
+            warn!("Unsupported SetReport ID: {:X}", report_id);
+            UsbError::ParseError
+        })?;
+        let mut properties = SensorProperties::unpack_from_slice(data).map_err(|e| {
+            error!("Error unpacking SensorProperties: {:?}", e);
+            UsbError::ParseError
+        })?;
+
+        let mut all = self.properties.get();
+        properties.sensor_state = all[sensor.index()].sensor_state;
+        all[sensor.index()] = properties;
+        self.properties.set(all);
+        Ok(())
+    }
+
+    fn get_report_by_id(
+        &mut self,
+        report_type: ReportType,
+        report_id: u8,
+        data: &mut [u8],
+    ) -> usb_device::Result<usize> {
+        let properties = self.properties.get();
+        self.feature_responder
+            .get_report(&mut self.inner, report_type, report_id, data, |data| {
+                let sensor =
+                    EnvironmentalSensor::from_report_id(report_id).ok_or(UsbError::Unsupported)?;
+                pack_with_id(report_id, &properties[sensor.index()], data)
+            })
+    }
+
+    fn get_report_ack(&mut self) -> usb_device::Result<()> {
+        self.feature_responder.get_report_ack(&mut self.inner)
+    }
+}
+
+impl<'a, B: UsbBus> WrappedInterface<'a, B, RawInterface<'a, B>, EnvironmentalSensorConfig>
+    for EnvironmentalSensorInterface<'a, B>
+{
+    fn new(interface: RawInterface<'a, B>, config: EnvironmentalSensorConfig) -> Self {
+        Self {
+            inner: interface,
+            config,
+            properties: Cell::new([config.properties; 2]),
+            feature_responder: FeatureResponder::default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::device::environmental::*;
+
+    #[test]
+    fn descriptor_has_a_collection_per_sensor() {
+        let descriptor = ENVIRONMENTAL_SENSOR_REPORT_DESCRIPTOR;
+        assert_eq!(
+            descriptor[..8],
+            [0x05, 0x20, 0x09, 0x01, 0xA1, 0x01, 0x85, 0x01]
+        );
+        assert_eq!(descriptor[descriptor.len() - 2..], [0xC0, 0xC0]);
+        assert_eq!(
+            descriptor
+                .windows(2)
+                .filter(|w| w == &[0x85, HUMIDITY_REPORT_ID])
+                .count(),
+            1
+        );
+    }
+
+    #[test]
+    fn sensor_report_ids() {
+        for sensor in [
+            EnvironmentalSensor::Temperature,
+            EnvironmentalSensor::Humidity,
+        ] {
+            assert_eq!(
+                EnvironmentalSensor::from_report_id(sensor.report_id()),
+                Some(sensor)
+            );
+        }
+        assert_eq!(EnvironmentalSensor::from_report_id(0), None);
+    }
+
+    #[cfg(feature = "simulation")]
+    #[test]
+    fn sensors_have_separate_properties() {
+        use usb_device::bus::UsbBusAllocator;
+
+        use crate::device::sensor::ReportingState;
+        use crate::simulation::{control_transfer, device_builder, Response, SimBus};
+
+        let usb_alloc = UsbBusAllocator::new(SimBus::new());
+        let mut sensor = UsbHidClassBuilder::new()
+            .add_interface(EnvironmentalSensorInterface::default_config())
+            .build(&usb_alloc)
+            .unwrap();
+        let mut usb_dev = device_builder(&usb_alloc, 64).build();
+
+        // SET_REPORT (Feature) humidity, no events, 5s interval
+        assert_eq!(
+            control_transfer(
+                &mut usb_dev,
+                &mut [&mut sensor],
+                [0x21, 0x09, 0x02, 0x03, 0x00, 0x00, 0x08, 0x00],
+                &[0x02, 0x00, 0x01, 0x01, 0x88, 0x13, 0x00, 0x00]
+            ),
+            Response::Ack
+        );
+        // GET_REPORT (Feature) temperature is unchanged
+        assert_eq!(
+            control_transfer(
+                &mut usb_dev,
+                &mut [&mut sensor],
+                [0xA1, 0x01, 0x01, 0x03, 0x00, 0x00, 0x08, 0x00],
+                &[]
+            ),
+            Response::Data(vec![0x01, 0x01, 0x01, 0x01, 0x64, 0x00, 0x00, 0x00])
+        );
+
+        let interface = sensor.interface::<EnvironmentalSensorInterface<'_, _>, _>();
+        let humidity = interface.properties(EnvironmentalSensor::Humidity);
+        assert_eq!(humidity.reporting_state, ReportingState::NoEvents);
+        assert_eq!(humidity.report_interval, 5000);
+
+        interface
+            .write_temperature(&TemperatureReport {
+                temperature: -550,
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(
+            usb_dev.bus().host_take_in(1).unwrap(),
+            [0x01, 0x01, 0x03, 0xDA, 0xFD]
+        );
+    }
+}
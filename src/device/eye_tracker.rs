@@ -0,0 +1,616 @@
+//!HID eye trackers on the Eye and Head Trackers usage page
+//!
+//! Windows supports eye trackers on this usage page without a vendor driver, exposing the gaze
+//! point to applications through the Windows eye control and gaze interaction APIs. The host reads
+//! the [`EyeTrackerCapabilities`] and [`EyeTrackerStatus`] feature reports, sets the
+//! [`EyeTrackerConfiguration`] of the screen the tracker is mounted on and requests tracking through
+//! the device mode feature report. Positions and distances are in micrometres.
+use core::default::Default;
+
+use delegate::delegate;
+use packed_struct::prelude::*;
+use usb_device::bus::{InterfaceNumber, StringIndex, UsbBus};
+use usb_device::class_prelude::{DescriptorWriter, EndpointAddress};
+
+use crate::hid_class::descriptor::{HidProtocol, ReportType};
+use crate::hid_class::prelude::*;
+use crate::interface::duration::PollInterval;
+use crate::interface::feature::FeatureReports;
+use crate::interface::latency::LatencyHistogram;
+use crate::interface::raw::RawInterfaceConfig;
+use crate::interface::report_id::{NumberedReport, ReportIdInterface};
+use crate::interface::{
+    DefaultConfig, HidDescriptorBodyBytes, InterfaceClass, WrappedInterface, WrappedInterfaceConfig,
+};
+use crate::UsbHidError;
+
+pub const GAZE_REPORT_ID: u8 = 0x01;
+pub const CAPABILITIES_REPORT_ID: u8 = 0x02;
+pub const CONFIGURATION_REPORT_ID: u8 = 0x03;
+pub const STATUS_REPORT_ID: u8 = 0x04;
+pub const CONTROL_REPORT_ID: u8 = 0x05;
+
+/// Eye tracker with a tracking data input report and capabilities, configuration, status and
+/// control feature reports
+///
+/// * Report ID [`GAZE_REPORT_ID`] - Input, see [`GazeReport`]
+/// * Report ID [`CAPABILITIES_REPORT_ID`] - Feature, see [`EyeTrackerCapabilities`]
+/// * Report ID [`CONFIGURATION_REPORT_ID`] - Feature, see [`EyeTrackerConfiguration`]
+/// * Report ID [`STATUS_REPORT_ID`] - Feature, see [`EyeTrackerStatus`]
+/// * Report ID [`CONTROL_REPORT_ID`] - Feature, byte 0 - Device mode request
+#[rustfmt::skip]
+pub const EYE_TRACKER_REPORT_DESCRIPTOR: &[u8] = &[
+    0x05, 0x12,        // Usage Page (Eye and Head Trackers),
+    0x09, 0x01,        // Usage (Eye Tracker),
+    0xA1, 0x01,        // Collection (Application),
+    0x85, 0x01,        //   Report ID (1),
+    0x09, 0x10,        //   Usage (Tracking Data),
+    0xA1, 0x02,        //   Collection (Logical),
+    0x09, 0x20,        //     Usage (Sensor Timestamp),
+    0x15, 0x00,        //     Logical Minimum (0),
+    0x26, 0xFF, 0x00,  //     Logical Maximum (255),
+    0x75, 0x08,        //     Report Size (8),
+    0x95, 0x08,        //     Report Count (8),
+    0x82, 0x02, 0x01,  //     Input (Data, Variable, Absolute, Buffered Bytes),
+    0x17, 0x01, 0x00, 0x00, 0x80, // Logical Minimum (-2147483647),
+    0x27, 0xFF, 0xFF, 0xFF, 0x7F, // Logical Maximum (2147483647),
+    0x65, 0x11,        //     Unit (Centimeter),
+    0x55, 0x0C,        //     Unit Exponent (-4),
+    0x75, 0x20,        //     Report Size (32),
+    0x09, 0x24,        //     Usage (Gaze Point),
+    0xA1, 0x00,        //     Collection (Physical),
+    0x09, 0x21,        //       Usage (Position X),
+    0x09, 0x22,        //       Usage (Position Y),
+    0x95, 0x02,        //       Report Count (2),
+    0x81, 0x02,        //       Input (Data, Variable, Absolute),
+    0xC0,              //     End Collection
+    0x09, 0x25,        //     Usage (Left Eye Position),
+    0xA1, 0x00,        //     Collection (Physical),
+    0x09, 0x21,        //       Usage (Position X),
+    0x09, 0x22,        //       Usage (Position Y),
+    0x09, 0x23,        //       Usage (Position Z),
+    0x95, 0x03,        //       Report Count (3),
+    0x81, 0x02,        //       Input (Data, Variable, Absolute),
+    0xC0,              //     End Collection
+    0x09, 0x26,        //     Usage (Right Eye Position),
+    0xA1, 0x00,        //     Collection (Physical),
+    0x09, 0x21,        //       Usage (Position X),
+    0x09, 0x22,        //       Usage (Position Y),
+    0x09, 0x23,        //       Usage (Position Z),
+    0x81, 0x02,        //       Input (Data, Variable, Absolute),
+    0xC0,              //     End Collection
+    0xC0,              //   End Collection
+    0x85, 0x02,        //   Report ID (2),
+    0x09, 0x11,        //   Usage (Capabilities),
+    0xA1, 0x02,        //   Collection (Logical),
+    0x65, 0x00,        //     Unit (None),
+    0x55, 0x00,        //     Unit Exponent (0),
+    0x0A, 0x00, 0x01,  //     Usage (Tracker Quality),
+    0x15, 0x00,        //     Logical Minimum (0),
+    0x26, 0xFF, 0x00,  //     Logical Maximum (255),
+    0x75, 0x08,        //     Report Size (8),
+    0x95, 0x01,        //     Report Count (1),
+    0xB1, 0x02,        //     Feature (Data, Variable, Absolute),
+    0x0A, 0x01, 0x01,  //     Usage (Minimum Tracking Distance),
+    0x0A, 0x02, 0x01,  //     Usage (Optimum Tracking Distance),
+    0x0A, 0x03, 0x01,  //     Usage (Maximum Tracking Distance),
+    0x0A, 0x04, 0x01,  //     Usage (Maximum Screen Plane Width),
+    0x0A, 0x05, 0x01,  //     Usage (Maximum Screen Plane Height),
+    0x27, 0xFF, 0xFF, 0xFF, 0x7F, // Logical Maximum (2147483647),
+    0x65, 0x11,        //     Unit (Centimeter),
+    0x55, 0x0C,        //     Unit Exponent (-4),
+    0x75, 0x20,        //     Report Size (32),
+    0x95, 0x05,        //     Report Count (5),
+    0xB1, 0x02,        //     Feature (Data, Variable, Absolute),
+    0xC0,              //   End Collection
+    0x85, 0x03,        //   Report ID (3),
+    0x09, 0x12,        //   Usage (Configuration),
+    0xA1, 0x02,        //   Collection (Logical),
+    0x65, 0x00,        //     Unit (None),
+    0x55, 0x00,        //     Unit Exponent (0),
+    0x0A, 0x00, 0x02,  //     Usage (Display Manufacturer ID),
+    0x0A, 0x01, 0x02,  //     Usage (Display Product ID),
+    0x27, 0xFF, 0xFF, 0x00, 0x00, // Logical Maximum (65535),
+    0x75, 0x10,        //     Report Size (16),
+    0x95, 0x02,        //     Report Count (2),
+    0xB1, 0x02,        //     Feature (Data, Variable, Absolute),
+    0x0A, 0x02, 0x02,  //     Usage (Display Serial Number),
+    0x27, 0xFF, 0xFF, 0xFF, 0x7F, // Logical Maximum (2147483647),
+    0x75, 0x20,        //     Report Size (32),
+    0x95, 0x01,        //     Report Count (1),
+    0xB1, 0x02,        //     Feature (Data, Variable, Absolute),
+    0x0A, 0x03, 0x02,  //     Usage (Display Manufacturer Date),
+    0x27, 0xFF, 0xFF, 0x00, 0x00, // Logical Maximum (65535),
+    0x75, 0x10,        //     Report Size (16),
+    0xB1, 0x02,        //     Feature (Data, Variable, Absolute),
+    0x0A, 0x04, 0x02,  //     Usage (Calibrated Screen Width),
+    0x0A, 0x05, 0x02,  //     Usage (Calibrated Screen Height),
+    0x27, 0xFF, 0xFF, 0xFF, 0x7F, // Logical Maximum (2147483647),
+    0x65, 0x11,        //     Unit (Centimeter),
+    0x55, 0x0C,        //     Unit Exponent (-4),
+    0x75, 0x20,        //     Report Size (32),
+    0x95, 0x02,        //     Report Count (2),
+    0xB1, 0x02,        //     Feature (Data, Variable, Absolute),
+    0xC0,              //   End Collection
+    0x85, 0x04,        //   Report ID (4),
+    0x09, 0x13,        //   Usage (Status),
+    0xA1, 0x02,        //   Collection (Logical),
+    0x65, 0x00,        //     Unit (None),
+    0x55, 0x00,        //     Unit Exponent (0),
+    0x0A, 0x01, 0x03,  //     Usage (Configuration Status),
+    0x26, 0xFF, 0x00,  //     Logical Maximum (255),
+    0x75, 0x08,        //     Report Size (8),
+    0x95, 0x01,        //     Report Count (1),
+    0xB1, 0x02,        //     Feature (Data, Variable, Absolute),
+    0x0A, 0x00, 0x03,  //     Usage (Sampling Frequency),
+    0x27, 0xFF, 0xFF, 0x00, 0x00, // Logical Maximum (65535),
+    0x66, 0x01, 0xF0,  //     Unit (Hertz),
+    0x75, 0x10,        //     Report Size (16),
+    0xB1, 0x02,        //     Feature (Data, Variable, Absolute),
+    0x65, 0x00,        //     Unit (None),
+    0xC0,              //   End Collection
+    0x85, 0x05,        //   Report ID (5),
+    0x09, 0x14,        //   Usage (Control),
+    0xA1, 0x02,        //   Collection (Logical),
+    0x0A, 0x00, 0x04,  //     Usage (Device Mode Request),
+    0x26, 0xFF, 0x00,  //     Logical Maximum (255),
+    0x75, 0x08,        //     Report Size (8),
+    0xB1, 0x02,        //     Feature (Data, Variable, Absolute),
+    0xC0,              //   End Collection
+    0xC0,              // End Collection
+];
+
+/// Gaze point on the screen and eye positions, in micrometres
+///
+/// The gaze point is relative to the top left corner of the calibrated screen. Eye positions are
+/// relative to the centre of the tracker, with Z pointing away from the screen.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Default, PackedStruct)]
+#[packed_struct(endian = "lsb", size_bytes = "40")]
+pub struct GazeReport {
+    /// Time of the sample in microseconds
+    #[packed_field]
+    pub timestamp: u64,
+    #[packed_field]
+    pub gaze_x: i32,
+    #[packed_field]
+    pub gaze_y: i32,
+    #[packed_field]
+    pub left_eye_x: i32,
+    #[packed_field]
+    pub left_eye_y: i32,
+    #[packed_field]
+    pub left_eye_z: i32,
+    #[packed_field]
+    pub right_eye_x: i32,
+    #[packed_field]
+    pub right_eye_y: i32,
+    #[packed_field]
+    pub right_eye_z: i32,
+}
+
+/// Fixed properties of the tracker, distances in micrometres
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Default, PackedStruct)]
+#[packed_struct(endian = "lsb", size_bytes = "21")]
+pub struct EyeTrackerCapabilities {
+    /// `1` for trackers precise enough for gaze interaction
+    #[packed_field]
+    pub tracker_quality: u8,
+    #[packed_field]
+    pub minimum_tracking_distance: u32,
+    #[packed_field]
+    pub optimum_tracking_distance: u32,
+    #[packed_field]
+    pub maximum_tracking_distance: u32,
+    #[packed_field]
+    pub maximum_screen_plane_width: u32,
+    #[packed_field]
+    pub maximum_screen_plane_height: u32,
+}
+
+/// Screen the tracker is calibrated for, set by the host, dimensions in micrometres
+///
+/// The display fields identify the screen as in its EDID.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Default, PackedStruct)]
+#[packed_struct(endian = "lsb", size_bytes = "18")]
+pub struct EyeTrackerConfiguration {
+    #[packed_field]
+    pub display_manufacturer_id: u16,
+    #[packed_field]
+    pub display_product_id: u16,
+    #[packed_field]
+    pub display_serial_number: u32,
+    #[packed_field]
+    pub display_manufacturer_date: u16,
+    #[packed_field]
+    pub calibrated_screen_width: i32,
+    #[packed_field]
+    pub calibrated_screen_height: i32,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Default, PrimitiveEnum)]
+#[repr(u8)]
+pub enum ConfigurationStatus {
+    Reserved = 0,
+    #[default]
+    Ready = 1,
+    Configuring = 2,
+    ScreenSetupNeeded = 3,
+    UserCalibrationNeeded = 4,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Default, PackedStruct)]
+#[packed_struct(endian = "lsb", size_bytes = "3")]
+pub struct EyeTrackerStatus {
+    #[packed_field(ty = "enum", element_size_bytes = "1")]
+    pub configuration_status: ConfigurationStatus,
+    /// Rate of [`GazeReport`]s in Hz
+    #[packed_field]
+    pub sampling_frequency: u16,
+}
+
+impl NumberedReport for GazeReport {
+    const REPORT_ID: u8 = GAZE_REPORT_ID;
+}
+
+/// Configuration of [`EyeTrackerInterface`]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct EyeTrackerConfig {
+    /// The same report descriptor as the inner [`RawInterfaceConfig`]
+    pub report_descriptor: &'static [u8],
+    pub capabilities: EyeTrackerCapabilities,
+    /// Screen configuration until the host sets it
+    pub configuration: EyeTrackerConfiguration,
+    /// Status until set with [`EyeTrackerInterface::set_status`]
+    pub status: EyeTrackerStatus,
+}
+
+/// Longest eye tracker feature report, [`EyeTrackerCapabilities`]
+const MAX_FEATURE_REPORT_LEN: usize = 21;
+
+type EyeTrackerReports = FeatureReports<4, MAX_FEATURE_REPORT_LEN>;
+
+type EyeTrackerInnerConfig<'a, B> = WrappedInterfaceConfig<
+    ReportIdInterface<'a, B, EyeTrackerReports, 1>,
+    RawInterfaceConfig<'a>,
+    EyeTrackerReports,
+>;
+
+/// Eye tracker reporting the gaze point on the screen
+///
+/// The feature reports are held in the interface's [`FeatureReports`], the host can only set the
+/// configuration and control feature reports.
+pub struct EyeTrackerInterface<'a, B: UsbBus> {
+    inner: ReportIdInterface<'a, B, EyeTrackerReports, 1>,
+    config: EyeTrackerConfig,
+}
+
+/// Eye tracker interface, see [`EyeTrackerInterface`]
+pub type EyeTracker<'a, B> = EyeTrackerInterface<'a, B>;
+
+impl<'a, B: UsbBus> EyeTrackerInterface<'a, B> {
+    delegate! {
+        to self.inner {
+            pub fn protocol(&self) -> HidProtocol;
+            pub fn report_latency(&self) -> LatencyHistogram;
+            pub fn clear_report_latency(&self);
+            pub fn host_stalls(&self) -> u32;
+            pub fn host_stalled(&self) -> bool;
+            pub fn write_ready(&self) -> bool;
+            /// Call every 1ms / at 1 KHz
+            pub fn tick(&self) -> Result<(), UsbHidError>;
+        }
+    }
+
+    /// Screen configuration last set by the host
+    pub fn configuration(&self) -> EyeTrackerConfiguration {
+        self.inner
+            .handler()
+            .get(CONFIGURATION_REPORT_ID)
+            .and_then(|data| EyeTrackerConfiguration::unpack_from_slice(data).ok())
+            .unwrap_or(self.config.configuration)
+    }
+
+    pub fn status(&self) -> EyeTrackerStatus {
+        self.inner
+            .handler()
+            .get(STATUS_REPORT_ID)
+            .and_then(|data| EyeTrackerStatus::unpack_from_slice(data).ok())
+            .unwrap_or(self.config.status)
+    }
+
+    /// Sets the status reported in the status feature report, such as
+    /// [`ConfigurationStatus::UserCalibrationNeeded`] until the tracker is calibrated
+    pub fn set_status(&mut self, status: EyeTrackerStatus) -> Result<(), UsbHidError> {
+        let data = status.pack().map_err(|e| {
+            error!("Error packing EyeTrackerStatus: {:?}", e);
+            UsbHidError::SerializationError
+        })?;
+        self.inner.handler_mut().set(STATUS_REPORT_ID, &data)
+    }
+
+    /// Device mode last requested by the host, if it has changed since the last call
+    pub fn take_mode_request(&mut self) -> Option<u8> {
+        self.inner
+            .handler_mut()
+            .take_changed(CONTROL_REPORT_ID)
+            .and_then(|data| data.first().copied())
+    }
+
+    pub fn write_gaze(&self, report: &GazeReport) -> Result<(), UsbHidError> {
+        self.inner.write_input(report)
+    }
+
+    pub fn default_config(
+        capabilities: EyeTrackerCapabilities,
+    ) -> WrappedInterfaceConfig<Self, EyeTrackerInnerConfig<'a, B>, EyeTrackerConfig> {
+        let config = EyeTrackerConfig {
+            report_descriptor: EYE_TRACKER_REPORT_DESCRIPTOR,
+            capabilities,
+            configuration: EyeTrackerConfiguration::default(),
+            status: EyeTrackerStatus {
+                configuration_status: ConfigurationStatus::ScreenSetupNeeded,
+                sampling_frequency: 0,
+            },
+        };
+        let mut reports = EyeTrackerReports::new();
+        reports
+            .register(
+                CAPABILITIES_REPORT_ID,
+                &config.capabilities.pack().unwrap(),
+                false,
+            )
+            .unwrap();
+        reports
+            .register(
+                CONFIGURATION_REPORT_ID,
+                &config.configuration.pack().unwrap(),
+                true,
+            )
+            .unwrap();
+        reports
+            .register(STATUS_REPORT_ID, &config.status.pack().unwrap(), false)
+            .unwrap();
+        reports.register(CONTROL_REPORT_ID, &[0], true).unwrap();
+        WrappedInterfaceConfig::new(
+            WrappedInterfaceConfig::new(
+                RawInterfaceBuilder::new(EYE_TRACKER_REPORT_DESCRIPTOR)
+                    .description("Eye Tracker")
+                    .in_endpoint(UsbPacketSize::Bytes64, PollInterval::millis(4))
+                    .without_out_endpoint()
+                    .build()
+                    .unwrap(),
+                reports,
+            ),
+            config,
+        )
+    }
+}
+
+impl<'a, B: UsbBus + 'a> DefaultConfig<'a, B> for EyeTrackerInterface<'a, B> {
+    type Config = WrappedInterfaceConfig<Self, EyeTrackerInnerConfig<'a, B>, EyeTrackerConfig>;
+
+    fn default_config() -> Self::Config {
+        Self::default_config(EyeTrackerCapabilities::default())
+    }
+}
+
+impl<'a, B: UsbBus> InterfaceClass<'a> for EyeTrackerInterface<'a, B> {
+    delegate! {
+        to self.inner{
+           fn report_descriptor(&self) -> &'_ [u8];
+           fn hid_descriptor_body(&self) -> HidDescriptorBodyBytes;
+           fn class_descriptor(&self, descriptor_type: u8, index: u8) -> Option<&'_ [u8]>;
+           fn take_wakeup_request(&self) -> bool;
+           fn id(&self) -> InterfaceNumber;
+           fn write_descriptors(&self, writer: &mut DescriptorWriter) -> usb_device::Result<()>;
+           fn get_string(&self, index: StringIndex, _lang_id: u16) -> Option<&'_ str>;
+           fn set_report(&mut self, data: &[u8]) -> usb_device::Result<()>;
+           fn set_report_by_id(
+               &mut self,
+               report_type: ReportType,
+               report_id: u8,
+               data: &[u8],
+           ) -> usb_device::Result<()>;
+           fn get_report(&mut self, data: &mut [u8]) -> usb_device::Result<usize>;
+           fn get_report_by_id(
+               &mut self,
+               report_type: ReportType,
+               report_id: u8,
+               data: &mut [u8],
+           ) -> usb_device::Result<usize>;
+           fn get_report_ack(&mut self) -> usb_device::Result<()>;
+           fn set_idle(&mut self, report_id: u8, value: u8);
+           fn get_idle(&self, report_id: u8) -> u8;
+           fn set_protocol(&mut self, protocol: HidProtocol) -> usb_device::Result<()>;
+           fn get_protocol(&self) -> HidProtocol;
+           fn out_endpoint_address(&self) -> Option<EndpointAddress>;
+           fn in_endpoint_address(&self) -> EndpointAddress;
+           fn endpoint_in_complete(&self, addr: EndpointAddress);
+           fn endpoint_out(&mut self);
+        }
+    }
+
+    fn static_report_descriptor(&self) -> Option<&'static [u8]> {
+        Some(self.config.report_descriptor)
+    }
+
+    /// Returns the screen configuration and device mode to their defaults
+    fn reset(&mut self) {
+        self.inner.reset();
+        let reports = self.inner.handler_mut();
+        if let Ok(configuration) = self.config.configuration.pack() {
+            reports.set(CONFIGURATION_REPORT_ID, &configuration).ok();
+        }
+        reports.set(CONTROL_REPORT_ID, &[0]).ok();
+    }
+}
+
+impl<'a, B: UsbBus>
+    WrappedInterface<'a, B, ReportIdInterface<'a, B, EyeTrackerReports, 1>, EyeTrackerConfig>
+    for EyeTrackerInterface<'a, B>
+{
+    fn new(
+        interface: ReportIdInterface<'a, B, EyeTrackerReports, 1>,
+        config: EyeTrackerConfig,
+    ) -> Self {
+        Self {
+            inner: interface,
+            config,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::device::eye_tracker::*;
+
+    #[test]
+    fn pack_reports() {
+        let report = GazeReport {
+            timestamp: 0x0102030405060708,
+            gaze_x: 250_000,
+            gaze_y: -1,
+            ..Default::default()
+        };
+        let data = report.pack().unwrap();
+        assert_eq!(
+            data[..16],
+            [
+                0x08, 0x07, 0x06, 0x05, 0x04, 0x03, 0x02, 0x01, 0x90, 0xD0, 0x03, 0x00, 0xFF, 0xFF,
+                0xFF, 0xFF
+            ]
+        );
+
+        let status = EyeTrackerStatus {
+            configuration_status: ConfigurationStatus::UserCalibrationNeeded,
+            sampling_frequency: 90,
+        };
+        assert_eq!(status.pack().unwrap(), [0x04, 0x5A, 0x00]);
+    }
+
+    #[cfg(feature = "simulation")]
+    #[test]
+    fn feature_reports() {
+        use usb_device::bus::UsbBusAllocator;
+
+        use crate::simulation::{control_transfer, device_builder, Response, SimBus};
+
+        let usb_alloc = UsbBusAllocator::new(SimBus::new());
+        let mut tracker = UsbHidClassBuilder::new()
+            .add_interface(EyeTrackerInterface::default_config(
+                EyeTrackerCapabilities {
+                    tracker_quality: 1,
+                    optimum_tracking_distance: 650_000,
+                    ..Default::default()
+                },
+            ))
+            .build(&usb_alloc)
+            .unwrap();
+        let mut usb_dev = device_builder(&usb_alloc, 64).build();
+
+        // GET_REPORT (Feature) capabilities
+        let Response::Data(capabilities) = control_transfer(
+            &mut usb_dev,
+            &mut [&mut tracker],
+            [0xA1, 0x01, 0x02, 0x03, 0x00, 0x00, 0x40, 0x00],
+            &[],
+        ) else {
+            panic!("no capabilities report");
+        };
+        assert_eq!(capabilities.len(), 22);
+        assert_eq!(
+            capabilities[..10],
+            [0x02, 0x01, 0, 0, 0, 0, 0x10, 0xEB, 0x09, 0]
+        );
+
+        // SET_REPORT (Feature) configuration, 527mm x 296mm screen
+        let mut configuration = [0; 19];
+        configuration[0] = CONFIGURATION_REPORT_ID;
+        configuration[11..].copy_from_slice(&[0x98, 0x0A, 0x08, 0x00, 0x40, 0x84, 0x04, 0x00]);
+        assert_eq!(
+            control_transfer(
+                &mut usb_dev,
+                &mut [&mut tracker],
+                [0x21, 0x09, 0x03, 0x03, 0x00, 0x00, 0x13, 0x00],
+                &configuration
+            ),
+            Response::Ack
+        );
+        // SET_REPORT (Feature) device mode
+        assert_eq!(
+            control_transfer(
+                &mut usb_dev,
+                &mut [&mut tracker],
+                [0x21, 0x09, 0x05, 0x03, 0x00, 0x00, 0x02, 0x00],
+                &[CONTROL_REPORT_ID, 0x01]
+            ),
+            Response::Ack
+        );
+        // SET_REPORT (Feature) capabilities is stalled, the capabilities are read only
+        let mut capabilities = [0x07; 22];
+        capabilities[0] = CAPABILITIES_REPORT_ID;
+        assert_eq!(
+            control_transfer(
+                &mut usb_dev,
+                &mut [&mut tracker],
+                [0x21, 0x09, 0x02, 0x03, 0x00, 0x00, 0x16, 0x00],
+                &capabilities
+            ),
+            Response::Stall
+        );
+        // SET_REPORT (Output) device mode is stalled, the tracker has no output reports
+        assert_eq!(
+            control_transfer(
+                &mut usb_dev,
+                &mut [&mut tracker],
+                [0x21, 0x09, 0x05, 0x02, 0x00, 0x00, 0x02, 0x00],
+                &[CONTROL_REPORT_ID, 0x02]
+            ),
+            Response::Stall
+        );
+        let Response::Data(capabilities) = control_transfer(
+            &mut usb_dev,
+            &mut [&mut tracker],
+            [0xA1, 0x01, 0x02, 0x03, 0x00, 0x00, 0x40, 0x00],
+            &[],
+        ) else {
+            panic!("no capabilities report");
+        };
+        assert_eq!(capabilities[..2], [CAPABILITIES_REPORT_ID, 0x01]);
+
+        let interface = tracker.interface_mut::<EyeTrackerInterface<'_, _>, _>();
+        assert_eq!(interface.configuration().calibrated_screen_width, 527_000);
+        assert_eq!(interface.configuration().calibrated_screen_height, 296_000);
+        assert_eq!(interface.take_mode_request(), Some(1));
+        assert_eq!(interface.take_mode_request(), None);
+
+        interface
+            .set_status(EyeTrackerStatus {
+                configuration_status: ConfigurationStatus::Ready,
+                sampling_frequency: 60,
+            })
+            .unwrap();
+        // GET_REPORT (Feature) status
+        assert_eq!(
+            control_transfer(
+                &mut usb_dev,
+                &mut [&mut tracker],
+                [0xA1, 0x01, 0x04, 0x03, 0x00, 0x00, 0x40, 0x00],
+                &[]
+            ),
+            Response::Data(vec![0x04, 0x01, 0x3C, 0x00])
+        );
+
+        tracker
+            .interface::<EyeTrackerInterface<'_, _>, _>()
+            .write_gaze(&GazeReport {
+                gaze_x: 1,
+                ..Default::default()
+            })
+            .unwrap();
+        let report = usb_dev.bus().host_take_in(1).unwrap();
+        assert_eq!(report.len(), 41);
+        assert_eq!(report[..2], [GAZE_REPORT_ID, 0x00]);
+        assert_eq!(report[9], 0x01);
+    }
+}
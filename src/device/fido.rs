@@ -1,13 +1,15 @@
 //! HID FIDO Universal 2nd Factor (U2F)
 use crate::hid_class::descriptor::HidProtocol;
 use delegate::delegate;
-use fugit::ExtU32;
 use usb_device::bus::{InterfaceNumber, StringIndex, UsbBus};
-use usb_device::class_prelude::DescriptorWriter;
+use usb_device::class_prelude::{DescriptorWriter, EndpointAddress};
 
 use crate::hid_class::prelude::*;
+use crate::interface::duration::PollInterval;
 use crate::interface::raw::{RawInterface, RawInterfaceConfig};
-use crate::interface::{InterfaceClass, WrappedInterface, WrappedInterfaceConfig};
+use crate::interface::{
+    DefaultConfig, HidDescriptorBodyBytes, InterfaceClass, WrappedInterface, WrappedInterfaceConfig,
+};
 use crate::UsbHidError;
 
 /// Raw FIDO report descriptor.
@@ -49,39 +51,55 @@ pub struct RawFidoInterface<'a, B: UsbBus> {
     inner: RawInterface<'a, B>,
 }
 
+/// FIDO U2F interface, see [`RawFidoInterface`]
+pub type RawFido<'a, B> = RawFidoInterface<'a, B>;
+
 impl<'a, B: UsbBus> RawFidoInterface<'a, B> {
     pub fn write_report(&self, report: &RawFidoMsg) -> Result<(), UsbHidError> {
-        self.inner
-            .write_report(&report.packet)
-            .map(|_| ())
-            .map_err(UsbHidError::from)
+        self.inner.write_report(&report.packet).map(|_| ())
     }
-    pub fn read_report(&self) -> usb_device::Result<RawFidoMsg> {
+    pub fn read_report(&self) -> Result<RawFidoMsg, UsbHidError> {
         let mut report = RawFidoMsg::default();
-        match self.inner.read_report(&mut report.packet) {
-            Err(e) => Err(e),
-            Ok(_) => Ok(report),
-        }
+        self.inner.read_report(&mut report.packet).map(|_| report)
     }
 
     pub fn default_config() -> WrappedInterfaceConfig<Self, RawInterfaceConfig<'a>> {
+        Self::default_config_with_descriptor(FIDO_REPORT_DESCRIPTOR)
+    }
+
+    /// Default configuration with an alternative report descriptor
+    ///
+    /// The descriptor must describe reports with the same layout as [`RawFidoMsg`]
+    pub fn default_config_with_descriptor(
+        report_descriptor: &'a [u8],
+    ) -> WrappedInterfaceConfig<Self, RawInterfaceConfig<'a>> {
         WrappedInterfaceConfig::new(
-            RawInterfaceBuilder::new(FIDO_REPORT_DESCRIPTOR)
+            RawInterfaceBuilder::new(report_descriptor)
                 .description("U2F Token")
-                .in_endpoint(UsbPacketSize::Bytes64, 5.millis())
-                .unwrap()
-                .with_out_endpoint(UsbPacketSize::Bytes64, 5.millis())
-                .unwrap()
-                .build(),
+                .in_endpoint(UsbPacketSize::Bytes64, PollInterval::millis(5))
+                .with_out_endpoint(UsbPacketSize::Bytes64, PollInterval::millis(5))
+                .build()
+                .unwrap(),
             (),
         )
     }
 }
 
+impl<'a, B: UsbBus + 'a> DefaultConfig<'a, B> for RawFidoInterface<'a, B> {
+    type Config = WrappedInterfaceConfig<Self, RawInterfaceConfig<'a>>;
+
+    fn default_config() -> Self::Config {
+        Self::default_config()
+    }
+}
+
 impl<'a, B: UsbBus> InterfaceClass<'a> for RawFidoInterface<'a, B> {
     delegate! {
         to self.inner{
            fn report_descriptor(&self) -> &'_ [u8];
+           fn hid_descriptor_body(&self) -> HidDescriptorBodyBytes;
+           fn class_descriptor(&self, descriptor_type: u8, index: u8) -> Option<&'_ [u8]>;
+           fn take_wakeup_request(&self) -> bool;
            fn id(&self) -> InterfaceNumber;
            fn write_descriptors(&self, writer: &mut DescriptorWriter) -> usb_device::Result<()>;
            fn get_string(&self, index: StringIndex, _lang_id: u16) -> Option<&'_ str>;
@@ -91,8 +109,12 @@ impl<'a, B: UsbBus> InterfaceClass<'a> for RawFidoInterface<'a, B> {
            fn get_report_ack(&mut self) -> usb_device::Result<()>;
            fn set_idle(&mut self, report_id: u8, value: u8);
            fn get_idle(&self, report_id: u8) -> u8;
-           fn set_protocol(&mut self, protocol: HidProtocol);
+           fn set_protocol(&mut self, protocol: HidProtocol) -> usb_device::Result<()>;
            fn get_protocol(&self) -> HidProtocol;
+           fn out_endpoint_address(&self) -> Option<EndpointAddress>;
+           fn in_endpoint_address(&self) -> EndpointAddress;
+           fn endpoint_in_complete(&self, addr: EndpointAddress);
+           fn endpoint_out(&mut self);
         }
     }
 }
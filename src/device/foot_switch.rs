@@ -0,0 +1,199 @@
+//!HID foot switches such as transcription and accessibility pedals
+//!
+//! Pedals are buttons of an application collection of their own, so hosts do not treat them as
+//! a pointing device or keyboard. [`FOOT_SWITCH_REPORT_DESCRIPTOR`] is a Generic Desktop joystick
+//! collection, seen by game controller APIs and readable without a driver.
+//! [`TELEPHONY_FOOT_SWITCH_REPORT_DESCRIPTOR`] is a Telephony collection, which operating systems
+//! leave to applications reading the device directly, as transcription software does.
+use crate::hid_class::descriptor::HidProtocol;
+use core::default::Default;
+use delegate::delegate;
+use packed_struct::prelude::*;
+use usb_device::bus::{InterfaceNumber, StringIndex, UsbBus};
+use usb_device::class_prelude::{DescriptorWriter, EndpointAddress};
+
+use crate::hid_class::prelude::*;
+use crate::interface::duration::PollInterval;
+use crate::interface::latency::LatencyHistogram;
+use crate::interface::raw::{RawInterface, RawInterfaceConfig};
+use crate::interface::{
+    DefaultConfig, HidDescriptorBodyBytes, InterfaceClass, WrappedInterface, WrappedInterfaceConfig,
+};
+use crate::UsbHidError;
+
+/// Foot switch with up to 8 pedals as a joystick
+///
+/// * Bits 0-7 - Pedals 1 to 8, switches with fewer pedals leave the remaining bits clear
+#[rustfmt::skip]
+pub const FOOT_SWITCH_REPORT_DESCRIPTOR: &[u8] = &[
+    0x05, 0x01,        // Usage Page (Generic Desktop),
+    0x09, 0x04,        // Usage (Joystick),
+    0xA1, 0x01,        // Collection (Application),
+    0x05, 0x09,        //   Usage Page (Buttons),
+    0x19, 0x01,        //   Usage Minimum (1),
+    0x29, 0x08,        //   Usage Maximum (8),
+    0x15, 0x00,        //   Logical Minimum (0),
+    0x25, 0x01,        //   Logical Maximum (1),
+    0x75, 0x01,        //   Report Size (1),
+    0x95, 0x08,        //   Report Count (8),
+    0x81, 0x02,        //   Input (Data, Variable, Absolute),
+    0xC0,              // End Collection
+];
+
+/// Foot switch with up to 8 pedals as a telephony programmable button collection, with the same
+/// report layout as [`FOOT_SWITCH_REPORT_DESCRIPTOR`]
+#[rustfmt::skip]
+pub const TELEPHONY_FOOT_SWITCH_REPORT_DESCRIPTOR: &[u8] = &[
+    0x05, 0x0B,        // Usage Page (Telephony),
+    0x09, 0x07,        // Usage (Programmable Button),
+    0xA1, 0x01,        // Collection (Application),
+    0x05, 0x09,        //   Usage Page (Buttons),
+    0x19, 0x01,        //   Usage Minimum (1),
+    0x29, 0x08,        //   Usage Maximum (8),
+    0x15, 0x00,        //   Logical Minimum (0),
+    0x25, 0x01,        //   Logical Maximum (1),
+    0x75, 0x01,        //   Report Size (1),
+    0x95, 0x08,        //   Report Count (8),
+    0x81, 0x02,        //   Input (Data, Variable, Absolute),
+    0xC0,              // End Collection
+];
+
+/// Pressed pedals, bit 0 is pedal 1
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Default, PackedStruct)]
+#[packed_struct(endian = "lsb", size_bytes = "1")]
+pub struct FootSwitchReport {
+    #[packed_field]
+    pub pedals: u8,
+}
+
+pub struct FootSwitchInterface<'a, B: UsbBus> {
+    inner: RawInterface<'a, B>,
+}
+
+/// Foot switch interface, see [`FootSwitchInterface`]
+pub type FootSwitch<'a, B> = FootSwitchInterface<'a, B>;
+
+impl<'a, B: UsbBus> FootSwitchInterface<'a, B> {
+    delegate! {
+        to self.inner {
+            pub fn protocol(&self) -> HidProtocol;
+            pub fn report_latency(&self) -> LatencyHistogram;
+            pub fn clear_report_latency(&self);
+            pub fn host_stalls(&self) -> u32;
+            pub fn host_stalled(&self) -> bool;
+            pub fn write_ready(&self) -> bool;
+        }
+    }
+
+    /// Writes the state of the pedals, bit 0 is pedal 1
+    pub fn write_buttons(&self, pedals: u8) -> Result<(), UsbHidError> {
+        self.write_report(&FootSwitchReport { pedals })
+    }
+
+    pub fn write_report(&self, report: &FootSwitchReport) -> Result<(), UsbHidError> {
+        let data = report.pack().map_err(|e| {
+            error!("Error packing FootSwitchReport: {:?}", e);
+            UsbHidError::SerializationError
+        })?;
+        self.inner.write_report(&data).map(|_| ())
+    }
+
+    pub fn default_config() -> WrappedInterfaceConfig<Self, RawInterfaceConfig<'a>> {
+        Self::default_config_with_descriptor(FOOT_SWITCH_REPORT_DESCRIPTOR)
+    }
+
+    /// Default configuration with an alternative report descriptor, such as
+    /// [`TELEPHONY_FOOT_SWITCH_REPORT_DESCRIPTOR`]
+    ///
+    /// The descriptor must describe reports with the same layout as [`FootSwitchReport`]
+    pub fn default_config_with_descriptor(
+        report_descriptor: &'a [u8],
+    ) -> WrappedInterfaceConfig<Self, RawInterfaceConfig<'a>> {
+        WrappedInterfaceConfig::new(
+            RawInterfaceBuilder::new(report_descriptor)
+                .description("Foot Switch")
+                .in_endpoint(UsbPacketSize::Bytes8, PollInterval::millis(10))
+                .without_out_endpoint()
+                .build()
+                .unwrap(),
+            (),
+        )
+    }
+}
+
+impl<'a, B: UsbBus + 'a> DefaultConfig<'a, B> for FootSwitchInterface<'a, B> {
+    type Config = WrappedInterfaceConfig<Self, RawInterfaceConfig<'a>>;
+
+    fn default_config() -> Self::Config {
+        Self::default_config()
+    }
+}
+
+impl<'a, B: UsbBus> InterfaceClass<'a> for FootSwitchInterface<'a, B> {
+    delegate! {
+        to self.inner{
+           fn report_descriptor(&self) -> &'_ [u8];
+           fn hid_descriptor_body(&self) -> HidDescriptorBodyBytes;
+           fn class_descriptor(&self, descriptor_type: u8, index: u8) -> Option<&'_ [u8]>;
+           fn take_wakeup_request(&self) -> bool;
+           fn id(&self) -> InterfaceNumber;
+           fn write_descriptors(&self, writer: &mut DescriptorWriter) -> usb_device::Result<()>;
+           fn get_string(&self, index: StringIndex, _lang_id: u16) -> Option<&'_ str>;
+           fn reset(&mut self);
+           fn set_report(&mut self, data: &[u8]) -> usb_device::Result<()>;
+           fn get_report(&mut self, data: &mut [u8]) -> usb_device::Result<usize>;
+           fn get_report_ack(&mut self) -> usb_device::Result<()>;
+           fn set_idle(&mut self, report_id: u8, value: u8);
+           fn get_idle(&self, report_id: u8) -> u8;
+           fn set_protocol(&mut self, protocol: HidProtocol) -> usb_device::Result<()>;
+           fn get_protocol(&self) -> HidProtocol;
+           fn out_endpoint_address(&self) -> Option<EndpointAddress>;
+           fn in_endpoint_address(&self) -> EndpointAddress;
+           fn endpoint_in_complete(&self, addr: EndpointAddress);
+           fn endpoint_out(&mut self);
+        }
+    }
+}
+
+impl<'a, B: UsbBus> WrappedInterface<'a, B, RawInterface<'a, B>> for FootSwitchInterface<'a, B> {
+    fn new(interface: RawInterface<'a, B>, _: ()) -> Self {
+        Self { inner: interface }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::device::foot_switch::*;
+
+    #[test]
+    fn descriptors_have_the_same_layout() {
+        assert_eq!(
+            FOOT_SWITCH_REPORT_DESCRIPTOR[6..],
+            TELEPHONY_FOOT_SWITCH_REPORT_DESCRIPTOR[6..]
+        );
+    }
+
+    #[cfg(feature = "simulation")]
+    #[test]
+    fn write_buttons() {
+        use usb_device::bus::UsbBusAllocator;
+        use usb_device::prelude::*;
+
+        use crate::simulation::SimBus;
+
+        let usb_alloc = UsbBusAllocator::new(SimBus::new());
+        let pedals = UsbHidClassBuilder::new()
+            .add_interface(FootSwitchInterface::default_config_with_descriptor(
+                TELEPHONY_FOOT_SWITCH_REPORT_DESCRIPTOR,
+            ))
+            .build(&usb_alloc)
+            .unwrap();
+        let usb_dev = UsbDeviceBuilder::new(&usb_alloc, UsbVidPid(0x1209, 0x0001)).build();
+
+        pedals
+            .interface::<FootSwitch<'_, _>, _>()
+            .write_buttons(0b101)
+            .unwrap();
+        assert_eq!(usb_dev.bus().host_take_in(1).unwrap(), [0b101]);
+    }
+}
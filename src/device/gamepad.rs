@@ -0,0 +1,312 @@
+//!HID gamepads
+use crate::hid_class::descriptor::HidProtocol;
+use core::default::Default;
+use delegate::delegate;
+use packed_struct::prelude::*;
+use usb_device::bus::{InterfaceNumber, StringIndex, UsbBus};
+use usb_device::class_prelude::{DescriptorWriter, EndpointAddress};
+
+use crate::hid_class::prelude::*;
+use crate::interface::duration::PollInterval;
+use crate::interface::latency::LatencyHistogram;
+use crate::interface::raw::{RawInterface, RawInterfaceConfig};
+use crate::interface::{
+    DefaultConfig, HidDescriptorBodyBytes, InterfaceClass, WrappedInterface, WrappedInterfaceConfig,
+};
+pub use crate::page::HatSwitch;
+use crate::UsbHidError;
+
+/// Gamepad with eight buttons, four axes and a hat switch, and two rumble motors
+///
+/// Input report:
+/// * Bits 0-7 - Buttons 1 to 8
+/// * Bytes 1-4 - X, Y, Z and Rz axes, `-127` to `127`
+/// * Byte 5 - Hat switch, `0` (up) to `7` (up left) clockwise in 45° steps, `8` when centred
+///
+/// Output report, see [`RumbleReport`]:
+/// * Bytes 0-1 - Left and right motor magnitudes, `0` to `255`
+/// * Bytes 2-3 - Duration in milliseconds
+#[rustfmt::skip]
+pub const GAMEPAD_REPORT_DESCRIPTOR: &[u8] = &[
+    0x05, 0x01,        // Usage Page (Generic Desktop),
+    0x09, 0x05,        // Usage (Game Pad),
+    0xA1, 0x01,        // Collection (Application),
+    0x05, 0x09,        //   Usage Page (Buttons),
+    0x19, 0x01,        //   Usage Minimum (1),
+    0x29, 0x08,        //   Usage Maximum (8),
+    0x15, 0x00,        //   Logical Minimum (0),
+    0x25, 0x01,        //   Logical Maximum (1),
+    0x75, 0x01,        //   Report Size (1),
+    0x95, 0x08,        //   Report Count (8),
+    0x81, 0x02,        //   Input (Data, Variable, Absolute),
+    0x05, 0x01,        //   Usage Page (Generic Desktop),
+    0x09, 0x30,        //   Usage (X),
+    0x09, 0x31,        //   Usage (Y),
+    0x09, 0x32,        //   Usage (Z),
+    0x09, 0x35,        //   Usage (Rz),
+    0x15, 0x81,        //   Logical Minimum (-127),
+    0x25, 0x7F,        //   Logical Maximum (127),
+    0x75, 0x08,        //   Report Size (8),
+    0x95, 0x04,        //   Report Count (4),
+    0x81, 0x02,        //   Input (Data, Variable, Absolute),
+    0x09, 0x39,        //   Usage (Hat Switch),
+    0x15, 0x00,        //   Logical Minimum (0),
+    0x25, 0x07,        //   Logical Maximum (7),
+    0x35, 0x00,        //   Physical Minimum (0),
+    0x46, 0x3B, 0x01,  //   Physical Maximum (315),
+    0x65, 0x14,        //   Unit (Degrees),
+    0x75, 0x04,        //   Report Size (4),
+    0x95, 0x01,        //   Report Count (1),
+    0x81, 0x42,        //   Input (Data, Variable, Absolute, Null State),
+    0x65, 0x00,        //   Unit (None),
+    0x81, 0x01,        //   Input (Constant),
+    0x05, 0x0F,        //   Usage Page (Physical Interface),
+    0x09, 0x70,        //   Usage (Magnitude),
+    0x09, 0x70,        //   Usage (Magnitude),
+    0x15, 0x00,        //   Logical Minimum (0),
+    0x26, 0xFF, 0x00,  //   Logical Maximum (255),
+    0x35, 0x00,        //   Physical Minimum (0),
+    0x45, 0x00,        //   Physical Maximum (0),
+    0x75, 0x08,        //   Report Size (8),
+    0x95, 0x02,        //   Report Count (2),
+    0x91, 0x02,        //   Output (Data, Variable, Absolute),
+    0x09, 0x50,        //   Usage (Duration),
+    0x27, 0xFF, 0xFF, 0x00, 0x00, //   Logical Maximum (65535),
+    0x66, 0x01, 0x10,  //   Unit (Seconds),
+    0x55, 0x0D,        //   Unit Exponent (-3),
+    0x75, 0x10,        //   Report Size (16),
+    0x95, 0x01,        //   Report Count (1),
+    0x91, 0x02,        //   Output (Data, Variable, Absolute),
+    0x65, 0x00,        //   Unit (None),
+    0x55, 0x00,        //   Unit Exponent (0),
+    0xC0,              // End Collection
+];
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Default, PackedStruct)]
+#[packed_struct(endian = "lsb", size_bytes = "6")]
+pub struct GamepadReport {
+    #[packed_field]
+    pub buttons: u8,
+    #[packed_field]
+    pub x: i8,
+    #[packed_field]
+    pub y: i8,
+    #[packed_field]
+    pub z: i8,
+    #[packed_field]
+    pub rz: i8,
+    #[packed_field(ty = "enum", element_size_bytes = "1")]
+    pub hat: HatSwitch,
+}
+
+/// Rumble motor magnitudes sent by the host
+///
+/// Motors run at the given magnitudes for `duration_ms`, or until the next rumble report
+/// replaces them. Magnitudes of `0` stop the motors.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Default, PackedStruct)]
+#[packed_struct(endian = "lsb", size_bytes = "4")]
+pub struct RumbleReport {
+    #[packed_field]
+    pub left: u8,
+    #[packed_field]
+    pub right: u8,
+    #[packed_field]
+    pub duration_ms: u16,
+}
+
+pub struct GamepadInterface<'a, B: UsbBus> {
+    inner: RawInterface<'a, B>,
+}
+
+/// Gamepad interface, see [`GamepadInterface`]
+pub type Gamepad<'a, B> = GamepadInterface<'a, B>;
+
+impl<'a, B: UsbBus> GamepadInterface<'a, B> {
+    delegate! {
+        to self.inner {
+            pub fn protocol(&self) -> HidProtocol;
+            pub fn report_latency(&self) -> LatencyHistogram;
+            pub fn clear_report_latency(&self);
+            pub fn host_stalls(&self) -> u32;
+            pub fn host_stalled(&self) -> bool;
+            pub fn write_ready(&self) -> bool;
+        }
+    }
+
+    pub fn write_report(&self, report: &GamepadReport) -> Result<(), UsbHidError> {
+        let data = report.pack().map_err(|e| {
+            error!("Error packing GamepadReport: {:?}", e);
+            UsbHidError::SerializationError
+        })?;
+        self.inner.write_report(&data).map(|_| ())
+    }
+
+    /// The last rumble report received from the host, [`UsbHidError::WouldBlock`] if it has
+    /// already been read
+    ///
+    /// Rumble reports are received while the [`UsbHidClass`](crate::hid_class::UsbHidClass) is
+    /// polled, whether sent to the OUT endpoint or with a `SET_REPORT` request.
+    pub fn read_rumble(&self) -> Result<RumbleReport, UsbHidError> {
+        let mut data = [0; 4];
+        self.inner.read_report(&mut data)?;
+        RumbleReport::unpack(&data).map_err(|e| {
+            error!("Error unpacking RumbleReport: {:?}", e);
+            UsbHidError::SerializationError
+        })
+    }
+
+    pub fn default_config() -> WrappedInterfaceConfig<Self, RawInterfaceConfig<'a>> {
+        Self::default_config_with_descriptor(GAMEPAD_REPORT_DESCRIPTOR)
+    }
+
+    /// Default configuration with an alternative report descriptor
+    ///
+    /// The descriptor must describe reports with the same layout as [`GamepadReport`] and
+    /// [`RumbleReport`]
+    pub fn default_config_with_descriptor(
+        report_descriptor: &'a [u8],
+    ) -> WrappedInterfaceConfig<Self, RawInterfaceConfig<'a>> {
+        WrappedInterfaceConfig::new(
+            RawInterfaceBuilder::new(report_descriptor)
+                .description("Gamepad")
+                .in_endpoint(UsbPacketSize::Bytes8, PollInterval::millis(10))
+                .with_out_endpoint(UsbPacketSize::Bytes8, PollInterval::millis(10))
+                .build()
+                .unwrap(),
+            (),
+        )
+    }
+}
+
+impl<'a, B: UsbBus + 'a> DefaultConfig<'a, B> for GamepadInterface<'a, B> {
+    type Config = WrappedInterfaceConfig<Self, RawInterfaceConfig<'a>>;
+
+    fn default_config() -> Self::Config {
+        Self::default_config()
+    }
+}
+
+impl<'a, B: UsbBus> InterfaceClass<'a> for GamepadInterface<'a, B> {
+    delegate! {
+        to self.inner{
+           fn report_descriptor(&self) -> &'_ [u8];
+           fn hid_descriptor_body(&self) -> HidDescriptorBodyBytes;
+           fn class_descriptor(&self, descriptor_type: u8, index: u8) -> Option<&'_ [u8]>;
+           fn take_wakeup_request(&self) -> bool;
+           fn id(&self) -> InterfaceNumber;
+           fn write_descriptors(&self, writer: &mut DescriptorWriter) -> usb_device::Result<()>;
+           fn get_string(&self, index: StringIndex, _lang_id: u16) -> Option<&'_ str>;
+           fn reset(&mut self);
+           fn set_report(&mut self, data: &[u8]) -> usb_device::Result<()>;
+           fn get_report(&mut self, data: &mut [u8]) -> usb_device::Result<usize>;
+           fn get_report_ack(&mut self) -> usb_device::Result<()>;
+           fn set_idle(&mut self, report_id: u8, value: u8);
+           fn get_idle(&self, report_id: u8) -> u8;
+           fn set_protocol(&mut self, protocol: HidProtocol) -> usb_device::Result<()>;
+           fn get_protocol(&self) -> HidProtocol;
+           fn out_endpoint_address(&self) -> Option<EndpointAddress>;
+           fn in_endpoint_address(&self) -> EndpointAddress;
+           fn endpoint_in_complete(&self, addr: EndpointAddress);
+           fn endpoint_out(&mut self);
+        }
+    }
+}
+
+impl<'a, B: UsbBus> WrappedInterface<'a, B, RawInterface<'a, B>> for GamepadInterface<'a, B> {
+    fn new(interface: RawInterface<'a, B>, _: ()) -> Self {
+        Self { inner: interface }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use packed_struct::PackedStruct;
+
+    use crate::device::gamepad::{GamepadReport, HatSwitch, RumbleReport};
+
+    #[test]
+    fn pack_report() {
+        let report = GamepadReport {
+            buttons: 0b1000_0001,
+            x: -127,
+            y: 127,
+            z: 0,
+            rz: -1,
+            hat: HatSwitch::W,
+        };
+        assert_eq!(report.pack().unwrap(), [0x81, 0x81, 0x7F, 0x00, 0xFF, 0x06]);
+        assert_eq!(GamepadReport::default().pack().unwrap()[5], 0x08);
+    }
+
+    #[test]
+    fn unpack_rumble_report() {
+        assert_eq!(
+            RumbleReport::unpack(&[0xFF, 0x40, 0xF4, 0x01]).unwrap(),
+            RumbleReport {
+                left: 0xFF,
+                right: 0x40,
+                duration_ms: 500,
+            }
+        );
+    }
+
+    #[cfg(feature = "simulation")]
+    #[test]
+    fn rumble_from_out_endpoint_and_set_report() {
+        use usb_device::bus::UsbBusAllocator;
+
+        use crate::device::gamepad::Gamepad;
+        use crate::hid_class::descriptor::ReportType;
+        use crate::prelude::*;
+        use crate::simulation::{
+            control_transfer, device_builder, requests, Response, SimBus, SimHost,
+        };
+        use crate::UsbHidError;
+
+        let usb_alloc = UsbBusAllocator::new(SimBus::new());
+        let mut class = UsbHidClassBuilder::new()
+            .add_device::<Gamepad<_>>()
+            .build(&usb_alloc)
+            .unwrap();
+        let mut usb_dev = device_builder(&usb_alloc, 8).build();
+        let host = SimHost::enumerate(&mut usb_dev, &mut [&mut class]).unwrap();
+        assert!(matches!(
+            class.interface::<Gamepad<'_, _>, _>().read_rumble(),
+            Err(UsbHidError::WouldBlock)
+        ));
+
+        let rumble = [0x80, 0x20, 0x64, 0x00];
+        assert_eq!(
+            host.write_report(&mut usb_dev, &mut [&mut class], 0, &rumble),
+            Response::Ack
+        );
+        assert_eq!(
+            class
+                .interface::<Gamepad<'_, _>, _>()
+                .read_rumble()
+                .unwrap(),
+            RumbleReport {
+                left: 0x80,
+                right: 0x20,
+                duration_ms: 100,
+            }
+        );
+
+        assert_eq!(
+            control_transfer(
+                &mut usb_dev,
+                &mut [&mut class],
+                requests::set_report(0, ReportType::Output, 0, 4),
+                &[0, 0, 0, 0]
+            ),
+            Response::Ack
+        );
+        assert_eq!(
+            class
+                .interface::<Gamepad<'_, _>, _>()
+                .read_rumble()
+                .unwrap(),
+            RumbleReport::default()
+        );
+    }
+}
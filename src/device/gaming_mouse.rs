@@ -0,0 +1,480 @@
+//!Gaming mice with configurable DPI, polling rate and profiles
+//!
+//! Settings are vendor defined feature reports, read and written by the vendor's configuration
+//! software and routed to a [`MouseSettingsHandler`]. Host mouse drivers only use the motion
+//! input report. Wrapping the handler in [`StoredMouseSettings`] saves the settings set by the
+//! host to a [`SettingsStore`] and restores them at startup.
+use crate::hid_class::descriptor::{HidProtocol, ReportType};
+use core::default::Default;
+use delegate::delegate;
+use packed_struct::prelude::*;
+use usb_device::bus::{InterfaceNumber, StringIndex, UsbBus};
+use usb_device::class_prelude::{DescriptorWriter, EndpointAddress};
+use usb_device::UsbError;
+
+use crate::hid_class::prelude::*;
+use crate::interface::duration::PollInterval;
+use crate::interface::feature::{write_with_id, FeatureResponder};
+use crate::interface::latency::LatencyHistogram;
+use crate::interface::raw::{RawInterface, RawInterfaceConfig};
+use crate::interface::settings::SettingsStore;
+use crate::interface::{
+    HidDescriptorBodyBytes, InterfaceClass, WrappedInterface, WrappedInterfaceConfig,
+};
+use crate::UsbHidError;
+
+pub const GAMING_MOUSE_REPORT_ID: u8 = 0x01;
+pub const DPI_REPORT_ID: u8 = 0x02;
+pub const POLLING_RATE_REPORT_ID: u8 = 0x03;
+pub const PROFILE_REPORT_ID: u8 = 0x04;
+
+/// Mouse with 16 bit motion and vendor defined settings
+///
+/// * Report ID 1, Input - [`GamingMouseReport`]
+/// * Report ID 2, Feature - DPI, little endian `u16`
+/// * Report ID 3, Feature - Polling rate in Hz, little endian `u16`
+/// * Report ID 4, Feature - Active profile, `u8`
+#[rustfmt::skip]
+pub const GAMING_MOUSE_REPORT_DESCRIPTOR: &[u8] = &[
+    0x05, 0x01,        // Usage Page (Generic Desktop),
+    0x09, 0x02,        // Usage (Mouse),
+    0xA1, 0x01,        // Collection (Application),
+    0x85, 0x01,        //   Report ID (1),
+    0x09, 0x01,        //   Usage (Pointer),
+    0xA1, 0x00,        //   Collection (Physical),
+    0x05, 0x09,        //     Usage Page (Buttons),
+    0x19, 0x01,        //     Usage Minimum (1),
+    0x29, 0x08,        //     Usage Maximum (8),
+    0x15, 0x00,        //     Logical Minimum (0),
+    0x25, 0x01,        //     Logical Maximum (1),
+    0x75, 0x01,        //     Report Size (1),
+    0x95, 0x08,        //     Report Count (8),
+    0x81, 0x02,        //     Input (Data, Variable, Absolute),
+    0x05, 0x01,        //     Usage Page (Generic Desktop),
+    0x09, 0x30,        //     Usage (X),
+    0x09, 0x31,        //     Usage (Y),
+    0x16, 0x01, 0x80,  //     Logical Minimum (-32767),
+    0x26, 0xFF, 0x7F,  //     Logical Maximum (32767),
+    0x75, 0x10,        //     Report Size (16),
+    0x95, 0x02,        //     Report Count (2),
+    0x81, 0x06,        //     Input (Data, Variable, Relative),
+    0x09, 0x38,        //     Usage (Wheel),
+    0x15, 0x81,        //     Logical Minimum (-127),
+    0x25, 0x7F,        //     Logical Maximum (127),
+    0x75, 0x08,        //     Report Size (8),
+    0x95, 0x01,        //     Report Count (1),
+    0x81, 0x06,        //     Input (Data, Variable, Relative),
+    0xC0,              //   End Collection
+    0x06, 0x00, 0xFF,  //   Usage Page (Vendor Defined 0xFF00),
+    0x85, 0x02,        //   Report ID (2),
+    0x09, 0x01,        //   Usage (DPI),
+    0x15, 0x00,        //   Logical Minimum (0),
+    0x27, 0xFF, 0xFF, 0x00, 0x00, // Logical Maximum (65535),
+    0x75, 0x10,        //   Report Size (16),
+    0xB1, 0x02,        //   Feature (Data, Variable, Absolute),
+    0x85, 0x03,        //   Report ID (3),
+    0x09, 0x02,        //   Usage (Polling Rate),
+    0xB1, 0x02,        //   Feature (Data, Variable, Absolute),
+    0x85, 0x04,        //   Report ID (4),
+    0x09, 0x03,        //   Usage (Profile),
+    0x26, 0xFF, 0x00,  //   Logical Maximum (255),
+    0x75, 0x08,        //   Report Size (8),
+    0xB1, 0x02,        //   Feature (Data, Variable, Absolute),
+    0xC0,              // End Collection
+];
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Default, PackedStruct)]
+#[packed_struct(endian = "lsb", size_bytes = "6")]
+pub struct GamingMouseReport {
+    #[packed_field]
+    pub buttons: u8,
+    #[packed_field]
+    pub x: i16,
+    #[packed_field]
+    pub y: i16,
+    #[packed_field]
+    pub wheel: i8,
+}
+
+/// Mouse settings, read and written by the host through feature reports
+///
+/// Unsupported values should be ignored or clamped, the host reads a setting back to find the
+/// value in effect. The polling rate of the interrupt endpoint is fixed at enumeration, so a changed polling rate
+/// must be applied by the application, e.g. by sending reports less often.
+pub trait MouseSettingsHandler {
+    fn dpi(&self) -> u16;
+    fn set_dpi(&mut self, dpi: u16);
+    fn polling_rate(&self) -> u16;
+    fn set_polling_rate(&mut self, hz: u16);
+    fn profile(&self) -> u8;
+    fn set_profile(&mut self, profile: u8);
+}
+
+/// [`MouseSettingsHandler`] saving the settings set by the host to a [`SettingsStore`]
+///
+/// Each setting is saved under the ID of its feature report, after the inner handler has applied
+/// it, so the saved value is the one in effect. Errors saving a setting are logged and otherwise
+/// ignored, the setting stays in effect until the device is reset.
+pub struct StoredMouseSettings<H, S> {
+    handler: H,
+    store: S,
+}
+
+impl<H: MouseSettingsHandler, S: SettingsStore> StoredMouseSettings<H, S> {
+    /// Restores the settings saved in `store` to `handler`
+    pub fn new(mut handler: H, mut store: S) -> Result<Self, S::Error> {
+        let mut data = [0; 2];
+        if let Some(2) = store.load(DPI_REPORT_ID, &mut data)? {
+            handler.set_dpi(u16::from_le_bytes(data));
+        }
+        if let Some(2) = store.load(POLLING_RATE_REPORT_ID, &mut data)? {
+            handler.set_polling_rate(u16::from_le_bytes(data));
+        }
+        if let Some(1) = store.load(PROFILE_REPORT_ID, &mut data)? {
+            handler.set_profile(data[0]);
+        }
+        Ok(Self { handler, store })
+    }
+
+    pub fn handler(&self) -> &H {
+        &self.handler
+    }
+
+    pub fn handler_mut(&mut self) -> &mut H {
+        &mut self.handler
+    }
+
+    pub fn into_inner(self) -> (H, S) {
+        (self.handler, self.store)
+    }
+
+    fn save(&mut self, report_id: u8, data: &[u8]) {
+        if self.store.save(report_id, data).is_err() {
+            warn!("Error saving setting, report ID {:X}", report_id);
+        }
+    }
+}
+
+impl<H: MouseSettingsHandler, S: SettingsStore> MouseSettingsHandler for StoredMouseSettings<H, S> {
+    fn dpi(&self) -> u16 {
+        self.handler.dpi()
+    }
+
+    fn set_dpi(&mut self, dpi: u16) {
+        self.handler.set_dpi(dpi);
+        self.save(DPI_REPORT_ID, &self.handler.dpi().to_le_bytes());
+    }
+
+    fn polling_rate(&self) -> u16 {
+        self.handler.polling_rate()
+    }
+
+    fn set_polling_rate(&mut self, hz: u16) {
+        self.handler.set_polling_rate(hz);
+        self.save(
+            POLLING_RATE_REPORT_ID,
+            &self.handler.polling_rate().to_le_bytes(),
+        );
+    }
+
+    fn profile(&self) -> u8 {
+        self.handler.profile()
+    }
+
+    fn set_profile(&mut self, profile: u8) {
+        self.handler.set_profile(profile);
+        self.save(PROFILE_REPORT_ID, &[self.handler.profile()]);
+    }
+}
+
+pub struct GamingMouseInterface<'a, B: UsbBus, H> {
+    inner: RawInterface<'a, B>,
+    handler: H,
+    feature_responder: FeatureResponder,
+}
+
+/// Gaming mouse interface, see [`GamingMouseInterface`]
+pub type GamingMouse<'a, B, H> = GamingMouseInterface<'a, B, H>;
+
+impl<'a, B: UsbBus, H: MouseSettingsHandler> GamingMouseInterface<'a, B, H> {
+    delegate! {
+        to self.inner {
+            pub fn protocol(&self) -> HidProtocol;
+            pub fn report_latency(&self) -> LatencyHistogram;
+            pub fn clear_report_latency(&self);
+            pub fn host_stalls(&self) -> u32;
+            pub fn host_stalled(&self) -> bool;
+            pub fn write_ready(&self) -> bool;
+        }
+    }
+
+    pub fn handler(&self) -> &H {
+        &self.handler
+    }
+
+    pub fn handler_mut(&mut self) -> &mut H {
+        &mut self.handler
+    }
+
+    pub fn write_report(&self, report: &GamingMouseReport) -> Result<(), UsbHidError> {
+        let data = report.pack().map_err(|e| {
+            error!("Error packing GamingMouseReport: {:?}", e);
+            UsbHidError::SerializationError
+        })?;
+        let mut buffer = [0; 7];
+        buffer[0] = GAMING_MOUSE_REPORT_ID;
+        buffer[1..].copy_from_slice(&data);
+        self.inner.write_report(&buffer).map(|_| ())
+    }
+
+    pub fn default_config(handler: H) -> WrappedInterfaceConfig<Self, RawInterfaceConfig<'a>, H> {
+        WrappedInterfaceConfig::new(
+            RawInterfaceBuilder::new(GAMING_MOUSE_REPORT_DESCRIPTOR)
+                .description("Gaming Mouse")
+                .in_endpoint(UsbPacketSize::Bytes8, PollInterval::millis(1))
+                .without_out_endpoint()
+                .build()
+                .unwrap(),
+            handler,
+        )
+    }
+}
+
+impl<'a, B: UsbBus, H: MouseSettingsHandler> InterfaceClass<'a> for GamingMouseInterface<'a, B, H> {
+    delegate! {
+        to self.inner{
+           fn report_descriptor(&self) -> &'_ [u8];
+           fn hid_descriptor_body(&self) -> HidDescriptorBodyBytes;
+           fn class_descriptor(&self, descriptor_type: u8, index: u8) -> Option<&'_ [u8]>;
+           fn take_wakeup_request(&self) -> bool;
+           fn id(&self) -> InterfaceNumber;
+           fn write_descriptors(&self, writer: &mut DescriptorWriter) -> usb_device::Result<()>;
+           fn get_string(&self, index: StringIndex, _lang_id: u16) -> Option<&'_ str>;
+           fn get_report(&mut self, data: &mut [u8]) -> usb_device::Result<usize>;
+           fn set_idle(&mut self, report_id: u8, value: u8);
+           fn get_idle(&self, report_id: u8) -> u8;
+           fn set_protocol(&mut self, protocol: HidProtocol) -> usb_device::Result<()>;
+           fn get_protocol(&self) -> HidProtocol;
+           fn out_endpoint_address(&self) -> Option<EndpointAddress>;
+           fn in_endpoint_address(&self) -> EndpointAddress;
+           fn endpoint_in_complete(&self, addr: EndpointAddress);
+           fn endpoint_out(&mut self);
+        }
+    }
+
+    fn reset(&mut self) {
+        self.inner.reset();
+        self.feature_responder.reset();
+    }
+
+    fn set_report(&mut self, data: &[u8]) -> usb_device::Result<()> {
+        match data {
+            [DPI_REPORT_ID, lo, hi] => self.handler.set_dpi(u16::from_le_bytes([*lo, *hi])),
+            [POLLING_RATE_REPORT_ID, lo, hi] => {
+                self.handler
+                    .set_polling_rate(u16::from_le_bytes([*lo, *hi]));
+            }
+            [PROFILE_REPORT_ID, profile] => self.handler.set_profile(*profile),
+            _ => {
+                warn!("Unsupported SetReport: {:X?}", data);
+                return Err(UsbError::ParseError);
+            }
+        }
+        Ok(())
+    }
+
+    fn get_report_by_id(
+        &mut self,
+        report_type: ReportType,
+        report_id: u8,
+        data: &mut [u8],
+    ) -> usb_device::Result<usize> {
+        let handler = &self.handler;
+        self.feature_responder
+            .get_report(
+                &mut self.inner,
+                report_type,
+                report_id,
+                data,
+                |data| match report_id {
+                    DPI_REPORT_ID => write_with_id(report_id, &handler.dpi().to_le_bytes(), data),
+                    POLLING_RATE_REPORT_ID => {
+                        write_with_id(report_id, &handler.polling_rate().to_le_bytes(), data)
+                    }
+                    PROFILE_REPORT_ID => write_with_id(report_id, &[handler.profile()], data),
+                    _ => Err(UsbError::Unsupported),
+                },
+            )
+    }
+
+    fn get_report_ack(&mut self) -> usb_device::Result<()> {
+        self.feature_responder.get_report_ack(&mut self.inner)
+    }
+}
+
+impl<'a, B: UsbBus, H: MouseSettingsHandler> WrappedInterface<'a, B, RawInterface<'a, B>, H>
+    for GamingMouseInterface<'a, B, H>
+{
+    fn new(interface: RawInterface<'a, B>, handler: H) -> Self {
+        Self {
+            inner: interface,
+            handler,
+            feature_responder: FeatureResponder::default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use packed_struct::PackedStruct;
+
+    use crate::device::gaming_mouse::*;
+
+    #[test]
+    fn pack_report() {
+        let report = GamingMouseReport {
+            buttons: 0x81,
+            x: -1000,
+            y: 1000,
+            wheel: -1,
+        };
+        assert_eq!(report.pack().unwrap(), [0x81, 0x18, 0xFC, 0xE8, 0x03, 0xFF]);
+    }
+
+    #[cfg(feature = "simulation")]
+    #[derive(Debug, Default, PartialEq, Eq)]
+    struct Settings {
+        dpi: u16,
+        polling_rate: u16,
+        profile: u8,
+    }
+
+    #[cfg(feature = "simulation")]
+    impl MouseSettingsHandler for Settings {
+        fn dpi(&self) -> u16 {
+            self.dpi
+        }
+        fn set_dpi(&mut self, dpi: u16) {
+            self.dpi = dpi;
+        }
+        fn polling_rate(&self) -> u16 {
+            self.polling_rate
+        }
+        fn set_polling_rate(&mut self, hz: u16) {
+            if matches!(hz, 125 | 250 | 500 | 1000) {
+                self.polling_rate = hz;
+            }
+        }
+        fn profile(&self) -> u8 {
+            self.profile
+        }
+        fn set_profile(&mut self, profile: u8) {
+            self.profile = profile;
+        }
+    }
+
+    #[cfg(feature = "simulation")]
+    #[test]
+    fn settings_routed_to_handler() {
+        use usb_device::bus::UsbBusAllocator;
+
+        use crate::simulation::{control_transfer, device_builder, Response, SimBus};
+
+        let usb_alloc = UsbBusAllocator::new(SimBus::new());
+        let mut mouse = UsbHidClassBuilder::new()
+            .add_interface(GamingMouse::default_config(Settings {
+                polling_rate: 1000,
+                ..Default::default()
+            }))
+            .build(&usb_alloc)
+            .unwrap();
+        let mut usb_dev = device_builder(&usb_alloc, 64).build();
+
+        // SET_REPORT (Feature) DPI 1600
+        assert_eq!(
+            control_transfer(
+                &mut usb_dev,
+                &mut [&mut mouse],
+                [0x21, 0x09, 0x02, 0x03, 0x00, 0x00, 0x03, 0x00],
+                &[0x02, 0x40, 0x06]
+            ),
+            Response::Ack
+        );
+        // SET_REPORT (Feature) polling rate 300Hz is ignored
+        assert_eq!(
+            control_transfer(
+                &mut usb_dev,
+                &mut [&mut mouse],
+                [0x21, 0x09, 0x03, 0x03, 0x00, 0x00, 0x03, 0x00],
+                &[0x03, 0x2C, 0x01]
+            ),
+            Response::Ack
+        );
+        // GET_REPORT (Feature) DPI
+        assert_eq!(
+            control_transfer(
+                &mut usb_dev,
+                &mut [&mut mouse],
+                [0xA1, 0x01, 0x02, 0x03, 0x00, 0x00, 0x03, 0x00],
+                &[]
+            ),
+            Response::Data(vec![0x02, 0x40, 0x06])
+        );
+
+        let settings = mouse
+            .interface::<GamingMouse<'_, _, Settings>, _>()
+            .handler();
+        assert_eq!((settings.dpi, settings.polling_rate), (1600, 1000));
+    }
+
+    #[cfg(feature = "simulation")]
+    #[test]
+    fn settings_saved_and_restored() {
+        use usb_device::bus::UsbBusAllocator;
+
+        use crate::interface::settings::MemorySettingsStore;
+        use crate::simulation::{control_transfer, device_builder, Response, SimBus};
+
+        let mut store = MemorySettingsStore::<3, 2>::new();
+        let usb_alloc = UsbBusAllocator::new(SimBus::new());
+        let mut mouse = UsbHidClassBuilder::new()
+            .add_interface(GamingMouse::default_config(
+                StoredMouseSettings::new(Settings::default(), &mut store).unwrap(),
+            ))
+            .build(&usb_alloc)
+            .unwrap();
+        let mut usb_dev = device_builder(&usb_alloc, 64).build();
+
+        // SET_REPORT (Feature) DPI 1600
+        assert_eq!(
+            control_transfer(
+                &mut usb_dev,
+                &mut [&mut mouse],
+                [0x21, 0x09, 0x02, 0x03, 0x00, 0x00, 0x03, 0x00],
+                &[0x02, 0x40, 0x06]
+            ),
+            Response::Ack
+        );
+        // SET_REPORT (Feature) profile 2
+        assert_eq!(
+            control_transfer(
+                &mut usb_dev,
+                &mut [&mut mouse],
+                [0x21, 0x09, 0x04, 0x03, 0x00, 0x00, 0x02, 0x00],
+                &[0x04, 0x02]
+            ),
+            Response::Ack
+        );
+        drop(mouse);
+
+        let restored = StoredMouseSettings::new(Settings::default(), &mut store).unwrap();
+        assert_eq!(
+            restored.into_inner().0,
+            Settings {
+                dpi: 1600,
+                polling_rate: 0,
+                profile: 2,
+            }
+        );
+    }
+}
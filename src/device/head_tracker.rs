@@ -0,0 +1,194 @@
+//!HID head trackers reporting head orientation as joystick axes
+//!
+//! Head tracking software such as opentrack reads the axes through its joystick input, mapping
+//! each axis to the yaw, pitch and roll of the view.
+use crate::hid_class::descriptor::HidProtocol;
+use core::default::Default;
+use delegate::delegate;
+use packed_struct::prelude::*;
+use usb_device::bus::{InterfaceNumber, StringIndex, UsbBus};
+use usb_device::class_prelude::{DescriptorWriter, EndpointAddress};
+
+use crate::hid_class::prelude::*;
+use crate::interface::duration::PollInterval;
+use crate::interface::latency::LatencyHistogram;
+use crate::interface::raw::{RawInterface, RawInterfaceConfig};
+use crate::interface::{
+    DefaultConfig, HidDescriptorBodyBytes, InterfaceClass, WrappedInterface, WrappedInterfaceConfig,
+};
+use crate::UsbHidError;
+
+/// Head tracker with yaw, pitch and roll axes
+///
+/// * Bytes 0-5 - Yaw, pitch and roll as X, Y and Z, little endian `-32767` to `32767` for
+///   `-180°` to `180°`
+#[rustfmt::skip]
+pub const HEAD_TRACKER_REPORT_DESCRIPTOR: &[u8] = &[
+    0x05, 0x01,        // Usage Page (Generic Desktop),
+    0x09, 0x04,        // Usage (Joystick),
+    0xA1, 0x01,        // Collection (Application),
+    0x09, 0x30,        //   Usage (X),
+    0x09, 0x31,        //   Usage (Y),
+    0x09, 0x32,        //   Usage (Z),
+    0x16, 0x01, 0x80,  //   Logical Minimum (-32767),
+    0x26, 0xFF, 0x7F,  //   Logical Maximum (32767),
+    0x36, 0x4C, 0xFF,  //   Physical Minimum (-180),
+    0x46, 0xB4, 0x00,  //   Physical Maximum (180),
+    0x65, 0x14,        //   Unit (Degrees),
+    0x75, 0x10,        //   Report Size (16),
+    0x95, 0x03,        //   Report Count (3),
+    0x81, 0x02,        //   Input (Data, Variable, Absolute),
+    0x65, 0x00,        //   Unit (None),
+    0xC0,              // End Collection
+];
+
+/// Head orientation, `-32767` to `32767` for `-180°` to `180°`
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Default, PackedStruct)]
+#[packed_struct(endian = "lsb", size_bytes = "6")]
+pub struct HeadTrackerReport {
+    #[packed_field]
+    pub yaw: i16,
+    #[packed_field]
+    pub pitch: i16,
+    #[packed_field]
+    pub roll: i16,
+}
+
+impl HeadTrackerReport {
+    /// Report of angles in degrees, angles outside `-180°` to `180°` are clamped
+    pub fn from_degrees(yaw: f32, pitch: f32, roll: f32) -> Self {
+        // Float to integer casts saturate
+        let axis = |degrees: f32| (degrees * (i16::MAX as f32 / 180.0)) as i16;
+        Self {
+            yaw: axis(yaw).max(-i16::MAX),
+            pitch: axis(pitch).max(-i16::MAX),
+            roll: axis(roll).max(-i16::MAX),
+        }
+    }
+}
+
+pub struct HeadTrackerInterface<'a, B: UsbBus> {
+    inner: RawInterface<'a, B>,
+}
+
+/// Head tracker interface, see [`HeadTrackerInterface`]
+pub type HeadTracker<'a, B> = HeadTrackerInterface<'a, B>;
+
+impl<'a, B: UsbBus> HeadTrackerInterface<'a, B> {
+    delegate! {
+        to self.inner {
+            pub fn protocol(&self) -> HidProtocol;
+            pub fn report_latency(&self) -> LatencyHistogram;
+            pub fn clear_report_latency(&self);
+            pub fn host_stalls(&self) -> u32;
+            pub fn host_stalled(&self) -> bool;
+            pub fn write_ready(&self) -> bool;
+        }
+    }
+
+    pub fn write_report(&self, report: &HeadTrackerReport) -> Result<(), UsbHidError> {
+        let data = report.pack().map_err(|e| {
+            error!("Error packing HeadTrackerReport: {:?}", e);
+            UsbHidError::SerializationError
+        })?;
+        self.inner.write_report(&data).map(|_| ())
+    }
+
+    pub fn default_config() -> WrappedInterfaceConfig<Self, RawInterfaceConfig<'a>> {
+        Self::default_config_with_descriptor(HEAD_TRACKER_REPORT_DESCRIPTOR)
+    }
+
+    /// Default configuration with an alternative report descriptor
+    ///
+    /// The descriptor must describe reports with the same layout as [`HeadTrackerReport`]
+    pub fn default_config_with_descriptor(
+        report_descriptor: &'a [u8],
+    ) -> WrappedInterfaceConfig<Self, RawInterfaceConfig<'a>> {
+        WrappedInterfaceConfig::new(
+            RawInterfaceBuilder::new(report_descriptor)
+                .description("Head Tracker")
+                .in_endpoint(UsbPacketSize::Bytes8, PollInterval::millis(2))
+                .without_out_endpoint()
+                .build()
+                .unwrap(),
+            (),
+        )
+    }
+}
+
+impl<'a, B: UsbBus + 'a> DefaultConfig<'a, B> for HeadTrackerInterface<'a, B> {
+    type Config = WrappedInterfaceConfig<Self, RawInterfaceConfig<'a>>;
+
+    fn default_config() -> Self::Config {
+        Self::default_config()
+    }
+}
+
+impl<'a, B: UsbBus> InterfaceClass<'a> for HeadTrackerInterface<'a, B> {
+    delegate! {
+        to self.inner{
+           fn report_descriptor(&self) -> &'_ [u8];
+           fn hid_descriptor_body(&self) -> HidDescriptorBodyBytes;
+           fn class_descriptor(&self, descriptor_type: u8, index: u8) -> Option<&'_ [u8]>;
+           fn take_wakeup_request(&self) -> bool;
+           fn id(&self) -> InterfaceNumber;
+           fn write_descriptors(&self, writer: &mut DescriptorWriter) -> usb_device::Result<()>;
+           fn get_string(&self, index: StringIndex, _lang_id: u16) -> Option<&'_ str>;
+           fn reset(&mut self);
+           fn set_report(&mut self, data: &[u8]) -> usb_device::Result<()>;
+           fn get_report(&mut self, data: &mut [u8]) -> usb_device::Result<usize>;
+           fn get_report_ack(&mut self) -> usb_device::Result<()>;
+           fn set_idle(&mut self, report_id: u8, value: u8);
+           fn get_idle(&self, report_id: u8) -> u8;
+           fn set_protocol(&mut self, protocol: HidProtocol) -> usb_device::Result<()>;
+           fn get_protocol(&self) -> HidProtocol;
+           fn out_endpoint_address(&self) -> Option<EndpointAddress>;
+           fn in_endpoint_address(&self) -> EndpointAddress;
+           fn endpoint_in_complete(&self, addr: EndpointAddress);
+           fn endpoint_out(&mut self);
+        }
+    }
+}
+
+impl<'a, B: UsbBus> WrappedInterface<'a, B, RawInterface<'a, B>> for HeadTrackerInterface<'a, B> {
+    fn new(interface: RawInterface<'a, B>, _: ()) -> Self {
+        Self { inner: interface }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use packed_struct::PackedStruct;
+
+    use crate::device::head_tracker::HeadTrackerReport;
+
+    #[test]
+    fn pack_report() {
+        let report = HeadTrackerReport {
+            yaw: -32767,
+            pitch: 0x1234,
+            roll: 0,
+        };
+        assert_eq!(report.pack().unwrap(), [0x01, 0x80, 0x34, 0x12, 0x00, 0x00]);
+    }
+
+    #[test]
+    fn report_from_degrees() {
+        assert_eq!(
+            HeadTrackerReport::from_degrees(180.0, -90.0, 0.0),
+            HeadTrackerReport {
+                yaw: 32767,
+                pitch: -16383,
+                roll: 0,
+            }
+        );
+        assert_eq!(
+            HeadTrackerReport::from_degrees(-360.0, 360.0, f32::NAN),
+            HeadTrackerReport {
+                yaw: -32767,
+                pitch: 32767,
+                roll: 0,
+            }
+        );
+    }
+}
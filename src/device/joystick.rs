@@ -0,0 +1,209 @@
+//!HID joysticks for flight and other simulation controls
+use crate::hid_class::descriptor::HidProtocol;
+use core::default::Default;
+use delegate::delegate;
+use packed_struct::prelude::*;
+use usb_device::bus::{InterfaceNumber, StringIndex, UsbBus};
+use usb_device::class_prelude::{DescriptorWriter, EndpointAddress};
+
+use crate::hid_class::prelude::*;
+use crate::interface::duration::PollInterval;
+use crate::interface::latency::LatencyHistogram;
+use crate::interface::raw::{RawInterface, RawInterfaceConfig};
+use crate::interface::{
+    DefaultConfig, HidDescriptorBodyBytes, InterfaceClass, WrappedInterface, WrappedInterfaceConfig,
+};
+use crate::page::HatSwitch;
+use crate::UsbHidError;
+
+/// HOTAS joystick with eight axes, two hat switches and 32 buttons
+///
+/// * Bytes 0-3 - Buttons 1 to 32
+/// * Bytes 4-19 - X, Y, Z, Rx, Ry, Rz, Slider and Dial axes, little endian `-32767` to `32767`
+/// * Bytes 20-21 - Hat switches, `0` (up) to `7` (up left) clockwise in 45° steps, `8` when
+///   centred
+#[rustfmt::skip]
+pub const JOYSTICK_REPORT_DESCRIPTOR: &[u8] = &[
+    0x05, 0x01,        // Usage Page (Generic Desktop),
+    0x09, 0x04,        // Usage (Joystick),
+    0xA1, 0x01,        // Collection (Application),
+    0x05, 0x09,        //   Usage Page (Buttons),
+    0x19, 0x01,        //   Usage Minimum (1),
+    0x29, 0x20,        //   Usage Maximum (32),
+    0x15, 0x00,        //   Logical Minimum (0),
+    0x25, 0x01,        //   Logical Maximum (1),
+    0x75, 0x01,        //   Report Size (1),
+    0x95, 0x20,        //   Report Count (32),
+    0x81, 0x02,        //   Input (Data, Variable, Absolute),
+    0x05, 0x01,        //   Usage Page (Generic Desktop),
+    0x09, 0x30,        //   Usage (X),
+    0x09, 0x31,        //   Usage (Y),
+    0x09, 0x32,        //   Usage (Z),
+    0x09, 0x33,        //   Usage (Rx),
+    0x09, 0x34,        //   Usage (Ry),
+    0x09, 0x35,        //   Usage (Rz),
+    0x09, 0x36,        //   Usage (Slider),
+    0x09, 0x37,        //   Usage (Dial),
+    0x16, 0x01, 0x80,  //   Logical Minimum (-32767),
+    0x26, 0xFF, 0x7F,  //   Logical Maximum (32767),
+    0x75, 0x10,        //   Report Size (16),
+    0x95, 0x08,        //   Report Count (8),
+    0x81, 0x02,        //   Input (Data, Variable, Absolute),
+    0x09, 0x39,        //   Usage (Hat Switch),
+    0x09, 0x39,        //   Usage (Hat Switch),
+    0x15, 0x00,        //   Logical Minimum (0),
+    0x25, 0x07,        //   Logical Maximum (7),
+    0x35, 0x00,        //   Physical Minimum (0),
+    0x46, 0x3B, 0x01,  //   Physical Maximum (315),
+    0x65, 0x14,        //   Unit (Degrees),
+    0x75, 0x08,        //   Report Size (8),
+    0x95, 0x02,        //   Report Count (2),
+    0x81, 0x42,        //   Input (Data, Variable, Absolute, Null State),
+    0x65, 0x00,        //   Unit (None),
+    0xC0,              // End Collection
+];
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Default, PackedStruct)]
+#[packed_struct(endian = "lsb", size_bytes = "22")]
+pub struct JoystickReport {
+    #[packed_field]
+    pub buttons: u32,
+    #[packed_field]
+    pub x: i16,
+    #[packed_field]
+    pub y: i16,
+    #[packed_field]
+    pub z: i16,
+    #[packed_field]
+    pub rx: i16,
+    #[packed_field]
+    pub ry: i16,
+    #[packed_field]
+    pub rz: i16,
+    #[packed_field]
+    pub slider: i16,
+    #[packed_field]
+    pub dial: i16,
+    #[packed_field(ty = "enum", element_size_bytes = "1")]
+    pub hat1: HatSwitch,
+    #[packed_field(ty = "enum", element_size_bytes = "1")]
+    pub hat2: HatSwitch,
+}
+
+pub struct JoystickInterface<'a, B: UsbBus> {
+    inner: RawInterface<'a, B>,
+}
+
+/// Joystick interface, see [`JoystickInterface`]
+pub type Joystick<'a, B> = JoystickInterface<'a, B>;
+
+impl<'a, B: UsbBus> JoystickInterface<'a, B> {
+    delegate! {
+        to self.inner {
+            pub fn protocol(&self) -> HidProtocol;
+            pub fn report_latency(&self) -> LatencyHistogram;
+            pub fn clear_report_latency(&self);
+            pub fn host_stalls(&self) -> u32;
+            pub fn host_stalled(&self) -> bool;
+            pub fn write_ready(&self) -> bool;
+        }
+    }
+
+    pub fn write_report(&self, report: &JoystickReport) -> Result<(), UsbHidError> {
+        let data = report.pack().map_err(|e| {
+            error!("Error packing JoystickReport: {:?}", e);
+            UsbHidError::SerializationError
+        })?;
+        self.inner.write_report(&data).map(|_| ())
+    }
+
+    pub fn default_config() -> WrappedInterfaceConfig<Self, RawInterfaceConfig<'a>> {
+        Self::default_config_with_descriptor(JOYSTICK_REPORT_DESCRIPTOR)
+    }
+
+    /// Default configuration with an alternative report descriptor
+    ///
+    /// The descriptor must describe reports with the same layout as [`JoystickReport`]
+    pub fn default_config_with_descriptor(
+        report_descriptor: &'a [u8],
+    ) -> WrappedInterfaceConfig<Self, RawInterfaceConfig<'a>> {
+        WrappedInterfaceConfig::new(
+            RawInterfaceBuilder::new(report_descriptor)
+                .description("Joystick")
+                .in_endpoint(UsbPacketSize::Bytes32, PollInterval::millis(10))
+                .without_out_endpoint()
+                .build()
+                .unwrap(),
+            (),
+        )
+    }
+}
+
+impl<'a, B: UsbBus + 'a> DefaultConfig<'a, B> for JoystickInterface<'a, B> {
+    type Config = WrappedInterfaceConfig<Self, RawInterfaceConfig<'a>>;
+
+    fn default_config() -> Self::Config {
+        Self::default_config()
+    }
+}
+
+impl<'a, B: UsbBus> InterfaceClass<'a> for JoystickInterface<'a, B> {
+    delegate! {
+        to self.inner{
+           fn report_descriptor(&self) -> &'_ [u8];
+           fn hid_descriptor_body(&self) -> HidDescriptorBodyBytes;
+           fn class_descriptor(&self, descriptor_type: u8, index: u8) -> Option<&'_ [u8]>;
+           fn take_wakeup_request(&self) -> bool;
+           fn id(&self) -> InterfaceNumber;
+           fn write_descriptors(&self, writer: &mut DescriptorWriter) -> usb_device::Result<()>;
+           fn get_string(&self, index: StringIndex, _lang_id: u16) -> Option<&'_ str>;
+           fn reset(&mut self);
+           fn set_report(&mut self, data: &[u8]) -> usb_device::Result<()>;
+           fn get_report(&mut self, data: &mut [u8]) -> usb_device::Result<usize>;
+           fn get_report_ack(&mut self) -> usb_device::Result<()>;
+           fn set_idle(&mut self, report_id: u8, value: u8);
+           fn get_idle(&self, report_id: u8) -> u8;
+           fn set_protocol(&mut self, protocol: HidProtocol) -> usb_device::Result<()>;
+           fn get_protocol(&self) -> HidProtocol;
+           fn out_endpoint_address(&self) -> Option<EndpointAddress>;
+           fn in_endpoint_address(&self) -> EndpointAddress;
+           fn endpoint_in_complete(&self, addr: EndpointAddress);
+           fn endpoint_out(&mut self);
+        }
+    }
+}
+
+impl<'a, B: UsbBus> WrappedInterface<'a, B, RawInterface<'a, B>> for JoystickInterface<'a, B> {
+    fn new(interface: RawInterface<'a, B>, _: ()) -> Self {
+        Self { inner: interface }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use packed_struct::PackedStruct;
+
+    use crate::device::joystick::JoystickReport;
+    use crate::page::HatSwitch;
+
+    #[test]
+    fn pack_report() {
+        let report = JoystickReport {
+            buttons: 0x8000_0001,
+            x: -32767,
+            y: 32767,
+            dial: 0x1234,
+            hat1: HatSwitch::N,
+            hat2: HatSwitch::SW,
+            ..Default::default()
+        };
+        let data = report.pack().unwrap();
+        assert_eq!(data[..4], [0x01, 0x00, 0x00, 0x80]);
+        assert_eq!(data[4..8], [0x01, 0x80, 0xFF, 0x7F]);
+        assert_eq!(data[18..], [0x34, 0x12, 0x00, 0x05]);
+        assert_eq!(
+            JoystickReport::default().pack().unwrap()[20..],
+            [0x08, 0x08]
+        );
+    }
+}
@@ -1,103 +1,569 @@
 //!HID keyboards
 
+use core::cell::Cell;
 use delegate::delegate;
-use fugit::ExtU32;
+use fugit::{ExtU32, MillisDurationU32};
 use packed_struct::prelude::*;
 use usb_device::class_prelude::*;
 use usb_device::UsbError;
 
 use crate::hid_class::prelude::*;
+use crate::interface::duration::{IdleDuration, PollInterval};
+use crate::interface::latency::LatencyHistogram;
 use crate::interface::managed::{ManagedInterface, ManagedInterfaceConfig};
-use crate::interface::{InterfaceClass, WrappedInterface, WrappedInterfaceConfig};
+use crate::interface::packet::MAX_CONTROL_REPORT_SIZE;
+use crate::interface::{
+    DefaultConfig, HidDescriptorBodyBytes, InterfaceClass, WrappedInterface, WrappedInterfaceConfig,
+};
 use crate::page::Keyboard;
 use crate::UsbHidError;
 
 /// Interface implementing the HID boot keyboard specification
 ///
 /// **Note:** This is a managed interfaces that support HID idle, [BootKeyboardInterface::tick()] must be called every 1ms/at 1kHz.
-pub struct BootKeyboardInterface<'a, B: UsbBus> {
-    inner: ManagedInterface<'a, B, BootKeyboardReport>,
+///
+/// LED reports are held in the inner interface's output report queue of `OUT_QUEUE` reports, see
+/// [`BootKeyboardInterface::read_report`], and input reports in its input report queue of `IN_QUEUE` reports.
+pub struct BootKeyboardInterface<
+    'a,
+    B: UsbBus,
+    const OUT_QUEUE: usize = 0,
+    const IN_QUEUE: usize = 0,
+> {
+    inner: ManagedInterface<'a, B, BootKeyboardReport, (), 0, OUT_QUEUE, IN_QUEUE>,
+    config: KeyboardConfig,
+    leds: LedsReceiver,
+    typematic: Cell<TypematicState<BootKeyboardReport>>,
 }
 
-impl<'a, B> BootKeyboardInterface<'a, B>
+/// Boot keyboard interface, see [`BootKeyboardInterface`]
+pub type BootKeyboard<'a, B, const OUT_QUEUE: usize = 0, const IN_QUEUE: usize = 0> =
+    BootKeyboardInterface<'a, B, OUT_QUEUE, IN_QUEUE>;
+
+impl<'a, B, const OUT_QUEUE: usize, const IN_QUEUE: usize>
+    BootKeyboardInterface<'a, B, OUT_QUEUE, IN_QUEUE>
 where
     B: UsbBus,
 {
     delegate! {
         to self.inner {
-            /// Call every 1ms / at 1 KHz
-            pub fn tick(&self) -> Result<(), UsbHidError>;
+            pub fn protocol(&self) -> HidProtocol;
+            pub fn report_latency(&self) -> LatencyHistogram;
+            pub fn clear_report_latency(&self);
+            pub fn host_stalls(&self) -> u32;
+            pub fn host_stalled(&self) -> bool;
+            pub fn write_ready(&self) -> bool;
+            pub fn flush(&self) -> Result<(), UsbHidError>;
         }
     }
 
     pub fn write_report(&self, report: &BootKeyboardReport) -> Result<(), UsbHidError> {
-        self.inner
-            .write_report(report)
-            .map(|_| ())
+        self.inner.write_report(report)?;
+        self.update_typematic(|t| t.report_written(*report));
+        Ok(())
+    }
+
+    /// Call every 1ms / at 1 KHz
+    pub fn tick(&self) -> Result<(), UsbHidError> {
+        self.tick_with(1.millis())
+    }
+
+    /// Advances the idle and key repeat timers by `elapsed`, see
+    /// [`ManagedInterface::tick_with`]
+    pub fn tick_with(&self, elapsed: MillisDurationU32) -> Result<(), UsbHidError> {
+        let idle = self.inner.tick_with(elapsed);
+        if let Some(report) = self.update_typematic(|t| t.tick_with(elapsed)) {
+            self.inner.write_input(&report)?;
+            self.update_typematic(TypematicState::repeat_written);
+        }
+        idle
+    }
+
+    fn update_typematic<T>(
+        &self,
+        f: impl FnOnce(&mut TypematicState<BootKeyboardReport>) -> T,
+    ) -> T {
+        let mut typematic = self.typematic.get();
+        let result = f(&mut typematic);
+        self.typematic.set(typematic);
+        result
     }
 
-    pub fn read_report(&self) -> usb_device::Result<KeyboardLedsReport> {
-        let data = &mut [0];
-        match self.inner.read_report(data) {
+    /// The last LED report received from the host, [`UsbHidError::WouldBlock`] if it has already
+    /// been read
+    ///
+    /// With an output report queue, see
+    /// [`RawInterfaceBuilder::out_report_queue`](crate::interface::raw::RawInterfaceBuilder::out_report_queue),
+    /// each LED report received is read in turn, oldest first.
+    pub fn read_report(&self) -> Result<KeyboardLedsReport, UsbHidError> {
+        if OUT_QUEUE > 0 {
+            let mut data = [0; 1];
+            self.inner.read_queued_packet(&mut data)?;
+            return KeyboardLedsReport::unpack(&data).map_err(|e| {
+                error!("Error unpacking KeyboardLedsReport: {:?}", e);
+                UsbHidError::SerializationError
+            });
+        }
+        match self.receive_leds() {
+            Ok(()) | Err(UsbHidError::WouldBlock) => self.leds.read(),
             Err(e) => Err(e),
-            Ok(_) => match KeyboardLedsReport::unpack(data) {
-                Ok(r) => Ok(r),
-                Err(_) => Err(UsbError::ParseError),
-            },
         }
     }
 
+    /// The LED state, if it changed since the last call
+    ///
+    /// LED reports are received while the [`UsbHidClass`](crate::hid_class::UsbHidClass) is
+    /// polled, whether sent to the OUT endpoint or with a `SET_REPORT` request.
+    pub fn leds_changed(&self) -> Option<KeyboardLedsReport> {
+        self.leds.changed()
+    }
+
+    /// The LED state set by the last LED report received from the host
+    pub fn leds(&self) -> KeyboardLedsReport {
+        self.leds.leds()
+    }
+
+    /// Statistics about LED output reports received from the host
+    pub fn leds_report_statistics(&self) -> LedsReportStatistics {
+        self.leds.statistics()
+    }
+
+    fn receive_leds(&self) -> Result<(), UsbHidError> {
+        //Like SET_REPORT, longer OUT packets are rejected with a ReportTooLong error
+        let mut data = [0; MAX_CONTROL_REPORT_SIZE];
+        let n = self.inner.read_unqueued_packet(&mut data)?;
+        Ok(self.receive_leds_report(&data[..n])?)
+    }
+
+    fn receive_leds_report(&self, data: &[u8]) -> usb_device::Result<()> {
+        let leds = self.leds.receive(data, self.config.leds_parsing)?;
+        if OUT_QUEUE > 0 {
+            let data = leds.pack().map_err(|_| UsbError::ParseError)?;
+            self.inner.queue_output_report(&data)?;
+        }
+        Ok(())
+    }
+}
+
+impl<'a, B> BootKeyboardInterface<'a, B>
+where
+    B: UsbBus,
+{
     pub fn default_config(
-    ) -> WrappedInterfaceConfig<Self, ManagedInterfaceConfig<'a, BootKeyboardReport>> {
+    ) -> WrappedInterfaceConfig<Self, ManagedInterfaceConfig<'a, BootKeyboardReport>, KeyboardConfig>
+    {
+        Self::default_config_with_descriptor(BOOT_KEYBOARD_REPORT_DESCRIPTOR)
+    }
+
+    /// Default configuration with an alternative report descriptor
+    ///
+    /// The descriptor must describe reports with the same layout as [`BootKeyboardReport`]
+    pub fn default_config_with_descriptor(
+        report_descriptor: &'a [u8],
+    ) -> WrappedInterfaceConfig<Self, ManagedInterfaceConfig<'a, BootKeyboardReport>, KeyboardConfig>
+    {
         WrappedInterfaceConfig::new(
             ManagedInterfaceConfig::new(
-                RawInterfaceBuilder::new(BOOT_KEYBOARD_REPORT_DESCRIPTOR)
+                RawInterfaceBuilder::new(report_descriptor)
                     .boot_device(InterfaceProtocol::Keyboard)
                     .description("Keyboard")
-                    .idle_default(500.millis())
-                    .unwrap()
-                    .in_endpoint(UsbPacketSize::Bytes8, 10.millis())
-                    .unwrap()
+                    .idle_default(IdleDuration::millis(500))
+                    .in_endpoint(UsbPacketSize::Bytes8, PollInterval::millis(10))
                     //.without_out_endpoint()
                     //Shouldn't require a dedicated out endpoint, but leds are flaky without it
-                    .with_out_endpoint(UsbPacketSize::Bytes8, 100.millis())
-                    .unwrap()
-                    .build(),
+                    .with_out_endpoint(UsbPacketSize::Bytes8, PollInterval::millis(100))
+                    .build()
+                    .unwrap(),
             ),
-            (),
+            KeyboardConfig::default(),
         )
     }
 }
 
-impl<'a, B> InterfaceClass<'a> for BootKeyboardInterface<'a, B>
+impl<'a, B> DefaultConfig<'a, B> for BootKeyboardInterface<'a, B>
+where
+    B: UsbBus + 'a,
+{
+    type Config = WrappedInterfaceConfig<
+        Self,
+        ManagedInterfaceConfig<'a, BootKeyboardReport>,
+        KeyboardConfig,
+    >;
+
+    fn default_config() -> Self::Config {
+        Self::default_config()
+    }
+}
+
+impl<'a, B, const OUT_QUEUE: usize, const IN_QUEUE: usize> InterfaceClass<'a>
+    for BootKeyboardInterface<'a, B, OUT_QUEUE, IN_QUEUE>
 where
     B: UsbBus,
 {
     delegate! {
         to self.inner{
            fn report_descriptor(&self) -> &'_ [u8];
+           fn hid_descriptor_body(&self) -> HidDescriptorBodyBytes;
+           fn class_descriptor(&self, descriptor_type: u8, index: u8) -> Option<&'_ [u8]>;
+           fn take_wakeup_request(&self) -> bool;
            fn id(&self) -> InterfaceNumber;
            fn write_descriptors(&self, writer: &mut DescriptorWriter) -> usb_device::Result<()>;
            fn get_string(&self, index: StringIndex, _lang_id: u16) -> Option<&'_ str>;
-           fn reset(&mut self);
-           fn set_report(&mut self, data: &[u8]) -> usb_device::Result<()>;
            fn get_report(&mut self, data: &mut [u8]) -> usb_device::Result<usize>;
            fn get_report_ack(&mut self) -> usb_device::Result<()>;
            fn set_idle(&mut self, report_id: u8, value: u8);
            fn get_idle(&self, report_id: u8) -> u8;
-           fn set_protocol(&mut self, protocol: HidProtocol);
+           fn set_protocol(&mut self, protocol: HidProtocol) -> usb_device::Result<()>;
            fn get_protocol(&self) -> HidProtocol;
+           fn out_endpoint_address(&self) -> Option<EndpointAddress>;
+           fn in_endpoint_address(&self) -> EndpointAddress;
+           fn endpoint_in_complete(&self, addr: EndpointAddress);
         }
     }
+
+    fn set_report(&mut self, data: &[u8]) -> usb_device::Result<()> {
+        self.receive_leds_report(data)
+    }
+
+    fn endpoint_out(&mut self) {
+        self.receive_leds().ok();
+    }
+
+    fn reset(&mut self) {
+        self.inner.reset();
+        self.typematic = Cell::new(TypematicState::new(self.config.typematic));
+    }
 }
 
-impl<'a, B> WrappedInterface<'a, B, ManagedInterface<'a, B, BootKeyboardReport>>
-    for BootKeyboardInterface<'a, B>
+impl<'a, B, const OUT_QUEUE: usize, const IN_QUEUE: usize>
+    WrappedInterface<
+        'a,
+        B,
+        ManagedInterface<'a, B, BootKeyboardReport, (), 0, OUT_QUEUE, IN_QUEUE>,
+        KeyboardConfig,
+    > for BootKeyboardInterface<'a, B, OUT_QUEUE, IN_QUEUE>
 where
     B: UsbBus,
 {
-    fn new(interface: ManagedInterface<'a, B, BootKeyboardReport>, _: ()) -> Self {
-        Self { inner: interface }
+    fn new(
+        interface: ManagedInterface<'a, B, BootKeyboardReport, (), 0, OUT_QUEUE, IN_QUEUE>,
+        config: KeyboardConfig,
+    ) -> Self {
+        Self {
+            inner: interface,
+            config,
+            leds: Default::default(),
+            typematic: Cell::new(TypematicState::new(config.typematic)),
+        }
+    }
+}
+
+/// Configuration of the keyboard interfaces, set on [`WrappedInterfaceConfig::config`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub struct KeyboardConfig {
+    pub leds_parsing: OutputReportParsing,
+    /// Device side key repeat, disabled by default as hosts repeat held keys themselves
+    ///
+    /// Only applied by the managed [`BootKeyboardInterface`], [`NKROBootKeyboardInterface`] and
+    /// [`NKROKeyboardInterface`].
+    pub typematic: Option<Typematic>,
+}
+
+/// Device side key repeat of the last key pressed while it is held
+///
+/// The key is repeated by sending a report with it released followed by the report with it
+/// pressed again, for hosts and applications that don't repeat held keys.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Typematic {
+    /// Time from the key being pressed to the first repeat
+    pub delay: MillisDurationU32,
+    /// Time between repeats
+    pub interval: MillisDurationU32,
+}
+
+impl Default for Typematic {
+    fn default() -> Self {
+        Self {
+            delay: 500.millis(),
+            interval: 33.millis(),
+        }
+    }
+}
+
+/// Keyboard reports supporting key repeat
+trait TypematicReport: Copy + Default {
+    /// A key pressed in `self` that is not pressed in `previous`, ignoring modifiers
+    fn newly_pressed(&self, previous: &Self) -> Option<Keyboard>;
+    fn contains(&self, key: Keyboard) -> bool;
+    /// The report with `key` released
+    fn without(&self, key: Keyboard) -> Self;
+}
+
+fn is_repeatable(key: Keyboard) -> bool {
+    !matches!(
+        key,
+        Keyboard::NoEventIndicated
+            | Keyboard::ErrorRollOver
+            | Keyboard::POSTFail
+            | Keyboard::ErrorUndefine
+    )
+}
+
+fn without_key(keys: &[Keyboard; 6], key: Keyboard) -> [Keyboard; 6] {
+    let mut remaining = [Keyboard::NoEventIndicated; 6];
+    for (slot, k) in remaining.iter_mut().zip(keys.iter().filter(|&&k| k != key)) {
+        *slot = *k;
+    }
+    remaining
+}
+
+impl TypematicReport for BootKeyboardReport {
+    fn newly_pressed(&self, previous: &Self) -> Option<Keyboard> {
+        self.keys
+            .iter()
+            .copied()
+            .filter(|&k| is_repeatable(k))
+            .rfind(|k| !previous.keys.contains(k))
+    }
+
+    fn contains(&self, key: Keyboard) -> bool {
+        self.keys.contains(&key)
+    }
+
+    fn without(&self, key: Keyboard) -> Self {
+        Self {
+            keys: without_key(&self.keys, key),
+            ..*self
+        }
+    }
+}
+
+impl TypematicReport for NKROBootKeyboardReport {
+    fn newly_pressed(&self, previous: &Self) -> Option<Keyboard> {
+        (0..=u8::MAX)
+            .rev()
+            .map(Keyboard::from)
+            .filter(|&k| is_repeatable(k) && (k as u8) < 0xE0)
+            .find(|&k| self.contains(k) && !previous.contains(k))
+    }
+
+    fn contains(&self, key: Keyboard) -> bool {
+        let usage = key as usize;
+        self.nkro_keys
+            .get(usage / 8)
+            .is_some_and(|b| b & (1 << (usage % 8)) != 0)
+            || self.boot_keys.contains(&key)
+    }
+
+    fn without(&self, key: Keyboard) -> Self {
+        let mut report = Self {
+            boot_keys: without_key(&self.boot_keys, key),
+            ..*self
+        };
+        let usage = key as usize;
+        if let Some(b) = report.nkro_keys.get_mut(usage / 8) {
+            *b &= !(1 << (usage % 8));
+        }
+        report
+    }
+}
+
+impl TypematicReport for NKROKeyboardReport {
+    fn newly_pressed(&self, previous: &Self) -> Option<Keyboard> {
+        (0..=u8::MAX)
+            .rev()
+            .map(Keyboard::from)
+            .filter(|&k| is_repeatable(k) && (k as u8) < 0xE0)
+            .find(|&k| self.contains(k) && !previous.contains(k))
+    }
+
+    fn contains(&self, key: Keyboard) -> bool {
+        let usage = key as usize;
+        self.nkro_keys
+            .get(usage / 8)
+            .is_some_and(|b| b & (1 << (usage % 8)) != 0)
+    }
+
+    fn without(&self, key: Keyboard) -> Self {
+        let mut report = *self;
+        let usage = key as usize;
+        if let Some(b) = report.nkro_keys.get_mut(usage / 8) {
+            *b &= !(1 << (usage % 8));
+        }
+        report
+    }
+}
+
+/// Key repeat state of a keyboard interface
+#[derive(Clone, Copy, Debug)]
+struct TypematicState<R> {
+    config: Option<Typematic>,
+    report: R,
+    key: Option<Keyboard>,
+    repeating: bool,
+    released: bool,
+    elapsed: MillisDurationU32,
+}
+
+impl<R: TypematicReport> TypematicState<R> {
+    fn new(config: Option<Typematic>) -> Self {
+        Self {
+            config,
+            report: R::default(),
+            key: None,
+            repeating: false,
+            released: false,
+            elapsed: 0.millis(),
+        }
+    }
+
+    fn report_written(&mut self, report: R) {
+        if let Some(key) = report.newly_pressed(&self.report) {
+            self.key = Some(key);
+            self.repeating = false;
+            self.elapsed = 0.millis();
+        } else if self.key.is_some_and(|k| !report.contains(k)) {
+            self.key = None;
+        }
+        self.report = report;
+        self.released = false;
+    }
+
+    #[cfg(test)]
+    fn tick(&mut self) -> Option<R> {
+        self.tick_with(1.millis())
+    }
+
+    /// The report to send to repeat the key, if a repeat is due after `elapsed`
+    fn tick_with(&mut self, elapsed: MillisDurationU32) -> Option<R> {
+        let config = self.config?;
+        let key = self.key?;
+        if self.released {
+            return Some(self.report);
+        }
+
+        self.elapsed = self.elapsed.checked_add(elapsed).unwrap_or(self.elapsed);
+        let due = if self.repeating {
+            config.interval
+        } else {
+            config.delay
+        };
+        (self.elapsed >= due).then(|| self.report.without(key))
+    }
+
+    /// The report returned by [`TypematicState::tick`] was sent
+    fn repeat_written(&mut self) {
+        if self.released {
+            self.repeating = true;
+            self.elapsed = 0.millis();
+        }
+        self.released = !self.released;
+    }
+}
+
+/// Handling of output reports that don't exactly match the report descriptor
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum OutputReportParsing {
+    /// Reject reports with trailing bytes or set padding bits
+    Strict,
+    /// Ignore trailing bytes, as sent by hosts using full 8 byte OUT packets, and padding bits
+    #[default]
+    Tolerant,
+}
+
+/// Counts of LED output reports received, including those not matching the report descriptor
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub struct LedsReportStatistics {
+    pub received: u32,
+    /// Reports longer than one byte
+    pub oversized: u32,
+    /// Reports with any of the padding bits set
+    pub padding_set: u32,
+    pub rejected: u32,
+}
+
+pub(crate) fn parse_leds_report(
+    data: &[u8],
+    parsing: OutputReportParsing,
+    statistics: &Cell<LedsReportStatistics>,
+) -> usb_device::Result<KeyboardLedsReport> {
+    const PADDING_MASK: u8 = 0xE0;
+
+    let mut stats = statistics.get();
+    stats.received = stats.received.wrapping_add(1);
+
+    let result = match data.first() {
+        None => Err(UsbError::ParseError),
+        Some(&leds) => {
+            let oversized = data.len() > 1;
+            let padding_set = leds & PADDING_MASK != 0;
+            if oversized {
+                stats.oversized = stats.oversized.wrapping_add(1);
+                warn!("LED report has {:X} trailing bytes", data.len() - 1);
+            }
+            if padding_set {
+                stats.padding_set = stats.padding_set.wrapping_add(1);
+                warn!("LED report has padding bits set: {:X}", leds);
+            }
+
+            if parsing == OutputReportParsing::Strict && (oversized || padding_set) {
+                Err(UsbError::ParseError)
+            } else {
+                KeyboardLedsReport::unpack(&[leds & !PADDING_MASK])
+                    .map_err(|_| UsbError::ParseError)
+            }
+        }
+    };
+
+    if result.is_err() {
+        stats.rejected = stats.rejected.wrapping_add(1);
+    }
+    statistics.set(stats);
+    result
+}
+
+/// LED output reports received by a keyboard interface
+#[derive(Debug, Default)]
+pub(crate) struct LedsReceiver {
+    statistics: Cell<LedsReportStatistics>,
+    leds: Cell<KeyboardLedsReport>,
+    unread: Cell<bool>,
+    changed: Cell<bool>,
+}
+
+impl LedsReceiver {
+    pub(crate) fn receive(
+        &self,
+        data: &[u8],
+        parsing: OutputReportParsing,
+    ) -> usb_device::Result<KeyboardLedsReport> {
+        let leds = parse_leds_report(data, parsing, &self.statistics)?;
+        if leds != self.leds.get() {
+            self.changed.set(true);
+        }
+        self.leds.set(leds);
+        self.unread.set(true);
+        Ok(leds)
+    }
+
+    pub(crate) fn read(&self) -> Result<KeyboardLedsReport, UsbHidError> {
+        if self.unread.replace(false) {
+            Ok(self.leds.get())
+        } else {
+            Err(UsbHidError::WouldBlock)
+        }
+    }
+
+    pub(crate) fn changed(&self) -> Option<KeyboardLedsReport> {
+        self.changed.replace(false).then(|| self.leds.get())
+    }
+
+    pub(crate) fn leds(&self) -> KeyboardLedsReport {
+        self.leds.get()
+    }
+
+    pub(crate) fn statistics(&self) -> LedsReportStatistics {
+        self.statistics.get()
     }
 }
 
@@ -117,6 +583,119 @@ pub struct KeyboardLedsReport {
     pub kana: bool,
 }
 
+/// Single authoritative LED state for composite devices with several keyboard interfaces
+///
+/// Hosts may send LED output reports to any keyboard interface, the most recent report received
+/// on any interface is taken as the current state.
+///
+/// ```
+/// # use usbd_human_interface_device::device::keyboard::{KeyboardLedsReport, KeyboardLedsSync};
+/// # use usbd_human_interface_device::UsbHidError;
+/// # fn keyboard_read_report() -> Result<KeyboardLedsReport, UsbHidError> { Err(UsbHidError::WouldBlock) }
+/// # fn keypad_read_report() -> Result<KeyboardLedsReport, UsbHidError> { Ok(KeyboardLedsReport { num_lock: true, ..Default::default() }) }
+/// let mut leds = KeyboardLedsSync::default();
+/// let changed = leds.update(keyboard_read_report()).unwrap() | leds.update(keypad_read_report()).unwrap();
+/// assert!(changed);
+/// assert!(leds.leds().num_lock);
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub struct KeyboardLedsSync {
+    leds: KeyboardLedsReport,
+}
+
+impl KeyboardLedsSync {
+    /// Merge the result of a keyboard interface's `read_report()`
+    ///
+    /// Returns `true` if the LED state changed. [`UsbHidError::WouldBlock`] is treated as no new
+    /// report, other errors are returned.
+    pub fn update(
+        &mut self,
+        report: Result<KeyboardLedsReport, UsbHidError>,
+    ) -> Result<bool, UsbHidError> {
+        match report {
+            Ok(leds) => {
+                let changed = leds != self.leds;
+                self.leds = leds;
+                Ok(changed)
+            }
+            Err(UsbHidError::WouldBlock) => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+
+    pub fn leds(&self) -> KeyboardLedsReport {
+        self.leds
+    }
+}
+
+/// Set of currently held keys, for building keyboard reports from key press and release events
+///
+/// ```
+/// # use usbd_human_interface_device::device::keyboard::KeyState;
+/// # use usbd_human_interface_device::page::Keyboard;
+/// let mut keys = KeyState::default();
+/// keys.press(Keyboard::LeftShift);
+/// keys.press(Keyboard::A);
+/// keys.release(Keyboard::LeftShift);
+/// assert_eq!(keys.boot_report().keys[0], Keyboard::A);
+/// assert!(!keys.boot_report().left_shift);
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub struct KeyState {
+    held: [u8; 32],
+}
+
+impl KeyState {
+    /// Returns `true` if the key was not already held
+    pub fn press(&mut self, key: Keyboard) -> bool {
+        let (byte, bit) = Self::position(key);
+        let pressed = self.held[byte] & bit == 0;
+        self.held[byte] |= bit;
+        pressed
+    }
+
+    /// Returns `true` if the key was held
+    pub fn release(&mut self, key: Keyboard) -> bool {
+        let (byte, bit) = Self::position(key);
+        let released = self.held[byte] & bit != 0;
+        self.held[byte] &= !bit;
+        released
+    }
+
+    pub fn release_all(&mut self) {
+        self.held = [0; 32];
+    }
+
+    pub fn is_pressed(&self, key: Keyboard) -> bool {
+        let (byte, bit) = Self::position(key);
+        self.held[byte] & bit != 0
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.held.iter().all(|&b| b == 0)
+    }
+
+    /// Held keys in usage order
+    pub fn keys(&self) -> impl Iterator<Item = Keyboard> + '_ {
+        (0..=u8::MAX)
+            .filter(|&usage| self.held[usage as usize / 8] & (1 << (usage % 8)) != 0)
+            .map(Keyboard::from)
+    }
+
+    pub fn boot_report(&self) -> BootKeyboardReport {
+        BootKeyboardReport::new(self.keys())
+    }
+
+    pub fn nkro_report(&self) -> NKROBootKeyboardReport {
+        NKROBootKeyboardReport::new(self.keys())
+    }
+
+    fn position(key: Keyboard) -> (usize, u8) {
+        let usage = u8::from(key);
+        (usage as usize / 8, 1 << (usage % 8))
+    }
+}
+
 /// Report implementing the HID boot keyboard specification
 #[derive(Clone, Copy, Debug, Eq, PartialEq, Default, PackedStruct)]
 #[packed_struct(endian = "lsb", bit_numbering = "msb0", size_bytes = "8")]
@@ -394,88 +973,249 @@ impl NKROBootKeyboardReport {
 
 /// Interface implementing a NKRO keyboard compatible with the HID boot keyboard specification
 ///
+/// In boot protocol only the boot keyboard report, the first 8 bytes of [`NKROBootKeyboardReport`],
+/// is sent.
+///
 /// **Note:** This is a managed interfaces that support HID idle, [NKROBootKeyboardInterface::tick()] must be called every 1ms/ at 1kHz.
-pub struct NKROBootKeyboardInterface<'a, B: UsbBus> {
-    inner: ManagedInterface<'a, B, NKROBootKeyboardReport>,
+///
+/// LED reports are held in the inner interface's output report queue of `OUT_QUEUE` reports, see
+/// [`NKROBootKeyboardInterface::read_report`], and input reports in its input report queue of `IN_QUEUE` reports.
+pub struct NKROBootKeyboardInterface<
+    'a,
+    B: UsbBus,
+    const OUT_QUEUE: usize = 0,
+    const IN_QUEUE: usize = 0,
+> {
+    inner: ManagedInterface<'a, B, NKROBootKeyboardReport, (), 0, OUT_QUEUE, IN_QUEUE>,
+    config: KeyboardConfig,
+    leds: LedsReceiver,
+    typematic: Cell<TypematicState<NKROBootKeyboardReport>>,
 }
 
-impl<'a, B> NKROBootKeyboardInterface<'a, B>
+/// NKRO boot compatible keyboard interface, see [`NKROBootKeyboardInterface`]
+pub type NKROBootKeyboard<'a, B, const OUT_QUEUE: usize = 0, const IN_QUEUE: usize = 0> =
+    NKROBootKeyboardInterface<'a, B, OUT_QUEUE, IN_QUEUE>;
+
+impl<'a, B, const OUT_QUEUE: usize, const IN_QUEUE: usize>
+    NKROBootKeyboardInterface<'a, B, OUT_QUEUE, IN_QUEUE>
 where
     B: UsbBus,
 {
     delegate! {
         to self.inner {
-            /// Call every 1ms / at 1 KHz
-            pub fn tick(&self) -> Result<(), UsbHidError>;
+            pub fn protocol(&self) -> HidProtocol;
+            pub fn report_latency(&self) -> LatencyHistogram;
+            pub fn clear_report_latency(&self);
+            pub fn host_stalls(&self) -> u32;
+            pub fn host_stalled(&self) -> bool;
+            pub fn write_ready(&self) -> bool;
+            pub fn flush(&self) -> Result<(), UsbHidError>;
         }
     }
 
     pub fn write_report(&self, report: &NKROBootKeyboardReport) -> Result<(), UsbHidError> {
-        self.inner
-            .write_report(report)
-            .map(|_| ())
+        self.inner.write_report(report)?;
+        self.update_typematic(|t| t.report_written(*report));
+        Ok(())
+    }
+
+    /// Call every 1ms / at 1 KHz
+    pub fn tick(&self) -> Result<(), UsbHidError> {
+        self.tick_with(1.millis())
+    }
+
+    /// Advances the idle and key repeat timers by `elapsed`, see
+    /// [`ManagedInterface::tick_with`]
+    pub fn tick_with(&self, elapsed: MillisDurationU32) -> Result<(), UsbHidError> {
+        let idle = self.inner.tick_with(elapsed);
+        if let Some(report) = self.update_typematic(|t| t.tick_with(elapsed)) {
+            self.inner.write_input(&report)?;
+            self.update_typematic(TypematicState::repeat_written);
+        }
+        idle
+    }
+
+    fn update_typematic<T>(
+        &self,
+        f: impl FnOnce(&mut TypematicState<NKROBootKeyboardReport>) -> T,
+    ) -> T {
+        let mut typematic = self.typematic.get();
+        let result = f(&mut typematic);
+        self.typematic.set(typematic);
+        result
     }
 
-    pub fn read_report(&self) -> usb_device::Result<KeyboardLedsReport> {
-        let data = &mut [0];
-        match self.inner.read_report(data) {
+    /// The last LED report received from the host, [`UsbHidError::WouldBlock`] if it has already
+    /// been read
+    ///
+    /// With an output report queue, see
+    /// [`RawInterfaceBuilder::out_report_queue`](crate::interface::raw::RawInterfaceBuilder::out_report_queue),
+    /// each LED report received is read in turn, oldest first.
+    pub fn read_report(&self) -> Result<KeyboardLedsReport, UsbHidError> {
+        if OUT_QUEUE > 0 {
+            let mut data = [0; 1];
+            self.inner.read_queued_packet(&mut data)?;
+            return KeyboardLedsReport::unpack(&data).map_err(|e| {
+                error!("Error unpacking KeyboardLedsReport: {:?}", e);
+                UsbHidError::SerializationError
+            });
+        }
+        match self.receive_leds() {
+            Ok(()) | Err(UsbHidError::WouldBlock) => self.leds.read(),
             Err(e) => Err(e),
-            Ok(_) => match KeyboardLedsReport::unpack(data) {
-                Ok(r) => Ok(r),
-                Err(_) => Err(UsbError::ParseError),
-            },
         }
     }
 
-    pub fn default_config(
-    ) -> WrappedInterfaceConfig<Self, ManagedInterfaceConfig<'a, NKROBootKeyboardReport>> {
+    /// The LED state, if it changed since the last call
+    ///
+    /// LED reports are received while the [`UsbHidClass`](crate::hid_class::UsbHidClass) is
+    /// polled, whether sent to the OUT endpoint or with a `SET_REPORT` request.
+    pub fn leds_changed(&self) -> Option<KeyboardLedsReport> {
+        self.leds.changed()
+    }
+
+    /// The LED state set by the last LED report received from the host
+    pub fn leds(&self) -> KeyboardLedsReport {
+        self.leds.leds()
+    }
+
+    /// Statistics about LED output reports received from the host
+    pub fn leds_report_statistics(&self) -> LedsReportStatistics {
+        self.leds.statistics()
+    }
+
+    fn receive_leds(&self) -> Result<(), UsbHidError> {
+        //Like SET_REPORT, longer OUT packets are rejected with a ReportTooLong error
+        let mut data = [0; MAX_CONTROL_REPORT_SIZE];
+        let n = self.inner.read_unqueued_packet(&mut data)?;
+        Ok(self.receive_leds_report(&data[..n])?)
+    }
+
+    fn receive_leds_report(&self, data: &[u8]) -> usb_device::Result<()> {
+        let leds = self.leds.receive(data, self.config.leds_parsing)?;
+        if OUT_QUEUE > 0 {
+            let data = leds.pack().map_err(|_| UsbError::ParseError)?;
+            self.inner.queue_output_report(&data)?;
+        }
+        Ok(())
+    }
+}
+
+impl<'a, B> NKROBootKeyboardInterface<'a, B>
+where
+    B: UsbBus,
+{
+    pub fn default_config() -> WrappedInterfaceConfig<
+        Self,
+        ManagedInterfaceConfig<'a, NKROBootKeyboardReport>,
+        KeyboardConfig,
+    > {
+        Self::default_config_with_descriptor(NKRO_BOOT_KEYBOARD_REPORT_DESCRIPTOR)
+    }
+
+    /// Default configuration with an alternative report descriptor
+    ///
+    /// The descriptor must describe reports with the same layout as [`NKROBootKeyboardReport`]
+    pub fn default_config_with_descriptor(
+        report_descriptor: &'a [u8],
+    ) -> WrappedInterfaceConfig<
+        Self,
+        ManagedInterfaceConfig<'a, NKROBootKeyboardReport>,
+        KeyboardConfig,
+    > {
         WrappedInterfaceConfig::new(
             ManagedInterfaceConfig::new(
-                RawInterfaceBuilder::new(NKRO_BOOT_KEYBOARD_REPORT_DESCRIPTOR)
+                RawInterfaceBuilder::new(report_descriptor)
                     .description("NKRO Keyboard")
                     .boot_device(InterfaceProtocol::Keyboard)
-                    .idle_default(500.millis())
-                    .unwrap()
-                    .in_endpoint(UsbPacketSize::Bytes32, 10.millis())
-                    .unwrap()
-                    .with_out_endpoint(UsbPacketSize::Bytes8, 100.millis())
-                    .unwrap()
-                    .build(),
-            ),
-            (),
+                    .boot_report_descriptor(BOOT_KEYBOARD_REPORT_DESCRIPTOR)
+                    .idle_default(IdleDuration::millis(500))
+                    .in_endpoint(UsbPacketSize::Bytes32, PollInterval::millis(10))
+                    .with_out_endpoint(UsbPacketSize::Bytes8, PollInterval::millis(100))
+                    .build()
+                    .unwrap(),
+            )
+            .boot_report_len(8),
+            KeyboardConfig::default(),
         )
     }
 }
 
-impl<'a, B> InterfaceClass<'a> for NKROBootKeyboardInterface<'a, B>
+impl<'a, B> DefaultConfig<'a, B> for NKROBootKeyboardInterface<'a, B>
+where
+    B: UsbBus + 'a,
+{
+    type Config = WrappedInterfaceConfig<
+        Self,
+        ManagedInterfaceConfig<'a, NKROBootKeyboardReport>,
+        KeyboardConfig,
+    >;
+
+    fn default_config() -> Self::Config {
+        Self::default_config()
+    }
+}
+
+impl<'a, B, const OUT_QUEUE: usize, const IN_QUEUE: usize> InterfaceClass<'a>
+    for NKROBootKeyboardInterface<'a, B, OUT_QUEUE, IN_QUEUE>
 where
     B: UsbBus,
 {
     delegate! {
         to self.inner{
             fn report_descriptor(&self) -> &'_ [u8];
+            fn hid_descriptor_body(&self) -> HidDescriptorBodyBytes;
+            fn class_descriptor(&self, descriptor_type: u8, index: u8) -> Option<&'_ [u8]>;
+            fn take_wakeup_request(&self) -> bool;
             fn id(&self) -> InterfaceNumber;
             fn write_descriptors(&self, writer: &mut DescriptorWriter) -> usb_device::Result<()>;
             fn get_string(&self, index: StringIndex, _lang_id: u16) -> Option<&'_ str>;
-            fn set_report(&mut self, data: &[u8]) -> usb_device::Result<()>;
             fn get_report(&mut self, data: &mut [u8]) -> usb_device::Result<usize>;
             fn get_report_ack(&mut self) -> usb_device::Result<()>;
             fn get_idle(&self, report_id: u8) -> u8;
-            fn set_protocol(&mut self, protocol: HidProtocol);
+            fn set_protocol(&mut self, protocol: HidProtocol) -> usb_device::Result<()>;
             fn get_protocol(&self) -> HidProtocol;
-            fn reset(&mut self);
+            fn out_endpoint_address(&self) -> Option<EndpointAddress>;
+            fn in_endpoint_address(&self) -> EndpointAddress;
+            fn endpoint_in_complete(&self, addr: EndpointAddress);
             fn set_idle(&mut self, report_id: u8, value: u8);
         }
     }
+
+    fn set_report(&mut self, data: &[u8]) -> usb_device::Result<()> {
+        self.receive_leds_report(data)
+    }
+
+    fn endpoint_out(&mut self) {
+        self.receive_leds().ok();
+    }
+
+    fn reset(&mut self) {
+        self.inner.reset();
+        self.typematic = Cell::new(TypematicState::new(self.config.typematic));
+    }
 }
 
-impl<'a, B> WrappedInterface<'a, B, ManagedInterface<'a, B, NKROBootKeyboardReport>>
-    for NKROBootKeyboardInterface<'a, B>
+impl<'a, B, const OUT_QUEUE: usize, const IN_QUEUE: usize>
+    WrappedInterface<
+        'a,
+        B,
+        ManagedInterface<'a, B, NKROBootKeyboardReport, (), 0, OUT_QUEUE, IN_QUEUE>,
+        KeyboardConfig,
+    > for NKROBootKeyboardInterface<'a, B, OUT_QUEUE, IN_QUEUE>
 where
     B: 'a + UsbBus,
 {
-    fn new(interface: ManagedInterface<'a, B, NKROBootKeyboardReport>, _: ()) -> Self {
-        Self { inner: interface }
+    fn new(
+        interface: ManagedInterface<'a, B, NKROBootKeyboardReport, (), 0, OUT_QUEUE, IN_QUEUE>,
+        config: KeyboardConfig,
+    ) -> Self {
+        Self {
+            inner: interface,
+            config,
+            leds: Default::default(),
+            typematic: Cell::new(TypematicState::new(config.typematic)),
+        }
     }
 }
 
@@ -520,12 +1260,327 @@ pub const NKRO_COMPACT_KEYBOARD_REPORT_DESCRIPTOR: &[u8] = &[
     0xc0                            // End Collection
 ];
 
+/// Report implementing an NKRO keyboard as a bitmap, see [`NKRO_COMPACT_KEYBOARD_REPORT_DESCRIPTOR`]
+///
+/// Keys with usages above 0x87 other than the modifiers are not described and are ignored.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Default, PackedStruct)]
+#[packed_struct(endian = "lsb", bit_numbering = "msb0", size_bytes = "18")]
+pub struct NKROKeyboardReport {
+    #[packed_field(bits = "0")]
+    pub right_gui: bool,
+    #[packed_field(bits = "1")]
+    pub right_alt: bool,
+    #[packed_field(bits = "2")]
+    pub right_shift: bool,
+    #[packed_field(bits = "3")]
+    pub right_ctrl: bool,
+    #[packed_field(bits = "4")]
+    pub left_gui: bool,
+    #[packed_field(bits = "5")]
+    pub left_alt: bool,
+    #[packed_field(bits = "6")]
+    pub left_shift: bool,
+    #[packed_field(bits = "7")]
+    pub left_ctrl: bool,
+    //The usb lsb/lsb0 expected ordering isn't compatible with pact structs
+    #[packed_field(bytes = "1..18", element_size_bits = "8")]
+    pub nkro_keys: [u8; 17],
+}
+
+impl NKROKeyboardReport {
+    pub fn new<K: IntoIterator<Item = Keyboard>>(keys: K) -> Self {
+        let mut report = Self::default();
+        for k in keys.into_iter() {
+            match k {
+                Keyboard::LeftControl => {
+                    report.left_ctrl = true;
+                }
+                Keyboard::LeftShift => {
+                    report.left_shift = true;
+                }
+                Keyboard::LeftAlt => {
+                    report.left_alt = true;
+                }
+                Keyboard::LeftGUI => {
+                    report.left_gui = true;
+                }
+                Keyboard::RightControl => {
+                    report.right_ctrl = true;
+                }
+                Keyboard::RightShift => {
+                    report.right_shift = true;
+                }
+                Keyboard::RightAlt => {
+                    report.right_alt = true;
+                }
+                Keyboard::RightGUI => {
+                    report.right_gui = true;
+                }
+                Keyboard::NoEventIndicated => {}
+                _ => {
+                    if (k as usize) < report.nkro_keys.len() * 8 {
+                        let byte = (k as usize) / 8;
+                        let bit = (k as u8) % 8;
+                        report.nkro_keys[byte] |= 1 << bit;
+                    }
+                }
+            }
+        }
+        report
+    }
+}
+
+/// Interface implementing an NKRO keyboard as a bitmap, not compatible with the HID boot keyboard
+/// specification
+///
+/// **Note:** This is a managed interfaces that support HID idle, [NKROKeyboardInterface::tick()] must be called every 1ms/ at 1kHz.
+///
+/// LED reports are held in the inner interface's output report queue of `OUT_QUEUE` reports, see
+/// [`NKROKeyboardInterface::read_report`], and input reports in its input report queue of `IN_QUEUE` reports.
+pub struct NKROKeyboardInterface<
+    'a,
+    B: UsbBus,
+    const OUT_QUEUE: usize = 0,
+    const IN_QUEUE: usize = 0,
+> {
+    inner: ManagedInterface<'a, B, NKROKeyboardReport, (), 0, OUT_QUEUE, IN_QUEUE>,
+    config: KeyboardConfig,
+    leds: LedsReceiver,
+    typematic: Cell<TypematicState<NKROKeyboardReport>>,
+}
+
+/// NKRO keyboard interface, see [`NKROKeyboardInterface`]
+pub type NKROKeyboard<'a, B, const OUT_QUEUE: usize = 0, const IN_QUEUE: usize = 0> =
+    NKROKeyboardInterface<'a, B, OUT_QUEUE, IN_QUEUE>;
+
+impl<'a, B, const OUT_QUEUE: usize, const IN_QUEUE: usize>
+    NKROKeyboardInterface<'a, B, OUT_QUEUE, IN_QUEUE>
+where
+    B: UsbBus,
+{
+    delegate! {
+        to self.inner {
+            pub fn protocol(&self) -> HidProtocol;
+            pub fn report_latency(&self) -> LatencyHistogram;
+            pub fn clear_report_latency(&self);
+            pub fn host_stalls(&self) -> u32;
+            pub fn host_stalled(&self) -> bool;
+            pub fn write_ready(&self) -> bool;
+            pub fn flush(&self) -> Result<(), UsbHidError>;
+        }
+    }
+
+    pub fn write_report(&self, report: &NKROKeyboardReport) -> Result<(), UsbHidError> {
+        self.inner.write_report(report)?;
+        self.update_typematic(|t| t.report_written(*report));
+        Ok(())
+    }
+
+    /// Call every 1ms / at 1 KHz
+    pub fn tick(&self) -> Result<(), UsbHidError> {
+        self.tick_with(1.millis())
+    }
+
+    /// Advances the idle and key repeat timers by `elapsed`, see
+    /// [`ManagedInterface::tick_with`]
+    pub fn tick_with(&self, elapsed: MillisDurationU32) -> Result<(), UsbHidError> {
+        let idle = self.inner.tick_with(elapsed);
+        if let Some(report) = self.update_typematic(|t| t.tick_with(elapsed)) {
+            self.inner.write_input(&report)?;
+            self.update_typematic(TypematicState::repeat_written);
+        }
+        idle
+    }
+
+    fn update_typematic<T>(
+        &self,
+        f: impl FnOnce(&mut TypematicState<NKROKeyboardReport>) -> T,
+    ) -> T {
+        let mut typematic = self.typematic.get();
+        let result = f(&mut typematic);
+        self.typematic.set(typematic);
+        result
+    }
+
+    /// The last LED report received from the host, [`UsbHidError::WouldBlock`] if it has already
+    /// been read
+    ///
+    /// With an output report queue, see
+    /// [`RawInterfaceBuilder::out_report_queue`](crate::interface::raw::RawInterfaceBuilder::out_report_queue),
+    /// each LED report received is read in turn, oldest first.
+    pub fn read_report(&self) -> Result<KeyboardLedsReport, UsbHidError> {
+        if OUT_QUEUE > 0 {
+            let mut data = [0; 1];
+            self.inner.read_queued_packet(&mut data)?;
+            return KeyboardLedsReport::unpack(&data).map_err(|e| {
+                error!("Error unpacking KeyboardLedsReport: {:?}", e);
+                UsbHidError::SerializationError
+            });
+        }
+        match self.receive_leds() {
+            Ok(()) | Err(UsbHidError::WouldBlock) => self.leds.read(),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// The LED state, if it changed since the last call
+    ///
+    /// LED reports are received while the [`UsbHidClass`](crate::hid_class::UsbHidClass) is
+    /// polled, whether sent to the OUT endpoint or with a `SET_REPORT` request.
+    pub fn leds_changed(&self) -> Option<KeyboardLedsReport> {
+        self.leds.changed()
+    }
+
+    /// The LED state set by the last LED report received from the host
+    pub fn leds(&self) -> KeyboardLedsReport {
+        self.leds.leds()
+    }
+
+    /// Statistics about LED output reports received from the host
+    pub fn leds_report_statistics(&self) -> LedsReportStatistics {
+        self.leds.statistics()
+    }
+
+    fn receive_leds(&self) -> Result<(), UsbHidError> {
+        //Like SET_REPORT, longer OUT packets are rejected with a ReportTooLong error
+        let mut data = [0; MAX_CONTROL_REPORT_SIZE];
+        let n = self.inner.read_unqueued_packet(&mut data)?;
+        Ok(self.receive_leds_report(&data[..n])?)
+    }
+
+    fn receive_leds_report(&self, data: &[u8]) -> usb_device::Result<()> {
+        let leds = self.leds.receive(data, self.config.leds_parsing)?;
+        if OUT_QUEUE > 0 {
+            let data = leds.pack().map_err(|_| UsbError::ParseError)?;
+            self.inner.queue_output_report(&data)?;
+        }
+        Ok(())
+    }
+}
+
+impl<'a, B> NKROKeyboardInterface<'a, B>
+where
+    B: UsbBus,
+{
+    pub fn default_config(
+    ) -> WrappedInterfaceConfig<Self, ManagedInterfaceConfig<'a, NKROKeyboardReport>, KeyboardConfig>
+    {
+        Self::default_config_with_descriptor(NKRO_COMPACT_KEYBOARD_REPORT_DESCRIPTOR)
+    }
+
+    /// Default configuration with an alternative report descriptor
+    ///
+    /// The descriptor must describe reports with the same layout as [`NKROKeyboardReport`]
+    pub fn default_config_with_descriptor(
+        report_descriptor: &'a [u8],
+    ) -> WrappedInterfaceConfig<Self, ManagedInterfaceConfig<'a, NKROKeyboardReport>, KeyboardConfig>
+    {
+        WrappedInterfaceConfig::new(
+            ManagedInterfaceConfig::new(
+                RawInterfaceBuilder::new(report_descriptor)
+                    .description("NKRO Keyboard")
+                    .idle_default(IdleDuration::millis(500))
+                    .in_endpoint(UsbPacketSize::Bytes32, PollInterval::millis(10))
+                    .with_out_endpoint(UsbPacketSize::Bytes8, PollInterval::millis(100))
+                    .build()
+                    .unwrap(),
+            ),
+            KeyboardConfig::default(),
+        )
+    }
+}
+
+impl<'a, B> DefaultConfig<'a, B> for NKROKeyboardInterface<'a, B>
+where
+    B: UsbBus + 'a,
+{
+    type Config = WrappedInterfaceConfig<
+        Self,
+        ManagedInterfaceConfig<'a, NKROKeyboardReport>,
+        KeyboardConfig,
+    >;
+
+    fn default_config() -> Self::Config {
+        Self::default_config()
+    }
+}
+
+impl<'a, B, const OUT_QUEUE: usize, const IN_QUEUE: usize> InterfaceClass<'a>
+    for NKROKeyboardInterface<'a, B, OUT_QUEUE, IN_QUEUE>
+where
+    B: UsbBus,
+{
+    delegate! {
+        to self.inner{
+            fn report_descriptor(&self) -> &'_ [u8];
+            fn hid_descriptor_body(&self) -> HidDescriptorBodyBytes;
+            fn class_descriptor(&self, descriptor_type: u8, index: u8) -> Option<&'_ [u8]>;
+            fn take_wakeup_request(&self) -> bool;
+            fn id(&self) -> InterfaceNumber;
+            fn write_descriptors(&self, writer: &mut DescriptorWriter) -> usb_device::Result<()>;
+            fn get_string(&self, index: StringIndex, _lang_id: u16) -> Option<&'_ str>;
+            fn get_report(&mut self, data: &mut [u8]) -> usb_device::Result<usize>;
+            fn get_report_ack(&mut self) -> usb_device::Result<()>;
+            fn get_idle(&self, report_id: u8) -> u8;
+            fn set_protocol(&mut self, protocol: HidProtocol) -> usb_device::Result<()>;
+            fn get_protocol(&self) -> HidProtocol;
+            fn out_endpoint_address(&self) -> Option<EndpointAddress>;
+            fn in_endpoint_address(&self) -> EndpointAddress;
+            fn endpoint_in_complete(&self, addr: EndpointAddress);
+            fn set_idle(&mut self, report_id: u8, value: u8);
+        }
+    }
+
+    fn set_report(&mut self, data: &[u8]) -> usb_device::Result<()> {
+        self.receive_leds_report(data)
+    }
+
+    fn endpoint_out(&mut self) {
+        self.receive_leds().ok();
+    }
+
+    fn reset(&mut self) {
+        self.inner.reset();
+        self.typematic = Cell::new(TypematicState::new(self.config.typematic));
+    }
+}
+
+impl<'a, B, const OUT_QUEUE: usize, const IN_QUEUE: usize>
+    WrappedInterface<
+        'a,
+        B,
+        ManagedInterface<'a, B, NKROKeyboardReport, (), 0, OUT_QUEUE, IN_QUEUE>,
+        KeyboardConfig,
+    > for NKROKeyboardInterface<'a, B, OUT_QUEUE, IN_QUEUE>
+where
+    B: 'a + UsbBus,
+{
+    fn new(
+        interface: ManagedInterface<'a, B, NKROKeyboardReport, (), 0, OUT_QUEUE, IN_QUEUE>,
+        config: KeyboardConfig,
+    ) -> Self {
+        Self {
+            inner: interface,
+            config,
+            leds: Default::default(),
+            typematic: Cell::new(TypematicState::new(config.typematic)),
+        }
+    }
+}
+
 #[cfg(test)]
 mod test {
     use packed_struct::prelude::*;
 
-    use crate::device::keyboard::{BootKeyboardReport, KeyboardLedsReport};
+    use core::cell::Cell;
+
+    use crate::device::keyboard::{
+        parse_leds_report, BootKeyboardReport, KeyState, KeyboardConfig, KeyboardLedsReport,
+        KeyboardLedsSync, LedsReportStatistics, NKROBootKeyboardReport, NKROKeyboardReport,
+        OutputReportParsing, Typematic, TypematicReport, TypematicState,
+    };
     use crate::page::Keyboard;
+    use crate::UsbHidError;
 
     #[test]
     fn leds_num_lock() {
@@ -567,6 +1622,66 @@ mod test {
         );
     }
 
+    #[test]
+    fn leds_tolerant_oversized_report() {
+        let stats = Cell::new(LedsReportStatistics::default());
+        assert_eq!(
+            parse_leds_report(
+                &[0x82, 0, 0, 0, 0, 0, 0, 0],
+                OutputReportParsing::Tolerant,
+                &stats
+            )
+            .ok(),
+            Some(KeyboardLedsReport {
+                caps_lock: true,
+                ..Default::default()
+            })
+        );
+        assert_eq!(
+            stats.get(),
+            LedsReportStatistics {
+                received: 1,
+                oversized: 1,
+                padding_set: 1,
+                rejected: 0,
+            }
+        );
+    }
+
+    #[test]
+    fn leds_strict_rejects_mismatched_report() {
+        let stats = Cell::new(LedsReportStatistics::default());
+        assert!(parse_leds_report(&[0x02, 0], OutputReportParsing::Strict, &stats).is_err());
+        assert!(parse_leds_report(&[0x82], OutputReportParsing::Strict, &stats).is_err());
+        assert!(parse_leds_report(&[], OutputReportParsing::Strict, &stats).is_err());
+        assert!(parse_leds_report(&[0x02], OutputReportParsing::Strict, &stats).is_ok());
+        assert_eq!(stats.get().received, 4);
+        assert_eq!(stats.get().rejected, 3);
+    }
+
+    #[test]
+    fn leds_sync_latest_report_wins() {
+        let mut sync = KeyboardLedsSync::default();
+        let caps = KeyboardLedsReport {
+            caps_lock: true,
+            ..Default::default()
+        };
+        let num = KeyboardLedsReport {
+            num_lock: true,
+            ..Default::default()
+        };
+
+        assert_eq!(sync.update(Ok(caps)).ok(), Some(true));
+        assert_eq!(sync.update(Err(UsbHidError::WouldBlock)).ok(), Some(false));
+        assert_eq!(sync.update(Ok(caps)).ok(), Some(false));
+        assert_eq!(sync.update(Ok(num)).ok(), Some(true));
+        assert!(matches!(
+            sync.update(Err(UsbHidError::SerializationError)),
+            Err(UsbHidError::SerializationError)
+        ));
+        assert_eq!(sync.leds(), num);
+    }
+
     #[test]
     fn boot_keyboard_report_mixed() {
         let bytes = BootKeyboardReport::new([
@@ -654,4 +1769,332 @@ mod test {
             ]
         );
     }
+
+    #[test]
+    fn key_state_press_release() {
+        let mut keys = KeyState::default();
+        assert!(keys.press(Keyboard::B));
+        assert!(keys.press(Keyboard::LeftControl));
+        assert!(!keys.press(Keyboard::B));
+        assert!(keys.is_pressed(Keyboard::B));
+
+        assert_eq!(
+            keys.boot_report(),
+            BootKeyboardReport::new([Keyboard::LeftControl, Keyboard::B])
+        );
+        assert_eq!(
+            keys.nkro_report(),
+            NKROBootKeyboardReport::new([Keyboard::LeftControl, Keyboard::B])
+        );
+
+        assert!(keys.release(Keyboard::B));
+        assert!(!keys.release(Keyboard::B));
+        assert_eq!(
+            keys.boot_report(),
+            BootKeyboardReport::new([Keyboard::LeftControl])
+        );
+
+        keys.release_all();
+        assert!(keys.is_empty());
+        assert_eq!(keys.boot_report(), BootKeyboardReport::default());
+    }
+
+    #[test]
+    fn key_state_rollover() {
+        let mut keys = KeyState::default();
+        for key in [
+            Keyboard::A,
+            Keyboard::B,
+            Keyboard::C,
+            Keyboard::D,
+            Keyboard::E,
+            Keyboard::F,
+            Keyboard::G,
+        ] {
+            keys.press(key);
+        }
+        assert_eq!(keys.boot_report().keys, [Keyboard::ErrorRollOver; 6]);
+        assert_eq!(keys.nkro_report().nkro_keys[0], 0xF0);
+        assert_eq!(keys.nkro_report().nkro_keys[1], 0x07);
+    }
+
+    #[test]
+    fn typematic_repeats_last_key_pressed() {
+        use fugit::ExtU32;
+
+        let mut typematic = TypematicState::new(Some(Typematic {
+            delay: 3.millis(),
+            interval: 2.millis(),
+        }));
+        let held = BootKeyboardReport::new([Keyboard::LeftShift, Keyboard::A, Keyboard::B]);
+        typematic.report_written(BootKeyboardReport::new([Keyboard::A]));
+        typematic.report_written(held);
+
+        assert_eq!(typematic.tick(), None);
+        assert_eq!(typematic.tick(), None);
+        let released = typematic.tick().unwrap();
+        assert_eq!(
+            released,
+            BootKeyboardReport::new([Keyboard::LeftShift, Keyboard::A])
+        );
+        typematic.repeat_written();
+        assert_eq!(typematic.tick(), Some(held));
+        typematic.repeat_written();
+
+        assert_eq!(typematic.tick(), None);
+        assert_eq!(typematic.tick(), Some(released));
+
+        typematic.report_written(BootKeyboardReport::new([Keyboard::A]));
+        assert!((0..10).all(|_| typematic.tick().is_none()));
+    }
+
+    #[test]
+    fn typematic_disabled_by_default() {
+        let mut typematic = TypematicState::new(KeyboardConfig::default().typematic);
+        typematic.report_written(BootKeyboardReport::new([Keyboard::A]));
+        assert!((0..1000).all(|_| typematic.tick().is_none()));
+    }
+
+    #[test]
+    fn typematic_nkro_release() {
+        let report = NKROBootKeyboardReport::new([Keyboard::A, Keyboard::B]);
+        assert_eq!(
+            report.newly_pressed(&NKROBootKeyboardReport::new([Keyboard::A])),
+            Some(Keyboard::B)
+        );
+        assert_eq!(
+            report.without(Keyboard::A),
+            NKROBootKeyboardReport::new([Keyboard::B])
+        );
+    }
+
+    #[test]
+    fn nkro_keyboard_report() {
+        let report = NKROKeyboardReport::new([Keyboard::LeftShift, Keyboard::A, Keyboard::F24]);
+        let mut expected = [0; 18];
+        expected[0] = 0x02;
+        expected[1] = 0x10;
+        expected[1 + 0x73 / 8] = 1 << (0x73 % 8);
+        assert_eq!(report.pack().unwrap(), expected);
+    }
+
+    #[cfg(feature = "simulation")]
+    #[test]
+    fn nkro_boot_protocol_fallback() {
+        use usb_device::bus::UsbBusAllocator;
+
+        use crate::device::keyboard::NKROBootKeyboard;
+        use crate::prelude::UsbHidClassBuilder;
+        use crate::simulation::{control_transfer, device_builder, Response, SimBus};
+
+        let usb_alloc = UsbBusAllocator::new(SimBus::new());
+        let mut keyboard = UsbHidClassBuilder::new()
+            .add_interface(NKROBootKeyboard::default_config())
+            .build(&usb_alloc)
+            .unwrap();
+        let mut usb_dev = device_builder(&usb_alloc, 64).build();
+
+        let report = NKROBootKeyboardReport::new([Keyboard::A]);
+        let set_protocol = |protocol: u8| [0x21, 0x0B, protocol, 0x00, 0x00, 0x00, 0x00, 0x00];
+
+        // SET_PROTOCOL (Boot)
+        assert_eq!(
+            control_transfer(&mut usb_dev, &mut [&mut keyboard], set_protocol(0), &[]),
+            Response::Ack
+        );
+        keyboard
+            .interface::<NKROBootKeyboard<'_, _>, _>()
+            .write_report(&report)
+            .unwrap();
+        assert_eq!(
+            usb_dev.bus().host_take_in(1).unwrap(),
+            report.pack().unwrap()[..8]
+        );
+
+        // SET_PROTOCOL (Report), the unchanged report is sent again in the report layout
+        assert_eq!(
+            control_transfer(&mut usb_dev, &mut [&mut keyboard], set_protocol(1), &[]),
+            Response::Ack
+        );
+        keyboard
+            .interface::<NKROBootKeyboard<'_, _>, _>()
+            .write_report(&report)
+            .unwrap();
+        assert_eq!(
+            usb_dev.bus().host_take_in(1).unwrap(),
+            report.pack().unwrap()
+        );
+    }
+
+    #[cfg(feature = "simulation")]
+    #[test]
+    fn leds_changed_on_poll() {
+        use usb_device::bus::UsbBusAllocator;
+
+        use crate::device::keyboard::BootKeyboard;
+        use crate::prelude::UsbHidClassBuilder;
+        use crate::simulation::{control_transfer, device_builder, Response, SimBus};
+
+        let usb_alloc = UsbBusAllocator::new(SimBus::new());
+        let mut keyboard = UsbHidClassBuilder::new()
+            .add_interface(BootKeyboard::default_config())
+            .build(&usb_alloc)
+            .unwrap();
+        let mut usb_dev = device_builder(&usb_alloc, 64).build();
+
+        // SET_REPORT (Output) caps lock
+        assert_eq!(
+            control_transfer(
+                &mut usb_dev,
+                &mut [&mut keyboard],
+                [0x21, 0x09, 0x00, 0x02, 0x00, 0x00, 0x01, 0x00],
+                &[0x02]
+            ),
+            Response::Ack
+        );
+        // SET_REPORT (Output) caps lock again, before the application has read the LEDs
+        assert_eq!(
+            control_transfer(
+                &mut usb_dev,
+                &mut [&mut keyboard],
+                [0x21, 0x09, 0x00, 0x02, 0x00, 0x00, 0x01, 0x00],
+                &[0x02]
+            ),
+            Response::Ack
+        );
+
+        let interface = keyboard.interface::<BootKeyboard<'_, _>, _>();
+        let caps_lock = KeyboardLedsReport {
+            caps_lock: true,
+            ..Default::default()
+        };
+        assert_eq!(interface.leds_changed(), Some(caps_lock));
+        assert_eq!(interface.leds_changed(), None);
+        assert_eq!(interface.read_report().unwrap(), caps_lock);
+        assert!(matches!(
+            interface.read_report(),
+            Err(UsbHidError::WouldBlock)
+        ));
+
+        // Output report on the OUT endpoint, num lock
+        usb_dev.bus().host_out(1, &[0x01]);
+        usb_dev.poll(&mut [&mut keyboard]);
+        let interface = keyboard.interface::<BootKeyboard<'_, _>, _>();
+        assert_eq!(
+            interface.leds_changed(),
+            Some(KeyboardLedsReport {
+                num_lock: true,
+                ..Default::default()
+            })
+        );
+        assert!(interface.leds().num_lock);
+        assert_eq!(interface.leds_report_statistics().received, 3);
+    }
+
+    #[cfg(feature = "simulation")]
+    #[test]
+    fn high_speed_leds_packet_too_long() {
+        use usb_device::bus::UsbBusAllocator;
+
+        use crate::device::keyboard::{BootKeyboard, BOOT_KEYBOARD_REPORT_DESCRIPTOR};
+        use crate::interface::managed::ManagedInterfaceConfig;
+        use crate::interface::WrappedInterfaceConfig;
+        use crate::prelude::*;
+        use crate::simulation::{device_builder, SimBus};
+
+        let usb_alloc = UsbBusAllocator::new(SimBus::new());
+        let keyboard = UsbHidClassBuilder::new()
+            .add_interface(WrappedInterfaceConfig::<BootKeyboard<'_, _>, _, _>::new(
+                ManagedInterfaceConfig::new(
+                    RawInterfaceBuilder::new(BOOT_KEYBOARD_REPORT_DESCRIPTOR)
+                        .speed(UsbSpeed::High)
+                        .in_endpoint(UsbPacketSize::Bytes8, PollInterval::millis(10))
+                        .with_out_endpoint(UsbPacketSize::Bytes128, PollInterval::millis(10))
+                        .build()
+                        .unwrap(),
+                ),
+                KeyboardConfig::default(),
+            ))
+            .build(&usb_alloc)
+            .unwrap();
+        let usb_dev = device_builder(&usb_alloc, 64).build();
+
+        usb_dev.bus().host_out(1, &[0x02; 65]);
+        let interface = keyboard.interface::<BootKeyboard<'_, _>, _>();
+        assert!(matches!(
+            interface.read_report(),
+            Err(UsbHidError::ReportTooLong)
+        ));
+
+        usb_dev.bus().host_out(1, &[0x02]);
+        assert!(interface.read_report().unwrap().caps_lock);
+    }
+
+    #[cfg(feature = "simulation")]
+    #[test]
+    fn queued_leds_read_in_order() {
+        use usb_device::bus::UsbBusAllocator;
+
+        use crate::device::keyboard::{
+            BootKeyboard, KeyboardConfig, BOOT_KEYBOARD_REPORT_DESCRIPTOR,
+        };
+        use crate::interface::managed::ManagedInterfaceConfig;
+        use crate::interface::WrappedInterfaceConfig;
+        use crate::prelude::*;
+        use crate::simulation::{control_transfer, device_builder, Response, SimBus};
+
+        type Keyboard<'a> = BootKeyboard<'a, SimBus, 2>;
+
+        let usb_alloc = UsbBusAllocator::new(SimBus::new());
+        let mut keyboard = UsbHidClassBuilder::new()
+            .add_interface(WrappedInterfaceConfig::<Keyboard<'_>, _, _>::new(
+                ManagedInterfaceConfig::new(
+                    RawInterfaceBuilder::new(BOOT_KEYBOARD_REPORT_DESCRIPTOR)
+                        .boot_device(InterfaceProtocol::Keyboard)
+                        .in_endpoint(UsbPacketSize::Bytes8, PollInterval::millis(10))
+                        .with_out_endpoint(UsbPacketSize::Bytes8, PollInterval::millis(10))
+                        .out_report_queue::<2>()
+                        .build()
+                        .unwrap(),
+                ),
+                KeyboardConfig::default(),
+            ))
+            .build(&usb_alloc)
+            .unwrap();
+        let mut usb_dev = device_builder(&usb_alloc, 64).build();
+
+        // SET_REPORT (Output) caps lock, then num lock on the OUT endpoint, before either is read
+        assert_eq!(
+            control_transfer(
+                &mut usb_dev,
+                &mut [&mut keyboard],
+                [0x21, 0x09, 0x00, 0x02, 0x00, 0x00, 0x01, 0x00],
+                &[0x02]
+            ),
+            Response::Ack
+        );
+        usb_dev.bus().host_out(1, &[0x01]);
+        usb_dev.poll(&mut [&mut keyboard]);
+
+        let interface = keyboard.interface::<Keyboard<'_>, _>();
+        assert!(interface.leds().num_lock);
+        assert_eq!(
+            interface.read_report().unwrap(),
+            KeyboardLedsReport {
+                caps_lock: true,
+                ..Default::default()
+            }
+        );
+        assert_eq!(
+            interface.read_report().unwrap(),
+            KeyboardLedsReport {
+                num_lock: true,
+                ..Default::default()
+            }
+        );
+        assert!(matches!(
+            interface.read_report(),
+            Err(UsbHidError::WouldBlock)
+        ));
+    }
 }
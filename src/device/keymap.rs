@@ -0,0 +1,137 @@
+//! Keymaps mixing keyboard and consumer control keys
+//!
+//! Firmware keymaps can be written in terms of [`Key`], then split into the reports of the keyboard
+//! and consumer control interfaces of a composite device. Keyboard page media keys, such as
+//! [`Keyboard::VolumeUp`], are routed to their Consumer page equivalents as many hosts, including
+//! Windows, ignore them when sent by a keyboard.
+//!
+//! ```
+//! use usbd_human_interface_device::device::keymap::{consumer_report, keyboard_keys, Key};
+//! use usbd_human_interface_device::page::{Consumer, Keyboard};
+//!
+//! let pressed = [
+//!     Key::from(Keyboard::A),
+//!     Key::from(Keyboard::VolumeUp),
+//!     Key::from(Consumer::PlayPause),
+//! ];
+//!
+//! assert!(keyboard_keys(pressed).eq([Keyboard::A]));
+//! assert_eq!(
+//!     consumer_report(pressed).codes,
+//!     [
+//!         Consumer::VolumeIncrement,
+//!         Consumer::PlayPause,
+//!         Consumer::Unassigned,
+//!         Consumer::Unassigned,
+//!     ]
+//! );
+//! ```
+
+use crate::device::consumer::MultipleConsumerReport;
+use crate::page::{Consumer, Keyboard};
+
+/// A key sent by either the keyboard or the consumer control interface
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum Key {
+    Keyboard(Keyboard),
+    Consumer(Consumer),
+}
+
+impl From<Keyboard> for Key {
+    fn from(k: Keyboard) -> Self {
+        Key::Keyboard(k)
+    }
+}
+
+impl From<Consumer> for Key {
+    fn from(c: Consumer) -> Self {
+        Key::Consumer(c)
+    }
+}
+
+impl Key {
+    /// The usage to send on the keyboard interface, if any
+    pub fn keyboard(self) -> Option<Keyboard> {
+        match self {
+            Key::Keyboard(k) if consumer_equivalent(k).is_none() => Some(k),
+            _ => None,
+        }
+    }
+
+    /// The usage to send on the consumer control interface, if any
+    pub fn consumer(self) -> Option<Consumer> {
+        match self {
+            Key::Keyboard(k) => consumer_equivalent(k),
+            Key::Consumer(Consumer::Unassigned) => None,
+            Key::Consumer(c) => Some(c),
+        }
+    }
+}
+
+/// The Consumer page usage for Keyboard page media keys
+pub fn consumer_equivalent(key: Keyboard) -> Option<Consumer> {
+    match key {
+        Keyboard::Mute => Some(Consumer::Mute),
+        Keyboard::VolumeUp => Some(Consumer::VolumeIncrement),
+        Keyboard::VolumeDown => Some(Consumer::VolumeDecrement),
+        _ => None,
+    }
+}
+
+/// The pressed keys to pass to the keyboard's report constructor
+pub fn keyboard_keys<K: IntoIterator<Item = Key>>(keys: K) -> impl Iterator<Item = Keyboard> {
+    keys.into_iter().filter_map(Key::keyboard)
+}
+
+/// Consumer control report of the pressed keys
+///
+/// Only the first four consumer usages fit in the report, any further usages are dropped.
+pub fn consumer_report<K: IntoIterator<Item = Key>>(keys: K) -> MultipleConsumerReport {
+    let mut report = MultipleConsumerReport::default();
+    report
+        .codes
+        .iter_mut()
+        .zip(keys.into_iter().filter_map(Key::consumer))
+        .for_each(|(code, c)| *code = c);
+    report
+}
+
+#[cfg(test)]
+mod test {
+    use crate::device::keymap::{consumer_report, keyboard_keys, Key};
+    use crate::page::{Consumer, Keyboard};
+
+    #[test]
+    fn consumer_report_drops_excess_usages() {
+        let keys = [
+            Consumer::DisplayBrightnessIncrement,
+            Consumer::Unassigned,
+            Consumer::ScanNextTrack,
+            Consumer::Stop,
+            Consumer::Eject,
+            Consumer::Mute,
+        ]
+        .map(Key::from);
+
+        assert_eq!(
+            consumer_report(keys).codes,
+            [
+                Consumer::DisplayBrightnessIncrement,
+                Consumer::ScanNextTrack,
+                Consumer::Stop,
+                Consumer::Eject,
+            ]
+        );
+    }
+
+    #[test]
+    fn media_keys_are_not_sent_by_keyboard() {
+        let keys = [Keyboard::LeftShift, Keyboard::Mute, Keyboard::VolumeDown].map(Key::from);
+
+        assert!(keyboard_keys(keys).eq([Keyboard::LeftShift]));
+        assert_eq!(
+            consumer_report(keys).codes[..2],
+            [Consumer::Mute, Consumer::VolumeDecrement]
+        );
+    }
+}
@@ -0,0 +1,806 @@
+//!HID LampArray, RGB lighting controlled by the host
+//!
+//! Windows Dynamic Lighting controls devices with a LampArray collection on the Lighting and
+//! Illumination usage page without a vendor driver. The host reads the [`LampArrayAttributes`] and
+//! the [`LampAttributes`] of each lamp, takes control of the lamps with the control feature report
+//! and sets their colours with [`LampMultiUpdateReport`]s and [`LampRangeUpdateReport`]s.
+//! Positions and dimensions are in micrometres.
+//!
+//! Colours set by the host are collected until an update with [`LAMP_UPDATE_COMPLETE`] is
+//! received, [`LampArrayInterface::take_update`] then returns the complete frame. With the
+//! `smart-leds` feature, [`LampArrayInterface::rgb8`] yields the frame as `RGB8` colours, which
+//! `SmartLedsWrite` LED drivers write directly.
+use crate::hid_class::descriptor::{HidProtocol, ReportType};
+use core::default::Default;
+use delegate::delegate;
+use packed_struct::prelude::*;
+use packed_struct::types::bits::ByteArray;
+use usb_device::bus::{InterfaceNumber, StringIndex, UsbBus};
+use usb_device::class_prelude::{DescriptorWriter, EndpointAddress};
+use usb_device::UsbError;
+
+use crate::hid_class::prelude::*;
+use crate::interface::duration::PollInterval;
+use crate::interface::raw::{RawInterface, RawInterfaceConfig};
+use crate::interface::{InterfaceClass, WrappedInterface, WrappedInterfaceConfig};
+
+pub const LAMP_ARRAY_ATTRIBUTES_REPORT_ID: u8 = 0x01;
+pub const LAMP_ATTRIBUTES_REQUEST_REPORT_ID: u8 = 0x02;
+pub const LAMP_ATTRIBUTES_RESPONSE_REPORT_ID: u8 = 0x03;
+pub const LAMP_MULTI_UPDATE_REPORT_ID: u8 = 0x04;
+pub const LAMP_RANGE_UPDATE_REPORT_ID: u8 = 0x05;
+pub const LAMP_ARRAY_CONTROL_REPORT_ID: u8 = 0x06;
+
+/// Lamp update flag, set on the last update of a frame
+pub const LAMP_UPDATE_COMPLETE: u8 = 0x01;
+
+/// Lamps set by a single [`LampMultiUpdateReport`]
+pub const MAX_MULTI_UPDATE_LAMPS: usize = 8;
+
+/// Lamp purpose flags of [`LampAttributes::purposes`]
+pub const LAMP_PURPOSE_CONTROL: u32 = 0x01;
+pub const LAMP_PURPOSE_ACCENT: u32 = 0x02;
+pub const LAMP_PURPOSE_BRANDING: u32 = 0x04;
+pub const LAMP_PURPOSE_STATUS: u32 = 0x08;
+pub const LAMP_PURPOSE_ILLUMINATION: u32 = 0x10;
+pub const LAMP_PURPOSE_PRESENTATION: u32 = 0x20;
+
+/// LampArray with attribute, multi update, range update and control feature reports, and no input
+/// reports
+///
+/// * Report ID [`LAMP_ARRAY_ATTRIBUTES_REPORT_ID`] - Feature, see [`LampArrayAttributes`]
+/// * Report ID [`LAMP_ATTRIBUTES_REQUEST_REPORT_ID`] - Feature, little endian `u16` lamp ID
+/// * Report ID [`LAMP_ATTRIBUTES_RESPONSE_REPORT_ID`] - Feature, little endian `u16` lamp ID
+///   followed by [`LampAttributes`]
+/// * Report ID [`LAMP_MULTI_UPDATE_REPORT_ID`] - Feature, see [`LampMultiUpdateReport`]
+/// * Report ID [`LAMP_RANGE_UPDATE_REPORT_ID`] - Feature, see [`LampRangeUpdateReport`]
+/// * Report ID [`LAMP_ARRAY_CONTROL_REPORT_ID`] - Feature, byte 0 - Autonomous mode
+#[rustfmt::skip]
+pub const LAMP_ARRAY_REPORT_DESCRIPTOR: &[u8] = &[
+    0x05, 0x59,        // Usage Page (Lighting and Illumination),
+    0x09, 0x01,        // Usage (LampArray),
+    0xA1, 0x01,        // Collection (Application),
+    0x85, 0x01,        //   Report ID (1),
+    0x09, 0x02,        //   Usage (LampArray Attributes Report),
+    0xA1, 0x02,        //   Collection (Logical),
+    0x09, 0x03,        //     Usage (Lamp Count),
+    0x15, 0x00,        //     Logical Minimum (0),
+    0x27, 0xFF, 0xFF, 0x00, 0x00, // Logical Maximum (65535),
+    0x75, 0x10,        //     Report Size (16),
+    0x95, 0x01,        //     Report Count (1),
+    0xB1, 0x03,        //     Feature (Constant, Variable, Absolute),
+    0x09, 0x04,        //     Usage (Bounding Box Width In Micrometers),
+    0x09, 0x05,        //     Usage (Bounding Box Height In Micrometers),
+    0x09, 0x06,        //     Usage (Bounding Box Depth In Micrometers),
+    0x09, 0x07,        //     Usage (LampArray Kind),
+    0x09, 0x08,        //     Usage (Min Update Interval In Microseconds),
+    0x27, 0xFF, 0xFF, 0xFF, 0x7F, // Logical Maximum (2147483647),
+    0x75, 0x20,        //     Report Size (32),
+    0x95, 0x05,        //     Report Count (5),
+    0xB1, 0x03,        //     Feature (Constant, Variable, Absolute),
+    0xC0,              //   End Collection
+    0x85, 0x02,        //   Report ID (2),
+    0x09, 0x20,        //   Usage (Lamp Attributes Request Report),
+    0xA1, 0x02,        //   Collection (Logical),
+    0x09, 0x21,        //     Usage (Lamp Id),
+    0x27, 0xFF, 0xFF, 0x00, 0x00, // Logical Maximum (65535),
+    0x75, 0x10,        //     Report Size (16),
+    0x95, 0x01,        //     Report Count (1),
+    0xB1, 0x02,        //     Feature (Data, Variable, Absolute),
+    0xC0,              //   End Collection
+    0x85, 0x03,        //   Report ID (3),
+    0x09, 0x22,        //   Usage (Lamp Attributes Response Report),
+    0xA1, 0x02,        //   Collection (Logical),
+    0x09, 0x21,        //     Usage (Lamp Id),
+    0xB1, 0x02,        //     Feature (Data, Variable, Absolute),
+    0x09, 0x23,        //     Usage (Position X In Micrometers),
+    0x09, 0x24,        //     Usage (Position Y In Micrometers),
+    0x09, 0x25,        //     Usage (Position Z In Micrometers),
+    0x09, 0x27,        //     Usage (Update Latency In Microseconds),
+    0x09, 0x26,        //     Usage (Lamp Purposes),
+    0x27, 0xFF, 0xFF, 0xFF, 0x7F, // Logical Maximum (2147483647),
+    0x75, 0x20,        //     Report Size (32),
+    0x95, 0x05,        //     Report Count (5),
+    0xB1, 0x02,        //     Feature (Data, Variable, Absolute),
+    0x09, 0x28,        //     Usage (Red Level Count),
+    0x09, 0x29,        //     Usage (Green Level Count),
+    0x09, 0x2A,        //     Usage (Blue Level Count),
+    0x09, 0x2B,        //     Usage (Intensity Level Count),
+    0x09, 0x2C,        //     Usage (Is Programmable),
+    0x09, 0x2D,        //     Usage (Input Binding),
+    0x26, 0xFF, 0x00,  //     Logical Maximum (255),
+    0x75, 0x08,        //     Report Size (8),
+    0x95, 0x06,        //     Report Count (6),
+    0xB1, 0x02,        //     Feature (Data, Variable, Absolute),
+    0xC0,              //   End Collection
+    0x85, 0x04,        //   Report ID (4),
+    0x09, 0x50,        //   Usage (Lamp Multi Update Report),
+    0xA1, 0x02,        //   Collection (Logical),
+    0x09, 0x03,        //     Usage (Lamp Count),
+    0x09, 0x55,        //     Usage (Lamp Update Flags),
+    0x25, 0x08,        //     Logical Maximum (8),
+    0x75, 0x08,        //     Report Size (8),
+    0x95, 0x02,        //     Report Count (2),
+    0xB1, 0x02,        //     Feature (Data, Variable, Absolute),
+    0x09, 0x21,        //     Usage (Lamp Id),
+    0x09, 0x21,        //     Usage (Lamp Id),
+    0x09, 0x21,        //     Usage (Lamp Id),
+    0x09, 0x21,        //     Usage (Lamp Id),
+    0x09, 0x21,        //     Usage (Lamp Id),
+    0x09, 0x21,        //     Usage (Lamp Id),
+    0x09, 0x21,        //     Usage (Lamp Id),
+    0x09, 0x21,        //     Usage (Lamp Id),
+    0x27, 0xFF, 0xFF, 0x00, 0x00, // Logical Maximum (65535),
+    0x75, 0x10,        //     Report Size (16),
+    0x95, 0x08,        //     Report Count (8),
+    0xB1, 0x02,        //     Feature (Data, Variable, Absolute),
+    0x09, 0x51,        //     Usage (Red Update Channel),
+    0x09, 0x52,        //     Usage (Green Update Channel),
+    0x09, 0x53,        //     Usage (Blue Update Channel),
+    0x09, 0x54,        //     Usage (Intensity Update Channel),
+    0x09, 0x51,        //     Usage (Red Update Channel),
+    0x09, 0x52,        //     Usage (Green Update Channel),
+    0x09, 0x53,        //     Usage (Blue Update Channel),
+    0x09, 0x54,        //     Usage (Intensity Update Channel),
+    0x09, 0x51,        //     Usage (Red Update Channel),
+    0x09, 0x52,        //     Usage (Green Update Channel),
+    0x09, 0x53,        //     Usage (Blue Update Channel),
+    0x09, 0x54,        //     Usage (Intensity Update Channel),
+    0x09, 0x51,        //     Usage (Red Update Channel),
+    0x09, 0x52,        //     Usage (Green Update Channel),
+    0x09, 0x53,        //     Usage (Blue Update Channel),
+    0x09, 0x54,        //     Usage (Intensity Update Channel),
+    0x09, 0x51,        //     Usage (Red Update Channel),
+    0x09, 0x52,        //     Usage (Green Update Channel),
+    0x09, 0x53,        //     Usage (Blue Update Channel),
+    0x09, 0x54,        //     Usage (Intensity Update Channel),
+    0x09, 0x51,        //     Usage (Red Update Channel),
+    0x09, 0x52,        //     Usage (Green Update Channel),
+    0x09, 0x53,        //     Usage (Blue Update Channel),
+    0x09, 0x54,        //     Usage (Intensity Update Channel),
+    0x09, 0x51,        //     Usage (Red Update Channel),
+    0x09, 0x52,        //     Usage (Green Update Channel),
+    0x09, 0x53,        //     Usage (Blue Update Channel),
+    0x09, 0x54,        //     Usage (Intensity Update Channel),
+    0x09, 0x51,        //     Usage (Red Update Channel),
+    0x09, 0x52,        //     Usage (Green Update Channel),
+    0x09, 0x53,        //     Usage (Blue Update Channel),
+    0x09, 0x54,        //     Usage (Intensity Update Channel),
+    0x26, 0xFF, 0x00,  //     Logical Maximum (255),
+    0x75, 0x08,        //     Report Size (8),
+    0x95, 0x20,        //     Report Count (32),
+    0xB1, 0x02,        //     Feature (Data, Variable, Absolute),
+    0xC0,              //   End Collection
+    0x85, 0x05,        //   Report ID (5),
+    0x09, 0x60,        //   Usage (Lamp Range Update Report),
+    0xA1, 0x02,        //   Collection (Logical),
+    0x09, 0x55,        //     Usage (Lamp Update Flags),
+    0x25, 0x08,        //     Logical Maximum (8),
+    0x75, 0x08,        //     Report Size (8),
+    0x95, 0x01,        //     Report Count (1),
+    0xB1, 0x02,        //     Feature (Data, Variable, Absolute),
+    0x09, 0x61,        //     Usage (Lamp Id Start),
+    0x09, 0x62,        //     Usage (Lamp Id End),
+    0x27, 0xFF, 0xFF, 0x00, 0x00, // Logical Maximum (65535),
+    0x75, 0x10,        //     Report Size (16),
+    0x95, 0x02,        //     Report Count (2),
+    0xB1, 0x02,        //     Feature (Data, Variable, Absolute),
+    0x09, 0x51,        //     Usage (Red Update Channel),
+    0x09, 0x52,        //     Usage (Green Update Channel),
+    0x09, 0x53,        //     Usage (Blue Update Channel),
+    0x09, 0x54,        //     Usage (Intensity Update Channel),
+    0x26, 0xFF, 0x00,  //     Logical Maximum (255),
+    0x75, 0x08,        //     Report Size (8),
+    0x95, 0x04,        //     Report Count (4),
+    0xB1, 0x02,        //     Feature (Data, Variable, Absolute),
+    0xC0,              //   End Collection
+    0x85, 0x06,        //   Report ID (6),
+    0x09, 0x70,        //   Usage (LampArray Control Report),
+    0xA1, 0x02,        //   Collection (Logical),
+    0x09, 0x71,        //     Usage (Autonomous Mode),
+    0x25, 0x01,        //     Logical Maximum (1),
+    0x75, 0x08,        //     Report Size (8),
+    0x95, 0x01,        //     Report Count (1),
+    0xB1, 0x02,        //     Feature (Data, Variable, Absolute),
+    0xC0,              //   End Collection
+    0xC0,              // End Collection
+];
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Default, PrimitiveEnum_u32)]
+#[repr(u32)]
+pub enum LampArrayKind {
+    #[default]
+    Undefined = 0,
+    Keyboard = 1,
+    Mouse = 2,
+    GameController = 3,
+    Peripheral = 4,
+    Scene = 5,
+    Notification = 6,
+    Chassis = 7,
+    Wearable = 8,
+    Furniture = 9,
+    Art = 10,
+}
+
+/// Fixed properties of the LampArray, dimensions in micrometres
+///
+/// `lamp_count` is set to the number of lamps by [`LampArrayInterface::default_config`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Default, PackedStruct)]
+#[packed_struct(endian = "lsb", size_bytes = "22")]
+pub struct LampArrayAttributes {
+    #[packed_field]
+    pub lamp_count: u16,
+    #[packed_field]
+    pub bounding_box_width: u32,
+    #[packed_field]
+    pub bounding_box_height: u32,
+    #[packed_field]
+    pub bounding_box_depth: u32,
+    #[packed_field(ty = "enum", element_size_bytes = "4")]
+    pub kind: LampArrayKind,
+    /// Shortest interval between updates the lamps can show, in microseconds
+    #[packed_field]
+    pub min_update_interval: u32,
+}
+
+/// Fixed properties of a lamp, positions in micrometres from the top left front corner of the
+/// bounding box
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Default, PackedStruct)]
+#[packed_struct(endian = "lsb", size_bytes = "26")]
+pub struct LampAttributes {
+    #[packed_field]
+    pub position_x: u32,
+    #[packed_field]
+    pub position_y: u32,
+    #[packed_field]
+    pub position_z: u32,
+    /// Time for an update to show, in microseconds
+    #[packed_field]
+    pub update_latency: u32,
+    /// `LAMP_PURPOSE_*` flags
+    #[packed_field]
+    pub purposes: u32,
+    #[packed_field]
+    pub red_level_count: u8,
+    #[packed_field]
+    pub green_level_count: u8,
+    #[packed_field]
+    pub blue_level_count: u8,
+    #[packed_field]
+    pub intensity_level_count: u8,
+    /// `1` if the host can set the colour of the lamp
+    #[packed_field]
+    pub is_programmable: u8,
+    /// Keyboard usage of the key the lamp lights, `0` for none
+    #[packed_field]
+    pub input_binding: u8,
+}
+
+impl LampAttributes {
+    /// Programmable RGB control lamp at the given position, with 255 levels of each colour and of
+    /// intensity
+    pub const fn new(position_x: u32, position_y: u32, position_z: u32) -> Self {
+        Self {
+            position_x,
+            position_y,
+            position_z,
+            update_latency: 4_000,
+            purposes: LAMP_PURPOSE_CONTROL,
+            red_level_count: 0xFF,
+            green_level_count: 0xFF,
+            blue_level_count: 0xFF,
+            intensity_level_count: 0xFF,
+            is_programmable: 1,
+            input_binding: 0,
+        }
+    }
+}
+
+/// Colour of a lamp, each channel from 0 to 255
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Default, PackedStruct)]
+#[packed_struct(endian = "lsb", size_bytes = "4")]
+pub struct LampColor {
+    #[packed_field]
+    pub red: u8,
+    #[packed_field]
+    pub green: u8,
+    #[packed_field]
+    pub blue: u8,
+    #[packed_field]
+    pub intensity: u8,
+}
+
+impl LampColor {
+    pub const OFF: Self = Self {
+        red: 0,
+        green: 0,
+        blue: 0,
+        intensity: 0,
+    };
+
+    /// Red, green and blue scaled by the intensity
+    pub fn scaled(&self) -> [u8; 3] {
+        let scale = |c: u8| ((u16::from(c) * u16::from(self.intensity)) / 0xFF) as u8;
+        [scale(self.red), scale(self.green), scale(self.blue)]
+    }
+}
+
+#[cfg(feature = "smart-leds")]
+impl From<LampColor> for rgb::RGB8 {
+    fn from(color: LampColor) -> Self {
+        let [r, g, b] = color.scaled();
+        rgb::RGB8::new(r, g, b)
+    }
+}
+
+/// Colours of up to [`MAX_MULTI_UPDATE_LAMPS`] lamps, the first `lamp_count` entries of `lamp_ids`
+/// and `colors` are used
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Default, PackedStruct)]
+#[packed_struct(endian = "lsb", size_bytes = "50")]
+pub struct LampMultiUpdateReport {
+    #[packed_field]
+    pub lamp_count: u8,
+    /// [`LAMP_UPDATE_COMPLETE`] on the last update of a frame
+    #[packed_field]
+    pub flags: u8,
+    #[packed_field]
+    pub lamp_ids: [u16; 8],
+    #[packed_field(element_size_bytes = "4")]
+    pub colors: [LampColor; 8],
+}
+
+/// Colour of the lamps from `lamp_id_start` to `lamp_id_end`, inclusive
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Default, PackedStruct)]
+#[packed_struct(endian = "lsb", size_bytes = "9")]
+pub struct LampRangeUpdateReport {
+    /// [`LAMP_UPDATE_COMPLETE`] on the last update of a frame
+    #[packed_field]
+    pub flags: u8,
+    #[packed_field]
+    pub lamp_id_start: u16,
+    #[packed_field]
+    pub lamp_id_end: u16,
+    #[packed_field(size_bytes = "4")]
+    pub color: LampColor,
+}
+
+/// Feature reports of a LampArray of `N` lamps, the configuration of [`LampArrayInterface`]
+///
+/// Lamp attributes are answered for the lamp requested by the host, then for each following lamp
+/// in turn. Colours set by the host are held until an update with [`LAMP_UPDATE_COMPLETE`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LampArrayReports<const N: usize> {
+    attributes: LampArrayAttributes,
+    lamps: &'static [LampAttributes; N],
+    lamp_id: usize,
+    pending: [LampColor; N],
+    colors: [LampColor; N],
+    updated: bool,
+    autonomous: bool,
+}
+
+impl<const N: usize> LampArrayReports<N> {
+    pub fn new(attributes: LampArrayAttributes, lamps: &'static [LampAttributes; N]) -> Self {
+        Self {
+            attributes: LampArrayAttributes {
+                lamp_count: N as u16,
+                ..attributes
+            },
+            lamps,
+            lamp_id: 0,
+            pending: [LampColor::OFF; N],
+            colors: [LampColor::OFF; N],
+            updated: false,
+            autonomous: true,
+        }
+    }
+
+    fn reset(&mut self) {
+        *self = Self::new(self.attributes, self.lamps);
+    }
+
+    fn lamp_index(lamp_id: u16) -> usb_device::Result<usize> {
+        let index = usize::from(lamp_id);
+        if index < N {
+            Ok(index)
+        } else {
+            warn!("Lamp ID {:X} out of range, {} lamps", lamp_id, N);
+            Err(UsbError::ParseError)
+        }
+    }
+
+    fn update(&mut self, flags: u8) {
+        if flags & LAMP_UPDATE_COMPLETE != 0 {
+            self.colors = self.pending;
+            self.updated = true;
+        }
+    }
+
+    fn multi_update(&mut self, report: &LampMultiUpdateReport) -> usb_device::Result<()> {
+        let count = usize::from(report.lamp_count);
+        let lamp_ids = report.lamp_ids.get(..count).ok_or_else(|| {
+            warn!("Lamp multi update of {} lamps", count);
+            UsbError::ParseError
+        })?;
+        for &lamp_id in lamp_ids {
+            Self::lamp_index(lamp_id)?;
+        }
+        for (&lamp_id, &color) in lamp_ids.iter().zip(report.colors.iter()) {
+            self.pending[usize::from(lamp_id)] = color;
+        }
+        self.update(report.flags);
+        Ok(())
+    }
+
+    fn range_update(&mut self, report: &LampRangeUpdateReport) -> usb_device::Result<()> {
+        let start = Self::lamp_index(report.lamp_id_start)?;
+        let end = Self::lamp_index(report.lamp_id_end)?;
+        let lamps = self.pending.get_mut(start..=end).ok_or_else(|| {
+            warn!("Lamp range update from {} to {}", start, end);
+            UsbError::ParseError
+        })?;
+        lamps.fill(report.color);
+        self.update(report.flags);
+        Ok(())
+    }
+
+    /// Applies a feature report set by the host, `data` starts with the report ID
+    fn set_report(&mut self, data: &[u8]) -> usb_device::Result<()> {
+        let Some((&report_id, report)) = data.split_first() else {
+            return Err(UsbError::ParseError);
+        };
+        match (report_id, report) {
+            (LAMP_ATTRIBUTES_REQUEST_REPORT_ID, &[lo, hi]) => {
+                self.lamp_id = Self::lamp_index(u16::from_le_bytes([lo, hi]))?;
+                Ok(())
+            }
+            (LAMP_MULTI_UPDATE_REPORT_ID, _) => self.multi_update(&unpack(report)?),
+            (LAMP_RANGE_UPDATE_REPORT_ID, _) => self.range_update(&unpack(report)?),
+            (LAMP_ARRAY_CONTROL_REPORT_ID, &[autonomous]) => {
+                self.autonomous = autonomous != 0;
+                Ok(())
+            }
+            _ => {
+                warn!("Unsupported SetReport ID: {:X}", report_id);
+                Err(UsbError::Unsupported)
+            }
+        }
+    }
+
+    /// Writes the feature report `report_id` to `data`, prefixed by the report ID
+    fn get_report(&mut self, report_id: u8, data: &mut [u8]) -> usb_device::Result<usize> {
+        match report_id {
+            LAMP_ARRAY_ATTRIBUTES_REPORT_ID => pack_with_id(report_id, &[], &self.attributes, data),
+            LAMP_ATTRIBUTES_RESPONSE_REPORT_ID => {
+                let lamp = self.lamps.get(self.lamp_id).ok_or(UsbError::Unsupported)?;
+                let lamp_id = (self.lamp_id as u16).to_le_bytes();
+                let len = pack_with_id(report_id, &lamp_id, lamp, data)?;
+                self.lamp_id = (self.lamp_id + 1) % N;
+                Ok(len)
+            }
+            _ => Err(UsbError::Unsupported),
+        }
+    }
+}
+
+fn unpack<R: PackedStruct>(data: &[u8]) -> usb_device::Result<R> {
+    R::unpack_from_slice(data).map_err(|e| {
+        warn!("Error unpacking lamp report: {:?}", e);
+        UsbError::ParseError
+    })
+}
+
+/// Writes the report ID, `prefix` and the packed `report` to `data`, returns the length written
+fn pack_with_id<R: PackedStruct>(
+    report_id: u8,
+    prefix: &[u8],
+    report: &R,
+    data: &mut [u8],
+) -> usb_device::Result<usize> {
+    let packed = report.pack().map_err(|_| UsbError::ParseError)?;
+    let packed = packed.as_bytes_slice();
+    let len = 1 + prefix.len() + packed.len();
+    let data = data.get_mut(..len).ok_or(UsbError::BufferOverflow)?;
+    data[0] = report_id;
+    data[1..=prefix.len()].copy_from_slice(prefix);
+    data[1 + prefix.len()..].copy_from_slice(packed);
+    Ok(len)
+}
+
+/// LampArray of `N` lamps, lit by the host
+///
+/// The LampArray starts in autonomous mode, with the application choosing the colours, until the
+/// host takes control of the lamps.
+pub struct LampArrayInterface<'a, B: UsbBus, const N: usize> {
+    inner: RawInterface<'a, B>,
+    reports: LampArrayReports<N>,
+    feature_report_sent: bool,
+}
+
+/// LampArray interface, see [`LampArrayInterface`]
+pub type LampArray<'a, B, const N: usize> = LampArrayInterface<'a, B, N>;
+
+impl<'a, B: UsbBus, const N: usize> LampArrayInterface<'a, B, N> {
+    delegate! {
+        to self.inner {
+            pub fn protocol(&self) -> HidProtocol;
+        }
+    }
+
+    /// `true` while the application chooses the colours of the lamps, `false` once the host has
+    /// taken control of them
+    pub fn autonomous_mode(&self) -> bool {
+        self.reports.autonomous
+    }
+
+    /// Colours of the last complete update from the host
+    pub fn colors(&self) -> &[LampColor; N] {
+        &self.reports.colors
+    }
+
+    /// Colours of the lamps, if the host has completed an update since the last call
+    pub fn take_update(&mut self) -> Option<&[LampColor; N]> {
+        if core::mem::take(&mut self.reports.updated) {
+            Some(&self.reports.colors)
+        } else {
+            None
+        }
+    }
+
+    /// Colours of the last complete update from the host, scaled by their intensity, in lamp ID
+    /// order
+    ///
+    /// Passed to `SmartLedsWrite::write` when the LED chain is wired in lamp ID order.
+    #[cfg(feature = "smart-leds")]
+    pub fn rgb8(&self) -> impl Iterator<Item = rgb::RGB8> + '_ {
+        self.colors().iter().map(|&color| color.into())
+    }
+
+    pub fn default_config(
+        attributes: LampArrayAttributes,
+        lamps: &'static [LampAttributes; N],
+    ) -> WrappedInterfaceConfig<Self, RawInterfaceConfig<'a>, LampArrayReports<N>> {
+        WrappedInterfaceConfig::new(
+            RawInterfaceBuilder::new(LAMP_ARRAY_REPORT_DESCRIPTOR)
+                .description("LampArray")
+                .in_endpoint(UsbPacketSize::Bytes8, PollInterval::millis(10))
+                .without_out_endpoint()
+                .build()
+                .unwrap(),
+            LampArrayReports::new(attributes, lamps),
+        )
+    }
+}
+
+impl<'a, B: UsbBus, const N: usize> InterfaceClass<'a> for LampArrayInterface<'a, B, N> {
+    delegate! {
+        to self.inner{
+           fn report_descriptor(&self) -> &'_ [u8];
+           fn id(&self) -> InterfaceNumber;
+           fn write_descriptors(&self, writer: &mut DescriptorWriter) -> usb_device::Result<()>;
+           fn get_string(&self, index: StringIndex, _lang_id: u16) -> Option<&'_ str>;
+           fn get_report(&mut self, data: &mut [u8]) -> usb_device::Result<usize>;
+           fn set_idle(&mut self, report_id: u8, value: u8);
+           fn get_idle(&self, report_id: u8) -> u8;
+           fn set_protocol(&mut self, protocol: HidProtocol) -> usb_device::Result<()>;
+           fn get_protocol(&self) -> HidProtocol;
+           fn out_endpoint_address(&self) -> Option<EndpointAddress>;
+           fn in_endpoint_address(&self) -> EndpointAddress;
+           fn endpoint_in_complete(&self, addr: EndpointAddress);
+        }
+    }
+
+    fn static_report_descriptor(&self) -> Option<&'static [u8]> {
+        Some(LAMP_ARRAY_REPORT_DESCRIPTOR)
+    }
+
+    /// Returns the lamps to autonomous mode and clears the colours set by the host
+    fn reset(&mut self) {
+        self.inner.reset();
+        self.reports.reset();
+        self.feature_report_sent = false;
+    }
+
+    fn set_report(&mut self, data: &[u8]) -> usb_device::Result<()> {
+        self.reports.set_report(data)
+    }
+
+    fn get_report_by_id(
+        &mut self,
+        report_type: ReportType,
+        report_id: u8,
+        data: &mut [u8],
+    ) -> usb_device::Result<usize> {
+        match report_type {
+            ReportType::Feature => {
+                let len = self.reports.get_report(report_id, data)?;
+                self.feature_report_sent = true;
+                Ok(len)
+            }
+            ReportType::Input => self.inner.get_report(data),
+            ReportType::Output => {
+                warn!("Unsupported GetReport type: {:?}", report_type);
+                Err(UsbError::Unsupported)
+            }
+        }
+    }
+
+    fn get_report_ack(&mut self) -> usb_device::Result<()> {
+        if core::mem::take(&mut self.feature_report_sent) {
+            Ok(())
+        } else {
+            self.inner.get_report_ack()
+        }
+    }
+}
+
+impl<'a, B: UsbBus, const N: usize>
+    WrappedInterface<'a, B, RawInterface<'a, B>, LampArrayReports<N>>
+    for LampArrayInterface<'a, B, N>
+{
+    fn new(interface: RawInterface<'a, B>, reports: LampArrayReports<N>) -> Self {
+        Self {
+            inner: interface,
+            reports,
+            feature_report_sent: false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::device::lamp_array::*;
+
+    static LAMPS: [LampAttributes; 3] = [
+        LampAttributes::new(10_000, 5_000, 0),
+        LampAttributes::new(20_000, 5_000, 0),
+        LampAttributes::new(30_000, 5_000, 0),
+    ];
+
+    const RED: LampColor = LampColor {
+        red: 0xFF,
+        green: 0,
+        blue: 0,
+        intensity: 0xFF,
+    };
+
+    fn with_id<R: PackedStruct>(report_id: u8, report: &R) -> std::vec::Vec<u8> {
+        let mut data = std::vec![report_id];
+        data.extend_from_slice(report.pack().unwrap().as_bytes_slice());
+        data
+    }
+
+    #[test]
+    fn updates_held_until_complete() {
+        let mut reports = LampArrayReports::new(LampArrayAttributes::default(), &LAMPS);
+        let mut update = LampMultiUpdateReport {
+            lamp_count: 2,
+            lamp_ids: [2, 0, 0, 0, 0, 0, 0, 0],
+            colors: [RED; MAX_MULTI_UPDATE_LAMPS],
+            ..Default::default()
+        };
+        update.colors[1] = LampColor::OFF;
+        reports
+            .set_report(&with_id(LAMP_MULTI_UPDATE_REPORT_ID, &update))
+            .unwrap();
+        assert!(!reports.updated);
+        assert_eq!(reports.colors, [LampColor::OFF; 3]);
+
+        let range = LampRangeUpdateReport {
+            flags: LAMP_UPDATE_COMPLETE,
+            lamp_id_start: 1,
+            lamp_id_end: 1,
+            color: RED,
+        };
+        reports
+            .set_report(&with_id(LAMP_RANGE_UPDATE_REPORT_ID, &range))
+            .unwrap();
+        assert!(reports.updated);
+        assert_eq!(reports.colors, [LampColor::OFF, RED, RED]);
+
+        // Lamp IDs past the last lamp are rejected without changing any lamp
+        update.lamp_ids[1] = 3;
+        update.flags = LAMP_UPDATE_COMPLETE;
+        assert!(reports
+            .set_report(&with_id(LAMP_MULTI_UPDATE_REPORT_ID, &update))
+            .is_err());
+        update.lamp_count = 9;
+        update.lamp_ids[1] = 0;
+        assert!(reports
+            .set_report(&with_id(LAMP_MULTI_UPDATE_REPORT_ID, &update))
+            .is_err());
+        assert_eq!(reports.colors, [LampColor::OFF, RED, RED]);
+    }
+
+    #[test]
+    fn lamp_attributes_answered_from_requested_lamp() {
+        let mut reports = LampArrayReports::new(LampArrayAttributes::default(), &LAMPS);
+        let mut data = [0; 64];
+
+        assert!(matches!(
+            reports.get_report(LAMP_ARRAY_ATTRIBUTES_REPORT_ID, &mut data),
+            Ok(23)
+        ));
+        assert_eq!(data[..3], [LAMP_ARRAY_ATTRIBUTES_REPORT_ID, 0x03, 0x00]);
+
+        // Attributes of lamp 2, then wrapping to lamp 0
+        reports
+            .set_report(&[LAMP_ATTRIBUTES_REQUEST_REPORT_ID, 0x02, 0x00])
+            .unwrap();
+        for (lamp_id, position_x) in [(2, [0x30, 0x75]), (0, [0x10, 0x27])] {
+            assert!(matches!(
+                reports.get_report(LAMP_ATTRIBUTES_RESPONSE_REPORT_ID, &mut data),
+                Ok(29)
+            ));
+            assert_eq!(
+                data[..5],
+                [
+                    LAMP_ATTRIBUTES_RESPONSE_REPORT_ID,
+                    lamp_id,
+                    0x00,
+                    position_x[0],
+                    position_x[1]
+                ]
+            );
+        }
+
+        // Lamp 3 does not exist and the attributes are read only
+        assert!(reports
+            .set_report(&[LAMP_ATTRIBUTES_REQUEST_REPORT_ID, 0x03, 0x00])
+            .is_err());
+        assert!(matches!(
+            reports.set_report(&[LAMP_ARRAY_ATTRIBUTES_REPORT_ID; 23]),
+            Err(UsbError::Unsupported)
+        ));
+        assert!(matches!(
+            reports.get_report(LAMP_MULTI_UPDATE_REPORT_ID, &mut data),
+            Err(UsbError::Unsupported)
+        ));
+    }
+
+    #[test]
+    fn host_takes_control_until_reset() {
+        let mut reports = LampArrayReports::new(LampArrayAttributes::default(), &LAMPS);
+        assert!(reports.autonomous);
+        reports
+            .set_report(&[LAMP_ARRAY_CONTROL_REPORT_ID, 0x00])
+            .unwrap();
+        assert!(!reports.autonomous);
+        reports
+            .set_report(&[
+                LAMP_RANGE_UPDATE_REPORT_ID,
+                LAMP_UPDATE_COMPLETE,
+                0x00,
+                0x00,
+                0x02,
+                0x00,
+                0xFF,
+                0x00,
+                0x00,
+                0xFF,
+            ])
+            .unwrap();
+        assert_eq!(reports.colors, [RED; 3]);
+
+        reports.reset();
+        assert!(reports.autonomous);
+        assert!(!reports.updated);
+        assert_eq!(reports.colors, [LampColor::OFF; 3]);
+    }
+
+    #[test]
+    fn colors_scaled_by_intensity() {
+        let color = LampColor {
+            red: 0xFF,
+            green: 0x80,
+            blue: 0x10,
+            intensity: 0x80,
+        };
+        assert_eq!(color.scaled(), [0x80, 0x40, 0x08]);
+        assert_eq!(RED.scaled(), [0xFF, 0, 0]);
+        assert_eq!(LampColor::OFF.scaled(), [0, 0, 0]);
+        #[cfg(feature = "smart-leds")]
+        assert_eq!(rgb::RGB8::from(color), rgb::RGB8::new(0x80, 0x40, 0x08));
+    }
+}
@@ -0,0 +1,261 @@
+//!Keyboard layouts mapping characters to key strokes
+//!
+//! The host, not the keyboard, decides which character a key types, so typing text requires
+//! knowing the layout the host has configured. Each layout is a function from `char` to the
+//! [`KeyCombo`] typing it, usable directly where the layout is fixed at compile time, or selected
+//! at runtime with [`Layout`].
+//!
+//! Characters typed with dead keys, such as `â` on French and German layouts, take more than one key
+//! stroke and are not mapped.
+use crate::page::alias::{self, KeyCombo};
+use crate::page::Keyboard;
+
+/// Layout selected at runtime
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum Layout {
+    /// US English, see [`us`]
+    #[default]
+    Us,
+    /// UK English, see [`uk`]
+    Uk,
+    /// German QWERTZ, see [`de`]
+    De,
+    /// French AZERTY, see [`fr`]
+    Fr,
+}
+
+impl Layout {
+    pub fn key_combo(self, c: char) -> Option<KeyCombo> {
+        self.to_fn()(c)
+    }
+
+    /// The layout's mapping function
+    pub fn to_fn(self) -> fn(char) -> Option<KeyCombo> {
+        match self {
+            Layout::Us => us,
+            Layout::Uk => uk,
+            Layout::De => de,
+            Layout::Fr => fr,
+        }
+    }
+}
+
+fn letter(c: char) -> Keyboard {
+    Keyboard::from(u8::from(Keyboard::A) + (c as u8 - b'a'))
+}
+
+fn digit(c: char) -> Keyboard {
+    match c {
+        '0' => Keyboard::Keyboard0,
+        _ => Keyboard::from(u8::from(Keyboard::Keyboard1) + (c as u8 - b'1')),
+    }
+}
+
+/// Strokes common to all layouts
+fn common(c: char) -> Option<KeyCombo> {
+    let stroke = match c {
+        '\n' => KeyCombo::plain(Keyboard::ReturnEnter),
+        '\t' => KeyCombo::plain(Keyboard::Tab),
+        ' ' => KeyCombo::plain(Keyboard::Space),
+        '\u{8}' => KeyCombo::plain(Keyboard::DeleteBackspace),
+        '\u{1B}' => KeyCombo::plain(Keyboard::Escape),
+        _ => return None,
+    };
+    Some(stroke)
+}
+
+/// US English layout, see [`KeyCombo`]
+pub fn us(c: char) -> Option<KeyCombo> {
+    KeyCombo::try_from(c).ok()
+}
+
+/// UK English layout
+pub fn uk(c: char) -> Option<KeyCombo> {
+    let stroke = match c {
+        '"' => KeyCombo::shifted(Keyboard::Keyboard2),
+        '£' => KeyCombo::shifted(Keyboard::Keyboard3),
+        '€' => KeyCombo::alt_gr(Keyboard::Keyboard4),
+        '@' => KeyCombo::shifted(Keyboard::Apostrophe),
+        '#' => KeyCombo::plain(Keyboard::NonUSHash),
+        '~' => KeyCombo::shifted(Keyboard::NonUSHash),
+        '\\' => KeyCombo::plain(Keyboard::NonUSBackslash),
+        '|' => KeyCombo::shifted(Keyboard::NonUSBackslash),
+        '¬' => KeyCombo::shifted(Keyboard::Grave),
+        _ => return us(c),
+    };
+    Some(stroke)
+}
+
+/// German QWERTZ layout, see [`alias::de`]
+pub fn de(c: char) -> Option<KeyCombo> {
+    let stroke = match c {
+        'y' => KeyCombo::plain(alias::de::Y),
+        'z' => KeyCombo::plain(alias::de::Z),
+        'Y' => KeyCombo::shifted(alias::de::Y),
+        'Z' => KeyCombo::shifted(alias::de::Z),
+        'a'..='z' => KeyCombo::plain(letter(c)),
+        'A'..='Z' => KeyCombo::shifted(letter(c.to_ascii_lowercase())),
+        '0'..='9' => KeyCombo::plain(digit(c)),
+        '!' => KeyCombo::shifted(Keyboard::Keyboard1),
+        '"' => KeyCombo::shifted(Keyboard::Keyboard2),
+        '²' => KeyCombo::alt_gr(Keyboard::Keyboard2),
+        '§' => KeyCombo::shifted(Keyboard::Keyboard3),
+        '³' => KeyCombo::alt_gr(Keyboard::Keyboard3),
+        '$' => KeyCombo::shifted(Keyboard::Keyboard4),
+        '%' => KeyCombo::shifted(Keyboard::Keyboard5),
+        '&' => KeyCombo::shifted(Keyboard::Keyboard6),
+        '/' => KeyCombo::shifted(Keyboard::Keyboard7),
+        '{' => alias::de::LEFT_BRACE,
+        '(' => KeyCombo::shifted(Keyboard::Keyboard8),
+        '[' => alias::de::LEFT_BRACKET,
+        ')' => KeyCombo::shifted(Keyboard::Keyboard9),
+        ']' => alias::de::RIGHT_BRACKET,
+        '=' => KeyCombo::shifted(Keyboard::Keyboard0),
+        '}' => alias::de::RIGHT_BRACE,
+        'ß' => KeyCombo::plain(alias::de::SHARP_S),
+        '?' => alias::de::QUESTION_MARK,
+        '\\' => alias::de::BACKSLASH,
+        'ü' => KeyCombo::plain(alias::de::U_UMLAUT),
+        'Ü' => KeyCombo::shifted(alias::de::U_UMLAUT),
+        '+' => KeyCombo::plain(alias::de::PLUS),
+        '*' => alias::de::ASTERISK,
+        '~' => alias::de::TILDE,
+        'ö' => KeyCombo::plain(alias::de::O_UMLAUT),
+        'Ö' => KeyCombo::shifted(alias::de::O_UMLAUT),
+        'ä' => KeyCombo::plain(alias::de::A_UMLAUT),
+        'Ä' => KeyCombo::shifted(alias::de::A_UMLAUT),
+        '#' => KeyCombo::plain(alias::de::HASH),
+        '\'' => alias::de::APOSTROPHE,
+        '°' => KeyCombo::shifted(alias::de::CIRCUMFLEX),
+        ',' => KeyCombo::plain(Keyboard::Comma),
+        ';' => KeyCombo::shifted(Keyboard::Comma),
+        '.' => KeyCombo::plain(Keyboard::Dot),
+        ':' => KeyCombo::shifted(Keyboard::Dot),
+        '-' => KeyCombo::plain(alias::de::MINUS),
+        '_' => alias::de::UNDERSCORE,
+        '<' => KeyCombo::plain(alias::de::LESS_THAN),
+        '>' => alias::de::GREATER_THAN,
+        '|' => alias::de::PIPE,
+        '@' => alias::de::AT,
+        '€' => alias::de::EURO,
+        'µ' => KeyCombo::alt_gr(Keyboard::M),
+        _ => return common(c),
+    };
+    Some(stroke)
+}
+
+/// French AZERTY layout
+pub fn fr(c: char) -> Option<KeyCombo> {
+    let stroke = match c {
+        'a' => KeyCombo::plain(Keyboard::Q),
+        'q' => KeyCombo::plain(Keyboard::A),
+        'z' => KeyCombo::plain(Keyboard::W),
+        'w' => KeyCombo::plain(Keyboard::Z),
+        'm' => KeyCombo::plain(Keyboard::Semicolon),
+        'a'..='z' => KeyCombo::plain(letter(c)),
+        'A'..='Z' => return fr(c.to_ascii_lowercase()).map(|stroke| KeyCombo::shifted(stroke.key)),
+        '0'..='9' => KeyCombo::shifted(digit(c)),
+        '&' => KeyCombo::plain(Keyboard::Keyboard1),
+        'é' => KeyCombo::plain(Keyboard::Keyboard2),
+        '"' => KeyCombo::plain(Keyboard::Keyboard3),
+        '#' => KeyCombo::alt_gr(Keyboard::Keyboard3),
+        '\'' => KeyCombo::plain(Keyboard::Keyboard4),
+        '{' => KeyCombo::alt_gr(Keyboard::Keyboard4),
+        '(' => KeyCombo::plain(Keyboard::Keyboard5),
+        '[' => KeyCombo::alt_gr(Keyboard::Keyboard5),
+        '-' => KeyCombo::plain(Keyboard::Keyboard6),
+        '|' => KeyCombo::alt_gr(Keyboard::Keyboard6),
+        'è' => KeyCombo::plain(Keyboard::Keyboard7),
+        '_' => KeyCombo::plain(Keyboard::Keyboard8),
+        '\\' => KeyCombo::alt_gr(Keyboard::Keyboard8),
+        'ç' => KeyCombo::plain(Keyboard::Keyboard9),
+        '^' => KeyCombo::alt_gr(Keyboard::Keyboard9),
+        'à' => KeyCombo::plain(Keyboard::Keyboard0),
+        '@' => KeyCombo::alt_gr(Keyboard::Keyboard0),
+        ')' => KeyCombo::plain(Keyboard::Minus),
+        '°' => KeyCombo::shifted(Keyboard::Minus),
+        ']' => KeyCombo::alt_gr(Keyboard::Minus),
+        '=' => KeyCombo::plain(Keyboard::Equal),
+        '+' => KeyCombo::shifted(Keyboard::Equal),
+        '}' => KeyCombo::alt_gr(Keyboard::Equal),
+        '$' => KeyCombo::plain(Keyboard::RightBrace),
+        '£' => KeyCombo::shifted(Keyboard::RightBrace),
+        '¤' => KeyCombo::alt_gr(Keyboard::RightBrace),
+        'ù' => KeyCombo::plain(Keyboard::Apostrophe),
+        '%' => KeyCombo::shifted(Keyboard::Apostrophe),
+        '*' => KeyCombo::plain(Keyboard::NonUSHash),
+        'µ' => KeyCombo::shifted(Keyboard::NonUSHash),
+        '²' => KeyCombo::plain(Keyboard::Grave),
+        ',' => KeyCombo::plain(Keyboard::M),
+        '?' => KeyCombo::shifted(Keyboard::M),
+        ';' => KeyCombo::plain(Keyboard::Comma),
+        '.' => KeyCombo::shifted(Keyboard::Comma),
+        ':' => KeyCombo::plain(Keyboard::Dot),
+        '/' => KeyCombo::shifted(Keyboard::Dot),
+        '!' => KeyCombo::plain(Keyboard::ForwardSlash),
+        '§' => KeyCombo::shifted(Keyboard::ForwardSlash),
+        '<' => KeyCombo::plain(Keyboard::NonUSBackslash),
+        '>' => KeyCombo::shifted(Keyboard::NonUSBackslash),
+        '€' => KeyCombo::alt_gr(Keyboard::E),
+        _ => return common(c),
+    };
+    Some(stroke)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::device::layout::*;
+
+    #[test]
+    fn us_strokes() {
+        assert_eq!(us('q'), Some(KeyCombo::plain(Keyboard::Q)));
+        assert_eq!(us('Z'), Some(KeyCombo::shifted(Keyboard::Z)));
+        assert_eq!(us('0'), Some(KeyCombo::plain(Keyboard::Keyboard0)));
+        assert_eq!(us('9'), Some(KeyCombo::plain(Keyboard::Keyboard9)));
+        assert_eq!(us('?'), Some(KeyCombo::shifted(Keyboard::ForwardSlash)));
+        assert_eq!(us('\n'), Some(KeyCombo::plain(Keyboard::ReturnEnter)));
+        assert_eq!(us('é'), None);
+    }
+
+    #[test]
+    fn uk_strokes() {
+        assert_eq!(uk('@'), Some(KeyCombo::shifted(Keyboard::Apostrophe)));
+        assert_eq!(uk('"'), Some(KeyCombo::shifted(Keyboard::Keyboard2)));
+        assert_eq!(uk('£'), Some(KeyCombo::shifted(Keyboard::Keyboard3)));
+        assert_eq!(uk('\\'), Some(KeyCombo::plain(Keyboard::NonUSBackslash)));
+        assert_eq!(uk('a'), us('a'));
+    }
+
+    #[test]
+    fn de_strokes() {
+        assert_eq!(de('y'), Some(KeyCombo::plain(Keyboard::Z)));
+        assert_eq!(de('Z'), Some(KeyCombo::shifted(Keyboard::Y)));
+        assert_eq!(de('ß'), Some(KeyCombo::plain(Keyboard::Minus)));
+        assert_eq!(de('@'), Some(KeyCombo::alt_gr(Keyboard::Q)));
+        assert_eq!(de('-'), Some(KeyCombo::plain(Keyboard::ForwardSlash)));
+        assert_eq!(de('â'), None);
+    }
+
+    #[test]
+    fn fr_strokes() {
+        assert_eq!(fr('a'), Some(KeyCombo::plain(Keyboard::Q)));
+        assert_eq!(fr('M'), Some(KeyCombo::shifted(Keyboard::Semicolon)));
+        assert_eq!(fr('1'), Some(KeyCombo::shifted(Keyboard::Keyboard1)));
+        assert_eq!(fr('é'), Some(KeyCombo::plain(Keyboard::Keyboard2)));
+        assert_eq!(fr(','), Some(KeyCombo::plain(Keyboard::M)));
+        assert_eq!(fr('@'), Some(KeyCombo::alt_gr(Keyboard::Keyboard0)));
+    }
+
+    #[test]
+    fn runtime_selection() {
+        assert_eq!(Layout::De.key_combo('z'), de('z'));
+        assert_eq!(Layout::default().key_combo('z'), us('z'));
+    }
+
+    #[test]
+    fn de_strokes_match_aliases() {
+        assert_eq!(de('@'), Some(alias::de::AT));
+        assert_eq!(de('z'), Some(KeyCombo::plain(alias::de::Z)));
+        assert_eq!(de('Ö'), Some(KeyCombo::shifted(alias::de::O_UMLAUT)));
+    }
+}
@@ -0,0 +1,405 @@
+//!HID macro pads with a keymap the host can reconfigure
+//!
+//! Each key sends the keyboard usage it is mapped to. The keymap is a vendor defined feature
+//! report, held in the interface's [`FeatureReports`] and read and written by configuration
+//! software with `GET_REPORT` and `SET_REPORT` requests. It is in a vendor defined top level
+//! collection of its own, as hosts do not give applications access to keyboard collections.
+//!
+//! A keymap set by the host is saved to a [`SettingsStore`] with [`MacroPadInterface::save_keymap`]
+//! and restored at startup with [`MacroPadInterface::config_from_store`].
+use crate::hid_class::descriptor::{HidProtocol, ReportType};
+use delegate::delegate;
+use packed_struct::prelude::*;
+use usb_device::bus::{InterfaceNumber, StringIndex, UsbBus};
+use usb_device::class_prelude::{DescriptorWriter, EndpointAddress};
+
+use crate::device::keyboard::BootKeyboardReport;
+use crate::hid_class::prelude::*;
+use crate::interface::duration::PollInterval;
+use crate::interface::feature::FeatureReports;
+use crate::interface::latency::LatencyHistogram;
+use crate::interface::raw::RawInterfaceConfig;
+use crate::interface::report_id::ReportIdInterface;
+use crate::interface::settings::SettingsStore;
+use crate::interface::{
+    DefaultConfig, HidDescriptorBodyBytes, InterfaceClass, WrappedInterface, WrappedInterfaceConfig,
+};
+use crate::page::Keyboard;
+use crate::UsbHidError;
+
+pub const MACRO_PAD_KEYS: usize = 16;
+pub const MACRO_PAD_REPORT_ID: u8 = 0x01;
+pub const KEYMAP_REPORT_ID: u8 = 0x02;
+
+/// Keyboard and vendor defined keymap top level collections
+///
+/// * Report ID [`MACRO_PAD_REPORT_ID`], Input - [`BootKeyboardReport`]
+/// * Report ID [`KEYMAP_REPORT_ID`], Feature - Keyboard usage of each key, one byte per key
+#[rustfmt::skip]
+pub const MACRO_PAD_REPORT_DESCRIPTOR: &[u8] = &[
+    0x05, 0x01,        // Usage Page (Generic Desktop),
+    0x09, 0x06,        // Usage (Keyboard),
+    0xA1, 0x01,        // Collection (Application),
+    0x85, 0x01,        //   Report ID (1),
+    0x75, 0x01,        //   Report Size (1),
+    0x95, 0x08,        //   Report Count (8),
+    0x05, 0x07,        //   Usage Page (Key Codes),
+    0x19, 0xE0,        //   Usage Minimum (224),
+    0x29, 0xE7,        //   Usage Maximum (231),
+    0x15, 0x00,        //   Logical Minimum (0),
+    0x25, 0x01,        //   Logical Maximum (1),
+    0x81, 0x02,        //   Input (Data, Variable, Absolute), ;Modifier byte
+    0x95, 0x01,        //   Report Count (1),
+    0x75, 0x08,        //   Report Size (8),
+    0x81, 0x01,        //   Input (Constant), ;Reserved byte
+    0x95, 0x06,        //   Report Count (6),
+    0x26, 0xFF, 0x00,  //   Logical Maximum (255),
+    0x19, 0x00,        //   Usage Minimum (0),
+    0x2A, 0xFF, 0x00,  //   Usage Maximum (255),
+    0x81, 0x00,        //   Input (Data, Array),
+    0xC0,              // End Collection
+    0x06, 0x00, 0xFF,  // Usage Page (FF00h),
+    0x09, 0x01,        // Usage (01h),
+    0xA1, 0x01,        // Collection (Application),
+    0x85, 0x02,        //   Report ID (2),
+    0x09, 0x02,        //   Usage (02h),
+    0x15, 0x00,        //   Logical Minimum (0),
+    0x26, 0xFF, 0x00,  //   Logical Maximum (255),
+    0x75, 0x08,        //   Report Size (8),
+    0x95, 0x10,        //   Report Count (16),
+    0xB1, 0x02,        //   Feature (Data, Variable, Absolute),
+    0xC0,              // End Collection
+];
+
+/// Keymap of a [`MacroPadInterface`]
+pub type MacroPadKeymap = [Keyboard; MACRO_PAD_KEYS];
+
+/// Keymap until the host sets it, `F13` to `F24` followed by `Keypad7` to `Keypad0`
+pub const DEFAULT_MACRO_PAD_KEYMAP: MacroPadKeymap = [
+    Keyboard::F13,
+    Keyboard::F14,
+    Keyboard::F15,
+    Keyboard::F16,
+    Keyboard::F17,
+    Keyboard::F18,
+    Keyboard::F19,
+    Keyboard::F20,
+    Keyboard::F21,
+    Keyboard::F22,
+    Keyboard::F23,
+    Keyboard::F24,
+    Keyboard::Keypad7,
+    Keyboard::Keypad8,
+    Keyboard::Keypad9,
+    Keyboard::Keypad0,
+];
+
+type MacroPadReports = FeatureReports<1, MACRO_PAD_KEYS>;
+
+type MacroPadInnerConfig<'a, B> = WrappedInterfaceConfig<
+    ReportIdInterface<'a, B, MacroPadReports, 1>,
+    RawInterfaceConfig<'a>,
+    MacroPadReports,
+>;
+
+pub struct MacroPadInterface<'a, B: UsbBus> {
+    inner: ReportIdInterface<'a, B, MacroPadReports, 1>,
+}
+
+/// Macro pad interface, see [`MacroPadInterface`]
+pub type MacroPad<'a, B> = MacroPadInterface<'a, B>;
+
+impl<'a, B: UsbBus> MacroPadInterface<'a, B> {
+    delegate! {
+        to self.inner {
+            pub fn protocol(&self) -> HidProtocol;
+            pub fn report_latency(&self) -> LatencyHistogram;
+            pub fn clear_report_latency(&self);
+            pub fn host_stalls(&self) -> u32;
+            pub fn host_stalled(&self) -> bool;
+            pub fn write_ready(&self) -> bool;
+            /// Call every 1ms / at 1 KHz
+            pub fn tick(&self) -> Result<(), UsbHidError>;
+        }
+    }
+
+    /// Current keymap, set by the host or with [`MacroPadInterface::set_keymap`]
+    pub fn keymap(&self) -> MacroPadKeymap {
+        let mut keymap = [Keyboard::NoEventIndicated; MACRO_PAD_KEYS];
+        if let Some(data) = self.inner.handler().get(KEYMAP_REPORT_ID) {
+            for (key, usage) in keymap.iter_mut().zip(data) {
+                *key = Keyboard::from(*usage);
+            }
+        }
+        keymap
+    }
+
+    pub fn set_keymap(&mut self, keymap: &MacroPadKeymap) -> Result<(), UsbHidError> {
+        self.inner
+            .handler_mut()
+            .set(KEYMAP_REPORT_ID, &keymap_bytes(keymap))
+    }
+
+    /// Keymap if the host has set it since the last call, to be saved to persistent storage
+    pub fn take_keymap_changed(&mut self) -> Option<MacroPadKeymap> {
+        self.inner.handler_mut().take_changed(KEYMAP_REPORT_ID)?;
+        Some(self.keymap())
+    }
+
+    /// Saves the keymap to `store` if the host has set it since the last call, returns whether it
+    /// was saved
+    ///
+    /// Call from the main loop rather than from the USB interrupt, as stores backed by flash may
+    /// take a while to write.
+    pub fn save_keymap<S: SettingsStore>(&mut self, store: &mut S) -> Result<bool, S::Error> {
+        match self.take_keymap_changed() {
+            Some(keymap) => store
+                .save(KEYMAP_REPORT_ID, &keymap_bytes(&keymap))
+                .map(|_| true),
+            None => Ok(false),
+        }
+    }
+
+    /// Writes the keys mapped from `keys`, bit 0 is key 1
+    ///
+    /// More than six pressed keys, excluding modifiers, are reported as a rollover error.
+    pub fn write_keys(&self, keys: u16) -> Result<(), UsbHidError> {
+        let keymap = self.keymap();
+        let pressed = keymap
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| keys & (1 << i) != 0)
+            .map(|(_, k)| *k);
+        let data = BootKeyboardReport::new(pressed).pack().map_err(|e| {
+            error!("Error packing BootKeyboardReport: {:?}", e);
+            UsbHidError::SerializationError
+        })?;
+        self.inner.write_report(MACRO_PAD_REPORT_ID, &data)
+    }
+
+    pub fn default_config() -> WrappedInterfaceConfig<Self, MacroPadInnerConfig<'a, B>> {
+        Self::default_config_with_keymap(&DEFAULT_MACRO_PAD_KEYMAP)
+    }
+
+    /// Default configuration with the keymap used until the host sets it, such as one restored
+    /// from persistent storage
+    /// Default configuration with the keymap saved in `store`, or the default keymap if none was
+    /// saved
+    pub fn config_from_store<S: SettingsStore>(
+        store: &mut S,
+    ) -> Result<WrappedInterfaceConfig<Self, MacroPadInnerConfig<'a, B>>, S::Error> {
+        let keymap = load_keymap(store)?.unwrap_or(DEFAULT_MACRO_PAD_KEYMAP);
+        Ok(Self::default_config_with_keymap(&keymap))
+    }
+
+    pub fn default_config_with_keymap(
+        keymap: &MacroPadKeymap,
+    ) -> WrappedInterfaceConfig<Self, MacroPadInnerConfig<'a, B>> {
+        let mut reports = MacroPadReports::new();
+        reports
+            .register(KEYMAP_REPORT_ID, &keymap_bytes(keymap), true)
+            .unwrap();
+        WrappedInterfaceConfig::new(
+            WrappedInterfaceConfig::new(
+                RawInterfaceBuilder::new(MACRO_PAD_REPORT_DESCRIPTOR)
+                    .description("Macro Pad")
+                    .in_endpoint(UsbPacketSize::Bytes16, PollInterval::millis(10))
+                    .without_out_endpoint()
+                    .build()
+                    .unwrap(),
+                reports,
+            ),
+            (),
+        )
+    }
+}
+
+/// Keymap saved in `store` by [`MacroPadInterface::save_keymap`], if any
+pub fn load_keymap<S: SettingsStore>(store: &mut S) -> Result<Option<MacroPadKeymap>, S::Error> {
+    let mut data = [0; MACRO_PAD_KEYS];
+    Ok(match store.load(KEYMAP_REPORT_ID, &mut data)? {
+        Some(MACRO_PAD_KEYS) => Some(data.map(Keyboard::from)),
+        _ => None,
+    })
+}
+
+fn keymap_bytes(keymap: &MacroPadKeymap) -> [u8; MACRO_PAD_KEYS] {
+    keymap.map(u8::from)
+}
+
+impl<'a, B: UsbBus + 'a> DefaultConfig<'a, B> for MacroPadInterface<'a, B> {
+    type Config = WrappedInterfaceConfig<Self, MacroPadInnerConfig<'a, B>>;
+
+    fn default_config() -> Self::Config {
+        Self::default_config()
+    }
+}
+
+impl<'a, B: UsbBus> InterfaceClass<'a> for MacroPadInterface<'a, B> {
+    delegate! {
+        to self.inner{
+           fn report_descriptor(&self) -> &'_ [u8];
+           fn hid_descriptor_body(&self) -> HidDescriptorBodyBytes;
+           fn class_descriptor(&self, descriptor_type: u8, index: u8) -> Option<&'_ [u8]>;
+           fn take_wakeup_request(&self) -> bool;
+           fn id(&self) -> InterfaceNumber;
+           fn write_descriptors(&self, writer: &mut DescriptorWriter) -> usb_device::Result<()>;
+           fn get_string(&self, index: StringIndex, _lang_id: u16) -> Option<&'_ str>;
+           fn reset(&mut self);
+           fn set_report(&mut self, data: &[u8]) -> usb_device::Result<()>;
+           fn set_report_by_id(
+               &mut self,
+               report_type: ReportType,
+               report_id: u8,
+               data: &[u8],
+           ) -> usb_device::Result<()>;
+           fn get_report(&mut self, data: &mut [u8]) -> usb_device::Result<usize>;
+           fn get_report_by_id(
+               &mut self,
+               report_type: ReportType,
+               report_id: u8,
+               data: &mut [u8],
+           ) -> usb_device::Result<usize>;
+           fn get_report_ack(&mut self) -> usb_device::Result<()>;
+           fn set_idle(&mut self, report_id: u8, value: u8);
+           fn get_idle(&self, report_id: u8) -> u8;
+           fn set_protocol(&mut self, protocol: HidProtocol) -> usb_device::Result<()>;
+           fn get_protocol(&self) -> HidProtocol;
+           fn out_endpoint_address(&self) -> Option<EndpointAddress>;
+           fn in_endpoint_address(&self) -> EndpointAddress;
+           fn endpoint_in_complete(&self, addr: EndpointAddress);
+           fn endpoint_out(&mut self);
+        }
+    }
+}
+
+impl<'a, B: UsbBus> WrappedInterface<'a, B, ReportIdInterface<'a, B, MacroPadReports, 1>>
+    for MacroPadInterface<'a, B>
+{
+    fn new(interface: ReportIdInterface<'a, B, MacroPadReports, 1>, _: ()) -> Self {
+        Self { inner: interface }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::device::macro_pad::*;
+
+    #[test]
+    fn keymap_as_usages() {
+        let bytes = keymap_bytes(&DEFAULT_MACRO_PAD_KEYMAP);
+        assert_eq!(bytes[..3], [0x68, 0x69, 0x6A]);
+        assert_eq!(bytes[MACRO_PAD_KEYS - 1], 0x62);
+    }
+
+    #[cfg(feature = "simulation")]
+    #[test]
+    fn host_remaps_keys() {
+        use usb_device::bus::UsbBusAllocator;
+
+        use crate::simulation::{control_transfer, device_builder, Response, SimBus};
+
+        let usb_alloc = UsbBusAllocator::new(SimBus::new());
+        let mut pad = UsbHidClassBuilder::new()
+            .add_interface(MacroPad::default_config())
+            .build(&usb_alloc)
+            .unwrap();
+        let mut usb_dev = device_builder(&usb_alloc, 64).build();
+
+        // GET_REPORT (Feature) keymap
+        let mut keymap = [0; MACRO_PAD_KEYS + 1];
+        keymap[0] = KEYMAP_REPORT_ID;
+        keymap[1..].copy_from_slice(&keymap_bytes(&DEFAULT_MACRO_PAD_KEYMAP));
+        assert_eq!(
+            control_transfer(
+                &mut usb_dev,
+                &mut [&mut pad],
+                [0xA1, 0x01, 0x02, 0x03, 0x00, 0x00, 0x11, 0x00],
+                &[]
+            ),
+            Response::Data(keymap.to_vec())
+        );
+
+        // SET_REPORT (Feature) keymap, key 1 to Ctrl and key 2 to C
+        keymap[1] = Keyboard::LeftControl.into();
+        keymap[2] = Keyboard::C.into();
+        assert_eq!(
+            control_transfer(
+                &mut usb_dev,
+                &mut [&mut pad],
+                [0x21, 0x09, 0x02, 0x03, 0x00, 0x00, 0x11, 0x00],
+                &keymap
+            ),
+            Response::Ack
+        );
+
+        let interface = pad.interface_mut::<MacroPad<'_, _>, _>();
+        let changed = interface.take_keymap_changed().unwrap();
+        assert_eq!(
+            changed[..3],
+            [Keyboard::LeftControl, Keyboard::C, Keyboard::F15]
+        );
+        assert_eq!(interface.take_keymap_changed(), None);
+
+        interface.write_keys(0b0111).unwrap();
+        assert_eq!(
+            usb_dev.bus().host_take_in(1).unwrap(),
+            [
+                MACRO_PAD_REPORT_ID,
+                0x01,
+                0x00,
+                0x06,
+                0x6A,
+                0x00,
+                0x00,
+                0x00,
+                0x00
+            ]
+        );
+        assert!(matches!(
+            interface.write_keys(0b0111),
+            Err(UsbHidError::Duplicate)
+        ));
+    }
+
+    #[cfg(feature = "simulation")]
+    #[test]
+    fn keymap_saved_and_restored() {
+        use usb_device::bus::UsbBusAllocator;
+
+        use crate::interface::settings::MemorySettingsStore;
+        use crate::simulation::{control_transfer, device_builder, Response, SimBus};
+
+        let mut store = MemorySettingsStore::<1, MACRO_PAD_KEYS>::new();
+        assert_eq!(load_keymap(&mut store), Ok(None));
+
+        let usb_alloc = UsbBusAllocator::new(SimBus::new());
+        let mut pad = UsbHidClassBuilder::new()
+            .add_interface(MacroPad::config_from_store(&mut store).unwrap())
+            .build(&usb_alloc)
+            .unwrap();
+        let mut usb_dev = device_builder(&usb_alloc, 64).build();
+
+        // SET_REPORT (Feature) keymap, key 1 to Ctrl
+        let mut keymap = [0; MACRO_PAD_KEYS + 1];
+        keymap[0] = KEYMAP_REPORT_ID;
+        keymap[1..].copy_from_slice(&keymap_bytes(&DEFAULT_MACRO_PAD_KEYMAP));
+        keymap[1] = Keyboard::LeftControl.into();
+        assert_eq!(
+            control_transfer(
+                &mut usb_dev,
+                &mut [&mut pad],
+                [0x21, 0x09, 0x02, 0x03, 0x00, 0x00, 0x11, 0x00],
+                &keymap
+            ),
+            Response::Ack
+        );
+
+        let interface = pad.interface_mut::<MacroPad<'_, _>, _>();
+        assert_eq!(interface.save_keymap(&mut store), Ok(true));
+        assert_eq!(interface.save_keymap(&mut store), Ok(false));
+
+        let restored = load_keymap(&mut store).unwrap().unwrap();
+        assert_eq!(restored[..2], [Keyboard::LeftControl, Keyboard::F14]);
+    }
+}
@@ -1,5 +1,31 @@
 //! Concrete implementation of Human Interface Devices
+pub mod button_pad;
+pub mod composite_keyboard;
 pub mod consumer;
+pub mod ctaphid;
+pub mod ds4;
+pub mod dual_analog;
+pub mod environmental;
+pub mod eye_tracker;
 pub mod fido;
+pub mod foot_switch;
+pub mod gamepad;
+pub mod gaming_mouse;
+pub mod head_tracker;
+pub mod joystick;
 pub mod keyboard;
+pub mod keymap;
+pub mod lamp_array;
+pub mod layout;
+pub mod macro_pad;
 pub mod mouse;
+pub mod multiaxis;
+pub mod multitouch;
+pub mod pid;
+pub mod power;
+pub mod sensor;
+pub mod switch;
+pub mod touchscreen;
+pub mod typer;
+pub mod vendor;
+pub mod wheel;
@@ -2,15 +2,17 @@
 use crate::hid_class::descriptor::HidProtocol;
 use core::default::Default;
 use delegate::delegate;
-use fugit::ExtU32;
-use log::error;
 use packed_struct::prelude::*;
 use usb_device::bus::{InterfaceNumber, StringIndex, UsbBus};
-use usb_device::class_prelude::DescriptorWriter;
+use usb_device::class_prelude::{DescriptorWriter, EndpointAddress};
 
 use crate::hid_class::prelude::*;
+use crate::interface::duration::PollInterval;
+use crate::interface::latency::LatencyHistogram;
 use crate::interface::raw::{RawInterface, RawInterfaceConfig};
-use crate::interface::{InterfaceClass, WrappedInterface, WrappedInterfaceConfig};
+use crate::interface::{
+    DefaultConfig, HidDescriptorBodyBytes, InterfaceClass, WrappedInterface, WrappedInterfaceConfig,
+};
 use crate::UsbHidError;
 
 /// HID Mouse report descriptor conforming to the Boot specification
@@ -116,40 +118,181 @@ pub struct WheelMouseReport {
     pub horizontal_wheel: i8,
 }
 
+/// Absolute positioning mouse with wheel and eight buttons, for KVMs and remote desktops
+///
+/// X and Y are `0` to `32767` across the whole screen, so the pointer moves directly to the
+/// reported position
+#[rustfmt::skip]
+pub const ABSOLUTE_MOUSE_REPORT_DESCRIPTOR: &[u8] = &[
+    0x05, 0x01,        // Usage Page (Generic Desktop),
+    0x09, 0x02,        // Usage (Mouse),
+    0xA1, 0x01,        // Collection (Application),
+    0x09, 0x01,        //   Usage (Pointer),
+    0xA1, 0x00,        //   Collection (Physical),
+    0x95, 0x08,        //     Report Count (8),
+    0x75, 0x01,        //     Report Size (1),
+    0x05, 0x09,        //     Usage Page (Buttons),
+    0x19, 0x01,        //     Usage Minimum (1),
+    0x29, 0x08,        //     Usage Maximum (8),
+    0x15, 0x00,        //     Logical Minimum (0),
+    0x25, 0x01,        //     Logical Maximum (1),
+    0x81, 0x02,        //     Input (Data, Variable, Absolute),
+    0x75, 0x10,        //     Report Size (16),
+    0x95, 0x02,        //     Report Count (2),
+    0x05, 0x01,        //     Usage Page (Generic Desktop),
+    0x09, 0x30,        //     Usage (X),
+    0x09, 0x31,        //     Usage (Y),
+    0x15, 0x00,        //     Logical Minimum (0),
+    0x26, 0xFF, 0x7F,  //     Logical Maximum (32767),
+    0x81, 0x02,        //     Input (Data, Variable, Absolute),
+    0x09, 0x38,        //     Usage (Wheel),
+    0x15, 0x81,        //     Logical Minimum (-127),
+    0x25, 0x7F,        //     Logical Maximum (127),
+    0x75, 0x08,        //     Report Size (8),
+    0x95, 0x01,        //     Report Count (1),
+    0x81, 0x06,        //     Input (Data, Variable, Relative),
+    0xC0,              //   End Collection
+    0xC0,              // End Collection
+];
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Default, PackedStruct)]
+#[packed_struct(endian = "lsb", size_bytes = "6")]
+pub struct AbsoluteMouseReport {
+    #[packed_field]
+    pub buttons: u8,
+    #[packed_field]
+    pub x: u16,
+    #[packed_field]
+    pub y: u16,
+    #[packed_field]
+    pub wheel: i8,
+}
+
+/// Boot compatible mouse with 16 bit motion, wheel and pan, for high DPI sensors
+#[rustfmt::skip]
+pub const HIGH_RES_MOUSE_REPORT_DESCRIPTOR: &[u8] = &[
+    0x05, 0x01,        // Usage Page (Generic Desktop),
+    0x09, 0x02,        // Usage (Mouse),
+    0xA1, 0x01,        // Collection (Application),
+    0x09, 0x01,        //   Usage (Pointer),
+    0xA1, 0x00,        //   Collection (Physical),
+    0x95, 0x08,        //     Report Count (8),
+    0x75, 0x01,        //     Report Size (1),
+    0x05, 0x09,        //     Usage Page (Buttons),
+    0x19, 0x01,        //     Usage Minimum (1),
+    0x29, 0x08,        //     Usage Maximum (8),
+    0x15, 0x00,        //     Logical Minimum (0),
+    0x25, 0x01,        //     Logical Maximum (1),
+    0x81, 0x02,        //     Input (Data, Variable, Absolute),
+    0x75, 0x10,        //     Report Size (16),
+    0x95, 0x03,        //     Report Count (3),
+    0x05, 0x01,        //     Usage Page (Generic Desktop),
+    0x09, 0x30,        //     Usage (X),
+    0x09, 0x31,        //     Usage (Y),
+    0x09, 0x38,        //     Usage (Wheel),
+    0x16, 0x01, 0x80,  //     Logical Minimum (-32767),
+    0x26, 0xFF, 0x7F,  //     Logical Maximum (32767),
+    0x81, 0x06,        //     Input (Data, Variable, Relative),
+    0x05, 0x0C,        //     Usage Page (Consumer),
+    0x0A, 0x38, 0x02,  //     Usage (AC Pan),
+    0x95, 0x01,        //     Report Count (1),
+    0x81, 0x06,        //     Input (Data, Variable, Relative),
+    0xC0,              //   End Collection
+    0xC0,              // End Collection
+];
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Default, PackedStruct)]
+#[packed_struct(endian = "lsb", size_bytes = "9")]
+pub struct HighResMouseReport {
+    #[packed_field]
+    pub buttons: u8,
+    #[packed_field]
+    pub x: i16,
+    #[packed_field]
+    pub y: i16,
+    #[packed_field]
+    pub vertical_wheel: i16,
+    #[packed_field]
+    pub horizontal_wheel: i16,
+}
+
+impl HighResMouseReport {
+    /// The report as a [`BootMouseReport`], with motion clamped to `-127` to `127`
+    pub fn to_boot_report(&self) -> BootMouseReport {
+        let clamp = |v: i16| v.clamp(-127, 127) as i8;
+        BootMouseReport {
+            buttons: self.buttons,
+            x: clamp(self.x),
+            y: clamp(self.y),
+        }
+    }
+}
+
 pub struct BootMouseInterface<'a, B: UsbBus> {
     inner: RawInterface<'a, B>,
 }
 
+/// Boot mouse interface, see [`BootMouseInterface`]
+pub type BootMouse<'a, B> = BootMouseInterface<'a, B>;
+
 impl<'a, B: UsbBus> BootMouseInterface<'a, B> {
+    delegate! {
+        to self.inner {
+            pub fn protocol(&self) -> HidProtocol;
+            pub fn report_latency(&self) -> LatencyHistogram;
+            pub fn clear_report_latency(&self);
+            pub fn host_stalls(&self) -> u32;
+            pub fn host_stalled(&self) -> bool;
+            pub fn write_ready(&self) -> bool;
+        }
+    }
+
     pub fn write_report(&self, report: &BootMouseReport) -> Result<(), UsbHidError> {
         let data = report.pack().map_err(|e| {
             error!("Error packing BootMouseReport: {:?}", e);
             UsbHidError::SerializationError
         })?;
-        self.inner
-            .write_report(&data)
-            .map(|_| ())
-            .map_err(UsbHidError::from)
+        self.inner.write_report(&data).map(|_| ())
     }
 
     pub fn default_config() -> WrappedInterfaceConfig<Self, RawInterfaceConfig<'a>> {
+        Self::default_config_with_descriptor(BOOT_MOUSE_REPORT_DESCRIPTOR)
+    }
+
+    /// Default configuration with an alternative report descriptor
+    ///
+    /// The descriptor must describe reports with the same layout as [`BootMouseReport`]
+    pub fn default_config_with_descriptor(
+        report_descriptor: &'a [u8],
+    ) -> WrappedInterfaceConfig<Self, RawInterfaceConfig<'a>> {
         WrappedInterfaceConfig::new(
-            RawInterfaceBuilder::new(BOOT_MOUSE_REPORT_DESCRIPTOR)
+            RawInterfaceBuilder::new(report_descriptor)
                 .boot_device(InterfaceProtocol::Mouse)
                 .description("Mouse")
-                .in_endpoint(UsbPacketSize::Bytes8, 10.millis())
-                .unwrap()
+                .in_endpoint(UsbPacketSize::Bytes8, PollInterval::millis(10))
                 .without_out_endpoint()
-                .build(),
+                .build()
+                .unwrap(),
             (),
         )
     }
 }
 
+impl<'a, B: UsbBus + 'a> DefaultConfig<'a, B> for BootMouseInterface<'a, B> {
+    type Config = WrappedInterfaceConfig<Self, RawInterfaceConfig<'a>>;
+
+    fn default_config() -> Self::Config {
+        Self::default_config()
+    }
+}
+
 impl<'a, B: UsbBus> InterfaceClass<'a> for BootMouseInterface<'a, B> {
     delegate! {
         to self.inner{
            fn report_descriptor(&self) -> &'_ [u8];
+           fn hid_descriptor_body(&self) -> HidDescriptorBodyBytes;
+           fn class_descriptor(&self, descriptor_type: u8, index: u8) -> Option<&'_ [u8]>;
+           fn take_wakeup_request(&self) -> bool;
            fn id(&self) -> InterfaceNumber;
            fn write_descriptors(&self, writer: &mut DescriptorWriter) -> usb_device::Result<()>;
            fn get_string(&self, index: StringIndex, _lang_id: u16) -> Option<&'_ str>;
@@ -159,8 +302,12 @@ impl<'a, B: UsbBus> InterfaceClass<'a> for BootMouseInterface<'a, B> {
            fn get_report_ack(&mut self) -> usb_device::Result<()>;
            fn set_idle(&mut self, report_id: u8, value: u8);
            fn get_idle(&self, report_id: u8) -> u8;
-           fn set_protocol(&mut self, protocol: HidProtocol);
+           fn set_protocol(&mut self, protocol: HidProtocol) -> usb_device::Result<()>;
            fn get_protocol(&self) -> HidProtocol;
+           fn out_endpoint_address(&self) -> Option<EndpointAddress>;
+           fn in_endpoint_address(&self) -> EndpointAddress;
+           fn endpoint_in_complete(&self, addr: EndpointAddress);
+           fn endpoint_out(&mut self);
         }
     }
 }
@@ -174,36 +321,81 @@ pub struct WheelMouseInterface<'a, B: UsbBus> {
     inner: RawInterface<'a, B>,
 }
 
+/// Boot compatible wheel mouse interface, see [`WheelMouseInterface`]
+pub type WheelMouse<'a, B> = WheelMouseInterface<'a, B>;
+
 impl<'a, B: UsbBus> WheelMouseInterface<'a, B> {
+    delegate! {
+        to self.inner {
+            pub fn protocol(&self) -> HidProtocol;
+            pub fn report_latency(&self) -> LatencyHistogram;
+            pub fn clear_report_latency(&self);
+            pub fn host_stalls(&self) -> u32;
+            pub fn host_stalled(&self) -> bool;
+            pub fn write_ready(&self) -> bool;
+            pub fn report_layout(&self) -> HidProtocol;
+        }
+    }
+
+    /// Writes the report, as a [`BootMouseReport`] while in boot protocol
     pub fn write_report(&self, report: &WheelMouseReport) -> Result<(), UsbHidError> {
-        let data = report.pack().map_err(|e| {
-            error!("Error packing WheelMouseReport: {:?}", e);
-            UsbHidError::SerializationError
-        })?;
-        self.inner
-            .write_report(&data)
+        let result = match self.report_layout() {
+            HidProtocol::Boot => BootMouseReport {
+                buttons: report.buttons,
+                x: report.x,
+                y: report.y,
+            }
+            .pack()
+            .map(|data| self.inner.write_report(&data)),
+            HidProtocol::Report => report.pack().map(|data| self.inner.write_report(&data)),
+        };
+        result
+            .map_err(|e| {
+                error!("Error packing WheelMouseReport: {:?}", e);
+                UsbHidError::SerializationError
+            })?
             .map(|_| ())
-            .map_err(UsbHidError::from)
     }
 
     pub fn default_config() -> WrappedInterfaceConfig<Self, RawInterfaceConfig<'a>> {
+        Self::default_config_with_descriptor(WHEEL_MOUSE_REPORT_DESCRIPTOR)
+    }
+
+    /// Default configuration with an alternative report descriptor
+    ///
+    /// The descriptor must describe reports with the same layout as [`WheelMouseReport`]
+    pub fn default_config_with_descriptor(
+        report_descriptor: &'a [u8],
+    ) -> WrappedInterfaceConfig<Self, RawInterfaceConfig<'a>> {
         WrappedInterfaceConfig::new(
-            RawInterfaceBuilder::new(WHEEL_MOUSE_REPORT_DESCRIPTOR)
+            RawInterfaceBuilder::new(report_descriptor)
                 .boot_device(InterfaceProtocol::Mouse)
+                .boot_report_descriptor(BOOT_MOUSE_REPORT_DESCRIPTOR)
                 .description("Wheel Mouse")
-                .in_endpoint(UsbPacketSize::Bytes8, 10.millis())
-                .unwrap()
+                .in_endpoint(UsbPacketSize::Bytes8, PollInterval::millis(10))
                 .without_out_endpoint()
-                .build(),
+                .build()
+                .unwrap(),
             (),
         )
     }
 }
 
+impl<'a, B: UsbBus + 'a> DefaultConfig<'a, B> for WheelMouseInterface<'a, B> {
+    type Config = WrappedInterfaceConfig<Self, RawInterfaceConfig<'a>>;
+
+    fn default_config() -> Self::Config {
+        Self::default_config()
+    }
+}
+
 impl<'a, B: UsbBus> InterfaceClass<'a> for WheelMouseInterface<'a, B> {
     delegate! {
         to self.inner{
            fn report_descriptor(&self) -> &'_ [u8];
+           fn hid_descriptor_body(&self) -> HidDescriptorBodyBytes;
+           fn class_descriptor(&self, descriptor_type: u8, index: u8) -> Option<&'_ [u8]>;
+           fn take_wakeup_request(&self) -> bool;
            fn id(&self) -> InterfaceNumber;
            fn write_descriptors(&self, writer: &mut DescriptorWriter) -> usb_device::Result<()>;
            fn get_string(&self, index: StringIndex, _lang_id: u16) -> Option<&'_ str>;
@@ -213,8 +405,12 @@ impl<'a, B: UsbBus> InterfaceClass<'a> for WheelMouseInterface<'a, B> {
            fn get_report_ack(&mut self) -> usb_device::Result<()>;
            fn set_idle(&mut self, report_id: u8, value: u8);
            fn get_idle(&self, report_id: u8) -> u8;
-           fn set_protocol(&mut self, protocol: HidProtocol);
+           fn set_protocol(&mut self, protocol: HidProtocol) -> usb_device::Result<()>;
            fn get_protocol(&self) -> HidProtocol;
+           fn out_endpoint_address(&self) -> Option<EndpointAddress>;
+           fn in_endpoint_address(&self) -> EndpointAddress;
+           fn endpoint_in_complete(&self, addr: EndpointAddress);
+           fn endpoint_out(&mut self);
         }
     }
 }
@@ -224,3 +420,267 @@ impl<'a, B: UsbBus> WrappedInterface<'a, B, RawInterface<'a, B>> for WheelMouseI
         Self { inner: interface }
     }
 }
+
+pub struct AbsoluteMouseInterface<'a, B: UsbBus> {
+    inner: RawInterface<'a, B>,
+}
+
+/// Absolute positioning mouse interface, see [`AbsoluteMouseInterface`]
+pub type AbsoluteMouse<'a, B> = AbsoluteMouseInterface<'a, B>;
+
+impl<'a, B: UsbBus> AbsoluteMouseInterface<'a, B> {
+    delegate! {
+        to self.inner {
+            pub fn protocol(&self) -> HidProtocol;
+            pub fn report_latency(&self) -> LatencyHistogram;
+            pub fn clear_report_latency(&self);
+            pub fn host_stalls(&self) -> u32;
+            pub fn host_stalled(&self) -> bool;
+            pub fn write_ready(&self) -> bool;
+        }
+    }
+
+    pub fn write_report(&self, report: &AbsoluteMouseReport) -> Result<(), UsbHidError> {
+        let data = report.pack().map_err(|e| {
+            error!("Error packing AbsoluteMouseReport: {:?}", e);
+            UsbHidError::SerializationError
+        })?;
+        self.inner.write_report(&data).map(|_| ())
+    }
+
+    pub fn default_config() -> WrappedInterfaceConfig<Self, RawInterfaceConfig<'a>> {
+        Self::default_config_with_descriptor(ABSOLUTE_MOUSE_REPORT_DESCRIPTOR)
+    }
+
+    /// Default configuration with an alternative report descriptor
+    ///
+    /// The descriptor must describe reports with the same layout as [`AbsoluteMouseReport`]
+    pub fn default_config_with_descriptor(
+        report_descriptor: &'a [u8],
+    ) -> WrappedInterfaceConfig<Self, RawInterfaceConfig<'a>> {
+        WrappedInterfaceConfig::new(
+            RawInterfaceBuilder::new(report_descriptor)
+                .description("Absolute Mouse")
+                .in_endpoint(UsbPacketSize::Bytes8, PollInterval::millis(10))
+                .without_out_endpoint()
+                .build()
+                .unwrap(),
+            (),
+        )
+    }
+}
+
+impl<'a, B: UsbBus + 'a> DefaultConfig<'a, B> for AbsoluteMouseInterface<'a, B> {
+    type Config = WrappedInterfaceConfig<Self, RawInterfaceConfig<'a>>;
+
+    fn default_config() -> Self::Config {
+        Self::default_config()
+    }
+}
+
+impl<'a, B: UsbBus> InterfaceClass<'a> for AbsoluteMouseInterface<'a, B> {
+    delegate! {
+        to self.inner{
+           fn report_descriptor(&self) -> &'_ [u8];
+           fn hid_descriptor_body(&self) -> HidDescriptorBodyBytes;
+           fn class_descriptor(&self, descriptor_type: u8, index: u8) -> Option<&'_ [u8]>;
+           fn take_wakeup_request(&self) -> bool;
+           fn id(&self) -> InterfaceNumber;
+           fn write_descriptors(&self, writer: &mut DescriptorWriter) -> usb_device::Result<()>;
+           fn get_string(&self, index: StringIndex, _lang_id: u16) -> Option<&'_ str>;
+           fn reset(&mut self);
+           fn set_report(&mut self, data: &[u8]) -> usb_device::Result<()>;
+           fn get_report(&mut self, data: &mut [u8]) -> usb_device::Result<usize>;
+           fn get_report_ack(&mut self) -> usb_device::Result<()>;
+           fn set_idle(&mut self, report_id: u8, value: u8);
+           fn get_idle(&self, report_id: u8) -> u8;
+           fn set_protocol(&mut self, protocol: HidProtocol) -> usb_device::Result<()>;
+           fn get_protocol(&self) -> HidProtocol;
+           fn out_endpoint_address(&self) -> Option<EndpointAddress>;
+           fn in_endpoint_address(&self) -> EndpointAddress;
+           fn endpoint_in_complete(&self, addr: EndpointAddress);
+           fn endpoint_out(&mut self);
+        }
+    }
+}
+
+impl<'a, B: UsbBus> WrappedInterface<'a, B, RawInterface<'a, B>> for AbsoluteMouseInterface<'a, B> {
+    fn new(interface: RawInterface<'a, B>, _: ()) -> Self {
+        Self { inner: interface }
+    }
+}
+
+pub struct HighResMouseInterface<'a, B: UsbBus> {
+    inner: RawInterface<'a, B>,
+}
+
+/// Boot compatible high resolution mouse interface, see [`HighResMouseInterface`]
+pub type HighResMouse<'a, B> = HighResMouseInterface<'a, B>;
+
+impl<'a, B: UsbBus> HighResMouseInterface<'a, B> {
+    delegate! {
+        to self.inner {
+            pub fn protocol(&self) -> HidProtocol;
+            pub fn report_latency(&self) -> LatencyHistogram;
+            pub fn clear_report_latency(&self);
+            pub fn host_stalls(&self) -> u32;
+            pub fn host_stalled(&self) -> bool;
+            pub fn write_ready(&self) -> bool;
+            pub fn report_layout(&self) -> HidProtocol;
+        }
+    }
+
+    /// Writes the report, as a [`BootMouseReport`] while in boot protocol
+    pub fn write_report(&self, report: &HighResMouseReport) -> Result<(), UsbHidError> {
+        let result = match self.report_layout() {
+            HidProtocol::Boot => report
+                .to_boot_report()
+                .pack()
+                .map(|data| self.inner.write_report(&data)),
+            HidProtocol::Report => report.pack().map(|data| self.inner.write_report(&data)),
+        };
+        result
+            .map_err(|e| {
+                error!("Error packing HighResMouseReport: {:?}", e);
+                UsbHidError::SerializationError
+            })?
+            .map(|_| ())
+    }
+
+    pub fn default_config() -> WrappedInterfaceConfig<Self, RawInterfaceConfig<'a>> {
+        Self::default_config_with_descriptor(HIGH_RES_MOUSE_REPORT_DESCRIPTOR)
+    }
+
+    /// Default configuration with an alternative report descriptor
+    ///
+    /// The descriptor must describe reports with the same layout as [`HighResMouseReport`]
+    pub fn default_config_with_descriptor(
+        report_descriptor: &'a [u8],
+    ) -> WrappedInterfaceConfig<Self, RawInterfaceConfig<'a>> {
+        WrappedInterfaceConfig::new(
+            RawInterfaceBuilder::new(report_descriptor)
+                .boot_device(InterfaceProtocol::Mouse)
+                .boot_report_descriptor(BOOT_MOUSE_REPORT_DESCRIPTOR)
+                .description("High Resolution Mouse")
+                .in_endpoint(UsbPacketSize::Bytes16, PollInterval::millis(1))
+                .without_out_endpoint()
+                .build()
+                .unwrap(),
+            (),
+        )
+    }
+}
+
+impl<'a, B: UsbBus + 'a> DefaultConfig<'a, B> for HighResMouseInterface<'a, B> {
+    type Config = WrappedInterfaceConfig<Self, RawInterfaceConfig<'a>>;
+
+    fn default_config() -> Self::Config {
+        Self::default_config()
+    }
+}
+
+impl<'a, B: UsbBus> InterfaceClass<'a> for HighResMouseInterface<'a, B> {
+    delegate! {
+        to self.inner{
+           fn report_descriptor(&self) -> &'_ [u8];
+           fn hid_descriptor_body(&self) -> HidDescriptorBodyBytes;
+           fn class_descriptor(&self, descriptor_type: u8, index: u8) -> Option<&'_ [u8]>;
+           fn take_wakeup_request(&self) -> bool;
+           fn id(&self) -> InterfaceNumber;
+           fn write_descriptors(&self, writer: &mut DescriptorWriter) -> usb_device::Result<()>;
+           fn get_string(&self, index: StringIndex, _lang_id: u16) -> Option<&'_ str>;
+           fn reset(&mut self);
+           fn set_report(&mut self, data: &[u8]) -> usb_device::Result<()>;
+           fn get_report(&mut self, data: &mut [u8]) -> usb_device::Result<usize>;
+           fn get_report_ack(&mut self) -> usb_device::Result<()>;
+           fn set_idle(&mut self, report_id: u8, value: u8);
+           fn get_idle(&self, report_id: u8) -> u8;
+           fn set_protocol(&mut self, protocol: HidProtocol) -> usb_device::Result<()>;
+           fn get_protocol(&self) -> HidProtocol;
+           fn out_endpoint_address(&self) -> Option<EndpointAddress>;
+           fn in_endpoint_address(&self) -> EndpointAddress;
+           fn endpoint_in_complete(&self, addr: EndpointAddress);
+           fn endpoint_out(&mut self);
+        }
+    }
+}
+
+impl<'a, B: UsbBus> WrappedInterface<'a, B, RawInterface<'a, B>> for HighResMouseInterface<'a, B> {
+    fn new(interface: RawInterface<'a, B>, _: ()) -> Self {
+        Self { inner: interface }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use packed_struct::PackedStruct;
+
+    use crate::device::mouse::{BootMouseReport, HighResMouseReport};
+    #[cfg(feature = "macros")]
+    use crate::{device::mouse::WheelMouseReport, hid_report};
+
+    #[test]
+    fn pack_high_res_report() {
+        let report = HighResMouseReport {
+            buttons: 0x01,
+            x: -1000,
+            y: 300,
+            vertical_wheel: -1,
+            horizontal_wheel: 0x0102,
+        };
+        assert_eq!(
+            report.pack().unwrap(),
+            [0x01, 0x18, 0xFC, 0x2C, 0x01, 0xFF, 0xFF, 0x02, 0x01]
+        );
+        assert_eq!(
+            report.to_boot_report(),
+            BootMouseReport {
+                buttons: 0x01,
+                x: -127,
+                y: 127,
+            }
+        );
+    }
+
+    #[cfg(feature = "macros")]
+    hid_report! {
+        struct GeneratedWheelMouseReport = [
+            0x05, 0x01, 0x09, 0x02, 0xA1, 0x01, 0x09, 0x01, 0xA1, 0x00,
+            0x95, 0x08, 0x75, 0x01, 0x05, 0x09, 0x19, 0x01, 0x29, 0x08,
+            0x15, 0x00, 0x25, 0x01, 0x81, 0x02, 0x75, 0x08, 0x95, 0x02,
+            0x05, 0x01, 0x09, 0x30, 0x09, 0x31, 0x15, 0x81, 0x25, 0x7F,
+            0x81, 0x06, 0x15, 0x81, 0x25, 0x7F, 0x09, 0x38, 0x75, 0x08,
+            0x95, 0x01, 0x81, 0x06, 0x05, 0x0C, 0x0A, 0x38, 0x02, 0x95,
+            0x01, 0x81, 0x06, 0xC0, 0xC0,
+        ];
+    }
+
+    #[cfg(feature = "macros")]
+    #[test]
+    fn generated_report_matches_wheel_mouse_report() {
+        let generated = GeneratedWheelMouseReport {
+            buttons: 0x81,
+            x: -2,
+            y: 3,
+            wheel: -1,
+            pan: 5,
+        };
+        let report = WheelMouseReport {
+            buttons: 0x81,
+            x: -2,
+            y: 3,
+            vertical_wheel: -1,
+            horizontal_wheel: 5,
+        };
+
+        assert_eq!(generated.pack(), report.pack().unwrap());
+        assert_eq!(
+            PackedStruct::pack(&generated).unwrap(),
+            report.pack().unwrap()
+        );
+        assert_eq!(
+            GeneratedWheelMouseReport::unpack(&report.pack().unwrap()),
+            Some(generated)
+        );
+    }
+}
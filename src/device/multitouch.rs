@@ -0,0 +1,477 @@
+//!HID multi-touch screens
+//!
+//! Contacts are reported in hybrid mode: each input report carries up to
+//! [`CONTACTS_PER_REPORT`] contacts, and a frame of `N` contacts is split across as many reports
+//! as needed. The first report of a frame holds the number of contacts in the frame, the rest a
+//! contact count of zero. Hosts read the maximum number of contacts, `N`, from the Contact Count
+//! Maximum feature report before accepting any input reports.
+use core::cell::RefCell;
+use core::default::Default;
+
+use delegate::delegate;
+use heapless::Vec;
+use packed_struct::prelude::*;
+use usb_device::bus::{InterfaceNumber, StringIndex, UsbBus};
+use usb_device::class_prelude::{DescriptorWriter, EndpointAddress};
+use usb_device::UsbError;
+
+use crate::hid_class::descriptor::{HidProtocol, ReportType};
+use crate::hid_class::prelude::*;
+use crate::interface::duration::PollInterval;
+use crate::interface::feature::{write_with_id, FeatureResponder};
+use crate::interface::latency::LatencyHistogram;
+use crate::interface::raw::{RawInterface, RawInterfaceConfig};
+use crate::interface::{
+    DefaultConfig, HidDescriptorBodyBytes, InterfaceClass, WrappedInterface, WrappedInterfaceConfig,
+};
+use crate::UsbHidError;
+
+/// Number of contacts in each input report
+pub const CONTACTS_PER_REPORT: usize = 2;
+pub const MULTI_TOUCH_REPORT_ID: u8 = 0x01;
+pub const CONTACT_COUNT_MAXIMUM_REPORT_ID: u8 = 0x02;
+
+/// Multi-touch screen with two contacts per input report
+///
+/// * Input report 1 - [`MultiTouchReport`], X and Y are `0` to
+///   [`TOUCH_SCREEN_LOGICAL_MAX`](crate::device::touchscreen::TOUCH_SCREEN_LOGICAL_MAX), scan time
+///   is in units of 100µs
+/// * Feature report 2 - Contact Count Maximum, one byte
+#[rustfmt::skip]
+pub const MULTI_TOUCH_REPORT_DESCRIPTOR: &[u8] = &[
+    0x05, 0x0D,        // Usage Page (Digitizers),
+    0x09, 0x04,        // Usage (Touch Screen),
+    0xA1, 0x01,        // Collection (Application),
+    0x85, 0x01,        //   Report ID (1),
+    0x09, 0x22,        //   Usage (Finger),
+    0xA1, 0x02,        //   Collection (Logical),
+    0x09, 0x42,        //     Usage (Tip Switch),
+    0x15, 0x00,        //     Logical Minimum (0),
+    0x25, 0x01,        //     Logical Maximum (1),
+    0x75, 0x01,        //     Report Size (1),
+    0x95, 0x01,        //     Report Count (1),
+    0x81, 0x02,        //     Input (Data, Variable, Absolute),
+    0x95, 0x07,        //     Report Count (7),
+    0x81, 0x03,        //     Input (Constant, Variable, Absolute),
+    0x09, 0x51,        //     Usage (Contact Identifier),
+    0x26, 0xFF, 0x00,  //     Logical Maximum (255),
+    0x75, 0x08,        //     Report Size (8),
+    0x95, 0x01,        //     Report Count (1),
+    0x81, 0x02,        //     Input (Data, Variable, Absolute),
+    0x05, 0x01,        //     Usage Page (Generic Desktop),
+    0x09, 0x30,        //     Usage (X),
+    0x09, 0x31,        //     Usage (Y),
+    0x26, 0xFF, 0x7F,  //     Logical Maximum (32767),
+    0x75, 0x10,        //     Report Size (16),
+    0x95, 0x02,        //     Report Count (2),
+    0x81, 0x02,        //     Input (Data, Variable, Absolute),
+    0x05, 0x0D,        //     Usage Page (Digitizers),
+    0xC0,              //   End Collection
+    0x09, 0x22,        //   Usage (Finger),
+    0xA1, 0x02,        //   Collection (Logical),
+    0x09, 0x42,        //     Usage (Tip Switch),
+    0x15, 0x00,        //     Logical Minimum (0),
+    0x25, 0x01,        //     Logical Maximum (1),
+    0x75, 0x01,        //     Report Size (1),
+    0x95, 0x01,        //     Report Count (1),
+    0x81, 0x02,        //     Input (Data, Variable, Absolute),
+    0x95, 0x07,        //     Report Count (7),
+    0x81, 0x03,        //     Input (Constant, Variable, Absolute),
+    0x09, 0x51,        //     Usage (Contact Identifier),
+    0x26, 0xFF, 0x00,  //     Logical Maximum (255),
+    0x75, 0x08,        //     Report Size (8),
+    0x95, 0x01,        //     Report Count (1),
+    0x81, 0x02,        //     Input (Data, Variable, Absolute),
+    0x05, 0x01,        //     Usage Page (Generic Desktop),
+    0x09, 0x30,        //     Usage (X),
+    0x09, 0x31,        //     Usage (Y),
+    0x26, 0xFF, 0x7F,  //     Logical Maximum (32767),
+    0x75, 0x10,        //     Report Size (16),
+    0x95, 0x02,        //     Report Count (2),
+    0x81, 0x02,        //     Input (Data, Variable, Absolute),
+    0x05, 0x0D,        //     Usage Page (Digitizers),
+    0xC0,              //   End Collection
+    0x09, 0x56,        //   Usage (Scan Time),
+    0x27, 0xFF, 0xFF, 0x00, 0x00, // Logical Maximum (65535),
+    0x66, 0x01, 0x10,  //   Unit (Seconds),
+    0x55, 0x0C,        //   Unit Exponent (-4),
+    0x75, 0x10,        //   Report Size (16),
+    0x95, 0x01,        //   Report Count (1),
+    0x81, 0x02,        //   Input (Data, Variable, Absolute),
+    0x55, 0x00,        //   Unit Exponent (0),
+    0x66, 0x00, 0x00,  //   Unit (None),
+    0x09, 0x54,        //   Usage (Contact Count),
+    0x26, 0xFF, 0x00,  //   Logical Maximum (255),
+    0x75, 0x08,        //   Report Size (8),
+    0x81, 0x02,        //   Input (Data, Variable, Absolute),
+    0x85, 0x02,        //   Report ID (2),
+    0x09, 0x55,        //   Usage (Contact Count Maximum),
+    0xB1, 0x02,        //   Feature (Data, Variable, Absolute),
+    0xC0,              // End Collection
+];
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Default, PackedStruct)]
+#[packed_struct(endian = "lsb", bit_numbering = "msb0", size_bytes = "6")]
+pub struct TouchContact {
+    #[packed_field(bits = "7")]
+    pub tip_switch: bool,
+    #[packed_field(bytes = "1")]
+    pub contact_id: u8,
+    #[packed_field(bytes = "2:3")]
+    pub x: u16,
+    #[packed_field(bytes = "4:5")]
+    pub y: u16,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Default, PackedStruct)]
+#[packed_struct(endian = "lsb", size_bytes = "15")]
+pub struct MultiTouchReport {
+    #[packed_field(element_size_bytes = "6")]
+    pub contacts: [TouchContact; 2],
+    /// Time of the frame's scan in units of 100µs, wrapping
+    #[packed_field]
+    pub scan_time: u16,
+    /// Contacts in the frame for the first report of the frame, otherwise `0`
+    #[packed_field]
+    pub contact_count: u8,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ContactError {
+    /// All `N` contacts are in use
+    Full,
+    /// A contact with the same identifier is already touching
+    Duplicate,
+    /// No contact with the identifier is touching
+    Unknown,
+}
+
+struct Frame<const N: usize> {
+    contacts: Vec<TouchContact, N>,
+    next: usize,
+    scan_time: u16,
+}
+
+pub struct MultiTouchInterface<'a, B: UsbBus, const N: usize> {
+    inner: RawInterface<'a, B>,
+    contacts: RefCell<Vec<TouchContact, N>>,
+    frame: RefCell<Option<Frame<N>>>,
+    feature_responder: FeatureResponder,
+}
+
+/// Multi-touch interface tracking up to `N` contacts, see [`MultiTouchInterface`]
+pub type MultiTouch<'a, B, const N: usize> = MultiTouchInterface<'a, B, N>;
+
+impl<'a, B: UsbBus, const N: usize> MultiTouchInterface<'a, B, N> {
+    delegate! {
+        to self.inner {
+            pub fn protocol(&self) -> HidProtocol;
+            pub fn report_latency(&self) -> LatencyHistogram;
+            pub fn clear_report_latency(&self);
+            pub fn host_stalls(&self) -> u32;
+            pub fn host_stalled(&self) -> bool;
+            pub fn write_ready(&self) -> bool;
+        }
+    }
+
+    /// Adds a contact touching the screen at `x`, `y`
+    pub fn add_contact(&self, contact_id: u8, x: u16, y: u16) -> Result<(), ContactError> {
+        let mut contacts = self.contacts.borrow_mut();
+        let contact = TouchContact {
+            tip_switch: true,
+            contact_id,
+            x,
+            y,
+        };
+        match contacts.iter_mut().find(|c| c.contact_id == contact_id) {
+            Some(c) if c.tip_switch => Err(ContactError::Duplicate),
+            // Released since the last frame, touching again
+            Some(c) => {
+                *c = contact;
+                Ok(())
+            }
+            None => contacts.push(contact).map_err(|_| ContactError::Full),
+        }
+    }
+
+    /// Moves a touching contact to `x`, `y`
+    pub fn update_contact(&self, contact_id: u8, x: u16, y: u16) -> Result<(), ContactError> {
+        let mut contacts = self.contacts.borrow_mut();
+        let contact = contacts
+            .iter_mut()
+            .find(|c| c.contact_id == contact_id && c.tip_switch)
+            .ok_or(ContactError::Unknown)?;
+        contact.x = x;
+        contact.y = y;
+        Ok(())
+    }
+
+    /// Lifts a contact from the screen, it is reported as released in the next frame
+    pub fn release_contact(&self, contact_id: u8) -> Result<(), ContactError> {
+        let mut contacts = self.contacts.borrow_mut();
+        let contact = contacts
+            .iter_mut()
+            .find(|c| c.contact_id == contact_id && c.tip_switch)
+            .ok_or(ContactError::Unknown)?;
+        contact.tip_switch = false;
+        Ok(())
+    }
+
+    /// Number of contacts touching the screen
+    pub fn contact_count(&self) -> usize {
+        self.contacts
+            .borrow()
+            .iter()
+            .filter(|c| c.tip_switch)
+            .count()
+    }
+
+    /// Writes the next report of the current frame, starting a new frame of the current contacts
+    /// if none is in progress
+    ///
+    /// Returns [`UsbHidError::WouldBlock`] until every report of the frame has been written.
+    /// Contacts can be changed while a frame is in progress, the changes are sent in the next
+    /// frame.
+    pub fn write_frame(&self, scan_time: u16) -> Result<(), UsbHidError> {
+        let mut frame = self.frame.borrow_mut();
+        let frame_ref = frame.get_or_insert_with(|| {
+            let mut contacts = self.contacts.borrow_mut();
+            let frame = Frame {
+                contacts: contacts.clone(),
+                next: 0,
+                scan_time,
+            };
+            contacts.retain(|c| c.tip_switch);
+            frame
+        });
+
+        let mut report = MultiTouchReport {
+            scan_time: frame_ref.scan_time,
+            contact_count: if frame_ref.next == 0 {
+                frame_ref.contacts.len() as u8
+            } else {
+                0
+            },
+            ..Default::default()
+        };
+        for (slot, contact) in report
+            .contacts
+            .iter_mut()
+            .zip(&frame_ref.contacts[frame_ref.next..])
+        {
+            *slot = *contact;
+        }
+
+        let data = report.pack().map_err(|e| {
+            error!("Error packing MultiTouchReport: {:?}", e);
+            UsbHidError::SerializationError
+        })?;
+        let mut buffer = [0; 16];
+        buffer[0] = MULTI_TOUCH_REPORT_ID;
+        buffer[1..].copy_from_slice(&data);
+        self.inner.write_report(&buffer)?;
+
+        frame_ref.next += CONTACTS_PER_REPORT;
+        if frame_ref.next >= frame_ref.contacts.len() {
+            *frame = None;
+            Ok(())
+        } else {
+            Err(UsbHidError::WouldBlock)
+        }
+    }
+
+    pub fn default_config() -> WrappedInterfaceConfig<Self, RawInterfaceConfig<'a>> {
+        Self::default_config_with_descriptor(MULTI_TOUCH_REPORT_DESCRIPTOR)
+    }
+
+    /// Default configuration with an alternative report descriptor
+    ///
+    /// The descriptor must describe reports with the same layout and report IDs as
+    /// [`MULTI_TOUCH_REPORT_DESCRIPTOR`]
+    pub fn default_config_with_descriptor(
+        report_descriptor: &'a [u8],
+    ) -> WrappedInterfaceConfig<Self, RawInterfaceConfig<'a>> {
+        WrappedInterfaceConfig::new(
+            RawInterfaceBuilder::new(report_descriptor)
+                .description("Multi-touch Screen")
+                .in_endpoint(UsbPacketSize::Bytes16, PollInterval::millis(10))
+                .without_out_endpoint()
+                .build()
+                .unwrap(),
+            (),
+        )
+    }
+}
+
+impl<'a, B: UsbBus + 'a, const N: usize> DefaultConfig<'a, B> for MultiTouchInterface<'a, B, N> {
+    type Config = WrappedInterfaceConfig<Self, RawInterfaceConfig<'a>>;
+
+    fn default_config() -> Self::Config {
+        Self::default_config()
+    }
+}
+
+impl<'a, B: UsbBus, const N: usize> InterfaceClass<'a> for MultiTouchInterface<'a, B, N> {
+    delegate! {
+        to self.inner{
+           fn report_descriptor(&self) -> &'_ [u8];
+           fn hid_descriptor_body(&self) -> HidDescriptorBodyBytes;
+           fn class_descriptor(&self, descriptor_type: u8, index: u8) -> Option<&'_ [u8]>;
+           fn take_wakeup_request(&self) -> bool;
+           fn id(&self) -> InterfaceNumber;
+           fn write_descriptors(&self, writer: &mut DescriptorWriter) -> usb_device::Result<()>;
+           fn get_string(&self, index: StringIndex, _lang_id: u16) -> Option<&'_ str>;
+           fn set_report(&mut self, data: &[u8]) -> usb_device::Result<()>;
+           fn get_report(&mut self, data: &mut [u8]) -> usb_device::Result<usize>;
+           fn set_idle(&mut self, report_id: u8, value: u8);
+           fn get_idle(&self, report_id: u8) -> u8;
+           fn set_protocol(&mut self, protocol: HidProtocol) -> usb_device::Result<()>;
+           fn get_protocol(&self) -> HidProtocol;
+           fn out_endpoint_address(&self) -> Option<EndpointAddress>;
+           fn in_endpoint_address(&self) -> EndpointAddress;
+           fn endpoint_in_complete(&self, addr: EndpointAddress);
+           fn endpoint_out(&mut self);
+        }
+    }
+
+    fn reset(&mut self) {
+        self.inner.reset();
+        self.contacts.borrow_mut().clear();
+        *self.frame.borrow_mut() = None;
+        self.feature_responder.reset();
+    }
+
+    fn get_report_by_id(
+        &mut self,
+        report_type: ReportType,
+        report_id: u8,
+        data: &mut [u8],
+    ) -> usb_device::Result<usize> {
+        self.feature_responder
+            .get_report(
+                &mut self.inner,
+                report_type,
+                report_id,
+                data,
+                |data| match report_id {
+                    CONTACT_COUNT_MAXIMUM_REPORT_ID => {
+                        write_with_id(report_id, &[u8::try_from(N).unwrap_or(u8::MAX)], data)
+                    }
+                    _ => Err(UsbError::Unsupported),
+                },
+            )
+    }
+
+    fn get_report_ack(&mut self) -> usb_device::Result<()> {
+        self.feature_responder.get_report_ack(&mut self.inner)
+    }
+}
+
+impl<'a, B: UsbBus, const N: usize> WrappedInterface<'a, B, RawInterface<'a, B>>
+    for MultiTouchInterface<'a, B, N>
+{
+    fn new(interface: RawInterface<'a, B>, _: ()) -> Self {
+        Self {
+            inner: interface,
+            contacts: RefCell::new(Vec::new()),
+            frame: RefCell::new(None),
+            feature_responder: FeatureResponder::default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use packed_struct::PackedStruct;
+
+    use crate::device::multitouch::{MultiTouchReport, TouchContact};
+
+    #[test]
+    fn pack_report() {
+        let report = MultiTouchReport {
+            contacts: [
+                TouchContact {
+                    tip_switch: true,
+                    contact_id: 7,
+                    x: 0x1234,
+                    y: 0x7FFF,
+                },
+                TouchContact::default(),
+            ],
+            scan_time: 0x0102,
+            contact_count: 1,
+        };
+        assert_eq!(
+            report.pack().unwrap(),
+            [
+                0x01, 0x07, 0x34, 0x12, 0xFF, 0x7F, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x02, 0x01,
+                0x01
+            ]
+        );
+    }
+
+    #[cfg(feature = "simulation")]
+    #[test]
+    fn hybrid_frames_and_contact_count_maximum() {
+        use usb_device::bus::UsbBusAllocator;
+
+        use crate::device::multitouch::{ContactError, MultiTouch};
+        use crate::hid_class::prelude::*;
+        use crate::simulation::{control_transfer, device_builder, Response, SimBus};
+        use crate::UsbHidError;
+
+        let usb_alloc = UsbBusAllocator::new(SimBus::new());
+        let mut touch = UsbHidClassBuilder::new()
+            .add_interface(MultiTouch::<_, 3>::default_config())
+            .build(&usb_alloc)
+            .unwrap();
+        let mut usb_dev = device_builder(&usb_alloc, 64).build();
+
+        // GET_REPORT (Feature) Contact Count Maximum
+        assert_eq!(
+            control_transfer(
+                &mut usb_dev,
+                &mut [&mut touch],
+                [0xA1, 0x01, 0x02, 0x03, 0x00, 0x00, 0x02, 0x00],
+                &[]
+            ),
+            Response::Data(vec![0x02, 0x03])
+        );
+
+        let interface = touch.interface::<MultiTouch<'_, _, 3>, _>();
+        let ep = 1;
+        interface.add_contact(1, 10, 10).unwrap();
+        interface.add_contact(2, 20, 20).unwrap();
+        interface.add_contact(3, 30, 30).unwrap();
+        assert_eq!(interface.add_contact(4, 0, 0), Err(ContactError::Full));
+        assert_eq!(interface.add_contact(3, 0, 0), Err(ContactError::Duplicate));
+
+        assert!(matches!(
+            interface.write_frame(1),
+            Err(UsbHidError::WouldBlock)
+        ));
+        let first = usb_dev.bus().host_take_in(ep).unwrap();
+        assert_eq!((first[0], first[3]), (0x01, 0x0A));
+        assert_eq!(first[13..], [0x01, 0x00, 0x03]);
+
+        interface.release_contact(2).unwrap();
+        assert_eq!(
+            interface.update_contact(2, 0, 0),
+            Err(ContactError::Unknown)
+        );
+        assert!(interface.write_frame(1).is_ok());
+        let second = usb_dev.bus().host_take_in(ep).unwrap();
+        assert_eq!((second[2], second[8], second[15]), (0x03, 0x00, 0x00));
+
+        assert!(matches!(
+            interface.write_frame(2),
+            Err(UsbHidError::WouldBlock)
+        ));
+        let released = usb_dev.bus().host_take_in(ep).unwrap();
+        assert_eq!((released[1], released[7], released[8]), (0x01, 0x00, 0x02));
+        assert_eq!(released[15], 0x03);
+        assert!(interface.write_frame(2).is_ok());
+        usb_dev.bus().host_take_in(ep).unwrap();
+        assert_eq!(interface.contact_count(), 2);
+    }
+}
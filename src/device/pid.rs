@@ -0,0 +1,1020 @@
+//!HID Physical Interface Device (PID) force feedback
+//!
+//! Force feedback devices receive effects from the host as output and feature reports, described
+//! by the report descriptor fragments in this module. [`ForceFeedbackJoystickInterface`]
+//! dispatches these reports to an [`EffectHandler`], which plays the effects on the device's
+//! actuators.
+//!
+//! Output reports are received with `SET_REPORT` requests, as the interface has no OUT endpoint.
+//! The fragments describe a subset of the PID specification: constant force, ramp, periodic and
+//! condition effect types, envelopes and constant force parameters. Parameter blocks for the other
+//! effect types can be added with a custom report descriptor, their reports are read with
+//! [`ForceFeedbackJoystickInterface::read_report`].
+use crate::hid_class::descriptor::{concat_descriptor, descriptor_len, HidProtocol, ReportType};
+use core::default::Default;
+use delegate::delegate;
+use packed_struct::prelude::*;
+use usb_device::bus::{InterfaceNumber, StringIndex, UsbBus};
+use usb_device::class_prelude::{DescriptorWriter, EndpointAddress};
+use usb_device::UsbError;
+
+use crate::hid_class::prelude::*;
+use crate::interface::duration::PollInterval;
+use crate::interface::feature::{pack_with_id, FeatureResponder};
+use crate::interface::latency::LatencyHistogram;
+use crate::interface::raw::{RawInterface, RawInterfaceConfig};
+use crate::interface::{
+    HidDescriptorBodyBytes, InterfaceClass, WrappedInterface, WrappedInterfaceConfig,
+};
+use crate::UsbHidError;
+
+/// Number of effect blocks, the logical maximum of the Effect Block Index usage in the fragments
+pub const PID_MAX_EFFECTS: u8 = 40;
+
+pub const JOYSTICK_REPORT_ID: u8 = 0x01;
+pub const PID_STATE_REPORT_ID: u8 = 0x02;
+pub const SET_EFFECT_REPORT_ID: u8 = 0x01;
+pub const SET_ENVELOPE_REPORT_ID: u8 = 0x02;
+pub const SET_CONSTANT_FORCE_REPORT_ID: u8 = 0x05;
+pub const EFFECT_OPERATION_REPORT_ID: u8 = 0x0A;
+pub const BLOCK_FREE_REPORT_ID: u8 = 0x0B;
+pub const DEVICE_CONTROL_REPORT_ID: u8 = 0x0C;
+pub const DEVICE_GAIN_REPORT_ID: u8 = 0x0D;
+pub const CREATE_NEW_EFFECT_REPORT_ID: u8 = 0x11;
+pub const BLOCK_LOAD_REPORT_ID: u8 = 0x12;
+pub const PID_POOL_REPORT_ID: u8 = 0x13;
+
+/// PID State input report, see [`PidStateReport`]
+#[rustfmt::skip]
+pub const PID_STATE_REPORT_DESCRIPTOR: &[u8] = &[
+    0x05, 0x0F,        // Usage Page (PID),
+    0x09, 0x92,        // Usage (PID State Report),
+    0xA1, 0x02,        // Collection (Logical),
+    0x85, 0x02,        //   Report ID (2),
+    0x09, 0x9F,        //   Usage (Device Paused),
+    0x09, 0xA0,        //   Usage (Actuators Enabled),
+    0x09, 0xA4,        //   Usage (Safety Switch),
+    0x09, 0xA6,        //   Usage (Actuator Power),
+    0x09, 0x94,        //   Usage (Effect Playing),
+    0x15, 0x00,        //   Logical Minimum (0),
+    0x25, 0x01,        //   Logical Maximum (1),
+    0x75, 0x01,        //   Report Size (1),
+    0x95, 0x05,        //   Report Count (5),
+    0x81, 0x02,        //   Input (Data, Variable, Absolute),
+    0x95, 0x03,        //   Report Count (3),
+    0x81, 0x03,        //   Input (Constant, Variable, Absolute),
+    0x09, 0x22,        //   Usage (Effect Block Index),
+    0x15, 0x01,        //   Logical Minimum (1),
+    0x25, 0x28,        //   Logical Maximum (40),
+    0x75, 0x08,        //   Report Size (8),
+    0x95, 0x01,        //   Report Count (1),
+    0x81, 0x02,        //   Input (Data, Variable, Absolute),
+    0xC0,              // End Collection
+];
+
+/// Set Effect output report, see [`SetEffectReport`]
+#[rustfmt::skip]
+pub const PID_SET_EFFECT_REPORT_DESCRIPTOR: &[u8] = &[
+    0x05, 0x0F,        // Usage Page (PID),
+    0x09, 0x21,        // Usage (Set Effect Report),
+    0xA1, 0x02,        // Collection (Logical),
+    0x85, 0x01,        //   Report ID (1),
+    0x09, 0x22,        //   Usage (Effect Block Index),
+    0x15, 0x01,        //   Logical Minimum (1),
+    0x25, 0x28,        //   Logical Maximum (40),
+    0x75, 0x08,        //   Report Size (8),
+    0x95, 0x01,        //   Report Count (1),
+    0x91, 0x02,        //   Output (Data, Variable, Absolute),
+    0x09, 0x25,        //   Usage (Effect Type),
+    0xA1, 0x02,        //   Collection (Logical),
+    0x09, 0x26,        //     Usage (ET Constant Force),
+    0x09, 0x27,        //     Usage (ET Ramp),
+    0x09, 0x30,        //     Usage (ET Square),
+    0x09, 0x31,        //     Usage (ET Sine),
+    0x09, 0x32,        //     Usage (ET Triangle),
+    0x09, 0x33,        //     Usage (ET Sawtooth Up),
+    0x09, 0x34,        //     Usage (ET Sawtooth Down),
+    0x09, 0x40,        //     Usage (ET Spring),
+    0x09, 0x41,        //     Usage (ET Damper),
+    0x09, 0x42,        //     Usage (ET Inertia),
+    0x09, 0x43,        //     Usage (ET Friction),
+    0x15, 0x01,        //     Logical Minimum (1),
+    0x25, 0x0B,        //     Logical Maximum (11),
+    0x91, 0x00,        //     Output (Data, Array, Absolute),
+    0xC0,              //   End Collection
+    0x09, 0x50,        //   Usage (Duration),
+    0x09, 0x54,        //   Usage (Trigger Repeat Interval),
+    0x09, 0x51,        //   Usage (Sample Period),
+    0x15, 0x00,        //   Logical Minimum (0),
+    0x27, 0xFF, 0xFF, 0x00, 0x00, // Logical Maximum (65535),
+    0x66, 0x01, 0x10,  //   Unit (Seconds),
+    0x55, 0x0D,        //   Unit Exponent (-3),
+    0x75, 0x10,        //   Report Size (16),
+    0x95, 0x03,        //   Report Count (3),
+    0x91, 0x02,        //   Output (Data, Variable, Absolute),
+    0x55, 0x00,        //   Unit Exponent (0),
+    0x66, 0x00, 0x00,  //   Unit (None),
+    0x09, 0x52,        //   Usage (Gain),
+    0x26, 0xFF, 0x00,  //   Logical Maximum (255),
+    0x75, 0x08,        //   Report Size (8),
+    0x95, 0x01,        //   Report Count (1),
+    0x91, 0x02,        //   Output (Data, Variable, Absolute),
+    0x09, 0x53,        //   Usage (Trigger Button),
+    0x15, 0x01,        //   Logical Minimum (1),
+    0x25, 0x08,        //   Logical Maximum (8),
+    0x91, 0x42,        //   Output (Data, Variable, Absolute, Null State),
+    0x09, 0x55,        //   Usage (Axes Enable),
+    0xA1, 0x02,        //   Collection (Logical),
+    0x05, 0x01,        //     Usage Page (Generic Desktop),
+    0x09, 0x30,        //     Usage (X),
+    0x09, 0x31,        //     Usage (Y),
+    0x15, 0x00,        //     Logical Minimum (0),
+    0x25, 0x01,        //     Logical Maximum (1),
+    0x75, 0x01,        //     Report Size (1),
+    0x95, 0x02,        //     Report Count (2),
+    0x91, 0x02,        //     Output (Data, Variable, Absolute),
+    0xC0,              //   End Collection
+    0x05, 0x0F,        //   Usage Page (PID),
+    0x09, 0x56,        //   Usage (Direction Enable),
+    0x95, 0x01,        //   Report Count (1),
+    0x91, 0x02,        //   Output (Data, Variable, Absolute),
+    0x95, 0x05,        //   Report Count (5),
+    0x91, 0x03,        //   Output (Constant, Variable, Absolute),
+    0x09, 0x57,        //   Usage (Direction),
+    0xA1, 0x02,        //   Collection (Logical),
+    0x0B, 0x01, 0x00, 0x0A, 0x00, // Usage (Ordinals: Instance 1),
+    0x0B, 0x02, 0x00, 0x0A, 0x00, // Usage (Ordinals: Instance 2),
+    0x15, 0x00,        //     Logical Minimum (0),
+    0x26, 0xFF, 0x00,  //     Logical Maximum (255),
+    0x35, 0x00,        //     Physical Minimum (0),
+    0x46, 0x68, 0x01,  //     Physical Maximum (360),
+    0x65, 0x14,        //     Unit (Degrees),
+    0x75, 0x08,        //     Report Size (8),
+    0x95, 0x02,        //     Report Count (2),
+    0x91, 0x02,        //     Output (Data, Variable, Absolute),
+    0x65, 0x00,        //     Unit (None),
+    0x45, 0x00,        //     Physical Maximum (0),
+    0xC0,              //   End Collection
+    0xC0,              // End Collection
+];
+
+/// Set Envelope output report, see [`SetEnvelopeReport`]
+#[rustfmt::skip]
+pub const PID_SET_ENVELOPE_REPORT_DESCRIPTOR: &[u8] = &[
+    0x05, 0x0F,        // Usage Page (PID),
+    0x09, 0x5A,        // Usage (Set Envelope Report),
+    0xA1, 0x02,        // Collection (Logical),
+    0x85, 0x02,        //   Report ID (2),
+    0x09, 0x22,        //   Usage (Effect Block Index),
+    0x15, 0x01,        //   Logical Minimum (1),
+    0x25, 0x28,        //   Logical Maximum (40),
+    0x75, 0x08,        //   Report Size (8),
+    0x95, 0x01,        //   Report Count (1),
+    0x91, 0x02,        //   Output (Data, Variable, Absolute),
+    0x09, 0x5B,        //   Usage (Attack Level),
+    0x09, 0x5D,        //   Usage (Fade Level),
+    0x15, 0x00,        //   Logical Minimum (0),
+    0x26, 0xFF, 0x00,  //   Logical Maximum (255),
+    0x95, 0x02,        //   Report Count (2),
+    0x91, 0x02,        //   Output (Data, Variable, Absolute),
+    0x09, 0x5C,        //   Usage (Attack Time),
+    0x09, 0x5E,        //   Usage (Fade Time),
+    0x27, 0xFF, 0xFF, 0x00, 0x00, // Logical Maximum (65535),
+    0x66, 0x01, 0x10,  //   Unit (Seconds),
+    0x55, 0x0D,        //   Unit Exponent (-3),
+    0x75, 0x10,        //   Report Size (16),
+    0x91, 0x02,        //   Output (Data, Variable, Absolute),
+    0x55, 0x00,        //   Unit Exponent (0),
+    0x66, 0x00, 0x00,  //   Unit (None),
+    0xC0,              // End Collection
+];
+
+/// Set Constant Force output report, see [`SetConstantForceReport`]
+#[rustfmt::skip]
+pub const PID_SET_CONSTANT_FORCE_REPORT_DESCRIPTOR: &[u8] = &[
+    0x05, 0x0F,        // Usage Page (PID),
+    0x09, 0x73,        // Usage (Set Constant Force Report),
+    0xA1, 0x02,        // Collection (Logical),
+    0x85, 0x05,        //   Report ID (5),
+    0x09, 0x22,        //   Usage (Effect Block Index),
+    0x15, 0x01,        //   Logical Minimum (1),
+    0x25, 0x28,        //   Logical Maximum (40),
+    0x75, 0x08,        //   Report Size (8),
+    0x95, 0x01,        //   Report Count (1),
+    0x91, 0x02,        //   Output (Data, Variable, Absolute),
+    0x09, 0x70,        //   Usage (Magnitude),
+    0x16, 0x01, 0x80,  //   Logical Minimum (-32767),
+    0x26, 0xFF, 0x7F,  //   Logical Maximum (32767),
+    0x75, 0x10,        //   Report Size (16),
+    0x91, 0x02,        //   Output (Data, Variable, Absolute),
+    0xC0,              // End Collection
+];
+
+/// Effect Operation output report, see [`EffectOperationReport`]
+#[rustfmt::skip]
+pub const PID_EFFECT_OPERATION_REPORT_DESCRIPTOR: &[u8] = &[
+    0x05, 0x0F,        // Usage Page (PID),
+    0x09, 0x77,        // Usage (Effect Operation Report),
+    0xA1, 0x02,        // Collection (Logical),
+    0x85, 0x0A,        //   Report ID (10),
+    0x09, 0x22,        //   Usage (Effect Block Index),
+    0x15, 0x01,        //   Logical Minimum (1),
+    0x25, 0x28,        //   Logical Maximum (40),
+    0x75, 0x08,        //   Report Size (8),
+    0x95, 0x01,        //   Report Count (1),
+    0x91, 0x02,        //   Output (Data, Variable, Absolute),
+    0x09, 0x78,        //   Usage (Effect Operation),
+    0xA1, 0x02,        //   Collection (Logical),
+    0x09, 0x79,        //     Usage (Op Effect Start),
+    0x09, 0x7A,        //     Usage (Op Effect Start Solo),
+    0x09, 0x7B,        //     Usage (Op Effect Stop),
+    0x15, 0x01,        //     Logical Minimum (1),
+    0x25, 0x03,        //     Logical Maximum (3),
+    0x91, 0x00,        //     Output (Data, Array, Absolute),
+    0xC0,              //   End Collection
+    0x09, 0x7C,        //   Usage (Loop Count),
+    0x15, 0x00,        //   Logical Minimum (0),
+    0x26, 0xFF, 0x00,  //   Logical Maximum (255),
+    0x91, 0x02,        //   Output (Data, Variable, Absolute),
+    0xC0,              // End Collection
+];
+
+/// PID Block Free output report, the index of the effect block to free
+#[rustfmt::skip]
+pub const PID_BLOCK_FREE_REPORT_DESCRIPTOR: &[u8] = &[
+    0x05, 0x0F,        // Usage Page (PID),
+    0x09, 0x90,        // Usage (PID Block Free Report),
+    0xA1, 0x02,        // Collection (Logical),
+    0x85, 0x0B,        //   Report ID (11),
+    0x09, 0x22,        //   Usage (Effect Block Index),
+    0x15, 0x01,        //   Logical Minimum (1),
+    0x25, 0x28,        //   Logical Maximum (40),
+    0x75, 0x08,        //   Report Size (8),
+    0x95, 0x01,        //   Report Count (1),
+    0x91, 0x02,        //   Output (Data, Variable, Absolute),
+    0xC0,              // End Collection
+];
+
+/// PID Device Control output report, see [`DeviceControl`]
+#[rustfmt::skip]
+pub const PID_DEVICE_CONTROL_REPORT_DESCRIPTOR: &[u8] = &[
+    0x05, 0x0F,        // Usage Page (PID),
+    0x09, 0x95,        // Usage (PID Device Control Report),
+    0xA1, 0x02,        // Collection (Logical),
+    0x85, 0x0C,        //   Report ID (12),
+    0x09, 0x96,        //   Usage (PID Device Control),
+    0xA1, 0x02,        //   Collection (Logical),
+    0x09, 0x97,        //     Usage (DC Enable Actuators),
+    0x09, 0x98,        //     Usage (DC Disable Actuators),
+    0x09, 0x99,        //     Usage (DC Stop All Effects),
+    0x09, 0x9A,        //     Usage (DC Device Reset),
+    0x09, 0x9B,        //     Usage (DC Device Pause),
+    0x09, 0x9C,        //     Usage (DC Device Continue),
+    0x15, 0x01,        //     Logical Minimum (1),
+    0x25, 0x06,        //     Logical Maximum (6),
+    0x75, 0x08,        //     Report Size (8),
+    0x95, 0x01,        //     Report Count (1),
+    0x91, 0x00,        //     Output (Data, Array, Absolute),
+    0xC0,              //   End Collection
+    0xC0,              // End Collection
+];
+
+/// Device Gain output report, the overall gain applied to all effects
+#[rustfmt::skip]
+pub const PID_DEVICE_GAIN_REPORT_DESCRIPTOR: &[u8] = &[
+    0x05, 0x0F,        // Usage Page (PID),
+    0x09, 0x7D,        // Usage (Device Gain Report),
+    0xA1, 0x02,        // Collection (Logical),
+    0x85, 0x0D,        //   Report ID (13),
+    0x09, 0x7E,        //   Usage (Device Gain),
+    0x15, 0x00,        //   Logical Minimum (0),
+    0x26, 0xFF, 0x00,  //   Logical Maximum (255),
+    0x75, 0x08,        //   Report Size (8),
+    0x95, 0x01,        //   Report Count (1),
+    0x91, 0x02,        //   Output (Data, Variable, Absolute),
+    0xC0,              // End Collection
+];
+
+/// Create New Effect feature report, see [`CreateNewEffectReport`]
+#[rustfmt::skip]
+pub const PID_CREATE_NEW_EFFECT_REPORT_DESCRIPTOR: &[u8] = &[
+    0x05, 0x0F,        // Usage Page (PID),
+    0x09, 0xAB,        // Usage (Create New Effect Report),
+    0xA1, 0x02,        // Collection (Logical),
+    0x85, 0x11,        //   Report ID (17),
+    0x09, 0x25,        //   Usage (Effect Type),
+    0xA1, 0x02,        //   Collection (Logical),
+    0x09, 0x26,        //     Usage (ET Constant Force),
+    0x09, 0x27,        //     Usage (ET Ramp),
+    0x09, 0x30,        //     Usage (ET Square),
+    0x09, 0x31,        //     Usage (ET Sine),
+    0x09, 0x32,        //     Usage (ET Triangle),
+    0x09, 0x33,        //     Usage (ET Sawtooth Up),
+    0x09, 0x34,        //     Usage (ET Sawtooth Down),
+    0x09, 0x40,        //     Usage (ET Spring),
+    0x09, 0x41,        //     Usage (ET Damper),
+    0x09, 0x42,        //     Usage (ET Inertia),
+    0x09, 0x43,        //     Usage (ET Friction),
+    0x15, 0x01,        //     Logical Minimum (1),
+    0x25, 0x0B,        //     Logical Maximum (11),
+    0x75, 0x08,        //     Report Size (8),
+    0x95, 0x01,        //     Report Count (1),
+    0xB1, 0x00,        //     Feature (Data, Array, Absolute),
+    0xC0,              //   End Collection
+    0x05, 0x01,        //   Usage Page (Generic Desktop),
+    0x09, 0x3B,        //   Usage (Byte Count),
+    0x15, 0x00,        //   Logical Minimum (0),
+    0x26, 0xFF, 0x7F,  //   Logical Maximum (32767),
+    0x75, 0x10,        //   Report Size (16),
+    0xB1, 0x02,        //   Feature (Data, Variable, Absolute),
+    0xC0,              // End Collection
+];
+
+/// PID Block Load feature report, see [`BlockLoadReport`]
+#[rustfmt::skip]
+pub const PID_BLOCK_LOAD_REPORT_DESCRIPTOR: &[u8] = &[
+    0x05, 0x0F,        // Usage Page (PID),
+    0x09, 0x89,        // Usage (PID Block Load Report),
+    0xA1, 0x02,        // Collection (Logical),
+    0x85, 0x12,        //   Report ID (18),
+    0x09, 0x22,        //   Usage (Effect Block Index),
+    0x15, 0x01,        //   Logical Minimum (1),
+    0x25, 0x28,        //   Logical Maximum (40),
+    0x75, 0x08,        //   Report Size (8),
+    0x95, 0x01,        //   Report Count (1),
+    0xB1, 0x02,        //   Feature (Data, Variable, Absolute),
+    0x09, 0x8B,        //   Usage (Block Load Status),
+    0xA1, 0x02,        //   Collection (Logical),
+    0x09, 0x8C,        //     Usage (Block Load Success),
+    0x09, 0x8D,        //     Usage (Block Load Full),
+    0x09, 0x8E,        //     Usage (Block Load Error),
+    0x15, 0x01,        //     Logical Minimum (1),
+    0x25, 0x03,        //     Logical Maximum (3),
+    0xB1, 0x00,        //     Feature (Data, Array, Absolute),
+    0xC0,              //   End Collection
+    0x09, 0xAC,        //   Usage (RAM Pool Available),
+    0x15, 0x00,        //   Logical Minimum (0),
+    0x27, 0xFF, 0xFF, 0x00, 0x00, // Logical Maximum (65535),
+    0x75, 0x10,        //   Report Size (16),
+    0xB1, 0x02,        //   Feature (Data, Variable, Absolute),
+    0xC0,              // End Collection
+];
+
+/// PID Pool feature report, see [`PidPoolReport`]
+#[rustfmt::skip]
+pub const PID_POOL_REPORT_DESCRIPTOR: &[u8] = &[
+    0x05, 0x0F,        // Usage Page (PID),
+    0x09, 0x7F,        // Usage (PID Pool Report),
+    0xA1, 0x02,        // Collection (Logical),
+    0x85, 0x13,        //   Report ID (19),
+    0x09, 0x80,        //   Usage (RAM Pool Size),
+    0x15, 0x00,        //   Logical Minimum (0),
+    0x27, 0xFF, 0xFF, 0x00, 0x00, // Logical Maximum (65535),
+    0x75, 0x10,        //   Report Size (16),
+    0x95, 0x01,        //   Report Count (1),
+    0xB1, 0x02,        //   Feature (Data, Variable, Absolute),
+    0x09, 0x83,        //   Usage (Simultaneous Effects Max),
+    0x26, 0xFF, 0x00,  //   Logical Maximum (255),
+    0x75, 0x08,        //   Report Size (8),
+    0xB1, 0x02,        //   Feature (Data, Variable, Absolute),
+    0x09, 0xA9,        //   Usage (Device Managed Pool),
+    0x09, 0xAA,        //   Usage (Shared Parameter Blocks),
+    0x25, 0x01,        //   Logical Maximum (1),
+    0x75, 0x01,        //   Report Size (1),
+    0x95, 0x02,        //   Report Count (2),
+    0xB1, 0x02,        //   Feature (Data, Variable, Absolute),
+    0x95, 0x06,        //   Report Count (6),
+    0xB1, 0x03,        //   Feature (Constant, Variable, Absolute),
+    0xC0,              // End Collection
+];
+
+/// Two axis joystick input report, see [`ForceFeedbackJoystickReport`]
+#[rustfmt::skip]
+const JOYSTICK_INPUT_REPORT_DESCRIPTOR: &[u8] = &[
+    0x05, 0x01,        // Usage Page (Generic Desktop),
+    0x09, 0x04,        // Usage (Joystick),
+    0xA1, 0x01,        // Collection (Application),
+    0x85, 0x01,        //   Report ID (1),
+    0x05, 0x09,        //   Usage Page (Buttons),
+    0x19, 0x01,        //   Usage Minimum (1),
+    0x29, 0x08,        //   Usage Maximum (8),
+    0x15, 0x00,        //   Logical Minimum (0),
+    0x25, 0x01,        //   Logical Maximum (1),
+    0x75, 0x01,        //   Report Size (1),
+    0x95, 0x08,        //   Report Count (8),
+    0x81, 0x02,        //   Input (Data, Variable, Absolute),
+    0x05, 0x01,        //   Usage Page (Generic Desktop),
+    0x09, 0x30,        //   Usage (X),
+    0x09, 0x31,        //   Usage (Y),
+    0x16, 0x01, 0x80,  //   Logical Minimum (-32767),
+    0x26, 0xFF, 0x7F,  //   Logical Maximum (32767),
+    0x75, 0x10,        //   Report Size (16),
+    0x95, 0x02,        //   Report Count (2),
+    0x81, 0x02,        //   Input (Data, Variable, Absolute),
+];
+
+const END_COLLECTION: &[u8] = &[0xC0];
+
+const FORCE_FEEDBACK_JOYSTICK_FRAGMENTS: &[&[u8]] = &[
+    JOYSTICK_INPUT_REPORT_DESCRIPTOR,
+    PID_STATE_REPORT_DESCRIPTOR,
+    PID_SET_EFFECT_REPORT_DESCRIPTOR,
+    PID_SET_ENVELOPE_REPORT_DESCRIPTOR,
+    PID_SET_CONSTANT_FORCE_REPORT_DESCRIPTOR,
+    PID_EFFECT_OPERATION_REPORT_DESCRIPTOR,
+    PID_BLOCK_FREE_REPORT_DESCRIPTOR,
+    PID_DEVICE_CONTROL_REPORT_DESCRIPTOR,
+    PID_DEVICE_GAIN_REPORT_DESCRIPTOR,
+    PID_CREATE_NEW_EFFECT_REPORT_DESCRIPTOR,
+    PID_BLOCK_LOAD_REPORT_DESCRIPTOR,
+    PID_POOL_REPORT_DESCRIPTOR,
+    END_COLLECTION,
+];
+
+const FORCE_FEEDBACK_JOYSTICK_DESCRIPTOR: [u8; descriptor_len(FORCE_FEEDBACK_JOYSTICK_FRAGMENTS)] =
+    concat_descriptor(FORCE_FEEDBACK_JOYSTICK_FRAGMENTS);
+
+/// Two axis joystick with eight buttons and all the PID fragments in this module
+///
+/// * Input report 1 - [`ForceFeedbackJoystickReport`]
+/// * Input report 2 - [`PidStateReport`]
+pub const FORCE_FEEDBACK_JOYSTICK_REPORT_DESCRIPTOR: &[u8] = &FORCE_FEEDBACK_JOYSTICK_DESCRIPTOR;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, PrimitiveEnum)]
+#[repr(u8)]
+pub enum EffectType {
+    ConstantForce = 1,
+    Ramp = 2,
+    Square = 3,
+    Sine = 4,
+    Triangle = 5,
+    SawtoothUp = 6,
+    SawtoothDown = 7,
+    Spring = 8,
+    Damper = 9,
+    Inertia = 10,
+    Friction = 11,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, PrimitiveEnum)]
+#[repr(u8)]
+pub enum EffectOperation {
+    Start = 1,
+    /// Start the effect, stopping all other effects
+    StartSolo = 2,
+    Stop = 3,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, PrimitiveEnum)]
+#[repr(u8)]
+pub enum DeviceControl {
+    EnableActuators = 1,
+    DisableActuators = 2,
+    StopAllEffects = 3,
+    /// Stop and free all effects, also sent on USB reset
+    DeviceReset = 4,
+    DevicePause = 5,
+    DeviceContinue = 6,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Default, PrimitiveEnum)]
+#[repr(u8)]
+pub enum BlockLoadStatus {
+    Success = 1,
+    /// No free effect blocks
+    Full = 2,
+    #[default]
+    Error = 3,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Default, PackedStruct)]
+#[packed_struct(endian = "lsb", bit_numbering = "msb0", size_bytes = "2")]
+pub struct PidStateReport {
+    #[packed_field(bits = "3")]
+    pub effect_playing: bool,
+    #[packed_field(bits = "4")]
+    pub actuator_power: bool,
+    #[packed_field(bits = "5")]
+    pub safety_switch: bool,
+    #[packed_field(bits = "6")]
+    pub actuators_enabled: bool,
+    #[packed_field(bits = "7")]
+    pub device_paused: bool,
+    #[packed_field(bytes = "1")]
+    pub effect_block_index: u8,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, PackedStruct)]
+#[packed_struct(endian = "lsb", bit_numbering = "msb0", size_bytes = "13")]
+pub struct SetEffectReport {
+    #[packed_field(bytes = "0")]
+    pub effect_block_index: u8,
+    #[packed_field(bytes = "1", ty = "enum")]
+    pub effect_type: EffectType,
+    /// Milliseconds, `u16::MAX` for an infinite duration
+    #[packed_field(bytes = "2:3")]
+    pub duration: u16,
+    /// Milliseconds
+    #[packed_field(bytes = "4:5")]
+    pub trigger_repeat_interval: u16,
+    /// Milliseconds, `0` for the device's default
+    #[packed_field(bytes = "6:7")]
+    pub sample_period: u16,
+    #[packed_field(bytes = "8")]
+    pub gain: u8,
+    /// Button from 1 to 8 that starts the effect, out of range for none
+    #[packed_field(bytes = "9")]
+    pub trigger_button: u8,
+    #[packed_field(bits = "85")]
+    pub direction_enable: bool,
+    #[packed_field(bits = "86")]
+    pub y_axis_enable: bool,
+    #[packed_field(bits = "87")]
+    pub x_axis_enable: bool,
+    /// Polar direction of the effect, `0` to `255` for 0° to 360°
+    #[packed_field(bytes = "11:12")]
+    pub direction: [u8; 2],
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Default, PackedStruct)]
+#[packed_struct(endian = "lsb", size_bytes = "7")]
+pub struct SetEnvelopeReport {
+    #[packed_field]
+    pub effect_block_index: u8,
+    #[packed_field]
+    pub attack_level: u8,
+    #[packed_field]
+    pub fade_level: u8,
+    /// Milliseconds
+    #[packed_field]
+    pub attack_time: u16,
+    /// Milliseconds
+    #[packed_field]
+    pub fade_time: u16,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Default, PackedStruct)]
+#[packed_struct(endian = "lsb", size_bytes = "3")]
+pub struct SetConstantForceReport {
+    #[packed_field]
+    pub effect_block_index: u8,
+    #[packed_field]
+    pub magnitude: i16,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, PackedStruct)]
+#[packed_struct(endian = "lsb", size_bytes = "3")]
+pub struct EffectOperationReport {
+    #[packed_field]
+    pub effect_block_index: u8,
+    #[packed_field(ty = "enum", element_size_bytes = "1")]
+    pub operation: EffectOperation,
+    /// Number of times to play the effect, `255` to repeat until stopped
+    #[packed_field]
+    pub loop_count: u8,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, PackedStruct)]
+#[packed_struct(endian = "lsb", size_bytes = "3")]
+pub struct CreateNewEffectReport {
+    #[packed_field(ty = "enum", element_size_bytes = "1")]
+    pub effect_type: EffectType,
+    /// Size of the effect's parameter blocks, for devices that don't manage their own memory
+    #[packed_field]
+    pub byte_count: u16,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Default, PackedStruct)]
+#[packed_struct(endian = "lsb", size_bytes = "4")]
+pub struct BlockLoadReport {
+    #[packed_field]
+    pub effect_block_index: u8,
+    #[packed_field(ty = "enum", element_size_bytes = "1")]
+    pub block_load_status: BlockLoadStatus,
+    #[packed_field]
+    pub ram_pool_available: u16,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, PackedStruct)]
+#[packed_struct(endian = "lsb", bit_numbering = "msb0", size_bytes = "4")]
+pub struct PidPoolReport {
+    #[packed_field(bytes = "0:1")]
+    pub ram_pool_size: u16,
+    #[packed_field(bytes = "2")]
+    pub simultaneous_effects_max: u8,
+    #[packed_field(bits = "30")]
+    pub shared_parameter_blocks: bool,
+    #[packed_field(bits = "31")]
+    pub device_managed_pool: bool,
+}
+
+impl Default for PidPoolReport {
+    fn default() -> Self {
+        Self {
+            ram_pool_size: u16::MAX,
+            simultaneous_effects_max: PID_MAX_EFFECTS,
+            shared_parameter_blocks: false,
+            device_managed_pool: true,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Default, PackedStruct)]
+#[packed_struct(endian = "lsb", size_bytes = "5")]
+pub struct ForceFeedbackJoystickReport {
+    #[packed_field]
+    pub buttons: u8,
+    #[packed_field]
+    pub x: i16,
+    #[packed_field]
+    pub y: i16,
+}
+
+/// Plays force feedback effects sent by the host
+///
+/// Called from `UsbDevice::poll` as each report is received. Effect block indexes are allocated by
+/// [`EffectHandler::create_effect`] and range from 1 to [`PID_MAX_EFFECTS`].
+pub trait EffectHandler {
+    /// Allocates an effect block, returning its index, or `None` if all blocks are in use
+    fn create_effect(&mut self, report: &CreateNewEffectReport) -> Option<u8>;
+    fn set_effect(&mut self, report: &SetEffectReport);
+    fn effect_operation(&mut self, report: &EffectOperationReport);
+    fn free_effect(&mut self, effect_block_index: u8);
+    fn device_control(&mut self, control: DeviceControl);
+    fn set_envelope(&mut self, _report: &SetEnvelopeReport) {}
+    fn set_constant_force(&mut self, _report: &SetConstantForceReport) {}
+    fn device_gain(&mut self, _gain: u8) {}
+    /// Effect memory reported to the host, by default managed by the device
+    fn pool(&self) -> PidPoolReport {
+        PidPoolReport::default()
+    }
+    /// Bytes of effect memory remaining, reported after each effect is created
+    fn ram_pool_available(&self) -> u16 {
+        u16::MAX
+    }
+}
+
+/// Configuration of [`ForceFeedbackJoystickInterface`]
+pub struct ForceFeedbackConfig<H> {
+    /// The same report descriptor as the inner [`RawInterfaceConfig`], PID descriptors are too
+    /// long to copy to the control buffer
+    pub report_descriptor: &'static [u8],
+    pub handler: H,
+}
+
+pub struct ForceFeedbackJoystickInterface<'a, B: UsbBus, H> {
+    inner: RawInterface<'a, B>,
+    report_descriptor: &'static [u8],
+    handler: H,
+    block_load: BlockLoadReport,
+    feature_responder: FeatureResponder,
+}
+
+/// Force feedback joystick interface, see [`ForceFeedbackJoystickInterface`]
+pub type ForceFeedbackJoystick<'a, B, H> = ForceFeedbackJoystickInterface<'a, B, H>;
+
+impl<'a, B: UsbBus, H: EffectHandler> ForceFeedbackJoystickInterface<'a, B, H> {
+    delegate! {
+        to self.inner {
+            pub fn protocol(&self) -> HidProtocol;
+            pub fn report_latency(&self) -> LatencyHistogram;
+            pub fn clear_report_latency(&self);
+            pub fn host_stalls(&self) -> u32;
+            pub fn host_stalled(&self) -> bool;
+            pub fn write_ready(&self) -> bool;
+            /// Reads output and feature reports not handled by the [`EffectHandler`], including the
+            /// report ID
+            pub fn read_report(&self, data: &mut [u8]) -> Result<usize, UsbHidError>;
+        }
+    }
+
+    pub fn handler(&self) -> &H {
+        &self.handler
+    }
+
+    pub fn write_report(&self, report: &ForceFeedbackJoystickReport) -> Result<(), UsbHidError> {
+        self.write_with_id(JOYSTICK_REPORT_ID, report)
+    }
+
+    pub fn write_state(&self, report: &PidStateReport) -> Result<(), UsbHidError> {
+        self.write_with_id(PID_STATE_REPORT_ID, report)
+    }
+
+    fn write_with_id<R: PackedStruct>(&self, report_id: u8, report: &R) -> Result<(), UsbHidError> {
+        let mut data = [0; 8];
+        let len = pack_with_id(report_id, report, &mut data)?;
+        self.inner.write_report(&data[..len]).map(|_| ())
+    }
+
+    fn create_effect(&mut self, report: &CreateNewEffectReport) {
+        let index = self
+            .handler
+            .create_effect(report)
+            .filter(|i| (1..=PID_MAX_EFFECTS).contains(i));
+        self.block_load = BlockLoadReport {
+            effect_block_index: index.unwrap_or_default(),
+            block_load_status: if index.is_some() {
+                BlockLoadStatus::Success
+            } else {
+                BlockLoadStatus::Full
+            },
+            ram_pool_available: self.handler.ram_pool_available(),
+        };
+    }
+
+    pub fn default_config(
+        handler: H,
+    ) -> WrappedInterfaceConfig<Self, RawInterfaceConfig<'a>, ForceFeedbackConfig<H>> {
+        Self::default_config_with_descriptor(FORCE_FEEDBACK_JOYSTICK_REPORT_DESCRIPTOR, handler)
+    }
+
+    /// Default configuration with an alternative report descriptor
+    ///
+    /// The descriptor must include the PID fragments in this module, and describe input report 1
+    /// with the same layout as [`ForceFeedbackJoystickReport`]
+    pub fn default_config_with_descriptor(
+        report_descriptor: &'static [u8],
+        handler: H,
+    ) -> WrappedInterfaceConfig<Self, RawInterfaceConfig<'a>, ForceFeedbackConfig<H>> {
+        WrappedInterfaceConfig::new(
+            RawInterfaceBuilder::new(report_descriptor)
+                .description("Force Feedback Joystick")
+                .in_endpoint(UsbPacketSize::Bytes8, PollInterval::millis(10))
+                .without_out_endpoint()
+                .build()
+                .unwrap(),
+            ForceFeedbackConfig {
+                report_descriptor,
+                handler,
+            },
+        )
+    }
+}
+
+fn unpack<R: PackedStruct>(data: &[u8]) -> usb_device::Result<R> {
+    R::unpack_from_slice(data).map_err(|e| {
+        error!("Error unpacking PID report: {:?}", e);
+        UsbError::ParseError
+    })
+}
+
+fn first_byte(data: &[u8]) -> usb_device::Result<u8> {
+    data.first().copied().ok_or(UsbError::ParseError)
+}
+
+impl<'a, B: UsbBus, H: EffectHandler> InterfaceClass<'a>
+    for ForceFeedbackJoystickInterface<'a, B, H>
+{
+    delegate! {
+        to self.inner{
+           fn report_descriptor(&self) -> &'_ [u8];
+           fn hid_descriptor_body(&self) -> HidDescriptorBodyBytes;
+           fn class_descriptor(&self, descriptor_type: u8, index: u8) -> Option<&'_ [u8]>;
+           fn take_wakeup_request(&self) -> bool;
+           fn id(&self) -> InterfaceNumber;
+           fn write_descriptors(&self, writer: &mut DescriptorWriter) -> usb_device::Result<()>;
+           fn get_string(&self, index: StringIndex, _lang_id: u16) -> Option<&'_ str>;
+           fn get_report(&mut self, data: &mut [u8]) -> usb_device::Result<usize>;
+           fn set_idle(&mut self, report_id: u8, value: u8);
+           fn get_idle(&self, report_id: u8) -> u8;
+           fn set_protocol(&mut self, protocol: HidProtocol) -> usb_device::Result<()>;
+           fn get_protocol(&self) -> HidProtocol;
+           fn out_endpoint_address(&self) -> Option<EndpointAddress>;
+           fn in_endpoint_address(&self) -> EndpointAddress;
+           fn endpoint_in_complete(&self, addr: EndpointAddress);
+           fn endpoint_out(&mut self);
+        }
+    }
+
+    fn static_report_descriptor(&self) -> Option<&'static [u8]> {
+        Some(self.report_descriptor)
+    }
+
+    fn reset(&mut self) {
+        self.inner.reset();
+        self.block_load = BlockLoadReport::default();
+        self.feature_responder.reset();
+        self.handler.device_control(DeviceControl::DeviceReset);
+    }
+
+    fn set_report(&mut self, data: &[u8]) -> usb_device::Result<()> {
+        let Some((&report_id, report)) = data.split_first() else {
+            return Err(UsbError::ParseError);
+        };
+        match report_id {
+            SET_EFFECT_REPORT_ID => self.handler.set_effect(&unpack(report)?),
+            SET_ENVELOPE_REPORT_ID => self.handler.set_envelope(&unpack(report)?),
+            SET_CONSTANT_FORCE_REPORT_ID => self.handler.set_constant_force(&unpack(report)?),
+            EFFECT_OPERATION_REPORT_ID => self.handler.effect_operation(&unpack(report)?),
+            BLOCK_FREE_REPORT_ID => self.handler.free_effect(first_byte(report)?),
+            DEVICE_CONTROL_REPORT_ID => {
+                let control = DeviceControl::from_primitive(first_byte(report)?)
+                    .ok_or(UsbError::ParseError)?;
+                self.handler.device_control(control);
+            }
+            DEVICE_GAIN_REPORT_ID => self.handler.device_gain(first_byte(report)?),
+            CREATE_NEW_EFFECT_REPORT_ID => self.create_effect(&unpack(report)?),
+            _ => return self.inner.set_report(data),
+        }
+        Ok(())
+    }
+
+    fn get_report_by_id(
+        &mut self,
+        report_type: ReportType,
+        report_id: u8,
+        data: &mut [u8],
+    ) -> usb_device::Result<usize> {
+        let (block_load, handler) = (&self.block_load, &self.handler);
+        self.feature_responder
+            .get_report(
+                &mut self.inner,
+                report_type,
+                report_id,
+                data,
+                |data| match report_id {
+                    BLOCK_LOAD_REPORT_ID => pack_with_id(report_id, block_load, data),
+                    PID_POOL_REPORT_ID => pack_with_id(report_id, &handler.pool(), data),
+                    _ => Err(UsbError::Unsupported),
+                },
+            )
+    }
+
+    fn get_report_ack(&mut self) -> usb_device::Result<()> {
+        self.feature_responder.get_report_ack(&mut self.inner)
+    }
+}
+
+impl<'a, B: UsbBus, H: EffectHandler>
+    WrappedInterface<'a, B, RawInterface<'a, B>, ForceFeedbackConfig<H>>
+    for ForceFeedbackJoystickInterface<'a, B, H>
+{
+    fn new(interface: RawInterface<'a, B>, config: ForceFeedbackConfig<H>) -> Self {
+        Self {
+            inner: interface,
+            report_descriptor: config.report_descriptor,
+            handler: config.handler,
+            block_load: Default::default(),
+            feature_responder: FeatureResponder::default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use packed_struct::PackedStruct;
+
+    use crate::device::pid::*;
+
+    #[test]
+    fn descriptor_concatenates_fragments() {
+        let descriptor = FORCE_FEEDBACK_JOYSTICK_REPORT_DESCRIPTOR;
+        assert_eq!(
+            descriptor.len(),
+            descriptor_len(FORCE_FEEDBACK_JOYSTICK_FRAGMENTS)
+        );
+        assert_eq!(descriptor[..6], [0x05, 0x01, 0x09, 0x04, 0xA1, 0x01]);
+        assert_eq!(descriptor.last(), Some(&0xC0));
+        assert!(descriptor
+            .windows(PID_POOL_REPORT_DESCRIPTOR.len())
+            .any(|w| w == PID_POOL_REPORT_DESCRIPTOR));
+    }
+
+    #[test]
+    fn unpack_set_effect() {
+        let report = SetEffectReport::unpack(&[
+            0x03, 0x04, 0xFF, 0xFF, 0xE8, 0x03, 0x00, 0x00, 0x80, 0xFF, 0x07, 0x40, 0x00,
+        ])
+        .unwrap();
+        assert_eq!(
+            report,
+            SetEffectReport {
+                effect_block_index: 3,
+                effect_type: EffectType::Sine,
+                duration: u16::MAX,
+                trigger_repeat_interval: 1000,
+                sample_period: 0,
+                gain: 0x80,
+                trigger_button: 0xFF,
+                direction_enable: true,
+                y_axis_enable: true,
+                x_axis_enable: true,
+                direction: [0x40, 0x00],
+            }
+        );
+        assert!(SetEffectReport::unpack(&[0; 13]).is_err());
+    }
+
+    #[test]
+    fn pack_feature_and_state_reports() {
+        assert_eq!(
+            PidPoolReport::default().pack().unwrap(),
+            [0xFF, 0xFF, PID_MAX_EFFECTS, 0x01]
+        );
+        assert_eq!(
+            BlockLoadReport {
+                effect_block_index: 2,
+                block_load_status: BlockLoadStatus::Success,
+                ram_pool_available: 0x1234,
+            }
+            .pack()
+            .unwrap(),
+            [0x02, 0x01, 0x34, 0x12]
+        );
+        assert_eq!(
+            PidStateReport {
+                actuators_enabled: true,
+                effect_playing: true,
+                effect_block_index: 5,
+                ..Default::default()
+            }
+            .pack()
+            .unwrap(),
+            [0b0001_0010, 0x05]
+        );
+    }
+
+    #[cfg(feature = "simulation")]
+    #[test]
+    fn dispatch_host_commands() {
+        use std::vec::Vec;
+        use usb_device::bus::UsbBusAllocator;
+
+        use crate::hid_class::prelude::*;
+        use crate::simulation::{control_transfer, device_builder, Response, SimBus};
+
+        #[derive(Default)]
+        struct Handler {
+            effects: Vec<EffectType>,
+            operations: Vec<EffectOperationReport>,
+        }
+
+        impl EffectHandler for Handler {
+            fn create_effect(&mut self, report: &CreateNewEffectReport) -> Option<u8> {
+                self.effects.push(report.effect_type);
+                u8::try_from(self.effects.len()).ok()
+            }
+            fn set_effect(&mut self, _: &SetEffectReport) {}
+            fn effect_operation(&mut self, report: &EffectOperationReport) {
+                self.operations.push(*report);
+            }
+            fn free_effect(&mut self, _: u8) {}
+            fn device_control(&mut self, _: DeviceControl) {}
+        }
+
+        let usb_alloc = UsbBusAllocator::new(SimBus::new());
+        let mut joystick = UsbHidClassBuilder::new()
+            .add_interface(ForceFeedbackJoystick::default_config(Handler::default()))
+            .build(&usb_alloc)
+            .unwrap();
+        let mut usb_dev = device_builder(&usb_alloc, 64).build();
+        let mut transfer = |setup: [u8; 8], data: &[u8]| {
+            control_transfer(&mut usb_dev, &mut [&mut joystick], setup, data)
+        };
+
+        let len = FORCE_FEEDBACK_JOYSTICK_REPORT_DESCRIPTOR.len() as u16;
+        let [len_lo, len_hi] = len.to_le_bytes();
+        assert!(usize::from(len) > crate::hid_class::DEFAULT_CONTROL_BUFFER_SIZE);
+        assert_eq!(
+            transfer([0x81, 0x06, 0x00, 0x22, 0x00, 0x00, len_lo, len_hi], &[]),
+            Response::Data(FORCE_FEEDBACK_JOYSTICK_REPORT_DESCRIPTOR.to_vec())
+        );
+        // SET_REPORT (Feature) Create New Effect, then GET_REPORT (Feature) Block Load
+        assert_eq!(
+            transfer(
+                [0x21, 0x09, 0x11, 0x03, 0x00, 0x00, 0x04, 0x00],
+                &[0x11, 0x04, 0x00, 0x00]
+            ),
+            Response::Ack
+        );
+        assert_eq!(
+            transfer([0xA1, 0x01, 0x12, 0x03, 0x00, 0x00, 0x05, 0x00], &[]),
+            Response::Data(vec![0x12, 0x01, 0x01, 0xFF, 0xFF])
+        );
+        // SET_REPORT (Output) Effect Operation
+        assert_eq!(
+            transfer(
+                [0x21, 0x09, 0x0A, 0x02, 0x00, 0x00, 0x04, 0x00],
+                &[0x0A, 0x01, 0x02, 0xFF]
+            ),
+            Response::Ack
+        );
+        assert_eq!(
+            transfer([0xA1, 0x01, 0x13, 0x03, 0x00, 0x00, 0x05, 0x00], &[]),
+            Response::Data(vec![0x13, 0xFF, 0xFF, PID_MAX_EFFECTS, 0x01])
+        );
+
+        let handler = joystick
+            .interface::<ForceFeedbackJoystick<'_, _, Handler>, _>()
+            .handler();
+        assert_eq!(handler.effects, [EffectType::Sine]);
+        assert_eq!(
+            handler.operations,
+            [EffectOperationReport {
+                effect_block_index: 1,
+                operation: EffectOperation::StartSolo,
+                loop_count: 0xFF,
+            }]
+        );
+    }
+}
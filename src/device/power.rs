@@ -0,0 +1,422 @@
+//!HID power devices
+//!
+//! A UPS reporting a single battery through the Power Device (0x84) and Battery System (0x85)
+//! usage pages. Hosts read the feature reports when the device is enumerated and then follow the
+//! input reports, so the values last written are also returned as feature reports.
+use core::cell::Cell;
+use core::default::Default;
+
+use delegate::delegate;
+use packed_struct::prelude::*;
+use usb_device::bus::{InterfaceNumber, StringIndex, UsbBus};
+use usb_device::class_prelude::{DescriptorWriter, EndpointAddress};
+use usb_device::UsbError;
+
+use crate::hid_class::descriptor::{HidProtocol, ReportType};
+use crate::hid_class::prelude::*;
+use crate::interface::duration::PollInterval;
+use crate::interface::feature::{pack_with_id, FeatureResponder};
+use crate::interface::latency::LatencyHistogram;
+use crate::interface::raw::{RawInterface, RawInterfaceConfig};
+use crate::interface::{
+    DefaultConfig, HidDescriptorBodyBytes, InterfaceClass, WrappedInterface, WrappedInterfaceConfig,
+};
+use crate::UsbHidError;
+
+pub const BATTERY_CAPACITY_REPORT_ID: u8 = 0x01;
+pub const PRESENT_STATUS_REPORT_ID: u8 = 0x02;
+pub const REMAINING_CAPACITY_REPORT_ID: u8 = 0x03;
+
+/// UPS with a single battery
+///
+/// * Report ID 1, Feature - [`BatteryCapacityReport`]
+/// * Report ID 2, Input and Feature - [`PresentStatusReport`]
+/// * Report ID 3, Input and Feature - [`RemainingCapacityReport`]
+#[rustfmt::skip]
+pub const UPS_REPORT_DESCRIPTOR: &[u8] = &[
+    0x05, 0x84,        // Usage Page (Power Device),
+    0x09, 0x04,        // Usage (UPS),
+    0xA1, 0x01,        // Collection (Application),
+    0x09, 0x24,        //   Usage (Power Summary),
+    0xA1, 0x02,        //   Collection (Logical),
+    0x85, 0x01,        //     Report ID (1),
+    0x05, 0x85,        //     Usage Page (Battery System),
+    0x09, 0x2C,        //     Usage (Capacity Mode),
+    0x09, 0x8B,        //     Usage (Rechargeable),
+    0x09, 0x83,        //     Usage (Design Capacity),
+    0x09, 0x67,        //     Usage (Full Charge Capacity),
+    0x09, 0x8C,        //     Usage (Warning Capacity Limit),
+    0x09, 0x29,        //     Usage (Remaining Capacity Limit),
+    0x09, 0x8D,        //     Usage (Capacity Granularity 1),
+    0x09, 0x8E,        //     Usage (Capacity Granularity 2),
+    0x15, 0x00,        //     Logical Minimum (0),
+    0x26, 0xFF, 0x00,  //     Logical Maximum (255),
+    0x75, 0x08,        //     Report Size (8),
+    0x95, 0x08,        //     Report Count (8),
+    0xB1, 0x03,        //     Feature (Constant, Variable, Absolute),
+    0x85, 0x02,        //     Report ID (2),
+    0x05, 0x84,        //     Usage Page (Power Device),
+    0x09, 0x02,        //     Usage (Present Status),
+    0xA1, 0x02,        //     Collection (Logical),
+    0x05, 0x85,        //       Usage Page (Battery System),
+    0x09, 0x44,        //       Usage (Charging),
+    0x09, 0x45,        //       Usage (Discharging),
+    0x09, 0xD0,        //       Usage (AC Present),
+    0x09, 0xD1,        //       Usage (Battery Present),
+    0x09, 0x42,        //       Usage (Below Remaining Capacity Limit),
+    0x09, 0x43,        //       Usage (Remaining Time Limit Expired),
+    0x09, 0x4B,        //       Usage (Need Replacement),
+    0x09, 0x46,        //       Usage (Fully Charged),
+    0x25, 0x01,        //       Logical Maximum (1),
+    0x75, 0x01,        //       Report Size (1),
+    0x81, 0x02,        //       Input (Data, Variable, Absolute),
+    0x09, 0x44,        //       Usage (Charging),
+    0x09, 0x45,        //       Usage (Discharging),
+    0x09, 0xD0,        //       Usage (AC Present),
+    0x09, 0xD1,        //       Usage (Battery Present),
+    0x09, 0x42,        //       Usage (Below Remaining Capacity Limit),
+    0x09, 0x43,        //       Usage (Remaining Time Limit Expired),
+    0x09, 0x4B,        //       Usage (Need Replacement),
+    0x09, 0x46,        //       Usage (Fully Charged),
+    0xB1, 0x03,        //       Feature (Constant, Variable, Absolute),
+    0xC0,              //     End Collection
+    0x85, 0x03,        //     Report ID (3),
+    0x09, 0x66,        //     Usage (Remaining Capacity),
+    0x25, 0x64,        //     Logical Maximum (100),
+    0x75, 0x08,        //     Report Size (8),
+    0x95, 0x01,        //     Report Count (1),
+    0x81, 0x02,        //     Input (Data, Variable, Absolute),
+    0x09, 0x66,        //     Usage (Remaining Capacity),
+    0xB1, 0x03,        //     Feature (Constant, Variable, Absolute),
+    0x09, 0x68,        //     Usage (Run Time To Empty),
+    0x27, 0xFF, 0xFF, 0x00, 0x00, // Logical Maximum (65535),
+    0x75, 0x10,        //     Report Size (16),
+    0x66, 0x01, 0x10,  //     Unit (Seconds),
+    0x81, 0x02,        //     Input (Data, Variable, Absolute),
+    0x09, 0x68,        //     Usage (Run Time To Empty),
+    0xB1, 0x03,        //     Feature (Constant, Variable, Absolute),
+    0xC0,              //   End Collection
+    0xC0,              // End Collection
+];
+
+/// Unit of the capacity values
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Default, PrimitiveEnum)]
+#[repr(u8)]
+pub enum CapacityMode {
+    MilliampHours = 0,
+    MilliwattHours = 1,
+    #[default]
+    Percent = 2,
+    Boolean = 3,
+}
+
+/// Fixed capacity information of the battery, capacities in the unit of the [`CapacityMode`]
+#[derive(Clone, Copy, Debug, Eq, PartialEq, PackedStruct)]
+#[packed_struct(endian = "lsb", bit_numbering = "msb0", size_bytes = "8")]
+pub struct BatteryCapacityReport {
+    #[packed_field(bytes = "0", ty = "enum")]
+    pub capacity_mode: CapacityMode,
+    #[packed_field(bits = "15")]
+    pub rechargeable: bool,
+    #[packed_field(bytes = "2")]
+    pub design_capacity: u8,
+    #[packed_field(bytes = "3")]
+    pub full_charge_capacity: u8,
+    /// Capacity at which the host warns of a low battery
+    #[packed_field(bytes = "4")]
+    pub warning_capacity_limit: u8,
+    /// Capacity at which the host shuts down
+    #[packed_field(bytes = "5")]
+    pub remaining_capacity_limit: u8,
+    #[packed_field(bytes = "6")]
+    pub capacity_granularity_1: u8,
+    #[packed_field(bytes = "7")]
+    pub capacity_granularity_2: u8,
+}
+
+impl Default for BatteryCapacityReport {
+    fn default() -> Self {
+        Self {
+            capacity_mode: CapacityMode::Percent,
+            rechargeable: true,
+            design_capacity: 100,
+            full_charge_capacity: 100,
+            warning_capacity_limit: 20,
+            remaining_capacity_limit: 5,
+            capacity_granularity_1: 1,
+            capacity_granularity_2: 1,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Default, PackedStruct)]
+#[packed_struct(endian = "lsb", bit_numbering = "msb0", size_bytes = "1")]
+pub struct PresentStatusReport {
+    #[packed_field(bits = "0")]
+    pub fully_charged: bool,
+    #[packed_field(bits = "1")]
+    pub need_replacement: bool,
+    #[packed_field(bits = "2")]
+    pub remaining_time_limit_expired: bool,
+    #[packed_field(bits = "3")]
+    pub below_remaining_capacity_limit: bool,
+    #[packed_field(bits = "4")]
+    pub battery_present: bool,
+    #[packed_field(bits = "5")]
+    pub ac_present: bool,
+    #[packed_field(bits = "6")]
+    pub discharging: bool,
+    #[packed_field(bits = "7")]
+    pub charging: bool,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Default, PackedStruct)]
+#[packed_struct(endian = "lsb", size_bytes = "3")]
+pub struct RemainingCapacityReport {
+    /// Percent of the full charge capacity, `0` to `100`
+    #[packed_field]
+    pub remaining_capacity: u8,
+    /// Seconds until the battery is empty at the current load
+    #[packed_field]
+    pub run_time_to_empty: u16,
+}
+
+/// Configuration of [`UpsInterface`]
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct UpsConfig {
+    pub capacity: BatteryCapacityReport,
+}
+
+pub struct UpsInterface<'a, B: UsbBus> {
+    inner: RawInterface<'a, B>,
+    config: UpsConfig,
+    status: Cell<PresentStatusReport>,
+    remaining_capacity: Cell<RemainingCapacityReport>,
+    feature_responder: FeatureResponder,
+}
+
+/// UPS interface, see [`UpsInterface`]
+pub type Ups<'a, B> = UpsInterface<'a, B>;
+
+impl<'a, B: UsbBus> UpsInterface<'a, B> {
+    delegate! {
+        to self.inner {
+            pub fn protocol(&self) -> HidProtocol;
+            pub fn report_latency(&self) -> LatencyHistogram;
+            pub fn clear_report_latency(&self);
+            pub fn host_stalls(&self) -> u32;
+            pub fn host_stalled(&self) -> bool;
+            pub fn write_ready(&self) -> bool;
+        }
+    }
+
+    /// Updates the present status and sends it to the host
+    ///
+    /// The status is returned to later feature report requests even if sending fails.
+    pub fn write_status(&self, report: &PresentStatusReport) -> Result<(), UsbHidError> {
+        self.status.set(*report);
+        self.write_report_with_id(PRESENT_STATUS_REPORT_ID, report)
+    }
+
+    /// Updates the remaining capacity and sends it to the host
+    ///
+    /// The capacity is returned to later feature report requests even if sending fails.
+    pub fn write_remaining_capacity(
+        &self,
+        report: &RemainingCapacityReport,
+    ) -> Result<(), UsbHidError> {
+        self.remaining_capacity.set(*report);
+        self.write_report_with_id(REMAINING_CAPACITY_REPORT_ID, report)
+    }
+
+    fn write_report_with_id<R: PackedStruct>(
+        &self,
+        report_id: u8,
+        report: &R,
+    ) -> Result<(), UsbHidError> {
+        let mut buffer = [0; 8];
+        let len = pack_with_id(report_id, report, &mut buffer)?;
+        self.inner.write_report(&buffer[..len]).map(|_| ())
+    }
+
+    pub fn default_config() -> WrappedInterfaceConfig<Self, RawInterfaceConfig<'a>, UpsConfig> {
+        Self::config(UpsConfig::default())
+    }
+
+    /// Configuration with the capacity information of the battery
+    pub fn config(
+        config: UpsConfig,
+    ) -> WrappedInterfaceConfig<Self, RawInterfaceConfig<'a>, UpsConfig> {
+        WrappedInterfaceConfig::new(
+            RawInterfaceBuilder::new(UPS_REPORT_DESCRIPTOR)
+                .description("UPS")
+                .in_endpoint(UsbPacketSize::Bytes8, PollInterval::millis(100))
+                .without_out_endpoint()
+                .build()
+                .unwrap(),
+            config,
+        )
+    }
+}
+
+impl<'a, B: UsbBus + 'a> DefaultConfig<'a, B> for UpsInterface<'a, B> {
+    type Config = WrappedInterfaceConfig<Self, RawInterfaceConfig<'a>, UpsConfig>;
+
+    fn default_config() -> Self::Config {
+        Self::default_config()
+    }
+}
+
+impl<'a, B: UsbBus> InterfaceClass<'a> for UpsInterface<'a, B> {
+    delegate! {
+        to self.inner{
+           fn report_descriptor(&self) -> &'_ [u8];
+           fn hid_descriptor_body(&self) -> HidDescriptorBodyBytes;
+           fn class_descriptor(&self, descriptor_type: u8, index: u8) -> Option<&'_ [u8]>;
+           fn take_wakeup_request(&self) -> bool;
+           fn id(&self) -> InterfaceNumber;
+           fn write_descriptors(&self, writer: &mut DescriptorWriter) -> usb_device::Result<()>;
+           fn get_string(&self, index: StringIndex, _lang_id: u16) -> Option<&'_ str>;
+           fn get_report(&mut self, data: &mut [u8]) -> usb_device::Result<usize>;
+           fn set_report(&mut self, data: &[u8]) -> usb_device::Result<()>;
+           fn set_idle(&mut self, report_id: u8, value: u8);
+           fn get_idle(&self, report_id: u8) -> u8;
+           fn set_protocol(&mut self, protocol: HidProtocol) -> usb_device::Result<()>;
+           fn get_protocol(&self) -> HidProtocol;
+           fn out_endpoint_address(&self) -> Option<EndpointAddress>;
+           fn in_endpoint_address(&self) -> EndpointAddress;
+           fn endpoint_in_complete(&self, addr: EndpointAddress);
+           fn endpoint_out(&mut self);
+        }
+    }
+
+    fn static_report_descriptor(&self) -> Option<&'static [u8]> {
+        Some(UPS_REPORT_DESCRIPTOR)
+    }
+
+    fn reset(&mut self) {
+        self.inner.reset();
+        self.feature_responder.reset();
+    }
+
+    fn get_report_by_id(
+        &mut self,
+        report_type: ReportType,
+        report_id: u8,
+        data: &mut [u8],
+    ) -> usb_device::Result<usize> {
+        let (capacity, status, remaining_capacity) = (
+            self.config.capacity,
+            self.status.get(),
+            self.remaining_capacity.get(),
+        );
+        self.feature_responder
+            .get_report(
+                &mut self.inner,
+                report_type,
+                report_id,
+                data,
+                |data| match report_id {
+                    BATTERY_CAPACITY_REPORT_ID => pack_with_id(report_id, &capacity, data),
+                    PRESENT_STATUS_REPORT_ID => pack_with_id(report_id, &status, data),
+                    REMAINING_CAPACITY_REPORT_ID => {
+                        pack_with_id(report_id, &remaining_capacity, data)
+                    }
+                    _ => Err(UsbError::Unsupported),
+                },
+            )
+    }
+
+    fn get_report_ack(&mut self) -> usb_device::Result<()> {
+        self.feature_responder.get_report_ack(&mut self.inner)
+    }
+}
+
+impl<'a, B: UsbBus> WrappedInterface<'a, B, RawInterface<'a, B>, UpsConfig>
+    for UpsInterface<'a, B>
+{
+    fn new(interface: RawInterface<'a, B>, config: UpsConfig) -> Self {
+        Self {
+            inner: interface,
+            config,
+            status: Cell::default(),
+            remaining_capacity: Cell::default(),
+            feature_responder: FeatureResponder::default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use packed_struct::PackedStruct;
+
+    use crate::device::power::*;
+
+    #[test]
+    fn pack_capacity_report() {
+        assert_eq!(
+            BatteryCapacityReport::default().pack().unwrap(),
+            [0x02, 0x01, 0x64, 0x64, 0x14, 0x05, 0x01, 0x01]
+        );
+    }
+
+    #[test]
+    fn present_status_bits_follow_usage_order() {
+        let status = PresentStatusReport {
+            charging: true,
+            ac_present: true,
+            fully_charged: true,
+            ..Default::default()
+        };
+        assert_eq!(status.pack().unwrap(), [0b1000_0101]);
+    }
+
+    #[cfg(feature = "simulation")]
+    #[test]
+    fn feature_reports_follow_input_reports() {
+        use usb_device::bus::UsbBusAllocator;
+
+        use crate::simulation::{control_transfer, device_builder, Response, SimBus};
+
+        let usb_alloc = UsbBusAllocator::new(SimBus::new());
+        let mut ups = UsbHidClassBuilder::new()
+            .add_interface(Ups::default_config())
+            .build(&usb_alloc)
+            .unwrap();
+        let mut usb_dev = device_builder(&usb_alloc, 64).build();
+
+        // GET_DESCRIPTOR (Report) is longer than the control buffer
+        assert_eq!(
+            control_transfer(
+                &mut usb_dev,
+                &mut [&mut ups],
+                [0x81, 0x06, 0x00, 0x22, 0x00, 0x00, 0xFF, 0x00],
+                &[]
+            ),
+            Response::Data(UPS_REPORT_DESCRIPTOR.to_vec())
+        );
+
+        let remaining = RemainingCapacityReport {
+            remaining_capacity: 80,
+            run_time_to_empty: 3600,
+        };
+        ups.interface::<Ups<'_, _>, _>()
+            .write_remaining_capacity(&remaining)
+            .unwrap();
+        assert_eq!(
+            usb_dev.bus().host_take_in(1).unwrap(),
+            [0x03, 0x50, 0x10, 0x0E]
+        );
+
+        // GET_REPORT (Feature) Remaining Capacity
+        assert_eq!(
+            control_transfer(
+                &mut usb_dev,
+                &mut [&mut ups],
+                [0xA1, 0x01, 0x03, 0x03, 0x00, 0x00, 0x04, 0x00],
+                &[]
+            ),
+            Response::Data(vec![0x03, 0x50, 0x10, 0x0E])
+        );
+    }
+}
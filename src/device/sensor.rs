@@ -0,0 +1,658 @@
+//!HID sensors
+//!
+//! Sensors on the HID Sensors usage page (0x20) have one feature report of
+//! [`SensorProperties`], set by the host to enable reporting and choose the report interval, and
+//! one input report starting with the [`SensorState`] and [`SensorEvent`], followed by the
+//! sensor's data fields.
+//!
+//! Report descriptors for other sensors are built from [`sensor_collection`],
+//! [`SENSOR_PROPERTIES_DESCRIPTOR`], [`SENSOR_STATE_EVENT_DESCRIPTOR`], a [`data_field`] for each
+//! value and [`END_COLLECTION`], joined with [`concat_descriptor`]. Data fields are in the default
+//! unit of their usage, scaled by 10 to the power of the field's exponent.
+//!
+//! Sensor report descriptors are longer than the usb-device control buffer, so must be `'static`.
+use core::cell::Cell;
+use core::default::Default;
+use core::marker::PhantomData;
+
+use delegate::delegate;
+use packed_struct::prelude::*;
+use packed_struct::types::bits::ByteArray;
+use usb_device::bus::{InterfaceNumber, StringIndex, UsbBus};
+use usb_device::class_prelude::{DescriptorWriter, EndpointAddress};
+use usb_device::UsbError;
+
+use crate::hid_class::descriptor::{concat_descriptor, descriptor_len, HidProtocol, ReportType};
+use crate::hid_class::prelude::*;
+use crate::interface::duration::PollInterval;
+use crate::interface::feature::{pack_into, FeatureResponder};
+use crate::interface::latency::LatencyHistogram;
+use crate::interface::raw::{RawInterface, RawInterfaceConfig};
+use crate::interface::{
+    DefaultConfig, HidDescriptorBodyBytes, InterfaceClass, WrappedInterface, WrappedInterfaceConfig,
+};
+use crate::UsbHidError;
+
+/// Sensor type and data field usages, see [`Sensors`](crate::page::Sensors) for all usages
+pub mod usage {
+    use crate::page::Sensors;
+
+    pub const SENSOR_COLLECTION: u16 = Sensors::Sensor as u16;
+    pub const ENVIRONMENTAL_HUMIDITY: u16 = Sensors::EnvironmentalHumidity as u16;
+    pub const ENVIRONMENTAL_TEMPERATURE: u16 = Sensors::EnvironmentalTemperature as u16;
+    pub const ACCELEROMETER_3D: u16 = Sensors::MotionAccelerometer3D as u16;
+    pub const GYROMETER_3D: u16 = Sensors::MotionGyrometer3D as u16;
+    pub const AMBIENT_LIGHT: u16 = Sensors::LightAmbientLight as u16;
+
+    pub const ACCELERATION_X: u16 = Sensors::DataFieldAccelerationAxisX as u16;
+    pub const ACCELERATION_Y: u16 = Sensors::DataFieldAccelerationAxisY as u16;
+    pub const ACCELERATION_Z: u16 = Sensors::DataFieldAccelerationAxisZ as u16;
+    pub const ANGULAR_VELOCITY_X: u16 = Sensors::DataFieldAngularVelocityAboutXAxis as u16;
+    pub const ANGULAR_VELOCITY_Y: u16 = Sensors::DataFieldAngularVelocityAboutYAxis as u16;
+    pub const ANGULAR_VELOCITY_Z: u16 = Sensors::DataFieldAngularVelocityAboutZAxis as u16;
+    pub const ILLUMINANCE: u16 = Sensors::DataFieldIlluminance as u16;
+    pub const RELATIVE_HUMIDITY: u16 = Sensors::DataFieldRelativeHumidity as u16;
+    pub const TEMPERATURE: u16 = Sensors::DataFieldTemperature as u16;
+}
+
+/// Usage Page (Sensors), Usage (`sensor_type`), Collection (Physical)
+pub const fn sensor_collection(sensor_type: u16) -> [u8; 7] {
+    let [lo, hi] = sensor_type.to_le_bytes();
+    [0x05, 0x20, 0x0A, lo, hi, 0xA1, 0x00]
+}
+
+pub const END_COLLECTION: &[u8] = &[0xC0];
+
+/// Length of a [`data_field`] descriptor fragment
+pub const DATA_FIELD_LEN: usize = 21;
+
+/// Value type of a sensor data field
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Field {
+    I8,
+    I16,
+    I32,
+    U8,
+    U16,
+    /// `0` to `i32::MAX`, the largest logical maximum a report descriptor can hold
+    U32,
+}
+
+impl Field {
+    const fn size_bits(self) -> u8 {
+        match self {
+            Field::I8 | Field::U8 => 8,
+            Field::I16 | Field::U16 => 16,
+            Field::I32 | Field::U32 => 32,
+        }
+    }
+
+    const fn logical_range(self) -> (i32, i32) {
+        match self {
+            Field::I8 => (i8::MIN as i32, i8::MAX as i32),
+            Field::I16 => (i16::MIN as i32, i16::MAX as i32),
+            Field::I32 => (i32::MIN, i32::MAX),
+            Field::U8 => (0, u8::MAX as i32),
+            Field::U16 => (0, u16::MAX as i32),
+            Field::U32 => (0, i32::MAX),
+        }
+    }
+}
+
+/// Input data field of sensor page `usage`, with values scaled by 10 to the power of `exponent`
+///
+/// `exponent` must be between `-8` and `7`.
+#[rustfmt::skip]
+pub const fn data_field(usage: u16, field: Field, exponent: i8) -> [u8; DATA_FIELD_LEN] {
+    assert!(exponent >= -8 && exponent <= 7, "unit exponent out of range");
+    let [usage_lo, usage_hi] = usage.to_le_bytes();
+    let (min, max) = field.logical_range();
+    let min = min.to_le_bytes();
+    let max = max.to_le_bytes();
+    [
+        0x0A, usage_lo, usage_hi,               // Usage (usage),
+        0x17, min[0], min[1], min[2], min[3],   // Logical Minimum (min),
+        0x27, max[0], max[1], max[2], max[3],   // Logical Maximum (max),
+        0x75, field.size_bits(),                // Report Size,
+        0x95, 0x01,                             // Report Count (1),
+        0x55, (exponent as u8) & 0x0F,          // Unit Exponent (exponent),
+        0x81, 0x02,                             // Input (Data, Variable, Absolute),
+    ]
+}
+
+/// Feature report of [`SensorProperties`]
+#[rustfmt::skip]
+pub const SENSOR_PROPERTIES_DESCRIPTOR: &[u8] = &[
+    0x0A, 0x16, 0x03,  // Usage (Reporting State),
+    0x15, 0x00,        // Logical Minimum (0),
+    0x25, 0x05,        // Logical Maximum (5),
+    0x75, 0x08,        // Report Size (8),
+    0x95, 0x01,        // Report Count (1),
+    0xA1, 0x02,        // Collection (Logical),
+    0x0A, 0x40, 0x08,  //   Usage (Report No Events),
+    0x0A, 0x41, 0x08,  //   Usage (Report All Events),
+    0x0A, 0x42, 0x08,  //   Usage (Report Threshold Events),
+    0x0A, 0x43, 0x08,  //   Usage (Wake On No Events),
+    0x0A, 0x44, 0x08,  //   Usage (Wake On All Events),
+    0x0A, 0x45, 0x08,  //   Usage (Wake On Threshold Events),
+    0xB1, 0x00,        //   Feature (Data, Array, Absolute),
+    0xC0,              // End Collection
+    0x0A, 0x19, 0x03,  // Usage (Power State),
+    0xA1, 0x02,        // Collection (Logical),
+    0x0A, 0x50, 0x08,  //   Usage (Undefined),
+    0x0A, 0x51, 0x08,  //   Usage (D0 Full Power),
+    0x0A, 0x52, 0x08,  //   Usage (D1 Low Power),
+    0x0A, 0x53, 0x08,  //   Usage (D2 Standby With Wake),
+    0x0A, 0x54, 0x08,  //   Usage (D3 Sleep With Wake),
+    0x0A, 0x55, 0x08,  //   Usage (D4 Power Off),
+    0xB1, 0x00,        //   Feature (Data, Array, Absolute),
+    0xC0,              // End Collection
+    0x0A, 0x01, 0x02,  // Usage (Sensor State),
+    0x25, 0x06,        // Logical Maximum (6),
+    0xA1, 0x02,        // Collection (Logical),
+    0x0A, 0x00, 0x08,  //   Usage (Undefined),
+    0x0A, 0x01, 0x08,  //   Usage (Ready),
+    0x0A, 0x02, 0x08,  //   Usage (Not Available),
+    0x0A, 0x03, 0x08,  //   Usage (No Data),
+    0x0A, 0x04, 0x08,  //   Usage (Initializing),
+    0x0A, 0x05, 0x08,  //   Usage (Access Denied),
+    0x0A, 0x06, 0x08,  //   Usage (Error),
+    0xB1, 0x00,        //   Feature (Data, Array, Absolute),
+    0xC0,              // End Collection
+    0x0A, 0x0E, 0x03,  // Usage (Report Interval),
+    0x27, 0xFF, 0xFF, 0xFF, 0x7F, // Logical Maximum (2147483647),
+    0x75, 0x20,        // Report Size (32),
+    0x66, 0x01, 0x10,  // Unit (Seconds),
+    0x55, 0x0D,        // Unit Exponent (-3),
+    0xB1, 0x02,        // Feature (Data, Variable, Absolute),
+    0x55, 0x00,        // Unit Exponent (0),
+    0x66, 0x00, 0x00,  // Unit (None),
+];
+
+/// Sensor state and event at the start of the input report
+#[rustfmt::skip]
+pub const SENSOR_STATE_EVENT_DESCRIPTOR: &[u8] = &[
+    0x0A, 0x01, 0x02,  // Usage (Sensor State),
+    0x15, 0x00,        // Logical Minimum (0),
+    0x25, 0x06,        // Logical Maximum (6),
+    0x75, 0x08,        // Report Size (8),
+    0x95, 0x01,        // Report Count (1),
+    0xA1, 0x02,        // Collection (Logical),
+    0x0A, 0x00, 0x08,  //   Usage (Undefined),
+    0x0A, 0x01, 0x08,  //   Usage (Ready),
+    0x0A, 0x02, 0x08,  //   Usage (Not Available),
+    0x0A, 0x03, 0x08,  //   Usage (No Data),
+    0x0A, 0x04, 0x08,  //   Usage (Initializing),
+    0x0A, 0x05, 0x08,  //   Usage (Access Denied),
+    0x0A, 0x06, 0x08,  //   Usage (Error),
+    0x81, 0x00,        //   Input (Data, Array, Absolute),
+    0xC0,              // End Collection
+    0x0A, 0x02, 0x02,  // Usage (Sensor Event),
+    0x25, 0x05,        // Logical Maximum (5),
+    0xA1, 0x02,        // Collection (Logical),
+    0x0A, 0x10, 0x08,  //   Usage (Unknown),
+    0x0A, 0x11, 0x08,  //   Usage (State Changed),
+    0x0A, 0x12, 0x08,  //   Usage (Property Changed),
+    0x0A, 0x13, 0x08,  //   Usage (Data Updated),
+    0x0A, 0x14, 0x08,  //   Usage (Poll Response),
+    0x0A, 0x15, 0x08,  //   Usage (Change Sensitivity),
+    0x81, 0x00,        //   Input (Data, Array, Absolute),
+    0xC0,              // End Collection
+];
+
+const ACCELEROMETER_FRAGMENTS: &[&[u8]] = &[
+    &sensor_collection(usage::ACCELEROMETER_3D),
+    SENSOR_PROPERTIES_DESCRIPTOR,
+    SENSOR_STATE_EVENT_DESCRIPTOR,
+    &data_field(usage::ACCELERATION_X, Field::I16, -2),
+    &data_field(usage::ACCELERATION_Y, Field::I16, -2),
+    &data_field(usage::ACCELERATION_Z, Field::I16, -2),
+    END_COLLECTION,
+];
+const ACCELEROMETER_DESCRIPTOR: [u8; descriptor_len(ACCELEROMETER_FRAGMENTS)] =
+    concat_descriptor(ACCELEROMETER_FRAGMENTS);
+
+/// 3D accelerometer, see [`AccelerometerReport`]
+pub const ACCELEROMETER_REPORT_DESCRIPTOR: &[u8] = &ACCELEROMETER_DESCRIPTOR;
+
+const GYROMETER_FRAGMENTS: &[&[u8]] = &[
+    &sensor_collection(usage::GYROMETER_3D),
+    SENSOR_PROPERTIES_DESCRIPTOR,
+    SENSOR_STATE_EVENT_DESCRIPTOR,
+    &data_field(usage::ANGULAR_VELOCITY_X, Field::I16, -2),
+    &data_field(usage::ANGULAR_VELOCITY_Y, Field::I16, -2),
+    &data_field(usage::ANGULAR_VELOCITY_Z, Field::I16, -2),
+    END_COLLECTION,
+];
+const GYROMETER_DESCRIPTOR: [u8; descriptor_len(GYROMETER_FRAGMENTS)] =
+    concat_descriptor(GYROMETER_FRAGMENTS);
+
+/// 3D gyrometer, see [`GyrometerReport`]
+pub const GYROMETER_REPORT_DESCRIPTOR: &[u8] = &GYROMETER_DESCRIPTOR;
+
+const AMBIENT_LIGHT_FRAGMENTS: &[&[u8]] = &[
+    &sensor_collection(usage::AMBIENT_LIGHT),
+    SENSOR_PROPERTIES_DESCRIPTOR,
+    SENSOR_STATE_EVENT_DESCRIPTOR,
+    &data_field(usage::ILLUMINANCE, Field::U32, -2),
+    END_COLLECTION,
+];
+const AMBIENT_LIGHT_DESCRIPTOR: [u8; descriptor_len(AMBIENT_LIGHT_FRAGMENTS)] =
+    concat_descriptor(AMBIENT_LIGHT_FRAGMENTS);
+
+/// Ambient light sensor, see [`AmbientLightReport`]
+pub const AMBIENT_LIGHT_REPORT_DESCRIPTOR: &[u8] = &AMBIENT_LIGHT_DESCRIPTOR;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Default, PrimitiveEnum)]
+#[repr(u8)]
+pub enum ReportingState {
+    NoEvents = 0,
+    #[default]
+    AllEvents = 1,
+    ThresholdEvents = 2,
+    WakeOnNoEvents = 3,
+    WakeOnAllEvents = 4,
+    WakeOnThresholdEvents = 5,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Default, PrimitiveEnum)]
+#[repr(u8)]
+pub enum PowerState {
+    Undefined = 0,
+    #[default]
+    FullPower = 1,
+    LowPower = 2,
+    StandbyWithWake = 3,
+    SleepWithWake = 4,
+    PowerOff = 5,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Default, PrimitiveEnum)]
+#[repr(u8)]
+pub enum SensorState {
+    Undefined = 0,
+    #[default]
+    Ready = 1,
+    NotAvailable = 2,
+    NoData = 3,
+    Initializing = 4,
+    AccessDenied = 5,
+    Error = 6,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Default, PrimitiveEnum)]
+#[repr(u8)]
+pub enum SensorEvent {
+    Unknown = 0,
+    StateChanged = 1,
+    PropertyChanged = 2,
+    #[default]
+    DataUpdated = 3,
+    PollResponse = 4,
+    ChangeSensitivity = 5,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, PackedStruct)]
+#[packed_struct(endian = "lsb", size_bytes = "7")]
+pub struct SensorProperties {
+    #[packed_field(ty = "enum", element_size_bytes = "1")]
+    pub reporting_state: ReportingState,
+    #[packed_field(ty = "enum", element_size_bytes = "1")]
+    pub power_state: PowerState,
+    /// Set by the device, changes from the host are ignored
+    #[packed_field(ty = "enum", element_size_bytes = "1")]
+    pub sensor_state: SensorState,
+    /// Milliseconds between input reports
+    #[packed_field]
+    pub report_interval: u32,
+}
+
+impl Default for SensorProperties {
+    fn default() -> Self {
+        Self {
+            reporting_state: ReportingState::default(),
+            power_state: PowerState::default(),
+            sensor_state: SensorState::default(),
+            report_interval: 100,
+        }
+    }
+}
+
+impl SensorProperties {
+    /// The host wants input reports of new data
+    pub fn reporting_enabled(&self) -> bool {
+        !matches!(
+            self.reporting_state,
+            ReportingState::NoEvents | ReportingState::WakeOnNoEvents
+        ) && self.power_state == PowerState::FullPower
+    }
+}
+
+/// Accelerations in hundredths of a g
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Default, PackedStruct)]
+#[packed_struct(endian = "lsb", size_bytes = "8")]
+pub struct AccelerometerReport {
+    #[packed_field(ty = "enum", element_size_bytes = "1")]
+    pub state: SensorState,
+    #[packed_field(ty = "enum", element_size_bytes = "1")]
+    pub event: SensorEvent,
+    #[packed_field]
+    pub x: i16,
+    #[packed_field]
+    pub y: i16,
+    #[packed_field]
+    pub z: i16,
+}
+
+/// Angular velocities in hundredths of a degree per second
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Default, PackedStruct)]
+#[packed_struct(endian = "lsb", size_bytes = "8")]
+pub struct GyrometerReport {
+    #[packed_field(ty = "enum", element_size_bytes = "1")]
+    pub state: SensorState,
+    #[packed_field(ty = "enum", element_size_bytes = "1")]
+    pub event: SensorEvent,
+    #[packed_field]
+    pub x: i16,
+    #[packed_field]
+    pub y: i16,
+    #[packed_field]
+    pub z: i16,
+}
+
+/// Illuminance in hundredths of a lux
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Default, PackedStruct)]
+#[packed_struct(endian = "lsb", size_bytes = "6")]
+pub struct AmbientLightReport {
+    #[packed_field(ty = "enum", element_size_bytes = "1")]
+    pub state: SensorState,
+    #[packed_field(ty = "enum", element_size_bytes = "1")]
+    pub event: SensorEvent,
+    #[packed_field]
+    pub illuminance: u32,
+}
+
+/// Input report of a sensor with a ready-made report descriptor
+pub trait SensorReport: PackedStruct {
+    const REPORT_DESCRIPTOR: &'static [u8];
+    const DESCRIPTION: &'static str;
+}
+
+impl SensorReport for AccelerometerReport {
+    const REPORT_DESCRIPTOR: &'static [u8] = ACCELEROMETER_REPORT_DESCRIPTOR;
+    const DESCRIPTION: &'static str = "Accelerometer";
+}
+
+impl SensorReport for GyrometerReport {
+    const REPORT_DESCRIPTOR: &'static [u8] = GYROMETER_REPORT_DESCRIPTOR;
+    const DESCRIPTION: &'static str = "Gyrometer";
+}
+
+impl SensorReport for AmbientLightReport {
+    const REPORT_DESCRIPTOR: &'static [u8] = AMBIENT_LIGHT_REPORT_DESCRIPTOR;
+    const DESCRIPTION: &'static str = "Ambient Light Sensor";
+}
+
+/// Configuration of [`SensorInterface`]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct SensorConfig {
+    /// The same report descriptor as the inner [`RawInterfaceConfig`]
+    pub report_descriptor: &'static [u8],
+    /// Properties until the host sets them
+    pub properties: SensorProperties,
+}
+
+/// Sensor with input reports of type `R`
+pub struct SensorInterface<'a, B: UsbBus, R> {
+    inner: RawInterface<'a, B>,
+    config: SensorConfig,
+    properties: Cell<SensorProperties>,
+    feature_responder: FeatureResponder,
+    _report: PhantomData<R>,
+}
+
+/// Accelerometer interface, see [`SensorInterface`]
+pub type Accelerometer<'a, B> = SensorInterface<'a, B, AccelerometerReport>;
+/// Gyrometer interface, see [`SensorInterface`]
+pub type Gyrometer<'a, B> = SensorInterface<'a, B, GyrometerReport>;
+/// Ambient light sensor interface, see [`SensorInterface`]
+pub type AmbientLight<'a, B> = SensorInterface<'a, B, AmbientLightReport>;
+
+impl<'a, B: UsbBus, R: PackedStruct> SensorInterface<'a, B, R> {
+    delegate! {
+        to self.inner {
+            pub fn protocol(&self) -> HidProtocol;
+            pub fn report_latency(&self) -> LatencyHistogram;
+            pub fn clear_report_latency(&self);
+            pub fn host_stalls(&self) -> u32;
+            pub fn host_stalled(&self) -> bool;
+            pub fn write_ready(&self) -> bool;
+        }
+    }
+
+    /// Properties last set by the host
+    pub fn properties(&self) -> SensorProperties {
+        self.properties.get()
+    }
+
+    /// Sets the sensor state reported in the feature report
+    pub fn set_sensor_state(&self, state: SensorState) {
+        let mut properties = self.properties.get();
+        properties.sensor_state = state;
+        self.properties.set(properties);
+    }
+
+    pub fn write_report(&self, report: &R) -> Result<(), UsbHidError> {
+        let data = report.pack().map_err(|e| {
+            error!("Error packing sensor report: {:?}", e);
+            UsbHidError::SerializationError
+        })?;
+        self.inner.write_report(data.as_bytes_slice()).map(|_| ())
+    }
+
+    /// Configuration with a report descriptor describing reports of type `R`
+    pub fn config_with_descriptor(
+        report_descriptor: &'static [u8],
+        description: &'static str,
+    ) -> WrappedInterfaceConfig<Self, RawInterfaceConfig<'a>, SensorConfig> {
+        WrappedInterfaceConfig::new(
+            RawInterfaceBuilder::new(report_descriptor)
+                .description(description)
+                .in_endpoint(UsbPacketSize::Bytes16, PollInterval::millis(10))
+                .without_out_endpoint()
+                .build()
+                .unwrap(),
+            SensorConfig {
+                report_descriptor,
+                properties: SensorProperties::default(),
+            },
+        )
+    }
+}
+
+impl<'a, B: UsbBus, R: SensorReport> SensorInterface<'a, B, R> {
+    pub fn default_config() -> WrappedInterfaceConfig<Self, RawInterfaceConfig<'a>, SensorConfig> {
+        Self::config_with_descriptor(R::REPORT_DESCRIPTOR, R::DESCRIPTION)
+    }
+}
+
+impl<'a, B: UsbBus + 'a, R: SensorReport> DefaultConfig<'a, B> for SensorInterface<'a, B, R> {
+    type Config = WrappedInterfaceConfig<Self, RawInterfaceConfig<'a>, SensorConfig>;
+
+    fn default_config() -> Self::Config {
+        Self::default_config()
+    }
+}
+
+impl<'a, B: UsbBus, R: PackedStruct> InterfaceClass<'a> for SensorInterface<'a, B, R> {
+    delegate! {
+        to self.inner{
+           fn report_descriptor(&self) -> &'_ [u8];
+           fn hid_descriptor_body(&self) -> HidDescriptorBodyBytes;
+           fn class_descriptor(&self, descriptor_type: u8, index: u8) -> Option<&'_ [u8]>;
+           fn take_wakeup_request(&self) -> bool;
+           fn id(&self) -> InterfaceNumber;
+           fn write_descriptors(&self, writer: &mut DescriptorWriter) -> usb_device::Result<()>;
+           fn get_string(&self, index: StringIndex, _lang_id: u16) -> Option<&'_ str>;
+           fn get_report(&mut self, data: &mut [u8]) -> usb_device::Result<usize>;
+           fn set_idle(&mut self, report_id: u8, value: u8);
+           fn get_idle(&self, report_id: u8) -> u8;
+           fn set_protocol(&mut self, protocol: HidProtocol) -> usb_device::Result<()>;
+           fn get_protocol(&self) -> HidProtocol;
+           fn out_endpoint_address(&self) -> Option<EndpointAddress>;
+           fn in_endpoint_address(&self) -> EndpointAddress;
+           fn endpoint_in_complete(&self, addr: EndpointAddress);
+           fn endpoint_out(&mut self);
+        }
+    }
+
+    fn static_report_descriptor(&self) -> Option<&'static [u8]> {
+        Some(self.config.report_descriptor)
+    }
+
+    fn reset(&mut self) {
+        self.inner.reset();
+        self.properties.set(self.config.properties);
+        self.feature_responder.reset();
+    }
+
+    /// Sensors have no output reports, `SET_REPORT` sets the [`SensorProperties`] feature report
+    fn set_report(&mut self, data: &[u8]) -> usb_device::Result<()> {
+        let mut properties = SensorProperties::unpack_from_slice(data).map_err(|e| {
+            error!("Error unpacking SensorProperties: {:?}", e);
+            UsbError::ParseError
+        })?;
+        properties.sensor_state = self.properties.get().sensor_state;
+        self.properties.set(properties);
+        Ok(())
+    }
+
+    fn get_report_by_id(
+        &mut self,
+        report_type: ReportType,
+        report_id: u8,
+        data: &mut [u8],
+    ) -> usb_device::Result<usize> {
+        let properties = self.properties.get();
+        self.feature_responder
+            .get_report(&mut self.inner, report_type, report_id, data, |data| {
+                pack_into(&properties, data)
+            })
+    }
+
+    fn get_report_ack(&mut self) -> usb_device::Result<()> {
+        self.feature_responder.get_report_ack(&mut self.inner)
+    }
+}
+
+impl<'a, B: UsbBus, R: PackedStruct> WrappedInterface<'a, B, RawInterface<'a, B>, SensorConfig>
+    for SensorInterface<'a, B, R>
+{
+    fn new(interface: RawInterface<'a, B>, config: SensorConfig) -> Self {
+        Self {
+            inner: interface,
+            config,
+            properties: Cell::new(config.properties),
+            feature_responder: FeatureResponder::default(),
+            _report: PhantomData,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use packed_struct::PackedStruct;
+
+    use crate::device::sensor::*;
+
+    #[test]
+    fn data_field_encoding() {
+        assert_eq!(
+            data_field(usage::ACCELERATION_X, Field::I16, -2),
+            [
+                0x0A, 0x53, 0x04, 0x17, 0x00, 0x80, 0xFF, 0xFF, 0x27, 0xFF, 0x7F, 0x00, 0x00, 0x75,
+                0x10, 0x95, 0x01, 0x55, 0x0E, 0x81, 0x02
+            ]
+        );
+        assert_eq!(data_field(usage::ILLUMINANCE, Field::U8, 3)[18], 0x03);
+    }
+
+    #[test]
+    fn descriptors_wrap_fields_in_sensor_collection() {
+        for descriptor in [
+            ACCELEROMETER_REPORT_DESCRIPTOR,
+            GYROMETER_REPORT_DESCRIPTOR,
+            AMBIENT_LIGHT_REPORT_DESCRIPTOR,
+        ] {
+            assert_eq!(descriptor[..2], [0x05, 0x20]);
+            assert_eq!(descriptor.last(), Some(&0xC0));
+        }
+        assert_eq!(
+            AMBIENT_LIGHT_REPORT_DESCRIPTOR.len(),
+            7 + SENSOR_PROPERTIES_DESCRIPTOR.len()
+                + SENSOR_STATE_EVENT_DESCRIPTOR.len()
+                + DATA_FIELD_LEN
+                + 1
+        );
+    }
+
+    #[test]
+    fn pack_reports() {
+        assert_eq!(
+            SensorProperties::default().pack().unwrap(),
+            [0x01, 0x01, 0x01, 0x64, 0x00, 0x00, 0x00]
+        );
+        assert_eq!(
+            AccelerometerReport {
+                z: -100,
+                ..Default::default()
+            }
+            .pack()
+            .unwrap(),
+            [0x01, 0x03, 0x00, 0x00, 0x00, 0x00, 0x9C, 0xFF]
+        );
+    }
+
+    #[cfg(feature = "simulation")]
+    #[test]
+    fn host_sets_properties() {
+        use usb_device::bus::UsbBusAllocator;
+
+        use crate::hid_class::prelude::*;
+        use crate::simulation::{control_transfer, device_builder, Response, SimBus};
+
+        let usb_alloc = UsbBusAllocator::new(SimBus::new());
+        let mut sensor = UsbHidClassBuilder::new()
+            .add_interface(AmbientLight::default_config())
+            .build(&usb_alloc)
+            .unwrap();
+        let mut usb_dev = device_builder(&usb_alloc, 64).build();
+
+        // SET_REPORT (Feature), no events, 1s interval, sensor state ignored
+        assert_eq!(
+            control_transfer(
+                &mut usb_dev,
+                &mut [&mut sensor],
+                [0x21, 0x09, 0x00, 0x03, 0x00, 0x00, 0x07, 0x00],
+                &[0x00, 0x01, 0x06, 0xE8, 0x03, 0x00, 0x00]
+            ),
+            Response::Ack
+        );
+        sensor
+            .interface::<AmbientLight<'_, _>, _>()
+            .set_sensor_state(SensorState::Initializing);
+        assert_eq!(
+            control_transfer(
+                &mut usb_dev,
+                &mut [&mut sensor],
+                [0xA1, 0x01, 0x00, 0x03, 0x00, 0x00, 0x07, 0x00],
+                &[]
+            ),
+            Response::Data(vec![0x00, 0x01, 0x04, 0xE8, 0x03, 0x00, 0x00])
+        );
+
+        let properties = sensor.interface::<AmbientLight<'_, _>, _>().properties();
+        assert_eq!(properties.report_interval, 1000);
+        assert!(!properties.reporting_enabled());
+    }
+}
@@ -0,0 +1,264 @@
+//!HID gamepads with the report layout of Nintendo Switch compatible wired controllers
+//!
+//! The report descriptor is the one of licensed wired controllers such as the HORIPAD S, which
+//! the console accepts as a controller over USB. The console identifies these controllers by
+//! vendor and product ID, the HORIPAD S is `0x0F0D:0x0092`. Other hosts see a generic gamepad.
+use crate::hid_class::descriptor::HidProtocol;
+use core::default::Default;
+use delegate::delegate;
+use packed_struct::prelude::*;
+use usb_device::bus::{InterfaceNumber, StringIndex, UsbBus};
+use usb_device::class_prelude::{DescriptorWriter, EndpointAddress};
+
+use crate::hid_class::prelude::*;
+use crate::interface::duration::PollInterval;
+use crate::interface::latency::LatencyHistogram;
+use crate::interface::raw::{RawInterface, RawInterfaceConfig};
+use crate::interface::{
+    DefaultConfig, HidDescriptorBodyBytes, InterfaceClass, WrappedInterface, WrappedInterfaceConfig,
+};
+use crate::page::HatSwitch;
+use crate::UsbHidError;
+
+/// Switch compatible controller with 16 buttons, a hat switch and two sticks
+///
+/// The items are in the fixed order expected by the console, it must not be rearranged.
+///
+/// Input report:
+/// * Bits 0-15 - Buttons 1 to 16, see [`SwitchButton`]
+/// * Byte 2 - Hat switch, `0` (up) to `7` (up left) clockwise in 45° steps, `8` when centred
+/// * Bytes 3-6 - Left stick X and Y, right stick X and Y, `0` to `255`, `128` when centred
+/// * Byte 7 - Vendor defined, `0`
+///
+/// Output report:
+/// * Bytes 0-7 - Vendor defined, ignored
+#[rustfmt::skip]
+pub const SWITCH_CONTROLLER_REPORT_DESCRIPTOR: &[u8] = &[
+    0x05, 0x01,        // Usage Page (Generic Desktop),
+    0x09, 0x05,        // Usage (Game Pad),
+    0xA1, 0x01,        // Collection (Application),
+    0x15, 0x00,        //   Logical Minimum (0),
+    0x25, 0x01,        //   Logical Maximum (1),
+    0x35, 0x00,        //   Physical Minimum (0),
+    0x45, 0x01,        //   Physical Maximum (1),
+    0x75, 0x01,        //   Report Size (1),
+    0x95, 0x10,        //   Report Count (16),
+    0x05, 0x09,        //   Usage Page (Buttons),
+    0x19, 0x01,        //   Usage Minimum (1),
+    0x29, 0x10,        //   Usage Maximum (16),
+    0x81, 0x02,        //   Input (Data, Variable, Absolute),
+    0x05, 0x01,        //   Usage Page (Generic Desktop),
+    0x25, 0x07,        //   Logical Maximum (7),
+    0x46, 0x3B, 0x01,  //   Physical Maximum (315),
+    0x75, 0x04,        //   Report Size (4),
+    0x95, 0x01,        //   Report Count (1),
+    0x65, 0x14,        //   Unit (Degrees),
+    0x09, 0x39,        //   Usage (Hat Switch),
+    0x81, 0x42,        //   Input (Data, Variable, Absolute, Null State),
+    0x65, 0x00,        //   Unit (None),
+    0x95, 0x01,        //   Report Count (1),
+    0x81, 0x01,        //   Input (Constant),
+    0x26, 0xFF, 0x00,  //   Logical Maximum (255),
+    0x46, 0xFF, 0x00,  //   Physical Maximum (255),
+    0x09, 0x30,        //   Usage (X),
+    0x09, 0x31,        //   Usage (Y),
+    0x09, 0x32,        //   Usage (Z),
+    0x09, 0x35,        //   Usage (Rz),
+    0x75, 0x08,        //   Report Size (8),
+    0x95, 0x04,        //   Report Count (4),
+    0x81, 0x02,        //   Input (Data, Variable, Absolute),
+    0x06, 0x00, 0xFF,  //   Usage Page (FF00h),
+    0x09, 0x20,        //   Usage (20h),
+    0x95, 0x01,        //   Report Count (1),
+    0x81, 0x02,        //   Input (Data, Variable, Absolute),
+    0x0A, 0x21, 0x26,  //   Usage (2621h),
+    0x95, 0x08,        //   Report Count (8),
+    0x91, 0x02,        //   Output (Data, Variable, Absolute),
+    0xC0,              // End Collection
+];
+
+/// Centre position of the sticks of a [`SwitchControllerReport`]
+pub const SWITCH_STICK_CENTRE: u8 = 0x80;
+
+/// Buttons of a [`SwitchControllerReport`]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[repr(u8)]
+pub enum SwitchButton {
+    Y = 0,
+    B = 1,
+    A = 2,
+    X = 3,
+    L = 4,
+    R = 5,
+    ZL = 6,
+    ZR = 7,
+    Minus = 8,
+    Plus = 9,
+    LeftStick = 10,
+    RightStick = 11,
+    Home = 12,
+    Capture = 13,
+}
+
+/// Input report of a Switch compatible controller, the default has centred sticks and no buttons
+/// pressed
+#[derive(Clone, Copy, Debug, Eq, PartialEq, PackedStruct)]
+#[packed_struct(endian = "lsb", size_bytes = "8")]
+pub struct SwitchControllerReport {
+    #[packed_field]
+    pub buttons: u16,
+    #[packed_field(ty = "enum", element_size_bytes = "1")]
+    pub hat: HatSwitch,
+    #[packed_field]
+    pub left_x: u8,
+    #[packed_field]
+    pub left_y: u8,
+    #[packed_field]
+    pub right_x: u8,
+    #[packed_field]
+    pub right_y: u8,
+    #[packed_field]
+    pub vendor: u8,
+}
+
+impl Default for SwitchControllerReport {
+    fn default() -> Self {
+        Self {
+            buttons: 0,
+            hat: HatSwitch::Null,
+            left_x: SWITCH_STICK_CENTRE,
+            left_y: SWITCH_STICK_CENTRE,
+            right_x: SWITCH_STICK_CENTRE,
+            right_y: SWITCH_STICK_CENTRE,
+            vendor: 0,
+        }
+    }
+}
+
+impl SwitchControllerReport {
+    pub fn set_button(&mut self, button: SwitchButton, pressed: bool) {
+        let mask = 1 << button as u8;
+        if pressed {
+            self.buttons |= mask;
+        } else {
+            self.buttons &= !mask;
+        }
+    }
+
+    pub fn button(&self, button: SwitchButton) -> bool {
+        self.buttons & (1 << button as u8) != 0
+    }
+}
+
+pub struct SwitchControllerInterface<'a, B: UsbBus> {
+    inner: RawInterface<'a, B>,
+}
+
+/// Switch compatible controller interface, see [`SwitchControllerInterface`]
+pub type SwitchController<'a, B> = SwitchControllerInterface<'a, B>;
+
+impl<'a, B: UsbBus> SwitchControllerInterface<'a, B> {
+    delegate! {
+        to self.inner {
+            pub fn protocol(&self) -> HidProtocol;
+            pub fn report_latency(&self) -> LatencyHistogram;
+            pub fn clear_report_latency(&self);
+            pub fn host_stalls(&self) -> u32;
+            pub fn host_stalled(&self) -> bool;
+            pub fn write_ready(&self) -> bool;
+        }
+    }
+
+    pub fn write_report(&self, report: &SwitchControllerReport) -> Result<(), UsbHidError> {
+        let data = report.pack().map_err(|e| {
+            error!("Error packing SwitchControllerReport: {:?}", e);
+            UsbHidError::SerializationError
+        })?;
+        self.inner.write_report(&data).map(|_| ())
+    }
+
+    /// Configuration with the endpoints of licensed wired controllers
+    pub fn default_config() -> WrappedInterfaceConfig<Self, RawInterfaceConfig<'a>> {
+        WrappedInterfaceConfig::new(
+            RawInterfaceBuilder::new(SWITCH_CONTROLLER_REPORT_DESCRIPTOR)
+                .in_endpoint(UsbPacketSize::Bytes64, PollInterval::millis(5))
+                .with_out_endpoint(UsbPacketSize::Bytes64, PollInterval::millis(5))
+                .build()
+                .unwrap(),
+            (),
+        )
+    }
+}
+
+impl<'a, B: UsbBus + 'a> DefaultConfig<'a, B> for SwitchControllerInterface<'a, B> {
+    type Config = WrappedInterfaceConfig<Self, RawInterfaceConfig<'a>>;
+
+    fn default_config() -> Self::Config {
+        Self::default_config()
+    }
+}
+
+impl<'a, B: UsbBus> InterfaceClass<'a> for SwitchControllerInterface<'a, B> {
+    delegate! {
+        to self.inner{
+           fn report_descriptor(&self) -> &'_ [u8];
+           fn hid_descriptor_body(&self) -> HidDescriptorBodyBytes;
+           fn class_descriptor(&self, descriptor_type: u8, index: u8) -> Option<&'_ [u8]>;
+           fn take_wakeup_request(&self) -> bool;
+           fn id(&self) -> InterfaceNumber;
+           fn write_descriptors(&self, writer: &mut DescriptorWriter) -> usb_device::Result<()>;
+           fn get_string(&self, index: StringIndex, _lang_id: u16) -> Option<&'_ str>;
+           fn reset(&mut self);
+           fn set_report(&mut self, data: &[u8]) -> usb_device::Result<()>;
+           fn get_report(&mut self, data: &mut [u8]) -> usb_device::Result<usize>;
+           fn get_report_ack(&mut self) -> usb_device::Result<()>;
+           fn set_idle(&mut self, report_id: u8, value: u8);
+           fn get_idle(&self, report_id: u8) -> u8;
+           fn set_protocol(&mut self, protocol: HidProtocol) -> usb_device::Result<()>;
+           fn get_protocol(&self) -> HidProtocol;
+           fn out_endpoint_address(&self) -> Option<EndpointAddress>;
+           fn in_endpoint_address(&self) -> EndpointAddress;
+           fn endpoint_in_complete(&self, addr: EndpointAddress);
+           fn endpoint_out(&mut self);
+        }
+    }
+}
+
+impl<'a, B: UsbBus> WrappedInterface<'a, B, RawInterface<'a, B>>
+    for SwitchControllerInterface<'a, B>
+{
+    fn new(interface: RawInterface<'a, B>, _: ()) -> Self {
+        Self { inner: interface }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use packed_struct::PackedStruct;
+
+    use crate::device::switch::{SwitchButton, SwitchControllerReport};
+    use crate::page::HatSwitch;
+
+    #[test]
+    fn pack_report() {
+        assert_eq!(
+            SwitchControllerReport::default().pack().unwrap(),
+            [0x00, 0x00, 0x08, 0x80, 0x80, 0x80, 0x80, 0x00]
+        );
+
+        let mut report = SwitchControllerReport {
+            hat: HatSwitch::SW,
+            left_x: 0,
+            right_y: 0xFF,
+            ..Default::default()
+        };
+        report.set_button(SwitchButton::A, true);
+        report.set_button(SwitchButton::Capture, true);
+        assert!(report.button(SwitchButton::A));
+        assert!(!report.button(SwitchButton::B));
+        assert_eq!(
+            report.pack().unwrap(),
+            [0x04, 0x20, 0x05, 0x00, 0x80, 0x80, 0xFF, 0x00]
+        );
+    }
+}
@@ -0,0 +1,220 @@
+//!HID single touch screens
+use crate::hid_class::descriptor::HidProtocol;
+use core::default::Default;
+use delegate::delegate;
+use packed_struct::prelude::*;
+use usb_device::bus::{InterfaceNumber, StringIndex, UsbBus};
+use usb_device::class_prelude::{DescriptorWriter, EndpointAddress};
+
+use crate::hid_class::prelude::*;
+use crate::interface::duration::PollInterval;
+use crate::interface::latency::LatencyHistogram;
+use crate::interface::raw::{RawInterface, RawInterfaceConfig};
+use crate::interface::{
+    DefaultConfig, HidDescriptorBodyBytes, InterfaceClass, WrappedInterface, WrappedInterfaceConfig,
+};
+use crate::UsbHidError;
+
+/// Logical maximum of the X and Y coordinates
+pub const TOUCH_SCREEN_LOGICAL_MAX: u16 = 0x7FFF;
+
+/// Single touch screen reporting one finger
+///
+/// * Bit 0 - Tip switch, the finger is touching the screen
+/// * Bit 1 - In range, the finger is detected by the screen
+/// * Byte 1 - Contact identifier
+/// * Bytes 2-5 - X and Y, little endian `0` to [`TOUCH_SCREEN_LOGICAL_MAX`]
+#[rustfmt::skip]
+pub const TOUCH_SCREEN_REPORT_DESCRIPTOR: &[u8] = &[
+    0x05, 0x0D,        // Usage Page (Digitizers),
+    0x09, 0x04,        // Usage (Touch Screen),
+    0xA1, 0x01,        // Collection (Application),
+    0x09, 0x22,        //   Usage (Finger),
+    0xA1, 0x02,        //   Collection (Logical),
+    0x09, 0x42,        //     Usage (Tip Switch),
+    0x09, 0x32,        //     Usage (In Range),
+    0x15, 0x00,        //     Logical Minimum (0),
+    0x25, 0x01,        //     Logical Maximum (1),
+    0x75, 0x01,        //     Report Size (1),
+    0x95, 0x02,        //     Report Count (2),
+    0x81, 0x02,        //     Input (Data, Variable, Absolute),
+    0x95, 0x06,        //     Report Count (6),
+    0x81, 0x03,        //     Input (Constant, Variable, Absolute),
+    0x09, 0x51,        //     Usage (Contact Identifier),
+    0x26, 0xFF, 0x00,  //     Logical Maximum (255),
+    0x75, 0x08,        //     Report Size (8),
+    0x95, 0x01,        //     Report Count (1),
+    0x81, 0x02,        //     Input (Data, Variable, Absolute),
+    0x05, 0x01,        //     Usage Page (Generic Desktop),
+    0x09, 0x30,        //     Usage (X),
+    0x09, 0x31,        //     Usage (Y),
+    0x26, 0xFF, 0x7F,  //     Logical Maximum (32767),
+    0x75, 0x10,        //     Report Size (16),
+    0x95, 0x02,        //     Report Count (2),
+    0x81, 0x02,        //     Input (Data, Variable, Absolute),
+    0xC0,              //   End Collection
+    0xC0,              // End Collection
+];
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Default, PackedStruct)]
+#[packed_struct(endian = "lsb", bit_numbering = "msb0", size_bytes = "6")]
+pub struct TouchScreenReport {
+    #[packed_field(bits = "6")]
+    pub in_range: bool,
+    #[packed_field(bits = "7")]
+    pub tip_switch: bool,
+    #[packed_field(bytes = "1")]
+    pub contact_id: u8,
+    #[packed_field(bytes = "2:3")]
+    pub x: u16,
+    #[packed_field(bytes = "4:5")]
+    pub y: u16,
+}
+
+impl TouchScreenReport {
+    /// A finger touching the screen at `x`, `y` of a `width` by `height` screen
+    ///
+    /// Coordinates are scaled to [`TOUCH_SCREEN_LOGICAL_MAX`], and clamped to the screen.
+    pub fn touch(contact_id: u8, x: u16, y: u16, width: u16, height: u16) -> Self {
+        Self {
+            in_range: true,
+            tip_switch: true,
+            contact_id,
+            x: scale(x, width),
+            y: scale(y, height),
+        }
+    }
+
+    /// The finger lifted from the screen, at its last position
+    pub fn release(self) -> Self {
+        Self {
+            in_range: false,
+            tip_switch: false,
+            ..self
+        }
+    }
+}
+
+/// Scales `value` in `0..size` to `0..=TOUCH_SCREEN_LOGICAL_MAX`
+fn scale(value: u16, size: u16) -> u16 {
+    if size <= 1 {
+        return 0;
+    }
+    let value = u32::from(value.min(size - 1));
+    (value * u32::from(TOUCH_SCREEN_LOGICAL_MAX) / u32::from(size - 1)) as u16
+}
+
+pub struct TouchScreenInterface<'a, B: UsbBus> {
+    inner: RawInterface<'a, B>,
+}
+
+/// Touch screen interface, see [`TouchScreenInterface`]
+pub type TouchScreen<'a, B> = TouchScreenInterface<'a, B>;
+
+impl<'a, B: UsbBus> TouchScreenInterface<'a, B> {
+    delegate! {
+        to self.inner {
+            pub fn protocol(&self) -> HidProtocol;
+            pub fn report_latency(&self) -> LatencyHistogram;
+            pub fn clear_report_latency(&self);
+            pub fn host_stalls(&self) -> u32;
+            pub fn host_stalled(&self) -> bool;
+            pub fn write_ready(&self) -> bool;
+        }
+    }
+
+    pub fn write_report(&self, report: &TouchScreenReport) -> Result<(), UsbHidError> {
+        let data = report.pack().map_err(|e| {
+            error!("Error packing TouchScreenReport: {:?}", e);
+            UsbHidError::SerializationError
+        })?;
+        self.inner.write_report(&data).map(|_| ())
+    }
+
+    pub fn default_config() -> WrappedInterfaceConfig<Self, RawInterfaceConfig<'a>> {
+        Self::default_config_with_descriptor(TOUCH_SCREEN_REPORT_DESCRIPTOR)
+    }
+
+    /// Default configuration with an alternative report descriptor
+    ///
+    /// The descriptor must describe reports with the same layout as [`TouchScreenReport`]
+    pub fn default_config_with_descriptor(
+        report_descriptor: &'a [u8],
+    ) -> WrappedInterfaceConfig<Self, RawInterfaceConfig<'a>> {
+        WrappedInterfaceConfig::new(
+            RawInterfaceBuilder::new(report_descriptor)
+                .description("Touch Screen")
+                .in_endpoint(UsbPacketSize::Bytes8, PollInterval::millis(10))
+                .without_out_endpoint()
+                .build()
+                .unwrap(),
+            (),
+        )
+    }
+}
+
+impl<'a, B: UsbBus + 'a> DefaultConfig<'a, B> for TouchScreenInterface<'a, B> {
+    type Config = WrappedInterfaceConfig<Self, RawInterfaceConfig<'a>>;
+
+    fn default_config() -> Self::Config {
+        Self::default_config()
+    }
+}
+
+impl<'a, B: UsbBus> InterfaceClass<'a> for TouchScreenInterface<'a, B> {
+    delegate! {
+        to self.inner{
+           fn report_descriptor(&self) -> &'_ [u8];
+           fn hid_descriptor_body(&self) -> HidDescriptorBodyBytes;
+           fn class_descriptor(&self, descriptor_type: u8, index: u8) -> Option<&'_ [u8]>;
+           fn take_wakeup_request(&self) -> bool;
+           fn id(&self) -> InterfaceNumber;
+           fn write_descriptors(&self, writer: &mut DescriptorWriter) -> usb_device::Result<()>;
+           fn get_string(&self, index: StringIndex, _lang_id: u16) -> Option<&'_ str>;
+           fn reset(&mut self);
+           fn set_report(&mut self, data: &[u8]) -> usb_device::Result<()>;
+           fn get_report(&mut self, data: &mut [u8]) -> usb_device::Result<usize>;
+           fn get_report_ack(&mut self) -> usb_device::Result<()>;
+           fn set_idle(&mut self, report_id: u8, value: u8);
+           fn get_idle(&self, report_id: u8) -> u8;
+           fn set_protocol(&mut self, protocol: HidProtocol) -> usb_device::Result<()>;
+           fn get_protocol(&self) -> HidProtocol;
+           fn out_endpoint_address(&self) -> Option<EndpointAddress>;
+           fn in_endpoint_address(&self) -> EndpointAddress;
+           fn endpoint_in_complete(&self, addr: EndpointAddress);
+           fn endpoint_out(&mut self);
+        }
+    }
+}
+
+impl<'a, B: UsbBus> WrappedInterface<'a, B, RawInterface<'a, B>> for TouchScreenInterface<'a, B> {
+    fn new(interface: RawInterface<'a, B>, _: ()) -> Self {
+        Self { inner: interface }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use packed_struct::PackedStruct;
+
+    use crate::device::touchscreen::{TouchScreenReport, TOUCH_SCREEN_LOGICAL_MAX};
+
+    #[test]
+    fn pack_report() {
+        let report = TouchScreenReport::touch(3, 0x4000, 0, 0x8000, 600);
+        assert_eq!(report.x, 0x4000);
+        assert_eq!(report.pack().unwrap(), [0x03, 0x03, 0x00, 0x40, 0x00, 0x00]);
+        assert_eq!(
+            report.release().pack().unwrap(),
+            [0x00, 0x03, 0x00, 0x40, 0x00, 0x00]
+        );
+    }
+
+    #[test]
+    fn coordinates_scaled_and_clamped() {
+        let report = TouchScreenReport::touch(0, 1919, 2000, 1920, 1080);
+        assert_eq!(report.x, TOUCH_SCREEN_LOGICAL_MAX);
+        assert_eq!(report.y, TOUCH_SCREEN_LOGICAL_MAX);
+        assert_eq!(TouchScreenReport::touch(0, 5, 5, 0, 1).x, 0);
+    }
+}
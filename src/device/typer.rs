@@ -0,0 +1,200 @@
+//!Typing text on keyboard interfaces
+//!
+//! [`KeyboardTyper`] queues characters and types them as a press report followed by a release
+//! report for each character, one report per [`KeyboardTyper::tick`]. Reports the keyboard cannot
+//! accept yet, because the host has not read the previous report, are retried on the next tick.
+use heapless::{Deque, Vec};
+
+use crate::device::composite_keyboard::CompositeKeyboardInterface;
+use crate::device::keyboard::{
+    BootKeyboardInterface, BootKeyboardReport, NKROBootKeyboardInterface, NKROBootKeyboardReport,
+    NKROKeyboardInterface, NKROKeyboardReport,
+};
+use crate::device::layout;
+use crate::page::alias::KeyCombo;
+use crate::page::Keyboard;
+use crate::UsbHidError;
+use usb_device::bus::UsbBus;
+
+/// Keyboard interface accepting reports of pressed keys
+pub trait KeyWriter {
+    /// Writes a report with `keys` pressed, [`UsbHidError::Duplicate`] and
+    /// [`UsbHidError::WouldBlock`] have the same meaning as for the interface's `write_report`
+    fn write_keys(&self, keys: &[Keyboard]) -> Result<(), UsbHidError>;
+}
+
+impl<B: UsbBus> KeyWriter for BootKeyboardInterface<'_, B> {
+    fn write_keys(&self, keys: &[Keyboard]) -> Result<(), UsbHidError> {
+        self.write_report(&BootKeyboardReport::new(keys.iter().copied()))
+    }
+}
+
+impl<B: UsbBus> KeyWriter for NKROBootKeyboardInterface<'_, B> {
+    fn write_keys(&self, keys: &[Keyboard]) -> Result<(), UsbHidError> {
+        self.write_report(&NKROBootKeyboardReport::new(keys.iter().copied()))
+    }
+}
+
+impl<B: UsbBus> KeyWriter for NKROKeyboardInterface<'_, B> {
+    fn write_keys(&self, keys: &[Keyboard]) -> Result<(), UsbHidError> {
+        self.write_report(&NKROKeyboardReport::new(keys.iter().copied()))
+    }
+}
+
+impl<B: UsbBus> KeyWriter for CompositeKeyboardInterface<'_, B> {
+    fn write_keys(&self, keys: &[Keyboard]) -> Result<(), UsbHidError> {
+        self.write_keyboard(&BootKeyboardReport::new(keys.iter().copied()))
+    }
+}
+
+/// Types queued text on a keyboard interface
+///
+/// Holds up to `N` characters waiting to be typed. Characters without a key stroke in the layout,
+/// see [`layout`], are skipped.
+///
+/// ```
+/// # use usbd_human_interface_device::device::layout::Layout;
+/// # use usbd_human_interface_device::device::typer::KeyboardTyper;
+/// let mut typer = KeyboardTyper::<32>::new(Layout::De.to_fn());
+/// assert_eq!(typer.push_str("Hello"), 5);
+/// assert!(!typer.is_idle());
+/// ```
+pub struct KeyboardTyper<const N: usize> {
+    queue: Deque<char, N>,
+    layout: fn(char) -> Option<KeyCombo>,
+    pressed: bool,
+}
+
+impl<const N: usize> KeyboardTyper<N> {
+    pub fn new(layout: fn(char) -> Option<KeyCombo>) -> Self {
+        Self {
+            queue: Deque::new(),
+            layout,
+            pressed: false,
+        }
+    }
+
+    /// Queues the characters of `s`, returning the number queued before the queue was full
+    pub fn push_str(&mut self, s: &str) -> usize {
+        s.chars()
+            .take_while(|&c| self.queue.push_back(c).is_ok())
+            .count()
+    }
+
+    /// Discards the queued characters, a key already pressed is still released
+    pub fn clear(&mut self) {
+        self.queue.clear();
+    }
+
+    /// Number of characters that can be queued
+    pub fn capacity_remaining(&self) -> usize {
+        N - self.queue.len()
+    }
+
+    /// True when all queued characters have been typed and released
+    pub fn is_idle(&self) -> bool {
+        self.queue.is_empty() && !self.pressed
+    }
+
+    /// Call every 1ms / at 1 KHz, writes at most one report to `keyboard`
+    ///
+    /// [`UsbHidError::WouldBlock`] and [`UsbHidError::Duplicate`] are handled by the typer, other
+    /// errors are returned and the report is retried on the next tick.
+    pub fn tick(&mut self, keyboard: &impl KeyWriter) -> Result<(), UsbHidError> {
+        if self.pressed {
+            if Self::written(keyboard.write_keys(&[]))? {
+                self.pressed = false;
+            }
+            return Ok(());
+        }
+
+        let Some(&c) = self.queue.front() else {
+            return Ok(());
+        };
+        let Some(stroke) = (self.layout)(c) else {
+            warn!("No key stroke for {:?}", c);
+            self.queue.pop_front();
+            return Ok(());
+        };
+
+        let keys: Vec<Keyboard, 3> = stroke.keys().collect();
+        if Self::written(keyboard.write_keys(&keys))? {
+            self.queue.pop_front();
+            self.pressed = true;
+        }
+        Ok(())
+    }
+
+    fn written(result: Result<(), UsbHidError>) -> Result<bool, UsbHidError> {
+        match result {
+            Ok(()) | Err(UsbHidError::Duplicate) => Ok(true),
+            Err(UsbHidError::WouldBlock) => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+impl<const N: usize> Default for KeyboardTyper<N> {
+    fn default() -> Self {
+        Self::new(layout::us)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use core::cell::RefCell;
+
+    use crate::device::typer::*;
+
+    #[derive(Default)]
+    struct Recorder {
+        reports: RefCell<std::vec::Vec<std::vec::Vec<Keyboard>>>,
+        busy: core::cell::Cell<bool>,
+    }
+
+    impl KeyWriter for Recorder {
+        fn write_keys(&self, keys: &[Keyboard]) -> Result<(), UsbHidError> {
+            if self.busy.get() {
+                Err(UsbHidError::WouldBlock)
+            } else {
+                self.reports.borrow_mut().push(keys.to_vec());
+                Ok(())
+            }
+        }
+    }
+
+    #[test]
+    fn types_press_and_release() {
+        let keyboard = Recorder::default();
+        let mut typer = KeyboardTyper::<8>::default();
+        assert_eq!(typer.push_str("aAé1"), 4);
+
+        keyboard.busy.set(true);
+        typer.tick(&keyboard).unwrap();
+        keyboard.busy.set(false);
+        while !typer.is_idle() {
+            typer.tick(&keyboard).unwrap();
+        }
+
+        assert_eq!(
+            *keyboard.reports.borrow(),
+            [
+                vec![Keyboard::A],
+                vec![],
+                vec![Keyboard::LeftShift, Keyboard::A],
+                vec![],
+                vec![Keyboard::Keyboard1],
+                vec![],
+            ]
+        );
+    }
+
+    #[test]
+    fn queue_capacity() {
+        let mut typer = KeyboardTyper::<4>::default();
+        assert_eq!(typer.push_str("abcdef"), 4);
+        assert_eq!(typer.capacity_remaining(), 0);
+        typer.clear();
+        assert!(typer.is_idle());
+    }
+}
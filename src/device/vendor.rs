@@ -0,0 +1,211 @@
+//!Vendor defined raw HID interfaces
+//!
+//! A side channel of fixed size reports on the vendor defined usage page 0xFF00, ignored by host
+//! drivers and accessed by applications through the host's raw HID API, e.g. hidapi.
+use crate::hid_class::descriptor::HidProtocol;
+use delegate::delegate;
+use usb_device::bus::{InterfaceNumber, StringIndex, UsbBus};
+use usb_device::class_prelude::{DescriptorWriter, EndpointAddress};
+
+use crate::hid_class::prelude::*;
+use crate::interface::duration::PollInterval;
+use crate::interface::latency::LatencyHistogram;
+use crate::interface::raw::{RawInterface, RawInterfaceConfig};
+use crate::interface::{
+    DefaultConfig, HidDescriptorBodyBytes, InterfaceClass, WrappedInterface, WrappedInterfaceConfig,
+};
+use crate::UsbHidError;
+
+/// Length of a [`vendor_raw_report_descriptor`]
+pub const VENDOR_RAW_REPORT_DESCRIPTOR_LEN: usize = 27;
+
+/// Vendor defined application collection with an `in_len` byte input report and an `out_len`
+/// byte output report
+#[rustfmt::skip]
+pub const fn vendor_raw_report_descriptor(
+    in_len: u8,
+    out_len: u8,
+) -> [u8; VENDOR_RAW_REPORT_DESCRIPTOR_LEN] {
+    [
+        0x06, 0x00, 0xFF,  // Usage Page (Vendor Defined 0xFF00),
+        0x09, 0x01,        // Usage (0x01),
+        0xA1, 0x01,        // Collection (Application),
+        0x09, 0x02,        //   Usage (0x02),
+        0x15, 0x00,        //   Logical Minimum (0),
+        0x26, 0xFF, 0x00,  //   Logical Maximum (255),
+        0x75, 0x08,        //   Report Size (8),
+        0x95, in_len,      //   Report Count (in_len),
+        0x81, 0x02,        //   Input (Data, Variable, Absolute),
+        0x09, 0x03,        //   Usage (0x03),
+        0x95, out_len,     //   Report Count (out_len),
+        0x91, 0x02,        //   Output (Data, Variable, Absolute),
+        0xC0,              // End Collection
+    ]
+}
+
+/// Report count of a `len` byte report
+const fn report_count(len: usize) -> u8 {
+    assert!(len > 0 && len <= 64, "vendor reports must be 1 to 64 bytes");
+    len as u8
+}
+
+/// Smallest packet size holding a `len` byte report
+const fn packet_size(len: usize) -> UsbPacketSize {
+    if len <= 8 {
+        UsbPacketSize::Bytes8
+    } else if len <= 16 {
+        UsbPacketSize::Bytes16
+    } else if len <= 32 {
+        UsbPacketSize::Bytes32
+    } else {
+        UsbPacketSize::Bytes64
+    }
+}
+
+/// Vendor defined interface with `IN` byte input reports and `OUT` byte output reports
+///
+/// Both report sizes must be between 1 and 64 bytes.
+pub struct VendorRawInterface<'a, B: UsbBus, const IN: usize, const OUT: usize> {
+    inner: RawInterface<'a, B>,
+}
+
+/// Vendor defined raw interface, see [`VendorRawInterface`]
+pub type VendorRaw<'a, B, const IN: usize, const OUT: usize> = VendorRawInterface<'a, B, IN, OUT>;
+
+impl<'a, B: UsbBus, const IN: usize, const OUT: usize> VendorRawInterface<'a, B, IN, OUT> {
+    /// Report descriptor for `IN` and `OUT` byte reports
+    pub const REPORT_DESCRIPTOR: &'static [u8] =
+        &vendor_raw_report_descriptor(report_count(IN), report_count(OUT));
+
+    delegate! {
+        to self.inner {
+            pub fn protocol(&self) -> HidProtocol;
+            pub fn report_latency(&self) -> LatencyHistogram;
+            pub fn clear_report_latency(&self);
+            pub fn host_stalls(&self) -> u32;
+            pub fn host_stalled(&self) -> bool;
+            pub fn write_ready(&self) -> bool;
+        }
+    }
+
+    pub fn write(&self, report: &[u8; IN]) -> Result<(), UsbHidError> {
+        self.inner.write_report(report).map(|_| ())
+    }
+
+    /// Reads an output report, returning the number of bytes the host sent
+    pub fn read(&self, report: &mut [u8; OUT]) -> Result<usize, UsbHidError> {
+        self.inner.read_report(report)
+    }
+
+    pub fn default_config() -> WrappedInterfaceConfig<Self, RawInterfaceConfig<'a>> {
+        WrappedInterfaceConfig::new(
+            RawInterfaceBuilder::new(Self::REPORT_DESCRIPTOR)
+                .description("Vendor Raw HID")
+                .in_endpoint(packet_size(IN), PollInterval::millis(10))
+                .with_out_endpoint(packet_size(OUT), PollInterval::millis(10))
+                .build()
+                .unwrap(),
+            (),
+        )
+    }
+}
+
+impl<'a, B: UsbBus + 'a, const IN: usize, const OUT: usize> DefaultConfig<'a, B>
+    for VendorRawInterface<'a, B, IN, OUT>
+{
+    type Config = WrappedInterfaceConfig<Self, RawInterfaceConfig<'a>>;
+
+    fn default_config() -> Self::Config {
+        Self::default_config()
+    }
+}
+
+impl<'a, B: UsbBus, const IN: usize, const OUT: usize> InterfaceClass<'a>
+    for VendorRawInterface<'a, B, IN, OUT>
+{
+    delegate! {
+        to self.inner{
+           fn report_descriptor(&self) -> &'_ [u8];
+           fn hid_descriptor_body(&self) -> HidDescriptorBodyBytes;
+           fn class_descriptor(&self, descriptor_type: u8, index: u8) -> Option<&'_ [u8]>;
+           fn take_wakeup_request(&self) -> bool;
+           fn id(&self) -> InterfaceNumber;
+           fn write_descriptors(&self, writer: &mut DescriptorWriter) -> usb_device::Result<()>;
+           fn get_string(&self, index: StringIndex, _lang_id: u16) -> Option<&'_ str>;
+           fn reset(&mut self);
+           fn set_report(&mut self, data: &[u8]) -> usb_device::Result<()>;
+           fn get_report(&mut self, data: &mut [u8]) -> usb_device::Result<usize>;
+           fn get_report_ack(&mut self) -> usb_device::Result<()>;
+           fn set_idle(&mut self, report_id: u8, value: u8);
+           fn get_idle(&self, report_id: u8) -> u8;
+           fn set_protocol(&mut self, protocol: HidProtocol) -> usb_device::Result<()>;
+           fn get_protocol(&self) -> HidProtocol;
+           fn out_endpoint_address(&self) -> Option<EndpointAddress>;
+           fn in_endpoint_address(&self) -> EndpointAddress;
+           fn endpoint_in_complete(&self, addr: EndpointAddress);
+           fn endpoint_out(&mut self);
+        }
+    }
+}
+
+impl<'a, B: UsbBus, const IN: usize, const OUT: usize> WrappedInterface<'a, B, RawInterface<'a, B>>
+    for VendorRawInterface<'a, B, IN, OUT>
+{
+    fn new(interface: RawInterface<'a, B>, _: ()) -> Self {
+        Self { inner: interface }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::device::vendor::*;
+
+    #[test]
+    fn report_counts_follow_sizes() {
+        let descriptor = vendor_raw_report_descriptor(32, 7);
+        assert_eq!(descriptor[..3], [0x06, 0x00, 0xFF]);
+        assert_eq!(descriptor[16..18], [0x95, 32]);
+        assert_eq!(descriptor[22..24], [0x95, 7]);
+    }
+
+    #[test]
+    fn packet_sizes() {
+        assert_eq!(packet_size(1), UsbPacketSize::Bytes8);
+        assert_eq!(packet_size(9), UsbPacketSize::Bytes16);
+        assert_eq!(packet_size(32), UsbPacketSize::Bytes32);
+        assert_eq!(packet_size(64), UsbPacketSize::Bytes64);
+    }
+
+    #[cfg(feature = "simulation")]
+    #[test]
+    fn write_and_read() {
+        use usb_device::bus::UsbBusAllocator;
+
+        use crate::simulation::{device_builder, SimBus};
+
+        let usb_alloc = UsbBusAllocator::new(SimBus::new());
+        let vendor = UsbHidClassBuilder::new()
+            .add_interface(VendorRaw::<'_, _, 12, 4>::default_config())
+            .build(&usb_alloc)
+            .unwrap();
+        let usb_dev = device_builder(&usb_alloc, 64).build();
+        let interface = vendor.interface::<VendorRaw<'_, _, 12, 4>, _>();
+
+        assert_eq!(
+            interface.report_descriptor(),
+            vendor_raw_report_descriptor(12, 4)
+        );
+
+        interface.write(&[0xAB; 12]).unwrap();
+        assert_eq!(usb_dev.bus().host_take_in(1).unwrap(), [0xAB; 12]);
+
+        let mut report = [0; 4];
+        assert!(matches!(
+            interface.read(&mut report),
+            Err(UsbHidError::WouldBlock)
+        ));
+        usb_dev.bus().host_out(1, &[1, 2, 3, 4]);
+        assert_eq!(interface.read(&mut report).unwrap(), 4);
+        assert_eq!(report, [1, 2, 3, 4]);
+    }
+}
@@ -0,0 +1,179 @@
+//!HID steering wheels and pedals for driving simulation
+use crate::hid_class::descriptor::HidProtocol;
+use core::default::Default;
+use delegate::delegate;
+use packed_struct::prelude::*;
+use usb_device::bus::{InterfaceNumber, StringIndex, UsbBus};
+use usb_device::class_prelude::{DescriptorWriter, EndpointAddress};
+
+use crate::hid_class::prelude::*;
+use crate::interface::duration::PollInterval;
+use crate::interface::latency::LatencyHistogram;
+use crate::interface::raw::{RawInterface, RawInterfaceConfig};
+use crate::interface::{
+    DefaultConfig, HidDescriptorBodyBytes, InterfaceClass, WrappedInterface, WrappedInterfaceConfig,
+};
+use crate::UsbHidError;
+
+/// Steering wheel with accelerator, brake and clutch pedals and 16 buttons
+///
+/// * Bytes 0-1 - Buttons 1 to 16
+/// * Bytes 2-3 - Steering, little endian `-32767` (full left) to `32767` (full right)
+/// * Bytes 4-9 - Accelerator, brake and clutch, little endian `0` (released) to `65535`
+///   (fully pressed)
+#[rustfmt::skip]
+pub const RACING_WHEEL_REPORT_DESCRIPTOR: &[u8] = &[
+    0x05, 0x01,        // Usage Page (Generic Desktop),
+    0x09, 0x04,        // Usage (Joystick),
+    0xA1, 0x01,        // Collection (Application),
+    0x05, 0x09,        //   Usage Page (Buttons),
+    0x19, 0x01,        //   Usage Minimum (1),
+    0x29, 0x10,        //   Usage Maximum (16),
+    0x15, 0x00,        //   Logical Minimum (0),
+    0x25, 0x01,        //   Logical Maximum (1),
+    0x75, 0x01,        //   Report Size (1),
+    0x95, 0x10,        //   Report Count (16),
+    0x81, 0x02,        //   Input (Data, Variable, Absolute),
+    0x05, 0x02,        //   Usage Page (Simulation Controls),
+    0x09, 0xC8,        //   Usage (Steering),
+    0x16, 0x01, 0x80,  //   Logical Minimum (-32767),
+    0x26, 0xFF, 0x7F,  //   Logical Maximum (32767),
+    0x75, 0x10,        //   Report Size (16),
+    0x95, 0x01,        //   Report Count (1),
+    0x81, 0x02,        //   Input (Data, Variable, Absolute),
+    0x09, 0xC4,        //   Usage (Accelerator),
+    0x09, 0xC5,        //   Usage (Brake),
+    0x09, 0xC6,        //   Usage (Clutch),
+    0x15, 0x00,        //   Logical Minimum (0),
+    0x27, 0xFF, 0xFF, 0x00, 0x00, // Logical Maximum (65535),
+    0x75, 0x10,        //   Report Size (16),
+    0x95, 0x03,        //   Report Count (3),
+    0x81, 0x02,        //   Input (Data, Variable, Absolute),
+    0xC0,              // End Collection
+];
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Default, PackedStruct)]
+#[packed_struct(endian = "lsb", size_bytes = "10")]
+pub struct RacingWheelReport {
+    #[packed_field]
+    pub buttons: u16,
+    #[packed_field]
+    pub steering: i16,
+    #[packed_field]
+    pub accelerator: u16,
+    #[packed_field]
+    pub brake: u16,
+    #[packed_field]
+    pub clutch: u16,
+}
+
+pub struct RacingWheelInterface<'a, B: UsbBus> {
+    inner: RawInterface<'a, B>,
+}
+
+/// Steering wheel and pedals interface, see [`RacingWheelInterface`]
+pub type RacingWheel<'a, B> = RacingWheelInterface<'a, B>;
+
+impl<'a, B: UsbBus> RacingWheelInterface<'a, B> {
+    delegate! {
+        to self.inner {
+            pub fn protocol(&self) -> HidProtocol;
+            pub fn report_latency(&self) -> LatencyHistogram;
+            pub fn clear_report_latency(&self);
+            pub fn host_stalls(&self) -> u32;
+            pub fn host_stalled(&self) -> bool;
+            pub fn write_ready(&self) -> bool;
+        }
+    }
+
+    pub fn write_report(&self, report: &RacingWheelReport) -> Result<(), UsbHidError> {
+        let data = report.pack().map_err(|e| {
+            error!("Error packing RacingWheelReport: {:?}", e);
+            UsbHidError::SerializationError
+        })?;
+        self.inner.write_report(&data).map(|_| ())
+    }
+
+    pub fn default_config() -> WrappedInterfaceConfig<Self, RawInterfaceConfig<'a>> {
+        Self::default_config_with_descriptor(RACING_WHEEL_REPORT_DESCRIPTOR)
+    }
+
+    /// Default configuration with an alternative report descriptor
+    ///
+    /// The descriptor must describe reports with the same layout as [`RacingWheelReport`]
+    pub fn default_config_with_descriptor(
+        report_descriptor: &'a [u8],
+    ) -> WrappedInterfaceConfig<Self, RawInterfaceConfig<'a>> {
+        WrappedInterfaceConfig::new(
+            RawInterfaceBuilder::new(report_descriptor)
+                .description("Racing Wheel")
+                .in_endpoint(UsbPacketSize::Bytes16, PollInterval::millis(2))
+                .without_out_endpoint()
+                .build()
+                .unwrap(),
+            (),
+        )
+    }
+}
+
+impl<'a, B: UsbBus + 'a> DefaultConfig<'a, B> for RacingWheelInterface<'a, B> {
+    type Config = WrappedInterfaceConfig<Self, RawInterfaceConfig<'a>>;
+
+    fn default_config() -> Self::Config {
+        Self::default_config()
+    }
+}
+
+impl<'a, B: UsbBus> InterfaceClass<'a> for RacingWheelInterface<'a, B> {
+    delegate! {
+        to self.inner{
+           fn report_descriptor(&self) -> &'_ [u8];
+           fn hid_descriptor_body(&self) -> HidDescriptorBodyBytes;
+           fn class_descriptor(&self, descriptor_type: u8, index: u8) -> Option<&'_ [u8]>;
+           fn take_wakeup_request(&self) -> bool;
+           fn id(&self) -> InterfaceNumber;
+           fn write_descriptors(&self, writer: &mut DescriptorWriter) -> usb_device::Result<()>;
+           fn get_string(&self, index: StringIndex, _lang_id: u16) -> Option<&'_ str>;
+           fn reset(&mut self);
+           fn set_report(&mut self, data: &[u8]) -> usb_device::Result<()>;
+           fn get_report(&mut self, data: &mut [u8]) -> usb_device::Result<usize>;
+           fn get_report_ack(&mut self) -> usb_device::Result<()>;
+           fn set_idle(&mut self, report_id: u8, value: u8);
+           fn get_idle(&self, report_id: u8) -> u8;
+           fn set_protocol(&mut self, protocol: HidProtocol) -> usb_device::Result<()>;
+           fn get_protocol(&self) -> HidProtocol;
+           fn out_endpoint_address(&self) -> Option<EndpointAddress>;
+           fn in_endpoint_address(&self) -> EndpointAddress;
+           fn endpoint_in_complete(&self, addr: EndpointAddress);
+           fn endpoint_out(&mut self);
+        }
+    }
+}
+
+impl<'a, B: UsbBus> WrappedInterface<'a, B, RawInterface<'a, B>> for RacingWheelInterface<'a, B> {
+    fn new(interface: RawInterface<'a, B>, _: ()) -> Self {
+        Self { inner: interface }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use packed_struct::PackedStruct;
+
+    use crate::device::wheel::RacingWheelReport;
+
+    #[test]
+    fn pack_report() {
+        let report = RacingWheelReport {
+            buttons: 0x8001,
+            steering: -32767,
+            accelerator: u16::MAX,
+            brake: 0x1234,
+            clutch: 0,
+        };
+        assert_eq!(
+            report.pack().unwrap(),
+            [0x01, 0x80, 0x01, 0x80, 0xFF, 0xFF, 0x34, 0x12, 0x00, 0x00]
+        );
+    }
+}
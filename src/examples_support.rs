@@ -0,0 +1,196 @@
+//! Ready-made composite devices with scripted input for validating [`UsbBus`] implementations
+//!
+//! Each configuration is built with a single call and paired with an exerciser that drives every
+//! interface with the generators from [`crate::demo`]. Calling the exerciser's `tick` method every
+//! 1ms/at 1kHz, alongside `UsbDevice::poll`, should produce continuous typing, mouse movement and
+//! volume changes on the host.
+//!
+//! Requires the `examples_support` feature.
+
+use frunk::HList;
+use usb_device::bus::{UsbBus, UsbBusAllocator};
+
+use crate::demo::{GamepadSweep, KeyboardTyping, MouseSquare};
+use crate::device::consumer::{ConsumerControl, MultipleConsumerReport};
+use crate::device::gamepad::Gamepad;
+use crate::device::keyboard::{BootKeyboard, NKROBootKeyboard};
+use crate::device::mouse::{BootMouse, WheelMouse, WheelMouseReport};
+use crate::hid_class::{BuilderResult, UsbHidClass, UsbHidClassBuilder};
+use crate::page::Consumer;
+use crate::UsbHidError;
+
+/// Boot keyboard and boot mouse
+pub type KeyboardMouse<'a, B> = UsbHidClass<B, HList!(BootMouse<'a, B>, BootKeyboard<'a, B>)>;
+
+/// Gamepad and consumer control
+pub type GamepadConsumer<'a, B> = UsbHidClass<B, HList!(ConsumerControl<'a, B>, Gamepad<'a, B>)>;
+
+/// NKRO keyboard, wheel mouse and consumer control
+pub type DeskSet<'a, B> = UsbHidClass<
+    B,
+    HList!(
+        ConsumerControl<'a, B>,
+        WheelMouse<'a, B>,
+        NKROBootKeyboard<'a, B>
+    ),
+>;
+
+pub fn keyboard_mouse<B: UsbBus>(
+    usb_alloc: &UsbBusAllocator<B>,
+) -> BuilderResult<KeyboardMouse<'_, B>> {
+    UsbHidClassBuilder::new()
+        .add_interface(BootKeyboard::default_config())
+        .add_interface(BootMouse::default_config())
+        .build(usb_alloc)
+}
+
+pub fn gamepad_consumer<B: UsbBus>(
+    usb_alloc: &UsbBusAllocator<B>,
+) -> BuilderResult<GamepadConsumer<'_, B>> {
+    UsbHidClassBuilder::new()
+        .add_interface(Gamepad::default_config())
+        .add_interface(ConsumerControl::default_config())
+        .build(usb_alloc)
+}
+
+pub fn desk_set<B: UsbBus>(usb_alloc: &UsbBusAllocator<B>) -> BuilderResult<DeskSet<'_, B>> {
+    UsbHidClassBuilder::new()
+        .add_interface(NKROBootKeyboard::default_config())
+        .add_interface(WheelMouse::default_config())
+        .add_interface(ConsumerControl::default_config())
+        .build(usb_alloc)
+}
+
+/// Presses volume up then volume down, leaving the host volume unchanged after each cycle
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ConsumerVolume {
+    period: u16,
+    elapsed: u16,
+    step: u8,
+}
+
+impl ConsumerVolume {
+    /// Hold each key for `period` ticks, then release it for `period` ticks
+    pub const fn new(period: u16) -> Self {
+        Self {
+            period,
+            elapsed: 0,
+            step: 0,
+        }
+    }
+
+    /// The usage currently pressed, [`Consumer::Unassigned`] between key presses
+    pub fn current_usage(&self) -> Consumer {
+        match self.step {
+            0 => Consumer::VolumeIncrement,
+            2 => Consumer::VolumeDecrement,
+            _ => Consumer::Unassigned,
+        }
+    }
+
+    pub fn tick<B: UsbBus>(
+        &mut self,
+        consumer: &ConsumerControl<'_, B>,
+    ) -> Result<(), UsbHidError> {
+        let report = MultipleConsumerReport {
+            codes: [
+                self.current_usage(),
+                Consumer::Unassigned,
+                Consumer::Unassigned,
+                Consumer::Unassigned,
+            ],
+        };
+        consumer.write_report(&report)?;
+        self.elapsed += 1;
+        if self.elapsed >= self.period {
+            self.elapsed = 0;
+            self.step = (self.step + 1) % 4;
+        }
+        Ok(())
+    }
+}
+
+impl Default for ConsumerVolume {
+    fn default() -> Self {
+        Self::new(500)
+    }
+}
+
+/// Drives a [`KeyboardMouse`]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct KeyboardMouseExerciser {
+    pub typing: KeyboardTyping<'static>,
+    pub mouse: MouseSquare,
+}
+
+impl KeyboardMouseExerciser {
+    /// Ticks the interfaces and generators, a busy endpoint is not an error
+    pub fn tick<B: UsbBus>(&mut self, class: &KeyboardMouse<'_, B>) -> Result<(), UsbHidError> {
+        let keyboard = class.interface::<BootKeyboard<'_, B>, _>();
+        let mouse = class.interface::<BootMouse<'_, B>, _>();
+
+        ignore_would_block(keyboard.tick())?;
+        ignore_would_block(self.typing.tick(keyboard))?;
+        ignore_would_block(self.mouse.tick(mouse))
+    }
+}
+
+/// Drives a [`GamepadConsumer`]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct GamepadConsumerExerciser {
+    pub gamepad: GamepadSweep,
+    pub volume: ConsumerVolume,
+}
+
+impl GamepadConsumerExerciser {
+    /// Ticks the generators, a busy endpoint is not an error
+    pub fn tick<B: UsbBus>(&mut self, class: &GamepadConsumer<'_, B>) -> Result<(), UsbHidError> {
+        let gamepad = class.interface::<Gamepad<'_, B>, _>();
+        let consumer = class.interface::<ConsumerControl<'_, B>, _>();
+
+        ignore_would_block(self.gamepad.tick(gamepad))?;
+        ignore_would_block(self.volume.tick(consumer))
+    }
+}
+
+/// Drives a [`DeskSet`]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DeskSetExerciser {
+    pub typing: KeyboardTyping<'static>,
+    pub mouse: MouseSquare,
+    pub volume: ConsumerVolume,
+}
+
+impl DeskSetExerciser {
+    /// Ticks the interfaces and generators, a busy endpoint is not an error
+    pub fn tick<B: UsbBus>(&mut self, class: &DeskSet<'_, B>) -> Result<(), UsbHidError> {
+        let keyboard = class.interface::<NKROBootKeyboard<'_, B>, _>();
+        let mouse = class.interface::<WheelMouse<'_, B>, _>();
+        let consumer = class.interface::<ConsumerControl<'_, B>, _>();
+
+        ignore_would_block(keyboard.tick())?;
+        ignore_would_block(self.typing.tick_nkro(keyboard))?;
+
+        let mut square = self.mouse;
+        let report = square.next_report();
+        match mouse.write_report(&WheelMouseReport {
+            buttons: report.buttons,
+            x: report.x,
+            y: report.y,
+            ..Default::default()
+        }) {
+            Ok(()) => self.mouse = square,
+            Err(UsbHidError::WouldBlock) => {}
+            Err(e) => return Err(e),
+        }
+
+        ignore_would_block(self.volume.tick(consumer))
+    }
+}
+
+fn ignore_would_block(result: Result<(), UsbHidError>) -> Result<(), UsbHidError> {
+    match result {
+        Err(UsbHidError::WouldBlock) => Ok(()),
+        r => r,
+    }
+}
@@ -0,0 +1,236 @@
+//! Endpoint and packet memory planning for composite devices
+//!
+//! Constrained USB peripherals, such as the STM32F103, only have a handful of endpoints and a
+//! small packet memory. Composite devices can easily exceed these limits, which otherwise only
+//! shows up as an [`usb_device::UsbError::EndpointOverflow`] panic during allocation.
+//!
+//! ```
+//! use usbd_human_interface_device::hid_class::budget::{EndpointBudget, EndpointUsage};
+//! use usbd_human_interface_device::hid_class::prelude::*;
+//! use usbd_human_interface_device::interface::raw::{EndpointConfig, RawInterfaceConfig};
+//!
+//! const KEYBOARD: RawInterfaceConfig = RawInterfaceConfig {
+//!     report_descriptor: &[],
+//!     boot_report_descriptor: None,
+//!     description: None,
+//!     protocol: InterfaceProtocol::Keyboard,
+//!     idle_default: IdleDuration::millis(500),
+//!     protocol_policy: ProtocolPolicy::Accept,
+//!     packet_policy: PacketPolicy::DEFAULT,
+//!     latency_clock: None,
+//!     stall_timeout: None,
+//!     hid_descriptor: HidDescriptorConfig::DEFAULT,
+//!     class_descriptors: &[],
+//!     wake_on_report: false,
+//!     speed: UsbSpeed::Full,
+//!     out_endpoint: Some(EndpointConfig {
+//!         poll_interval: PollInterval::millis(100),
+//!         max_packet_size: UsbPacketSize::Bytes8,
+//!     }),
+//!     in_endpoint: EndpointConfig {
+//!         poll_interval: PollInterval::millis(10),
+//!         max_packet_size: UsbPacketSize::Bytes8,
+//!     },
+//! };
+//!
+//! // Fails to compile if the interfaces need more than the declared budget
+//! const USAGE: EndpointUsage = EndpointUsage::from_configs(&[KEYBOARD, KEYBOARD])
+//!     .assert_within(&EndpointBudget::new(3, 3, 256));
+//! ```
+
+#[cfg(feature = "frunk")]
+use frunk::{HCons, HNil};
+
+use crate::interface::managed::ManagedInterfaceConfig;
+use crate::interface::raw::{EndpointConfig, RawInterfaceConfig};
+use crate::interface::WrappedInterfaceConfig;
+
+const INTERFACE_DESCRIPTOR_LEN: usize = 9;
+const HID_DESCRIPTOR_LEN: usize = 9;
+const CLASS_DESCRIPTOR_ENTRY_LEN: usize = 3;
+const ENDPOINT_DESCRIPTOR_LEN: usize = 7;
+
+/// Endpoints and packet memory available for HID interfaces
+///
+/// The control endpoint is not included, deduct it from the peripheral's totals.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EndpointBudget {
+    pub in_endpoints: usize,
+    pub out_endpoints: usize,
+    pub packet_memory: usize,
+}
+
+impl EndpointBudget {
+    pub const fn new(in_endpoints: usize, out_endpoints: usize, packet_memory: usize) -> Self {
+        Self {
+            in_endpoints,
+            out_endpoints,
+            packet_memory,
+        }
+    }
+}
+
+/// Endpoints and packet memory required by a set of interfaces
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct EndpointUsage {
+    pub interfaces: usize,
+    /// Class descriptors listed in HID descriptors besides the report descriptors
+    pub class_descriptors: usize,
+    pub in_endpoints: usize,
+    pub out_endpoints: usize,
+    pub packet_memory: usize,
+}
+
+impl EndpointUsage {
+    pub const NONE: Self = Self {
+        interfaces: 0,
+        class_descriptors: 0,
+        in_endpoints: 0,
+        out_endpoints: 0,
+        packet_memory: 0,
+    };
+
+    pub const fn from_config<const OUT_QUEUE: usize, const IN_QUEUE: usize>(
+        config: &RawInterfaceConfig<'_, OUT_QUEUE, IN_QUEUE>,
+    ) -> Self {
+        let usage = Self {
+            interfaces: 1,
+            class_descriptors: config.class_descriptors.len(),
+            ..Self::NONE
+        }
+        .add_in_endpoint(&config.in_endpoint);
+        match &config.out_endpoint {
+            Some(e) => usage.add_out_endpoint(e),
+            None => usage,
+        }
+    }
+
+    pub const fn from_configs<const OUT_QUEUE: usize, const IN_QUEUE: usize>(
+        configs: &[RawInterfaceConfig<'_, OUT_QUEUE, IN_QUEUE>],
+    ) -> Self {
+        let mut usage = Self::NONE;
+        let mut i = 0;
+        while i < configs.len() {
+            usage = usage.add(Self::from_config(&configs[i]));
+            i += 1;
+        }
+        usage
+    }
+
+    pub const fn add(self, other: Self) -> Self {
+        Self {
+            interfaces: self.interfaces + other.interfaces,
+            class_descriptors: self.class_descriptors + other.class_descriptors,
+            in_endpoints: self.in_endpoints + other.in_endpoints,
+            out_endpoints: self.out_endpoints + other.out_endpoints,
+            packet_memory: self.packet_memory + other.packet_memory,
+        }
+    }
+
+    const fn add_in_endpoint(self, endpoint: &EndpointConfig) -> Self {
+        Self {
+            in_endpoints: self.in_endpoints + 1,
+            packet_memory: self.packet_memory + endpoint.max_packet_size as usize,
+            ..self
+        }
+    }
+
+    const fn add_out_endpoint(self, endpoint: &EndpointConfig) -> Self {
+        Self {
+            out_endpoints: self.out_endpoints + 1,
+            packet_memory: self.packet_memory + endpoint.max_packet_size as usize,
+            ..self
+        }
+    }
+
+    /// Bytes of interface, HID and endpoint descriptors written to the configuration descriptor
+    pub const fn descriptor_size(&self) -> usize {
+        self.interfaces * (INTERFACE_DESCRIPTOR_LEN + HID_DESCRIPTOR_LEN)
+            + self.class_descriptors * CLASS_DESCRIPTOR_ENTRY_LEN
+            + (self.in_endpoints + self.out_endpoints) * ENDPOINT_DESCRIPTOR_LEN
+    }
+
+    pub const fn fits(&self, budget: &EndpointBudget) -> bool {
+        self.in_endpoints <= budget.in_endpoints
+            && self.out_endpoints <= budget.out_endpoints
+            && self.packet_memory <= budget.packet_memory
+    }
+
+    /// Panics if the usage exceeds `budget`, a compile error when evaluated in a const context
+    pub const fn assert_within(self, budget: &EndpointBudget) -> Self {
+        if self.in_endpoints > budget.in_endpoints {
+            panic!("HID interfaces require more IN endpoints than available");
+        }
+        if self.out_endpoints > budget.out_endpoints {
+            panic!("HID interfaces require more OUT endpoints than available");
+        }
+        if self.packet_memory > budget.packet_memory {
+            panic!("HID interfaces require more packet memory than available");
+        }
+        self
+    }
+}
+
+/// Interface configurations that can report their endpoint requirements before allocation
+pub trait EndpointRequirements {
+    fn endpoint_usage(&self) -> EndpointUsage;
+}
+
+#[cfg(feature = "frunk")]
+impl EndpointRequirements for HNil {
+    fn endpoint_usage(&self) -> EndpointUsage {
+        EndpointUsage::NONE
+    }
+}
+
+#[cfg(feature = "frunk")]
+impl<Head: EndpointRequirements, Tail: EndpointRequirements> EndpointRequirements
+    for HCons<Head, Tail>
+{
+    fn endpoint_usage(&self) -> EndpointUsage {
+        self.head.endpoint_usage().add(self.tail.endpoint_usage())
+    }
+}
+
+impl<const OUT_QUEUE: usize, const IN_QUEUE: usize> EndpointRequirements
+    for RawInterfaceConfig<'_, OUT_QUEUE, IN_QUEUE>
+{
+    fn endpoint_usage(&self) -> EndpointUsage {
+        EndpointUsage::from_config(self)
+    }
+}
+
+impl<I, O, const N: usize, const OUT_QUEUE: usize, const IN_QUEUE: usize> EndpointRequirements
+    for ManagedInterfaceConfig<'_, I, O, N, OUT_QUEUE, IN_QUEUE>
+{
+    fn endpoint_usage(&self) -> EndpointUsage {
+        self.inner_config.endpoint_usage()
+    }
+}
+
+impl<I, InnerConfig, Config> EndpointRequirements for WrappedInterfaceConfig<I, InnerConfig, Config>
+where
+    InnerConfig: EndpointRequirements,
+{
+    fn endpoint_usage(&self) -> EndpointUsage {
+        self.inner_config.endpoint_usage()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::hid_class::budget::{EndpointBudget, EndpointUsage};
+
+    #[test]
+    #[should_panic]
+    fn assert_within_panics_on_overflow() {
+        EndpointUsage {
+            interfaces: 4,
+            class_descriptors: 0,
+            in_endpoints: 4,
+            out_endpoints: 0,
+            packet_memory: 32,
+        }
+        .assert_within(&EndpointBudget::new(3, 3, 512));
+    }
+}
@@ -17,6 +17,16 @@ pub enum InterfaceProtocol {
 pub enum DescriptorType {
     Hid = 0x21,
     Report = 0x22,
+    Physical = 0x23,
+}
+
+/// Report type of `GET_REPORT` and `SET_REPORT` requests
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PrimitiveEnum)]
+#[repr(u8)]
+pub enum ReportType {
+    Input = 0x01,
+    Output = 0x02,
+    Feature = 0x03,
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
@@ -42,3 +52,33 @@ pub enum HidProtocol {
     Boot = 0x00,
     Report = 0x01,
 }
+
+/// Total length of report descriptor `fragments`
+pub const fn descriptor_len(fragments: &[&[u8]]) -> usize {
+    let mut len = 0;
+    let mut i = 0;
+    while i < fragments.len() {
+        len += fragments[i].len();
+        i += 1;
+    }
+    len
+}
+
+/// Concatenates report descriptor `fragments`, `N` must be their [`descriptor_len`]
+pub const fn concat_descriptor<const N: usize>(fragments: &[&[u8]]) -> [u8; N] {
+    let mut descriptor = [0; N];
+    let mut n = 0;
+    let mut i = 0;
+    while i < fragments.len() {
+        let fragment = fragments[i];
+        let mut j = 0;
+        while j < fragment.len() {
+            descriptor[n] = fragment[j];
+            n += 1;
+            j += 1;
+        }
+        i += 1;
+    }
+    assert!(n == N, "descriptor length does not match fragments");
+    descriptor
+}
@@ -0,0 +1,531 @@
+//! Human readable annotation of report descriptors
+//!
+//! [`annotate`] decodes each item of a report descriptor into its name and value, in the style of
+//! the comments next to the descriptors in this crate. [`AnnotatedDescriptor`] formats the whole
+//! descriptor, one item per line:
+//!
+//! ```
+//! use usbd_human_interface_device::hid_class::descriptor_annotator::AnnotatedDescriptor;
+//!
+//! let descriptor = [0x05, 0x01, 0x09, 0x06, 0xA1, 0x01, 0xC0];
+//! assert_eq!(
+//!     AnnotatedDescriptor(&descriptor).to_string(),
+//!     "\
+//! 0x05, 0x01,        // Usage Page (Generic Desktop),
+//! 0x09, 0x06,        // Usage (Keyboard),
+//! 0xA1, 0x01,        // Collection (Application),
+//! 0xC0,              // End Collection
+//! "
+//! );
+//! ```
+//!
+//! Requires the `std` feature.
+
+use core::fmt::{self, Display, Formatter, Write};
+use std::string::String;
+use std::vec::Vec;
+
+use packed_struct::PrimitiveEnum;
+
+use crate::hid_class::descriptor_parser::DescriptorError;
+use crate::page;
+
+const ITEM_TYPE_MAIN: u8 = 0;
+const ITEM_TYPE_GLOBAL: u8 = 1;
+const ITEM_TYPE_LOCAL: u8 = 2;
+const LONG_ITEM_PREFIX: u8 = 0xFE;
+
+const TAG_COLLECTION: u8 = 0xA;
+const TAG_END_COLLECTION: u8 = 0xC;
+const TAG_USAGE_PAGE: u8 = 0x0;
+const TAG_PUSH: u8 = 0xA;
+const TAG_POP: u8 = 0xB;
+
+/// Width of the bytes column, including the trailing space
+const BYTES_COLUMN_WIDTH: usize = 19;
+
+/// One decoded item
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AnnotatedItem<'a> {
+    /// Offset of the item's prefix in the descriptor
+    pub offset: usize,
+    /// Prefix and data of the item
+    pub bytes: &'a [u8],
+    /// Number of collections open at the item, not counting a collection the item opens or closes
+    pub depth: usize,
+    /// Item name and decoded value, such as `Usage Page (Generic Desktop)`
+    pub annotation: String,
+}
+
+/// Decodes each item of `descriptor`
+///
+/// Only the item framing is checked, [`parse_descriptor`](super::descriptor_parser::parse_descriptor)
+/// validates the descriptor as a whole.
+pub fn annotate(descriptor: &[u8]) -> Result<Vec<AnnotatedItem<'_>>, DescriptorError> {
+    let mut items = Vec::new();
+    let mut usage_page = 0_u16;
+    let mut stack = Vec::new();
+    let mut depth = 0_usize;
+    let mut offset = 0;
+
+    while offset < descriptor.len() {
+        let prefix = descriptor[offset];
+        if prefix == LONG_ITEM_PREFIX {
+            let header = descriptor
+                .get(offset + 1..offset + 3)
+                .ok_or(DescriptorError::Truncated { offset })?;
+            let end = offset + 3 + usize::from(header[0]);
+            let bytes = descriptor
+                .get(offset..end)
+                .ok_or(DescriptorError::Truncated { offset })?;
+            items.push(AnnotatedItem {
+                offset,
+                bytes,
+                depth,
+                annotation: format!("Long Item (tag 0x{:02X}, {} bytes)", header[1], header[0]),
+            });
+            offset = end;
+            continue;
+        }
+
+        let size = match prefix & 0x03 {
+            3 => 4,
+            s => usize::from(s),
+        };
+        let bytes = descriptor
+            .get(offset..offset + 1 + size)
+            .ok_or(DescriptorError::Truncated { offset })?;
+        let value = Value::new(&bytes[1..]);
+        let tag = prefix >> 4;
+
+        let annotation = match (prefix >> 2) & 0x03 {
+            ITEM_TYPE_MAIN => {
+                if tag == TAG_END_COLLECTION {
+                    depth = depth.saturating_sub(1);
+                }
+                main_item(tag, value)
+            }
+            ITEM_TYPE_GLOBAL => {
+                match tag {
+                    TAG_USAGE_PAGE => usage_page = value.unsigned as u16,
+                    TAG_PUSH => stack.push(usage_page),
+                    TAG_POP => usage_page = stack.pop().unwrap_or_default(),
+                    _ => {}
+                }
+                global_item(tag, value)
+            }
+            ITEM_TYPE_LOCAL => local_item(tag, value, usage_page),
+            _ => return Err(DescriptorError::ReservedItem { offset }),
+        };
+        items.push(AnnotatedItem {
+            offset,
+            bytes,
+            depth,
+            annotation,
+        });
+        if is_main_item(prefix, TAG_COLLECTION) {
+            depth += 1;
+        }
+        offset += bytes.len();
+    }
+    Ok(items)
+}
+
+/// Report descriptor formatted as annotated bytes, one item per line
+///
+/// Items are indented by collection depth. A descriptor that ends part way through an item, or
+/// that has an item with the reserved item type, ends with the remaining bytes and the error.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AnnotatedDescriptor<'a>(pub &'a [u8]);
+
+impl Display for AnnotatedDescriptor<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let (items, error) = match annotate(self.0) {
+            Ok(items) => (items, None),
+            Err(e) => {
+                // Annotate the items before the error
+                let offset = match e {
+                    DescriptorError::Truncated { offset }
+                    | DescriptorError::ReservedItem { offset } => offset,
+                    _ => 0,
+                };
+                let items = annotate(&self.0[..offset]).unwrap_or_default();
+                (items, Some((offset, e)))
+            }
+        };
+
+        for item in &items {
+            let terminator = if is_main_item(item.bytes[0], TAG_END_COLLECTION) {
+                ""
+            } else {
+                ","
+            };
+            write_line(
+                f,
+                item.bytes,
+                item.depth,
+                format_args!("{}{}", item.annotation, terminator),
+            )?;
+        }
+        if let Some((offset, e)) = error {
+            let depth = items.last().map_or(0, |i| {
+                if is_main_item(i.bytes[0], TAG_COLLECTION) {
+                    i.depth + 1
+                } else {
+                    i.depth
+                }
+            });
+            write_line(f, &self.0[offset..], depth, format_args!("{:?}", e))?;
+        }
+        Ok(())
+    }
+}
+
+fn is_main_item(prefix: u8, tag: u8) -> bool {
+    prefix != LONG_ITEM_PREFIX && prefix & 0xFC == tag << 4 | ITEM_TYPE_MAIN << 2
+}
+
+fn write_line(
+    f: &mut Formatter<'_>,
+    bytes: &[u8],
+    depth: usize,
+    annotation: fmt::Arguments<'_>,
+) -> fmt::Result {
+    let mut column = String::new();
+    for b in bytes {
+        write!(column, "0x{:02X}, ", b)?;
+    }
+    writeln!(
+        f,
+        "{:<width$} // {:indent$}{}",
+        column.trim_end(),
+        "",
+        annotation,
+        width = BYTES_COLUMN_WIDTH - 1,
+        indent = depth * 2
+    )
+}
+
+/// Item data as both an unsigned and a sign extended value
+#[derive(Debug, Clone, Copy)]
+struct Value {
+    unsigned: u32,
+    signed: i32,
+    size: usize,
+}
+
+impl Value {
+    fn new(data: &[u8]) -> Self {
+        let mut bytes = [0; 4];
+        bytes[..data.len()].copy_from_slice(data);
+        let unsigned = u32::from_le_bytes(bytes);
+        let signed = match data.len() {
+            1 => i32::from(unsigned as u8 as i8),
+            2 => i32::from(unsigned as u16 as i16),
+            _ => unsigned as i32,
+        };
+        Self {
+            unsigned,
+            signed,
+            size: data.len(),
+        }
+    }
+}
+
+fn main_item(tag: u8, value: Value) -> String {
+    match tag {
+        0x8 => format!("Input ({})", main_flags(value.unsigned, false)),
+        0x9 => format!("Output ({})", main_flags(value.unsigned, true)),
+        0xB => format!("Feature ({})", main_flags(value.unsigned, true)),
+        TAG_COLLECTION => format!("Collection ({})", collection_type(value.unsigned)),
+        TAG_END_COLLECTION => String::from("End Collection"),
+        _ => format!("Reserved Main Item 0x{:X} ({})", tag, value.unsigned),
+    }
+}
+
+fn global_item(tag: u8, value: Value) -> String {
+    match tag {
+        TAG_USAGE_PAGE => format!("Usage Page ({})", usage_page_name(value.unsigned as u16)),
+        0x1 => format!("Logical Minimum ({})", value.signed),
+        0x2 => format!("Logical Maximum ({})", value.signed),
+        0x3 => format!("Physical Minimum ({})", value.signed),
+        0x4 => format!("Physical Maximum ({})", value.signed),
+        0x5 => format!("Unit Exponent ({})", unit_exponent(value)),
+        0x6 => format!("Unit (0x{:X})", value.unsigned),
+        0x7 => format!("Report Size ({})", value.unsigned),
+        0x8 => format!("Report ID ({})", value.unsigned),
+        0x9 => format!("Report Count ({})", value.unsigned),
+        TAG_PUSH => String::from("Push"),
+        TAG_POP => String::from("Pop"),
+        _ => format!("Reserved Global Item 0x{:X} ({})", tag, value.unsigned),
+    }
+}
+
+fn local_item(tag: u8, value: Value, usage_page: u16) -> String {
+    match tag {
+        0x0 => format!("Usage ({})", usage_name(value, usage_page)),
+        0x1 => format!("Usage Minimum ({})", usage_name(value, usage_page)),
+        0x2 => format!("Usage Maximum ({})", usage_name(value, usage_page)),
+        0x3 => format!("Designator Index ({})", value.unsigned),
+        0x4 => format!("Designator Minimum ({})", value.unsigned),
+        0x5 => format!("Designator Maximum ({})", value.unsigned),
+        0x7 => format!("String Index ({})", value.unsigned),
+        0x8 => format!("String Minimum ({})", value.unsigned),
+        0x9 => format!("String Maximum ({})", value.unsigned),
+        0xA => match value.unsigned {
+            1 => String::from("Delimiter (Open)"),
+            0 => String::from("Delimiter (Close)"),
+            v => format!("Delimiter ({})", v),
+        },
+        _ => format!("Reserved Local Item 0x{:X} ({})", tag, value.unsigned),
+    }
+}
+
+/// Unit Exponent is a signed 4 bit value when encoded in one byte
+fn unit_exponent(value: Value) -> i32 {
+    if value.size == 1 && value.unsigned < 0x10 {
+        ((value.unsigned as i32) << 28) >> 28
+    } else {
+        value.signed
+    }
+}
+
+fn main_flags(flags: u32, volatile: bool) -> String {
+    if flags == 0x01 {
+        return String::from("Constant");
+    }
+    let mut names = vec![
+        if flags & 0x01 == 0 {
+            "Data"
+        } else {
+            "Constant"
+        },
+        if flags & 0x02 == 0 {
+            "Array"
+        } else {
+            "Variable"
+        },
+        if flags & 0x04 == 0 {
+            "Absolute"
+        } else {
+            "Relative"
+        },
+    ];
+    let extras = [
+        (0x08, "Wrap"),
+        (0x10, "Non Linear"),
+        (0x20, "No Preferred State"),
+        (0x40, "Null State"),
+        (0x80, if volatile { "Volatile" } else { "Reserved" }),
+        (0x100, "Buffered Bytes"),
+    ];
+    names.extend(
+        extras
+            .iter()
+            .filter(|(bit, _)| flags & bit != 0)
+            .map(|(_, name)| *name),
+    );
+    names.join(", ")
+}
+
+fn collection_type(value: u32) -> String {
+    match value {
+        0x00 => String::from("Physical"),
+        0x01 => String::from("Application"),
+        0x02 => String::from("Logical"),
+        0x03 => String::from("Report"),
+        0x04 => String::from("Named Array"),
+        0x05 => String::from("Usage Switch"),
+        0x06 => String::from("Usage Modifier"),
+        0x80..=0xFF => format!("Vendor Defined 0x{:02X}", value),
+        v => format!("Reserved 0x{:02X}", v),
+    }
+}
+
+fn usage_page_name(usage_page: u16) -> String {
+    let name = match usage_page {
+        0x01 => "Generic Desktop",
+        0x02 => "Simulation Controls",
+        0x05 => "Game Controls",
+        0x07 => "Keyboard/Keypad",
+        0x08 => "LEDs",
+        0x09 => "Button",
+        0x0A => "Ordinal",
+        0x0B => "Telephony",
+        0x0C => "Consumer",
+        0x0D => "Digitizers",
+        0x0F => "Physical Interface Device",
+        0x20 => "Sensors",
+        0x84 => "Power Device",
+        0x85 => "Battery System",
+        0xFF00..=0xFFFF => return format!("Vendor Defined 0x{:04X}", usage_page),
+        _ => return format!("0x{:02X}", usage_page),
+    };
+    String::from(name)
+}
+
+/// Name of a Sensors page usage, with its modifier if it has one
+fn sensor_usage_name(usage: u16) -> Option<String> {
+    let name = format!("{:?}", page::Sensors::from_primitive(usage & 0x0FFF)?);
+    match page::SensorModifier::from_primitive(usage & 0xF000) {
+        Some(page::SensorModifier::None) | None => Some(name),
+        Some(modifier) => Some(format!("{} ({:?})", name, modifier)),
+    }
+}
+
+/// Name of `value` from the usage page enums in [`page`], or its number
+///
+/// Four byte usages carry their own usage page in the high 16 bits.
+fn usage_name(value: Value, usage_page: u16) -> String {
+    let (usage_page, usage) = if value.size == 4 {
+        ((value.unsigned >> 16) as u16, value.unsigned as u16)
+    } else {
+        (usage_page, value.unsigned as u16)
+    };
+    let name = match (usage_page, u8::try_from(usage)) {
+        (0x01, Ok(u)) => page::Desktop::from_primitive(u).map(|u| format!("{:?}", u)),
+        (0x02, Ok(u)) => page::Simulation::from_primitive(u).map(|u| format!("{:?}", u)),
+        (0x05, Ok(u)) => page::Game::from_primitive(u).map(|u| format!("{:?}", u)),
+        (0x07, Ok(u)) => page::Keyboard::from_primitive(u).map(|u| format!("{:?}", u)),
+        (0x08, Ok(u)) => page::Leds::from_primitive(u).map(|u| format!("{:?}", u)),
+        (0x0B, Ok(u)) => page::Telephony::from_primitive(u).map(|u| format!("{:?}", u)),
+        (0x0C, _) => page::Consumer::from_primitive(usage).map(|u| format!("{:?}", u)),
+        (0x20, _) => sensor_usage_name(usage),
+        (0x84, Ok(u)) => page::PowerDevice::from_primitive(u).map(|u| format!("{:?}", u)),
+        (0x85, Ok(u)) => page::BatterySystem::from_primitive(u).map(|u| format!("{:?}", u)),
+        _ => None,
+    };
+    let name = name.unwrap_or_else(|| format!("0x{:02X}", usage));
+    if value.size == 4 {
+        format!("{}: {}", usage_page_name(usage_page), name)
+    } else {
+        name
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::string::ToString;
+
+    use crate::hid_class::descriptor_annotator::*;
+
+    #[test]
+    fn boot_mouse_annotated() {
+        use crate::device::mouse::BOOT_MOUSE_REPORT_DESCRIPTOR;
+
+        let expected = "\
+0x05, 0x01,        // Usage Page (Generic Desktop),
+0x09, 0x02,        // Usage (Mouse),
+0xA1, 0x01,        // Collection (Application),
+0x09, 0x01,        //   Usage (Pointer),
+0xA1, 0x00,        //   Collection (Physical),
+0x95, 0x03,        //     Report Count (3),
+0x75, 0x01,        //     Report Size (1),
+0x05, 0x09,        //     Usage Page (Button),
+0x19, 0x01,        //     Usage Minimum (0x01),
+0x29, 0x03,        //     Usage Maximum (0x03),
+0x15, 0x00,        //     Logical Minimum (0),
+0x25, 0x01,        //     Logical Maximum (1),
+0x81, 0x02,        //     Input (Data, Variable, Absolute),
+0x95, 0x01,        //     Report Count (1),
+0x75, 0x05,        //     Report Size (5),
+0x81, 0x01,        //     Input (Constant),
+0x75, 0x08,        //     Report Size (8),
+0x95, 0x02,        //     Report Count (2),
+0x05, 0x01,        //     Usage Page (Generic Desktop),
+0x09, 0x30,        //     Usage (X),
+0x09, 0x31,        //     Usage (Y),
+0x15, 0x81,        //     Logical Minimum (-127),
+0x25, 0x7F,        //     Logical Maximum (127),
+0x81, 0x06,        //     Input (Data, Variable, Relative),
+0xC0,              //   End Collection
+0xC0,              // End Collection
+";
+        assert_eq!(
+            AnnotatedDescriptor(BOOT_MOUSE_REPORT_DESCRIPTOR).to_string(),
+            expected
+        );
+    }
+
+    #[test]
+    fn items_decoded() {
+        let descriptor = [
+            0x05, 0x0C, // Usage Page (Consumer)
+            0x0A, 0x23, 0x02, // Usage (AC Home)
+            0x27, 0xFF, 0xFF, 0x00, 0x00, // Logical Maximum (65535)
+            0x16, 0x00, 0x80, // Logical Minimum (-32768)
+            0x55, 0x0E, // Unit Exponent (-2)
+            0xA4, // Push
+            0x06, 0x00, 0xFF, // Usage Page (Vendor Defined 0xFF00)
+            0xB4, // Pop
+            0x0B, 0x30, 0x00, 0x01, 0x00, // Usage (Generic Desktop: X)
+            0xB1, 0x62, // Feature (Data, Variable, Absolute, No Preferred State, Null State)
+            0xFE, 0x01, 0x10, 0xAA, // Long Item
+        ];
+        let annotations: Vec<_> = annotate(&descriptor)
+            .unwrap()
+            .into_iter()
+            .map(|i| i.annotation)
+            .collect();
+        assert_eq!(
+            annotations,
+            [
+                "Usage Page (Consumer)",
+                "Usage (ACHome)",
+                "Logical Maximum (65535)",
+                "Logical Minimum (-32768)",
+                "Unit Exponent (-2)",
+                "Push",
+                "Usage Page (Vendor Defined 0xFF00)",
+                "Pop",
+                "Usage (Generic Desktop: X)",
+                "Feature (Data, Variable, Absolute, No Preferred State, Null State)",
+                "Long Item (tag 0x10, 1 bytes)",
+            ]
+        );
+    }
+
+    #[test]
+    fn sensor_usages_named() {
+        use crate::page::{SensorModifier, Sensors};
+
+        let [lo, hi] = Sensors::DataFieldAccelerationAxisX
+            .with_modifier(SensorModifier::Maximum)
+            .to_le_bytes();
+        let descriptor = [
+            0x05, 0x20, // Usage Page (Sensors)
+            0x09, 0x73, // Usage (Motion: Accelerometer 3D)
+            0x0A, lo, hi, // Usage (Data Field: Acceleration Axis X, Maximum)
+        ];
+        let annotations: Vec<_> = annotate(&descriptor)
+            .unwrap()
+            .into_iter()
+            .map(|i| i.annotation)
+            .collect();
+        assert_eq!(
+            annotations,
+            [
+                "Usage Page (Sensors)",
+                "Usage (MotionAccelerometer3D)",
+                "Usage (DataFieldAccelerationAxisX (Maximum))",
+            ]
+        );
+    }
+
+    #[test]
+    fn truncated_descriptor_annotated() {
+        let descriptor = [0x05, 0x01, 0xA1, 0x01, 0x26, 0xFF];
+        assert_eq!(
+            annotate(&descriptor),
+            Err(DescriptorError::Truncated { offset: 4 })
+        );
+        assert_eq!(
+            AnnotatedDescriptor(&descriptor).to_string(),
+            "\
+0x05, 0x01,        // Usage Page (Generic Desktop),
+0xA1, 0x01,        // Collection (Application),
+0x26, 0xFF,        //   Truncated { offset: 4 }
+"
+        );
+    }
+}
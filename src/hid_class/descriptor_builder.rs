@@ -0,0 +1,569 @@
+//! Report descriptors constructed at runtime
+//!
+//! [`ReportDescriptorBuilder`] writes the short items of a report descriptor, defined in section
+//! 6.2.2 of [Device Class Definition for Human Interface Devices (Hid) Version
+//! 1.11](<https://www.usb.org/sites/default/files/hid1_11.pdf>), to a caller provided buffer. For
+//! descriptors that depend on the hardware, such as the number of keys detected at startup.
+//!
+//! ```
+//! use usbd_human_interface_device::hid_class::descriptor_builder::{
+//!     Collection, ItemFlags, ReportDescriptorBuilder,
+//! };
+//!
+//! let keys = 12;
+//! let mut buffer = [0; 64];
+//! let descriptor = ReportDescriptorBuilder::new(&mut buffer)
+//!     .usage_page(0x09) // Button
+//!     .collection(Collection::Application)
+//!     .usage_minimum(1)
+//!     .usage_maximum(keys)
+//!     .logical_minimum(0)
+//!     .logical_maximum(1)
+//!     .report_size(1)
+//!     .report_count(u32::from(keys))
+//!     .input(ItemFlags::DATA_VARIABLE_ABSOLUTE)
+//!     .end_collection()
+//!     .finish()
+//!     .unwrap();
+//! assert_eq!(descriptor.len(), 19);
+//! ```
+//!
+//! Descriptors known at compile time can be written with the same items using the
+//! [`hid_descriptor!`](crate::hid_descriptor) macro, which evaluates to a `&'static [u8]`:
+//!
+//! ```
+//! use usbd_human_interface_device::hid_class::descriptor_builder::{Collection, ItemFlags};
+//! use usbd_human_interface_device::hid_descriptor;
+//!
+//! const BUTTONS: &[u8] = hid_descriptor![
+//!     usage_page(0x09),
+//!     collection(Collection::Application),
+//!     usage_minimum(1),
+//!     usage_maximum(12),
+//!     logical_minimum(0),
+//!     logical_maximum(1),
+//!     report_size(1),
+//!     report_count(12),
+//!     input(ItemFlags::DATA_VARIABLE_ABSOLUTE),
+//!     end_collection(),
+//! ];
+//! assert_eq!(BUTTONS.len(), 19);
+//! ```
+
+use core::ops::BitOr;
+
+use crate::hid_class::{BuilderResult, UsbHidBuilderError};
+
+const ITEM_TYPE_MAIN: u8 = 0;
+const ITEM_TYPE_GLOBAL: u8 = 1;
+const ITEM_TYPE_LOCAL: u8 = 2;
+
+const TAG_INPUT: u8 = 0x8;
+const TAG_OUTPUT: u8 = 0x9;
+const TAG_COLLECTION: u8 = 0xA;
+const TAG_FEATURE: u8 = 0xB;
+const TAG_END_COLLECTION: u8 = 0xC;
+
+const TAG_USAGE_PAGE: u8 = 0x0;
+const TAG_LOGICAL_MINIMUM: u8 = 0x1;
+const TAG_LOGICAL_MAXIMUM: u8 = 0x2;
+const TAG_PHYSICAL_MINIMUM: u8 = 0x3;
+const TAG_PHYSICAL_MAXIMUM: u8 = 0x4;
+const TAG_UNIT_EXPONENT: u8 = 0x5;
+const TAG_UNIT: u8 = 0x6;
+const TAG_REPORT_SIZE: u8 = 0x7;
+const TAG_REPORT_ID: u8 = 0x8;
+const TAG_REPORT_COUNT: u8 = 0x9;
+
+const TAG_USAGE: u8 = 0x0;
+const TAG_USAGE_MINIMUM: u8 = 0x1;
+const TAG_USAGE_MAXIMUM: u8 = 0x2;
+
+/// Data of Input, Output and Feature items - Hid spec 6.2.2.5
+///
+/// Combine with `|`, e.g. `ItemFlags::DATA_VARIABLE_ABSOLUTE | ItemFlags::NULL_STATE`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ItemFlags(u16);
+
+impl ItemFlags {
+    pub const DATA_ARRAY_ABSOLUTE: Self = Self(0);
+    pub const CONSTANT: Self = Self(1 << 0);
+    pub const VARIABLE: Self = Self(1 << 1);
+    pub const RELATIVE: Self = Self(1 << 2);
+    pub const WRAP: Self = Self(1 << 3);
+    pub const NON_LINEAR: Self = Self(1 << 4);
+    pub const NO_PREFERRED_STATE: Self = Self(1 << 5);
+    pub const NULL_STATE: Self = Self(1 << 6);
+    pub const VOLATILE: Self = Self(1 << 7);
+    pub const BUFFERED_BYTES: Self = Self(1 << 8);
+
+    pub const DATA_VARIABLE_ABSOLUTE: Self = Self::VARIABLE;
+    pub const DATA_VARIABLE_RELATIVE: Self = Self(Self::VARIABLE.0 | Self::RELATIVE.0);
+    /// Padding
+    pub const CONSTANT_VARIABLE_ABSOLUTE: Self = Self(Self::CONSTANT.0 | Self::VARIABLE.0);
+
+    pub const fn bits(&self) -> u16 {
+        self.0
+    }
+
+    /// `self | other` in a const context
+    pub const fn union(self, other: Self) -> Self {
+        Self(self.0 | other.0)
+    }
+}
+
+impl BitOr for ItemFlags {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        Self(self.0 | rhs.0)
+    }
+}
+
+/// Type of a Collection item - Hid spec 6.2.2.6
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum Collection {
+    Physical = 0x00,
+    Application = 0x01,
+    Logical = 0x02,
+    Report = 0x03,
+    NamedArray = 0x04,
+    UsageSwitch = 0x05,
+    UsageModifier = 0x06,
+}
+
+/// An encoded short item, the prefix and up to 4 bytes of data - Hid spec 6.2.2.2
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Item {
+    bytes: [u8; 5],
+    len: usize,
+}
+
+impl Item {
+    pub const fn usage_page(page: u16) -> Self {
+        Self::unsigned(ITEM_TYPE_GLOBAL, TAG_USAGE_PAGE, page as u32)
+    }
+
+    pub const fn usage(usage: u16) -> Self {
+        Self::unsigned(ITEM_TYPE_LOCAL, TAG_USAGE, usage as u32)
+    }
+
+    pub const fn usage_minimum(usage: u16) -> Self {
+        Self::unsigned(ITEM_TYPE_LOCAL, TAG_USAGE_MINIMUM, usage as u32)
+    }
+
+    pub const fn usage_maximum(usage: u16) -> Self {
+        Self::unsigned(ITEM_TYPE_LOCAL, TAG_USAGE_MAXIMUM, usage as u32)
+    }
+
+    pub const fn logical_minimum(value: i32) -> Self {
+        Self::signed(ITEM_TYPE_GLOBAL, TAG_LOGICAL_MINIMUM, value)
+    }
+
+    pub const fn logical_maximum(value: i32) -> Self {
+        Self::signed(ITEM_TYPE_GLOBAL, TAG_LOGICAL_MAXIMUM, value)
+    }
+
+    pub const fn physical_minimum(value: i32) -> Self {
+        Self::signed(ITEM_TYPE_GLOBAL, TAG_PHYSICAL_MINIMUM, value)
+    }
+
+    pub const fn physical_maximum(value: i32) -> Self {
+        Self::signed(ITEM_TYPE_GLOBAL, TAG_PHYSICAL_MAXIMUM, value)
+    }
+
+    pub const fn unit_exponent(exponent: i8) -> Self {
+        Self::signed(ITEM_TYPE_GLOBAL, TAG_UNIT_EXPONENT, exponent as i32)
+    }
+
+    pub const fn unit(unit: u32) -> Self {
+        Self::unsigned(ITEM_TYPE_GLOBAL, TAG_UNIT, unit)
+    }
+
+    pub const fn report_size(bits: u32) -> Self {
+        Self::unsigned(ITEM_TYPE_GLOBAL, TAG_REPORT_SIZE, bits)
+    }
+
+    pub const fn report_count(count: u32) -> Self {
+        Self::unsigned(ITEM_TYPE_GLOBAL, TAG_REPORT_COUNT, count)
+    }
+
+    /// Panics for report ID 0, which is reserved, a compile error when evaluated in a const
+    /// context
+    pub const fn report_id(id: u8) -> Self {
+        assert!(id != 0, "report ID 0 is reserved");
+        Self::unsigned(ITEM_TYPE_GLOBAL, TAG_REPORT_ID, id as u32)
+    }
+
+    pub const fn input(flags: ItemFlags) -> Self {
+        Self::unsigned(ITEM_TYPE_MAIN, TAG_INPUT, flags.0 as u32)
+    }
+
+    pub const fn output(flags: ItemFlags) -> Self {
+        Self::unsigned(ITEM_TYPE_MAIN, TAG_OUTPUT, flags.0 as u32)
+    }
+
+    pub const fn feature(flags: ItemFlags) -> Self {
+        Self::unsigned(ITEM_TYPE_MAIN, TAG_FEATURE, flags.0 as u32)
+    }
+
+    pub const fn collection(collection: Collection) -> Self {
+        Self::unsigned(ITEM_TYPE_MAIN, TAG_COLLECTION, collection as u32)
+    }
+
+    pub const fn end_collection() -> Self {
+        Self::new(ITEM_TYPE_MAIN, TAG_END_COLLECTION, 0, 0)
+    }
+
+    pub const fn as_bytes(&self) -> &[u8] {
+        self.bytes.split_at(self.len).0
+    }
+
+    /// Data in the fewest bytes holding `value` as an unsigned integer
+    const fn unsigned(item_type: u8, tag: u8, value: u32) -> Self {
+        let size = if value <= u8::MAX as u32 {
+            1
+        } else if value <= u16::MAX as u32 {
+            2
+        } else {
+            4
+        };
+        Self::new(item_type, tag, value, size)
+    }
+
+    /// Data in the fewest bytes holding `value` as a two's complement integer
+    const fn signed(item_type: u8, tag: u8, value: i32) -> Self {
+        let size = if value >= i8::MIN as i32 && value <= i8::MAX as i32 {
+            1
+        } else if value >= i16::MIN as i32 && value <= i16::MAX as i32 {
+            2
+        } else {
+            4
+        };
+        Self::new(item_type, tag, value as u32, size)
+    }
+
+    const fn new(item_type: u8, tag: u8, data: u32, size: usize) -> Self {
+        let size_code = if size == 4 { 3 } else { size as u8 };
+        let data = data.to_le_bytes();
+        Self {
+            bytes: [
+                tag << 4 | item_type << 2 | size_code,
+                data[0],
+                data[1],
+                data[2],
+                data[3],
+            ],
+            len: 1 + size,
+        }
+    }
+}
+
+/// Total length of encoded `items`
+pub const fn items_len(items: &[Item]) -> usize {
+    let mut len = 0;
+    let mut i = 0;
+    while i < items.len() {
+        len += items[i].len;
+        i += 1;
+    }
+    len
+}
+
+/// Concatenates encoded `items`, `N` must be their [`items_len`]
+pub const fn concat_items<const N: usize>(items: &[Item]) -> [u8; N] {
+    let mut descriptor = [0; N];
+    let mut n = 0;
+    let mut i = 0;
+    while i < items.len() {
+        let item = items[i].as_bytes();
+        let mut j = 0;
+        while j < item.len() {
+            descriptor[n] = item[j];
+            n += 1;
+            j += 1;
+        }
+        i += 1;
+    }
+    assert!(n == N, "descriptor length does not match items");
+    descriptor
+}
+
+/// A report descriptor, as a `&'static [u8]`, from a list of items encoded at compile time
+///
+/// Items are named after the [`Item`](crate::hid_class::descriptor_builder::Item) constructors,
+/// see the [`descriptor_builder`](crate::hid_class::descriptor_builder) module.
+#[macro_export]
+macro_rules! hid_descriptor {
+    ($($item:ident($($arg:expr),*)),* $(,)?) => {{
+        const ITEMS: &[$crate::hid_class::descriptor_builder::Item] =
+            &[$($crate::hid_class::descriptor_builder::Item::$item($($arg),*)),*];
+        const LEN: usize = $crate::hid_class::descriptor_builder::items_len(ITEMS);
+        const DESCRIPTOR: [u8; LEN] = $crate::hid_class::descriptor_builder::concat_items(ITEMS);
+        &DESCRIPTOR
+    }};
+}
+
+/// Writes report descriptor items to a buffer
+///
+/// Errors are deferred to [`ReportDescriptorBuilder::finish`], so items can be chained without
+/// checking each one.
+#[must_use = "the descriptor is only returned by `ReportDescriptorBuilder::finish`"]
+#[derive(Debug)]
+pub struct ReportDescriptorBuilder<'b> {
+    buffer: &'b mut [u8],
+    len: usize,
+    open_collections: usize,
+    error: Option<UsbHidBuilderError>,
+}
+
+impl<'b> ReportDescriptorBuilder<'b> {
+    pub fn new(buffer: &'b mut [u8]) -> Self {
+        Self {
+            buffer,
+            len: 0,
+            open_collections: 0,
+            error: None,
+        }
+    }
+
+    pub fn usage_page(self, page: u16) -> Self {
+        self.item(Item::usage_page(page))
+    }
+
+    pub fn usage(self, usage: u16) -> Self {
+        self.item(Item::usage(usage))
+    }
+
+    pub fn usage_minimum(self, usage: u16) -> Self {
+        self.item(Item::usage_minimum(usage))
+    }
+
+    pub fn usage_maximum(self, usage: u16) -> Self {
+        self.item(Item::usage_maximum(usage))
+    }
+
+    pub fn logical_minimum(self, value: i32) -> Self {
+        self.item(Item::logical_minimum(value))
+    }
+
+    pub fn logical_maximum(self, value: i32) -> Self {
+        self.item(Item::logical_maximum(value))
+    }
+
+    pub fn physical_minimum(self, value: i32) -> Self {
+        self.item(Item::physical_minimum(value))
+    }
+
+    pub fn physical_maximum(self, value: i32) -> Self {
+        self.item(Item::physical_maximum(value))
+    }
+
+    pub fn unit_exponent(self, exponent: i8) -> Self {
+        self.item(Item::unit_exponent(exponent))
+    }
+
+    pub fn unit(self, unit: u32) -> Self {
+        self.item(Item::unit(unit))
+    }
+
+    /// Size of each field in bits
+    pub fn report_size(self, bits: u32) -> Self {
+        self.item(Item::report_size(bits))
+    }
+
+    pub fn report_count(self, count: u32) -> Self {
+        self.item(Item::report_count(count))
+    }
+
+    /// Report ID of the following reports, [`UsbHidBuilderError::InvalidValue`] for 0, which is
+    /// reserved
+    pub fn report_id(mut self, id: u8) -> Self {
+        if id == 0 {
+            self.fail(UsbHidBuilderError::InvalidValue);
+            self
+        } else {
+            self.item(Item::report_id(id))
+        }
+    }
+
+    pub fn input(self, flags: ItemFlags) -> Self {
+        self.item(Item::input(flags))
+    }
+
+    pub fn output(self, flags: ItemFlags) -> Self {
+        self.item(Item::output(flags))
+    }
+
+    pub fn feature(self, flags: ItemFlags) -> Self {
+        self.item(Item::feature(flags))
+    }
+
+    pub fn collection(mut self, collection: Collection) -> Self {
+        self.open_collections += 1;
+        self.item(Item::collection(collection))
+    }
+
+    /// [`UsbHidBuilderError::InvalidValue`] without an open collection
+    pub fn end_collection(mut self) -> Self {
+        if self.open_collections == 0 {
+            self.fail(UsbHidBuilderError::InvalidValue);
+        } else {
+            self.open_collections -= 1;
+        }
+        self.item(Item::end_collection())
+    }
+
+    pub fn item(mut self, item: Item) -> Self {
+        self.write(item.as_bytes());
+        self
+    }
+
+    /// Appends already encoded items, such as a fragment of a static descriptor
+    pub fn raw(mut self, items: &[u8]) -> Self {
+        self.write(items);
+        self
+    }
+
+    /// The descriptor, [`UsbHidBuilderError::ValueOverflow`] if it did not fit in the buffer and
+    /// [`UsbHidBuilderError::InvalidValue`] for an invalid item or unbalanced collections
+    pub fn finish(self) -> BuilderResult<&'b [u8]> {
+        match self.error {
+            Some(e) => Err(e),
+            None if self.open_collections > 0 => Err(UsbHidBuilderError::InvalidValue),
+            None => Ok(&self.buffer[..self.len]),
+        }
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        match self.buffer.get_mut(self.len..self.len + bytes.len()) {
+            Some(dest) => {
+                dest.copy_from_slice(bytes);
+                self.len += bytes.len();
+            }
+            None => self.fail(UsbHidBuilderError::ValueOverflow),
+        }
+    }
+
+    fn fail(&mut self, error: UsbHidBuilderError) {
+        self.error.get_or_insert(error);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::device::vendor::vendor_raw_report_descriptor;
+    use crate::hid_class::descriptor_builder::*;
+
+    #[test]
+    fn matches_static_descriptor() {
+        let mut buffer = [0; 64];
+        let descriptor = ReportDescriptorBuilder::new(&mut buffer)
+            .usage_page(0xFF00)
+            .usage(0x01)
+            .collection(Collection::Application)
+            .usage(0x02)
+            .logical_minimum(0)
+            .logical_maximum(255)
+            .report_size(8)
+            .report_count(4)
+            .input(ItemFlags::DATA_VARIABLE_ABSOLUTE)
+            .usage(0x03)
+            .report_count(8)
+            .output(ItemFlags::DATA_VARIABLE_ABSOLUTE)
+            .end_collection()
+            .finish()
+            .unwrap();
+        assert_eq!(descriptor, vendor_raw_report_descriptor(4, 8));
+    }
+
+    #[test]
+    fn item_sizes() {
+        let mut buffer = [0; 32];
+        let descriptor = ReportDescriptorBuilder::new(&mut buffer)
+            .logical_minimum(-127)
+            .logical_maximum(32767)
+            .physical_maximum(-40000)
+            .unit(0x0001_0001)
+            .input(ItemFlags::CONSTANT | ItemFlags::BUFFERED_BYTES)
+            .finish()
+            .unwrap();
+        assert_eq!(
+            descriptor,
+            [
+                0x15, 0x81, //
+                0x26, 0xFF, 0x7F, //
+                0x47, 0xC0, 0x63, 0xFF, 0xFF, //
+                0x67, 0x01, 0x00, 0x01, 0x00, //
+                0x82, 0x01, 0x01,
+            ]
+        );
+    }
+
+    #[test]
+    fn macro_matches_static_descriptor() {
+        use crate::device::mouse::BOOT_MOUSE_REPORT_DESCRIPTOR;
+
+        const DESCRIPTOR: &[u8] = hid_descriptor![
+            usage_page(0x01),
+            usage(0x02),
+            collection(Collection::Application),
+            usage(0x01),
+            collection(Collection::Physical),
+            report_count(3),
+            report_size(1),
+            usage_page(0x09),
+            usage_minimum(1),
+            usage_maximum(3),
+            logical_minimum(0),
+            logical_maximum(1),
+            input(ItemFlags::DATA_VARIABLE_ABSOLUTE),
+            report_count(1),
+            report_size(5),
+            input(ItemFlags::CONSTANT),
+            report_size(8),
+            report_count(2),
+            usage_page(0x01),
+            usage(0x30),
+            usage(0x31),
+            logical_minimum(-127),
+            logical_maximum(127),
+            input(ItemFlags::DATA_VARIABLE_ABSOLUTE.union(ItemFlags::RELATIVE)),
+            end_collection(),
+            end_collection(),
+        ];
+        assert_eq!(DESCRIPTOR, BOOT_MOUSE_REPORT_DESCRIPTOR);
+    }
+
+    #[test]
+    fn errors() {
+        let mut buffer = [0; 4];
+        assert_eq!(
+            ReportDescriptorBuilder::new(&mut buffer)
+                .usage_page(0x01)
+                .usage(0x02)
+                .usage(0x03)
+                .finish(),
+            Err(UsbHidBuilderError::ValueOverflow)
+        );
+        assert_eq!(
+            ReportDescriptorBuilder::new(&mut buffer)
+                .collection(Collection::Application)
+                .finish(),
+            Err(UsbHidBuilderError::InvalidValue)
+        );
+        assert_eq!(
+            ReportDescriptorBuilder::new(&mut buffer)
+                .end_collection()
+                .finish(),
+            Err(UsbHidBuilderError::InvalidValue)
+        );
+        assert_eq!(
+            ReportDescriptorBuilder::new(&mut buffer)
+                .report_id(0)
+                .finish(),
+            Err(UsbHidBuilderError::InvalidValue)
+        );
+    }
+}
@@ -0,0 +1,427 @@
+//! Report descriptor parsing and validation
+//!
+//! [`parse_descriptor`] walks the items of a report descriptor, defined in section 6.2.2 of
+//! [Device Class Definition for Human Interface Devices (Hid) Version
+//! 1.11](<https://www.usb.org/sites/default/files/hid1_11.pdf>), checks that collections are
+//! balanced and that main items have the global and local state they need, and sums the size of
+//! each input, output and feature report.
+//!
+//! ```
+//! use usbd_human_interface_device::device::mouse::BOOT_MOUSE_REPORT_DESCRIPTOR;
+//! use usbd_human_interface_device::hid_class::descriptor::ReportType;
+//! use usbd_human_interface_device::hid_class::descriptor_parser::parse_descriptor;
+//!
+//! let summary = parse_descriptor::<4>(BOOT_MOUSE_REPORT_DESCRIPTOR).unwrap();
+//! assert_eq!(summary.report_len(ReportType::Input, 0), Some(3));
+//! assert!(!summary.uses_report_ids());
+//! ```
+
+use heapless::Vec;
+
+use crate::hid_class::descriptor::ReportType;
+
+const ITEM_TYPE_MAIN: u8 = 0;
+const ITEM_TYPE_GLOBAL: u8 = 1;
+const ITEM_TYPE_LOCAL: u8 = 2;
+const LONG_ITEM_PREFIX: u8 = 0xFE;
+
+const TAG_INPUT: u8 = 0x8;
+const TAG_OUTPUT: u8 = 0x9;
+const TAG_COLLECTION: u8 = 0xA;
+const TAG_FEATURE: u8 = 0xB;
+const TAG_END_COLLECTION: u8 = 0xC;
+
+const TAG_REPORT_SIZE: u8 = 0x7;
+const TAG_REPORT_ID: u8 = 0x8;
+const TAG_REPORT_COUNT: u8 = 0x9;
+const TAG_PUSH: u8 = 0xA;
+const TAG_POP: u8 = 0xB;
+
+const TAG_USAGE_MINIMUM: u8 = 0x1;
+const TAG_USAGE_MAXIMUM: u8 = 0x2;
+
+const GLOBAL_STACK_DEPTH: usize = 4;
+
+/// Errors found while parsing a report descriptor, with the offset of the item at fault
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DescriptorError {
+    /// The descriptor ends part way through an item
+    Truncated { offset: usize },
+    /// An item with the reserved item type
+    ReservedItem { offset: usize },
+    /// An End Collection without an open collection, or collections left open at the end
+    UnbalancedCollection { offset: usize },
+    /// An Input, Output or Feature item before the Report Size or Report Count is set
+    MissingGlobal { offset: usize },
+    /// A Usage Minimum without a Usage Maximum, or the other way around
+    UnpairedUsageRange { offset: usize },
+    /// Report ID 0, which is reserved
+    InvalidReportId { offset: usize },
+    /// A Push beyond the supported depth or a Pop without a Push
+    GlobalStack { offset: usize },
+    /// Reports both with and without report IDs
+    MixedReportIds,
+    /// More reports than the summary can hold
+    TooManyReports,
+}
+
+/// Size of one report
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReportSize {
+    pub report_type: ReportType,
+    /// Report ID, 0 if the descriptor does not use report IDs
+    pub report_id: u8,
+    /// Size in bits, excluding the report ID
+    pub bits: u32,
+}
+
+impl ReportSize {
+    /// Size in bytes, excluding the report ID
+    pub fn len(&self) -> usize {
+        self.bits.div_ceil(8) as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bits == 0
+    }
+}
+
+/// Sizes of up to `N` reports of a valid report descriptor
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct DescriptorSummary<const N: usize> {
+    reports: Vec<ReportSize, N>,
+    collections: usize,
+}
+
+impl<const N: usize> DescriptorSummary<N> {
+    pub fn reports(&self) -> &[ReportSize] {
+        &self.reports
+    }
+
+    /// Number of top level, application, collections
+    pub fn collections(&self) -> usize {
+        self.collections
+    }
+
+    pub fn uses_report_ids(&self) -> bool {
+        self.reports.iter().any(|r| r.report_id != 0)
+    }
+
+    /// Length in bytes of report `report_id`, excluding the report ID, 0 for descriptors without
+    /// report IDs
+    pub fn report_len(&self, report_type: ReportType, report_id: u8) -> Option<usize> {
+        self.reports
+            .iter()
+            .find(|r| r.report_type == report_type && r.report_id == report_id)
+            .map(ReportSize::len)
+    }
+
+    fn add(
+        &mut self,
+        report_type: ReportType,
+        report_id: u8,
+        bits: u32,
+    ) -> Result<(), DescriptorError> {
+        match self
+            .reports
+            .iter_mut()
+            .find(|r| r.report_type == report_type && r.report_id == report_id)
+        {
+            Some(report) => report.bits += bits,
+            None => self
+                .reports
+                .push(ReportSize {
+                    report_type,
+                    report_id,
+                    bits,
+                })
+                .map_err(|_| DescriptorError::TooManyReports)?,
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct Globals {
+    report_size: Option<u32>,
+    report_count: Option<u32>,
+    report_id: u8,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct Locals {
+    usage_minimum: bool,
+    usage_maximum: bool,
+}
+
+/// Parses and validates `descriptor`, summarising up to `N` reports
+pub fn parse_descriptor<const N: usize>(
+    descriptor: &[u8],
+) -> Result<DescriptorSummary<N>, DescriptorError> {
+    let mut summary = DescriptorSummary::default();
+    let mut globals = Globals::default();
+    let mut stack: Vec<Globals, GLOBAL_STACK_DEPTH> = Vec::new();
+    let mut locals = Locals::default();
+    let mut depth = 0_usize;
+    let mut offset = 0;
+
+    while offset < descriptor.len() {
+        let prefix = descriptor[offset];
+        if prefix == LONG_ITEM_PREFIX {
+            let size = *descriptor
+                .get(offset + 1)
+                .ok_or(DescriptorError::Truncated { offset })?;
+            offset += 3 + usize::from(size);
+            if offset > descriptor.len() {
+                return Err(DescriptorError::Truncated { offset });
+            }
+            continue;
+        }
+
+        let size = match prefix & 0x03 {
+            3 => 4,
+            s => usize::from(s),
+        };
+        let data = descriptor
+            .get(offset + 1..offset + 1 + size)
+            .ok_or(DescriptorError::Truncated { offset })?;
+        let mut bytes = [0; 4];
+        bytes[..size].copy_from_slice(data);
+        let value = u32::from_le_bytes(bytes);
+        let tag = prefix >> 4;
+
+        match (prefix >> 2) & 0x03 {
+            ITEM_TYPE_MAIN => {
+                let report_type = match tag {
+                    TAG_INPUT => Some(ReportType::Input),
+                    TAG_OUTPUT => Some(ReportType::Output),
+                    TAG_FEATURE => Some(ReportType::Feature),
+                    TAG_COLLECTION => {
+                        if depth == 0 {
+                            summary.collections += 1;
+                        }
+                        depth += 1;
+                        None
+                    }
+                    TAG_END_COLLECTION => {
+                        depth = depth
+                            .checked_sub(1)
+                            .ok_or(DescriptorError::UnbalancedCollection { offset })?;
+                        None
+                    }
+                    _ => None,
+                };
+                if locals.usage_minimum != locals.usage_maximum {
+                    return Err(DescriptorError::UnpairedUsageRange { offset });
+                }
+                if let Some(report_type) = report_type {
+                    let (Some(report_size), Some(report_count)) =
+                        (globals.report_size, globals.report_count)
+                    else {
+                        return Err(DescriptorError::MissingGlobal { offset });
+                    };
+                    summary.add(report_type, globals.report_id, report_size * report_count)?;
+                }
+                locals = Locals::default();
+            }
+            ITEM_TYPE_GLOBAL => match tag {
+                TAG_REPORT_SIZE => globals.report_size = Some(value),
+                TAG_REPORT_COUNT => globals.report_count = Some(value),
+                TAG_REPORT_ID => {
+                    if value == 0 || value > u32::from(u8::MAX) {
+                        return Err(DescriptorError::InvalidReportId { offset });
+                    }
+                    globals.report_id = value as u8;
+                }
+                TAG_PUSH => stack
+                    .push(globals)
+                    .map_err(|_| DescriptorError::GlobalStack { offset })?,
+                TAG_POP => globals = stack.pop().ok_or(DescriptorError::GlobalStack { offset })?,
+                _ => {}
+            },
+            ITEM_TYPE_LOCAL => match tag {
+                TAG_USAGE_MINIMUM => locals.usage_minimum = true,
+                TAG_USAGE_MAXIMUM => locals.usage_maximum = true,
+                _ => {}
+            },
+            _ => return Err(DescriptorError::ReservedItem { offset }),
+        }
+        offset += 1 + size;
+    }
+
+    if depth > 0 {
+        return Err(DescriptorError::UnbalancedCollection { offset });
+    }
+    let with_id = summary.reports.iter().filter(|r| r.report_id != 0).count();
+    if with_id > 0 && with_id < summary.reports.len() {
+        return Err(DescriptorError::MixedReportIds);
+    }
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod test {
+    use packed_struct::PackedStruct;
+
+    use crate::hid_class::descriptor_builder::{Collection, ItemFlags};
+    use crate::hid_class::descriptor_parser::*;
+
+    fn packed_len<T: PackedStruct>() -> usize {
+        core::mem::size_of::<T::ByteArray>()
+    }
+
+    fn assert_report<T: PackedStruct>(descriptor: &[u8], report_type: ReportType, report_id: u8) {
+        let summary = parse_descriptor::<16>(descriptor).unwrap();
+        assert_eq!(
+            summary.report_len(report_type, report_id),
+            Some(packed_len::<T>()),
+            "{} {:?} report ID {}",
+            core::any::type_name::<T>(),
+            report_type,
+            report_id
+        );
+    }
+
+    #[test]
+    fn device_reports_match_descriptors() {
+        use crate::device::button_pad::*;
+        use crate::device::consumer::*;
+        use crate::device::ds4::*;
+        use crate::device::dual_analog::*;
+        use crate::device::eye_tracker::*;
+        use crate::device::foot_switch::*;
+        use crate::device::gamepad::*;
+        use crate::device::head_tracker::*;
+        use crate::device::joystick::*;
+        use crate::device::keyboard::*;
+        use crate::device::macro_pad::*;
+        use crate::device::mouse::*;
+        use crate::device::multiaxis::*;
+        use crate::device::switch::*;
+        use crate::device::touchscreen::*;
+        use crate::device::wheel::*;
+
+        let input = ReportType::Input;
+        let feature = ReportType::Feature;
+        assert_report::<BootKeyboardReport>(BOOT_KEYBOARD_REPORT_DESCRIPTOR, input, 0);
+        assert_report::<KeyboardLedsReport>(BOOT_KEYBOARD_REPORT_DESCRIPTOR, ReportType::Output, 0);
+        assert_report::<NKROBootKeyboardReport>(NKRO_BOOT_KEYBOARD_REPORT_DESCRIPTOR, input, 0);
+        assert_report::<NKROKeyboardReport>(NKRO_COMPACT_KEYBOARD_REPORT_DESCRIPTOR, input, 0);
+        assert_report::<BootMouseReport>(BOOT_MOUSE_REPORT_DESCRIPTOR, input, 0);
+        assert_report::<WheelMouseReport>(WHEEL_MOUSE_REPORT_DESCRIPTOR, input, 0);
+        assert_report::<AbsoluteMouseReport>(ABSOLUTE_MOUSE_REPORT_DESCRIPTOR, input, 0);
+        assert_report::<HighResMouseReport>(HIGH_RES_MOUSE_REPORT_DESCRIPTOR, input, 0);
+        assert_report::<MultipleConsumerReport>(MULTIPLE_CODE_REPORT_DESCRIPTOR, input, 0);
+        assert_report::<FixedFunctionReport>(FIXED_FUNCTION_REPORT_DESCRIPTOR, input, 0);
+        assert_report::<GamepadReport>(GAMEPAD_REPORT_DESCRIPTOR, input, 0);
+        assert_report::<RumbleReport>(GAMEPAD_REPORT_DESCRIPTOR, ReportType::Output, 0);
+        assert_report::<DualAnalogGamepadReport>(DUAL_ANALOG_GAMEPAD_REPORT_DESCRIPTOR, input, 0);
+        assert_report::<SwitchControllerReport>(SWITCH_CONTROLLER_REPORT_DESCRIPTOR, input, 0);
+        assert_report::<DS4GamepadReport>(DS4_REPORT_DESCRIPTOR, input, DS4_INPUT_REPORT_ID);
+        assert_report::<BootKeyboardReport>(
+            MACRO_PAD_REPORT_DESCRIPTOR,
+            input,
+            MACRO_PAD_REPORT_ID,
+        );
+        assert_report::<[u8; MACRO_PAD_KEYS]>(
+            MACRO_PAD_REPORT_DESCRIPTOR,
+            feature,
+            KEYMAP_REPORT_ID,
+        );
+        assert_report::<JoystickReport>(JOYSTICK_REPORT_DESCRIPTOR, input, 0);
+        assert_report::<MultiAxisReport>(MULTI_AXIS_CONTROLLER_REPORT_DESCRIPTOR, input, 0);
+        assert_report::<TouchScreenReport>(TOUCH_SCREEN_REPORT_DESCRIPTOR, input, 0);
+        assert_report::<RacingWheelReport>(RACING_WHEEL_REPORT_DESCRIPTOR, input, 0);
+        assert_report::<ButtonPadReport>(BUTTON_PAD_REPORT_DESCRIPTOR, input, 0);
+        assert_report::<HeadTrackerReport>(HEAD_TRACKER_REPORT_DESCRIPTOR, input, 0);
+        assert_report::<FootSwitchReport>(FOOT_SWITCH_REPORT_DESCRIPTOR, input, 0);
+        assert_report::<FootSwitchReport>(TELEPHONY_FOOT_SWITCH_REPORT_DESCRIPTOR, input, 0);
+        assert_report::<GazeReport>(EYE_TRACKER_REPORT_DESCRIPTOR, input, GAZE_REPORT_ID);
+        let descriptor = EYE_TRACKER_REPORT_DESCRIPTOR;
+        assert_report::<EyeTrackerCapabilities>(descriptor, feature, CAPABILITIES_REPORT_ID);
+        assert_report::<EyeTrackerConfiguration>(descriptor, feature, CONFIGURATION_REPORT_ID);
+        assert_report::<EyeTrackerStatus>(descriptor, feature, STATUS_REPORT_ID);
+    }
+
+    #[test]
+    fn report_ids_summarised() {
+        const DESCRIPTOR: &[u8] = crate::hid_descriptor![
+            usage_page(0x01),
+            collection(Collection::Application),
+            report_id(1),
+            report_size(8),
+            report_count(2),
+            input(ItemFlags::DATA_VARIABLE_ABSOLUTE),
+            report_id(2),
+            report_size(1),
+            report_count(3),
+            usage_minimum(1),
+            usage_maximum(3),
+            input(ItemFlags::DATA_VARIABLE_ABSOLUTE),
+            feature(ItemFlags::DATA_VARIABLE_ABSOLUTE),
+            end_collection(),
+        ];
+        let summary = parse_descriptor::<4>(DESCRIPTOR).unwrap();
+        assert!(summary.uses_report_ids());
+        assert_eq!(summary.collections(), 1);
+        assert_eq!(summary.report_len(ReportType::Input, 1), Some(2));
+        assert_eq!(summary.report_len(ReportType::Input, 2), Some(1));
+        assert_eq!(summary.report_len(ReportType::Feature, 2), Some(1));
+        assert_eq!(summary.report_len(ReportType::Output, 2), None);
+        assert_eq!(
+            parse_descriptor::<2>(DESCRIPTOR),
+            Err(DescriptorError::TooManyReports)
+        );
+    }
+
+    #[test]
+    fn invalid_descriptors() {
+        fn parse(descriptor: &[u8]) -> Result<DescriptorSummary<4>, DescriptorError> {
+            parse_descriptor(descriptor)
+        }
+
+        assert_eq!(
+            parse(&[0x26, 0xFF]),
+            Err(DescriptorError::Truncated { offset: 0 })
+        );
+        assert_eq!(
+            parse(&[0x0C]),
+            Err(DescriptorError::ReservedItem { offset: 0 })
+        );
+        assert_eq!(
+            parse(&[0xA1, 0x01]),
+            Err(DescriptorError::UnbalancedCollection { offset: 2 })
+        );
+        assert_eq!(
+            parse(&[0xC0]),
+            Err(DescriptorError::UnbalancedCollection { offset: 0 })
+        );
+        assert_eq!(
+            parse(&[0x75, 0x08, 0x81, 0x02]),
+            Err(DescriptorError::MissingGlobal { offset: 2 })
+        );
+        assert_eq!(
+            parse(&[0x75, 0x01, 0x95, 0x03, 0x19, 0x01, 0x81, 0x02]),
+            Err(DescriptorError::UnpairedUsageRange { offset: 6 })
+        );
+        assert_eq!(
+            parse(&[0x85, 0x00]),
+            Err(DescriptorError::InvalidReportId { offset: 0 })
+        );
+        assert_eq!(
+            parse(&[0xB4]),
+            Err(DescriptorError::GlobalStack { offset: 0 })
+        );
+        assert_eq!(
+            parse(&[0x75, 0x08, 0x95, 0x01, 0x81, 0x02, 0x85, 0x01, 0x81, 0x02]),
+            Err(DescriptorError::MixedReportIds)
+        );
+        // Push and Pop restore the report size
+        assert_eq!(
+            parse(&[0x75, 0x08, 0x95, 0x01, 0xA4, 0x75, 0x01, 0xB4, 0x81, 0x02])
+                .unwrap()
+                .report_len(ReportType::Input, 0),
+            Some(1)
+        );
+    }
+}
@@ -0,0 +1,214 @@
+//! Events accumulated by [`super::UsbHidClass`] between calls to
+//! [`super::UsbHidClass::poll_events`]
+
+use usb_device::bus::InterfaceNumber;
+
+/// Constructor of a [`HidEvent`] for an interface number
+type InterfaceEvent = fn(u8) -> HidEvent;
+
+/// One event from [`HidEvents::iter`]
+///
+/// `interface` is the interface's number, as returned by
+/// [`crate::interface::InterfaceClass::id`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HidEvent {
+    /// See [`HidEvents::output_ready`]
+    OutputReady { interface: u8 },
+    /// See [`HidEvents::input_complete`]
+    InputComplete { interface: u8 },
+    /// See [`HidEvents::feature_changed`]
+    FeatureChanged { interface: u8 },
+    /// See [`HidEvents::protocol_changed`]
+    ProtocolChanged { interface: u8 },
+    /// See [`HidEvents::idle_changed`]
+    IdleChanged { interface: u8 },
+    /// See [`HidEvents::reset`]
+    Reset,
+    /// See [`HidEvents::start_of_frame`]
+    StartOfFrame,
+}
+
+/// Compact set of events that occurred since the last call to
+/// [`super::UsbHidClass::poll_events`]
+///
+/// Interfaces are identified by their [`InterfaceNumber`], as returned by
+/// [`crate::interface::InterfaceClass::id`]. Interface numbers of 32 and above are not tracked.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct HidEvents {
+    output_ready: u32,
+    input_complete: u32,
+    feature_changed: u32,
+    protocol_changed: u32,
+    idle_changed: u32,
+    reset: bool,
+    start_of_frame: bool,
+}
+
+impl HidEvents {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// The events as [`HidEvent`]s, a reset first, then each interface's events in order of
+    /// interface number, then the start of frame
+    pub fn iter(&self) -> impl Iterator<Item = HidEvent> {
+        let masks: [(u32, InterfaceEvent); 5] = [
+            (self.output_ready, |interface| HidEvent::OutputReady {
+                interface,
+            }),
+            (self.input_complete, |interface| HidEvent::InputComplete {
+                interface,
+            }),
+            (self.feature_changed, |interface| HidEvent::FeatureChanged {
+                interface,
+            }),
+            (self.protocol_changed, |interface| {
+                HidEvent::ProtocolChanged { interface }
+            }),
+            (self.idle_changed, |interface| HidEvent::IdleChanged {
+                interface,
+            }),
+        ];
+        let interface_events = (0..u32::BITS as u8).flat_map(move |interface| {
+            masks
+                .into_iter()
+                .filter(move |(mask, _)| mask & 1 << interface != 0)
+                .map(move |(_, event)| event(interface))
+        });
+        self.reset
+            .then_some(HidEvent::Reset)
+            .into_iter()
+            .chain(interface_events)
+            .chain(self.start_of_frame.then_some(HidEvent::StartOfFrame))
+    }
+
+    /// Output report data is available to read from the interface
+    pub fn output_ready(&self, interface: InterfaceNumber) -> bool {
+        Self::is_set(self.output_ready, interface)
+    }
+
+    /// Output report data is available to read from at least one interface
+    pub fn any_output_ready(&self) -> bool {
+        self.output_ready != 0
+    }
+
+    /// The host has read the interface's last input report from its IN endpoint
+    pub fn input_complete(&self, interface: InterfaceNumber) -> bool {
+        Self::is_set(self.input_complete, interface)
+    }
+
+    /// The host has read an input report from at least one interface
+    pub fn any_input_complete(&self) -> bool {
+        self.input_complete != 0
+    }
+
+    /// The host set a feature report of the interface with a `SET_REPORT` request
+    pub fn feature_changed(&self, interface: InterfaceNumber) -> bool {
+        Self::is_set(self.feature_changed, interface)
+    }
+
+    /// The host set a feature report of at least one interface
+    pub fn any_feature_changed(&self) -> bool {
+        self.feature_changed != 0
+    }
+
+    /// The host changed the protocol of the interface
+    pub fn protocol_changed(&self, interface: InterfaceNumber) -> bool {
+        Self::is_set(self.protocol_changed, interface)
+    }
+
+    /// The host changed the protocol of at least one interface
+    pub fn any_protocol_changed(&self) -> bool {
+        self.protocol_changed != 0
+    }
+
+    /// The host changed the idle rate of one of the interface's reports, or of all its reports
+    pub fn idle_changed(&self, interface: InterfaceNumber) -> bool {
+        Self::is_set(self.idle_changed, interface)
+    }
+
+    /// The host changed an idle rate of at least one interface
+    pub fn any_idle_changed(&self) -> bool {
+        self.idle_changed != 0
+    }
+
+    /// The bus was reset, all interfaces have returned to their default state
+    pub fn reset(&self) -> bool {
+        self.reset
+    }
+
+    /// A new frame started, see [`super::UsbHidClass::start_of_frame`]
+    pub fn start_of_frame(&self) -> bool {
+        self.start_of_frame
+    }
+
+    pub(crate) fn set_output_ready(&mut self, interface: u8) {
+        Self::set(&mut self.output_ready, interface);
+    }
+
+    pub(crate) fn set_input_complete(&mut self, interface: u8) {
+        Self::set(&mut self.input_complete, interface);
+    }
+
+    pub(crate) fn set_feature_changed(&mut self, interface: u8) {
+        Self::set(&mut self.feature_changed, interface);
+    }
+
+    pub(crate) fn set_protocol_changed(&mut self, interface: u8) {
+        Self::set(&mut self.protocol_changed, interface);
+    }
+
+    pub(crate) fn set_idle_changed(&mut self, interface: u8) {
+        Self::set(&mut self.idle_changed, interface);
+    }
+
+    pub(crate) fn set_reset(&mut self) {
+        self.reset = true;
+    }
+
+    pub(crate) fn set_start_of_frame(&mut self) {
+        self.start_of_frame = true;
+    }
+
+    fn set(mask: &mut u32, interface: u8) {
+        if let Some(bit) = 1_u32.checked_shl(interface.into()) {
+            *mask |= bit;
+        }
+    }
+
+    fn is_set(mask: u32, interface: InterfaceNumber) -> bool {
+        1_u32
+            .checked_shl(u8::from(interface).into())
+            .map(|bit| mask & bit != 0)
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::hid_class::events::{HidEvent, HidEvents};
+
+    #[test]
+    fn events_iterated_in_order() {
+        let mut events = HidEvents::default();
+        events.set_start_of_frame();
+        events.set_idle_changed(2);
+        events.set_output_ready(2);
+        events.set_protocol_changed(0);
+        events.set_reset();
+        // Not tracked
+        events.set_output_ready(32);
+
+        assert_eq!(
+            events.iter().collect::<std::vec::Vec<_>>(),
+            [
+                HidEvent::Reset,
+                HidEvent::ProtocolChanged { interface: 0 },
+                HidEvent::OutputReady { interface: 2 },
+                HidEvent::IdleChanged { interface: 2 },
+                HidEvent::StartOfFrame,
+            ]
+        );
+        assert_eq!(HidEvents::default().iter().next(), None);
+    }
+}
@@ -1,23 +1,44 @@
 //! Abstract Human Interface Device Class for implementing any HID compliant device
 
+#[cfg(feature = "host-os")]
+use crate::host_os::{HostOs, HostOsDetector};
+use crate::interface::dynamic::DynamicInterfaces;
+use crate::interface::packet::MAX_CONTROL_REPORT_SIZE;
+use crate::interface::tuple::InterfaceTuple;
+#[cfg(feature = "frunk")]
+use crate::interface::DefaultConfig;
 use crate::interface::InterfaceHList;
-use crate::interface::{InterfaceClass, UsbAllocatable};
+use crate::interface::{InterfaceClass, UsbAllocatable, MAX_HID_DESCRIPTOR_BODY_LEN};
+use budget::{EndpointBudget, EndpointRequirements, EndpointUsage};
 use core::default::Default;
 use core::marker::PhantomData;
 use descriptor::*;
+use descriptor_parser::DescriptorError;
+use events::HidEvents;
+#[cfg(feature = "frunk")]
 use frunk::hlist::{HList, Selector};
-use frunk::{HCons, HNil};
-use log::{error, info, trace, warn};
+#[cfg(feature = "frunk")]
+use frunk::{HCons, HNil, ToRef};
+use heapless::Vec;
 use packed_struct::prelude::*;
 use usb_device::class_prelude::*;
 use usb_device::control::Recipient;
 use usb_device::control::Request;
 use usb_device::control::RequestType;
+use usb_device::device::{UsbDevice, UsbDeviceState};
 use usb_device::Result;
 
+pub mod budget;
 pub mod descriptor;
+#[cfg(feature = "std")]
+pub mod descriptor_annotator;
+pub mod descriptor_builder;
+pub mod descriptor_parser;
+pub mod events;
 pub mod prelude;
-#[cfg(test)]
+#[cfg(feature = "critical-section")]
+pub mod shared;
+#[cfg(all(test, feature = "frunk"))]
 mod test;
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq, PrimitiveEnum)]
@@ -31,42 +52,180 @@ pub enum HidRequest {
     SetProtocol = 0x0B,
 }
 
+/// Maximum packet size of an interrupt endpoint
+///
+/// Sizes above 64 bytes are only valid on high-speed buses. Reports longer than
+/// [`MAX_CONTROL_REPORT_SIZE`] cannot be read with `GET_REPORT` or queued.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, PrimitiveEnum)]
-#[repr(u8)]
+#[repr(u16)]
 pub enum UsbPacketSize {
     Bytes8 = 8,
     Bytes16 = 16,
     Bytes32 = 32,
     Bytes64 = 64,
+    Bytes128 = 128,
+    Bytes256 = 256,
+    Bytes512 = 512,
+    Bytes1024 = 1024,
+}
+
+/// Speed of the USB bus, which determines how endpoint polling intervals are encoded
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum UsbSpeed {
+    /// Full-speed, 12 Mbit/s, `bInterval` in milliseconds
+    #[default]
+    Full,
+    /// High-speed, 480 Mbit/s, `bInterval` as a power of two of 125µs microframes
+    High,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum UsbHidBuilderError {
     ValueOverflow,
+    InvalidValue,
+    /// The configuration descriptor, `size` bytes, would not fit in the usb-device control buffer
+    DescriptorTooLarge {
+        size: usize,
+        limit: usize,
+    },
+    /// A report descriptor failed validation
+    InvalidReportDescriptor(DescriptorError),
+    /// Output reports are longer than [`MAX_CONTROL_REPORT_SIZE`] and can only be received on an
+    /// OUT endpoint
+    OutEndpointRequired,
+}
+
+/// Maximum number of Interface Association Descriptors written by a class
+pub const MAX_INTERFACE_ASSOCIATIONS: usize = 4;
+
+/// Consecutive interfaces grouped into one function by an Interface Association Descriptor
+///
+/// Interfaces are counted from 0 in the order they are added to the [`UsbHidClassBuilder`], or by
+/// their position in the tuple passed to [`UsbHidClassBuilder::with_interfaces`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InterfaceAssociation {
+    /// Position of the function's first interface
+    pub first: u8,
+    /// Number of interfaces in the function, at least 2
+    pub count: u8,
+    /// Protocol of the function, usually that of its boot interface
+    pub protocol: InterfaceProtocol,
+}
+
+impl InterfaceAssociation {
+    pub const fn new(first: u8, count: u8, protocol: InterfaceProtocol) -> Self {
+        Self {
+            first,
+            count,
+            protocol,
+        }
+    }
+
+    /// Position of the function's last interface
+    pub(crate) fn last(&self) -> usize {
+        usize::from(self.first) + usize::from(self.count) - 1
+    }
+
+    /// Writes the Interface Association Descriptor of the function starting at `first_interface`
+    #[cfg(not(feature = "usb-device-03"))]
+    pub(crate) fn write_descriptor(
+        &self,
+        writer: &mut DescriptorWriter,
+        first_interface: InterfaceNumber,
+    ) -> Result<()> {
+        writer.iad(
+            first_interface,
+            self.count,
+            USB_CLASS_HID,
+            InterfaceSubClass::from(self.protocol) as u8,
+            self.protocol as u8,
+        )
+    }
+
+    /// Writes the Interface Association Descriptor of the function starting at `first_interface`,
+    /// without a function string
+    #[cfg(feature = "usb-device-03")]
+    pub(crate) fn write_descriptor(
+        &self,
+        writer: &mut DescriptorWriter,
+        first_interface: InterfaceNumber,
+    ) -> Result<()> {
+        writer.iad(
+            first_interface,
+            self.count,
+            USB_CLASS_HID,
+            InterfaceSubClass::from(self.protocol) as u8,
+            self.protocol as u8,
+            None,
+        )
+    }
 }
 
+/// Size of the usb-device control buffer, without the `control-buffer-256` feature
+pub const DEFAULT_CONTROL_BUFFER_SIZE: usize = 128;
+const CONFIGURATION_DESCRIPTOR_LEN: usize = 9;
+const INTERFACE_ASSOCIATION_DESCRIPTOR_LEN: usize = 8;
+const FRAME_NUMBER_MASK: u16 = 0x7FF;
+
 #[must_use = "this `UsbHidClassBuilder` must be assigned or consumed by `::build()`"]
 #[derive(Debug, Clone, Copy, Eq, PartialEq)]
 pub struct UsbHidClassBuilder<'a, B, InterfaceList> {
     interface_list: InterfaceList,
+    control_buffer_size: usize,
+    interface_associations: &'a [InterfaceAssociation],
     _marker: PhantomData<&'a B>,
 }
 
+#[cfg(feature = "frunk")]
 impl<'a, B> UsbHidClassBuilder<'a, B, HNil> {
     pub fn new() -> Self {
         Self {
             interface_list: HNil,
+            control_buffer_size: DEFAULT_CONTROL_BUFFER_SIZE,
+            interface_associations: &[],
+            _marker: Default::default(),
+        }
+    }
+
+    /// Builder of a class with the interfaces configured by `interface_configs`, a tuple of up
+    /// to [`MAX_TUPLE_INTERFACES`](crate::interface::tuple::MAX_TUPLE_INTERFACES) configs, as an alternative to [`Self::add_interface`]
+    ///
+    /// See [`crate::interface::tuple`].
+    pub fn with_interfaces<T: InterfaceTuple>(
+        self,
+        interface_configs: T,
+    ) -> UsbHidClassBuilder<'a, B, T> {
+        UsbHidClassBuilder {
+            interface_list: interface_configs,
+            control_buffer_size: self.control_buffer_size,
+            interface_associations: self.interface_associations,
             _marker: Default::default(),
         }
     }
 }
 
+impl<'a, B, T: InterfaceTuple> UsbHidClassBuilder<'a, B, T> {
+    /// Builder of a class with the interfaces configured by `interface_configs`, the same as
+    /// `UsbHidClassBuilder::new().with_interfaces(interface_configs)`, available without the
+    /// `frunk` feature
+    pub fn from_tuple(interface_configs: T) -> Self {
+        Self {
+            interface_list: interface_configs,
+            control_buffer_size: DEFAULT_CONTROL_BUFFER_SIZE,
+            interface_associations: &[],
+            _marker: Default::default(),
+        }
+    }
+}
+
+#[cfg(feature = "frunk")]
 impl<'a, B> Default for UsbHidClassBuilder<'a, B, HNil> {
     fn default() -> Self {
         Self::new()
     }
 }
 
+#[cfg(feature = "frunk")]
 impl<'a, B: UsbBus, I: HList> UsbHidClassBuilder<'a, B, I> {
     pub fn add_interface<Conf, Class>(
         self,
@@ -78,37 +237,235 @@ impl<'a, B: UsbBus, I: HList> UsbHidClassBuilder<'a, B, I> {
     {
         UsbHidClassBuilder {
             interface_list: self.interface_list.prepend(interface_config),
+            control_buffer_size: self.control_buffer_size,
+            interface_associations: self.interface_associations,
             _marker: Default::default(),
         }
     }
+
+    /// Adds an interface of type `D` with its default configuration
+    ///
+    /// ```
+    /// # #[cfg(not(feature = "usb-device-03"))] {
+    /// # use usb_device::bus::{UsbBus, UsbBusAllocator};
+    /// use usbd_human_interface_device::prelude::*;
+    ///
+    /// # fn example<B: UsbBus>(usb_alloc: &UsbBusAllocator<B>) {
+    /// let hid = UsbHidClassBuilder::new()
+    ///     .add_device::<BootKeyboard<_>>()
+    ///     .add_device::<BootMouse<_>>()
+    ///     .build(usb_alloc)
+    ///     .unwrap();
+    /// # }
+    /// # }
+    /// ```
+    pub fn add_device<D>(self) -> UsbHidClassBuilder<'a, B, HCons<D::Config, I>>
+    where
+        D: DefaultConfig<'a, B>,
+    {
+        self.add_interface(D::default_config())
+    }
 }
 
+impl<'a, B, I: EndpointRequirements> UsbHidClassBuilder<'a, B, I> {
+    /// Endpoints and packet memory required by the interfaces added so far
+    pub fn endpoint_usage(&self) -> EndpointUsage {
+        self.interface_list.endpoint_usage()
+    }
+
+    /// Panics if the interfaces added so far require more than `budget`
+    pub fn check_endpoint_budget(self, budget: &EndpointBudget) -> Self {
+        self.endpoint_usage().assert_within(budget);
+        self
+    }
+
+    /// Size of the usb-device control buffer the configuration descriptor is written to
+    ///
+    /// Defaults to [`DEFAULT_CONTROL_BUFFER_SIZE`]. Set to 256 if usb-device's `control-buffer-256`
+    /// feature is enabled, or reduce by the size of descriptors written by other classes.
+    pub fn control_buffer_size(mut self, size: usize) -> Self {
+        self.control_buffer_size = size;
+        self
+    }
+
+    /// Group interfaces into functions with Interface Association Descriptors, so hosts bind one
+    /// driver to, for example, a keyboard and its vendor configuration interface
+    ///
+    /// Up to [`MAX_INTERFACE_ASSOCIATIONS`] groups, which must not overlap. The descriptors are
+    /// only written if the device is built with
+    /// [`UsbDeviceBuilder::composite_with_iads`](usb_device::device::UsbDeviceBuilder::composite_with_iads).
+    pub fn interface_associations(mut self, associations: &'a [InterfaceAssociation]) -> Self {
+        self.interface_associations = associations;
+        self
+    }
+
+    /// Size of the configuration descriptor, including the configuration header, if this is the
+    /// only class on the device
+    pub fn configuration_descriptor_size(&self) -> usize {
+        CONFIGURATION_DESCRIPTOR_LEN
+            + self.endpoint_usage().descriptor_size()
+            + self.interface_associations.len() * INTERFACE_ASSOCIATION_DESCRIPTOR_LEN
+    }
+}
+
+#[cfg(feature = "frunk")]
 impl<'a, B, C, Tail> UsbHidClassBuilder<'a, B, HCons<C, Tail>>
 where
     B: UsbBus,
-    Tail: UsbAllocatable<'a, B>,
-    C: UsbAllocatable<'a, B>,
+    Tail: UsbAllocatable<'a, B> + EndpointRequirements,
+    C: UsbAllocatable<'a, B> + EndpointRequirements,
+    Tail: HList,
 {
+    /// Returns [`UsbHidBuilderError::DescriptorTooLarge`] if the configuration descriptor would
+    /// not fit in the control buffer, which otherwise fails enumeration, and
+    /// [`UsbHidBuilderError::InvalidValue`] if the interface associations are invalid
+    #[allow(clippy::type_complexity)]
     pub fn build(
         self,
         usb_alloc: &'a UsbBusAllocator<B>,
-    ) -> UsbHidClass<B, HCons<C::Allocated, Tail::Allocated>> {
-        UsbHidClass {
+    ) -> BuilderResult<UsbHidClass<B, HCons<C::Allocated, Tail::Allocated>>> {
+        self.build_class(usb_alloc, HCons::<C, Tail>::LEN)
+    }
+}
+
+impl<'a, B, T> UsbHidClassBuilder<'a, B, T>
+where
+    B: UsbBus,
+    T: InterfaceTuple + UsbAllocatable<'a, B> + EndpointRequirements,
+{
+    /// Returns [`UsbHidBuilderError::DescriptorTooLarge`] if the configuration descriptor would
+    /// not fit in the control buffer, which otherwise fails enumeration, and
+    /// [`UsbHidBuilderError::InvalidValue`] if the interface associations are invalid
+    pub fn build(
+        self,
+        usb_alloc: &'a UsbBusAllocator<B>,
+    ) -> BuilderResult<UsbHidClass<B, T::Allocated>> {
+        self.build_class(usb_alloc, T::LEN)
+    }
+}
+
+impl<'a, B, I> UsbHidClassBuilder<'a, B, I>
+where
+    B: UsbBus,
+    I: UsbAllocatable<'a, B> + EndpointRequirements,
+{
+    /// Builds the class of the `interface_count` interfaces in the list
+    fn build_class(
+        self,
+        usb_alloc: &'a UsbBusAllocator<B>,
+        interface_count: usize,
+    ) -> BuilderResult<UsbHidClass<B, I::Allocated>> {
+        let interface_associations = self.checked_interface_associations(interface_count)?;
+        let size = self.configuration_descriptor_size();
+        if size > self.control_buffer_size {
+            error!(
+                "Configuration descriptor of {} bytes exceeds control buffer of {} bytes",
+                size, self.control_buffer_size
+            );
+            return Err(UsbHidBuilderError::DescriptorTooLarge {
+                size,
+                limit: self.control_buffer_size,
+            });
+        }
+        Ok(UsbHidClass {
             interfaces: self.interface_list.allocate(usb_alloc),
+            interface_associations,
+            events: Default::default(),
+            frame_number: None,
+            #[cfg(feature = "host-os")]
+            host_os: Default::default(),
             _marker: Default::default(),
+        })
+    }
+
+    fn checked_interface_associations(
+        &self,
+        interface_count: usize,
+    ) -> BuilderResult<Vec<InterfaceAssociation, MAX_INTERFACE_ASSOCIATIONS>> {
+        let associations = Vec::from_slice(self.interface_associations).map_err(|_| {
+            error!(
+                "More than {} interface associations",
+                MAX_INTERFACE_ASSOCIATIONS
+            );
+            UsbHidBuilderError::InvalidValue
+        })?;
+        for (i, a) in self.interface_associations.iter().enumerate() {
+            let invalid = a.count < 2
+                || a.last() >= interface_count
+                || self.interface_associations[..i]
+                    .iter()
+                    .any(|b| a.first as usize <= b.last() && b.first as usize <= a.last());
+            if invalid {
+                error!("Invalid interface association {:?}", a);
+                return Err(UsbHidBuilderError::InvalidValue);
+            }
         }
+        Ok(associations)
     }
 }
 
 pub type BuilderResult<B> = core::result::Result<B, UsbHidBuilderError>;
 
+/// The interface of type `$interface` of a [`UsbHidClass`], without naming the lifetime, bus
+/// and HList index type parameters
+///
+/// `hid_interface!(hid, BootKeyboard)` expands to `hid.interface::<BootKeyboard<'_, _>, _>()`,
+/// and `hid_interface!(mut hid, BootKeyboard)` to [`UsbHidClass::interface_mut`]. Interfaces with
+/// further type parameters are named in full, such as
+/// `hid_interface!(hid, VendorRaw<'_, _, 64, 64>)`.
+///
+/// ```
+/// # #[cfg(not(feature = "usb-device-03"))] {
+/// # use usb_device::bus::{UsbBus, UsbBusAllocator};
+/// use usbd_human_interface_device::device::keyboard::{BootKeyboard, BootKeyboardReport};
+/// use usbd_human_interface_device::device::mouse::BootMouse;
+/// use usbd_human_interface_device::hid_interface;
+/// use usbd_human_interface_device::page::Keyboard;
+/// use usbd_human_interface_device::prelude::*;
+///
+/// # fn example<B: UsbBus>(usb_alloc: &UsbBusAllocator<B>) {
+/// let hid = UsbHidClassBuilder::new()
+///     .add_interface(BootKeyboard::default_config())
+///     .add_interface(BootMouse::default_config())
+///     .build(usb_alloc)
+///     .unwrap();
+///
+/// hid_interface!(hid, BootKeyboard)
+///     .write_report(&BootKeyboardReport::new([Keyboard::A]))
+///     .ok();
+/// # }
+/// # }
+/// ```
+#[cfg(feature = "frunk")]
+#[macro_export]
+macro_rules! hid_interface {
+    (mut $class:expr, $($interface:ident)::+) => {
+        $class.interface_mut::<$($interface)::+<'_, _>, _>()
+    };
+    (mut $class:expr, $interface:ty) => {
+        $class.interface_mut::<$interface, _>()
+    };
+    ($class:expr, $($interface:ident)::+) => {
+        $class.interface::<$($interface)::+<'_, _>, _>()
+    };
+    ($class:expr, $interface:ty) => {
+        $class.interface::<$interface, _>()
+    };
+}
+
 /// USB Human Interface Device class
-#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+#[derive(Debug, Clone, Eq, PartialEq)]
 pub struct UsbHidClass<B, I> {
     interfaces: I,
+    interface_associations: Vec<InterfaceAssociation, MAX_INTERFACE_ASSOCIATIONS>,
+    events: HidEvents,
+    frame_number: Option<u16>,
+    #[cfg(feature = "host-os")]
+    host_os: core::cell::Cell<HostOsDetector>,
     _marker: PhantomData<B>,
 }
 
+#[cfg(feature = "frunk")]
 impl<'a, B, InterfaceList: InterfaceHList<'a>> UsbHidClass<B, InterfaceList> {
     pub fn interface<T, Index>(&self) -> &T
     where
@@ -117,17 +474,125 @@ impl<'a, B, InterfaceList: InterfaceHList<'a>> UsbHidClass<B, InterfaceList> {
         self.interfaces.get()
     }
 
-    pub fn interfaces(&'a self) -> InterfaceList::Output {
+    pub fn interface_mut<T, Index>(&mut self) -> &mut T
+    where
+        InterfaceList: Selector<T, Index>,
+    {
+        self.interfaces.get_mut()
+    }
+
+    pub fn interfaces(&'a self) -> InterfaceList::Output
+    where
+        InterfaceList: ToRef<'a>,
+    {
         self.interfaces.to_ref()
     }
 }
 
+impl<'a, B, InterfaceList: InterfaceHList<'a>> UsbHidClass<B, InterfaceList> {
+    /// Events that occurred since the last call, the events are cleared by this call
+    pub fn poll_events(&mut self) -> HidEvents {
+        core::mem::take(&mut self.events)
+    }
+
+    /// Record the start of a USB frame, `frame_number` is the 11 bit frame number from the SOF
+    /// packet
+    ///
+    /// usb-device does not report SOF packets, call this from the peripheral's SOF interrupt
+    /// to schedule reports on frame boundaries rather than with a free running timer. Each
+    /// interface is notified through [`InterfaceClass::start_of_frame`].
+    pub fn start_of_frame(&mut self, frame_number: u16) {
+        let frame_number = frame_number & FRAME_NUMBER_MASK;
+        self.frame_number = Some(frame_number);
+        self.events.set_start_of_frame();
+        self.interfaces.start_of_frame(frame_number);
+    }
+
+    /// Number of the current USB frame, `None` until [`Self::start_of_frame`] is called or after a
+    /// bus reset
+    pub fn frame_number(&self) -> Option<u16> {
+        self.frame_number
+    }
+
+    /// Whether to signal resume to the host now, because an interface configured with
+    /// [`wake_on_report`](crate::interface::raw::RawInterfaceBuilder::wake_on_report) has written a
+    /// report while `usb_dev` is suspended and the host has enabled remote wakeup
+    ///
+    /// usb-device cannot signal resume itself, when this returns `true` drive resume signalling
+    /// with the peripheral's HAL, typically for 1 to 15ms. The device must be built with
+    /// [`supports_remote_wakeup`](usb_device::device::UsbDeviceBuilder::supports_remote_wakeup)
+    /// for the host to enable remote wakeup. Wakeup requests are cleared by this call, requests
+    /// made while the device isn't suspended are discarded as the report is sent normally.
+    pub fn remote_wakeup(&self, usb_dev: &UsbDevice<'_, B>) -> bool
+    where
+        B: UsbBus,
+    {
+        let requested = self.interfaces.take_wakeup_request();
+        requested && usb_dev.state() == UsbDeviceState::Suspend && usb_dev.remote_wakeup_enabled()
+    }
+
+    /// Best guess of the host operating system, based on the requests made since the last reset
+    #[cfg(feature = "host-os")]
+    pub fn host_os(&self) -> HostOs {
+        self.host_os.get().host_os()
+    }
+}
+
+impl<'i, B, T: ?Sized> UsbHidClass<B, DynamicInterfaces<'i, T>> {
+    /// Class of `interfaces` chosen at runtime, see [`crate::interface::dynamic`]
+    ///
+    /// `interfaces` must be listed in the order they were allocated. Unlike
+    /// [`UsbHidClassBuilder::build`], the size of the configuration descriptor isn't checked and
+    /// interfaces can't be grouped with [`InterfaceAssociation`]s.
+    pub fn new_dynamic(interfaces: &'i mut [&'i mut T]) -> Self {
+        Self {
+            interfaces: DynamicInterfaces::new(interfaces),
+            interface_associations: Vec::new(),
+            events: Default::default(),
+            frame_number: None,
+            #[cfg(feature = "host-os")]
+            host_os: Default::default(),
+            _marker: Default::default(),
+        }
+    }
+
+    pub fn dynamic_interfaces(&self) -> &[&'i mut T] {
+        self.interfaces.get()
+    }
+
+    pub fn dynamic_interfaces_mut(&mut self) -> &mut [&'i mut T] {
+        self.interfaces.get_mut()
+    }
+}
+
+impl<B, T: InterfaceTuple> UsbHidClass<B, T> {
+    /// Interfaces of a class built from a tuple, see [`UsbHidClassBuilder::from_tuple`], in tuple order
+    pub fn interface_tuple(&self) -> &T {
+        &self.interfaces
+    }
+
+    pub fn interface_tuple_mut(&mut self) -> &mut T {
+        &mut self.interfaces
+    }
+}
+
 impl<B: UsbBus, I> UsbHidClass<B, I> {
+    #[cfg(feature = "host-os")]
+    fn update_host_os(&self, f: impl FnOnce(&mut HostOsDetector)) {
+        let mut detector = self.host_os.get();
+        f(&mut detector);
+        self.host_os.set(detector);
+    }
+
     fn get_descriptor(transfer: ControlIn<B>, interface: &dyn InterfaceClass<'_>) {
         let request: &Request = transfer.request();
         match DescriptorType::from_primitive((request.value >> 8) as u8) {
             Some(DescriptorType::Report) => {
-                match transfer.accept_with(interface.report_descriptor()) {
+                let result = match interface.static_report_descriptor() {
+                    Some(descriptor) => transfer.accept_with_static(descriptor),
+                    None => transfer.accept_with(interface.report_descriptor()),
+                };
+                match result {
                     Err(e) => error!("Failed to send report descriptor - {:?}", e),
                     Ok(_) => {
                         trace!("Sent report descriptor")
@@ -135,11 +600,13 @@ impl<B: UsbBus, I> UsbHidClass<B, I> {
                 }
             }
             Some(DescriptorType::Hid) => {
-                let mut buffer = [0; 9];
-                buffer[0] = buffer.len() as u8;
+                let body = interface.hid_descriptor_body();
+                let mut buffer = [0; 2 + MAX_HID_DESCRIPTOR_BODY_LEN];
+                let len = 2 + body.len();
+                buffer[0] = len as u8;
                 buffer[1] = DescriptorType::Hid as u8;
-                (buffer[2..]).copy_from_slice(&interface.hid_descriptor_body());
-                match transfer.accept_with(&buffer) {
+                buffer[2..len].copy_from_slice(&body);
+                match transfer.accept_with(&buffer[..len]) {
                     Err(e) => {
                         error!("Failed to send Hid descriptor - {:?}", e);
                     }
@@ -149,10 +616,19 @@ impl<B: UsbBus, I> UsbHidClass<B, I> {
                 }
             }
             _ => {
-                warn!(
-                    "Unsupported descriptor type, request type:{:X?}, request:{:X}, value:{:X}",
-                    request.request_type, request.request, request.value
-                );
+                let descriptor_type = (request.value >> 8) as u8;
+                match interface.class_descriptor(descriptor_type, request.value as u8) {
+                    Some(descriptor) => match transfer.accept_with(descriptor) {
+                        Err(e) => error!("Failed to send class descriptor - {:?}", e),
+                        Ok(_) => {
+                            trace!("Sent class descriptor {:X}", descriptor_type)
+                        }
+                    },
+                    None => warn!(
+                        "Unsupported descriptor type, request type:{:X?}, request:{:X}, value:{:X}",
+                        request.request_type, request.request, request.value
+                    ),
+                }
             }
         }
     }
@@ -164,18 +640,53 @@ where
     I: InterfaceHList<'a>,
 {
     fn get_configuration_descriptors(&self, writer: &mut DescriptorWriter) -> Result<()> {
-        self.interfaces.write_descriptors(writer)?;
+        self.interfaces
+            .write_descriptors(writer, &self.interface_associations)?;
         info!("wrote class config descriptor");
         Ok(())
     }
 
+    #[cfg(not(feature = "usb-device-03"))]
     fn get_string(&self, index: StringIndex, lang_id: u16) -> Option<&str> {
+        #[cfg(feature = "host-os")]
+        self.update_host_os(|d| d.string_requested(u8::from(index)));
         self.interfaces.get_string(index, lang_id)
     }
 
+    #[cfg(feature = "usb-device-03")]
+    fn get_string(&self, index: StringIndex, lang_id: LangID) -> Option<&str> {
+        #[cfg(feature = "host-os")]
+        self.update_host_os(|d| d.string_requested(u8::from(index)));
+        self.interfaces.get_string(index, u16::from(lang_id))
+    }
+
     fn reset(&mut self) {
         info!("Reset");
         self.interfaces.reset();
+        self.events.set_reset();
+        self.frame_number = None;
+        #[cfg(feature = "host-os")]
+        self.update_host_os(HostOsDetector::reset);
+    }
+
+    fn endpoint_in_complete(&mut self, addr: EndpointAddress) {
+        if let Some(id) = self.interfaces.get_in_endpoint_id(addr) {
+            self.events.set_input_complete(id);
+        }
+        self.interfaces.endpoint_in_complete(addr);
+    }
+
+    fn poll(&mut self) {
+        self.interfaces.poll();
+    }
+
+    fn endpoint_out(&mut self, addr: EndpointAddress) {
+        if let Some(id) = self.interfaces.get_out_endpoint_id(addr) {
+            self.events.set_output_ready(id);
+            if let Some(interface) = self.interfaces.get_id_mut(id) {
+                interface.endpoint_out();
+            }
+        }
     }
 
     fn control_out(&mut self, transfer: ControlOut<B>) {
@@ -207,8 +718,33 @@ where
 
         match HidRequest::from_primitive(request.request) {
             Some(HidRequest::SetReport) => {
-                interface.set_report(transfer.data()).ok();
-                transfer.accept().ok();
+                let report_id = (request.value & 0xFF) as u8;
+                match ReportType::from_primitive((request.value >> 8) as u8) {
+                    Some(report_type) => {
+                        let id = u8::from(interface.id());
+                        match interface.set_report_by_id(report_type, report_id, transfer.data()) {
+                            Ok(()) => {
+                                if report_type == ReportType::Feature {
+                                    self.events.set_feature_changed(id);
+                                } else {
+                                    self.events.set_output_ready(id);
+                                }
+                                transfer.accept().ok();
+                            }
+                            Err(e) => {
+                                warn!(
+                                    "SetReport rejected, type: {:?}, ID: {:X}, error: {:?}",
+                                    report_type, report_id, e
+                                );
+                                transfer.reject().ok();
+                            }
+                        }
+                    }
+                    None => {
+                        warn!("Unsupported report type, value:{:X}", request.value);
+                        transfer.reject().ok();
+                    }
+                }
             }
             Some(HidRequest::SetIdle) => {
                 if request.length != 0 {
@@ -218,7 +754,14 @@ where
                     );
                 }
 
-                interface.set_idle((request.value & 0xFF) as u8, (request.value >> 8) as u8);
+                let report_id = (request.value & 0xFF) as u8;
+                let previous = interface.get_idle(report_id);
+                interface.set_idle(report_id, (request.value >> 8) as u8);
+                if interface.get_idle(report_id) != previous {
+                    self.events.set_idle_changed(u8::from(interface.id()));
+                }
+                #[cfg(feature = "host-os")]
+                self.update_host_os(HostOsDetector::set_idle);
                 transfer.accept().ok();
             }
             Some(HidRequest::SetProtocol) => {
@@ -229,8 +772,14 @@ where
                     );
                 }
                 if let Some(protocol) = HidProtocol::from_primitive((request.value & 0xFF) as u8) {
-                    interface.set_protocol(protocol);
-                    transfer.accept().ok();
+                    let previous = interface.get_protocol();
+                    match interface.set_protocol(protocol) {
+                        Ok(()) => transfer.accept().ok(),
+                        Err(_) => transfer.reject().ok(),
+                    };
+                    if interface.get_protocol() != previous {
+                        self.events.set_protocol_changed(u8::from(interface.id()));
+                    }
                 } else {
                     error!(
                         "Unable to set protocol, unsupported value:{:X}",
@@ -279,6 +828,10 @@ where
 
                 if request.request == Request::GET_DESCRIPTOR {
                     info!("Get descriptor");
+                    #[cfg(feature = "host-os")]
+                    if (request.value >> 8) as u8 == DescriptorType::Report as u8 {
+                        self.update_host_os(HostOsDetector::report_descriptor_requested);
+                    }
                     Self::get_descriptor(transfer, interface);
                 }
             }
@@ -293,8 +846,16 @@ where
 
                 match HidRequest::from_primitive(request.request) {
                     Some(HidRequest::GetReport) => {
-                        let mut data = [0_u8; 64];
-                        if let Ok(n) = interface.get_report(&mut data) {
+                        let Some(report_type) =
+                            ReportType::from_primitive((request.value >> 8) as u8)
+                        else {
+                            warn!("Unsupported report type, value:{:X}", request.value);
+                            return;
+                        };
+                        let report_id = (request.value & 0xFF) as u8;
+                        let mut data = [0_u8; MAX_CONTROL_REPORT_SIZE];
+                        if let Ok(n) = interface.get_report_by_id(report_type, report_id, &mut data)
+                        {
                             if n != transfer.request().length as usize {
                                 warn!(
                                     "GetReport expected {:X} bytes, got {:X} bytes",
@@ -9,10 +9,17 @@
 //! ```
 
 pub use super::{
-    descriptor::HidProtocol, descriptor::InterfaceProtocol, UsbHidClass, UsbHidClassBuilder,
-    UsbPacketSize,
+    budget::EndpointBudget, descriptor::HidProtocol, descriptor::InterfaceProtocol,
+    events::HidEvent, events::HidEvents, InterfaceAssociation, UsbHidClass, UsbHidClassBuilder,
+    UsbPacketSize, UsbSpeed,
 };
+pub use crate::interface::duration::{IdleDuration, PollInterval};
+pub use crate::interface::latency::LatencyHistogram;
 pub use crate::interface::managed::ManagedInterface;
 pub use crate::interface::managed::ManagedInterfaceConfig;
+pub use crate::interface::managed::TransmitPolicy;
+pub use crate::interface::packet::PacketPolicy;
+pub use crate::interface::raw::ProtocolPolicy;
 pub use crate::interface::raw::RawInterface;
 pub use crate::interface::raw::RawInterfaceBuilder;
+pub use crate::interface::{ClassDescriptor, HidDescriptorConfig};
@@ -0,0 +1,173 @@
+//! A [`UsbHidClass`] and its [`UsbDevice`] shared between the USB interrupt and the main loop
+//!
+//! ```
+//! # #[cfg(not(feature = "usb-device-03"))] {
+//! use usb_device::bus::{UsbBus, UsbBusAllocator};
+//! use usb_device::prelude::*;
+//! use usbd_human_interface_device::device::keyboard::{BootKeyboard, BootKeyboardReport};
+//! use usbd_human_interface_device::hid_class::shared::SharedUsbHidClass;
+//! use usbd_human_interface_device::page::Keyboard;
+//! use usbd_human_interface_device::prelude::*;
+//!
+//! fn setup<B: UsbBus>(usb_alloc: &UsbBusAllocator<B>) {
+//!     // Usually a static, with the USB interrupt handler calling `HID.poll_from_isr()`
+//!     let hid = SharedUsbHidClass::new();
+//!     let class = UsbHidClassBuilder::new()
+//!         .add_device::<BootKeyboard<_>>()
+//!         .build(usb_alloc)
+//!         .unwrap();
+//!     let usb_dev = UsbDeviceBuilder::new(usb_alloc, UsbVidPid(0x1209, 0x0001)).build();
+//!     // Safety: nothing else was allocated from `usb_alloc`
+//!     unsafe { hid.init(usb_dev, class) };
+//!
+//!     hid.with(|class| {
+//!         class
+//!             .interface::<BootKeyboard<'_, _>, _>()
+//!             .write_report(&BootKeyboardReport::new([Keyboard::A]))
+//!             .ok();
+//!     });
+//! }
+//! # }
+//! ```
+//!
+//! Requires the `critical-section` feature and a `critical-section` implementation, usually
+//! provided by the HAL.
+
+use core::cell::RefCell;
+
+use critical_section::Mutex;
+use usb_device::bus::UsbBus;
+use usb_device::device::UsbDevice;
+
+use crate::hid_class::UsbHidClass;
+use crate::interface::InterfaceHList;
+
+struct Shared<'a, B: UsbBus, I> {
+    usb_dev: UsbDevice<'a, B>,
+    class: UsbHidClass<B, I>,
+}
+
+/// [`UsbHidClass`] and [`UsbDevice`] accessed within critical sections, so they can be polled
+/// from the USB interrupt while reports are written from thread context
+///
+/// Create in a `static` with [`SharedUsbHidClass::new`], then move the device and class in with
+/// [`SharedUsbHidClass::init`]. Until then [`SharedUsbHidClass::poll_from_isr`] does nothing and
+/// the accessors return `None`.
+pub struct SharedUsbHidClass<'a, B: UsbBus, I> {
+    shared: Mutex<RefCell<Option<Shared<'a, B, I>>>>,
+}
+
+// Safety: the device and class, and through their endpoints the bus allocator, are only accessed
+// within critical sections. `init` requires nothing else to use the allocator. The interfaces are
+// handed between the interrupt and thread context, so they must be `Send`.
+unsafe impl<B: UsbBus + Send, I: Send> Sync for SharedUsbHidClass<'_, B, I> {}
+
+impl<B: UsbBus, I> Default for SharedUsbHidClass<'_, B, I> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'a, B: UsbBus, I> SharedUsbHidClass<'a, B, I> {
+    pub const fn new() -> Self {
+        Self {
+            shared: Mutex::new(RefCell::new(None)),
+        }
+    }
+
+    /// Moves `usb_dev` and `class` into the shared state, replacing any previously moved in
+    ///
+    /// # Safety
+    ///
+    /// No endpoint or class allocated from `usb_dev`'s bus allocator may be used other than
+    /// through this `SharedUsbHidClass`, the allocator is not safe to access from both the
+    /// interrupt and thread context.
+    pub unsafe fn init(&self, usb_dev: UsbDevice<'a, B>, class: UsbHidClass<B, I>) {
+        critical_section::with(|cs| {
+            self.shared
+                .borrow(cs)
+                .replace(Some(Shared { usb_dev, class }));
+        });
+    }
+
+    /// Calls `f` with the class within a critical section, `None` before [`Self::init`]
+    pub fn with<R>(&self, f: impl FnOnce(&mut UsbHidClass<B, I>) -> R) -> Option<R> {
+        self.with_device(|_, class| f(class))
+    }
+
+    /// Calls `f` with the device and class within a critical section, `None` before
+    /// [`Self::init`]
+    pub fn with_device<R>(
+        &self,
+        f: impl FnOnce(&mut UsbDevice<'a, B>, &mut UsbHidClass<B, I>) -> R,
+    ) -> Option<R> {
+        critical_section::with(|cs| {
+            let mut shared = self.shared.borrow(cs).borrow_mut();
+            shared
+                .as_mut()
+                .map(|shared| f(&mut shared.usb_dev, &mut shared.class))
+        })
+    }
+}
+
+impl<'a, B: UsbBus, I: InterfaceHList<'a>> SharedUsbHidClass<'a, B, I> {
+    /// Polls the device, call from the USB interrupt handler
+    ///
+    /// Returns `true` if the class may have new data, as [`UsbDevice::poll`], and `false` before
+    /// [`Self::init`].
+    pub fn poll_from_isr(&self) -> bool {
+        self.with_device(|usb_dev, class| usb_dev.poll(&mut [class]))
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    #[cfg(feature = "simulation")]
+    #[test]
+    fn reports_written_while_polled_elsewhere() {
+        use usb_device::bus::UsbBusAllocator;
+
+        use crate::device::mouse::{BootMouse, BootMouseReport};
+        use crate::hid_class::shared::SharedUsbHidClass;
+        use crate::prelude::*;
+        use crate::simulation::{device_builder, SimBus, SimHost};
+
+        fn assert_sync<T: Sync>(_: &T) {}
+
+        let usb_alloc = UsbBusAllocator::new(SimBus::new());
+        let hid = SharedUsbHidClass::new();
+        assert_sync(&hid);
+        assert!(!hid.poll_from_isr());
+        assert_eq!(hid.with(|_| ()), None);
+
+        let class = UsbHidClassBuilder::new()
+            .add_device::<BootMouse<_>>()
+            .build(&usb_alloc)
+            .unwrap();
+        let usb_dev = device_builder(&usb_alloc, 8).build();
+        unsafe { hid.init(usb_dev, class) };
+
+        let host = hid
+            .with_device(|usb_dev, class| SimHost::enumerate(usb_dev, &mut [class]))
+            .unwrap()
+            .unwrap();
+        hid.poll_from_isr();
+
+        let report = BootMouseReport {
+            x: 3,
+            ..Default::default()
+        };
+        hid.with(|class| {
+            class
+                .interface::<BootMouse<'_, _>, _>()
+                .write_report(&report)
+                .unwrap()
+        })
+        .unwrap();
+        assert_eq!(
+            hid.with_device(|usb_dev, class| host.read_report(usb_dev, &mut [class], 0)),
+            Some(Some(vec![0x00, 0x03, 0x00]))
+        );
+    }
+}
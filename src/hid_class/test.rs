@@ -2,7 +2,16 @@ use std::cell::RefCell;
 use std::sync::Mutex;
 use std::vec::Vec;
 
-use crate::interface::raw::RawInterfaceBuilder;
+use crate::device::keyboard::{BootKeyboardInterface, NKROBootKeyboardInterface};
+use crate::device::lamp_array::{
+    LampArrayAttributes, LampArrayInterface, LampAttributes, LAMP_ARRAY_ATTRIBUTES_REPORT_ID,
+};
+use crate::device::mouse::BootMouseInterface;
+use crate::hid_class::budget::EndpointUsage;
+use crate::hid_class::events::HidEvent;
+use crate::interface::duration::{IdleDuration, PollInterval};
+use crate::interface::raw::{ProtocolPolicy, RawInterface, RawInterfaceBuilder};
+use crate::interface::{ClassDescriptor, HidDescriptorConfig};
 use env_logger::Env;
 use fugit::MillisDurationU32;
 use usb_device::bus::PollResult;
@@ -26,6 +35,8 @@ struct TestUsbBus<'a, F> {
 struct TestUsbBusInner {
     next_read_data: usize,
     write_data: Vec<u8>,
+    stalled: Vec<EndpointAddress>,
+    stall_count: usize,
 }
 
 impl<'a, F> TestUsbBus<'a, F> {
@@ -37,9 +48,16 @@ impl<'a, F> TestUsbBus<'a, F> {
             inner: Mutex::new(RefCell::new(TestUsbBusInner {
                 write_data: Vec::new(),
                 next_read_data: 0,
+                stalled: Vec::new(),
+                stall_count: 0,
             })),
         }
     }
+
+    /// Number of times an endpoint has been stalled
+    fn stall_count(&self) -> usize {
+        self.inner.lock().unwrap().borrow().stall_count
+    }
 }
 
 impl<F> UsbBus for TestUsbBus<'_, F>
@@ -91,9 +109,19 @@ where
         inner.next_read_data += 1;
         Ok(read_data.len())
     }
-    fn set_stalled(&self, _ep_addr: EndpointAddress, _stalled: bool) {}
-    fn is_stalled(&self, _ep_addr: EndpointAddress) -> bool {
-        todo!()
+    fn set_stalled(&self, ep_addr: EndpointAddress, stalled: bool) {
+        let inner_ref = self.inner.lock().unwrap();
+        let mut inner = inner_ref.borrow_mut();
+        inner.stalled.retain(|&ep| ep != ep_addr);
+        if stalled {
+            inner.stalled.push(ep_addr);
+            inner.stall_count += 1;
+        }
+    }
+    fn is_stalled(&self, ep_addr: EndpointAddress) -> bool {
+        let inner_ref = self.inner.lock().unwrap();
+        let inner = inner_ref.borrow();
+        inner.stalled.contains(&ep_addr)
     }
     fn suspend(&self) {
         todo!()
@@ -125,6 +153,46 @@ where
     }
 }
 
+/// Builder of a device with an 8 byte endpoint 0
+#[cfg(not(feature = "usb-device-03"))]
+fn device_builder<B: UsbBus>(usb_alloc: &UsbBusAllocator<B>) -> UsbDeviceBuilder<'_, B> {
+    UsbDeviceBuilder::new(usb_alloc, UsbVidPid(0x1209, 0x0001)).max_packet_size_0(8)
+}
+
+/// Builder of a device with an 8 byte endpoint 0
+#[cfg(feature = "usb-device-03")]
+fn device_builder<B: UsbBus>(usb_alloc: &UsbBusAllocator<B>) -> UsbDeviceBuilder<'_, B> {
+    UsbDeviceBuilder::new(usb_alloc, UsbVidPid(0x1209, 0x0001))
+        .max_packet_size_0(8)
+        .unwrap()
+}
+
+/// Composite device with string descriptors, as enumerated by the descriptor tests
+#[cfg(not(feature = "usb-device-03"))]
+fn composite_device<B: UsbBus>(usb_alloc: &UsbBusAllocator<B>) -> UsbDevice<'_, B> {
+    device_builder(usb_alloc)
+        .manufacturer("usbd-human-interface-device")
+        .product("Test Hid Device")
+        .serial_number("TEST")
+        .device_class(USB_CLASS_HID)
+        .composite_with_iads()
+        .build()
+}
+
+/// Composite device with string descriptors, as enumerated by the descriptor tests
+#[cfg(feature = "usb-device-03")]
+fn composite_device<B: UsbBus>(usb_alloc: &UsbBusAllocator<B>) -> UsbDevice<'_, B> {
+    device_builder(usb_alloc)
+        .strings(&[StringDescriptors::default()
+            .manufacturer("usbd-human-interface-device")
+            .product("Test Hid Device")
+            .serial_number("TEST")])
+        .unwrap()
+        .device_class(USB_CLASS_HID)
+        .composite_with_iads()
+        .build()
+}
+
 #[derive(Clone, Copy, Debug, PartialEq, Eq, PackedStruct)]
 #[packed_struct(endian = "lsb", bit_numbering = "msb0", size_bytes = "8")]
 struct UsbRequest {
@@ -204,17 +272,16 @@ fn descriptor_ordering_satisfies_boot_spec() {
     let usb_alloc = UsbBusAllocator::new(usb_bus);
 
     let mut hid = UsbHidClassBuilder::new()
-        .add_interface(RawInterfaceBuilder::new(&[]).build())
-        .build(&usb_alloc);
+        .add_interface(
+            RawInterfaceBuilder::new(&[])
+                .in_endpoint(UsbPacketSize::Bytes8, PollInterval::millis(20))
+                .build()
+                .unwrap(),
+        )
+        .build(&usb_alloc)
+        .unwrap();
 
-    let mut usb_dev = UsbDeviceBuilder::new(&usb_alloc, UsbVidPid(0x1209, 0x0001))
-        .manufacturer("usbd-human-interface-device")
-        .product("Test Hid Device")
-        .serial_number("TEST")
-        .device_class(USB_CLASS_HID)
-        .composite_with_iads()
-        .max_packet_size_0(8)
-        .build();
+    let mut usb_dev = composite_device(&usb_alloc);
 
     //poll the usb bus
     for _ in 0..10 {
@@ -252,17 +319,16 @@ fn get_protocol_default_to_report() {
     let usb_alloc = UsbBusAllocator::new(usb_bus);
 
     let mut hid = UsbHidClassBuilder::new()
-        .add_interface(RawInterfaceBuilder::new(&[]).build())
-        .build(&usb_alloc);
+        .add_interface(
+            RawInterfaceBuilder::new(&[])
+                .in_endpoint(UsbPacketSize::Bytes8, PollInterval::millis(20))
+                .build()
+                .unwrap(),
+        )
+        .build(&usb_alloc)
+        .unwrap();
 
-    let mut usb_dev = UsbDeviceBuilder::new(&usb_alloc, UsbVidPid(0x1209, 0x0001))
-        .manufacturer("usbd-human-interface-device")
-        .product("Test Hid Device")
-        .serial_number("TEST")
-        .device_class(USB_CLASS_HID)
-        .composite_with_iads()
-        .max_packet_size_0(8)
-        .build();
+    let mut usb_dev = composite_device(&usb_alloc);
 
     //poll the usb bus
     for _ in 0..10 {
@@ -314,22 +380,26 @@ fn set_protocol() {
     let usb_alloc = UsbBusAllocator::new(usb_bus);
 
     let mut hid = UsbHidClassBuilder::new()
-        .add_interface(RawInterfaceBuilder::new(&[]).build())
-        .build(&usb_alloc);
+        .add_interface(
+            RawInterfaceBuilder::new(&[])
+                .in_endpoint(UsbPacketSize::Bytes8, PollInterval::millis(20))
+                .build()
+                .unwrap(),
+        )
+        .build(&usb_alloc)
+        .unwrap();
 
-    let mut usb_dev = UsbDeviceBuilder::new(&usb_alloc, UsbVidPid(0x1209, 0x0001))
-        .manufacturer("usbd-human-interface-device")
-        .product("Test Hid Device")
-        .serial_number("TEST")
-        .device_class(USB_CLASS_HID)
-        .composite_with_iads()
-        .max_packet_size_0(8)
-        .build();
+    let mut usb_dev = composite_device(&usb_alloc);
 
     //poll the usb bus
     for _ in 0..10 {
         assert!(usb_dev.poll(&mut [&mut hid]));
     }
+
+    let events = hid.poll_events();
+    assert!(events.any_protocol_changed());
+    assert!(!events.reset());
+    assert!(hid.poll_events().is_empty());
 }
 
 #[test]
@@ -376,17 +446,16 @@ fn get_protocol_default_post_reset() {
     let usb_alloc = UsbBusAllocator::new(usb_bus);
 
     let mut hid = UsbHidClassBuilder::new()
-        .add_interface(RawInterfaceBuilder::new(&[]).build())
-        .build(&usb_alloc);
+        .add_interface(
+            RawInterfaceBuilder::new(&[])
+                .in_endpoint(UsbPacketSize::Bytes8, PollInterval::millis(20))
+                .build()
+                .unwrap(),
+        )
+        .build(&usb_alloc)
+        .unwrap();
 
-    let mut usb_dev = UsbDeviceBuilder::new(&usb_alloc, UsbVidPid(0x1209, 0x0001))
-        .manufacturer("usbd-human-interface-device")
-        .product("Test Hid Device")
-        .serial_number("TEST")
-        .device_class(USB_CLASS_HID)
-        .composite_with_iads()
-        .max_packet_size_0(8)
-        .build();
+    let mut usb_dev = composite_device(&usb_alloc);
 
     //poll the usb bus
     assert!(usb_dev.poll(&mut [&mut hid]));
@@ -397,6 +466,8 @@ fn get_protocol_default_post_reset() {
     for _ in 0..10 {
         assert!(usb_dev.poll(&mut [&mut hid]));
     }
+
+    assert!(hid.poll_events().reset());
 }
 
 #[test]
@@ -432,20 +503,15 @@ fn get_global_idle_default() {
     let mut hid = UsbHidClassBuilder::new()
         .add_interface(
             RawInterfaceBuilder::new(&[])
-                .idle_default(IDLE_DEFAULT)
-                .unwrap()
-                .build(),
+                .in_endpoint(UsbPacketSize::Bytes8, PollInterval::millis(20))
+                .idle_default(IdleDuration::try_from(IDLE_DEFAULT).unwrap())
+                .build()
+                .unwrap(),
         )
-        .build(&usb_alloc);
+        .build(&usb_alloc)
+        .unwrap();
 
-    let mut usb_dev = UsbDeviceBuilder::new(&usb_alloc, UsbVidPid(0x1209, 0x0001))
-        .manufacturer("usbd-human-interface-device")
-        .product("Test Hid Device")
-        .serial_number("TEST")
-        .device_class(USB_CLASS_HID)
-        .composite_with_iads()
-        .max_packet_size_0(8)
-        .build();
+    let mut usb_dev = composite_device(&usb_alloc);
 
     //poll the usb bus
     for _ in 0..10 {
@@ -501,25 +567,25 @@ fn set_global_idle() {
     let mut hid = UsbHidClassBuilder::new()
         .add_interface(
             RawInterfaceBuilder::new(&[])
-                .idle_default(IDLE_DEFAULT)
-                .unwrap()
-                .build(),
+                .in_endpoint(UsbPacketSize::Bytes8, PollInterval::millis(20))
+                .idle_default(IdleDuration::try_from(IDLE_DEFAULT).unwrap())
+                .build()
+                .unwrap(),
         )
-        .build(&usb_alloc);
+        .build(&usb_alloc)
+        .unwrap();
 
-    let mut usb_dev = UsbDeviceBuilder::new(&usb_alloc, UsbVidPid(0x1209, 0x0001))
-        .manufacturer("usbd-human-interface-device")
-        .product("Test Hid Device")
-        .serial_number("TEST")
-        .device_class(USB_CLASS_HID)
-        .composite_with_iads()
-        .max_packet_size_0(8)
-        .build();
+    let mut usb_dev = composite_device(&usb_alloc);
 
     //poll the usb bus
     for _ in 0..10 {
         assert!(usb_dev.poll(&mut [&mut hid]));
     }
+
+    assert_eq!(
+        hid.poll_events().iter().collect::<Vec<_>>(),
+        [HidEvent::IdleChanged { interface: 0 }]
+    );
 }
 
 #[test]
@@ -571,20 +637,15 @@ fn get_global_idle_default_post_reset() {
     let mut hid = UsbHidClassBuilder::new()
         .add_interface(
             RawInterfaceBuilder::new(&[])
-                .idle_default(IDLE_DEFAULT)
-                .unwrap()
-                .build(),
+                .in_endpoint(UsbPacketSize::Bytes8, PollInterval::millis(20))
+                .idle_default(IdleDuration::try_from(IDLE_DEFAULT).unwrap())
+                .build()
+                .unwrap(),
         )
-        .build(&usb_alloc);
+        .build(&usb_alloc)
+        .unwrap();
 
-    let mut usb_dev = UsbDeviceBuilder::new(&usb_alloc, UsbVidPid(0x1209, 0x0001))
-        .manufacturer("usbd-human-interface-device")
-        .product("Test Hid Device")
-        .serial_number("TEST")
-        .device_class(USB_CLASS_HID)
-        .composite_with_iads()
-        .max_packet_size_0(8)
-        .build();
+    let mut usb_dev = composite_device(&usb_alloc);
 
     //poll the usb bus
     assert!(usb_dev.poll(&mut [&mut hid]));
@@ -632,20 +693,15 @@ fn get_report_idle_default() {
     let mut hid = UsbHidClassBuilder::new()
         .add_interface(
             RawInterfaceBuilder::new(&[])
-                .idle_default(IDLE_DEFAULT)
-                .unwrap()
-                .build(),
+                .in_endpoint(UsbPacketSize::Bytes8, PollInterval::millis(20))
+                .idle_default(IdleDuration::try_from(IDLE_DEFAULT).unwrap())
+                .build()
+                .unwrap(),
         )
-        .build(&usb_alloc);
+        .build(&usb_alloc)
+        .unwrap();
 
-    let mut usb_dev = UsbDeviceBuilder::new(&usb_alloc, UsbVidPid(0x1209, 0x0001))
-        .manufacturer("usbd-human-interface-device")
-        .product("Test Hid Device")
-        .serial_number("TEST")
-        .device_class(USB_CLASS_HID)
-        .composite_with_iads()
-        .max_packet_size_0(8)
-        .build();
+    let mut usb_dev = composite_device(&usb_alloc);
 
     //poll the usb bus
     for _ in 0..10 {
@@ -719,20 +775,15 @@ fn set_report_idle() {
     let mut hid = UsbHidClassBuilder::new()
         .add_interface(
             RawInterfaceBuilder::new(&[])
-                .idle_default(IDLE_DEFAULT)
-                .unwrap()
-                .build(),
+                .in_endpoint(UsbPacketSize::Bytes8, PollInterval::millis(20))
+                .idle_default(IdleDuration::try_from(IDLE_DEFAULT).unwrap())
+                .build()
+                .unwrap(),
         )
-        .build(&usb_alloc);
+        .build(&usb_alloc)
+        .unwrap();
 
-    let mut usb_dev = UsbDeviceBuilder::new(&usb_alloc, UsbVidPid(0x1209, 0x0001))
-        .manufacturer("usbd-human-interface-device")
-        .product("Test Hid Device")
-        .serial_number("TEST")
-        .device_class(USB_CLASS_HID)
-        .composite_with_iads()
-        .max_packet_size_0(8)
-        .build();
+    let mut usb_dev = composite_device(&usb_alloc);
 
     //poll the usb bus
     for _ in 0..10 {
@@ -791,20 +842,15 @@ fn get_report_idle_default_post_reset() {
     let mut hid = UsbHidClassBuilder::new()
         .add_interface(
             RawInterfaceBuilder::new(&[])
-                .idle_default(IDLE_DEFAULT)
-                .unwrap()
-                .build(),
+                .in_endpoint(UsbPacketSize::Bytes8, PollInterval::millis(20))
+                .idle_default(IdleDuration::try_from(IDLE_DEFAULT).unwrap())
+                .build()
+                .unwrap(),
         )
-        .build(&usb_alloc);
+        .build(&usb_alloc)
+        .unwrap();
 
-    let mut usb_dev = UsbDeviceBuilder::new(&usb_alloc, UsbVidPid(0x1209, 0x0001))
-        .manufacturer("usbd-human-interface-device")
-        .product("Test Hid Device")
-        .serial_number("TEST")
-        .device_class(USB_CLASS_HID)
-        .composite_with_iads()
-        .max_packet_size_0(8)
-        .build();
+    let mut usb_dev = composite_device(&usb_alloc);
 
     //poll the usb bus
     assert!(usb_dev.poll(&mut [&mut hid]));
@@ -816,3 +862,724 @@ fn get_report_idle_default_post_reset() {
         assert!(usb_dev.poll(&mut [&mut hid]));
     }
 }
+
+#[test]
+fn set_protocol_ignored() {
+    init_logging();
+
+    let read_data: &[&[u8]] = &[
+        //Set protocol to boot
+        &UsbRequest {
+            direction: UsbDirection::In != UsbDirection::In,
+            request_type: RequestType::Class as u8,
+            recipient: Recipient::Interface as u8,
+            request: HidRequest::SetProtocol as u8,
+            value: HidProtocol::Boot as u16,
+            index: 0x0,
+            length: 0x0,
+        }
+        .pack()
+        .unwrap(),
+        //Get protocol
+        &UsbRequest {
+            direction: UsbDirection::In != UsbDirection::Out,
+            request_type: RequestType::Class as u8,
+            recipient: Recipient::Interface as u8,
+            request: HidRequest::GetProtocol as u8,
+            value: 0x0,
+            index: 0x0,
+            length: 0x1,
+        }
+        .pack()
+        .unwrap(),
+    ];
+
+    let validate_write_data = |v: &Vec<u8>| {
+        assert_eq!(
+            v[0],
+            HidProtocol::Report as u8,
+            "Expected protocol to remain Report"
+        );
+    };
+
+    let usb_bus = TestUsbBus::new(read_data, validate_write_data);
+
+    let usb_alloc = UsbBusAllocator::new(usb_bus);
+
+    let mut hid = UsbHidClassBuilder::new()
+        .add_interface(
+            RawInterfaceBuilder::new(&[])
+                .in_endpoint(UsbPacketSize::Bytes8, PollInterval::millis(20))
+                .protocol_policy(ProtocolPolicy::Ignore)
+                .build()
+                .unwrap(),
+        )
+        .build(&usb_alloc)
+        .unwrap();
+
+    let mut usb_dev = composite_device(&usb_alloc);
+
+    //SetProtocol
+    assert!(usb_dev.poll(&mut [&mut hid]));
+    assert_eq!(
+        usb_dev.bus().stall_count(),
+        0,
+        "Expected SetProtocol to be accepted"
+    );
+
+    //poll the usb bus
+    for _ in 0..9 {
+        assert!(usb_dev.poll(&mut [&mut hid]));
+    }
+
+    assert!(!hid.poll_events().any_protocol_changed());
+}
+
+#[test]
+fn set_protocol_stalled() {
+    init_logging();
+
+    let read_data: &[&[u8]] = &[
+        //Set protocol to boot
+        &UsbRequest {
+            direction: UsbDirection::In != UsbDirection::In,
+            request_type: RequestType::Class as u8,
+            recipient: Recipient::Interface as u8,
+            request: HidRequest::SetProtocol as u8,
+            value: HidProtocol::Boot as u16,
+            index: 0x0,
+            length: 0x0,
+        }
+        .pack()
+        .unwrap(),
+        //Get protocol
+        &UsbRequest {
+            direction: UsbDirection::In != UsbDirection::Out,
+            request_type: RequestType::Class as u8,
+            recipient: Recipient::Interface as u8,
+            request: HidRequest::GetProtocol as u8,
+            value: 0x0,
+            index: 0x0,
+            length: 0x1,
+        }
+        .pack()
+        .unwrap(),
+    ];
+
+    let validate_write_data = |v: &Vec<u8>| {
+        assert_eq!(
+            v[0],
+            HidProtocol::Report as u8,
+            "Expected protocol to remain Report"
+        );
+    };
+
+    let usb_bus = TestUsbBus::new(read_data, validate_write_data);
+
+    let usb_alloc = UsbBusAllocator::new(usb_bus);
+
+    let mut hid = UsbHidClassBuilder::new()
+        .add_interface(
+            RawInterfaceBuilder::new(&[])
+                .in_endpoint(UsbPacketSize::Bytes8, PollInterval::millis(20))
+                .protocol_policy(ProtocolPolicy::Stall)
+                .build()
+                .unwrap(),
+        )
+        .build(&usb_alloc)
+        .unwrap();
+
+    let mut usb_dev = composite_device(&usb_alloc);
+
+    //SetProtocol stalls both directions of the control endpoint
+    assert!(usb_dev.poll(&mut [&mut hid]));
+    assert_eq!(
+        usb_dev.bus().stall_count(),
+        2,
+        "Expected SetProtocol to be stalled"
+    );
+
+    //poll the usb bus
+    for _ in 0..9 {
+        assert!(usb_dev.poll(&mut [&mut hid]));
+    }
+
+    assert!(!hid.poll_events().any_protocol_changed());
+}
+
+#[test]
+fn endpoint_usage_of_default_configs() {
+    type Bus = TestUsbBus<'static, fn(&Vec<u8>)>;
+
+    let builder = UsbHidClassBuilder::<Bus, _>::new()
+        .add_interface(BootKeyboardInterface::default_config())
+        .add_interface(NKROBootKeyboardInterface::default_config())
+        .add_interface(BootMouseInterface::default_config());
+
+    let usage = builder.endpoint_usage();
+    assert_eq!(
+        usage,
+        EndpointUsage {
+            interfaces: 3,
+            class_descriptors: 0,
+            in_endpoints: 3,
+            out_endpoints: 2,
+            packet_memory: 8 + 8 + 32 + 8 + 8,
+        }
+    );
+    assert_eq!(usage.descriptor_size(), 3 * 18 + 5 * 7);
+    assert!(usage.fits(&EndpointBudget::new(3, 2, 64)));
+    assert!(!usage.fits(&EndpointBudget::new(2, 2, 64)));
+    assert!(!usage.fits(&EndpointBudget::new(3, 2, 63)));
+
+    let _ = builder.check_endpoint_budget(&EndpointBudget::new(3, 2, 64));
+}
+
+#[test]
+fn boot_report_descriptor_served_in_boot_protocol() {
+    init_logging();
+
+    let read_data: &[&[u8]] = &[
+        //Set protocol to boot
+        &UsbRequest {
+            direction: UsbDirection::In != UsbDirection::In,
+            request_type: RequestType::Class as u8,
+            recipient: Recipient::Interface as u8,
+            request: HidRequest::SetProtocol as u8,
+            value: HidProtocol::Boot as u16,
+            index: 0x0,
+            length: 0x0,
+        }
+        .pack()
+        .unwrap(),
+        //Get report descriptor
+        &UsbRequest {
+            direction: UsbDirection::In != UsbDirection::Out,
+            request_type: RequestType::Standard as u8,
+            recipient: Recipient::Interface as u8,
+            request: Request::GET_DESCRIPTOR,
+            value: (DescriptorType::Report as u16) << 8,
+            index: 0x0,
+            length: 0x40,
+        }
+        .pack()
+        .unwrap(),
+    ];
+
+    let validate_write_data = |v: &Vec<u8>| {
+        assert_eq!(v, &[0x05, 0x01], "Expected boot report descriptor");
+    };
+
+    let usb_bus = TestUsbBus::new(read_data, validate_write_data);
+
+    let usb_alloc = UsbBusAllocator::new(usb_bus);
+
+    let mut hid = UsbHidClassBuilder::new()
+        .add_interface(
+            RawInterfaceBuilder::new(&[0x05, 0x01, 0x09, 0x02])
+                .in_endpoint(UsbPacketSize::Bytes8, PollInterval::millis(20))
+                .boot_device(InterfaceProtocol::Mouse)
+                .boot_report_descriptor(&[0x05, 0x01])
+                .build()
+                .unwrap(),
+        )
+        .build(&usb_alloc)
+        .unwrap();
+
+    let mut usb_dev = composite_device(&usb_alloc);
+
+    //poll the usb bus
+    for _ in 0..10 {
+        assert!(usb_dev.poll(&mut [&mut hid]));
+    }
+
+    let interface = hid.interface::<RawInterface<'_, _>, _>();
+    assert_eq!(interface.report_layout(), HidProtocol::Boot);
+}
+
+#[test]
+fn hid_descriptor_fields_configurable() {
+    init_logging();
+
+    let read_data: &[&[u8]] = &[&UsbRequest {
+        direction: UsbDirection::In != UsbDirection::Out,
+        request_type: RequestType::Standard as u8,
+        recipient: Recipient::Interface as u8,
+        request: Request::GET_DESCRIPTOR,
+        value: (DescriptorType::Hid as u16) << 8,
+        index: 0x0,
+        length: 0x9,
+    }
+    .pack()
+    .unwrap()];
+
+    let validate_write_data = |v: &Vec<u8>| {
+        assert_eq!(
+            v,
+            &[0x09, 0x21, 0x00, 0x01, 0x21, 0x01, 0x22, 0x02, 0x00],
+            "Expected HID 1.00 descriptor for a US keyboard"
+        );
+    };
+
+    let usb_bus = TestUsbBus::new(read_data, validate_write_data);
+    let usb_alloc = UsbBusAllocator::new(usb_bus);
+
+    let mut hid = UsbHidClassBuilder::new()
+        .add_interface(
+            RawInterfaceBuilder::new(&[0x05, 0x01])
+                .in_endpoint(UsbPacketSize::Bytes8, PollInterval::millis(20))
+                .hid_descriptor(HidDescriptorConfig {
+                    bcd_hid: 0x0100,
+                    country_code: 33,
+                })
+                .build()
+                .unwrap(),
+        )
+        .build(&usb_alloc)
+        .unwrap();
+
+    let mut usb_dev = device_builder(&usb_alloc).build();
+
+    for _ in 0..10 {
+        assert!(usb_dev.poll(&mut [&mut hid]));
+    }
+}
+
+#[test]
+fn lamp_array_feature_reports() {
+    init_logging();
+
+    static LAMPS: [LampAttributes; 2] = [
+        LampAttributes::new(10_000, 5_000, 0),
+        LampAttributes::new(20_000, 5_000, 0),
+    ];
+
+    let read_data: &[&[u8]] = &[
+        //Get LampArray attributes
+        &UsbRequest {
+            direction: UsbDirection::In != UsbDirection::Out,
+            request_type: RequestType::Class as u8,
+            recipient: Recipient::Interface as u8,
+            request: HidRequest::GetReport as u8,
+            value: (ReportType::Feature as u16) << 8 | LAMP_ARRAY_ATTRIBUTES_REPORT_ID as u16,
+            index: 0x0,
+            length: 0x40,
+        }
+        .pack()
+        .unwrap(),
+    ];
+
+    let validate_write_data = |v: &Vec<u8>| {
+        assert_eq!(v.len(), 23, "Expected LampArray attributes report");
+        assert_eq!(
+            v[..7],
+            [
+                LAMP_ARRAY_ATTRIBUTES_REPORT_ID,
+                0x02,
+                0x00,
+                0x40,
+                0x9C,
+                0x00,
+                0x00
+            ],
+            "Unexpected LampArray attributes"
+        );
+    };
+
+    let usb_bus = TestUsbBus::new(read_data, validate_write_data);
+
+    let usb_alloc = UsbBusAllocator::new(usb_bus);
+
+    let mut hid = UsbHidClassBuilder::new()
+        .add_interface(LampArrayInterface::default_config(
+            LampArrayAttributes {
+                bounding_box_width: 40_000,
+                ..Default::default()
+            },
+            &LAMPS,
+        ))
+        .build(&usb_alloc)
+        .unwrap();
+
+    let mut usb_dev = composite_device(&usb_alloc);
+
+    //poll the usb bus
+    for _ in 0..10 {
+        assert!(usb_dev.poll(&mut [&mut hid]));
+    }
+}
+
+#[test]
+fn class_descriptors_listed_and_served() {
+    init_logging();
+
+    const PHYSICAL: &[u8] = &[0x01, 0x03, 0x00];
+    const CLASS_DESCRIPTORS: &[ClassDescriptor] = &[ClassDescriptor {
+        descriptor_type: DescriptorType::Physical,
+        data: PHYSICAL,
+    }];
+
+    fn get_descriptor(descriptor_type: DescriptorType, expected: &'static [u8]) {
+        let request = UsbRequest {
+            direction: UsbDirection::In != UsbDirection::Out,
+            request_type: RequestType::Standard as u8,
+            recipient: Recipient::Interface as u8,
+            request: Request::GET_DESCRIPTOR,
+            value: (descriptor_type as u16) << 8,
+            index: 0x0,
+            length: 0x40,
+        }
+        .pack()
+        .unwrap();
+        let read_data: &[&[u8]] = &[&request];
+
+        let validate_write_data = move |v: &Vec<u8>| {
+            assert_eq!(v, expected, "Expected {:?} descriptor", descriptor_type);
+        };
+
+        let usb_bus = TestUsbBus::new(read_data, validate_write_data);
+        let usb_alloc = UsbBusAllocator::new(usb_bus);
+
+        let builder = UsbHidClassBuilder::new().add_interface(
+            RawInterfaceBuilder::new(&[0x05, 0x01])
+                .in_endpoint(UsbPacketSize::Bytes8, PollInterval::millis(20))
+                .class_descriptors(CLASS_DESCRIPTORS)
+                .build()
+                .unwrap(),
+        );
+        assert_eq!(builder.endpoint_usage().descriptor_size(), 18 + 3 + 7);
+        let mut hid = builder.build(&usb_alloc).unwrap();
+
+        let mut usb_dev = device_builder(&usb_alloc).build();
+
+        for _ in 0..10 {
+            assert!(usb_dev.poll(&mut [&mut hid]));
+        }
+    }
+
+    get_descriptor(
+        DescriptorType::Hid,
+        &[
+            0x0C, 0x21, 0x11, 0x01, 0x00, 0x02, 0x22, 0x02, 0x00, 0x23, 0x03, 0x00,
+        ],
+    );
+    get_descriptor(DescriptorType::Physical, PHYSICAL);
+}
+
+#[test]
+fn interface_association_precedes_grouped_interfaces() {
+    init_logging();
+
+    let validate_write_data = |v: &Vec<u8>| {
+        let mut descriptors = Vec::new();
+        let mut rest = &v[..];
+        while let Some(&len) = rest.first() {
+            descriptors.push(&rest[..usize::from(len)]);
+            rest = &rest[usize::from(len)..];
+        }
+        let types: Vec<u8> = descriptors.iter().map(|d| d[1]).collect();
+        assert_eq!(
+            types,
+            [0x02, 0x0B, 0x04, 0x21, 0x05, 0x04, 0x21, 0x05, 0x04, 0x21, 0x05],
+            "Expected Interface Association descriptor before the first interface"
+        );
+        assert_eq!(
+            descriptors[1],
+            &[0x08, 0x0B, 0x00, 0x02, 0x03, 0x01, 0x01, 0x00]
+        );
+    };
+    let read_data: &[&[u8]] = &[&UsbRequest {
+        direction: UsbDirection::In != UsbDirection::Out,
+        request_type: RequestType::Standard as u8,
+        recipient: Recipient::Device as u8,
+        request: Request::GET_DESCRIPTOR,
+        value: (usb_device::descriptor::descriptor_type::CONFIGURATION as u16) << 8,
+        index: 0,
+        length: 0xFFFF,
+    }
+    .pack()
+    .unwrap()];
+
+    let usb_bus = TestUsbBus::new(read_data, validate_write_data);
+    let usb_alloc = UsbBusAllocator::new(usb_bus);
+
+    // The vendor and keyboard interfaces form one function, numbered first as the last added
+    let associations = [InterfaceAssociation::new(1, 2, InterfaceProtocol::Keyboard)];
+    let builder = UsbHidClassBuilder::new()
+        .add_interface(
+            RawInterfaceBuilder::new(&[])
+                .in_endpoint(UsbPacketSize::Bytes8, PollInterval::millis(20))
+                .build()
+                .unwrap(),
+        )
+        .add_interface(
+            RawInterfaceBuilder::new(&[])
+                .in_endpoint(UsbPacketSize::Bytes8, PollInterval::millis(20))
+                .build()
+                .unwrap(),
+        )
+        .add_interface(
+            RawInterfaceBuilder::new(&[])
+                .in_endpoint(UsbPacketSize::Bytes8, PollInterval::millis(20))
+                .boot_device(InterfaceProtocol::Keyboard)
+                .build()
+                .unwrap(),
+        )
+        .interface_associations(&associations);
+    assert_eq!(builder.configuration_descriptor_size(), 9 + 8 + 3 * 25);
+    let mut hid = builder.build(&usb_alloc).unwrap();
+
+    let mut usb_dev = device_builder(&usb_alloc).composite_with_iads().build();
+
+    for _ in 0..20 {
+        assert!(usb_dev.poll(&mut [&mut hid]));
+    }
+}
+
+#[test]
+fn invalid_interface_associations_rejected() {
+    let usb_bus = TestUsbBus::new(&[], |_: &Vec<u8>| {});
+    let usb_alloc = UsbBusAllocator::new(usb_bus);
+
+    let build = |associations: &[InterfaceAssociation]| {
+        UsbHidClassBuilder::new()
+            .add_interface(
+                RawInterfaceBuilder::new(&[])
+                    .in_endpoint(UsbPacketSize::Bytes8, PollInterval::millis(20))
+                    .build()
+                    .unwrap(),
+            )
+            .add_interface(
+                RawInterfaceBuilder::new(&[])
+                    .in_endpoint(UsbPacketSize::Bytes8, PollInterval::millis(20))
+                    .build()
+                    .unwrap(),
+            )
+            .add_interface(
+                RawInterfaceBuilder::new(&[])
+                    .in_endpoint(UsbPacketSize::Bytes8, PollInterval::millis(20))
+                    .build()
+                    .unwrap(),
+            )
+            .interface_associations(associations)
+            .build(&usb_alloc)
+            .map(drop)
+    };
+    let protocol = InterfaceProtocol::None;
+
+    for associations in [
+        &[InterfaceAssociation::new(0, 1, protocol)][..],
+        &[InterfaceAssociation::new(2, 2, protocol)],
+        &[
+            InterfaceAssociation::new(0, 2, protocol),
+            InterfaceAssociation::new(1, 2, protocol),
+        ],
+        &[InterfaceAssociation::new(0, 2, protocol); MAX_INTERFACE_ASSOCIATIONS + 1],
+    ] {
+        assert_eq!(
+            build(associations),
+            Err(UsbHidBuilderError::InvalidValue),
+            "{:?}",
+            associations
+        );
+    }
+}
+
+#[test]
+fn build_rejects_oversized_configuration_descriptor() {
+    let usb_bus = TestUsbBus::new(&[], |_: &Vec<u8>| {});
+    let usb_alloc = UsbBusAllocator::new(usb_bus);
+
+    let builder = || {
+        UsbHidClassBuilder::new()
+            .add_interface(BootKeyboardInterface::default_config())
+            .add_interface(BootKeyboardInterface::default_config())
+            .add_interface(BootKeyboardInterface::default_config())
+            .add_interface(BootKeyboardInterface::default_config())
+    };
+
+    assert_eq!(builder().configuration_descriptor_size(), 9 + 4 * 32);
+    assert!(matches!(
+        builder().build(&usb_alloc),
+        Err(UsbHidBuilderError::DescriptorTooLarge {
+            size: 137,
+            limit: DEFAULT_CONTROL_BUFFER_SIZE,
+        })
+    ));
+    assert!(builder().control_buffer_size(256).build(&usb_alloc).is_ok());
+}
+
+#[test]
+fn start_of_frame() {
+    let usb_bus = TestUsbBus::new(&[], |_: &Vec<u8>| {});
+    let usb_alloc = UsbBusAllocator::new(usb_bus);
+
+    let mut hid = UsbHidClassBuilder::new()
+        .add_interface(
+            RawInterfaceBuilder::new(&[])
+                .in_endpoint(UsbPacketSize::Bytes8, PollInterval::millis(20))
+                .build()
+                .unwrap(),
+        )
+        .build(&usb_alloc)
+        .unwrap();
+
+    assert_eq!(hid.frame_number(), None);
+
+    hid.start_of_frame(0x0FFF);
+    assert_eq!(hid.frame_number(), Some(0x07FF));
+    assert!(hid.poll_events().start_of_frame());
+    assert!(hid.poll_events().is_empty());
+
+    UsbClass::reset(&mut hid);
+    assert_eq!(hid.frame_number(), None);
+}
+
+struct FrameRecorder<'a, B: UsbBus> {
+    inner: RawInterface<'a, B>,
+    frames: Vec<u16>,
+}
+
+impl<'a, B: UsbBus> InterfaceClass<'a> for FrameRecorder<'a, B> {
+    delegate::delegate! {
+        to self.inner {
+            fn report_descriptor(&self) -> &'_ [u8];
+            fn id(&self) -> InterfaceNumber;
+            fn write_descriptors(&self, writer: &mut DescriptorWriter) -> usb_device::Result<()>;
+            fn get_string(&self, index: StringIndex, lang_id: u16) -> Option<&'_ str>;
+            fn reset(&mut self);
+            fn set_report(&mut self, data: &[u8]) -> usb_device::Result<()>;
+            fn get_report(&mut self, data: &mut [u8]) -> usb_device::Result<usize>;
+            fn get_report_ack(&mut self) -> usb_device::Result<()>;
+            fn set_idle(&mut self, report_id: u8, value: u8);
+            fn get_idle(&self, report_id: u8) -> u8;
+            fn set_protocol(&mut self, protocol: HidProtocol) -> usb_device::Result<()>;
+            fn get_protocol(&self) -> HidProtocol;
+            fn out_endpoint_address(&self) -> Option<EndpointAddress>;
+            fn in_endpoint_address(&self) -> EndpointAddress;
+            fn endpoint_in_complete(&self, addr: EndpointAddress);
+        }
+    }
+
+    fn start_of_frame(&mut self, frame_number: u16) {
+        self.frames.push(frame_number);
+    }
+}
+
+#[test]
+fn start_of_frame_notifies_interfaces() {
+    let usb_bus = TestUsbBus::new(&[], |_: &Vec<u8>| {});
+    let usb_alloc = UsbBusAllocator::new(usb_bus);
+    let config = || {
+        RawInterfaceBuilder::new(&[])
+            .in_endpoint(UsbPacketSize::Bytes8, PollInterval::millis(20))
+            .build()
+            .unwrap()
+    };
+
+    let mut first = FrameRecorder {
+        inner: config().allocate(&usb_alloc),
+        frames: Vec::new(),
+    };
+    let mut second = FrameRecorder {
+        inner: config().allocate(&usb_alloc),
+        frames: Vec::new(),
+    };
+    let mut interfaces: [&mut dyn InterfaceClass<'_>; 2] = [&mut first, &mut second];
+    let mut hid = UsbHidClass::<(), _>::new_dynamic(&mut interfaces);
+
+    hid.start_of_frame(0x0801);
+    hid.start_of_frame(0x0002);
+    drop(hid);
+
+    assert_eq!(first.frames, [0x0001, 0x0002]);
+    assert_eq!(second.frames, [0x0001, 0x0002]);
+}
+
+#[cfg(feature = "examples_support")]
+#[test]
+fn examples_support_exercisers() {
+    use crate::examples_support::{
+        desk_set, gamepad_consumer, keyboard_mouse, DeskSetExerciser, GamepadConsumerExerciser,
+        KeyboardMouseExerciser,
+    };
+
+    let usb_alloc = UsbBusAllocator::new(TestUsbBus::new(&[], |_: &Vec<u8>| {}));
+    let class = keyboard_mouse(&usb_alloc).unwrap();
+    let _usb_dev = UsbDeviceBuilder::new(&usb_alloc, UsbVidPid(0x1209, 0x0001)).build();
+    let mut exerciser = KeyboardMouseExerciser::default();
+    for _ in 0..10 {
+        exerciser.tick(&class).unwrap();
+    }
+
+    let usb_alloc = UsbBusAllocator::new(TestUsbBus::new(&[], |_: &Vec<u8>| {}));
+    let class = gamepad_consumer(&usb_alloc).unwrap();
+    let _usb_dev = UsbDeviceBuilder::new(&usb_alloc, UsbVidPid(0x1209, 0x0001)).build();
+    let mut exerciser = GamepadConsumerExerciser::default();
+    for _ in 0..10 {
+        exerciser.tick(&class).unwrap();
+    }
+    assert_ne!(exerciser.gamepad, Default::default());
+
+    let usb_alloc = UsbBusAllocator::new(TestUsbBus::new(&[], |_: &Vec<u8>| {}));
+    let class = desk_set(&usb_alloc).unwrap();
+    let _usb_dev = UsbDeviceBuilder::new(&usb_alloc, UsbVidPid(0x1209, 0x0001)).build();
+    let mut exerciser = DeskSetExerciser::default();
+    for _ in 0..10 {
+        exerciser.tick(&class).unwrap();
+    }
+    assert_ne!(exerciser.mouse, Default::default());
+}
+
+#[test]
+fn interfaces_accessed_with_macro() {
+    use crate::hid_interface;
+    use crate::interface::InterfaceClass;
+
+    let usb_bus = TestUsbBus::new(&[], |_: &Vec<u8>| {});
+    let usb_alloc = UsbBusAllocator::new(usb_bus);
+
+    let mut hid = UsbHidClassBuilder::new()
+        .add_interface(
+            RawInterfaceBuilder::new(&[])
+                .in_endpoint(UsbPacketSize::Bytes8, PollInterval::millis(20))
+                .build()
+                .unwrap(),
+        )
+        .add_interface(BootMouseInterface::default_config())
+        .add_interface(BootKeyboardInterface::default_config())
+        .build(&usb_alloc)
+        .unwrap();
+
+    assert_eq!(u8::from(hid_interface!(hid, BootKeyboardInterface).id()), 0);
+    assert_eq!(
+        u8::from(hid_interface!(hid, crate::device::mouse::BootMouseInterface).id()),
+        1
+    );
+    assert_eq!(u8::from(hid_interface!(hid, RawInterface<'_, _>).id()), 2);
+    hid_interface!(mut hid, BootKeyboardInterface).reset();
+    hid_interface!(mut hid, RawInterface<'_, _>).reset();
+}
+
+#[test]
+fn devices_added_with_default_config() {
+    use crate::interface::InterfaceClass;
+
+    let usb_bus = TestUsbBus::new(&[], |_: &Vec<u8>| {});
+    let usb_alloc = UsbBusAllocator::new(usb_bus);
+
+    let builder = UsbHidClassBuilder::new()
+        .add_device::<BootMouseInterface<_>>()
+        .add_device::<BootKeyboardInterface<_>>();
+    assert_eq!(
+        builder.endpoint_usage(),
+        UsbHidClassBuilder::<TestUsbBus<fn(&Vec<u8>)>, _>::new()
+            .add_interface(BootMouseInterface::default_config())
+            .add_interface(BootKeyboardInterface::default_config())
+            .endpoint_usage()
+    );
+
+    let hid = builder.build(&usb_alloc).unwrap();
+    let keyboard = hid.interface::<BootKeyboardInterface<'_, _>, _>();
+    assert_eq!(u8::from(keyboard.id()), 0);
+}
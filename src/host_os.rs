@@ -0,0 +1,121 @@
+//! Best-guess host operating system detection from enumeration behaviour
+//!
+//! Keyboards commonly use this to switch between macOS and Windows modifier layouts. The guess is
+//! built from the HID requests the host makes while configuring the device:
+//!
+//! * Windows requests the Microsoft OS string descriptor (index `0xEE`), and sends `SET_IDLE`
+//!   after reading the report descriptor
+//! * Linux sends `SET_IDLE` before reading the report descriptor
+//! * macOS reads the report descriptor but does not send `SET_IDLE`
+//!
+//! Device level requests, such as the device descriptor, are handled by `usb-device` and are not
+//! visible to the HID class. The result is a heuristic and should be user overridable.
+
+/// Microsoft OS string descriptor index
+const MS_OS_STRING_INDEX: u8 = 0xEE;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum HostOs {
+    #[default]
+    Unknown,
+    Windows,
+    MacOs,
+    Linux,
+}
+
+/// Enumeration requests observed since the last bus reset
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct HostOsDetector {
+    report_descriptor_requested: bool,
+    set_idle_before_report_descriptor: bool,
+    set_idle_after_report_descriptor: bool,
+    ms_os_string_requested: bool,
+    string_requests: u8,
+}
+
+impl HostOsDetector {
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+
+    pub fn report_descriptor_requested(&mut self) {
+        self.report_descriptor_requested = true;
+    }
+
+    pub fn set_idle(&mut self) {
+        if self.report_descriptor_requested {
+            self.set_idle_after_report_descriptor = true;
+        } else {
+            self.set_idle_before_report_descriptor = true;
+        }
+    }
+
+    pub fn string_requested(&mut self, index: u8) {
+        self.string_requests = self.string_requests.saturating_add(1);
+        if index == MS_OS_STRING_INDEX {
+            self.ms_os_string_requested = true;
+        }
+    }
+
+    /// Number of interface string descriptor requests
+    pub fn string_requests(&self) -> u8 {
+        self.string_requests
+    }
+
+    /// Best guess of the host operating system
+    ///
+    /// Returns [`HostOs::Unknown`] until the host has read a report descriptor.
+    pub fn host_os(&self) -> HostOs {
+        if self.ms_os_string_requested {
+            HostOs::Windows
+        } else if self.set_idle_before_report_descriptor {
+            HostOs::Linux
+        } else if !self.report_descriptor_requested {
+            HostOs::Unknown
+        } else if self.set_idle_after_report_descriptor {
+            HostOs::Windows
+        } else {
+            HostOs::MacOs
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::host_os::{HostOs, HostOsDetector};
+
+    #[test]
+    fn unknown_before_enumeration() {
+        let detector = HostOsDetector::default();
+        assert_eq!(detector.host_os(), HostOs::Unknown);
+    }
+
+    #[test]
+    fn detects_from_request_order() {
+        let mut linux = HostOsDetector::default();
+        linux.set_idle();
+        linux.report_descriptor_requested();
+        assert_eq!(linux.host_os(), HostOs::Linux);
+
+        let mut windows = HostOsDetector::default();
+        windows.report_descriptor_requested();
+        windows.set_idle();
+        assert_eq!(windows.host_os(), HostOs::Windows);
+
+        let mut mac = HostOsDetector::default();
+        mac.report_descriptor_requested();
+        assert_eq!(mac.host_os(), HostOs::MacOs);
+    }
+
+    #[test]
+    fn ms_os_string_implies_windows() {
+        let mut detector = HostOsDetector::default();
+        detector.set_idle();
+        detector.string_requested(0xEE);
+        assert_eq!(detector.host_os(), HostOs::Windows);
+        assert_eq!(detector.string_requests(), 1);
+
+        detector.reset();
+        assert_eq!(detector.host_os(), HostOs::Unknown);
+    }
+}
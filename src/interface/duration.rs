@@ -0,0 +1,267 @@
+//! Durations with the range and resolution defined by the HID and USB specifications
+
+use fugit::{ExtU32, MicrosDurationU32, MillisDurationU32};
+
+use crate::hid_class::{BuilderResult, UsbHidBuilderError, UsbSpeed};
+
+/// HID idle rate, 0 to 1020ms in 4ms steps - Hid spec 7.2.4 Set_Idle Request
+///
+/// A duration of zero is indefinite, reports are only sent on change.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default, Hash)]
+pub struct IdleDuration(u8);
+
+impl IdleDuration {
+    pub const INDEFINITE: Self = Self(0);
+    pub const MAX: Self = Self(u8::MAX);
+
+    /// Returns [`UsbHidBuilderError::ValueOverflow`] above 1020ms and
+    /// [`UsbHidBuilderError::InvalidValue`] if `ms` is not a multiple of 4ms
+    pub const fn from_millis(ms: u32) -> BuilderResult<Self> {
+        if !ms.is_multiple_of(4) {
+            Err(UsbHidBuilderError::InvalidValue)
+        } else if ms / 4 > u8::MAX as u32 {
+            Err(UsbHidBuilderError::ValueOverflow)
+        } else {
+            Ok(Self((ms / 4) as u8))
+        }
+    }
+
+    /// Panics if `ms` is not a valid idle duration, a compile error when evaluated in a const context
+    pub const fn millis(ms: u32) -> Self {
+        match Self::from_millis(ms) {
+            Ok(d) => d,
+            Err(_) => panic!("idle duration must be a multiple of 4ms, from 0 to 1020ms"),
+        }
+    }
+
+    /// Idle rate in the 4ms units used by `SET_IDLE` and `GET_IDLE`
+    pub const fn from_raw(units: u8) -> Self {
+        Self(units)
+    }
+
+    pub const fn raw(&self) -> u8 {
+        self.0
+    }
+
+    pub const fn is_indefinite(&self) -> bool {
+        self.0 == 0
+    }
+
+    pub fn to_duration(&self) -> MillisDurationU32 {
+        (u32::from(self.0) * 4).millis()
+    }
+}
+
+impl TryFrom<MillisDurationU32> for IdleDuration {
+    type Error = UsbHidBuilderError;
+
+    fn try_from(duration: MillisDurationU32) -> BuilderResult<Self> {
+        Self::from_millis(duration.to_millis())
+    }
+}
+
+impl From<IdleDuration> for MillisDurationU32 {
+    fn from(duration: IdleDuration) -> Self {
+        duration.to_duration()
+    }
+}
+
+/// Interrupt endpoint polling interval, 125µs to 255ms - USB spec 9.6.6 Endpoint
+///
+/// Full-speed endpoints are polled at most every 1ms, shorter intervals need a high-speed bus,
+/// see [`PollInterval::b_interval`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct PollInterval(u32);
+
+impl PollInterval {
+    /// Shortest full-speed interval
+    pub const MIN: Self = Self(1000);
+    pub const MAX: Self = Self(u8::MAX as u32 * 1000);
+    /// One high-speed microframe, the shortest high-speed interval
+    pub const MICROFRAME: Self = Self(125);
+
+    /// Returns [`UsbHidBuilderError::ValueOverflow`] above 255ms and
+    /// [`UsbHidBuilderError::InvalidValue`] for 0ms
+    pub const fn from_millis(ms: u32) -> BuilderResult<Self> {
+        if ms == 0 {
+            Err(UsbHidBuilderError::InvalidValue)
+        } else if ms > u8::MAX as u32 {
+            Err(UsbHidBuilderError::ValueOverflow)
+        } else {
+            Ok(Self(ms * 1000))
+        }
+    }
+
+    /// Panics if `ms` is not a valid polling interval, a compile error when evaluated in a const
+    /// context
+    pub const fn millis(ms: u32) -> Self {
+        match Self::from_millis(ms) {
+            Ok(i) => i,
+            Err(_) => panic!("poll interval must be from 1 to 255ms"),
+        }
+    }
+
+    /// Returns [`UsbHidBuilderError::ValueOverflow`] above 255ms and
+    /// [`UsbHidBuilderError::InvalidValue`] if `us` is not a multiple of 125µs
+    pub const fn from_micros(us: u32) -> BuilderResult<Self> {
+        if us == 0 || !us.is_multiple_of(Self::MICROFRAME.0) {
+            Err(UsbHidBuilderError::InvalidValue)
+        } else if us > Self::MAX.0 {
+            Err(UsbHidBuilderError::ValueOverflow)
+        } else {
+            Ok(Self(us))
+        }
+    }
+
+    /// Panics if `us` is not a valid polling interval, a compile error when evaluated in a const
+    /// context
+    pub const fn micros(us: u32) -> Self {
+        match Self::from_micros(us) {
+            Ok(i) => i,
+            Err(_) => panic!("poll interval must be a multiple of 125us up to 255ms"),
+        }
+    }
+
+    /// Interval in whole milliseconds, at least 1ms, as written to the `bInterval` field of
+    /// full-speed endpoint descriptors
+    pub const fn as_millis(&self) -> u8 {
+        let ms = self.0 / 1000;
+        if ms == 0 {
+            1
+        } else {
+            ms as u8
+        }
+    }
+
+    pub const fn as_micros(&self) -> u32 {
+        self.0
+    }
+
+    /// The `bInterval` field of the endpoint descriptor on a bus of `speed`
+    ///
+    /// Intervals are rounded down to what the bus supports, whole milliseconds on full-speed buses.
+    /// High-speed endpoints are polled every 2^(bInterval-1) microframes of 125µs, so the interval
+    /// is rounded down to a power of two microframes.
+    ///
+    /// Full-speed buses can't poll faster than every 1ms, so sub-millisecond intervals are rounded
+    /// up to 1ms. [`RawInterfaceBuilder::build`](crate::interface::raw::RawInterfaceBuilder::build)
+    /// rejects them unless the interface is built for [`UsbSpeed::High`].
+    pub const fn b_interval(&self, speed: UsbSpeed) -> u8 {
+        match speed {
+            UsbSpeed::Full => self.as_millis(),
+            UsbSpeed::High => {
+                let microframes = self.0 / Self::MICROFRAME.0;
+                (u32::BITS - microframes.leading_zeros()) as u8
+            }
+        }
+    }
+
+    /// Interval in whole milliseconds, see [`PollInterval::as_millis`]
+    pub fn to_duration(&self) -> MillisDurationU32 {
+        u32::from(self.as_millis()).millis()
+    }
+
+    pub fn to_micros(&self) -> MicrosDurationU32 {
+        self.0.micros()
+    }
+}
+
+impl TryFrom<MillisDurationU32> for PollInterval {
+    type Error = UsbHidBuilderError;
+
+    fn try_from(duration: MillisDurationU32) -> BuilderResult<Self> {
+        Self::from_millis(duration.to_millis())
+    }
+}
+
+impl From<PollInterval> for MillisDurationU32 {
+    fn from(interval: PollInterval) -> Self {
+        interval.to_duration()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use fugit::MillisDurationU32;
+
+    use crate::hid_class::{UsbHidBuilderError, UsbSpeed};
+    use crate::interface::duration::{IdleDuration, PollInterval};
+
+    #[test]
+    fn idle_duration_range() {
+        assert_eq!(IdleDuration::from_millis(0), Ok(IdleDuration::INDEFINITE));
+        assert_eq!(IdleDuration::from_millis(1020), Ok(IdleDuration::MAX));
+        assert_eq!(
+            IdleDuration::from_millis(1024),
+            Err(UsbHidBuilderError::ValueOverflow)
+        );
+        assert_eq!(
+            IdleDuration::from_millis(2),
+            Err(UsbHidBuilderError::InvalidValue)
+        );
+        assert_eq!(IdleDuration::millis(500).raw(), 125);
+        assert_eq!(
+            IdleDuration::from_raw(125).to_duration(),
+            MillisDurationU32::millis(500)
+        );
+    }
+
+    #[test]
+    fn poll_interval_range() {
+        assert_eq!(PollInterval::from_millis(1), Ok(PollInterval::MIN));
+        assert_eq!(PollInterval::from_millis(255), Ok(PollInterval::MAX));
+        assert_eq!(
+            PollInterval::from_millis(0),
+            Err(UsbHidBuilderError::InvalidValue)
+        );
+        assert_eq!(
+            PollInterval::try_from(MillisDurationU32::millis(256)),
+            Err(UsbHidBuilderError::ValueOverflow)
+        );
+        assert_eq!(PollInterval::millis(10).as_millis(), 10);
+    }
+
+    #[test]
+    fn high_speed_b_interval() {
+        assert_eq!(PollInterval::millis(10).b_interval(UsbSpeed::Full), 10);
+        // 2^(4-1) microframes = 1ms
+        assert_eq!(PollInterval::millis(1).b_interval(UsbSpeed::High), 4);
+        assert_eq!(PollInterval::millis(8).b_interval(UsbSpeed::High), 7);
+        // 10ms rounds down to 8ms
+        assert_eq!(PollInterval::millis(10).b_interval(UsbSpeed::High), 7);
+        assert_eq!(PollInterval::MAX.b_interval(UsbSpeed::High), 11);
+    }
+
+    #[test]
+    fn microsecond_intervals() {
+        assert_eq!(PollInterval::from_micros(125), Ok(PollInterval::MICROFRAME));
+        assert_eq!(PollInterval::from_micros(1000), Ok(PollInterval::MIN));
+        assert_eq!(
+            PollInterval::from_micros(100),
+            Err(UsbHidBuilderError::InvalidValue)
+        );
+        assert_eq!(
+            PollInterval::from_micros(256_000),
+            Err(UsbHidBuilderError::ValueOverflow)
+        );
+
+        assert_eq!(PollInterval::micros(125).b_interval(UsbSpeed::High), 1);
+        assert_eq!(PollInterval::micros(500).b_interval(UsbSpeed::High), 3);
+        assert_eq!(PollInterval::micros(1500).b_interval(UsbSpeed::Full), 1);
+        assert_eq!(PollInterval::micros(1500).b_interval(UsbSpeed::High), 4);
+        assert_eq!(PollInterval::micros(500).to_micros().to_micros(), 500);
+    }
+
+    #[test]
+    fn sub_millisecond_intervals_rounded_up_on_full_speed() {
+        // 8 kHz polling is only available on high-speed buses, full-speed polls every 1ms at most
+        for us in [125, 250, 500, 875] {
+            let interval = PollInterval::micros(us);
+            assert!(interval < PollInterval::MIN);
+            assert_eq!(interval.b_interval(UsbSpeed::Full), 1);
+            assert_eq!(interval.as_millis(), 1);
+            assert_eq!(interval.to_duration().to_millis(), 1);
+        }
+        assert_eq!(PollInterval::MIN.b_interval(UsbSpeed::Full), 1);
+    }
+}
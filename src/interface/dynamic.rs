@@ -0,0 +1,223 @@
+//! Interfaces chosen at runtime, as an alternative to the statically typed HList built by
+//! [`UsbHidClassBuilder`](crate::hid_class::UsbHidClassBuilder)
+//!
+//! ```
+//! # #[cfg(not(feature = "usb-device-03"))] {
+//! use usb_device::bus::{UsbBus, UsbBusAllocator};
+//! use usbd_human_interface_device::device::keyboard::BootKeyboard;
+//! use usbd_human_interface_device::device::mouse::BootMouse;
+//! use usbd_human_interface_device::interface::{InterfaceClass, UsbAllocatable};
+//! use usbd_human_interface_device::prelude::*;
+//!
+//! fn hid_class<'a, B: UsbBus>(usb_alloc: &'a UsbBusAllocator<B>, debug: bool) {
+//!     let mut keyboard = BootKeyboard::default_config().allocate(usb_alloc);
+//!     let mut mouse = BootMouse::default_config().allocate(usb_alloc);
+//!
+//!     let mut interfaces: [&mut dyn InterfaceClass<'a>; 2] = [&mut keyboard, &mut mouse];
+//!     let len = if debug { 2 } else { 1 };
+//!     let mut hid: UsbHidClass<B, _> = UsbHidClass::new_dynamic(&mut interfaces[..len]);
+//!     // usb_dev.poll(&mut [&mut hid]);
+//! #   let _ = &mut hid;
+//! }
+//! # }
+//! ```
+//!
+//! Unused interfaces are still allocated, so their interface numbers and endpoints stay reserved
+//! but are not reported to the host.
+
+use usb_device::bus::StringIndex;
+use usb_device::class_prelude::DescriptorWriter;
+use usb_device::endpoint::EndpointAddress;
+
+use crate::hid_class::InterfaceAssociation;
+use crate::interface::{InterfaceClass, InterfaceHList};
+
+/// Interfaces of a [`UsbHidClass`](crate::hid_class::UsbHidClass) created with
+/// [`UsbHidClass::new_dynamic`](crate::hid_class::UsbHidClass::new_dynamic)
+///
+/// `T` is usually `dyn InterfaceClass`, or an enum of the application's interfaces to keep access
+/// to their report methods.
+#[derive(Debug)]
+pub struct DynamicInterfaces<'i, T: ?Sized> {
+    interfaces: &'i mut [&'i mut T],
+}
+
+impl<'i, T: ?Sized> DynamicInterfaces<'i, T> {
+    pub(crate) fn new(interfaces: &'i mut [&'i mut T]) -> Self {
+        Self { interfaces }
+    }
+
+    /// Interfaces in the order their descriptors are written
+    pub fn get(&self) -> &[&'i mut T] {
+        self.interfaces
+    }
+
+    pub fn get_mut(&mut self) -> &mut [&'i mut T] {
+        self.interfaces
+    }
+}
+
+/// Conversion to `dyn InterfaceClass`, for both interfaces and `dyn InterfaceClass` itself
+pub trait AsInterfaceClass<'a> {
+    fn as_interface(&self) -> &dyn InterfaceClass<'a>;
+    fn as_interface_mut(&mut self) -> &mut dyn InterfaceClass<'a>;
+}
+
+impl<'a, T: InterfaceClass<'a>> AsInterfaceClass<'a> for T {
+    fn as_interface(&self) -> &dyn InterfaceClass<'a> {
+        self
+    }
+    fn as_interface_mut(&mut self) -> &mut dyn InterfaceClass<'a> {
+        self
+    }
+}
+
+impl<'a> AsInterfaceClass<'a> for dyn InterfaceClass<'a> + '_ {
+    fn as_interface(&self) -> &dyn InterfaceClass<'a> {
+        self
+    }
+    fn as_interface_mut(&mut self) -> &mut dyn InterfaceClass<'a> {
+        self
+    }
+}
+
+impl<'a: 'i, 'i, T> InterfaceHList<'a> for DynamicInterfaces<'i, T>
+where
+    T: AsInterfaceClass<'a> + ?Sized,
+{
+    fn get_id_mut(&mut self, id: u8) -> Option<&mut dyn InterfaceClass<'a>> {
+        self.interfaces
+            .iter_mut()
+            .map(|i| i.as_interface_mut())
+            .find(|i| u8::from(i.id()) == id)
+    }
+
+    fn get_id(&self, id: u8) -> Option<&dyn InterfaceClass<'a>> {
+        self.interfaces
+            .iter()
+            .map(|i| i.as_interface())
+            .find(|i| u8::from(i.id()) == id)
+    }
+
+    fn get_out_endpoint_id(&self, addr: EndpointAddress) -> Option<u8> {
+        self.interfaces
+            .iter()
+            .map(|i| i.as_interface())
+            .find(|i| i.out_endpoint_address() == Some(addr))
+            .map(|i| u8::from(i.id()))
+    }
+
+    fn get_in_endpoint_id(&self, addr: EndpointAddress) -> Option<u8> {
+        self.interfaces
+            .iter()
+            .map(|i| i.as_interface())
+            .find(|i| i.in_endpoint_address() == addr)
+            .map(|i| u8::from(i.id()))
+    }
+
+    fn endpoint_in_complete(&self, addr: EndpointAddress) {
+        for interface in self.interfaces.iter().map(|i| i.as_interface()) {
+            interface.endpoint_in_complete(addr);
+        }
+    }
+
+    fn poll(&self) {
+        for interface in self.interfaces.iter().map(|i| i.as_interface()) {
+            interface.poll();
+        }
+    }
+
+    fn start_of_frame(&mut self, frame_number: u16) {
+        for interface in self.interfaces.iter_mut() {
+            interface.as_interface_mut().start_of_frame(frame_number);
+        }
+    }
+
+    fn reset(&mut self) {
+        for interface in self.interfaces.iter_mut() {
+            interface.as_interface_mut().reset();
+        }
+    }
+
+    fn take_wakeup_request(&self) -> bool {
+        //Clear every interface's request, not only the first
+        let mut requested = false;
+        for interface in self.interfaces.iter() {
+            requested |= interface.as_interface().take_wakeup_request();
+        }
+        requested
+    }
+
+    /// Interface associations aren't supported, `associations` is always empty
+    fn write_descriptors(
+        &self,
+        writer: &mut DescriptorWriter,
+        _: &[InterfaceAssociation],
+    ) -> usb_device::Result<()> {
+        for interface in self.interfaces.iter().map(|i| i.as_interface()) {
+            interface.write_descriptors(writer)?;
+        }
+        Ok(())
+    }
+
+    fn get_string(&self, index: StringIndex, lang_id: u16) -> Option<&'_ str> {
+        self.interfaces
+            .iter()
+            .find_map(|i| i.as_interface().get_string(index, lang_id))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    #[cfg(feature = "simulation")]
+    #[test]
+    fn interfaces_chosen_at_runtime() {
+        use usb_device::bus::UsbBusAllocator;
+
+        use crate::device::keyboard::{BootKeyboard, BOOT_KEYBOARD_REPORT_DESCRIPTOR};
+        use crate::device::mouse::BootMouse;
+        use crate::interface::raw::RawInterfaceBuilder;
+        use crate::interface::{InterfaceClass, UsbAllocatable};
+        use crate::prelude::*;
+        use crate::simulation::{device_builder, SimBus, SimHost};
+
+        let usb_alloc = UsbBusAllocator::new(SimBus::new());
+        let mut keyboard = BootKeyboard::default_config().allocate(&usb_alloc);
+        let mut mouse = BootMouse::default_config().allocate(&usb_alloc);
+        let mut interfaces: [&mut dyn InterfaceClass<'_>; 2] = [&mut keyboard, &mut mouse];
+        let mut hid: UsbHidClass<SimBus, _> = UsbHidClass::new_dynamic(&mut interfaces[..1]);
+        let mut usb_dev = device_builder(&usb_alloc, 8).build();
+
+        let host = SimHost::enumerate(&mut usb_dev, &mut [&mut hid]).unwrap();
+        assert_eq!(host.interfaces().len(), 1);
+        assert_eq!(
+            host.interfaces()[0].report_descriptor,
+            BOOT_KEYBOARD_REPORT_DESCRIPTOR
+        );
+
+        // Interfaces of one type remain accessible through the class
+        let usb_alloc = UsbBusAllocator::new(SimBus::new());
+        let mut first = RawInterfaceBuilder::new(&[0x05, 0x01])
+            .in_endpoint(UsbPacketSize::Bytes8, PollInterval::millis(20))
+            .build()
+            .unwrap()
+            .allocate(&usb_alloc);
+        let mut second = RawInterfaceBuilder::new(&[0x05, 0x0C])
+            .in_endpoint(UsbPacketSize::Bytes8, PollInterval::millis(20))
+            .build()
+            .unwrap()
+            .allocate(&usb_alloc);
+        let mut interfaces = [&mut first, &mut second];
+        let mut hid: UsbHidClass<SimBus, _> = UsbHidClass::new_dynamic(&mut interfaces);
+        let mut usb_dev = device_builder(&usb_alloc, 8).build();
+
+        let host = SimHost::enumerate(&mut usb_dev, &mut [&mut hid]).unwrap();
+        assert_eq!(host.interfaces().len(), 2);
+        assert_eq!(host.interfaces()[1].report_descriptor, [0x05, 0x0C]);
+        hid.dynamic_interfaces()[1].write_report(&[0x42]).unwrap();
+        assert_eq!(
+            host.read_report(&mut usb_dev, &mut [&mut hid], 1),
+            Some(vec![0x42])
+        );
+    }
+}
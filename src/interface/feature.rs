@@ -0,0 +1,403 @@
+//! Storage for feature reports
+//!
+//! [`FeatureReports`] holds the current value of feature reports registered by the application.
+//! Used as the [`ReportHandler`](crate::interface::report_id::ReportHandler) of a [`ReportIdInterface`](crate::interface::report_id::ReportIdInterface),
+//! `GET_REPORT(Feature)` requests are answered from the stored values and `SET_REPORT(Feature)`
+//! requests update writable reports.
+//!
+//! Interfaces that build their feature reports from their own state answer `GET_REPORT(Feature)`
+//! requests through a [`FeatureResponder`] instead.
+use heapless::{LinearMap, Vec};
+use packed_struct::types::bits::ByteArray;
+use packed_struct::PackedStruct;
+use usb_device::bus::UsbBus;
+use usb_device::UsbError;
+
+use crate::hid_class::descriptor::ReportType;
+use crate::interface::raw::RawInterface;
+use crate::interface::report_id::{ReportSink, ReportSource};
+use crate::interface::InterfaceClass;
+use crate::UsbHidError;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct FeatureReport<const LEN: usize> {
+    data: Vec<u8, LEN>,
+    writable: bool,
+    changed: bool,
+}
+
+/// Up to `N` feature reports, by report ID, of up to `LEN` bytes each, excluding the report ID
+#[derive(Clone, Debug, Default)]
+pub struct FeatureReports<const N: usize, const LEN: usize> {
+    reports: LinearMap<u8, FeatureReport<LEN>, N>,
+}
+
+impl<const N: usize, const LEN: usize> FeatureReports<N, LEN> {
+    pub fn new() -> Self {
+        Self {
+            reports: LinearMap::new(),
+        }
+    }
+
+    /// Registers the feature report `report_id` with its initial value
+    ///
+    /// The host can only change reports registered as `writable`, and only to a value of the
+    /// same length.
+    pub fn register(
+        &mut self,
+        report_id: u8,
+        data: &[u8],
+        writable: bool,
+    ) -> Result<(), UsbHidError> {
+        let report = FeatureReport {
+            data: Vec::from_slice(data).map_err(|_| {
+                error!(
+                    "Feature report ID {:X} is too long: {:X} bytes",
+                    report_id,
+                    data.len()
+                );
+                UsbHidError::ReportTooLong
+            })?,
+            writable,
+            changed: false,
+        };
+        self.reports.insert(report_id, report).map_err(|_| {
+            error!(
+                "No space for feature report ID {:X}, maximum {} IDs",
+                report_id, N
+            );
+            UsbHidError::UsbError(UsbError::BufferOverflow)
+        })?;
+        Ok(())
+    }
+
+    /// Current value of the feature report `report_id`
+    pub fn get(&self, report_id: u8) -> Option<&[u8]> {
+        self.reports.get(&report_id).map(|r| r.data.as_slice())
+    }
+
+    /// Updates the value of the registered feature report `report_id`
+    pub fn set(&mut self, report_id: u8, data: &[u8]) -> Result<(), UsbHidError> {
+        let report = self
+            .reports
+            .get_mut(&report_id)
+            .ok_or(UsbHidError::UsbError(UsbError::InvalidEndpoint))?;
+        report.data = Vec::from_slice(data).map_err(|_| UsbHidError::ReportTooLong)?;
+        report.changed = false;
+        Ok(())
+    }
+
+    /// Value of the feature report `report_id` if the host has set it since the last call
+    pub fn take_changed(&mut self, report_id: u8) -> Option<&[u8]> {
+        let report = self.reports.get_mut(&report_id)?;
+        if report.changed {
+            report.changed = false;
+            Some(report.data.as_slice())
+        } else {
+            None
+        }
+    }
+}
+
+impl<const N: usize, const LEN: usize> ReportSink for FeatureReports<N, LEN> {
+    fn set_report(
+        &mut self,
+        report_type: ReportType,
+        report_id: u8,
+        data: &[u8],
+    ) -> usb_device::Result<()> {
+        if report_type != ReportType::Feature {
+            warn!(
+                "Unsupported SetReport type: {:?}, ID: {:X}",
+                report_type, report_id
+            );
+            return Err(UsbError::Unsupported);
+        }
+        let report = self
+            .reports
+            .get_mut(&report_id)
+            .filter(|r| r.writable)
+            .ok_or_else(|| {
+                warn!("Feature report ID {:X} is not writable", report_id);
+                UsbError::Unsupported
+            })?;
+        if data.len() != report.data.len() {
+            warn!(
+                "Feature report ID {:X} expected {:X} bytes, got {:X} bytes",
+                report_id,
+                report.data.len(),
+                data.len()
+            );
+            return Err(UsbError::ParseError);
+        }
+        report.data.copy_from_slice(data);
+        report.changed = true;
+        Ok(())
+    }
+}
+
+impl<const N: usize, const LEN: usize> ReportSource for FeatureReports<N, LEN> {
+    fn get_report(
+        &mut self,
+        report_type: ReportType,
+        report_id: u8,
+        data: &mut [u8],
+    ) -> usb_device::Result<usize> {
+        let report = self
+            .get(report_id)
+            .filter(|_| report_type == ReportType::Feature)
+            .ok_or(UsbError::Unsupported)?;
+        let data = data
+            .get_mut(..report.len())
+            .ok_or(UsbError::BufferOverflow)?;
+        data.copy_from_slice(report);
+        Ok(report.len())
+    }
+}
+
+/// Answers the `GET_REPORT` requests of an interface wrapping a [`RawInterface`], building
+/// feature reports from the interface's state
+///
+/// Input reports are answered by the inner interface, which only acknowledges the requests it
+/// answered.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub(crate) struct FeatureResponder {
+    sent: bool,
+}
+
+impl FeatureResponder {
+    /// Answers a feature report with `feature`, which writes the report `report_id` to `data`,
+    /// prefixed by the report ID if the interface has report IDs, and returns its length
+    ///
+    /// `feature` returns [`UsbError::Unsupported`] for report IDs without a feature report.
+    pub(crate) fn get_report<B: UsbBus>(
+        &mut self,
+        inner: &mut RawInterface<'_, B>,
+        report_type: ReportType,
+        report_id: u8,
+        data: &mut [u8],
+        feature: impl FnOnce(&mut [u8]) -> usb_device::Result<usize>,
+    ) -> usb_device::Result<usize> {
+        let result = match report_type {
+            ReportType::Feature => feature(data),
+            ReportType::Input => return inner.get_report(data),
+            ReportType::Output => Err(UsbError::Unsupported),
+        };
+        match result {
+            Ok(len) => {
+                self.sent = true;
+                Ok(len)
+            }
+            Err(UsbError::Unsupported) => {
+                warn!(
+                    "Unsupported GetReport type: {:?}, ID: {:X}",
+                    report_type, report_id
+                );
+                Err(UsbError::Unsupported)
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    pub(crate) fn get_report_ack<B: UsbBus>(
+        &mut self,
+        inner: &mut RawInterface<'_, B>,
+    ) -> usb_device::Result<()> {
+        if core::mem::take(&mut self.sent) {
+            Ok(())
+        } else {
+            inner.get_report_ack()
+        }
+    }
+
+    pub(crate) fn reset(&mut self) {
+        self.sent = false;
+    }
+}
+
+/// Packs `report` into `data`, for interfaces without report IDs, returns its length
+pub(crate) fn pack_into<R: PackedStruct>(report: &R, data: &mut [u8]) -> usb_device::Result<usize> {
+    let packed = report.pack().map_err(|e| {
+        error!("Error packing feature report: {:?}", e);
+        UsbError::ParseError
+    })?;
+    let packed = packed.as_bytes_slice();
+    data.get_mut(..packed.len())
+        .ok_or(UsbError::BufferOverflow)?
+        .copy_from_slice(packed);
+    Ok(packed.len())
+}
+
+/// Packs `report` into `data` after `report_id`, returns the length including the report ID
+pub(crate) fn pack_with_id<R: PackedStruct>(
+    report_id: u8,
+    report: &R,
+    data: &mut [u8],
+) -> usb_device::Result<usize> {
+    let packed = report.pack().map_err(|e| {
+        error!("Error packing feature report ID {:X}: {:?}", report_id, e);
+        UsbError::ParseError
+    })?;
+    write_with_id(report_id, packed.as_bytes_slice(), data)
+}
+
+/// Copies `report` into `data` after `report_id`, returns the length including the report ID
+pub(crate) fn write_with_id(
+    report_id: u8,
+    report: &[u8],
+    data: &mut [u8],
+) -> usb_device::Result<usize> {
+    let data = data
+        .get_mut(..=report.len())
+        .ok_or(UsbError::BufferOverflow)?;
+    data[0] = report_id;
+    data[1..].copy_from_slice(report);
+    Ok(data.len())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::interface::feature::*;
+
+    #[test]
+    fn host_sets_writable_reports() {
+        let mut reports = FeatureReports::<2, 4>::new();
+        reports.register(1, &[0x10], false).unwrap();
+        reports.register(2, &[0x20, 0x21], true).unwrap();
+        assert!(reports.register(3, &[0x30], true).is_err());
+        assert!(FeatureReports::<1, 1>::new()
+            .register(1, &[0, 0], true)
+            .is_err());
+
+        assert!(reports.set_report(ReportType::Feature, 1, &[0x11]).is_err());
+        assert!(reports.set_report(ReportType::Feature, 2, &[0x22]).is_err());
+        assert!(reports
+            .set_report(ReportType::Output, 2, &[0x22, 0x23])
+            .is_err());
+        assert_eq!(reports.take_changed(2), None);
+        reports
+            .set_report(ReportType::Feature, 2, &[0x22, 0x23])
+            .unwrap();
+        assert_eq!(reports.take_changed(2), Some(&[0x22, 0x23][..]));
+        assert_eq!(reports.take_changed(2), None);
+
+        let mut data = [0; 4];
+        assert_eq!(
+            reports
+                .get_report(ReportType::Feature, 2, &mut data)
+                .unwrap(),
+            2
+        );
+        assert_eq!(data[..2], [0x22, 0x23]);
+        assert!(reports
+            .get_report(ReportType::Feature, 3, &mut data)
+            .is_err());
+        assert!(reports.get_report(ReportType::Input, 2, &mut data).is_err());
+    }
+
+    #[cfg(feature = "simulation")]
+    #[test]
+    fn feature_reports_by_control_transfer() {
+        use usb_device::bus::UsbBusAllocator;
+
+        use crate::hid_class::UsbPacketSize;
+        use crate::interface::duration::PollInterval;
+        use crate::interface::raw::RawInterfaceBuilder;
+        use crate::interface::report_id::ReportIdInterface;
+        use crate::interface::{InterfaceClass, WrappedInterfaceConfig};
+        use crate::prelude::UsbHidClassBuilder;
+        use crate::simulation::{control_transfer, device_builder, Response, SimBus};
+
+        type Interface<'a> = ReportIdInterface<'a, SimBus, FeatureReports<2, 4>, 1>;
+
+        let mut reports = FeatureReports::new();
+        reports.register(1, &[0x10, 0x11], true).unwrap();
+        reports.register(2, &[0x20], false).unwrap();
+
+        let usb_alloc = UsbBusAllocator::new(SimBus::new());
+        let config: WrappedInterfaceConfig<Interface<'_>, _, _> = WrappedInterfaceConfig::new(
+            RawInterfaceBuilder::new(&[0x05, 0x01])
+                .in_endpoint(UsbPacketSize::Bytes8, PollInterval::millis(20))
+                .build()
+                .unwrap(),
+            reports,
+        );
+        let mut class = UsbHidClassBuilder::new()
+            .add_interface(config)
+            .build(&usb_alloc)
+            .unwrap();
+        let mut usb_dev = device_builder(&usb_alloc, 64).build();
+
+        // GET_REPORT (Feature) ID 2
+        assert_eq!(
+            control_transfer(
+                &mut usb_dev,
+                &mut [&mut class],
+                [0xA1, 0x01, 0x02, 0x03, 0x00, 0x00, 0x02, 0x00],
+                &[]
+            ),
+            Response::Data(vec![0x02, 0x20])
+        );
+        // SET_REPORT (Feature) ID 1
+        assert_eq!(
+            control_transfer(
+                &mut usb_dev,
+                &mut [&mut class],
+                [0x21, 0x09, 0x01, 0x03, 0x00, 0x00, 0x03, 0x00],
+                &[0x01, 0x12, 0x13]
+            ),
+            Response::Ack
+        );
+
+        let events = class.poll_events();
+        let interface = class.interface_mut::<Interface<'_>, _>();
+        assert!(events.feature_changed(interface.id()));
+        assert!(!events.any_output_ready());
+        assert_eq!(
+            interface.handler_mut().take_changed(1),
+            Some(&[0x12, 0x13][..])
+        );
+
+        // GET_REPORT (Feature) ID 1
+        assert_eq!(
+            control_transfer(
+                &mut usb_dev,
+                &mut [&mut class],
+                [0xA1, 0x01, 0x01, 0x03, 0x00, 0x00, 0x03, 0x00],
+                &[]
+            ),
+            Response::Data(vec![0x01, 0x12, 0x13])
+        );
+
+        // SET_REPORT (Feature) ID 2, read only
+        assert_eq!(
+            control_transfer(
+                &mut usb_dev,
+                &mut [&mut class],
+                [0x21, 0x09, 0x02, 0x03, 0x00, 0x00, 0x02, 0x00],
+                &[0x02, 0x21]
+            ),
+            Response::Stall
+        );
+        // SET_REPORT (Feature) ID 3, not registered
+        assert_eq!(
+            control_transfer(
+                &mut usb_dev,
+                &mut [&mut class],
+                [0x21, 0x09, 0x03, 0x03, 0x00, 0x00, 0x02, 0x00],
+                &[0x03, 0x30]
+            ),
+            Response::Stall
+        );
+        assert!(!class.poll_events().any_feature_changed());
+        assert_eq!(
+            control_transfer(
+                &mut usb_dev,
+                &mut [&mut class],
+                [0xA1, 0x01, 0x02, 0x03, 0x00, 0x00, 0x02, 0x00],
+                &[]
+            ),
+            Response::Data(vec![0x02, 0x20])
+        );
+    }
+}
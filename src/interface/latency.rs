@@ -0,0 +1,156 @@
+//! Input report latency measurement
+//!
+//! With a [`LatencyClock`] configured, an interface timestamps each report when it is written to
+//! the IN endpoint and again when the host reads it, recording the difference in a
+//! [`LatencyHistogram`].
+
+/// Free running microsecond clock, expected to wrap at [`u32::MAX`]
+#[derive(Debug, Clone, Copy)]
+pub struct LatencyClock(pub fn() -> u32);
+
+impl PartialEq for LatencyClock {
+    fn eq(&self, other: &Self) -> bool {
+        // Configurations compare equal if they use the same clock function
+        self.0 as usize == other.0 as usize
+    }
+}
+
+impl Eq for LatencyClock {}
+
+/// Number of samples after which the histogram is aged, by halving all buckets
+const WINDOW: u32 = 1024;
+
+/// Rolling histogram of the time from report write to host acceptance
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct LatencyHistogram {
+    buckets: [u16; LatencyHistogram::BUCKETS],
+    max_us: u32,
+}
+
+impl LatencyHistogram {
+    pub const BUCKETS: usize = 8;
+    /// Exclusive upper bound of each bucket in microseconds, the last bucket is unbounded
+    pub const BUCKET_LIMITS_US: [u32; LatencyHistogram::BUCKETS - 1] =
+        [125, 250, 500, 1000, 2000, 4000, 8000];
+
+    pub fn record(&mut self, latency_us: u32) {
+        if self.samples() >= WINDOW {
+            self.buckets.iter_mut().for_each(|b| *b /= 2);
+        }
+        let bucket = Self::BUCKET_LIMITS_US
+            .iter()
+            .position(|&limit| latency_us < limit)
+            .unwrap_or(Self::BUCKETS - 1);
+        self.buckets[bucket] += 1;
+        self.max_us = self.max_us.max(latency_us);
+    }
+
+    pub fn buckets(&self) -> &[u16; LatencyHistogram::BUCKETS] {
+        &self.buckets
+    }
+
+    /// Number of samples in the histogram, older samples are aged out
+    pub fn samples(&self) -> u32 {
+        self.buckets.iter().map(|&b| u32::from(b)).sum()
+    }
+
+    /// Largest latency recorded since the histogram was cleared
+    pub fn max_us(&self) -> u32 {
+        self.max_us
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct LatencyTracker {
+    clock: Option<LatencyClock>,
+    pending: Option<u32>,
+    histogram: LatencyHistogram,
+}
+
+impl LatencyTracker {
+    pub(crate) fn new(clock: Option<LatencyClock>) -> Self {
+        Self {
+            clock,
+            ..Default::default()
+        }
+    }
+
+    pub(crate) fn enqueued(&mut self) {
+        if let Some(clock) = self.clock {
+            self.pending = Some((clock.0)());
+        }
+    }
+
+    pub(crate) fn completed(&mut self) {
+        if let (Some(clock), Some(start)) = (self.clock, self.pending.take()) {
+            self.histogram.record((clock.0)().wrapping_sub(start));
+        }
+    }
+
+    pub(crate) fn reset(&mut self) {
+        self.pending = None;
+    }
+
+    pub(crate) fn histogram(&self) -> LatencyHistogram {
+        self.histogram
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.histogram = Default::default();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::interface::latency::{LatencyClock, LatencyHistogram, LatencyTracker};
+
+    #[test]
+    fn histogram_buckets() {
+        let mut histogram = LatencyHistogram::default();
+        histogram.record(0);
+        histogram.record(999);
+        histogram.record(1000);
+        histogram.record(100_000);
+
+        assert_eq!(histogram.buckets(), &[1, 0, 0, 1, 1, 0, 0, 1]);
+        assert_eq!(histogram.max_us(), 100_000);
+    }
+
+    #[test]
+    fn histogram_ages_samples() {
+        let mut histogram = LatencyHistogram::default();
+        for _ in 0..1024 {
+            histogram.record(0);
+        }
+        histogram.record(0);
+
+        assert_eq!(histogram.samples(), 513);
+    }
+
+    #[test]
+    fn tracker_without_clock_records_nothing() {
+        let mut tracker = LatencyTracker::new(None);
+        tracker.enqueued();
+        tracker.completed();
+
+        assert_eq!(tracker.histogram().samples(), 0);
+    }
+
+    #[test]
+    fn tracker_handles_clock_wrap() {
+        fn clock() -> u32 {
+            static NOW: core::sync::atomic::AtomicU32 = core::sync::atomic::AtomicU32::new(0);
+            NOW.fetch_add(u32::MAX / 2 + 1, core::sync::atomic::Ordering::Relaxed)
+        }
+
+        let mut tracker = LatencyTracker::new(Some(LatencyClock(clock)));
+        tracker.completed();
+        assert_eq!(tracker.histogram().samples(), 0);
+
+        tracker.enqueued();
+        tracker.enqueued();
+        tracker.completed();
+        assert_eq!(tracker.histogram().samples(), 1);
+        assert_eq!(tracker.histogram().max_us(), u32::MAX / 2 + 1);
+    }
+}
@@ -1,25 +1,43 @@
 use core::cell::RefCell;
 use core::marker::PhantomData;
+#[cfg(feature = "async")]
+use core::task::{Context, Poll};
 
 use delegate::delegate;
 use fugit::{ExtU32, MillisDurationU32};
-use log::error;
 use packed_struct::PackedStruct;
 use usb_device::bus::UsbBus;
 use usb_device::class_prelude::*;
-use usb_device::UsbError;
 
+use crate::interface::latency::LatencyHistogram;
+use crate::interface::queue::ReportConsumer;
 use crate::interface::raw::{RawInterface, RawInterfaceConfig};
 use crate::interface::InterfaceNumber;
+use crate::interface::{HidDescriptorBodyBytes, InterfaceClass, WrappedInterface};
 use crate::interface::{HidProtocol, UsbAllocatable};
-use crate::interface::{InterfaceClass, WrappedInterface};
 use crate::UsbHidError;
 
+/// When a [`ManagedInterface`] repeats the last report sent
+///
+/// In both cases reports are only sent when they differ from the last report sent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TransmitPolicy {
+    /// Repeat the last report at the idle rate requested by the host
+    #[default]
+    HostIdle,
+    /// Repeat the last report every `keepalive`, or never if `None`, regardless of the idle rate
+    /// requested by the host
+    OnChange {
+        keepalive: Option<MillisDurationU32>,
+    },
+}
+
 pub struct IdleManager<R> {
     last_report: Option<R>,
     current_timeout: MillisDurationU32,
     default_timeout: MillisDurationU32,
     since_last_report: MillisDurationU32,
+    policy: TransmitPolicy,
 }
 
 impl<R> IdleManager<R>
@@ -32,6 +50,18 @@ where
             current_timeout: default,
             default_timeout: default,
             since_last_report: 0.millis(),
+            policy: TransmitPolicy::HostIdle,
+        }
+    }
+
+    pub fn set_transmit_policy(&mut self, policy: TransmitPolicy) {
+        self.policy = policy;
+    }
+
+    fn timeout(&self) -> MillisDurationU32 {
+        match self.policy {
+            TransmitPolicy::HostIdle => self.current_timeout,
+            TransmitPolicy::OnChange { keepalive } => keepalive.unwrap_or(0.millis()),
         }
     }
 
@@ -55,19 +85,34 @@ where
     }
 
     /// Call every 1ms / at 1 KHz
+    ///
+    /// Returns true once the last report is due to be resent, and on every following tick until
+    /// it has been resent, [`IdleManager::report_written`] restarts the timer. An idle rate of zero
+    /// never resends, reports are only sent when they change.
     pub fn tick(&mut self) -> bool {
-        if self.current_timeout.ticks() == 0 {
+        self.tick_with(1.millis())
+    }
+
+    /// Advances the idle timer by `elapsed`, see [`IdleManager::tick`]
+    pub fn tick_with(&mut self, elapsed: MillisDurationU32) -> bool {
+        let timeout = self.timeout();
+        if timeout.ticks() == 0 {
             self.since_last_report = 0.millis();
             return false;
         }
 
-        if self.since_last_report >= self.current_timeout {
-            self.since_last_report = 0.millis();
-            true
-        } else {
-            self.since_last_report += 1.millis();
-            false
+        if self.since_last_report < timeout {
+            self.since_last_report = self
+                .since_last_report
+                .checked_add(elapsed)
+                .unwrap_or(timeout);
         }
+        self.since_last_report >= timeout
+    }
+
+    /// The last report was not delivered, the next report is sent even if it is a duplicate
+    pub fn report_lost(&mut self) {
+        self.last_report = None;
     }
 
     pub fn last_report(&self) -> Option<R> {
@@ -75,16 +120,51 @@ where
     }
 }
 
-pub struct ManagedInterface<'a, B: UsbBus, R> {
-    inner: RawInterface<'a, B>,
-    idle_manager: RefCell<IdleManager<R>>,
+/// Interface managing HID idle for input reports of type `I`
+///
+/// Output reports are unpacked into `O` by [`ManagedInterface::read_output`], interfaces without
+/// typed output reports use the default of `()` and read raw bytes with [`ManagedInterface::read_report`].
+///
+/// Input reports can also be enqueued in a [`ReportQueue`](crate::interface::queue::ReportQueue)
+/// of `N` reports, see [`ManagedInterfaceConfig::report_queue`]. `OUT_QUEUE` and `IN_QUEUE` are
+/// the depths of the inner [`RawInterface`]'s report queues, see
+/// [`RawInterfaceBuilder::out_report_queue`](crate::interface::raw::RawInterfaceBuilder::out_report_queue).
+pub struct ManagedInterface<
+    'a,
+    B: UsbBus,
+    I,
+    O = (),
+    const N: usize = 0,
+    const OUT_QUEUE: usize = 0,
+    const IN_QUEUE: usize = 0,
+> {
+    inner: RawInterface<'a, B, OUT_QUEUE, IN_QUEUE>,
+    idle_manager: RefCell<IdleManager<I>>,
+    boot_report_len: Option<usize>,
+    report_id: Option<u8>,
+    report_queue: RefCell<Option<ReportConsumer<'a, I, N>>>,
+    output: PhantomData<O>,
 }
 
-impl<'a, B: UsbBus, R, const LEN: usize> ManagedInterface<'a, B, R>
+impl<
+        'a,
+        B: UsbBus,
+        I,
+        O,
+        const LEN: usize,
+        const N: usize,
+        const OUT_QUEUE: usize,
+        const IN_QUEUE: usize,
+    > ManagedInterface<'a, B, I, O, N, OUT_QUEUE, IN_QUEUE>
 where
-    R: Copy + Eq + PackedStruct<ByteArray = [u8; LEN]>,
+    I: Copy + Eq + PackedStruct<ByteArray = [u8; LEN]>,
 {
-    pub fn write_report(&self, report: &R) -> Result<(), UsbHidError> {
+    /// Same as [`ManagedInterface::write_input`]
+    pub fn write_report(&self, report: &I) -> Result<(), UsbHidError> {
+        self.write_input(report)
+    }
+
+    pub fn write_input(&self, report: &I) -> Result<(), UsbHidError> {
         if self.idle_manager.borrow().is_duplicate(report) {
             Err(UsbHidError::Duplicate)
         } else {
@@ -93,53 +173,191 @@ where
                 UsbHidError::SerializationError
             })?;
 
-            self.inner
-                .write_report(&data)
-                .map_err(UsbHidError::from)
-                .map(|_| {
-                    self.idle_manager.borrow_mut().report_written(*report);
-                })
+            self.inner.write_report(self.layout_data(&data)).map(|_| {
+                self.idle_manager.borrow_mut().report_written(*report);
+            })
+        }
+    }
+
+    /// Writes an input report, or registers the task to be woken when the IN endpoint may have
+    /// space, see [`RawInterface::poll_write_report`]
+    ///
+    /// Like [`ManagedInterface::write_input`], [`UsbHidError::Duplicate`] is returned without
+    /// waiting if the report has not changed.
+    #[cfg(feature = "async")]
+    pub fn poll_write_report(
+        &self,
+        cx: &mut Context<'_>,
+        report: &I,
+    ) -> Poll<Result<(), UsbHidError>> {
+        self.inner.register_in_waker(cx.waker());
+        match self.write_input(report) {
+            Err(UsbHidError::WouldBlock) => Poll::Pending,
+            result => Poll::Ready(result),
         }
     }
 
+    /// Writes an input report, waiting while the IN endpoint is busy, see
+    /// [`ManagedInterface::poll_write_report`]
+    #[cfg(feature = "async")]
+    pub async fn write_report_async(&self, report: &I) -> Result<(), UsbHidError> {
+        core::future::poll_fn(|cx| self.poll_write_report(cx, report)).await
+    }
+
     /// Call every 1ms / at 1 KHz
     pub fn tick(&self) -> Result<(), UsbHidError> {
+        self.tick_with(1.millis())
+    }
+
+    /// Advances the interface's timers by `elapsed`, for applications that cannot call
+    /// [`ManagedInterface::tick`] every 1ms, such as those sleeping between events
+    ///
+    /// An idle report due during `elapsed` is sent once, late, rather than once per idle period.
+    pub fn tick_with(&self, elapsed: MillisDurationU32) -> Result<(), UsbHidError> {
+        self.flush().ok();
         let mut idle_manager = self.idle_manager.borrow_mut();
-        if !(idle_manager.tick()) {
+        if self.inner.tick_with(elapsed) {
+            idle_manager.report_lost();
+        }
+        if !(idle_manager.tick_with(elapsed)) {
             Ok(())
         } else if let Some(r) = idle_manager.last_report() {
             let data = r.pack().map_err(|e| {
                 error!("Error packing report: {:?}", e);
                 UsbHidError::SerializationError
             })?;
-            match self.inner.write_report(&data) {
-                Ok(n) => {
-                    idle_manager.report_written(r);
-                    Ok(n)
-                }
-                Err(UsbError::WouldBlock) => Err(UsbHidError::WouldBlock),
-                Err(e) => Err(UsbHidError::UsbError(e)),
-            }
-            .map(|_| ())
+            self.inner
+                .write_report(self.layout_data(&data))
+                .map(|_| idle_manager.report_written(r))
         } else {
             Ok(())
         }
     }
 
+    /// Writes the input reports waiting in the inner interface's input report queue, then those
+    /// waiting in the report queue, until the IN endpoint is busy
+    ///
+    /// Called when the [`UsbHidClass`](crate::hid_class::UsbHidClass) is polled and on
+    /// [`ManagedInterface::tick`]. Returns the error of the inner interface's
+    /// [`RawInterface::flush`].
+    pub fn flush(&self) -> Result<(), UsbHidError> {
+        let result = self.inner.flush();
+        self.write_queued_reports();
+        result
+    }
+
+    /// Writes the reports waiting in the report queue until the IN endpoint is full
+    ///
+    /// Reports that fail for any other reason than a full endpoint, such as duplicates of the last
+    /// report, are dropped.
+    fn write_queued_reports(&self) {
+        let mut report_queue = self.report_queue.borrow_mut();
+        let Some(consumer) = report_queue.as_mut() else {
+            return;
+        };
+        while let Some(report) = consumer.peek() {
+            match self.write_input(report) {
+                Err(UsbHidError::WouldBlock) => break,
+                Ok(()) | Err(UsbHidError::Duplicate) => {}
+                Err(e) => warn!("Dropping queued report: {:?}", e),
+            }
+            consumer.dequeue();
+        }
+    }
+
+    /// Number of reports waiting in the report queue
+    pub fn queued_reports(&self) -> usize {
+        self.report_queue.borrow().as_ref().map_or(0, |c| c.len())
+    }
+
+    /// The part of a packed report sent in the current protocol
+    fn layout_data<'d>(&self, data: &'d [u8]) -> &'d [u8] {
+        match (self.inner.report_layout(), self.boot_report_len) {
+            (HidProtocol::Boot, Some(len)) => &data[..len.min(data.len())],
+            _ => data,
+        }
+    }
+
     delegate! {
         to self.inner{
-            pub fn read_report(&self, data: &mut [u8]) -> usb_device::Result<usize>;
+            pub fn read_report(&self, data: &mut [u8]) -> Result<usize, UsbHidError>;
+            pub(crate) fn read_queued_packet(&self, data: &mut [u8]) -> usb_device::Result<usize>;
+            pub(crate) fn read_unqueued_packet(&self, data: &mut [u8]) -> usb_device::Result<usize>;
+            pub(crate) fn queue_output_report(&self, data: &[u8]) -> usb_device::Result<()>;
+            pub fn protocol(&self) -> HidProtocol;
+            pub fn report_latency(&self) -> LatencyHistogram;
+            pub fn clear_report_latency(&self);
+            pub fn host_stalls(&self) -> u32;
+            pub fn host_stalled(&self) -> bool;
+            pub fn write_ready(&self) -> bool;
+            pub fn report_layout(&self) -> HidProtocol;
+            pub fn queued_output_reports(&self) -> usize;
+            pub fn queued_input_reports(&self) -> usize;
+            pub fn dropped_output_reports(&self) -> u32;
+        }
+    }
+}
+
+impl<
+        'a,
+        B: UsbBus,
+        I,
+        O,
+        const LEN: usize,
+        const N: usize,
+        const OUT_QUEUE: usize,
+        const IN_QUEUE: usize,
+    > ManagedInterface<'a, B, I, O, N, OUT_QUEUE, IN_QUEUE>
+where
+    O: PackedStruct<ByteArray = [u8; LEN]>,
+{
+    pub fn read_output(&self) -> Result<O, UsbHidError> {
+        let mut data = [0; LEN];
+        self.inner.read_report(&mut data)?;
+        O::unpack(&data).map_err(|e| {
+            error!("Error unpacking report: {:?}", e);
+            UsbHidError::SerializationError
+        })
+    }
+
+    /// Reads an output report, or registers the task to be woken when the host sends one, see
+    /// [`RawInterface::poll_read_report`]
+    #[cfg(feature = "async")]
+    pub fn poll_read_output(&self, cx: &mut Context<'_>) -> Poll<Result<O, UsbHidError>> {
+        self.inner.register_out_waker(cx.waker());
+        match self.read_output() {
+            Err(UsbHidError::WouldBlock) => Poll::Pending,
+            result => Poll::Ready(result),
         }
     }
+
+    /// Reads an output report, waiting until the host sends one, see
+    /// [`ManagedInterface::poll_read_output`]
+    #[cfg(feature = "async")]
+    pub async fn read_output_async(&self) -> Result<O, UsbHidError> {
+        core::future::poll_fn(|cx| self.poll_read_output(cx)).await
+    }
 }
 
-impl<'a, B: UsbBus, R> InterfaceClass<'a> for ManagedInterface<'a, B, R>
+impl<
+        'a,
+        B: UsbBus,
+        I,
+        O,
+        const LEN: usize,
+        const N: usize,
+        const OUT_QUEUE: usize,
+        const IN_QUEUE: usize,
+    > InterfaceClass<'a> for ManagedInterface<'a, B, I, O, N, OUT_QUEUE, IN_QUEUE>
 where
-    R: Copy + Eq,
+    I: Copy + Eq + PackedStruct<ByteArray = [u8; LEN]>,
 {
     delegate! {
         to self.inner{
            fn report_descriptor(&self) -> &'_ [u8];
+           fn hid_descriptor_body(&self) -> HidDescriptorBodyBytes;
+           fn class_descriptor(&self, descriptor_type: u8, index: u8) -> Option<&'_ [u8]>;
+           fn take_wakeup_request(&self) -> bool;
            fn id(&self) -> InterfaceNumber;
            fn write_descriptors(&self, writer: &mut DescriptorWriter) -> usb_device::Result<()>;
            fn get_string(&self, index: StringIndex, _lang_id: u16) -> Option<&'_ str>;
@@ -147,61 +365,557 @@ where
            fn get_report(&mut self, data: &mut [u8]) -> usb_device::Result<usize>;
            fn get_report_ack(&mut self) -> usb_device::Result<()>;
            fn get_idle(&self, report_id: u8) -> u8;
-           fn set_protocol(&mut self, protocol: HidProtocol);
            fn get_protocol(&self) -> HidProtocol;
+           fn out_endpoint_address(&self) -> Option<EndpointAddress>;
+           fn in_endpoint_address(&self) -> EndpointAddress;
+           fn endpoint_out(&mut self);
         }
     }
 
+    fn endpoint_in_complete(&self, addr: EndpointAddress) {
+        self.inner.endpoint_in_complete(addr);
+        self.flush().ok();
+    }
+    fn poll(&self) {
+        self.flush().ok();
+    }
     fn reset(&mut self) {
         self.inner.reset();
         self.idle_manager.borrow_mut().reset();
     }
+    fn set_protocol(&mut self, protocol: HidProtocol) -> usb_device::Result<()> {
+        let layout = self.inner.report_layout();
+        self.inner.set_protocol(protocol)?;
+        if self.inner.report_layout() != layout {
+            //The last report hasn't been sent in the new layout
+            self.idle_manager.borrow_mut().report_lost();
+        }
+        Ok(())
+    }
     fn set_idle(&mut self, report_id: u8, value: u8) {
         self.inner.set_idle(report_id, value);
-        if report_id == 0 {
-            self.idle_manager
-                .borrow_mut()
-                .set_duration(self.inner.global_idle());
-        }
+        //A report ID of 0 sets the idle rate of all reports and clears the per report idle rates
+        let duration = self
+            .report_id
+            .and_then(|id| self.inner.report_idle(id))
+            .unwrap_or_else(|| self.inner.global_idle());
+        self.idle_manager.borrow_mut().set_duration(duration);
     }
 }
 
-impl<'a, B: UsbBus, R> WrappedInterface<'a, B, RawInterface<'a, B>, ()>
-    for ManagedInterface<'a, B, R>
+impl<
+        'a,
+        B: UsbBus,
+        I,
+        O,
+        const LEN: usize,
+        const N: usize,
+        const OUT_QUEUE: usize,
+        const IN_QUEUE: usize,
+    > WrappedInterface<'a, B, RawInterface<'a, B, OUT_QUEUE, IN_QUEUE>, TransmitPolicy>
+    for ManagedInterface<'a, B, I, O, N, OUT_QUEUE, IN_QUEUE>
 where
-    R: Copy + Eq,
+    I: Copy + Eq + PackedStruct<ByteArray = [u8; LEN]>,
 {
-    fn new(interface: RawInterface<'a, B>, _config: ()) -> Self {
-        let default_idle = interface.global_idle();
+    fn new(interface: RawInterface<'a, B, OUT_QUEUE, IN_QUEUE>, policy: TransmitPolicy) -> Self {
+        let mut idle_manager = IdleManager::new(interface.global_idle());
+        idle_manager.set_transmit_policy(policy);
         Self {
             inner: interface,
-            idle_manager: RefCell::new(IdleManager::new(default_idle)),
+            idle_manager: RefCell::new(idle_manager),
+            boot_report_len: None,
+            report_id: None,
+            report_queue: RefCell::new(None),
+            output: Default::default(),
         }
     }
 }
 
-pub struct ManagedInterfaceConfig<'a, R> {
-    report: PhantomData<R>,
-    inner_config: RawInterfaceConfig<'a>,
+pub struct ManagedInterfaceConfig<
+    'a,
+    I,
+    O = (),
+    const N: usize = 0,
+    const OUT_QUEUE: usize = 0,
+    const IN_QUEUE: usize = 0,
+> {
+    report: PhantomData<(I, O)>,
+    pub(crate) inner_config: RawInterfaceConfig<'a, OUT_QUEUE, IN_QUEUE>,
+    transmit_policy: TransmitPolicy,
+    boot_report_len: Option<usize>,
+    report_id: Option<u8>,
+    report_queue: Option<ReportConsumer<'a, I, N>>,
 }
 
-impl<'a, R> ManagedInterfaceConfig<'a, R> {
-    pub fn new(inner_config: RawInterfaceConfig<'a>) -> Self {
+impl<'a, I, O, const OUT_QUEUE: usize, const IN_QUEUE: usize>
+    ManagedInterfaceConfig<'a, I, O, 0, OUT_QUEUE, IN_QUEUE>
+{
+    pub fn new(inner_config: RawInterfaceConfig<'a, OUT_QUEUE, IN_QUEUE>) -> Self {
         Self {
             inner_config,
             report: Default::default(),
+            transmit_policy: Default::default(),
+            boot_report_len: None,
+            report_id: None,
+            report_queue: None,
+        }
+    }
+
+    /// Write the input reports enqueued with the [`ReportProducer`](crate::interface::queue::ReportProducer)
+    /// of `consumer`'s queue while the class is polled
+    ///
+    /// Reports are written in order as the IN endpoint has space, a report equal to the last
+    /// report written is dropped. The queue is drained whenever the device is polled with an event
+    /// and on every [`ManagedInterface::tick`], as the USB interrupt may not fire while the IN
+    /// endpoint is idle. See [`queue`](crate::interface::queue).
+    pub fn report_queue<const M: usize>(
+        self,
+        consumer: ReportConsumer<'a, I, M>,
+    ) -> ManagedInterfaceConfig<'a, I, O, M, OUT_QUEUE, IN_QUEUE> {
+        ManagedInterfaceConfig {
+            report: self.report,
+            inner_config: self.inner_config,
+            transmit_policy: self.transmit_policy,
+            boot_report_len: self.boot_report_len,
+            report_id: self.report_id,
+            report_queue: Some(consumer),
         }
     }
 }
 
-impl<'a, B, R> UsbAllocatable<'a, B> for ManagedInterfaceConfig<'a, R>
+impl<'a, I, O, const N: usize, const OUT_QUEUE: usize, const IN_QUEUE: usize>
+    ManagedInterfaceConfig<'a, I, O, N, OUT_QUEUE, IN_QUEUE>
+{
+    pub fn transmit_policy(mut self, policy: TransmitPolicy) -> Self {
+        self.transmit_policy = policy;
+        self
+    }
+
+    /// Request a remote wakeup whenever a changed report is written, see
+    /// [`RawInterfaceBuilder::wake_on_report`](crate::interface::raw::RawInterfaceBuilder::wake_on_report)
+    pub fn wake_on_report(mut self, wake: bool) -> Self {
+        self.inner_config.wake_on_report = wake;
+        self
+    }
+
+    /// Send only the first `len` bytes of each report while the host has selected boot protocol
+    ///
+    /// For reports starting with their boot report, the interface must also have a boot report
+    /// descriptor, see [`RawInterfaceBuilder::boot_report_descriptor`](crate::interface::raw::RawInterfaceBuilder::boot_report_descriptor).
+    pub fn boot_report_len(mut self, len: usize) -> Self {
+        self.boot_report_len = Some(len);
+        self
+    }
+
+    /// Report ID of the input reports, whose idle rate is set by `SET_IDLE` requests for this
+    /// report ID as well as for all reports
+    pub fn report_id(mut self, report_id: u8) -> Self {
+        self.report_id = Some(report_id);
+        self
+    }
+}
+
+impl<
+        'a,
+        B,
+        I,
+        O,
+        const LEN: usize,
+        const N: usize,
+        const OUT_QUEUE: usize,
+        const IN_QUEUE: usize,
+    > UsbAllocatable<'a, B> for ManagedInterfaceConfig<'a, I, O, N, OUT_QUEUE, IN_QUEUE>
 where
     B: UsbBus + 'a,
-    R: Copy + Eq,
+    I: Copy + Eq + PackedStruct<ByteArray = [u8; LEN]>,
 {
-    type Allocated = ManagedInterface<'a, B, R>;
+    type Allocated = ManagedInterface<'a, B, I, O, N, OUT_QUEUE, IN_QUEUE>;
 
     fn allocate(self, usb_alloc: &'a UsbBusAllocator<B>) -> Self::Allocated {
-        ManagedInterface::new(self.inner_config.allocate(usb_alloc), ())
+        let mut interface =
+            ManagedInterface::new(self.inner_config.allocate(usb_alloc), self.transmit_policy);
+        interface.boot_report_len = self.boot_report_len;
+        interface.report_id = self.report_id;
+        interface.report_queue = RefCell::new(self.report_queue);
+        interface
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use fugit::ExtU32;
+
+    use crate::interface::managed::{IdleManager, TransmitPolicy};
+
+    fn ticks_until_resend(idle_manager: &mut IdleManager<u8>) -> Option<u32> {
+        (1..=1000).find(|_| idle_manager.tick())
+    }
+
+    #[test]
+    fn host_idle_resends_at_idle_rate() {
+        let mut idle_manager = IdleManager::new(20.millis());
+        idle_manager.report_written(1);
+        assert_eq!(ticks_until_resend(&mut idle_manager), Some(20));
+    }
+
+    #[test]
+    fn host_idle_retries_until_resent() {
+        let mut idle_manager = IdleManager::new(4.millis());
+        idle_manager.report_written(1);
+        assert_eq!(ticks_until_resend(&mut idle_manager), Some(4));
+        assert!(idle_manager.tick());
+        idle_manager.report_written(1);
+        assert_eq!(ticks_until_resend(&mut idle_manager), Some(4));
+    }
+
+    #[test]
+    fn changed_report_restarts_idle_timer() {
+        let mut idle_manager = IdleManager::new(4.millis());
+        idle_manager.report_written(1);
+        assert!(!idle_manager.tick());
+        assert!(!idle_manager.tick());
+        idle_manager.report_written(2);
+        assert_eq!(ticks_until_resend(&mut idle_manager), Some(4));
+    }
+
+    #[test]
+    fn tick_with_elapsed_time() {
+        let mut idle_manager = IdleManager::new(20.millis());
+        idle_manager.report_written(1);
+        assert!(!idle_manager.tick_with(15.millis()));
+        assert!(idle_manager.tick_with(15.millis()));
+        idle_manager.report_written(1);
+        assert!(!idle_manager.tick_with(19.millis()));
+        assert!(idle_manager.tick_with(1.millis()));
+        assert!(idle_manager.tick_with(u32::MAX.millis()));
+    }
+
+    #[test]
+    fn zero_idle_never_resends() {
+        let mut idle_manager = IdleManager::new(0.millis());
+        idle_manager.report_written(1);
+        assert_eq!(ticks_until_resend(&mut idle_manager), None);
+        assert!(idle_manager.is_duplicate(&1));
+        assert!(!idle_manager.is_duplicate(&2));
+    }
+
+    #[test]
+    fn on_change_resends_at_keepalive() {
+        let mut idle_manager = IdleManager::new(20.millis());
+        idle_manager.set_transmit_policy(TransmitPolicy::OnChange {
+            keepalive: Some(100.millis()),
+        });
+        idle_manager.report_written(1);
+        assert_eq!(ticks_until_resend(&mut idle_manager), Some(100));
+    }
+
+    #[test]
+    fn on_change_without_keepalive_never_resends() {
+        let mut idle_manager = IdleManager::new(20.millis());
+        idle_manager.set_transmit_policy(TransmitPolicy::OnChange { keepalive: None });
+        idle_manager.report_written(1);
+        assert_eq!(ticks_until_resend(&mut idle_manager), None);
+        assert!(idle_manager.is_duplicate(&1));
+    }
+
+    #[cfg(feature = "simulation")]
+    #[test]
+    fn idle_rate_of_report_id() {
+        use packed_struct::prelude::*;
+        use usb_device::bus::UsbBusAllocator;
+
+        use crate::hid_class::UsbPacketSize;
+        use crate::interface::duration::PollInterval;
+        use crate::interface::managed::{ManagedInterface, ManagedInterfaceConfig};
+        use crate::interface::raw::RawInterfaceBuilder;
+        use crate::prelude::UsbHidClassBuilder;
+        use crate::simulation::{control_transfer, device_builder, Response, SimBus};
+
+        #[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PackedStruct)]
+        #[packed_struct(endian = "lsb", size_bytes = "2")]
+        struct NumberedReport {
+            #[packed_field]
+            id: u8,
+            #[packed_field]
+            value: u8,
+        }
+
+        type Interface<'a> = ManagedInterface<'a, SimBus, NumberedReport>;
+
+        let usb_alloc = UsbBusAllocator::new(SimBus::new());
+        let mut class = UsbHidClassBuilder::new()
+            .add_interface(
+                ManagedInterfaceConfig::<NumberedReport>::new(
+                    RawInterfaceBuilder::new(&[0x05, 0x01])
+                        .in_endpoint(UsbPacketSize::Bytes8, PollInterval::millis(20))
+                        .build()
+                        .unwrap(),
+                )
+                .report_id(2),
+            )
+            .build(&usb_alloc)
+            .unwrap();
+        let mut usb_dev = device_builder(&usb_alloc, 64).build();
+
+        // SET_IDLE 4ms for report ID 3, then 8ms for report ID 2
+        for (report_id, value) in [(3, 1), (2, 2)] {
+            assert_eq!(
+                control_transfer(
+                    &mut usb_dev,
+                    &mut [&mut class],
+                    [0x21, 0x0A, report_id, value, 0x00, 0x00, 0x00, 0x00],
+                    &[]
+                ),
+                Response::Ack
+            );
+        }
+
+        // GET_IDLE per report ID
+        for (report_id, idle) in [(0, 0), (2, 2), (3, 1)] {
+            assert_eq!(
+                control_transfer(
+                    &mut usb_dev,
+                    &mut [&mut class],
+                    [0xA1, 0x02, report_id, 0x00, 0x00, 0x00, 0x01, 0x00],
+                    &[]
+                ),
+                Response::Data(vec![idle])
+            );
+        }
+
+        let interface = class.interface::<Interface<'_>, _>();
+        interface
+            .write_report(&NumberedReport { id: 2, value: 1 })
+            .unwrap();
+        assert_eq!(usb_dev.bus().host_take_in(1).unwrap(), [2, 1]);
+        let resend = (1..=100).find(|_| {
+            interface.tick().unwrap();
+            usb_dev.bus().host_take_in(1).is_some()
+        });
+        assert_eq!(resend, Some(8));
+
+        // SET_IDLE indefinite for all reports clears the idle rate of report ID 2
+        assert_eq!(
+            control_transfer(
+                &mut usb_dev,
+                &mut [&mut class],
+                [0x21, 0x0A, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00],
+                &[]
+            ),
+            Response::Ack
+        );
+        let interface = class.interface::<Interface<'_>, _>();
+        let resend = (1..=100).find(|_| {
+            interface.tick().unwrap();
+            usb_dev.bus().host_take_in(1).is_some()
+        });
+        assert_eq!(resend, None);
+    }
+
+    #[cfg(feature = "simulation")]
+    #[test]
+    fn queued_reports_written_when_polled() {
+        use usb_device::bus::UsbBusAllocator;
+
+        use crate::device::keyboard::{BootKeyboardReport, BOOT_KEYBOARD_REPORT_DESCRIPTOR};
+        use crate::hid_class::UsbPacketSize;
+        use crate::interface::duration::PollInterval;
+        use crate::interface::managed::{ManagedInterface, ManagedInterfaceConfig};
+        use crate::interface::queue::ReportQueue;
+        use crate::interface::raw::RawInterfaceBuilder;
+        use crate::page::Keyboard;
+        use crate::prelude::UsbHidClassBuilder;
+        use crate::simulation::{device_builder, SimBus, SimHost};
+
+        type Interface<'a> = ManagedInterface<'a, SimBus, BootKeyboardReport, (), 4>;
+
+        let mut queue = ReportQueue::<BootKeyboardReport, 4>::new();
+        let (mut producer, consumer) = queue.split();
+        let usb_alloc = UsbBusAllocator::new(SimBus::new());
+        let mut class = UsbHidClassBuilder::new()
+            .add_interface(
+                ManagedInterfaceConfig::<BootKeyboardReport>::new(
+                    RawInterfaceBuilder::new(BOOT_KEYBOARD_REPORT_DESCRIPTOR)
+                        .in_endpoint(UsbPacketSize::Bytes8, PollInterval::millis(10))
+                        .build()
+                        .unwrap(),
+                )
+                .report_queue(consumer),
+            )
+            .build(&usb_alloc)
+            .unwrap();
+        let mut usb_dev = device_builder(&usb_alloc, 8).build();
+        let host = SimHost::enumerate(&mut usb_dev, &mut [&mut class]).unwrap();
+
+        let press = BootKeyboardReport::new([Keyboard::A]);
+        let release = BootKeyboardReport::default();
+        for report in [press, release, release] {
+            producer.enqueue(report).unwrap();
+        }
+        assert!(producer.enqueue(press).is_err());
+        assert_eq!(class.interface::<Interface<'_>, _>().queued_reports(), 3);
+
+        // The first report is written on the tick, the next as the host reads each report
+        class.interface::<Interface<'_>, _>().tick().unwrap();
+        assert_eq!(
+            host.read_report(&mut usb_dev, &mut [&mut class], 0),
+            Some(vec![0, 0, 0x04, 0, 0, 0, 0, 0])
+        );
+        assert_eq!(
+            host.read_report(&mut usb_dev, &mut [&mut class], 0),
+            Some(vec![0; 8])
+        );
+        // The repeated release is a duplicate and is dropped
+        assert_eq!(class.interface::<Interface<'_>, _>().queued_reports(), 0);
+        assert_eq!(host.read_report(&mut usb_dev, &mut [&mut class], 0), None);
+    }
+
+    #[cfg(feature = "simulation")]
+    #[test]
+    fn input_report_queue_flushed_as_host_reads() {
+        use usb_device::bus::UsbBusAllocator;
+
+        use crate::device::keyboard::{BootKeyboardReport, BOOT_KEYBOARD_REPORT_DESCRIPTOR};
+        use crate::hid_class::UsbPacketSize;
+        use crate::interface::duration::PollInterval;
+        use crate::interface::managed::{ManagedInterface, ManagedInterfaceConfig};
+        use crate::interface::raw::RawInterfaceBuilder;
+        use crate::page::Keyboard;
+        use crate::prelude::UsbHidClassBuilder;
+        use crate::simulation::{device_builder, SimBus, SimHost};
+        use crate::UsbHidError;
+
+        type Interface<'a> = ManagedInterface<'a, SimBus, BootKeyboardReport, (), 0, 0, 4>;
+
+        let usb_alloc = UsbBusAllocator::new(SimBus::new());
+        let mut class = UsbHidClassBuilder::new()
+            .add_interface(
+                ManagedInterfaceConfig::<BootKeyboardReport, (), 0, 0, 4>::new(
+                    RawInterfaceBuilder::new(BOOT_KEYBOARD_REPORT_DESCRIPTOR)
+                        .in_endpoint(UsbPacketSize::Bytes8, PollInterval::millis(10))
+                        .in_report_queue::<4>()
+                        .build()
+                        .unwrap(),
+                ),
+            )
+            .build(&usb_alloc)
+            .unwrap();
+        let mut usb_dev = device_builder(&usb_alloc, 8).build();
+        let host = SimHost::enumerate(&mut usb_dev, &mut [&mut class]).unwrap();
+
+        // A macro key's press and release are both written in one scan cycle
+        let interface = class.interface::<Interface<'_>, _>();
+        interface
+            .write_report(&BootKeyboardReport::new([Keyboard::A]))
+            .unwrap();
+        interface
+            .write_report(&BootKeyboardReport::default())
+            .unwrap();
+        assert_eq!(interface.queued_input_reports(), 1);
+        assert!(matches!(interface.flush(), Err(UsbHidError::WouldBlock)));
+
+        assert_eq!(
+            host.read_report(&mut usb_dev, &mut [&mut class], 0),
+            Some(vec![0, 0, 0x04, 0, 0, 0, 0, 0])
+        );
+        assert_eq!(
+            host.read_report(&mut usb_dev, &mut [&mut class], 0),
+            Some(vec![0; 8])
+        );
+        assert_eq!(
+            class.interface::<Interface<'_>, _>().queued_input_reports(),
+            0
+        );
+        class.interface::<Interface<'_>, _>().flush().unwrap();
+        assert_eq!(host.read_report(&mut usb_dev, &mut [&mut class], 0), None);
+    }
+
+    #[cfg(feature = "simulation")]
+    #[test]
+    fn output_reports_read_from_control_and_out_endpoint() {
+        use usb_device::bus::UsbBusAllocator;
+
+        use crate::device::keyboard::{
+            BootKeyboardReport, KeyboardLedsReport, BOOT_KEYBOARD_REPORT_DESCRIPTOR,
+        };
+        use crate::hid_class::UsbPacketSize;
+        use crate::interface::duration::PollInterval;
+        use crate::interface::managed::{ManagedInterface, ManagedInterfaceConfig};
+        use crate::interface::raw::RawInterfaceBuilder;
+        use crate::prelude::UsbHidClassBuilder;
+        use crate::simulation::{control_transfer, device_builder, Response, SimBus};
+        use crate::UsbHidError;
+
+        type Interface<'a> = ManagedInterface<'a, SimBus, BootKeyboardReport, KeyboardLedsReport>;
+
+        let usb_alloc = UsbBusAllocator::new(SimBus::new());
+        let mut class = UsbHidClassBuilder::new()
+            .add_interface(ManagedInterfaceConfig::<
+                BootKeyboardReport,
+                KeyboardLedsReport,
+            >::new(
+                RawInterfaceBuilder::new(BOOT_KEYBOARD_REPORT_DESCRIPTOR)
+                    .in_endpoint(UsbPacketSize::Bytes8, PollInterval::millis(10))
+                    .with_out_endpoint(UsbPacketSize::Bytes8, PollInterval::millis(10))
+                    .build()
+                    .unwrap(),
+            ))
+            .build(&usb_alloc)
+            .unwrap();
+        let mut usb_dev = device_builder(&usb_alloc, 64).build();
+
+        assert!(matches!(
+            class.interface::<Interface<'_>, _>().read_output(),
+            Err(UsbHidError::WouldBlock)
+        ));
+
+        // SET_REPORT(Output), caps lock
+        assert_eq!(
+            control_transfer(
+                &mut usb_dev,
+                &mut [&mut class],
+                [0x21, 0x09, 0x00, 0x02, 0x00, 0x00, 0x01, 0x00],
+                &[0x02]
+            ),
+            Response::Ack
+        );
+        assert_eq!(
+            class.interface::<Interface<'_>, _>().read_output().unwrap(),
+            KeyboardLedsReport {
+                caps_lock: true,
+                ..Default::default()
+            }
+        );
+
+        // Output report on the OUT endpoint, num lock
+        usb_dev.bus().host_out(1, &[0x01]);
+        usb_dev.poll(&mut [&mut class]);
+        let interface = class.interface::<Interface<'_>, _>();
+        assert_eq!(
+            interface.read_output().unwrap(),
+            KeyboardLedsReport {
+                num_lock: true,
+                ..Default::default()
+            }
+        );
+        assert!(matches!(
+            interface.read_output(),
+            Err(UsbHidError::WouldBlock)
+        ));
+
+        // A report longer than the output type does not fit
+        assert_eq!(
+            control_transfer(
+                &mut usb_dev,
+                &mut [&mut class],
+                [0x21, 0x09, 0x00, 0x02, 0x00, 0x00, 0x02, 0x00],
+                &[0x01, 0x02]
+            ),
+            Response::Ack
+        );
+        assert!(matches!(
+            class.interface::<Interface<'_>, _>().read_output(),
+            Err(UsbHidError::ReportTooLong)
+        ));
     }
 }
@@ -1,16 +1,53 @@
 //! Abstract Human Interface Device Interfaces
 use core::marker::PhantomData;
-use frunk::{HCons, HNil, ToRef};
+#[cfg(feature = "frunk")]
+use frunk::hlist::HList;
+#[cfg(feature = "frunk")]
+use frunk::{HCons, HNil};
+use heapless::Vec;
 use packed_struct::prelude::*;
 use usb_device::bus::{InterfaceNumber, StringIndex, UsbBus, UsbBusAllocator};
 use usb_device::class_prelude::DescriptorWriter;
+use usb_device::endpoint::EndpointAddress;
 
 use crate::hid_class::descriptor::{
-    DescriptorType, HidProtocol, COUNTRY_CODE_NOT_SUPPORTED, SPEC_VERSION_1_11,
+    DescriptorType, HidProtocol, ReportType, COUNTRY_CODE_NOT_SUPPORTED, SPEC_VERSION_1_11,
 };
+use crate::hid_class::InterfaceAssociation;
 
+pub mod duration;
+pub mod dynamic;
+pub mod feature;
+pub mod latency;
 pub mod managed;
+pub mod packet;
+pub mod queue;
 pub mod raw;
+pub mod report_id;
+pub mod settings;
+pub mod tuple;
+#[cfg(feature = "async")]
+pub(crate) mod waker;
+pub mod watchdog;
+
+/// Maximum number of class descriptors listed in a HID descriptor besides the report descriptor
+pub const MAX_CLASS_DESCRIPTORS: usize = 4;
+/// Maximum length of a HID descriptor body, see [`InterfaceClass::hid_descriptor_body`]
+pub const MAX_HID_DESCRIPTOR_BODY_LEN: usize = 7 + 3 * MAX_CLASS_DESCRIPTORS;
+
+/// HID descriptor following its length and descriptor type
+pub type HidDescriptorBodyBytes = Vec<u8, MAX_HID_DESCRIPTOR_BODY_LEN>;
+
+/// Class descriptor, such as a Physical descriptor, listed in an interface's HID descriptor after
+/// the report descriptor
+///
+/// The host reads class descriptors with `GET_DESCRIPTOR` requests for their type, the
+/// descriptor index selecting between descriptors of the same type in the order they are listed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ClassDescriptor<'a> {
+    pub descriptor_type: DescriptorType,
+    pub data: &'a [u8],
+}
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq, PackedStruct)]
 #[packed_struct(endian = "lsb", size_bytes = 7)]
@@ -23,11 +60,72 @@ pub struct HidDescriptorBody {
     descriptor_length: u16,
 }
 
+/// Fields of an interface's HID descriptor set by the application, see
+/// [`RawInterfaceBuilder::hid_descriptor`](raw::RawInterfaceBuilder::hid_descriptor)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct HidDescriptorConfig {
+    /// Release of the HID specification the interface complies with, in BCD
+    pub bcd_hid: u16,
+    /// Country of localized hardware, [`COUNTRY_CODE_NOT_SUPPORTED`] if not localized
+    pub country_code: u8,
+}
+
+impl HidDescriptorConfig {
+    pub const DEFAULT: Self = Self {
+        bcd_hid: SPEC_VERSION_1_11,
+        country_code: COUNTRY_CODE_NOT_SUPPORTED,
+    };
+
+    /// HID descriptor body listing a report descriptor of `report_descriptor_len` bytes followed
+    /// by `class_descriptors`
+    pub fn body(
+        &self,
+        report_descriptor_len: usize,
+        class_descriptors: &[ClassDescriptor<'_>],
+    ) -> HidDescriptorBodyBytes {
+        if report_descriptor_len > u16::MAX as usize {
+            panic!("Report descriptor too long");
+        }
+        if class_descriptors.len() > MAX_CLASS_DESCRIPTORS {
+            panic!("Too many class descriptors");
+        }
+        let header = HidDescriptorBody {
+            bcd_hid: self.bcd_hid,
+            country_code: self.country_code,
+            num_descriptors: 1 + class_descriptors.len() as u8,
+            descriptor_type: DescriptorType::Report,
+            descriptor_length: report_descriptor_len as u16,
+        }
+        .pack()
+        .map_err(drop) // Avoid pulling all the core::fmt code into final binary
+        .expect("Failed to pack HidDescriptor");
+
+        let mut body = Vec::new();
+        // Capacity is checked above
+        let _ = body.extend_from_slice(&header);
+        for descriptor in class_descriptors {
+            if descriptor.data.len() > u16::MAX as usize {
+                panic!("Class descriptor too long");
+            }
+            let [lsb, msb] = (descriptor.data.len() as u16).to_le_bytes();
+            let _ = body.extend_from_slice(&[descriptor.descriptor_type as u8, lsb, msb]);
+        }
+        body
+    }
+}
+
+impl Default for HidDescriptorConfig {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
 pub trait UsbAllocatable<'a, B: UsbBus> {
     type Allocated;
     fn allocate(self, usb_alloc: &'a UsbBusAllocator<B>) -> Self::Allocated;
 }
 
+#[cfg(feature = "frunk")]
 impl<'a, B: UsbBus + 'a> UsbAllocatable<'a, B> for HNil {
     type Allocated = HNil;
 
@@ -36,6 +134,7 @@ impl<'a, B: UsbBus + 'a> UsbAllocatable<'a, B> for HNil {
     }
 }
 
+#[cfg(feature = "frunk")]
 impl<'a, B, C, Tail> UsbAllocatable<'a, B> for HCons<C, Tail>
 where
     B: UsbBus + 'a,
@@ -54,44 +153,110 @@ where
 
 pub trait InterfaceClass<'a> {
     fn report_descriptor(&self) -> &'_ [u8];
+    /// Report descriptor in static memory, which is sent without copying to the control buffer
+    ///
+    /// Report descriptors longer than the usb-device control buffer can only be sent from static
+    /// memory.
+    fn static_report_descriptor(&self) -> Option<&'static [u8]> {
+        None
+    }
     fn id(&self) -> InterfaceNumber;
     fn write_descriptors(&self, writer: &mut DescriptorWriter) -> usb_device::Result<()>;
     fn get_string(&self, index: StringIndex, _lang_id: u16) -> Option<&'_ str>;
     fn reset(&mut self);
     fn set_report(&mut self, data: &[u8]) -> usb_device::Result<()>;
     fn get_report(&mut self, data: &mut [u8]) -> usb_device::Result<usize>;
+    /// Report for a `GET_REPORT` request of `report_type` and `report_id`
+    ///
+    /// Defaults to [`InterfaceClass::get_report`], the last input report, for interfaces without
+    /// feature reports or report IDs
+    fn get_report_by_id(
+        &mut self,
+        report_type: ReportType,
+        report_id: u8,
+        data: &mut [u8],
+    ) -> usb_device::Result<usize> {
+        let _ = (report_type, report_id);
+        self.get_report(data)
+    }
+    /// Report from a `SET_REPORT` request of `report_type` and `report_id`, `data` includes the
+    /// report ID if the report has one
+    ///
+    /// Defaults to [`InterfaceClass::set_report`], for interfaces handling only output reports
+    fn set_report_by_id(
+        &mut self,
+        report_type: ReportType,
+        report_id: u8,
+        data: &[u8],
+    ) -> usb_device::Result<()> {
+        let _ = (report_type, report_id);
+        self.set_report(data)
+    }
     fn get_report_ack(&mut self) -> usb_device::Result<()>;
     fn set_idle(&mut self, report_id: u8, value: u8);
     fn get_idle(&self, report_id: u8) -> u8;
-    fn set_protocol(&mut self, protocol: HidProtocol);
+    fn set_protocol(&mut self, protocol: HidProtocol) -> usb_device::Result<()>;
     fn get_protocol(&self) -> HidProtocol;
-    fn hid_descriptor_body(&self) -> [u8; 7] {
-        let descriptor_len = self.report_descriptor().len();
-        if descriptor_len > u16::MAX as usize {
-            panic!("Report descriptor too long");
-        } else {
-            HidDescriptorBody {
-                bcd_hid: SPEC_VERSION_1_11,
-                country_code: COUNTRY_CODE_NOT_SUPPORTED,
-                num_descriptors: 1,
-                descriptor_type: DescriptorType::Report,
-                descriptor_length: descriptor_len as u16,
-            }
-            .pack()
-            .map_err(drop) // Avoid pulling all the core::fmt code into final binary
-            .expect("Failed to pack HidDescriptor")
-        }
+    fn out_endpoint_address(&self) -> Option<EndpointAddress>;
+    fn in_endpoint_address(&self) -> EndpointAddress;
+    /// The host has read the data written to the IN endpoint `addr`
+    fn endpoint_in_complete(&self, addr: EndpointAddress);
+    /// The host has written an output report to the interface's OUT endpoint
+    ///
+    /// Interfaces that consume output reports as they arrive read them here, others leave them
+    /// for the application to read.
+    fn endpoint_out(&mut self) {}
+    /// The device has been polled, after the endpoint events of the poll were handled
+    fn poll(&self) {}
+    /// A USB frame started, see [`UsbHidClass::start_of_frame`](crate::hid_class::UsbHidClass::start_of_frame)
+    ///
+    /// `frame_number` is the 11 bit frame number. Interfaces can use it to schedule reports on
+    /// frame boundaries.
+    fn start_of_frame(&mut self, frame_number: u16) {
+        let _ = frame_number;
+    }
+    /// Body of the interface's HID descriptor, following the length and descriptor type
+    fn hid_descriptor_body(&self) -> HidDescriptorBodyBytes {
+        HidDescriptorConfig::DEFAULT.body(self.report_descriptor().len(), &[])
+    }
+    /// Class descriptor of `descriptor_type` listed in the HID descriptor, `index` selecting
+    /// between descriptors of the same type
+    fn class_descriptor(&self, descriptor_type: u8, index: u8) -> Option<&'_ [u8]> {
+        let _ = (descriptor_type, index);
+        None
+    }
+    /// Whether the interface has written a report that should wake a suspended host since the
+    /// last call, the request is cleared by this call
+    fn take_wakeup_request(&self) -> bool {
+        false
     }
 }
 
-pub trait InterfaceHList<'a>: ToRef<'a> {
+/// Interfaces of a [`UsbHidClass`](crate::hid_class::UsbHidClass), an HList of interfaces built
+/// by the [`UsbHidClassBuilder`](crate::hid_class::UsbHidClassBuilder) or
+/// [`DynamicInterfaces`](dynamic::DynamicInterfaces) chosen at runtime
+pub trait InterfaceHList<'a> {
     fn get_id_mut(&mut self, id: u8) -> Option<&mut dyn InterfaceClass<'a>>;
     fn get_id(&self, id: u8) -> Option<&dyn InterfaceClass<'a>>;
+    fn get_out_endpoint_id(&self, addr: EndpointAddress) -> Option<u8>;
+    fn get_in_endpoint_id(&self, addr: EndpointAddress) -> Option<u8>;
+    fn endpoint_in_complete(&self, addr: EndpointAddress);
+    fn poll(&self);
+    fn start_of_frame(&mut self, frame_number: u16);
     fn reset(&mut self);
-    fn write_descriptors(&self, writer: &mut DescriptorWriter) -> usb_device::Result<()>;
+    /// Whether any interface requested a remote wakeup, clearing the requests of all interfaces
+    fn take_wakeup_request(&self) -> bool;
+    /// Writes the interfaces' descriptors, preceding each group of `associations` with an
+    /// Interface Association Descriptor
+    fn write_descriptors(
+        &self,
+        writer: &mut DescriptorWriter,
+        associations: &[InterfaceAssociation],
+    ) -> usb_device::Result<()>;
     fn get_string(&self, index: StringIndex, lang_id: u16) -> Option<&'_ str>;
 }
 
+#[cfg(feature = "frunk")]
 impl<'a> InterfaceHList<'a> for HNil {
     #[inline(always)]
     fn get_id_mut(&mut self, _: u8) -> Option<&mut dyn InterfaceClass<'a>> {
@@ -102,9 +267,31 @@ impl<'a> InterfaceHList<'a> for HNil {
         None
     }
     #[inline(always)]
+    fn get_out_endpoint_id(&self, _: EndpointAddress) -> Option<u8> {
+        None
+    }
+    #[inline(always)]
+    fn get_in_endpoint_id(&self, _: EndpointAddress) -> Option<u8> {
+        None
+    }
+    #[inline(always)]
+    fn endpoint_in_complete(&self, _: EndpointAddress) {}
+    #[inline(always)]
+    fn poll(&self) {}
+    #[inline(always)]
+    fn start_of_frame(&mut self, _: u16) {}
+    #[inline(always)]
     fn reset(&mut self) {}
     #[inline(always)]
-    fn write_descriptors(&self, _: &mut DescriptorWriter) -> usb_device::Result<()> {
+    fn take_wakeup_request(&self) -> bool {
+        false
+    }
+    #[inline(always)]
+    fn write_descriptors(
+        &self,
+        _: &mut DescriptorWriter,
+        _: &[InterfaceAssociation],
+    ) -> usb_device::Result<()> {
         Ok(())
     }
     #[inline(always)]
@@ -113,7 +300,8 @@ impl<'a> InterfaceHList<'a> for HNil {
     }
 }
 
-impl<'a, Head: InterfaceClass<'a> + 'a, Tail: InterfaceHList<'a>> InterfaceHList<'a>
+#[cfg(feature = "frunk")]
+impl<'a, Head: InterfaceClass<'a> + 'a, Tail: InterfaceHList<'a> + HList> InterfaceHList<'a>
     for HCons<Head, Tail>
 {
     #[inline(always)]
@@ -133,14 +321,61 @@ impl<'a, Head: InterfaceClass<'a> + 'a, Tail: InterfaceHList<'a>> InterfaceHList
         }
     }
     #[inline(always)]
+    fn get_out_endpoint_id(&self, addr: EndpointAddress) -> Option<u8> {
+        if self.head.out_endpoint_address() == Some(addr) {
+            Some(u8::from(self.head.id()))
+        } else {
+            self.tail.get_out_endpoint_id(addr)
+        }
+    }
+    #[inline(always)]
+    fn get_in_endpoint_id(&self, addr: EndpointAddress) -> Option<u8> {
+        if self.head.in_endpoint_address() == addr {
+            Some(u8::from(self.head.id()))
+        } else {
+            self.tail.get_in_endpoint_id(addr)
+        }
+    }
+    #[inline(always)]
+    fn endpoint_in_complete(&self, addr: EndpointAddress) {
+        self.head.endpoint_in_complete(addr);
+        self.tail.endpoint_in_complete(addr);
+    }
+    #[inline(always)]
+    fn poll(&self) {
+        self.head.poll();
+        self.tail.poll();
+    }
+    #[inline(always)]
+    fn start_of_frame(&mut self, frame_number: u16) {
+        self.head.start_of_frame(frame_number);
+        self.tail.start_of_frame(frame_number);
+    }
+    #[inline(always)]
     fn reset(&mut self) {
         self.head.reset();
         self.tail.reset();
     }
     #[inline(always)]
-    fn write_descriptors(&self, writer: &mut DescriptorWriter) -> usb_device::Result<()> {
+    fn take_wakeup_request(&self) -> bool {
+        //Clear every interface's request, not only the first
+        let head = self.head.take_wakeup_request();
+        let tail = self.tail.take_wakeup_request();
+        head || tail
+    }
+    #[inline(always)]
+    fn write_descriptors(
+        &self,
+        writer: &mut DescriptorWriter,
+        associations: &[InterfaceAssociation],
+    ) -> usb_device::Result<()> {
+        // The head is the last interface added and the first written, so it starts any group
+        // it is the last member of
+        if let Some(association) = associations.iter().find(|a| a.last() == Tail::LEN) {
+            association.write_descriptor(writer, self.head.id())?;
+        }
         self.head.write_descriptors(writer)?;
-        self.tail.write_descriptors(writer)
+        self.tail.write_descriptors(writer, associations)
     }
     #[inline(always)]
     fn get_string(&self, index: StringIndex, lang_id: u16) -> Option<&'_ str> {
@@ -191,3 +426,11 @@ where
         I::new(self.inner_config.allocate(usb_alloc), self.config)
     }
 }
+
+/// Interface with a default configuration, added to a class by type with
+/// [`UsbHidClassBuilder::add_device`](crate::hid_class::UsbHidClassBuilder::add_device)
+pub trait DefaultConfig<'a, B: UsbBus>: InterfaceClass<'a> {
+    type Config: UsbAllocatable<'a, B, Allocated = Self>;
+
+    fn default_config() -> Self::Config;
+}
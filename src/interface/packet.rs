@@ -0,0 +1,106 @@
+//! Handling of reports at packet size boundaries
+//!
+//! Each input report is written to the IN endpoint as a single packet, so reports longer than the
+//! endpoint's maximum packet size are rejected with [`UsbError::BufferOverflow`]. A transfer ends
+//! with a packet shorter than the maximum packet size, which leaves two cases where hosts and USB
+//! backends differ, configured with a [`PacketPolicy`]:
+//!
+//! * Empty reports - some firmware sends zero length packets as a keepalive, but most hosts
+//!   discard them
+//! * Reports exactly the maximum packet size - hosts that request more than one packet from the
+//!   endpoint may wait for a zero length packet to terminate the transfer
+//!
+//! Control transfers are handled by `usb-device`, which terminates responses shorter than the
+//! host's requested length with a zero length packet. Reports returned by `GET_REPORT`, and
+//! accepted by `SET_REPORT`, are limited to [`MAX_CONTROL_REPORT_SIZE`] bytes.
+
+use usb_device::UsbError;
+
+/// Size of the buffers holding reports for `GET_REPORT` and `SET_REPORT` requests
+pub const MAX_CONTROL_REPORT_SIZE: usize = 64;
+
+/// Handling of empty input reports
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum EmptyReport {
+    /// Fail writes with [`UsbError::Unsupported`]
+    #[default]
+    Reject,
+    /// Send a zero length packet, which is not buffered for `GET_REPORT` requests
+    Send,
+}
+
+/// Handling of input reports exactly the IN endpoint's maximum packet size
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FullPacket {
+    /// Send the report alone, hosts know the report length from the report descriptor
+    #[default]
+    Unterminated,
+    /// Follow the report with a zero length packet once the host has read it
+    ZeroLengthPacket,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PacketPolicy {
+    pub empty_report: EmptyReport,
+    pub full_packet: FullPacket,
+}
+
+impl Default for PacketPolicy {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+impl PacketPolicy {
+    pub const DEFAULT: Self = Self {
+        empty_report: EmptyReport::Reject,
+        full_packet: FullPacket::Unterminated,
+    };
+
+    /// Checks a report of `len` bytes can be written to an IN endpoint, returning whether it must
+    /// be followed by a zero length packet
+    pub fn check(&self, len: usize, max_packet_size: usize) -> usb_device::Result<bool> {
+        if len == 0 {
+            match self.empty_report {
+                EmptyReport::Reject => Err(UsbError::Unsupported),
+                EmptyReport::Send => Ok(false),
+            }
+        } else if len > max_packet_size {
+            Err(UsbError::BufferOverflow)
+        } else {
+            Ok(len == max_packet_size && self.full_packet == FullPacket::ZeroLengthPacket)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use usb_device::UsbError;
+
+    use crate::interface::packet::{EmptyReport, FullPacket, PacketPolicy};
+
+    #[test]
+    fn default_policy() {
+        let policy = PacketPolicy::default();
+        assert!(matches!(policy.check(0, 8), Err(UsbError::Unsupported)));
+        assert!(matches!(policy.check(7, 8), Ok(false)));
+        assert!(matches!(policy.check(8, 8), Ok(false)));
+        assert!(matches!(policy.check(9, 8), Err(UsbError::BufferOverflow)));
+    }
+
+    #[test]
+    fn keepalive_and_terminated_full_packets() {
+        let policy = PacketPolicy {
+            empty_report: EmptyReport::Send,
+            full_packet: FullPacket::ZeroLengthPacket,
+        };
+        assert!(matches!(policy.check(0, 8), Ok(false)));
+        assert!(matches!(policy.check(7, 8), Ok(false)));
+        assert!(matches!(policy.check(8, 8), Ok(true)));
+        assert!(matches!(policy.check(64, 64), Ok(true)));
+        assert!(matches!(
+            policy.check(65, 64),
+            Err(UsbError::BufferOverflow)
+        ));
+    }
+}
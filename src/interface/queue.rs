@@ -0,0 +1,203 @@
+//! Queues of reports waiting for the application or the host
+//!
+//! Output reports are read from the OUT endpoint, or accepted from `SET_REPORT` requests, while
+//! the [`UsbHidClass`](crate::hid_class::UsbHidClass) is polled, and held until the application
+//! reads them. When the queue is full the oldest report is dropped, so the application always
+//! sees the most recent reports.
+//!
+//! Input reports are held until the IN endpoint has space, and sent in order as the host reads
+//! the endpoint. Writes fail with [`UsbError::WouldBlock`] when the queue is full, so no input
+//! report, such as a key release, is lost.
+//!
+//! Reports can also be handed to a [`ManagedInterface`] through a [`ReportQueue`], without
+//! disabling interrupts or borrowing the class. The application enqueues reports with the
+//! [`ReportProducer`], and the interface owns the [`ReportConsumer`], writing the queued reports
+//! while the class is polled.
+//!
+//! ```
+//! # #[cfg(not(feature = "usb-device-03"))] {
+//! use usb_device::bus::{UsbBus, UsbBusAllocator};
+//! use usbd_human_interface_device::device::keyboard::{
+//!     BootKeyboardReport, BOOT_KEYBOARD_REPORT_DESCRIPTOR,
+//! };
+//! use usbd_human_interface_device::hid_class::prelude::*;
+//! use usbd_human_interface_device::interface::queue::ReportQueue;
+//! use usbd_human_interface_device::prelude::*;
+//!
+//! fn setup<B: UsbBus>(usb_alloc: &UsbBusAllocator<B>) {
+//!     // Usually a static, so the producer can be moved to another task
+//!     let mut queue = ReportQueue::<BootKeyboardReport, 8>::new();
+//!     let (mut producer, consumer) = queue.split();
+//!     let mut hid = UsbHidClassBuilder::from_tuple((
+//!         ManagedInterfaceConfig::<BootKeyboardReport>::new(
+//!             RawInterfaceBuilder::new(BOOT_KEYBOARD_REPORT_DESCRIPTOR)
+//!                 .in_endpoint(UsbPacketSize::Bytes8, PollInterval::millis(10))
+//!                 .build()
+//!                 .unwrap(),
+//!         )
+//!         .report_queue(consumer),
+//!     ))
+//!     .build(usb_alloc)
+//!     .unwrap();
+//!
+//!     producer.enqueue(BootKeyboardReport::default()).ok();
+//!     // Sent on the next usb_dev.poll(&mut [&mut hid]);
+//! #   let _ = &mut hid;
+//! }
+//! # }
+//! ```
+//!
+//! [`ManagedInterface`]: crate::interface::managed::ManagedInterface
+
+use heapless::Vec;
+use usb_device::UsbError;
+
+use crate::interface::packet::MAX_CONTROL_REPORT_SIZE;
+
+/// Lock-free single producer, single consumer queue of up to `N - 1` input reports of type `R`
+pub type ReportQueue<R, const N: usize> = heapless::spsc::Queue<R, N>;
+
+/// Application side of a [`ReportQueue`], enqueues reports from thread context
+pub type ReportProducer<'q, R, const N: usize> = heapless::spsc::Producer<'q, R, N>;
+
+/// Interface side of a [`ReportQueue`], see
+/// [`ManagedInterfaceConfig::report_queue`](crate::interface::managed::ManagedInterfaceConfig::report_queue)
+pub type ReportConsumer<'q, R, const N: usize> = heapless::spsc::Consumer<'q, R, N>;
+
+/// Queue of up to `N` raw reports, oldest first, disabled and without storage if `N` is 0
+#[derive(Debug, Default)]
+pub(crate) struct RawReportQueue<const N: usize> {
+    reports: Vec<Vec<u8, MAX_CONTROL_REPORT_SIZE>, N>,
+    dropped: u32,
+}
+
+impl<const N: usize> RawReportQueue<N> {
+    pub(crate) fn is_enabled(&self) -> bool {
+        N > 0
+    }
+
+    pub(crate) fn push(&mut self, data: &[u8]) -> usb_device::Result<()> {
+        let report = Vec::from_slice(data).map_err(|_| UsbError::BufferOverflow)?;
+        if self.reports.is_full() {
+            warn!("Report queue full, dropping oldest report");
+            self.pop_front();
+            self.dropped = self.dropped.wrapping_add(1);
+        }
+        self.reports
+            .push(report)
+            .map_err(|_| UsbError::BufferOverflow)
+    }
+
+    /// Adds a report to the end of the queue, [`UsbError::WouldBlock`] if the queue is full
+    pub(crate) fn try_push(&mut self, data: &[u8]) -> usb_device::Result<()> {
+        if self.reports.is_full() {
+            return Err(UsbError::WouldBlock);
+        }
+        let report = Vec::from_slice(data).map_err(|_| UsbError::BufferOverflow)?;
+        self.reports
+            .push(report)
+            .map_err(|_| UsbError::BufferOverflow)
+    }
+
+    pub(crate) fn front(&self) -> Option<&[u8]> {
+        self.reports.first().map(|r| r.as_slice())
+    }
+
+    pub(crate) fn pop_front(&mut self) {
+        if !self.reports.is_empty() {
+            self.reports.remove(0);
+        }
+    }
+
+    /// Copies the oldest report to `data` and removes it from the queue
+    ///
+    /// The report remains queued if `data` is too short.
+    pub(crate) fn pop_into(&mut self, data: &mut [u8]) -> usb_device::Result<usize> {
+        let report = self.reports.first().ok_or(UsbError::WouldBlock)?;
+        let n = report.len();
+        data.get_mut(..n)
+            .ok_or(UsbError::BufferOverflow)?
+            .copy_from_slice(report);
+        self.pop_front();
+        Ok(n)
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.reports.len()
+    }
+
+    pub(crate) fn dropped(&self) -> u32 {
+        self.dropped
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.reports.clear();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use usb_device::UsbError;
+
+    use crate::interface::packet::MAX_CONTROL_REPORT_SIZE;
+    use crate::interface::queue::RawReportQueue;
+
+    #[test]
+    fn oldest_report_dropped_when_full() {
+        let mut queue = RawReportQueue::<2>::default();
+        let mut data = [0; 2];
+        assert!(matches!(
+            queue.pop_into(&mut data),
+            Err(UsbError::WouldBlock)
+        ));
+
+        queue.push(&[1]).unwrap();
+        queue.push(&[2, 2]).unwrap();
+        queue.push(&[3]).unwrap();
+        assert_eq!(queue.len(), 2);
+        assert_eq!(queue.dropped(), 1);
+
+        assert!(matches!(
+            queue.pop_into(&mut [0; 1]),
+            Err(UsbError::BufferOverflow)
+        ));
+        assert_eq!(queue.pop_into(&mut data).unwrap(), 2);
+        assert_eq!(data, [2, 2]);
+        assert_eq!(queue.pop_into(&mut data).unwrap(), 1);
+        assert_eq!(data[0], 3);
+        assert!(matches!(
+            queue.pop_into(&mut data),
+            Err(UsbError::WouldBlock)
+        ));
+    }
+
+    #[test]
+    fn full_queue_rejects_report() {
+        let mut queue = RawReportQueue::<2>::default();
+        queue.try_push(&[1]).unwrap();
+        queue.try_push(&[2]).unwrap();
+        assert!(matches!(queue.try_push(&[3]), Err(UsbError::WouldBlock)));
+        assert_eq!(queue.dropped(), 0);
+
+        assert_eq!(queue.front(), Some(&[1][..]));
+        queue.pop_front();
+        assert_eq!(queue.front(), Some(&[2][..]));
+        queue.try_push(&[3]).unwrap();
+        assert_eq!(queue.len(), 2);
+    }
+
+    #[test]
+    fn depth_limited() {
+        assert!(!RawReportQueue::<0>::default().is_enabled());
+        // Disabled queues have no report storage
+        assert!(core::mem::size_of::<RawReportQueue<0>>() < MAX_CONTROL_REPORT_SIZE);
+        assert!(RawReportQueue::<0>::default().try_push(&[1]).is_err());
+        let mut queue = RawReportQueue::<8>::default();
+        for i in 0..10 {
+            queue.push(&[i]).unwrap();
+        }
+        assert_eq!(queue.len(), 8);
+        assert_eq!(queue.dropped(), 2);
+        assert!(queue.push(&[0; 65]).is_err());
+    }
+}
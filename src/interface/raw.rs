@@ -1,46 +1,136 @@
 use crate::hid_class::descriptor::{
-    DescriptorType, HidProtocol, InterfaceProtocol, InterfaceSubClass, USB_CLASS_HID,
+    DescriptorType, HidProtocol, InterfaceProtocol, InterfaceSubClass, ReportType, USB_CLASS_HID,
 };
-use crate::hid_class::{BuilderResult, UsbHidBuilderError, UsbPacketSize};
-use crate::interface::{InterfaceClass, UsbAllocatable};
-use core::cell::RefCell;
+use crate::hid_class::descriptor_parser::{parse_descriptor, DescriptorError};
+use crate::hid_class::{BuilderResult, UsbHidBuilderError, UsbPacketSize, UsbSpeed};
+use crate::interface::duration::{IdleDuration, PollInterval};
+use crate::interface::latency::{LatencyClock, LatencyHistogram, LatencyTracker};
+use crate::interface::packet::{PacketPolicy, MAX_CONTROL_REPORT_SIZE};
+use crate::interface::queue::RawReportQueue;
+#[cfg(feature = "async")]
+use crate::interface::waker::WakerSlot;
+use crate::interface::watchdog::StallWatchdog;
+use crate::interface::{
+    ClassDescriptor, HidDescriptorBodyBytes, HidDescriptorConfig, InterfaceClass, UsbAllocatable,
+    MAX_CLASS_DESCRIPTORS,
+};
+use crate::UsbHidError;
+use core::cell::{Cell, RefCell};
+use core::marker::PhantomData;
+#[cfg(feature = "async")]
+use core::task::{Context, Poll, Waker};
 use fugit::{ExtU32, MillisDurationU32};
 use heapless::Vec;
-use log::{error, info, trace, warn};
 use option_block::Block32;
 use usb_device::bus::{InterfaceNumber, StringIndex, UsbBus, UsbBusAllocator};
-use usb_device::class_prelude::{DescriptorWriter, EndpointIn, EndpointOut};
+use usb_device::class_prelude::{DescriptorWriter, EndpointAddress, EndpointIn, EndpointOut};
 use usb_device::UsbError;
 
+/// Reports summarised when report descriptors are validated
+const MAX_VALIDATED_REPORTS: usize = 16;
+
+/// Configuration of a [`RawInterface`], holding up to `OUT_QUEUE` output and `IN_QUEUE` input
+/// reports, see [`RawInterfaceBuilder::out_report_queue`] and
+/// [`RawInterfaceBuilder::in_report_queue`]
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub struct RawInterfaceConfig<'a> {
+pub struct RawInterfaceConfig<'a, const OUT_QUEUE: usize = 0, const IN_QUEUE: usize = 0> {
     pub report_descriptor: &'a [u8],
+    /// Report descriptor for the layout used while in [`HidProtocol::Boot`]
+    pub boot_report_descriptor: Option<&'a [u8]>,
     pub description: Option<&'a str>,
     pub protocol: InterfaceProtocol,
-    pub idle_default: u8,
+    pub idle_default: IdleDuration,
+    pub protocol_policy: ProtocolPolicy,
+    pub packet_policy: PacketPolicy,
+    /// Clock used to measure input report latency, see [`RawInterface::report_latency`]
+    pub latency_clock: Option<LatencyClock>,
+    /// Time a report may wait on the IN endpoint before the host is considered stalled, see
+    /// [`RawInterface::tick`]
+    pub stall_timeout: Option<MillisDurationU32>,
+    /// Fields of the interface's HID descriptor, see [`RawInterfaceBuilder::hid_descriptor`]
+    pub hid_descriptor: HidDescriptorConfig,
+    /// Class descriptors listed in the HID descriptor after the report descriptor, see
+    /// [`RawInterfaceBuilder::class_descriptors`]
+    pub class_descriptors: &'a [ClassDescriptor<'a>],
+    /// Writing an input report requests a remote wakeup, see [`RawInterfaceBuilder::wake_on_report`]
+    pub wake_on_report: bool,
+    /// Speed of the bus, which determines the encoding of the endpoints' polling intervals
+    pub speed: UsbSpeed,
     pub out_endpoint: Option<EndpointConfig>,
     pub in_endpoint: EndpointConfig,
 }
 
+impl<'a, const OUT_QUEUE: usize, const IN_QUEUE: usize>
+    RawInterfaceConfig<'a, OUT_QUEUE, IN_QUEUE>
+{
+    /// The same configuration with report queues of other depths
+    fn with_queues<const OUT: usize, const IN: usize>(self) -> RawInterfaceConfig<'a, OUT, IN> {
+        RawInterfaceConfig {
+            report_descriptor: self.report_descriptor,
+            boot_report_descriptor: self.boot_report_descriptor,
+            description: self.description,
+            protocol: self.protocol,
+            idle_default: self.idle_default,
+            protocol_policy: self.protocol_policy,
+            packet_policy: self.packet_policy,
+            latency_clock: self.latency_clock,
+            stall_timeout: self.stall_timeout,
+            hid_descriptor: self.hid_descriptor,
+            class_descriptors: self.class_descriptors,
+            wake_on_report: self.wake_on_report,
+            speed: self.speed,
+            out_endpoint: self.out_endpoint,
+            in_endpoint: self.in_endpoint,
+        }
+    }
+}
+
+/// Behaviour of an interface when the host requests a switch to [`HidProtocol::Boot`]
+///
+/// Requests to switch to [`HidProtocol::Report`] are always accepted
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ProtocolPolicy {
+    /// Switch to the requested protocol and report it to the application
+    #[default]
+    Accept,
+    /// Acknowledge the request but remain in report protocol
+    Ignore,
+    /// Stall the request
+    Stall,
+}
+
 // TODO: make configurable, size depends on number of reports for given interface,
 // in most cases Block8 (max 8 reports) would be enough (size 9B vs 36B for Block32)
 type ReportIdleArray = Block32<u8>;
 
-pub struct RawInterface<'a, B: UsbBus> {
+pub struct RawInterface<'a, B: UsbBus, const OUT_QUEUE: usize = 0, const IN_QUEUE: usize = 0> {
     id: InterfaceNumber,
-    config: RawInterfaceConfig<'a>,
+    config: RawInterfaceConfig<'a, OUT_QUEUE, IN_QUEUE>,
     out_endpoint: Option<EndpointOut<'a, B>>,
     in_endpoint: EndpointIn<'a, B>,
     description_index: Option<StringIndex>,
     protocol: HidProtocol,
     report_idle: ReportIdleArray,
     global_idle: u8,
-    control_in_report_buffer: RefCell<Vec<u8, 64>>,
-    control_out_report_buffer: RefCell<Vec<u8, 64>>,
+    control_in_report_buffer: RefCell<Vec<u8, MAX_CONTROL_REPORT_SIZE>>,
+    control_out_report_buffer: RefCell<Vec<u8, MAX_CONTROL_REPORT_SIZE>>,
+    out_queue: RefCell<RawReportQueue<OUT_QUEUE>>,
+    in_queue: RefCell<RawReportQueue<IN_QUEUE>>,
+    zlp_pending: Cell<bool>,
+    in_flight: Cell<bool>,
+    latency: Cell<LatencyTracker>,
+    watchdog: Cell<StallWatchdog>,
+    wakeup_requested: Cell<bool>,
+    #[cfg(feature = "async")]
+    in_waker: WakerSlot,
+    #[cfg(feature = "async")]
+    out_waker: WakerSlot,
 }
 
-impl<'a, B: UsbBus + 'a> UsbAllocatable<'a, B> for RawInterfaceConfig<'a> {
-    type Allocated = RawInterface<'a, B>;
+impl<'a, B: UsbBus + 'a, const OUT_QUEUE: usize, const IN_QUEUE: usize> UsbAllocatable<'a, B>
+    for RawInterfaceConfig<'a, OUT_QUEUE, IN_QUEUE>
+{
+    type Allocated = RawInterface<'a, B, OUT_QUEUE, IN_QUEUE>;
 
     fn allocate(self, usb_alloc: &'a UsbBusAllocator<B>) -> Self::Allocated {
         RawInterface {
@@ -48,25 +138,64 @@ impl<'a, B: UsbBus + 'a> UsbAllocatable<'a, B> for RawInterfaceConfig<'a> {
             id: usb_alloc.interface(),
             in_endpoint: usb_alloc.interrupt(
                 self.in_endpoint.max_packet_size as u16,
-                self.in_endpoint.poll_interval,
+                self.in_endpoint.poll_interval.b_interval(self.speed),
             ),
-            out_endpoint: self
-                .out_endpoint
-                .map(|c| usb_alloc.interrupt(c.max_packet_size as u16, c.poll_interval)),
+            out_endpoint: self.out_endpoint.map(|c| {
+                usb_alloc.interrupt(
+                    c.max_packet_size as u16,
+                    c.poll_interval.b_interval(self.speed),
+                )
+            }),
             description_index: self.description.map(|_| usb_alloc.string()),
             //When initialized, all devices default to report protocol - Hid spec 7.2.6 Set_Protocol Request
             protocol: HidProtocol::Report,
             report_idle: Default::default(),
-            global_idle: self.idle_default,
+            global_idle: self.idle_default.raw(),
             control_in_report_buffer: RefCell::new(Default::default()),
             control_out_report_buffer: RefCell::new(Default::default()),
+            out_queue: RefCell::new(Default::default()),
+            in_queue: RefCell::new(Default::default()),
+            zlp_pending: Cell::new(false),
+            in_flight: Cell::new(false),
+            latency: Cell::new(LatencyTracker::new(self.latency_clock)),
+            watchdog: Cell::new(StallWatchdog::new(self.stall_timeout)),
+            wakeup_requested: Cell::new(false),
+            #[cfg(feature = "async")]
+            in_waker: Default::default(),
+            #[cfg(feature = "async")]
+            out_waker: Default::default(),
         }
     }
 }
 
-impl<'a, B: UsbBus> InterfaceClass<'a> for RawInterface<'a, B> {
+impl<'a, B: UsbBus, const OUT_QUEUE: usize, const IN_QUEUE: usize> InterfaceClass<'a>
+    for RawInterface<'a, B, OUT_QUEUE, IN_QUEUE>
+{
     fn report_descriptor(&self) -> &'_ [u8] {
-        self.config.report_descriptor
+        match (self.report_layout(), self.config.boot_report_descriptor) {
+            (HidProtocol::Boot, Some(descriptor)) => descriptor,
+            _ => self.config.report_descriptor,
+        }
+    }
+
+    fn hid_descriptor_body(&self) -> HidDescriptorBodyBytes {
+        self.config.hid_descriptor.body(
+            self.report_descriptor().len(),
+            self.config.class_descriptors,
+        )
+    }
+
+    fn class_descriptor(&self, descriptor_type: u8, index: u8) -> Option<&'_ [u8]> {
+        self.config
+            .class_descriptors
+            .iter()
+            .filter(|d| d.descriptor_type as u8 == descriptor_type)
+            .nth(usize::from(index))
+            .map(|d| d.data)
+    }
+
+    fn take_wakeup_request(&self) -> bool {
+        self.wakeup_requested.take()
     }
 
     fn id(&self) -> InterfaceNumber {
@@ -100,12 +229,27 @@ impl<'a, B: UsbBus> InterfaceClass<'a> for RawInterface<'a, B> {
     }
     fn reset(&mut self) {
         self.protocol = HidProtocol::Report;
-        self.global_idle = self.config.idle_default;
+        self.global_idle = self.config.idle_default.raw();
         self.clear_report_idle();
         self.control_in_report_buffer.borrow_mut().clear();
         self.control_out_report_buffer.borrow_mut().clear();
+        self.out_queue.borrow_mut().clear();
+        self.in_queue.borrow_mut().clear();
+        self.zlp_pending.set(false);
+        self.in_flight.set(false);
+        self.update_latency(LatencyTracker::reset);
+        self.update_watchdog(StallWatchdog::reset);
+        self.wakeup_requested.set(false);
     }
     fn set_report(&mut self, data: &[u8]) -> usb_device::Result<()> {
+        let mut out_queue = self.out_queue.borrow_mut();
+        if out_queue.is_enabled() {
+            out_queue.push(data)?;
+            trace!("Queued set report, {:X} bytes", data.len());
+            #[cfg(feature = "async")]
+            self.out_waker.wake();
+            return Ok(());
+        }
         let mut out_buffer = self.control_out_report_buffer.borrow_mut();
         if !out_buffer.is_empty() {
             trace!("Failed to set report, buffer not empty");
@@ -122,6 +266,8 @@ impl<'a, B: UsbBus> InterfaceClass<'a> for RawInterface<'a, B> {
                 }
                 Ok(_) => {
                     trace!("Set report, {:X} bytes", &out_buffer.len());
+                    #[cfg(feature = "async")]
+                    self.out_waker.wake();
                     Ok(())
                 }
             }
@@ -178,17 +324,134 @@ impl<'a, B: UsbBus> InterfaceClass<'a> for RawInterface<'a, B> {
             self.get_report_idle(report_id).unwrap_or(self.global_idle)
         }
     }
-    fn set_protocol(&mut self, protocol: HidProtocol) {
-        self.protocol = protocol;
-        info!("Set protocol to {:?}", protocol);
+    fn set_protocol(&mut self, protocol: HidProtocol) -> usb_device::Result<()> {
+        match (protocol, self.config.protocol_policy) {
+            (HidProtocol::Report, _) | (_, ProtocolPolicy::Accept) => {
+                self.protocol = protocol;
+                info!("Set protocol to {:?}", protocol);
+                Ok(())
+            }
+            (_, ProtocolPolicy::Ignore) => {
+                info!("Ignored set protocol to {:?}", protocol);
+                Ok(())
+            }
+            (_, ProtocolPolicy::Stall) => {
+                warn!("Rejected set protocol to {:?}", protocol);
+                Err(UsbError::Unsupported)
+            }
+        }
     }
 
     fn get_protocol(&self) -> HidProtocol {
         self.protocol
     }
+
+    fn out_endpoint_address(&self) -> Option<EndpointAddress> {
+        self.out_endpoint.as_ref().map(|e| e.address())
+    }
+    fn in_endpoint_address(&self) -> EndpointAddress {
+        self.in_endpoint.address()
+    }
+
+    fn endpoint_in_complete(&self, addr: EndpointAddress) {
+        if addr == self.in_endpoint.address() {
+            self.update_latency(LatencyTracker::completed);
+            self.update_watchdog(StallWatchdog::completed);
+            self.in_flight.set(false);
+            if self.zlp_pending.get() && self.in_endpoint.write(&[]).is_ok() {
+                self.zlp_pending.set(false);
+                self.in_flight.set(true);
+            }
+            self.flush().ok();
+            #[cfg(feature = "async")]
+            self.in_waker.wake();
+        }
+    }
+    fn endpoint_out(&mut self) {
+        let mut out_queue = self.out_queue.borrow_mut();
+        if let (true, Some(ep)) = (out_queue.is_enabled(), &self.out_endpoint) {
+            let mut data = [0; MAX_CONTROL_REPORT_SIZE];
+            match ep.read(&mut data) {
+                Ok(n) => {
+                    if let Err(e) = out_queue.push(&data[..n]) {
+                        error!("Failed to queue output report: {:?}", e);
+                    }
+                }
+                Err(UsbError::WouldBlock) => {}
+                Err(e) => error!("Failed to read output report: {:?}", e),
+            }
+        }
+        #[cfg(feature = "async")]
+        self.out_waker.wake();
+    }
+}
+
+#[cfg(feature = "async")]
+impl<'a, B: UsbBus, const OUT_QUEUE: usize, const IN_QUEUE: usize>
+    RawInterface<'a, B, OUT_QUEUE, IN_QUEUE>
+{
+    /// Writes an input report, or registers the task to be woken when the IN endpoint may have
+    /// space
+    ///
+    /// The task is woken when [`UsbHidClass`](crate::hid_class::UsbHidClass) is polled after the
+    /// host reads the IN endpoint. Unlike [`RawInterface::write_report_async`], this does not
+    /// borrow the interface while pending, so the class can be polled in between.
+    pub fn poll_write_report(
+        &self,
+        cx: &mut Context<'_>,
+        data: &[u8],
+    ) -> Poll<Result<usize, UsbHidError>> {
+        self.in_waker.register(cx.waker());
+        match self.write_report(data) {
+            Err(UsbHidError::WouldBlock) => Poll::Pending,
+            result => Poll::Ready(result),
+        }
+    }
+    /// Reads an output report, or registers the task to be woken when the host sends one with a
+    /// write to the OUT endpoint or a `SET_REPORT` request
+    pub fn poll_read_report(
+        &self,
+        cx: &mut Context<'_>,
+        data: &mut [u8],
+    ) -> Poll<Result<usize, UsbHidError>> {
+        self.out_waker.register(cx.waker());
+        match self.read_report(data) {
+            Err(UsbHidError::WouldBlock) => Poll::Pending,
+            result => Poll::Ready(result),
+        }
+    }
+    /// Writes an input report, waiting while the IN endpoint is busy, see
+    /// [`RawInterface::poll_write_report`]
+    pub async fn write_report_async(&self, data: &[u8]) -> Result<usize, UsbHidError> {
+        core::future::poll_fn(|cx| self.poll_write_report(cx, data)).await
+    }
+    /// Reads an output report, waiting until the host sends one, see
+    /// [`RawInterface::poll_read_report`]
+    pub async fn read_report_async(&self, data: &mut [u8]) -> Result<usize, UsbHidError> {
+        core::future::poll_fn(|cx| self.poll_read_report(cx, data)).await
+    }
+    pub(crate) fn register_in_waker(&self, waker: &Waker) {
+        self.in_waker.register(waker);
+    }
+    pub(crate) fn register_out_waker(&self, waker: &Waker) {
+        self.out_waker.register(waker);
+    }
 }
 
-impl<'a, B: UsbBus> RawInterface<'a, B> {
+impl<'a, B: UsbBus, const OUT_QUEUE: usize, const IN_QUEUE: usize>
+    RawInterface<'a, B, OUT_QUEUE, IN_QUEUE>
+{
+    fn update_latency(&self, f: impl FnOnce(&mut LatencyTracker)) {
+        let mut tracker = self.latency.get();
+        f(&mut tracker);
+        self.latency.set(tracker);
+    }
+    fn update_watchdog<T>(&self, f: impl FnOnce(&mut StallWatchdog) -> T) -> T {
+        let mut watchdog = self.watchdog.get();
+        let result = f(&mut watchdog);
+        self.watchdog.set(watchdog);
+        result
+    }
     fn clear_report_idle(&mut self) {
         self.report_idle = Default::default();
     }
@@ -202,21 +465,158 @@ impl<'a, B: UsbBus> RawInterface<'a, B> {
     pub fn protocol(&self) -> HidProtocol {
         self.protocol
     }
+    /// Layout of the reports the interface should send, [`HidProtocol::Boot`] only if the
+    /// interface has a boot report descriptor and the host has selected boot protocol
+    pub fn report_layout(&self) -> HidProtocol {
+        if self.config.boot_report_descriptor.is_some() {
+            self.protocol
+        } else {
+            HidProtocol::Report
+        }
+    }
+    /// Time from input reports being written to the IN endpoint to the host reading them
+    ///
+    /// Empty unless a latency clock was configured with [`RawInterfaceBuilder::latency_clock`].
+    pub fn report_latency(&self) -> LatencyHistogram {
+        self.latency.get().histogram()
+    }
+    pub fn clear_report_latency(&self) {
+        self.update_latency(LatencyTracker::clear);
+    }
+    /// True if the host has read the last report written to the IN endpoint, so the next
+    /// [`RawInterface::write_report`] is not expected to fail with [`UsbError::WouldBlock`]
+    pub fn write_ready(&self) -> bool {
+        !self.in_flight.get() && !self.zlp_pending.get()
+    }
+    /// Number of output reports waiting to be read, see [`RawInterfaceBuilder::out_report_queue`]
+    pub fn queued_output_reports(&self) -> usize {
+        self.out_queue.borrow().len()
+    }
+    /// Number of input reports waiting for the IN endpoint, see
+    /// [`RawInterfaceBuilder::in_report_queue`]
+    pub fn queued_input_reports(&self) -> usize {
+        self.in_queue.borrow().len()
+    }
+    /// Number of output reports dropped because the queue was full
+    pub fn dropped_output_reports(&self) -> u32 {
+        self.out_queue.borrow().dropped()
+    }
+    /// Number of times the host was considered stalled, see [`RawInterface::tick`]
+    pub fn host_stalls(&self) -> u32 {
+        self.watchdog.get().stalls()
+    }
+    /// Whether the host is considered stalled, from the stall detected by [`RawInterface::tick`]
+    /// until the host next reads the IN endpoint or the bus is reset
+    pub fn host_stalled(&self) -> bool {
+        self.watchdog.get().is_stalled()
+    }
+    /// Call every 1ms / at 1 KHz if a stall timeout was configured with
+    /// [`RawInterfaceBuilder::stall_timeout`]
+    ///
+    /// Returns true when a report has waited on the IN endpoint for the stall timeout. The report
+    /// buffered for `GET_REPORT` requests and the queued input reports are then discarded,
+    /// [`RawInterface::host_stalls`] incremented and [`RawInterface::host_stalled`] set. The report
+    /// already written to the IN endpoint cannot be withdrawn and is sent if the host resumes
+    /// polling.
+    pub fn tick(&self) -> bool {
+        self.tick_with(1.millis())
+    }
+    /// Advances the stall timeout by `elapsed`, see [`RawInterface::tick`]
+    pub fn tick_with(&self, elapsed: MillisDurationU32) -> bool {
+        self.flush().ok();
+        let stalled = self.update_watchdog(|w| w.tick_with(elapsed));
+        if stalled {
+            warn!("Host stopped polling interface {:X}", u8::from(self.id));
+            self.control_in_report_buffer.borrow_mut().clear();
+            self.in_queue.borrow_mut().clear();
+            self.wakeup_requested.set(false);
+            self.update_latency(LatencyTracker::reset);
+        }
+        stalled
+    }
     pub fn global_idle(&self) -> MillisDurationU32 {
-        ((self.global_idle as u32) * 4).millis()
+        IdleDuration::from_raw(self.global_idle).to_duration()
     }
     pub fn report_idle(&self, report_id: u8) -> Option<MillisDurationU32> {
         if report_id == 0 {
             None
         } else {
             self.get_report_idle(report_id)
-                .map(|i| ((i as u32) * 4).millis())
+                .map(|i| IdleDuration::from_raw(i).to_duration())
+        }
+    }
+    /// Writes an input report to the IN endpoint, and buffers it for `GET_REPORT` requests
+    ///
+    /// Empty reports, and reports the IN endpoint's maximum packet size, are handled according to
+    /// the interface's [`PacketPolicy`].
+    ///
+    /// With an [input report queue](RawInterfaceBuilder::in_report_queue), the report is queued
+    /// behind any reports still waiting for the IN endpoint.
+    pub fn write_report(&self, data: &[u8]) -> Result<usize, UsbHidError> {
+        let written = self.write_or_queue_report(data)?;
+        //A stalled host isn't woken again until it reads the IN endpoint
+        if self.config.wake_on_report && !self.host_stalled() {
+            self.wakeup_requested.set(true);
+        }
+        Ok(written)
+    }
+    fn write_or_queue_report(&self, data: &[u8]) -> Result<usize, UsbHidError> {
+        if !self.in_queue.borrow().is_enabled() {
+            return self.write_packet(data).map_err(UsbHidError::from);
+        }
+        //Reject reports that could never be sent before queueing them
+        self.config
+            .packet_policy
+            .check(data.len(), self.in_endpoint.max_packet_size().into())?;
+        self.in_queue.borrow_mut().try_push(data)?;
+        match self.flush() {
+            Ok(()) | Err(UsbHidError::WouldBlock) => Ok(data.len()),
+            Err(e) => Err(e),
         }
     }
-    pub fn write_report(&self, data: &[u8]) -> usb_device::Result<usize> {
+    /// Writes queued input reports to the IN endpoint until it is busy, see
+    /// [`RawInterfaceBuilder::in_report_queue`]
+    ///
+    /// Called when the [`UsbHidClass`](crate::hid_class::UsbHidClass) is polled and on
+    /// [`RawInterface::tick`]. A report that cannot be sent is dropped and its error returned.
+    pub fn flush(&self) -> Result<(), UsbHidError> {
+        let mut in_queue = self.in_queue.borrow_mut();
+        while let Some(report) = in_queue.front() {
+            //Only the IN endpoint accepting the report counts as sent
+            if !self.write_ready() {
+                return Err(UsbHidError::WouldBlock);
+            }
+            match self.write_packet(report) {
+                Ok(_) => in_queue.pop_front(),
+                Err(UsbError::WouldBlock) => return Err(UsbHidError::WouldBlock),
+                Err(e) => {
+                    error!("Dropping queued input report: {:?}", e);
+                    in_queue.pop_front();
+                    return Err(e.into());
+                }
+            }
+        }
+        Ok(())
+    }
+    pub(crate) fn write_packet(&self, data: &[u8]) -> usb_device::Result<usize> {
+        let terminate = self
+            .config
+            .packet_policy
+            .check(data.len(), self.in_endpoint.max_packet_size().into())?;
+
+        //The previous report must be terminated before the next report is sent
+        if self.zlp_pending.get() {
+            self.in_endpoint.write(&[])?;
+            self.zlp_pending.set(false);
+            self.in_flight.set(true);
+            return Err(UsbError::WouldBlock);
+        }
+
         //Try to write report to the report buffer for the config endpoint
         let mut in_buffer = self.control_in_report_buffer.borrow_mut();
-        let control_result = if in_buffer.is_empty() {
+        let control_result = if data.is_empty() {
+            Err(UsbError::WouldBlock)
+        } else if in_buffer.is_empty() {
             match in_buffer.extend_from_slice(data) {
                 Ok(_) => Ok(data.len()),
                 Err(_) => Err(UsbError::BufferOverflow),
@@ -227,6 +627,12 @@ impl<'a, B: UsbBus> RawInterface<'a, B> {
 
         //Also try to write report to the in endpoint
         let endpoint_result = self.in_endpoint.write(data);
+        if endpoint_result.is_ok() {
+            self.in_flight.set(true);
+            self.update_latency(LatencyTracker::enqueued);
+            self.update_watchdog(StallWatchdog::enqueued);
+            self.zlp_pending.set(terminate);
+        }
 
         match (control_result, endpoint_result) {
             //OK if either succeeded
@@ -238,7 +644,27 @@ impl<'a, B: UsbBus> RawInterface<'a, B> {
             (_, Err(e)) => Err(e),
         }
     }
-    pub fn read_report(&self, data: &mut [u8]) -> usb_device::Result<usize> {
+    pub fn read_report(&self, data: &mut [u8]) -> Result<usize, UsbHidError> {
+        self.read_packet(data).map_err(UsbHidError::from)
+    }
+    pub(crate) fn read_packet(&self, data: &mut [u8]) -> usb_device::Result<usize> {
+        //Reports queued while polling were received before any still on the out endpoint
+        match self.read_queued_packet(data) {
+            Err(UsbError::WouldBlock) => self.read_unqueued_packet(data),
+            result => result,
+        }
+    }
+    /// Reads the oldest report from the output report queue
+    pub(crate) fn read_queued_packet(&self, data: &mut [u8]) -> usb_device::Result<usize> {
+        self.out_queue.borrow_mut().pop_into(data)
+    }
+    /// Adds a report to the output report queue, dropping the oldest report if it is full
+    pub(crate) fn queue_output_report(&self, data: &[u8]) -> usb_device::Result<()> {
+        self.out_queue.borrow_mut().push(data)
+    }
+    /// Reads a report from the OUT endpoint or the last `SET_REPORT` request, bypassing the output
+    /// report queue
+    pub(crate) fn read_unqueued_packet(&self, data: &mut [u8]) -> usb_device::Result<usize> {
         //If there is an out endpoint, try to read from it first
         let ep_result = if let Some(ep) = &self.out_endpoint {
             ep.read(data)
@@ -269,14 +695,58 @@ impl<'a, B: UsbBus> RawInterface<'a, B> {
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct EndpointConfig {
-    pub poll_interval: u8,
+    pub poll_interval: PollInterval,
     pub max_packet_size: UsbPacketSize,
 }
 
+impl EndpointConfig {
+    /// Whether the endpoint's polling interval and packet size can be encoded for a bus of `speed`
+    fn supported(&self, speed: UsbSpeed) -> bool {
+        match speed {
+            UsbSpeed::Full => {
+                self.poll_interval >= PollInterval::MIN
+                    && self.max_packet_size <= UsbPacketSize::Bytes64
+            }
+            UsbSpeed::High => true,
+        }
+    }
+}
+
+/// State of a [`RawInterfaceBuilder`] endpoint that hasn't been configured yet
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct EndpointUnset;
+
+/// State of a [`RawInterfaceBuilder`] endpoint that has been configured
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct EndpointSet;
+
+/// Builder of a [`RawInterfaceConfig`]
+///
+/// Invalid values are recorded rather than returned by each call, [`RawInterfaceBuilder::build`]
+/// returns the first. The `In` and `Out` type parameters track whether the endpoints have been
+/// configured, so the IN endpoint must be chosen with [`RawInterfaceBuilder::in_endpoint`] before
+/// building and each endpoint can only be configured once. `OUT_QUEUE` and `IN_QUEUE` are the
+/// depths of the report queues, set with [`RawInterfaceBuilder::out_report_queue`] and
+/// [`RawInterfaceBuilder::in_report_queue`].
+///
+/// ```compile_fail
+/// use usbd_human_interface_device::interface::raw::RawInterfaceBuilder;
+///
+/// // No IN endpoint
+/// let config = RawInterfaceBuilder::new(&[0x05, 0x01]).build();
+/// ```
 #[must_use = "this `UsbHidInterfaceBuilder` must be assigned or consumed by `::build_interface()`"]
 #[derive(Clone, Debug)]
-pub struct RawInterfaceBuilder<'a> {
-    config: RawInterfaceConfig<'a>,
+pub struct RawInterfaceBuilder<
+    'a,
+    In = EndpointUnset,
+    Out = EndpointUnset,
+    const OUT_QUEUE: usize = 0,
+    const IN_QUEUE: usize = 0,
+> {
+    config: RawInterfaceConfig<'a, OUT_QUEUE, IN_QUEUE>,
+    error: Option<UsbHidBuilderError>,
+    _endpoints: PhantomData<(In, Out)>,
 }
 
 impl<'a> RawInterfaceBuilder<'a> {
@@ -284,38 +754,148 @@ impl<'a> RawInterfaceBuilder<'a> {
         RawInterfaceBuilder {
             config: RawInterfaceConfig {
                 report_descriptor,
+                boot_report_descriptor: None,
                 description: None,
                 protocol: InterfaceProtocol::None,
-                idle_default: 0,
+                idle_default: IdleDuration::INDEFINITE,
+                protocol_policy: ProtocolPolicy::Accept,
+                packet_policy: PacketPolicy::DEFAULT,
+                latency_clock: None,
+                stall_timeout: None,
+                hid_descriptor: HidDescriptorConfig::DEFAULT,
+                class_descriptors: &[],
+                wake_on_report: false,
+                speed: UsbSpeed::Full,
                 out_endpoint: None,
+                // Replaced by in_endpoint before the config can be built
                 in_endpoint: EndpointConfig {
                     max_packet_size: UsbPacketSize::Bytes8,
-                    poll_interval: 20,
+                    poll_interval: PollInterval::millis(20),
                 },
             },
+            error: None,
+            _endpoints: PhantomData,
         }
     }
+}
 
+impl<'a, In, Out, const OUT_QUEUE: usize, const IN_QUEUE: usize>
+    RawInterfaceBuilder<'a, In, Out, OUT_QUEUE, IN_QUEUE>
+{
     pub fn boot_device(mut self, protocol: InterfaceProtocol) -> Self {
         self.config.protocol = protocol;
         self
     }
 
-    pub fn idle_default(mut self, duration: MillisDurationU32) -> BuilderResult<Self> {
-        if duration.ticks() == 0 {
-            self.config.idle_default = 0;
-        } else {
-            let scaled_duration = duration.to_millis() / 4;
-
-            if scaled_duration == 0 {
-                //round up for 1-3ms
-                self.config.idle_default = 1;
-            } else {
-                self.config.idle_default =
-                    u8::try_from(scaled_duration).map_err(|_| UsbHidBuilderError::ValueOverflow)?;
-            }
+    /// Serve an alternative report descriptor, and report layout, while in boot protocol
+    pub fn boot_report_descriptor(mut self, report_descriptor: &'a [u8]) -> Self {
+        self.config.boot_report_descriptor = Some(report_descriptor);
+        self
+    }
+
+    pub fn protocol_policy(mut self, policy: ProtocolPolicy) -> Self {
+        self.config.protocol_policy = policy;
+        self
+    }
+
+    pub fn idle_default(mut self, duration: IdleDuration) -> Self {
+        self.config.idle_default = duration;
+        self
+    }
+
+    /// Handling of empty reports and reports the IN endpoint's maximum packet size
+    pub fn packet_policy(mut self, policy: PacketPolicy) -> Self {
+        self.config.packet_policy = policy;
+        self
+    }
+
+    /// Measure input report latency using `clock`, a free running microsecond counter
+    pub fn latency_clock(mut self, clock: fn() -> u32) -> Self {
+        self.config.latency_clock = Some(LatencyClock(clock));
+        self
+    }
+
+    /// Consider the host stalled if it doesn't read a report from the IN endpoint within `timeout`
+    pub fn stall_timeout(mut self, timeout: MillisDurationU32) -> Self {
+        self.config.stall_timeout = Some(timeout);
+        self
+    }
+
+    /// Hold up to `N` output reports until the application reads them
+    ///
+    /// Reports are read from the OUT endpoint, or accepted from `SET_REPORT` requests, while the
+    /// [`UsbHidClass`](crate::hid_class::UsbHidClass) is polled. When the queue is full the
+    /// oldest report is dropped. Without a queue, a report the application hasn't read blocks the
+    /// OUT endpoint and causes later `SET_REPORT` requests to fail.
+    ///
+    /// Each queued report takes [`MAX_CONTROL_REPORT_SIZE`] bytes in the [`RawInterface`],
+    /// interfaces without a queue take none. The OUT endpoint's maximum packet size must not be
+    /// larger, [`RawInterfaceBuilder::build`] fails otherwise.
+    pub fn out_report_queue<const N: usize>(self) -> RawInterfaceBuilder<'a, In, Out, N, IN_QUEUE> {
+        RawInterfaceBuilder {
+            config: self.config.with_queues(),
+            error: self.error,
+            _endpoints: PhantomData,
         }
-        Ok(self)
+    }
+
+    /// Hold up to `N` input reports until the IN endpoint has space
+    ///
+    /// Several reports written in one scan cycle, such as the press and release of a macro key,
+    /// are sent in order as the host reads the endpoint. Writes only fail with
+    /// [`UsbHidError::WouldBlock`] when the queue is full. Queued reports are sent when the
+    /// [`UsbHidClass`](crate::hid_class::UsbHidClass) is polled, on [`RawInterface::tick`], or
+    /// by calling [`RawInterface::flush`].
+    ///
+    /// Each queued report takes [`MAX_CONTROL_REPORT_SIZE`] bytes in the [`RawInterface`],
+    /// interfaces without a queue take none.
+    pub fn in_report_queue<const N: usize>(self) -> RawInterfaceBuilder<'a, In, Out, OUT_QUEUE, N> {
+        RawInterfaceBuilder {
+            config: self.config.with_queues(),
+            error: self.error,
+            _endpoints: PhantomData,
+        }
+    }
+
+    /// Encode the endpoints' polling intervals for a bus of `speed`
+    ///
+    /// High-speed peripherals, such as the Synopsys OTG HS core, poll endpoints at the wrong rate
+    /// with full-speed intervals.
+    pub fn speed(mut self, speed: UsbSpeed) -> Self {
+        self.config.speed = speed;
+        self
+    }
+
+    /// Request a remote wakeup whenever an input report is written, see
+    /// [`UsbHidClass::remote_wakeup`](crate::hid_class::UsbHidClass::remote_wakeup)
+    ///
+    /// Reports that fail to be written don't request a wakeup. A host stall detected by
+    /// [`RawInterface::tick`] cancels the request, and reports written while the host is
+    /// [stalled](RawInterface::host_stalled) don't request another.
+    ///
+    /// Enable on interfaces reporting user input, such as key presses, that should wake the host.
+    pub fn wake_on_report(mut self, wake: bool) -> Self {
+        self.config.wake_on_report = wake;
+        self
+    }
+
+    /// Report `config`'s HID specification release and country code in the interface's HID
+    /// descriptor, instead of HID 1.11 and no country
+    pub fn hid_descriptor(mut self, config: HidDescriptorConfig) -> Self {
+        self.config.hid_descriptor = config;
+        self
+    }
+
+    /// List up to [`MAX_CLASS_DESCRIPTORS`] `descriptors`, such as Physical descriptors, in the
+    /// interface's HID descriptor and serve them to `GET_DESCRIPTOR` requests,
+    /// [`UsbHidBuilderError::ValueOverflow`] for more descriptors
+    pub fn class_descriptors(mut self, descriptors: &'a [ClassDescriptor<'a>]) -> Self {
+        if descriptors.len() > MAX_CLASS_DESCRIPTORS {
+            error!("Too many class descriptors");
+            self.fail(UsbHidBuilderError::ValueOverflow);
+        }
+        self.config.class_descriptors = descriptors;
+        self
     }
 
     pub fn description(mut self, s: &'static str) -> Self {
@@ -323,38 +903,590 @@ impl<'a> RawInterfaceBuilder<'a> {
         self
     }
 
+    fn with_state<NewIn, NewOut>(
+        self,
+    ) -> RawInterfaceBuilder<'a, NewIn, NewOut, OUT_QUEUE, IN_QUEUE> {
+        RawInterfaceBuilder {
+            config: self.config,
+            error: self.error,
+            _endpoints: PhantomData,
+        }
+    }
+
+    fn fail(&mut self, error: UsbHidBuilderError) {
+        self.error.get_or_insert(error);
+    }
+}
+
+impl<'a, In, const OUT_QUEUE: usize, const IN_QUEUE: usize>
+    RawInterfaceBuilder<'a, In, EndpointUnset, OUT_QUEUE, IN_QUEUE>
+{
     pub fn with_out_endpoint(
         mut self,
         max_packet_size: UsbPacketSize,
-        poll_interval: MillisDurationU32,
-    ) -> BuilderResult<Self> {
+        poll_interval: PollInterval,
+    ) -> RawInterfaceBuilder<'a, In, EndpointSet, OUT_QUEUE, IN_QUEUE> {
         self.config.out_endpoint = Some(EndpointConfig {
             max_packet_size,
-            poll_interval: u8::try_from(poll_interval.to_millis())
-                .map_err(|_| UsbHidBuilderError::ValueOverflow)?,
+            poll_interval,
         });
-        Ok(self)
+        self.with_state()
     }
 
-    pub fn without_out_endpoint(mut self) -> Self {
+    /// Receive output reports only with `SET_REPORT` requests, the default if neither this nor
+    /// [`RawInterfaceBuilder::with_out_endpoint`] is called
+    pub fn without_out_endpoint(
+        mut self,
+    ) -> RawInterfaceBuilder<'a, In, EndpointSet, OUT_QUEUE, IN_QUEUE> {
         self.config.out_endpoint = None;
-        self
+        self.with_state()
     }
+}
 
+impl<'a, Out, const OUT_QUEUE: usize, const IN_QUEUE: usize>
+    RawInterfaceBuilder<'a, EndpointUnset, Out, OUT_QUEUE, IN_QUEUE>
+{
+    /// Intervals shorter than 1ms, such as [`PollInterval::MICROFRAME`] for 8 kHz polling, and
+    /// packets longer than 64 bytes are only supported with [`UsbSpeed::High`], see
+    /// [`RawInterfaceBuilder::speed`]
     pub fn in_endpoint(
         mut self,
         max_packet_size: UsbPacketSize,
-        poll_interval: MillisDurationU32,
-    ) -> BuilderResult<Self> {
+        poll_interval: PollInterval,
+    ) -> RawInterfaceBuilder<'a, EndpointSet, Out, OUT_QUEUE, IN_QUEUE> {
         self.config.in_endpoint = EndpointConfig {
             max_packet_size,
-            poll_interval: u8::try_from(poll_interval.to_millis())
-                .map_err(|_| UsbHidBuilderError::ValueOverflow)?,
+            poll_interval,
         };
-        Ok(self)
+        self.with_state()
     }
+}
 
-    pub fn build(self) -> RawInterfaceConfig<'a> {
-        self.config
+impl<'a, Out, const OUT_QUEUE: usize, const IN_QUEUE: usize>
+    RawInterfaceBuilder<'a, EndpointSet, Out, OUT_QUEUE, IN_QUEUE>
+{
+    /// Returns the first error recorded while configuring the builder, or
+    /// [`UsbHidBuilderError::InvalidValue`] if an endpoint's polling interval or packet size
+    /// isn't supported at the bus [`speed`](RawInterfaceBuilder::speed)
+    ///
+    /// Also returns [`UsbHidBuilderError::InvalidReportDescriptor`] if a report
+    /// descriptor fails [`parse_descriptor`](crate::hid_class::descriptor_parser::parse_descriptor),
+    /// [`UsbHidBuilderError::OutEndpointRequired`] if output reports are too long for
+    /// `SET_REPORT` and there is no OUT endpoint, and [`UsbHidBuilderError::InvalidValue`] if
+    /// packets on the OUT endpoint are too long for the
+    /// [output report queue](RawInterfaceBuilder::out_report_queue).
+    pub fn build(mut self) -> BuilderResult<RawInterfaceConfig<'a, OUT_QUEUE, IN_QUEUE>> {
+        let endpoints = [Some(self.config.in_endpoint), self.config.out_endpoint];
+        for endpoint in endpoints.into_iter().flatten() {
+            if !endpoint.supported(self.config.speed) {
+                error!(
+                    "Endpoint {:?} not supported at {:?} speed",
+                    endpoint, self.config.speed
+                );
+                self.fail(UsbHidBuilderError::InvalidValue);
+            }
+        }
+        if let Some(endpoint) = self.config.out_endpoint {
+            if OUT_QUEUE > 0 && endpoint.max_packet_size as usize > MAX_CONTROL_REPORT_SIZE {
+                error!(
+                    "OUT endpoint {:?} packets too long for the output report queue",
+                    endpoint
+                );
+                self.fail(UsbHidBuilderError::InvalidValue);
+            }
+        }
+        self.validate_report_descriptors();
+
+        match self.error {
+            Some(e) => Err(e),
+            None => Ok(self.config),
+        }
+    }
+
+    fn validate_report_descriptors(&mut self) {
+        if let Some(descriptor) = self.config.boot_report_descriptor {
+            match parse_descriptor::<MAX_VALIDATED_REPORTS>(descriptor) {
+                Ok(_) | Err(DescriptorError::TooManyReports) => {}
+                Err(e) => self.fail(UsbHidBuilderError::InvalidReportDescriptor(e)),
+            }
+        }
+        match parse_descriptor::<MAX_VALIDATED_REPORTS>(self.config.report_descriptor) {
+            Ok(summary) => {
+                let control_too_short = summary.reports().iter().any(|r| {
+                    r.report_type == ReportType::Output
+                        && r.len() + usize::from(r.report_id != 0) > MAX_CONTROL_REPORT_SIZE
+                });
+                if control_too_short && self.config.out_endpoint.is_none() {
+                    error!("Output reports too long for SET_REPORT need an OUT endpoint");
+                    self.fail(UsbHidBuilderError::OutEndpointRequired);
+                }
+            }
+            Err(DescriptorError::TooManyReports) => {}
+            Err(e) => self.fail(UsbHidBuilderError::InvalidReportDescriptor(e)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    #[test]
+    fn builder_errors() {
+        use crate::hid_class::descriptor::DescriptorType;
+        use crate::hid_class::descriptor_parser::DescriptorError;
+        use crate::hid_class::{UsbHidBuilderError, UsbPacketSize, UsbSpeed};
+        use crate::interface::duration::PollInterval;
+        use crate::interface::raw::RawInterfaceBuilder;
+        use crate::interface::{ClassDescriptor, MAX_CLASS_DESCRIPTORS};
+
+        const DESCRIPTOR: &[u8] = &[0x05, 0x01];
+
+        let sub_millisecond = RawInterfaceBuilder::new(DESCRIPTOR)
+            .in_endpoint(UsbPacketSize::Bytes8, PollInterval::MICROFRAME);
+        assert_eq!(
+            sub_millisecond.clone().build().map(drop),
+            Err(UsbHidBuilderError::InvalidValue)
+        );
+        assert!(sub_millisecond.speed(UsbSpeed::High).build().is_ok());
+        assert_eq!(
+            RawInterfaceBuilder::new(DESCRIPTOR)
+                .in_endpoint(UsbPacketSize::Bytes8, PollInterval::millis(20))
+                .with_out_endpoint(UsbPacketSize::Bytes128, PollInterval::MIN)
+                .build()
+                .map(drop),
+            Err(UsbHidBuilderError::InvalidValue)
+        );
+
+        // The first error is returned
+        assert_eq!(
+            RawInterfaceBuilder::new(DESCRIPTOR)
+                .class_descriptors(
+                    &[ClassDescriptor {
+                        descriptor_type: DescriptorType::Physical,
+                        data: &[],
+                    }; MAX_CLASS_DESCRIPTORS + 1]
+                )
+                .in_endpoint(UsbPacketSize::Bytes8, PollInterval::MICROFRAME)
+                .build()
+                .map(drop),
+            Err(UsbHidBuilderError::ValueOverflow)
+        );
+
+        assert_eq!(
+            RawInterfaceBuilder::new(&[0xC0])
+                .in_endpoint(UsbPacketSize::Bytes8, PollInterval::millis(20))
+                .build()
+                .map(drop),
+            Err(UsbHidBuilderError::InvalidReportDescriptor(
+                DescriptorError::UnbalancedCollection { offset: 0 }
+            ))
+        );
+
+        // 65 byte output report
+        const LONG_OUTPUT: &[u8] = &[
+            0x06, 0x00, 0xFF, 0x09, 0x01, 0xA1, 0x01, 0x75, 0x08, 0x95, 0x41, 0x91, 0x02, 0xC0,
+        ];
+        assert_eq!(
+            RawInterfaceBuilder::new(LONG_OUTPUT)
+                .in_endpoint(UsbPacketSize::Bytes8, PollInterval::millis(20))
+                .build()
+                .map(drop),
+            Err(UsbHidBuilderError::OutEndpointRequired)
+        );
+        assert!(RawInterfaceBuilder::new(LONG_OUTPUT)
+            .in_endpoint(UsbPacketSize::Bytes8, PollInterval::millis(20))
+            .with_out_endpoint(UsbPacketSize::Bytes64, PollInterval::MIN)
+            .build()
+            .is_ok());
+
+        // Queued output reports are limited to MAX_CONTROL_REPORT_SIZE
+        let high_speed_out = RawInterfaceBuilder::new(DESCRIPTOR)
+            .speed(UsbSpeed::High)
+            .in_endpoint(UsbPacketSize::Bytes8, PollInterval::millis(20))
+            .with_out_endpoint(UsbPacketSize::Bytes128, PollInterval::MIN);
+        assert!(high_speed_out.clone().build().is_ok());
+        assert_eq!(
+            high_speed_out.out_report_queue::<2>().build().map(drop),
+            Err(UsbHidBuilderError::InvalidValue)
+        );
+    }
+
+    #[cfg(feature = "simulation")]
+    #[test]
+    fn high_speed_out_packet_longer_than_control_buffer() {
+        use usb_device::bus::UsbBusAllocator;
+
+        use crate::interface::raw::{RawInterface, RawInterfaceBuilder};
+        use crate::prelude::*;
+        use crate::simulation::{device_builder, SimBus};
+
+        let usb_alloc = UsbBusAllocator::new(SimBus::new());
+        let mut class = UsbHidClassBuilder::new()
+            .add_interface(
+                RawInterfaceBuilder::new(&[0x06, 0x00, 0xFF])
+                    .speed(UsbSpeed::High)
+                    .in_endpoint(UsbPacketSize::Bytes8, PollInterval::millis(20))
+                    .with_out_endpoint(UsbPacketSize::Bytes128, PollInterval::MIN)
+                    .build()
+                    .unwrap(),
+            )
+            .build(&usb_alloc)
+            .unwrap();
+        let mut usb_dev = device_builder(&usb_alloc, 64).build();
+
+        let report: [u8; 100] = core::array::from_fn(|i| i as u8);
+        usb_dev.bus().host_out(1, &report);
+        usb_dev.poll(&mut [&mut class]);
+
+        let mut data = [0; 128];
+        let interface = class.interface::<RawInterface<'_, _>, _>();
+        assert_eq!(interface.read_report(&mut data).unwrap(), 100);
+        assert_eq!(data[..100], report);
+    }
+
+    #[cfg(feature = "simulation")]
+    #[test]
+    fn input_complete_event_and_write_ready() {
+        use usb_device::bus::UsbBusAllocator;
+
+        use crate::interface::raw::{RawInterface, RawInterfaceBuilder};
+        use crate::interface::InterfaceClass;
+        use crate::prelude::*;
+        use crate::simulation::{device_builder, SimBus};
+
+        let usb_alloc = UsbBusAllocator::new(SimBus::new());
+        let mut class = UsbHidClassBuilder::new()
+            .add_interface(
+                RawInterfaceBuilder::new(&[0x05, 0x01])
+                    .in_endpoint(UsbPacketSize::Bytes8, PollInterval::millis(20))
+                    .build()
+                    .unwrap(),
+            )
+            .build(&usb_alloc)
+            .unwrap();
+        let mut usb_dev = device_builder(&usb_alloc, 64).build();
+
+        let interface = class.interface::<RawInterface<'_, _>, _>();
+        let id = interface.id();
+        assert!(interface.write_ready());
+        interface.write_report(&[1]).unwrap();
+        assert!(!interface.write_ready());
+
+        usb_dev.poll(&mut [&mut class]);
+        assert!(!class.poll_events().input_complete(id));
+
+        assert_eq!(usb_dev.bus().host_take_in(1).unwrap(), [1]);
+        usb_dev.poll(&mut [&mut class]);
+        assert!(class.poll_events().input_complete(id));
+        assert!(class.interface::<RawInterface<'_, _>, _>().write_ready());
+    }
+
+    #[cfg(feature = "simulation")]
+    #[test]
+    fn errors_are_usb_hid_errors() {
+        use usb_device::bus::UsbBusAllocator;
+
+        use crate::interface::raw::{RawInterface, RawInterfaceBuilder};
+        use crate::prelude::*;
+        use crate::simulation::{device_builder, SimBus};
+
+        let usb_alloc = UsbBusAllocator::new(SimBus::new());
+        let class = UsbHidClassBuilder::new()
+            .add_interface(
+                RawInterfaceBuilder::new(&[0x05, 0x01])
+                    .in_endpoint(UsbPacketSize::Bytes8, PollInterval::millis(20))
+                    .build()
+                    .unwrap(),
+            )
+            .build(&usb_alloc)
+            .unwrap();
+        let _usb_dev = device_builder(&usb_alloc, 64).build();
+
+        let interface = class.interface::<RawInterface<'_, _>, _>();
+        assert!(matches!(
+            interface.write_report(&[0; 200]),
+            Err(UsbHidError::ReportTooLong)
+        ));
+        assert!(matches!(
+            interface.read_report(&mut [0; 8]),
+            Err(UsbHidError::WouldBlock)
+        ));
+        interface.write_report(&[1]).unwrap();
+        assert!(matches!(
+            interface.write_report(&[2]),
+            Err(UsbHidError::WouldBlock)
+        ));
+    }
+
+    #[cfg(feature = "simulation")]
+    #[test]
+    fn output_reports_queued_while_polling() {
+        use usb_device::bus::UsbBusAllocator;
+
+        use crate::interface::raw::{RawInterface, RawInterfaceBuilder};
+        use crate::prelude::*;
+        use crate::simulation::{control_transfer, device_builder, Response, SimBus};
+
+        let usb_alloc = UsbBusAllocator::new(SimBus::new());
+        let mut class = UsbHidClassBuilder::new()
+            .add_interface(
+                RawInterfaceBuilder::new(&[0x05, 0x01])
+                    .in_endpoint(UsbPacketSize::Bytes8, PollInterval::millis(20))
+                    .with_out_endpoint(UsbPacketSize::Bytes8, PollInterval::millis(10))
+                    .out_report_queue::<3>()
+                    .build()
+                    .unwrap(),
+            )
+            .build(&usb_alloc)
+            .unwrap();
+        let mut usb_dev = device_builder(&usb_alloc, 64).build();
+
+        for report in [[1], [2], [3]] {
+            usb_dev.bus().host_out(1, &report);
+            usb_dev.poll(&mut [&mut class]);
+        }
+        // SET_REPORT (Output) while the queue is full drops the oldest report
+        assert_eq!(
+            control_transfer(
+                &mut usb_dev,
+                &mut [&mut class],
+                [0x21, 0x09, 0x00, 0x02, 0x00, 0x00, 0x01, 0x00],
+                &[4]
+            ),
+            Response::Ack
+        );
+
+        let interface = class.interface::<RawInterface<'_, _, 3>, _>();
+        assert_eq!(interface.queued_output_reports(), 3);
+        assert_eq!(interface.dropped_output_reports(), 1);
+        let mut data = [0; 8];
+        for expected in [2, 3, 4] {
+            assert_eq!(interface.read_report(&mut data).unwrap(), 1);
+            assert_eq!(data[0], expected);
+        }
+        assert!(matches!(
+            interface.read_report(&mut data),
+            Err(UsbHidError::WouldBlock)
+        ));
+    }
+
+    #[cfg(feature = "simulation")]
+    #[test]
+    fn input_reports_queued_until_endpoint_ready() {
+        use usb_device::bus::UsbBusAllocator;
+
+        use crate::interface::raw::{RawInterface, RawInterfaceBuilder};
+        use crate::prelude::*;
+        use crate::simulation::{device_builder, SimBus};
+
+        let usb_alloc = UsbBusAllocator::new(SimBus::new());
+        let mut class = UsbHidClassBuilder::new()
+            .add_interface(
+                RawInterfaceBuilder::new(&[0x05, 0x01])
+                    .in_endpoint(UsbPacketSize::Bytes8, PollInterval::millis(20))
+                    .in_report_queue::<2>()
+                    .build()
+                    .unwrap(),
+            )
+            .build(&usb_alloc)
+            .unwrap();
+        let mut usb_dev = device_builder(&usb_alloc, 64).build();
+
+        let interface = class.interface::<RawInterface<'_, _, 0, 2>, _>();
+        for report in [[1], [2], [3]] {
+            interface.write_report(&report).unwrap();
+        }
+        assert_eq!(interface.queued_input_reports(), 2);
+        assert!(matches!(
+            interface.write_report(&[4]),
+            Err(UsbHidError::WouldBlock)
+        ));
+        assert!(matches!(
+            interface.write_report(&[0; 9]),
+            Err(UsbHidError::ReportTooLong)
+        ));
+
+        // Each report is sent once the host has read the previous one
+        for expected in [1, 2, 3] {
+            assert_eq!(usb_dev.bus().host_take_in(1).unwrap(), [expected]);
+            usb_dev.poll(&mut [&mut class]);
+        }
+        assert_eq!(usb_dev.bus().host_take_in(1), None);
+        let interface = class.interface::<RawInterface<'_, _, 0, 2>, _>();
+        assert_eq!(interface.queued_input_reports(), 0);
+        assert!(interface.flush().is_ok());
+    }
+
+    #[cfg(feature = "simulation")]
+    #[test]
+    fn host_stall_discards_queued_reports() {
+        use fugit::ExtU32;
+        use usb_device::bus::UsbBusAllocator;
+
+        use crate::interface::raw::{RawInterface, RawInterfaceBuilder};
+        use crate::prelude::*;
+        use crate::simulation::{device_builder, SimBus};
+
+        let usb_alloc = UsbBusAllocator::new(SimBus::new());
+        let mut class = UsbHidClassBuilder::new()
+            .add_interface(
+                RawInterfaceBuilder::new(&[0x05, 0x01])
+                    .in_endpoint(UsbPacketSize::Bytes8, PollInterval::millis(20))
+                    .stall_timeout(5.millis())
+                    .in_report_queue::<2>()
+                    .build()
+                    .unwrap(),
+            )
+            .build(&usb_alloc)
+            .unwrap();
+        let mut usb_dev = device_builder(&usb_alloc, 64).build();
+
+        let interface = class.interface::<RawInterface<'_, _, 0, 2>, _>();
+        for report in [[1], [2], [3]] {
+            interface.write_report(&report).unwrap();
+        }
+        (0..4).for_each(|_| assert!(!interface.tick()));
+        assert!(interface.tick());
+        assert!(interface.host_stalled());
+        assert_eq!(interface.host_stalls(), 1);
+        assert_eq!(interface.queued_input_reports(), 0);
+
+        // The report already on the endpoint is sent when the host resumes polling
+        assert_eq!(usb_dev.bus().host_take_in(1).unwrap(), [1]);
+        usb_dev.poll(&mut [&mut class]);
+        assert_eq!(usb_dev.bus().host_take_in(1), None);
+        assert!(!class
+            .interface::<RawInterface<'_, _, 0, 2>, _>()
+            .host_stalled());
+    }
+
+    #[cfg(feature = "simulation")]
+    #[test]
+    fn wakeup_requested_by_written_reports_until_host_stalls() {
+        use fugit::ExtU32;
+        use usb_device::bus::UsbBusAllocator;
+
+        use crate::interface::raw::{RawInterface, RawInterfaceBuilder};
+        use crate::interface::InterfaceClass;
+        use crate::prelude::*;
+        use crate::simulation::{device_builder, SimBus};
+
+        type Interface<'a> = RawInterface<'a, SimBus, 0, 2>;
+
+        let usb_alloc = UsbBusAllocator::new(SimBus::new());
+        let mut class = UsbHidClassBuilder::new()
+            .add_interface(
+                RawInterfaceBuilder::new(&[0x05, 0x01])
+                    .in_endpoint(UsbPacketSize::Bytes8, PollInterval::millis(20))
+                    .stall_timeout(5.millis())
+                    .in_report_queue::<2>()
+                    .wake_on_report(true)
+                    .build()
+                    .unwrap(),
+            )
+            .build(&usb_alloc)
+            .unwrap();
+        let mut usb_dev = device_builder(&usb_alloc, 64).build();
+
+        let interface = class.interface::<Interface<'_>, _>();
+        interface.write_report(&[1]).unwrap();
+        assert!(interface.take_wakeup_request());
+        interface.write_report(&[2]).unwrap();
+
+        // The stall cancels the request, and reports written while stalled don't request another
+        (0..5).for_each(|_| {
+            interface.tick();
+        });
+        assert!(interface.host_stalled());
+        assert!(!interface.take_wakeup_request());
+        interface.write_report(&[3]).unwrap();
+        assert!(!interface.take_wakeup_request());
+
+        assert_eq!(usb_dev.bus().host_take_in(1).unwrap(), [1]);
+        usb_dev.poll(&mut [&mut class]);
+        let interface = class.interface::<Interface<'_>, _>();
+        assert!(!interface.host_stalled());
+        interface.write_report(&[4]).unwrap();
+        interface.write_report(&[5]).unwrap();
+        assert!(interface.take_wakeup_request());
+
+        // Reports that aren't written don't request a wakeup
+        assert!(matches!(
+            interface.write_report(&[6]),
+            Err(UsbHidError::WouldBlock)
+        ));
+        assert!(!interface.take_wakeup_request());
+    }
+
+    #[cfg(all(feature = "async", feature = "simulation"))]
+    #[test]
+    fn async_read_and_write_woken_by_poll() {
+        use core::future::Future;
+        use core::pin::pin;
+        use core::task::{Context, Poll};
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Arc;
+        use std::task::Wake;
+
+        use usb_device::bus::UsbBusAllocator;
+
+        use crate::interface::raw::{RawInterface, RawInterfaceBuilder};
+        use crate::prelude::*;
+        use crate::simulation::{device_builder, SimBus};
+
+        #[derive(Default)]
+        struct CountingWaker(AtomicUsize);
+
+        impl Wake for CountingWaker {
+            fn wake(self: Arc<Self>) {
+                self.0.fetch_add(1, Ordering::SeqCst);
+            }
+        }
+
+        let usb_alloc = UsbBusAllocator::new(SimBus::new());
+        let mut class = UsbHidClassBuilder::new()
+            .add_interface(
+                RawInterfaceBuilder::new(&[0x05, 0x01])
+                    .in_endpoint(UsbPacketSize::Bytes8, PollInterval::millis(20))
+                    .with_out_endpoint(UsbPacketSize::Bytes8, PollInterval::millis(10))
+                    .build()
+                    .unwrap(),
+            )
+            .build(&usb_alloc)
+            .unwrap();
+        let mut usb_dev = device_builder(&usb_alloc, 64).build();
+
+        let counter = Arc::new(CountingWaker::default());
+        let waker = counter.clone().into();
+        let mut cx = Context::from_waker(&waker);
+
+        // The first report fills the IN endpoint, the second waits for the host to read it
+        let interface = class.interface::<RawInterface<'_, _>, _>();
+        assert!(matches!(
+            interface.poll_write_report(&mut cx, &[1]),
+            Poll::Ready(Ok(1))
+        ));
+        assert!(interface.poll_write_report(&mut cx, &[2]).is_pending());
+        assert_eq!(usb_dev.bus().host_take_in(1).unwrap(), [1]);
+        assert_eq!(counter.0.load(Ordering::SeqCst), 0);
+        usb_dev.poll(&mut [&mut class]);
+        assert_eq!(counter.0.load(Ordering::SeqCst), 1);
+        let interface = class.interface::<RawInterface<'_, _>, _>();
+        {
+            let write = pin!(interface.write_report_async(&[2]));
+            assert!(matches!(write.poll(&mut cx), Poll::Ready(Ok(1))));
+        }
+
+        // A read waits for the host to write the OUT endpoint
+        let mut data = [0; 8];
+        assert!(interface.poll_read_report(&mut cx, &mut data).is_pending());
+        usb_dev.bus().host_out(1, &[0x42]);
+        usb_dev.poll(&mut [&mut class]);
+        assert_eq!(counter.0.load(Ordering::SeqCst), 2);
+        let interface = class.interface::<RawInterface<'_, _>, _>();
+        assert!(matches!(
+            interface.poll_read_report(&mut cx, &mut data),
+            Poll::Ready(Ok(1))
+        ));
+        assert_eq!(data[0], 0x42);
     }
 }
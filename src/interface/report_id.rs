@@ -0,0 +1,525 @@
+//! Interfaces with several numbered reports
+//!
+//! [`ReportIdInterface`] prefixes input reports with their report ID and manages HID idle for
+//! each report ID separately. Output and feature reports are routed by report ID to a
+//! [`ReportSink`], such as [`FeatureReports`](crate::interface::feature::FeatureReports).
+//! `GET_REPORT` requests are answered by the handler's [`ReportSource`], falling back to the last
+//! input report written for each report ID.
+use core::cell::RefCell;
+
+use delegate::delegate;
+use fugit::{ExtU32, MillisDurationU32};
+use heapless::LinearMap;
+use packed_struct::types::bits::ByteArray;
+use packed_struct::PackedStruct;
+use usb_device::bus::{StringIndex, UsbBus};
+use usb_device::class_prelude::*;
+use usb_device::UsbError;
+
+use crate::hid_class::descriptor::{HidProtocol, ReportType};
+use crate::interface::latency::LatencyHistogram;
+use crate::interface::managed::IdleManager;
+use crate::interface::raw::RawInterface;
+use crate::interface::{HidDescriptorBodyBytes, InterfaceClass, InterfaceNumber, WrappedInterface};
+use crate::UsbHidError;
+
+/// Longest input report following the report ID, filling a full speed interrupt packet
+pub const MAX_NUMBERED_REPORT_LEN: usize = 63;
+
+/// Input report sent with a report ID
+pub trait NumberedReport: PackedStruct {
+    const REPORT_ID: u8;
+}
+
+/// Source of the reports returned for `GET_REPORT` requests
+///
+/// `data` excludes the report ID, which is prefixed by the interface. Returns the length of the
+/// report written to `data`. The default supports no reports.
+pub trait ReportSource {
+    fn get_report(
+        &mut self,
+        report_type: ReportType,
+        report_id: u8,
+        data: &mut [u8],
+    ) -> usb_device::Result<usize> {
+        let _ = data;
+        warn!(
+            "Unsupported GetReport type: {:?}, ID: {:X}",
+            report_type, report_id
+        );
+        Err(UsbError::Unsupported)
+    }
+}
+
+/// Sink for the reports sent by the host, with `SET_REPORT` requests or to the OUT endpoint
+///
+/// `data` excludes the report ID, reports from the OUT endpoint have the
+/// [`ReportType::Output`] type. The default rejects all reports.
+pub trait ReportSink {
+    fn set_report(
+        &mut self,
+        report_type: ReportType,
+        report_id: u8,
+        data: &[u8],
+    ) -> usb_device::Result<()> {
+        let _ = data;
+        warn!(
+            "Unsupported SetReport type: {:?}, ID: {:X}",
+            report_type, report_id
+        );
+        Err(UsbError::Unsupported)
+    }
+}
+
+/// Handler for the reports of a [`ReportIdInterface`], implemented for every type that is both a
+/// [`ReportSource`] and a [`ReportSink`]
+pub trait ReportHandler: ReportSource + ReportSink {}
+
+impl<T: ReportSource + ReportSink> ReportHandler for T {}
+
+impl ReportSource for () {}
+
+impl ReportSink for () {}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct ReportData {
+    len: usize,
+    data: [u8; MAX_NUMBERED_REPORT_LEN + 1],
+}
+
+impl ReportData {
+    fn new(report_id: u8, report: &[u8]) -> Option<Self> {
+        let mut data = [0; MAX_NUMBERED_REPORT_LEN + 1];
+        data[0] = report_id;
+        data.get_mut(1..=report.len())?.copy_from_slice(report);
+        Some(Self {
+            len: report.len() + 1,
+            data,
+        })
+    }
+
+    fn as_slice(&self) -> &[u8] {
+        &self.data[..self.len]
+    }
+}
+
+/// Interface sending up to `N` input reports with different report IDs
+///
+/// **Note:** Like [`ManagedInterface`](crate::interface::managed::ManagedInterface),
+/// [`ReportIdInterface::tick()`] must be called every 1ms/at 1kHz to repeat reports at the
+/// host's idle rate.
+pub struct ReportIdInterface<'a, B: UsbBus, H, const N: usize> {
+    inner: RawInterface<'a, B>,
+    handler: H,
+    reports: RefCell<LinearMap<u8, IdleManager<ReportData>, N>>,
+    control_report_sent: bool,
+}
+
+fn idle_duration<B: UsbBus>(inner: &RawInterface<'_, B>, report_id: u8) -> MillisDurationU32 {
+    inner
+        .report_idle(report_id)
+        .unwrap_or_else(|| inner.global_idle())
+}
+
+impl<'a, B: UsbBus, H: ReportHandler, const N: usize> ReportIdInterface<'a, B, H, N> {
+    delegate! {
+        to self.inner {
+            pub fn protocol(&self) -> HidProtocol;
+            pub fn report_latency(&self) -> LatencyHistogram;
+            pub fn clear_report_latency(&self);
+            pub fn host_stalls(&self) -> u32;
+            pub fn host_stalled(&self) -> bool;
+            pub fn write_ready(&self) -> bool;
+        }
+    }
+
+    pub fn handler(&self) -> &H {
+        &self.handler
+    }
+
+    pub fn handler_mut(&mut self) -> &mut H {
+        &mut self.handler
+    }
+
+    pub fn write_input<R: NumberedReport>(&self, report: &R) -> Result<(), UsbHidError> {
+        let data = report.pack().map_err(|e| {
+            error!("Error packing report: {:?}", e);
+            UsbHidError::SerializationError
+        })?;
+        self.write_report(R::REPORT_ID, data.as_bytes_slice())
+    }
+
+    /// Writes `data` with the report ID prefixed
+    ///
+    /// Returns [`UsbHidError::Duplicate`] if `data` is the last report written with the same
+    /// report ID.
+    pub fn write_report(&self, report_id: u8, data: &[u8]) -> Result<(), UsbHidError> {
+        let report = ReportData::new(report_id, data).ok_or_else(|| {
+            error!(
+                "Report ID {:X} is too long: {:X} bytes",
+                report_id,
+                data.len()
+            );
+            UsbHidError::ReportTooLong
+        })?;
+
+        let mut reports = self.reports.borrow_mut();
+        if !reports.contains_key(&report_id) {
+            let idle_manager = IdleManager::new(idle_duration(&self.inner, report_id));
+            if reports.insert(report_id, idle_manager).is_err() {
+                error!("No space for report ID {:X}, maximum {} IDs", report_id, N);
+                return Err(UsbHidError::UsbError(UsbError::BufferOverflow));
+            }
+        }
+        let idle_manager = reports.get_mut(&report_id).unwrap();
+
+        if idle_manager.is_duplicate(&report) {
+            Err(UsbHidError::Duplicate)
+        } else {
+            self.inner.write_report(report.as_slice())?;
+            idle_manager.report_written(report);
+            Ok(())
+        }
+    }
+
+    /// Call every 1ms / at 1 KHz
+    pub fn tick(&self) -> Result<(), UsbHidError> {
+        self.tick_with(1.millis())
+    }
+
+    /// Advances the idle timers of all reports by `elapsed`, see
+    /// [`ManagedInterface::tick_with`](crate::interface::managed::ManagedInterface::tick_with)
+    pub fn tick_with(&self, elapsed: MillisDurationU32) -> Result<(), UsbHidError> {
+        let mut reports = self.reports.borrow_mut();
+        if self.inner.tick_with(elapsed) {
+            reports.values_mut().for_each(IdleManager::report_lost);
+        }
+
+        let mut result = Ok(());
+        for idle_manager in reports.values_mut() {
+            if !idle_manager.tick_with(elapsed) {
+                continue;
+            }
+            if let Some(report) = idle_manager.last_report() {
+                match self.inner.write_report(report.as_slice()) {
+                    Ok(_) => idle_manager.report_written(report),
+                    Err(e) => result = Err(e),
+                }
+            }
+        }
+        result
+    }
+
+    pub fn read_report(&self, data: &mut [u8]) -> Result<usize, UsbHidError> {
+        self.inner.read_report(data)
+    }
+
+    fn route_report(&mut self, report_type: ReportType, data: &[u8]) -> usb_device::Result<()> {
+        match data {
+            [report_id, report @ ..] => self.handler.set_report(report_type, *report_id, report),
+            [] => Err(UsbError::ParseError),
+        }
+    }
+}
+
+impl<'a, B: UsbBus, H: ReportHandler, const N: usize> InterfaceClass<'a>
+    for ReportIdInterface<'a, B, H, N>
+{
+    delegate! {
+        to self.inner{
+           fn report_descriptor(&self) -> &'_ [u8];
+           fn hid_descriptor_body(&self) -> HidDescriptorBodyBytes;
+           fn class_descriptor(&self, descriptor_type: u8, index: u8) -> Option<&'_ [u8]>;
+           fn take_wakeup_request(&self) -> bool;
+           fn id(&self) -> InterfaceNumber;
+           fn write_descriptors(&self, writer: &mut DescriptorWriter) -> usb_device::Result<()>;
+           fn get_string(&self, index: StringIndex, _lang_id: u16) -> Option<&'_ str>;
+           fn get_report(&mut self, data: &mut [u8]) -> usb_device::Result<usize>;
+           fn get_idle(&self, report_id: u8) -> u8;
+           fn set_protocol(&mut self, protocol: HidProtocol) -> usb_device::Result<()>;
+           fn get_protocol(&self) -> HidProtocol;
+           fn out_endpoint_address(&self) -> Option<EndpointAddress>;
+           fn in_endpoint_address(&self) -> EndpointAddress;
+           fn endpoint_in_complete(&self, addr: EndpointAddress);
+        }
+    }
+
+    fn reset(&mut self) {
+        self.inner.reset();
+        self.reports.get_mut().clear();
+        self.control_report_sent = false;
+    }
+
+    fn set_report(&mut self, data: &[u8]) -> usb_device::Result<()> {
+        self.route_report(ReportType::Output, data)
+    }
+
+    fn set_report_by_id(
+        &mut self,
+        report_type: ReportType,
+        report_id: u8,
+        data: &[u8],
+    ) -> usb_device::Result<()> {
+        if data.first() != Some(&report_id) {
+            warn!(
+                "SetReport ID {:X} does not match the report's ID prefix",
+                report_id
+            );
+            return Err(UsbError::ParseError);
+        }
+        self.route_report(report_type, data)
+    }
+
+    fn endpoint_out(&mut self) {
+        let mut data = [0; 64];
+        if let Ok(n) = self.inner.read_report(&mut data) {
+            self.route_report(ReportType::Output, &data[..n]).ok();
+        }
+    }
+
+    fn get_report_by_id(
+        &mut self,
+        report_type: ReportType,
+        report_id: u8,
+        data: &mut [u8],
+    ) -> usb_device::Result<usize> {
+        let mut buffer = [0; MAX_NUMBERED_REPORT_LEN + 1];
+        buffer[0] = report_id;
+        let len = match self
+            .handler
+            .get_report(report_type, report_id, &mut buffer[1..])
+        {
+            Ok(n) => n + 1,
+            // Input reports not provided by the handler are the last report written
+            Err(UsbError::Unsupported) if report_type == ReportType::Input => {
+                let report = self
+                    .reports
+                    .get_mut()
+                    .get(&report_id)
+                    .and_then(IdleManager::last_report)
+                    .ok_or(UsbError::WouldBlock)?;
+                buffer[..report.len].copy_from_slice(report.as_slice());
+                report.len
+            }
+            Err(e) => return Err(e),
+        };
+        let data = data.get_mut(..len).ok_or(UsbError::BufferOverflow)?;
+        data.copy_from_slice(&buffer[..len]);
+        self.control_report_sent = true;
+        Ok(len)
+    }
+
+    fn get_report_ack(&mut self) -> usb_device::Result<()> {
+        if self.control_report_sent {
+            self.control_report_sent = false;
+            Ok(())
+        } else {
+            self.inner.get_report_ack()
+        }
+    }
+
+    fn set_idle(&mut self, report_id: u8, value: u8) {
+        self.inner.set_idle(report_id, value);
+        let inner = &self.inner;
+        for (&id, idle_manager) in self.reports.get_mut().iter_mut() {
+            idle_manager.set_duration(idle_duration(inner, id));
+        }
+    }
+}
+
+impl<'a, B: UsbBus, H: ReportHandler, const N: usize>
+    WrappedInterface<'a, B, RawInterface<'a, B>, H> for ReportIdInterface<'a, B, H, N>
+{
+    fn new(interface: RawInterface<'a, B>, handler: H) -> Self {
+        Self {
+            inner: interface,
+            handler,
+            reports: RefCell::new(LinearMap::new()),
+            control_report_sent: false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::interface::report_id::*;
+
+    #[test]
+    fn report_data_prefixes_id() {
+        let report = ReportData::new(3, &[0xAA, 0xBB]).unwrap();
+        assert_eq!(report.as_slice(), [3, 0xAA, 0xBB]);
+        assert!(ReportData::new(1, &[0; MAX_NUMBERED_REPORT_LEN]).is_some());
+        assert!(ReportData::new(1, &[0; MAX_NUMBERED_REPORT_LEN + 1]).is_none());
+    }
+
+    #[cfg(feature = "simulation")]
+    #[test]
+    fn reports_routed_by_id() {
+        use std::vec::Vec;
+
+        use usb_device::bus::UsbBusAllocator;
+        use usb_device::prelude::*;
+
+        use crate::hid_class::UsbPacketSize;
+        use crate::interface::duration::PollInterval;
+        use crate::interface::raw::RawInterfaceBuilder;
+        use crate::interface::WrappedInterfaceConfig;
+        use crate::prelude::UsbHidClassBuilder;
+        use crate::simulation::{control_transfer, device_builder, Response, SimBus};
+
+        #[derive(Default)]
+        struct Recorder {
+            reports: Vec<(ReportType, u8, Vec<u8>)>,
+        }
+
+        impl ReportSink for Recorder {
+            fn set_report(
+                &mut self,
+                report_type: ReportType,
+                report_id: u8,
+                data: &[u8],
+            ) -> usb_device::Result<()> {
+                self.reports.push((report_type, report_id, data.to_vec()));
+                Ok(())
+            }
+        }
+
+        impl ReportSource for Recorder {
+            fn get_report(
+                &mut self,
+                report_type: ReportType,
+                report_id: u8,
+                data: &mut [u8],
+            ) -> usb_device::Result<usize> {
+                match (report_type, report_id) {
+                    (ReportType::Feature, 4) => {
+                        data[..2].copy_from_slice(&[0x12, 0x34]);
+                        Ok(2)
+                    }
+                    (ReportType::Input, 6) => {
+                        data[0] = 0x56;
+                        Ok(1)
+                    }
+                    _ => Err(UsbError::Unsupported),
+                }
+            }
+        }
+
+        type Interface<'a> = ReportIdInterface<'a, SimBus, Recorder, 2>;
+
+        let usb_alloc = UsbBusAllocator::new(SimBus::new());
+        let config: WrappedInterfaceConfig<Interface<'_>, _, _> = WrappedInterfaceConfig::new(
+            RawInterfaceBuilder::new(&[0x05, 0x01])
+                .in_endpoint(UsbPacketSize::Bytes8, PollInterval::millis(20))
+                .build()
+                .unwrap(),
+            Recorder::default(),
+        );
+        let mut class = UsbHidClassBuilder::new()
+            .add_interface(config)
+            .build(&usb_alloc)
+            .unwrap();
+        let mut usb_dev = device_builder(&usb_alloc, 64).build();
+
+        let interface = class.interface::<Interface<'_>, _>();
+        interface.write_report(1, &[0x01]).unwrap();
+        assert_eq!(usb_dev.bus().host_take_in(1).unwrap(), [0x01, 0x01]);
+        interface.write_report(2, &[0x02, 0x03]).unwrap();
+        assert_eq!(usb_dev.bus().host_take_in(1).unwrap(), [0x02, 0x02, 0x03]);
+        assert!(matches!(
+            interface.write_report(1, &[0x01]),
+            Err(UsbHidError::Duplicate)
+        ));
+        assert!(matches!(
+            interface.write_report(3, &[0x01]),
+            Err(UsbHidError::UsbError(UsbError::BufferOverflow))
+        ));
+
+        // SET_IDLE 4ms for report ID 2 only
+        assert_eq!(
+            control_transfer(
+                &mut usb_dev,
+                &mut [&mut class],
+                [0x21, 0x0A, 0x02, 0x01, 0x00, 0x00, 0x00, 0x00],
+                &[]
+            ),
+            Response::Ack
+        );
+        let interface = class.interface::<Interface<'_>, _>();
+        for _ in 0..5 {
+            interface.tick().unwrap();
+        }
+        assert_eq!(usb_dev.bus().host_take_in(1).unwrap(), [0x02, 0x02, 0x03]);
+        assert!(usb_dev.bus().host_take_in(1).is_none());
+
+        // GET_REPORT (Input) ID 1
+        assert_eq!(
+            control_transfer(
+                &mut usb_dev,
+                &mut [&mut class],
+                [0xA1, 0x01, 0x01, 0x01, 0x00, 0x00, 0x40, 0x00],
+                &[]
+            ),
+            Response::Data(vec![0x01, 0x01])
+        );
+        // GET_REPORT (Input) ID 6, provided by the handler
+        assert_eq!(
+            control_transfer(
+                &mut usb_dev,
+                &mut [&mut class],
+                [0xA1, 0x01, 0x06, 0x01, 0x00, 0x00, 0x40, 0x00],
+                &[]
+            ),
+            Response::Data(vec![0x06, 0x56])
+        );
+        // GET_REPORT (Feature) ID 4
+        assert_eq!(
+            control_transfer(
+                &mut usb_dev,
+                &mut [&mut class],
+                [0xA1, 0x01, 0x04, 0x03, 0x00, 0x00, 0x40, 0x00],
+                &[]
+            ),
+            Response::Data(vec![0x04, 0x12, 0x34])
+        );
+        // SET_REPORT (Output) ID 5
+        assert_eq!(
+            control_transfer(
+                &mut usb_dev,
+                &mut [&mut class],
+                [0x21, 0x09, 0x05, 0x02, 0x00, 0x00, 0x02, 0x00],
+                &[0x05, 0x42]
+            ),
+            Response::Ack
+        );
+        // SET_REPORT (Feature) ID 7
+        assert_eq!(
+            control_transfer(
+                &mut usb_dev,
+                &mut [&mut class],
+                [0x21, 0x09, 0x07, 0x03, 0x00, 0x00, 0x03, 0x00],
+                &[0x07, 0x01, 0x02]
+            ),
+            Response::Ack
+        );
+        // SET_REPORT (Feature) ID 8 with a mismatched ID prefix is stalled
+        assert_eq!(
+            control_transfer(
+                &mut usb_dev,
+                &mut [&mut class],
+                [0x21, 0x09, 0x08, 0x03, 0x00, 0x00, 0x02, 0x00],
+                &[0x09, 0x01]
+            ),
+            Response::Stall
+        );
+
+        let interface = class.interface::<Interface<'_>, _>();
+        assert_eq!(
+            interface.handler().reports,
+            [
+                (ReportType::Output, 5, vec![0x42]),
+                (ReportType::Feature, 7, vec![0x01, 0x02])
+            ]
+        );
+    }
+}
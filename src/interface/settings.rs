@@ -0,0 +1,112 @@
+//! Persistence of host configured settings
+//!
+//! Devices that accept configuration from the host through feature reports, such as DPI or key
+//! remaps, save the report data through a [`SettingsStore`] so firmware can back it with EEPROM or
+//! flash.
+
+use heapless::{LinearMap, Vec};
+
+/// Storage for settings blobs, keyed by the ID of the report that carried them
+pub trait SettingsStore {
+    type Error;
+
+    /// Reads the blob saved for `report_id` into `data`
+    ///
+    /// Returns the length of the blob, or `None` if nothing has been saved for `report_id`.
+    fn load(&mut self, report_id: u8, data: &mut [u8]) -> Result<Option<usize>, Self::Error>;
+
+    fn save(&mut self, report_id: u8, data: &[u8]) -> Result<(), Self::Error>;
+}
+
+impl<S: SettingsStore> SettingsStore for &mut S {
+    type Error = S::Error;
+
+    fn load(&mut self, report_id: u8, data: &mut [u8]) -> Result<Option<usize>, Self::Error> {
+        (**self).load(report_id, data)
+    }
+
+    fn save(&mut self, report_id: u8, data: &[u8]) -> Result<(), Self::Error> {
+        (**self).save(report_id, data)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MemorySettingsError {
+    /// The blob is longer than the store's blob size, or the load buffer is too small
+    BufferOverflow,
+    /// All slots are in use by other report IDs
+    Full,
+}
+
+/// Volatile [`SettingsStore`] with `N` slots of up to `LEN` bytes
+///
+/// Useful for testing, or for devices without persistent storage that should still remember
+/// settings until power is removed.
+#[derive(Debug, Default)]
+pub struct MemorySettingsStore<const N: usize, const LEN: usize> {
+    blobs: LinearMap<u8, Vec<u8, LEN>, N>,
+}
+
+impl<const N: usize, const LEN: usize> MemorySettingsStore<N, LEN> {
+    pub fn new() -> Self {
+        Self {
+            blobs: LinearMap::new(),
+        }
+    }
+}
+
+impl<const N: usize, const LEN: usize> SettingsStore for MemorySettingsStore<N, LEN> {
+    type Error = MemorySettingsError;
+
+    fn load(&mut self, report_id: u8, data: &mut [u8]) -> Result<Option<usize>, Self::Error> {
+        match self.blobs.get(&report_id) {
+            None => Ok(None),
+            Some(blob) if blob.len() > data.len() => Err(MemorySettingsError::BufferOverflow),
+            Some(blob) => {
+                data[..blob.len()].copy_from_slice(blob);
+                Ok(Some(blob.len()))
+            }
+        }
+    }
+
+    fn save(&mut self, report_id: u8, data: &[u8]) -> Result<(), Self::Error> {
+        let blob = Vec::from_slice(data).map_err(|_| MemorySettingsError::BufferOverflow)?;
+        self.blobs
+            .insert(report_id, blob)
+            .map(|_| ())
+            .map_err(|_| MemorySettingsError::Full)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::interface::settings::{MemorySettingsError, MemorySettingsStore, SettingsStore};
+
+    #[test]
+    fn memory_store_round_trip() {
+        let mut store = MemorySettingsStore::<2, 4>::new();
+        let mut data = [0; 4];
+
+        assert_eq!(store.load(1, &mut data), Ok(None));
+        assert_eq!(store.save(1, &[1, 2, 3]), Ok(()));
+        assert_eq!(store.save(1, &[4, 5]), Ok(()));
+        assert_eq!(store.load(1, &mut data), Ok(Some(2)));
+        assert_eq!(data[..2], [4, 5]);
+    }
+
+    #[test]
+    fn memory_store_limits() {
+        let mut store = MemorySettingsStore::<1, 2>::new();
+
+        assert_eq!(
+            store.save(1, &[1, 2, 3]),
+            Err(MemorySettingsError::BufferOverflow)
+        );
+        assert_eq!(store.save(1, &[1, 2]), Ok(()));
+        assert_eq!(store.save(2, &[1]), Err(MemorySettingsError::Full));
+        assert_eq!(
+            store.load(1, &mut [0; 1]),
+            Err(MemorySettingsError::BufferOverflow)
+        );
+    }
+}
@@ -0,0 +1,220 @@
+//! Interfaces listed as a tuple, as an alternative to the HList built by
+//! [`UsbHidClassBuilder::add_interface`](crate::hid_class::UsbHidClassBuilder::add_interface)
+//!
+//! ```
+//! # #[cfg(not(feature = "usb-device-03"))] {
+//! use usb_device::bus::{UsbBus, UsbBusAllocator};
+//! use usbd_human_interface_device::device::keyboard::{BootKeyboard, BootKeyboardReport};
+//! use usbd_human_interface_device::device::mouse::BootMouse;
+//! use usbd_human_interface_device::page::Keyboard;
+//! use usbd_human_interface_device::prelude::*;
+//!
+//! fn hid_class<B: UsbBus>(usb_alloc: &UsbBusAllocator<B>) {
+//!     let mut hid =
+//!         UsbHidClassBuilder::from_tuple((BootKeyboard::default_config(), BootMouse::default_config()))
+//!             .build(usb_alloc)
+//!             .unwrap();
+//!
+//!     let (keyboard, _mouse) = hid.interface_tuple();
+//!     keyboard
+//!         .write_report(&BootKeyboardReport::new([Keyboard::A]))
+//!         .ok();
+//!     // usb_dev.poll(&mut [&mut hid]);
+//! #   let _ = &mut hid;
+//! }
+//! # }
+//! ```
+//!
+//! Interfaces are numbered in tuple order, the first element is interface 0, and
+//! [`InterfaceAssociation::first`](crate::hid_class::InterfaceAssociation) indexes the tuple.
+//! Tuples of up to [`MAX_TUPLE_INTERFACES`] interfaces are supported. The class type names each
+//! interface once, rather than nesting them in `HCons`, and interfaces are accessed by position
+//! rather than by type, so there are no HList indices for the compiler to infer.
+//!
+//! Tuple interface lists don't use frunk, and are the only interface lists built by
+//! [`UsbHidClassBuilder`](crate::hid_class::UsbHidClassBuilder) when the default `frunk` feature
+//! is disabled. Start them with
+//! [`UsbHidClassBuilder::from_tuple`](crate::hid_class::UsbHidClassBuilder::from_tuple) rather
+//! than `UsbHidClassBuilder::new().with_interfaces(..)`.
+
+use usb_device::bus::{StringIndex, UsbBus, UsbBusAllocator};
+use usb_device::class_prelude::DescriptorWriter;
+use usb_device::endpoint::EndpointAddress;
+
+use crate::hid_class::budget::{EndpointRequirements, EndpointUsage};
+use crate::hid_class::InterfaceAssociation;
+use crate::interface::{InterfaceClass, InterfaceHList, UsbAllocatable};
+
+/// Largest tuple of interfaces supported
+pub const MAX_TUPLE_INTERFACES: usize = 8;
+
+/// Tuple of interface configs or interfaces, of 1 to [`MAX_TUPLE_INTERFACES`] elements
+pub trait InterfaceTuple {
+    const LEN: usize;
+}
+
+macro_rules! interface_tuple {
+    ($len:literal: $($I:ident $index:tt),+) => {
+        impl<$($I),+> InterfaceTuple for ($($I,)+) {
+            const LEN: usize = $len;
+        }
+
+        impl<'a, B: UsbBus + 'a, $($I: UsbAllocatable<'a, B>),+> UsbAllocatable<'a, B>
+            for ($($I,)+)
+        {
+            type Allocated = ($($I::Allocated,)+);
+
+            fn allocate(self, usb_alloc: &'a UsbBusAllocator<B>) -> Self::Allocated {
+                // Tuple expressions are evaluated left to right, numbering interfaces in order
+                ($(self.$index.allocate(usb_alloc),)+)
+            }
+        }
+
+        impl<$($I: EndpointRequirements),+> EndpointRequirements for ($($I,)+) {
+            fn endpoint_usage(&self) -> EndpointUsage {
+                EndpointUsage::NONE$(.add(self.$index.endpoint_usage()))+
+            }
+        }
+
+        impl<'a, $($I: InterfaceClass<'a> + 'a),+> InterfaceHList<'a> for ($($I,)+) {
+            fn get_id_mut(&mut self, id: u8) -> Option<&mut dyn InterfaceClass<'a>> {
+                $(
+                    if u8::from(self.$index.id()) == id {
+                        return Some(&mut self.$index);
+                    }
+                )+
+                None
+            }
+
+            fn get_id(&self, id: u8) -> Option<&dyn InterfaceClass<'a>> {
+                $(
+                    if u8::from(self.$index.id()) == id {
+                        return Some(&self.$index);
+                    }
+                )+
+                None
+            }
+
+            fn get_out_endpoint_id(&self, addr: EndpointAddress) -> Option<u8> {
+                $(
+                    if self.$index.out_endpoint_address() == Some(addr) {
+                        return Some(u8::from(self.$index.id()));
+                    }
+                )+
+                None
+            }
+
+            fn get_in_endpoint_id(&self, addr: EndpointAddress) -> Option<u8> {
+                $(
+                    if self.$index.in_endpoint_address() == addr {
+                        return Some(u8::from(self.$index.id()));
+                    }
+                )+
+                None
+            }
+
+            fn endpoint_in_complete(&self, addr: EndpointAddress) {
+                $(self.$index.endpoint_in_complete(addr);)+
+            }
+
+            fn poll(&self) {
+                $(self.$index.poll();)+
+            }
+
+            fn start_of_frame(&mut self, frame_number: u16) {
+                $(self.$index.start_of_frame(frame_number);)+
+            }
+
+            fn reset(&mut self) {
+                $(self.$index.reset();)+
+            }
+
+            fn take_wakeup_request(&self) -> bool {
+                //Clear every interface's request, not only the first
+                let mut requested = false;
+                $(requested |= self.$index.take_wakeup_request();)+
+                requested
+            }
+
+            fn write_descriptors(
+                &self,
+                writer: &mut DescriptorWriter,
+                associations: &[InterfaceAssociation],
+            ) -> usb_device::Result<()> {
+                $(
+                    if let Some(association) =
+                        associations.iter().find(|a| usize::from(a.first) == $index)
+                    {
+                        association.write_descriptor(writer, self.$index.id())?;
+                    }
+                    self.$index.write_descriptors(writer)?;
+                )+
+                Ok(())
+            }
+
+            fn get_string(&self, index: StringIndex, lang_id: u16) -> Option<&'_ str> {
+                None$(.or_else(|| self.$index.get_string(index, lang_id)))+
+            }
+        }
+    };
+}
+
+interface_tuple!(1: I0 0);
+interface_tuple!(2: I0 0, I1 1);
+interface_tuple!(3: I0 0, I1 1, I2 2);
+interface_tuple!(4: I0 0, I1 1, I2 2, I3 3);
+interface_tuple!(5: I0 0, I1 1, I2 2, I3 3, I4 4);
+interface_tuple!(6: I0 0, I1 1, I2 2, I3 3, I4 4, I5 5);
+interface_tuple!(7: I0 0, I1 1, I2 2, I3 3, I4 4, I5 5, I6 6);
+interface_tuple!(8: I0 0, I1 1, I2 2, I3 3, I4 4, I5 5, I6 6, I7 7);
+
+#[cfg(test)]
+mod test {
+    #[cfg(feature = "simulation")]
+    #[test]
+    fn interfaces_numbered_in_tuple_order() {
+        use usb_device::bus::UsbBusAllocator;
+
+        use crate::device::keyboard::{BootKeyboard, BOOT_KEYBOARD_REPORT_DESCRIPTOR};
+        use crate::device::mouse::{BootMouse, BootMouseReport, BOOT_MOUSE_REPORT_DESCRIPTOR};
+        use crate::hid_class::descriptor::InterfaceProtocol;
+        use crate::hid_class::InterfaceAssociation;
+        use crate::prelude::*;
+        use crate::simulation::{device_builder, SimBus, SimHost};
+
+        let usb_alloc = UsbBusAllocator::new(SimBus::new());
+        let associations = [InterfaceAssociation::new(0, 2, InterfaceProtocol::Keyboard)];
+        let mut hid = UsbHidClassBuilder::new()
+            .with_interfaces((BootKeyboard::default_config(), BootMouse::default_config()))
+            .interface_associations(&associations)
+            .build(&usb_alloc)
+            .unwrap();
+        let mut usb_dev = device_builder(&usb_alloc, 8).composite_with_iads().build();
+
+        let host = SimHost::enumerate(&mut usb_dev, &mut [&mut hid]).unwrap();
+        assert_eq!(host.interfaces().len(), 2);
+        assert_eq!(
+            host.interface(0).unwrap().report_descriptor,
+            BOOT_KEYBOARD_REPORT_DESCRIPTOR
+        );
+        assert_eq!(
+            host.interface(1).unwrap().report_descriptor,
+            BOOT_MOUSE_REPORT_DESCRIPTOR
+        );
+        // Interface Association Descriptor for interfaces 0 and 1 follows the configuration header
+        assert_eq!(
+            &host.configuration_descriptor()[9..13],
+            &[0x08, 0x0B, 0x00, 0x02]
+        );
+
+        let report = BootMouseReport {
+            x: 5,
+            ..Default::default()
+        };
+        hid.interface_tuple().1.write_report(&report).unwrap();
+        assert_eq!(
+            host.read_report(&mut usb_dev, &mut [&mut hid], 1),
+            Some(vec![0x00, 0x05, 0x00])
+        );
+    }
+}
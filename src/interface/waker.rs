@@ -0,0 +1,26 @@
+//! Wakers of tasks waiting on an interface's endpoints
+
+use core::cell::Cell;
+use core::task::Waker;
+
+/// Waker of the last task to wait for an endpoint, woken from the class poll path
+#[derive(Default)]
+pub(crate) struct WakerSlot {
+    waker: Cell<Option<Waker>>,
+}
+
+impl WakerSlot {
+    pub(crate) fn register(&self, waker: &Waker) {
+        let waker = match self.waker.take() {
+            Some(w) if w.will_wake(waker) => w,
+            _ => waker.clone(),
+        };
+        self.waker.set(Some(waker));
+    }
+
+    pub(crate) fn wake(&self) {
+        if let Some(waker) = self.waker.take() {
+            waker.wake();
+        }
+    }
+}
@@ -0,0 +1,125 @@
+//! Detection of hosts that stop polling an IN endpoint
+//!
+//! Some hosts, often KVM switches or hosts entering sleep, stop reading an interrupt endpoint
+//! without resetting the bus. With a stall timeout configured, an interface that has had a report
+//! pending on its IN endpoint for longer than the timeout discards its queued data and counts a
+//! host stall. The host remains stalled until it reads the IN endpoint again.
+
+use fugit::{ExtU32, MillisDurationU32};
+
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct StallWatchdog {
+    timeout: Option<MillisDurationU32>,
+    pending_for: Option<MillisDurationU32>,
+    stalled: bool,
+    stalls: u32,
+}
+
+impl StallWatchdog {
+    pub(crate) fn new(timeout: Option<MillisDurationU32>) -> Self {
+        Self {
+            timeout,
+            ..Default::default()
+        }
+    }
+
+    pub(crate) fn enqueued(&mut self) {
+        if self.timeout.is_some() {
+            self.pending_for = Some(0.millis());
+        }
+    }
+
+    pub(crate) fn completed(&mut self) {
+        self.pending_for = None;
+        self.stalled = false;
+    }
+
+    pub(crate) fn reset(&mut self) {
+        self.pending_for = None;
+        self.stalled = false;
+    }
+
+    #[cfg(test)]
+    pub(crate) fn tick(&mut self) -> bool {
+        self.tick_with(1.millis())
+    }
+
+    /// Advances the pending report's wait by `elapsed`, returns true when the wait reaches the
+    /// timeout and the host is considered stalled
+    pub(crate) fn tick_with(&mut self, elapsed: MillisDurationU32) -> bool {
+        let (Some(timeout), Some(pending_for)) = (self.timeout, self.pending_for) else {
+            return false;
+        };
+        let pending_for = pending_for.checked_add(elapsed).unwrap_or(timeout);
+        if pending_for < timeout {
+            self.pending_for = Some(pending_for);
+            return false;
+        }
+        self.pending_for = None;
+        self.stalled = true;
+        self.stalls = self.stalls.wrapping_add(1);
+        true
+    }
+
+    pub(crate) fn is_stalled(&self) -> bool {
+        self.stalled
+    }
+
+    pub(crate) fn stalls(&self) -> u32 {
+        self.stalls
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use fugit::ExtU32;
+
+    use crate::interface::watchdog::StallWatchdog;
+
+    fn ticks_until_stall(watchdog: &mut StallWatchdog) -> Option<u32> {
+        (1..=1000).find(|_| watchdog.tick())
+    }
+
+    #[test]
+    fn stall_detected_after_timeout() {
+        let mut watchdog = StallWatchdog::new(Some(50.millis()));
+        watchdog.enqueued();
+        assert_eq!(ticks_until_stall(&mut watchdog), Some(50));
+        assert_eq!(watchdog.stalls(), 1);
+        assert!(watchdog.is_stalled());
+
+        // Nothing is pending after a stall
+        assert_eq!(ticks_until_stall(&mut watchdog), None);
+
+        // Until the host reads the endpoint again
+        watchdog.completed();
+        assert!(!watchdog.is_stalled());
+    }
+
+    #[test]
+    fn completion_clears_pending_report() {
+        let mut watchdog = StallWatchdog::new(Some(50.millis()));
+        watchdog.enqueued();
+        (0..49).for_each(|_| assert!(!watchdog.tick()));
+        watchdog.completed();
+        assert_eq!(ticks_until_stall(&mut watchdog), None);
+        assert_eq!(watchdog.stalls(), 0);
+    }
+
+    #[test]
+    fn stall_detected_with_elapsed_time() {
+        let mut watchdog = StallWatchdog::new(Some(50.millis()));
+        watchdog.enqueued();
+        assert!(!watchdog.tick_with(30.millis()));
+        assert!(!watchdog.tick_with(19.millis()));
+        assert!(watchdog.tick_with(1.millis()));
+        assert_eq!(watchdog.stalls(), 1);
+    }
+
+    #[test]
+    fn disabled_without_timeout() {
+        let mut watchdog = StallWatchdog::new(None);
+        watchdog.enqueued();
+        assert_eq!(ticks_until_stall(&mut watchdog), None);
+    }
+}
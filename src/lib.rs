@@ -18,7 +18,7 @@
 //! # use usb_device::bus::PollResult;
 //! # use fugit::{ExtU32, MillisDurationU32};
 //! use usbd_human_interface_device::page::Keyboard;
-//! use usbd_human_interface_device::device::keyboard::{KeyboardLedsReport, NKROBootKeyboardInterface};
+//! use usbd_human_interface_device::device::keyboard::{KeyboardLedsReport, NKROBootKeyboardInterface, NKROBootKeyboardReport};
 //! use usbd_human_interface_device::prelude::*;
 //! # use usb_device::class_prelude::*;
 //! # use usb_device::prelude::*;
@@ -93,13 +93,15 @@
 //! # }
 //! # let timer: Timer = todo!();
 //!
+//! # #[cfg(all(feature = "frunk", not(feature = "usb-device-03")))] {
 //! let usb_alloc = UsbBusAllocator::new(usb_bus);
 //!
 //! let mut keyboard = UsbHidClassBuilder::new()
 //!     .add_interface(
 //!         NKROBootKeyboardInterface::default_config(),
 //!     )
-//!     .build(&usb_alloc);
+//!     .build(&usb_alloc)
+//!     .unwrap();
 //!
 //! let mut usb_dev = UsbDeviceBuilder::new(&usb_alloc, UsbVidPid(0x1209, 0x0001))
 //!     .manufacturer("usbd-human-interface-device")
@@ -117,7 +119,7 @@
 //!             [Keyboard::NoEventIndicated]
 //!     };
 //!
-//!     keyboard.interface().write_report(keys).ok();
+//!     keyboard.interface().write_report(&NKROBootKeyboardReport::new(keys)).ok();
 //!
 //!     //tick once per ms/at 1kHz
 //!     if tick_timer.wait().is_ok() {
@@ -135,19 +137,60 @@
 //!         }
 //!     }
 //! }
+//! # }
 //! ```
 //!
 //! Features
 //! --------
 //!
-//! * Keyboard implementations - standard boot compliant keyboard, boot compatible NKRO(N-Key Roll Over) keyboard
-//! * Mouse - standard boot compliant mouse, boot compatible mouse with scroll wheel and pan
+//! * Keyboard implementations - standard boot compliant keyboard, boot compatible NKRO(N-Key Roll Over) keyboard, bitmap only NKRO keyboard, boot compatible keyboard with consumer and system controls using report IDs, typing of text queued as strings with US, UK, German and French layouts, optional device side key repeat
+//! * Mouse - standard boot compliant mouse, boot compatible mouse with scroll wheel and pan, boot compatible mouse with 16 bit motion, absolute positioning mouse, gaming mouse with DPI, polling rate and profile settings
 //! * Consumer Control - fixed function media control device, arbitrary consumer control device
-//! * Enums defining the Consumer, Desktop, Game, Keyboard, LED, Simulation and Telephony HID usage pages
+//! * FIDO - raw U2F interface, CTAP-HID transport with channel allocation and message reassembly
+//! * Gamepad - eight buttons, four axes, a hat switch and rumble motor output reports
+//! * Dual analog gamepad - console controller layout with 14 buttons, two sticks, analog triggers and a hat switch, numbered for the W3C Gamepad API standard mapping
+//! * Switch compatible controller - 16 buttons, a hat switch and two sticks in the report layout of licensed wired controllers
+//! * DualShock 4 compatible gamepad - the DualShock 4 input report layout, including motion sensor and touchpad fields
+//! * Joystick - HOTAS style joystick with 32 buttons, eight 16 bit axes and two hat switches
+//! * Racing wheel - 16 bit steering, accelerator, brake and clutch axes using Simulation Controls usages, and 16 buttons
+//! * Button pad - 32 buttons and no axes, with per button debouncing, for dance pads, arcade sticks and button boxes
+//! * Eye tracker - gaze point and eye positions on the Eye and Head Trackers usage page, with capability and screen configuration feature reports, supported by Windows without a driver
+//! * Macro pad - 16 keys sending keyboard usages from a keymap the host reads and writes as a vendor defined feature report
+//! * Foot switch - up to 8 pedals as a joystick or telephony collection, for transcription and accessibility pedals
+//! * Head tracker - yaw, pitch and roll as 16 bit joystick axes, for head tracking software such as opentrack
+//! * Multi-axis controller - six degree of freedom 3D mouse for CAD navigation
+//! * Force feedback joystick - Physical Interface Device (PID) effects dispatched to an effect handler
+//! * Touch screens - single touch digitizer, multi-touch digitizer with contact tracking
+//! * UPS - battery charge level, charging state and run time to empty on the HID power device usage pages
+//! * Sensors - HID Sensors usage page framework, accelerometer, gyrometer, ambient light sensor and environmental temperature and humidity sensor
+//! * Vendor defined raw HID - fixed size input and output reports for application side channels
+//! * LampArray - RGB lamps lit by the host through Windows Dynamic Lighting on the Lighting and Illumination usage page, with complete frames as `RGB8` colours for smart-leds LED drivers (`smart-leds` feature)
+//! * Enums defining the Battery System, Consumer, Desktop, Game, Keyboard, LED, Power Device, Sensors, Simulation and Telephony HID usage pages, with Button and Hat Switch value types
 //! * Support for multi-interface devices
+//! * Interfaces chosen at runtime, as an alternative to the statically typed interface list
+//! * Interfaces listed as a tuple of up to eight, numbered and accessed by position, as an alternative to the HList builder, which can be disabled with the default `frunk` feature
+//! * Support for high-speed buses, with packet sizes up to 1024 bytes
 //! * Support for HID idle
 //! * Support for HID protocol changing
+//! * Support for remote wakeup, signalled when input reports are written while the host is suspended
 //! * Support for both single and multiple reports
+//! * Report descriptors encoded from items, at compile time with `hid_descriptor!` or at runtime, e.g. sized by the detected hardware
+//! * Report descriptor parsing and validation, checked against every interface when it is built
+//! * Support for feature reports, stored per report ID or handled by the application
+//! * Optional queues of input reports waiting for the IN endpoint and output reports received while the application is busy
+//! * Lock-free queues handing input reports from the application to a managed interface, written as the class is polled
+//! * Optional report generators for bring-up testing (`demo` feature)
+//! * Optional ready-made composite devices for validating `UsbBus` implementations (`examples_support` feature)
+//! * Optional async report reads and writes woken by the class poll, for executors such as embassy (`async` feature)
+//! * Optional class and device wrapper polled from the USB interrupt while reports are written from the main loop (`critical-section` feature)
+//! * Logging through the `log` facade, compiled out when the default `log` feature is disabled
+//! * Optional host operating system detection heuristics (`host-os` feature)
+//! * Optional generation of report structs from existing report descriptors (`macros` feature)
+//! * Optional test utilities asserting report structs pack to the size their descriptors declare, requires `std` (`std` feature)
+//! * Optional report descriptor annotator decoding each item into a readable line, for golden tests and debugging, requires `std` (`std` feature)
+//! * Optional host simulation replaying recorded control transfers and unit testing devices through control and interrupt transfers, requires `std` (`simulation` feature)
+//! * Optional fuzzing entry point for control request handling, with a `cargo fuzz` target in `fuzz` (`fuzz` feature)
+//! * Builds against usb-device 0.2, or 0.3 with the `usb-device-03` feature, the crate in use is re-exported as `usb_device` (usb-device 0.2 stays a dependency either way)
 //!
 //! Examples
 //! --------
@@ -157,23 +200,53 @@
 
 #![no_std]
 
-//Allow the use of std in tests
-#[cfg(test)]
+//Allow the use of std in tests, test utilities and the host simulation
+#[cfg(any(test, feature = "std"))]
 #[macro_use]
 extern crate std;
 
+/// The usb-device crate the classes are built on, 0.2 or, with the `usb-device-03` feature, 0.3
+#[cfg(not(feature = "usb-device-03"))]
+pub use usb_device;
+/// The usb-device crate the classes are built on, 0.2 or, with the `usb-device-03` feature, 0.3
+#[cfg(feature = "usb-device-03")]
+pub extern crate usb_device_03 as usb_device;
+
 use usb_device::UsbError;
 
+#[macro_use]
+mod fmt;
+
+#[cfg(feature = "demo")]
+pub mod demo;
 pub mod device;
+#[cfg(feature = "examples_support")]
+pub mod examples_support;
 pub mod hid_class;
+#[cfg(feature = "host-os")]
+pub mod host_os;
 pub mod interface;
 pub mod page;
 pub mod prelude;
+#[cfg(feature = "simulation")]
+pub mod simulation;
+#[cfg(feature = "std")]
+pub mod testing;
+
+#[cfg(feature = "macros")]
+pub use usbd_human_interface_device_macros::hid_report;
 
+/// Errors of reading and writing reports, for raw, managed and device interfaces
 #[derive(Debug)]
 pub enum UsbHidError {
+    /// The endpoint is busy, try again later
     WouldBlock,
+    /// The report is the same as the last report sent and was not sent again
     Duplicate,
+    /// The device has not been configured by the host
+    NotConfigured,
+    /// The report is longer than the endpoint or buffer it is written to
+    ReportTooLong,
     UsbError(UsbError),
     SerializationError,
 }
@@ -182,6 +255,8 @@ impl From<UsbError> for UsbHidError {
     fn from(e: UsbError) -> Self {
         match e {
             UsbError::WouldBlock => UsbHidError::WouldBlock,
+            UsbError::InvalidState => UsbHidError::NotConfigured,
+            UsbError::BufferOverflow => UsbHidError::ReportTooLong,
             _ => UsbHidError::UsbError(e),
         }
     }
@@ -0,0 +1,264 @@
+//! Layout specific names for [`Keyboard`] usages
+//!
+//! Keyboard usages are named after the legends of a US keyboard, but the host translates them
+//! using its configured layout. The constants in each module name the usage, and any modifiers,
+//! that produce a legend when the host uses that layout, so keymaps can be written in terms of the
+//! legends printed on the keys.
+//!
+//! ```
+//! use usbd_human_interface_device::page::alias::{de, KeyCombo};
+//! use usbd_human_interface_device::page::Keyboard;
+//!
+//! assert_eq!(de::Z, Keyboard::Y);
+//! assert!(de::AT.keys().eq([Keyboard::RightAlt, Keyboard::Q]));
+//! assert_eq!(KeyCombo::from(de::O_UMLAUT), KeyCombo::plain(Keyboard::Semicolon));
+//! ```
+//!
+//! The US layout, which the usages are named after, converts between characters and key
+//! combinations in both directions with [`TryFrom`]:
+//!
+//! ```
+//! use usbd_human_interface_device::page::alias::KeyCombo;
+//! use usbd_human_interface_device::page::Keyboard;
+//!
+//! assert_eq!(KeyCombo::try_from('?'), Ok(KeyCombo::shifted(Keyboard::ForwardSlash)));
+//! assert_eq!(char::try_from(KeyCombo::shifted(Keyboard::Keyboard2)), Ok('@'));
+//! ```
+
+use crate::page::Keyboard;
+
+/// A key along with the modifiers that must be held to produce a legend
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct KeyCombo {
+    pub key: Keyboard,
+    pub shift: bool,
+    /// Right Alt, labelled AltGr on most non-US keyboards
+    pub alt_gr: bool,
+}
+
+impl KeyCombo {
+    pub const fn plain(key: Keyboard) -> Self {
+        Self {
+            key,
+            shift: false,
+            alt_gr: false,
+        }
+    }
+
+    pub const fn shifted(key: Keyboard) -> Self {
+        Self {
+            key,
+            shift: true,
+            alt_gr: false,
+        }
+    }
+
+    pub const fn alt_gr(key: Keyboard) -> Self {
+        Self {
+            key,
+            shift: false,
+            alt_gr: true,
+        }
+    }
+
+    /// The usages to press, modifiers first
+    pub fn keys(&self) -> impl Iterator<Item = Keyboard> {
+        [
+            self.shift.then_some(Keyboard::LeftShift),
+            self.alt_gr.then_some(Keyboard::RightAlt),
+            Some(self.key),
+        ]
+        .into_iter()
+        .flatten()
+    }
+}
+
+impl From<Keyboard> for KeyCombo {
+    fn from(key: Keyboard) -> Self {
+        Self::plain(key)
+    }
+}
+
+/// Keys of the US layout typing characters other than letters, with the characters they type
+/// unshifted and shifted
+const US_KEYS: [(Keyboard, char, char); 26] = [
+    (Keyboard::Keyboard1, '1', '!'),
+    (Keyboard::Keyboard2, '2', '@'),
+    (Keyboard::Keyboard3, '3', '#'),
+    (Keyboard::Keyboard4, '4', '$'),
+    (Keyboard::Keyboard5, '5', '%'),
+    (Keyboard::Keyboard6, '6', '^'),
+    (Keyboard::Keyboard7, '7', '&'),
+    (Keyboard::Keyboard8, '8', '*'),
+    (Keyboard::Keyboard9, '9', '('),
+    (Keyboard::Keyboard0, '0', ')'),
+    (Keyboard::ReturnEnter, '\n', '\n'),
+    (Keyboard::Escape, '\u{1B}', '\u{1B}'),
+    (Keyboard::DeleteBackspace, '\u{8}', '\u{8}'),
+    (Keyboard::Tab, '\t', '\t'),
+    (Keyboard::Space, ' ', ' '),
+    (Keyboard::Minus, '-', '_'),
+    (Keyboard::Equal, '=', '+'),
+    (Keyboard::LeftBrace, '[', '{'),
+    (Keyboard::RightBrace, ']', '}'),
+    (Keyboard::Backslash, '\\', '|'),
+    (Keyboard::Semicolon, ';', ':'),
+    (Keyboard::Apostrophe, '\'', '"'),
+    (Keyboard::Grave, '`', '~'),
+    (Keyboard::Comma, ',', '<'),
+    (Keyboard::Dot, '.', '>'),
+    (Keyboard::ForwardSlash, '/', '?'),
+];
+
+/// Key combination typing a character on the US layout, which [`Keyboard`] usages are named
+/// after
+///
+/// Fails with the character for characters that aren't on the layout.
+impl TryFrom<char> for KeyCombo {
+    type Error = char;
+
+    fn try_from(c: char) -> Result<Self, Self::Error> {
+        if c.is_ascii_alphabetic() {
+            let key = Keyboard::from(u8::from(Keyboard::A) + (c.to_ascii_lowercase() as u8 - b'a'));
+            return Ok(if c.is_ascii_uppercase() {
+                Self::shifted(key)
+            } else {
+                Self::plain(key)
+            });
+        }
+        US_KEYS
+            .iter()
+            .find_map(|&(key, plain, shifted)| {
+                if c == plain {
+                    Some(Self::plain(key))
+                } else if c == shifted {
+                    Some(Self::shifted(key))
+                } else {
+                    None
+                }
+            })
+            .ok_or(c)
+    }
+}
+
+/// Character typed by a key combination on the US layout
+///
+/// Fails with the combination for keys that don't type a character, and for combinations with
+/// AltGr, which has no characters on the US layout.
+impl TryFrom<KeyCombo> for char {
+    type Error = KeyCombo;
+
+    fn try_from(combo: KeyCombo) -> Result<Self, Self::Error> {
+        if combo.alt_gr {
+            return Err(combo);
+        }
+        let letters = u8::from(Keyboard::A)..=u8::from(Keyboard::Z);
+        let usage = u8::from(combo.key);
+        if letters.contains(&usage) {
+            let c = char::from(b'a' + (usage - letters.start()));
+            return Ok(if combo.shift {
+                c.to_ascii_uppercase()
+            } else {
+                c
+            });
+        }
+        US_KEYS
+            .iter()
+            .find(|(key, ..)| *key == combo.key)
+            .map(|&(_, plain, shifted)| if combo.shift { shifted } else { plain })
+            .ok_or(combo)
+    }
+}
+
+/// German (QWERTZ) layout
+pub mod de {
+    use super::KeyCombo;
+    use crate::page::Keyboard;
+
+    pub const Z: Keyboard = Keyboard::Y;
+    pub const Y: Keyboard = Keyboard::Z;
+    pub const A_UMLAUT: Keyboard = Keyboard::Apostrophe;
+    pub const O_UMLAUT: Keyboard = Keyboard::Semicolon;
+    pub const U_UMLAUT: Keyboard = Keyboard::LeftBrace;
+    pub const SHARP_S: Keyboard = Keyboard::Minus;
+    /// Dead key
+    pub const ACUTE: Keyboard = Keyboard::Equal;
+    /// Dead key
+    pub const CIRCUMFLEX: Keyboard = Keyboard::Grave;
+    pub const PLUS: Keyboard = Keyboard::RightBrace;
+    pub const HASH: Keyboard = Keyboard::NonUSHash;
+    pub const LESS_THAN: Keyboard = Keyboard::NonUSBackslash;
+    pub const MINUS: Keyboard = Keyboard::ForwardSlash;
+
+    pub const QUESTION_MARK: KeyCombo = KeyCombo::shifted(SHARP_S);
+    pub const ASTERISK: KeyCombo = KeyCombo::shifted(PLUS);
+    pub const APOSTROPHE: KeyCombo = KeyCombo::shifted(HASH);
+    pub const GREATER_THAN: KeyCombo = KeyCombo::shifted(LESS_THAN);
+    pub const UNDERSCORE: KeyCombo = KeyCombo::shifted(MINUS);
+    pub const AT: KeyCombo = KeyCombo::alt_gr(Keyboard::Q);
+    pub const EURO: KeyCombo = KeyCombo::alt_gr(Keyboard::E);
+    pub const LEFT_BRACE: KeyCombo = KeyCombo::alt_gr(Keyboard::Keyboard7);
+    pub const LEFT_BRACKET: KeyCombo = KeyCombo::alt_gr(Keyboard::Keyboard8);
+    pub const RIGHT_BRACKET: KeyCombo = KeyCombo::alt_gr(Keyboard::Keyboard9);
+    pub const RIGHT_BRACE: KeyCombo = KeyCombo::alt_gr(Keyboard::Keyboard0);
+    pub const BACKSLASH: KeyCombo = KeyCombo::alt_gr(SHARP_S);
+    pub const TILDE: KeyCombo = KeyCombo::alt_gr(PLUS);
+    pub const PIPE: KeyCombo = KeyCombo::alt_gr(LESS_THAN);
+}
+
+/// Japanese (JIS) layout
+///
+/// The [`Keyboard::Kanji1`] to [`Keyboard::Kanji9`] usages are named International1 to
+/// International9 in the usage tables.
+pub mod jis {
+    use crate::page::Keyboard;
+
+    pub const HANKAKU_ZENKAKU: Keyboard = Keyboard::Grave;
+    pub const RO: Keyboard = Keyboard::Kanji1;
+    pub const KATAKANA_HIRAGANA: Keyboard = Keyboard::Kanji2;
+    pub const YEN: Keyboard = Keyboard::Kanji3;
+    pub const HENKAN: Keyboard = Keyboard::Kanji4;
+    pub const MUHENKAN: Keyboard = Keyboard::Kanji5;
+    /// Kana key of Apple JIS keyboards
+    pub const KANA: Keyboard = Keyboard::LANG1;
+    /// Eisu key of Apple JIS keyboards
+    pub const EISU: Keyboard = Keyboard::LANG2;
+    pub const CARET: Keyboard = Keyboard::Equal;
+    pub const AT: Keyboard = Keyboard::LeftBrace;
+    pub const LEFT_BRACKET: Keyboard = Keyboard::RightBrace;
+    pub const RIGHT_BRACKET: Keyboard = Keyboard::NonUSHash;
+    pub const COLON: Keyboard = Keyboard::Apostrophe;
+}
+
+#[cfg(test)]
+mod test {
+    use crate::page::alias::{de, KeyCombo};
+    use crate::page::Keyboard;
+
+    #[test]
+    fn us_characters_round_trip() {
+        for c in (' '..='~').chain(['\n', '\t', '\u{8}', '\u{1B}']) {
+            let combo = KeyCombo::try_from(c).unwrap();
+            assert_eq!(char::try_from(combo), Ok(c));
+        }
+        assert_eq!(KeyCombo::try_from('Q'), Ok(KeyCombo::shifted(Keyboard::Q)));
+        assert_eq!(
+            KeyCombo::try_from('"'),
+            Ok(KeyCombo::shifted(Keyboard::Apostrophe))
+        );
+        assert_eq!(KeyCombo::try_from('£'), Err('£'));
+        assert_eq!(char::try_from(KeyCombo::plain(Keyboard::F1)).ok(), None);
+        assert_eq!(char::try_from(de::AT).ok(), None);
+    }
+
+    #[test]
+    fn combo_keys_modifiers_first() {
+        assert!(KeyCombo::plain(Keyboard::A).keys().eq([Keyboard::A]));
+        assert!(de::UNDERSCORE
+            .keys()
+            .eq([Keyboard::LeftShift, Keyboard::ForwardSlash]));
+        assert!(de::BACKSLASH
+            .keys()
+            .eq([Keyboard::RightAlt, Keyboard::Minus]));
+    }
+}
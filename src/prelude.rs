@@ -8,6 +8,71 @@
 //! use usbd_human_interface_device::prelude::*;
 //! ```
 
+#[cfg(feature = "critical-section")]
+pub use crate::hid_class::shared::SharedUsbHidClass;
 pub use crate::hid_class::UsbHidClass;
 pub use crate::hid_class::UsbHidClassBuilder;
 pub use crate::UsbHidError;
+
+pub use crate::hid_class::prelude::{
+    ClassDescriptor, EndpointBudget, HidDescriptorConfig, HidEvent, HidEvents, HidProtocol,
+    IdleDuration, InterfaceAssociation, InterfaceProtocol, PacketPolicy, PollInterval,
+    ProtocolPolicy, RawInterfaceBuilder, TransmitPolicy, UsbPacketSize, UsbSpeed,
+};
+pub use crate::hid_class::{BuilderResult, UsbHidBuilderError};
+
+pub use crate::device::button_pad::{ButtonPad, ButtonPadReport};
+pub use crate::device::composite_keyboard::{CompositeKeyboard, SystemControlReport};
+pub use crate::device::consumer::{
+    ConsumerControl, ConsumerControlFixed, FixedFunctionReport, MultipleConsumerReport,
+};
+pub use crate::device::ctaphid::{CtapHid, CtapHidMessage};
+pub use crate::device::ds4::{DS4Gamepad, DS4GamepadReport, DS4TouchPoint};
+pub use crate::device::dual_analog::{
+    DualAnalogButton, DualAnalogGamepad, DualAnalogGamepadReport,
+};
+pub use crate::device::environmental::{
+    EnvironmentalSensor, EnvironmentalSensorInterface, HumidityReport, TemperatureReport,
+};
+pub use crate::device::eye_tracker::{
+    EyeTracker, EyeTrackerCapabilities, EyeTrackerConfiguration, EyeTrackerStatus, GazeReport,
+};
+pub use crate::device::fido::{RawFido, RawFidoMsg};
+pub use crate::device::foot_switch::{FootSwitch, FootSwitchReport};
+pub use crate::device::gamepad::{Gamepad, GamepadReport, RumbleReport};
+pub use crate::device::gaming_mouse::{GamingMouse, GamingMouseReport, MouseSettingsHandler};
+pub use crate::device::head_tracker::{HeadTracker, HeadTrackerReport};
+pub use crate::device::joystick::{Joystick, JoystickReport};
+pub use crate::device::keyboard::{
+    BootKeyboard, BootKeyboardReport, KeyState, KeyboardConfig, KeyboardLedsReport,
+    KeyboardLedsSync, NKROBootKeyboard, NKROBootKeyboardReport, NKROKeyboard, NKROKeyboardReport,
+    OutputReportParsing, Typematic,
+};
+pub use crate::device::lamp_array::{LampArray, LampArrayAttributes, LampAttributes, LampColor};
+pub use crate::device::layout::Layout;
+pub use crate::device::macro_pad::{MacroPad, MacroPadKeymap};
+pub use crate::device::mouse::{
+    AbsoluteMouse, AbsoluteMouseReport, BootMouse, BootMouseReport, HighResMouse,
+    HighResMouseReport, WheelMouse, WheelMouseReport,
+};
+pub use crate::device::multiaxis::{MultiAxisController, MultiAxisReport};
+pub use crate::device::multitouch::{MultiTouch, MultiTouchReport};
+pub use crate::device::pid::{EffectHandler, ForceFeedbackJoystick, ForceFeedbackJoystickReport};
+pub use crate::device::power::{
+    BatteryCapacityReport, PresentStatusReport, RemainingCapacityReport, Ups,
+};
+pub use crate::device::sensor::{
+    Accelerometer, AccelerometerReport, AmbientLight, AmbientLightReport, Gyrometer,
+    GyrometerReport, SensorProperties, SensorState,
+};
+pub use crate::device::switch::{SwitchButton, SwitchController, SwitchControllerReport};
+pub use crate::device::touchscreen::{TouchScreen, TouchScreenReport};
+pub use crate::device::typer::{KeyWriter, KeyboardTyper};
+pub use crate::device::vendor::VendorRaw;
+pub use crate::device::wheel::{RacingWheel, RacingWheelReport};
+
+pub use crate::page::alias::KeyCombo;
+pub use crate::page::{
+    BatterySystem, Button, Consumer, Desktop, Game, HatSwitch, Keyboard, Leds, PowerDevice,
+    Sensors, Simulation, Telephony,
+};
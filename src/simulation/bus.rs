@@ -0,0 +1,239 @@
+//! In-memory [`UsbBus`] standing in for both the device controller and the host
+
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::vec::Vec;
+
+use usb_device::bus::{PollResult, UsbBus};
+use usb_device::endpoint::{EndpointAddress, EndpointType};
+use usb_device::{Result, UsbDirection, UsbError};
+
+const MAX_ENDPOINTS: usize = 16;
+
+#[derive(Debug, Default)]
+struct Endpoint {
+    allocated: bool,
+    max_packet_size: u16,
+    stalled: bool,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    endpoints_in: [Endpoint; MAX_ENDPOINTS],
+    endpoints_out: [Endpoint; MAX_ENDPOINTS],
+    setup: Option<[u8; 8]>,
+    out_packets: [VecDeque<Vec<u8>>; MAX_ENDPOINTS],
+    in_packets: [Option<Vec<u8>>; MAX_ENDPOINTS],
+    in_complete: u16,
+    address: u8,
+    suspend: bool,
+    resume: bool,
+}
+
+/// Simulated bus, the host side is driven through the `host_*` methods
+///
+/// Packets written by the device to an IN endpoint remain pending, and further writes to that
+/// endpoint fail with [`UsbError::WouldBlock`], until the host takes them with
+/// [`SimBus::host_take_in`].
+#[derive(Debug, Default)]
+pub struct SimBus {
+    state: Mutex<State>,
+}
+
+impl SimBus {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Sends a SETUP packet to the control endpoint, discarding any unfinished control transfer
+    pub fn host_setup(&self, setup: [u8; 8]) {
+        let mut state = self.state();
+        state.setup = Some(setup);
+        state.out_packets[0].clear();
+        state.in_packets[0] = None;
+        state.endpoints_in[0].stalled = false;
+        state.endpoints_out[0].stalled = false;
+    }
+
+    /// Sends an OUT packet to endpoint `index`
+    pub fn host_out(&self, index: usize, data: &[u8]) {
+        self.state().out_packets[index].push_back(data.to_vec());
+    }
+
+    /// Takes the packet the device has written to IN endpoint `index`, if any
+    pub fn host_take_in(&self, index: usize) -> Option<Vec<u8>> {
+        let mut state = self.state();
+        let packet = state.in_packets[index].take();
+        if packet.is_some() {
+            state.in_complete |= 1 << index;
+        }
+        packet
+    }
+
+    /// The device has stalled endpoint `index` in either direction
+    pub fn is_endpoint_stalled(&self, index: usize) -> bool {
+        let state = self.state();
+        state.endpoints_in[index].stalled || state.endpoints_out[index].stalled
+    }
+
+    pub fn max_packet_size(&self, ep_addr: EndpointAddress) -> u16 {
+        let state = self.state();
+        let endpoints = match ep_addr.direction() {
+            UsbDirection::In => &state.endpoints_in,
+            UsbDirection::Out => &state.endpoints_out,
+        };
+        endpoints[ep_addr.index()].max_packet_size
+    }
+
+    /// Stops sending SOF packets, the device is suspended when next polled
+    pub fn host_suspend(&self) {
+        self.state().suspend = true;
+    }
+
+    /// Signals resume, the device returns to its state before suspend when next polled
+    pub fn host_resume(&self) {
+        self.state().resume = true;
+    }
+
+    /// Address assigned by the host with `SET_ADDRESS`
+    pub fn address(&self) -> u8 {
+        self.state().address
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<'_, State> {
+        self.state.lock().unwrap()
+    }
+}
+
+impl UsbBus for SimBus {
+    fn alloc_ep(
+        &mut self,
+        ep_dir: UsbDirection,
+        ep_addr: Option<EndpointAddress>,
+        _ep_type: EndpointType,
+        max_packet_size: u16,
+        _interval: u8,
+    ) -> Result<EndpointAddress> {
+        let state = self.state.get_mut().unwrap();
+        let endpoints = match ep_dir {
+            UsbDirection::In => &mut state.endpoints_in,
+            UsbDirection::Out => &mut state.endpoints_out,
+        };
+
+        let index = match ep_addr {
+            Some(addr) if endpoints[addr.index()].allocated => {
+                return Err(UsbError::InvalidEndpoint)
+            }
+            Some(addr) => addr.index(),
+            None => (1..MAX_ENDPOINTS)
+                .find(|&i| !endpoints[i].allocated)
+                .ok_or(UsbError::EndpointOverflow)?,
+        };
+
+        endpoints[index] = Endpoint {
+            allocated: true,
+            max_packet_size,
+            stalled: false,
+        };
+        Ok(EndpointAddress::from_parts(index, ep_dir))
+    }
+
+    fn enable(&mut self) {}
+
+    fn reset(&self) {
+        let mut state = self.state();
+        state.address = 0;
+        state.setup = None;
+        state.in_complete = 0;
+        state.out_packets.iter_mut().for_each(VecDeque::clear);
+        state.in_packets = Default::default();
+    }
+
+    fn set_device_address(&self, addr: u8) {
+        self.state().address = addr;
+    }
+
+    fn write(&self, ep_addr: EndpointAddress, buf: &[u8]) -> Result<usize> {
+        let mut state = self.state();
+        let index = ep_addr.index();
+        if buf.len() > state.endpoints_in[index].max_packet_size.into() {
+            Err(UsbError::BufferOverflow)
+        } else if state.in_packets[index].is_some() {
+            Err(UsbError::WouldBlock)
+        } else {
+            state.in_packets[index] = Some(buf.to_vec());
+            Ok(buf.len())
+        }
+    }
+
+    fn read(&self, ep_addr: EndpointAddress, buf: &mut [u8]) -> Result<usize> {
+        let mut state = self.state();
+        let index = ep_addr.index();
+        let packet = match state.setup.take() {
+            Some(setup) if index == 0 => setup.to_vec(),
+            setup => {
+                state.setup = setup;
+                state.out_packets[index]
+                    .pop_front()
+                    .ok_or(UsbError::WouldBlock)?
+            }
+        };
+
+        if packet.len() > buf.len() {
+            Err(UsbError::BufferOverflow)
+        } else {
+            buf[..packet.len()].copy_from_slice(&packet);
+            Ok(packet.len())
+        }
+    }
+
+    fn set_stalled(&self, ep_addr: EndpointAddress, stalled: bool) {
+        let mut state = self.state();
+        let index = ep_addr.index();
+        match ep_addr.direction() {
+            UsbDirection::In => state.endpoints_in[index].stalled = stalled,
+            UsbDirection::Out => state.endpoints_out[index].stalled = stalled,
+        }
+    }
+
+    fn is_stalled(&self, ep_addr: EndpointAddress) -> bool {
+        let state = self.state();
+        let index = ep_addr.index();
+        match ep_addr.direction() {
+            UsbDirection::In => state.endpoints_in[index].stalled,
+            UsbDirection::Out => state.endpoints_out[index].stalled,
+        }
+    }
+
+    fn suspend(&self) {}
+
+    fn resume(&self) {}
+
+    fn poll(&self) -> PollResult {
+        let mut state = self.state();
+        if core::mem::take(&mut state.suspend) {
+            return PollResult::Suspend;
+        }
+        if core::mem::take(&mut state.resume) {
+            return PollResult::Resume;
+        }
+        let ep_setup = u16::from(state.setup.is_some());
+        let ep_out = state
+            .out_packets
+            .iter()
+            .enumerate()
+            .filter(|(_, packets)| !packets.is_empty())
+            .fold(0, |bits, (i, _)| bits | 1 << i);
+        let ep_in_complete = core::mem::take(&mut state.in_complete);
+
+        if ep_setup | ep_out | ep_in_complete == 0 {
+            PollResult::None
+        } else {
+            PollResult::Data {
+                ep_out,
+                ep_in_complete,
+                ep_setup,
+            }
+        }
+    }
+}
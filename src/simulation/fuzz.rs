@@ -0,0 +1,76 @@
+//! Deterministic entry point for fuzzing control request handling
+//!
+//! ```
+//! use usbd_human_interface_device::simulation::fuzz::handle_control;
+//!
+//! // SET_REPORT (Output) to the boot keyboard
+//! handle_control(&[0x21, 0x09, 0x00, 0x02, 0x00, 0x00, 0x01, 0x00], &[0x01]);
+//! ```
+//!
+//! A cargo-fuzz target calls [`handle_control`] with the fuzzer's input split into the setup
+//! packet and the data stage. Requires the `fuzz` feature.
+
+use usb_device::bus::UsbBusAllocator;
+
+use crate::device::composite_keyboard::CompositeKeyboard;
+use crate::device::keyboard::BootKeyboard;
+use crate::device::power::Ups;
+use crate::hid_class::UsbHidClassBuilder;
+use crate::simulation::{control_transfer, device_builder, requests, Response, SimBus};
+
+/// Performs the control transfer `setup`, with OUT data stage `data`, on a newly configured
+/// device
+///
+/// The device combines a boot keyboard, a keyboard with report IDs and a UPS with feature
+/// reports, covering protocol, idle, output, input and feature report handling. `setup` is
+/// padded with zeros, or truncated, to 8 bytes.
+pub fn handle_control(setup: &[u8], data: &[u8]) -> Response {
+    let mut packet = [0; 8];
+    let len = setup.len().min(packet.len());
+    packet[..len].copy_from_slice(&setup[..len]);
+
+    let usb_alloc = UsbBusAllocator::new(SimBus::new());
+    let mut hid = UsbHidClassBuilder::new()
+        .add_interface(BootKeyboard::default_config())
+        .add_interface(CompositeKeyboard::default_config())
+        .add_interface(Ups::default_config())
+        .build(&usb_alloc)
+        .expect("Failed to build fuzzed device");
+    let mut usb_dev = device_builder(&usb_alloc, 8).build();
+
+    control_transfer(
+        &mut usb_dev,
+        &mut [&mut hid],
+        requests::set_configuration(1),
+        &[],
+    );
+    control_transfer(&mut usb_dev, &mut [&mut hid], packet, data)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::simulation::fuzz::handle_control;
+    use crate::simulation::Response;
+
+    #[test]
+    fn malformed_requests_handled() {
+        // GET_REPORT longer than any report, for each report type and interface
+        for interface in 0..4 {
+            for report_type in 0..5 {
+                let setup = [0xA1, 0x01, 0x01, report_type, interface, 0x00, 0xFF, 0xFF];
+                let _ = handle_control(&setup, &[]);
+            }
+        }
+        // SET_REPORT with data stages shorter and longer than the reports
+        for data in [&[][..], &[0x01], &[0xFF; 64]] {
+            for report_id in 0..4 {
+                for interface in 0..4 {
+                    let setup = [0x21, 0x09, report_id, 0x03, interface, 0x00, 0x40, 0x00];
+                    let _ = handle_control(&setup, data);
+                }
+            }
+        }
+        // Truncated setup packet
+        assert_eq!(handle_control(&[0x21], &[]), Response::Stall);
+    }
+}
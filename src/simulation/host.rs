@@ -0,0 +1,237 @@
+//! Simulated host enumerating a device and exchanging reports with its HID interfaces
+
+use std::vec::Vec;
+
+use usb_device::class::UsbClass;
+use usb_device::descriptor::descriptor_type;
+use usb_device::device::UsbDevice;
+
+use crate::hid_class::descriptor::{
+    DescriptorType, HidProtocol, InterfaceSubClass, ReportType, USB_CLASS_HID,
+};
+use crate::hid_class::descriptor_parser::parse_descriptor;
+use crate::simulation::{
+    control_transfer, interrupt_in, interrupt_out, requests, Response, SimBus,
+};
+
+/// Address the host assigns to the device
+const DEVICE_ADDRESS: u8 = 1;
+const CONFIGURATION_HEADER_LEN: u16 = 9;
+const MAX_PARSED_REPORTS: usize = 32;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EnumerationError {
+    /// The device did not respond to `setup` as expected
+    Request { setup: [u8; 8], response: Response },
+    /// A descriptor is shorter than its declared length or missing required fields
+    InvalidDescriptor,
+}
+
+/// HID interface found while enumerating
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HidInterface {
+    pub number: u8,
+    pub subclass: u8,
+    pub protocol: u8,
+    /// HID descriptor read with `GET_DESCRIPTOR`
+    pub hid_descriptor: Vec<u8>,
+    pub report_descriptor: Vec<u8>,
+    /// Index of the interrupt IN endpoint
+    pub in_endpoint: Option<usize>,
+    /// Index of the interrupt OUT endpoint
+    pub out_endpoint: Option<usize>,
+}
+
+/// Host that enumerates a device the way common operating systems do
+///
+/// [`SimHost::enumerate`] reads the device and configuration descriptors, configures the device,
+/// then reads the HID and report descriptors of each HID interface, sets an indefinite idle rate
+/// and switches boot interfaces to report protocol.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SimHost {
+    device_descriptor: Vec<u8>,
+    configuration_descriptor: Vec<u8>,
+    interfaces: Vec<HidInterface>,
+}
+
+impl SimHost {
+    pub fn enumerate(
+        usb_dev: &mut UsbDevice<'_, SimBus>,
+        classes: &mut [&mut dyn UsbClass<SimBus>],
+    ) -> Result<Self, EnumerationError> {
+        let mut transfer = |setup: [u8; 8], data_out: &[u8]| match control_transfer(
+            usb_dev, classes, setup, data_out,
+        ) {
+            Response::Data(data) => Ok(data),
+            Response::Ack => Ok(Vec::new()),
+            response => Err(EnumerationError::Request { setup, response }),
+        };
+
+        transfer(requests::get_device_descriptor(8), &[])?;
+        transfer(requests::set_address(DEVICE_ADDRESS), &[])?;
+        let device_descriptor = transfer(requests::get_device_descriptor(18), &[])?;
+
+        let header = transfer(
+            requests::get_configuration_descriptor(0, CONFIGURATION_HEADER_LEN),
+            &[],
+        )?;
+        let total_len = header
+            .get(2..4)
+            .map(|b| u16::from_le_bytes([b[0], b[1]]))
+            .ok_or(EnumerationError::InvalidDescriptor)?;
+        let configuration_descriptor =
+            transfer(requests::get_configuration_descriptor(0, total_len), &[])?;
+        if configuration_descriptor.len() != usize::from(total_len) {
+            return Err(EnumerationError::InvalidDescriptor);
+        }
+        let mut interfaces = hid_interfaces(&configuration_descriptor)?;
+
+        transfer(requests::set_configuration(header[5]), &[])?;
+
+        for interface in &mut interfaces {
+            let number = interface.number;
+            interface.hid_descriptor = transfer(
+                requests::get_interface_descriptor(number, DescriptorType::Hid, 0, 0xFF),
+                &[],
+            )?;
+            let report_descriptor_len = interface
+                .hid_descriptor
+                .get(7..9)
+                .map(|b| u16::from_le_bytes([b[0], b[1]]))
+                .ok_or(EnumerationError::InvalidDescriptor)?;
+            interface.report_descriptor = transfer(
+                requests::get_interface_descriptor(
+                    number,
+                    DescriptorType::Report,
+                    0,
+                    report_descriptor_len,
+                ),
+                &[],
+            )?;
+            transfer(requests::set_idle(number, 0, 0), &[])?;
+            if interface.subclass == InterfaceSubClass::Boot as u8 {
+                transfer(requests::set_protocol(number, HidProtocol::Report), &[])?;
+            }
+        }
+
+        Ok(Self {
+            device_descriptor,
+            configuration_descriptor,
+            interfaces,
+        })
+    }
+
+    pub fn device_descriptor(&self) -> &[u8] {
+        &self.device_descriptor
+    }
+
+    /// Configuration descriptor with all its interface, HID and endpoint descriptors
+    pub fn configuration_descriptor(&self) -> &[u8] {
+        &self.configuration_descriptor
+    }
+
+    /// HID interfaces in the order they appear in the configuration descriptor
+    pub fn interfaces(&self) -> &[HidInterface] {
+        &self.interfaces
+    }
+
+    pub fn interface(&self, number: u8) -> Option<&HidInterface> {
+        self.interfaces.iter().find(|i| i.number == number)
+    }
+
+    /// Reads the next input report packet from `interface`'s IN endpoint
+    pub fn read_report(
+        &self,
+        usb_dev: &mut UsbDevice<'_, SimBus>,
+        classes: &mut [&mut dyn UsbClass<SimBus>],
+        interface: u8,
+    ) -> Option<Vec<u8>> {
+        let index = self.interface(interface)?.in_endpoint?;
+        interrupt_in(usb_dev, classes, index)
+    }
+
+    /// Sends an output report to `interface`, on its OUT endpoint if it has one, otherwise with
+    /// `SET_REPORT`
+    ///
+    /// `report` includes the report ID if the report has one.
+    pub fn write_report(
+        &self,
+        usb_dev: &mut UsbDevice<'_, SimBus>,
+        classes: &mut [&mut dyn UsbClass<SimBus>],
+        interface: u8,
+        report: &[u8],
+    ) -> Response {
+        let out_endpoint = self.interface(interface).and_then(|i| i.out_endpoint);
+        match out_endpoint {
+            Some(index) => {
+                interrupt_out(usb_dev, classes, index, report);
+                Response::Ack
+            }
+            None => {
+                let report_id = if self.uses_report_ids(interface) {
+                    report.first().copied().unwrap_or_default()
+                } else {
+                    0
+                };
+                let setup = requests::set_report(
+                    interface,
+                    ReportType::Output,
+                    report_id,
+                    report.len() as u16,
+                );
+                control_transfer(usb_dev, classes, setup, report)
+            }
+        }
+    }
+
+    fn uses_report_ids(&self, interface: u8) -> bool {
+        self.interface(interface).is_some_and(|i| {
+            parse_descriptor::<MAX_PARSED_REPORTS>(&i.report_descriptor)
+                .is_ok_and(|summary| summary.uses_report_ids())
+        })
+    }
+}
+
+/// HID interfaces of `configuration_descriptor` with their endpoints, descriptors still to be read
+fn hid_interfaces(configuration_descriptor: &[u8]) -> Result<Vec<HidInterface>, EnumerationError> {
+    let mut interfaces: Vec<HidInterface> = Vec::new();
+    let mut in_hid_interface = false;
+    let mut rest = configuration_descriptor;
+    while let Some(&len) = rest.first() {
+        let len = usize::from(len);
+        if len < 2 || len > rest.len() {
+            return Err(EnumerationError::InvalidDescriptor);
+        }
+        let (descriptor, tail) = rest.split_at(len);
+        rest = tail;
+
+        match descriptor[1] {
+            descriptor_type::INTERFACE if len >= 9 => {
+                in_hid_interface = descriptor[5] == USB_CLASS_HID;
+                if in_hid_interface {
+                    interfaces.push(HidInterface {
+                        number: descriptor[2],
+                        subclass: descriptor[6],
+                        protocol: descriptor[7],
+                        hid_descriptor: Vec::new(),
+                        report_descriptor: Vec::new(),
+                        in_endpoint: None,
+                        out_endpoint: None,
+                    });
+                }
+            }
+            descriptor_type::ENDPOINT if len >= 7 && in_hid_interface => {
+                let interface = interfaces.last_mut().expect("HID interface");
+                let index = usize::from(descriptor[2] & 0x0F);
+                if descriptor[2] & 0x80 != 0 {
+                    interface.in_endpoint = Some(index);
+                } else {
+                    interface.out_endpoint = Some(index);
+                }
+            }
+            descriptor_type::INTERFACE => return Err(EnumerationError::InvalidDescriptor),
+            _ => {}
+        }
+    }
+    Ok(interfaces)
+}
@@ -0,0 +1,528 @@
+//! Host simulation for regression testing against recorded host behaviour
+//!
+//! Classes are instantiated on a [`SimBus`], then [`replay`] plays back the control transfers of
+//! a [`Trace`], typically captured from a real host, and compares the device's responses with the
+//! recorded ones. The `hid-sim` binary replays trace files against the devices in
+//! [`crate::device`].
+//!
+//! ```
+//! # #[cfg(not(feature = "usb-device-03"))] {
+//! use usb_device::bus::UsbBusAllocator;
+//! use usb_device::prelude::*;
+//! use usbd_human_interface_device::device::keyboard::BootKeyboard;
+//! use usbd_human_interface_device::prelude::*;
+//! use usbd_human_interface_device::simulation::{replay, Compare, SimBus, Trace};
+//!
+//! let usb_alloc = UsbBusAllocator::new(SimBus::new());
+//! let mut keyboard = UsbHidClassBuilder::new()
+//!     .add_interface(BootKeyboard::default_config())
+//!     .build(&usb_alloc)
+//!     .unwrap();
+//! let mut usb_dev = UsbDeviceBuilder::new(&usb_alloc, UsbVidPid(0x1209, 0x0001))
+//!     .max_packet_size_0(64)
+//!     .build();
+//!
+//! let trace = Trace::parse_text(
+//!     "00 09 01 00 00 00 00 00 => ack # SET_CONFIGURATION\n\
+//!      a1 03 00 00 00 00 01 00 => 01  # GET_PROTOCOL",
+//! )
+//! .unwrap();
+//!
+//! let report = replay(&mut usb_dev, &mut [&mut keyboard], &trace, Compare::All);
+//! assert!(report.mismatches.is_empty());
+//! # }
+//! ```
+//!
+//! Applications can unit test their devices in the same way, issuing individual requests with
+//! [`control_transfer`] and the setup packets of [`requests`], and exchanging reports with
+//! [`interrupt_in`] and [`interrupt_out`]. [`SimHost`] enumerates a device like a real host
+//! does, for end-to-end tests of composite devices.
+//!
+//! Requires the `simulation` feature, which depends on `std`.
+
+use std::vec::Vec;
+
+use usb_device::bus::{UsbBus, UsbBusAllocator};
+use usb_device::class::UsbClass;
+use usb_device::device::{UsbDevice, UsbDeviceBuilder, UsbVidPid};
+use usb_device::endpoint::EndpointAddress;
+use usb_device::UsbDirection;
+
+pub use bus::SimBus;
+pub use host::{EnumerationError, HidInterface, SimHost};
+pub use trace::{ControlTransfer, Response, Trace, TraceError};
+
+mod bus;
+#[cfg(feature = "fuzz")]
+pub mod fuzz;
+mod host;
+pub mod requests;
+mod trace;
+
+/// Number of polls a device has to respond at each stage of a control transfer
+const POLL_LIMIT: usize = 16;
+
+/// Transfers whose responses are compared by [`replay`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Compare {
+    /// Compare all responses
+    #[default]
+    All,
+    /// Only compare responses to requests addressed to an interface
+    ///
+    /// Useful when the device and string descriptors of the capture differ from those of the
+    /// simulated device.
+    InterfaceRequests,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mismatch {
+    /// Index of the transfer in [`Trace::transfers`]
+    pub index: usize,
+    pub expected: Response,
+    pub actual: Response,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct ReplayReport {
+    pub transfers: usize,
+    pub mismatches: Vec<Mismatch>,
+}
+
+/// Builder of a device on `usb_alloc` with the test VID/PID 1209:0001 and an endpoint 0 of
+/// `max_packet_size_0` bytes
+///
+/// Builds the same device with usb-device 0.2 and 0.3, panicking on invalid packet sizes.
+#[cfg(not(feature = "usb-device-03"))]
+pub fn device_builder<B: UsbBus>(
+    usb_alloc: &UsbBusAllocator<B>,
+    max_packet_size_0: u8,
+) -> UsbDeviceBuilder<'_, B> {
+    UsbDeviceBuilder::new(usb_alloc, UsbVidPid(0x1209, 0x0001)).max_packet_size_0(max_packet_size_0)
+}
+
+/// Builder of a device on `usb_alloc` with the test VID/PID 1209:0001 and an endpoint 0 of
+/// `max_packet_size_0` bytes
+///
+/// Builds the same device with usb-device 0.2 and 0.3, panicking on invalid packet sizes.
+#[cfg(feature = "usb-device-03")]
+pub fn device_builder<B: UsbBus>(
+    usb_alloc: &UsbBusAllocator<B>,
+    max_packet_size_0: u8,
+) -> UsbDeviceBuilder<'_, B> {
+    UsbDeviceBuilder::new(usb_alloc, UsbVidPid(0x1209, 0x0001))
+        .max_packet_size_0(max_packet_size_0)
+        .expect("Invalid endpoint 0 packet size")
+}
+
+/// Plays back each transfer of `trace`, collecting responses that differ from those recorded
+pub fn replay(
+    usb_dev: &mut UsbDevice<'_, SimBus>,
+    classes: &mut [&mut dyn UsbClass<SimBus>],
+    trace: &Trace,
+    compare: Compare,
+) -> ReplayReport {
+    let mut report = ReplayReport::default();
+    for (index, transfer) in trace.transfers.iter().enumerate() {
+        let actual = control_transfer(usb_dev, classes, transfer.setup, &transfer.data_out);
+        report.transfers += 1;
+
+        let compared = compare == Compare::All || transfer.is_interface_request();
+        if compared && !transfer.response.matches(&actual) {
+            report.mismatches.push(Mismatch {
+                index,
+                expected: transfer.response.clone(),
+                actual,
+            });
+        }
+    }
+    report
+}
+
+/// Performs a single control transfer, returning the device's response
+///
+/// The IN data stage is ended by a short packet or once the requested length has been received.
+pub fn control_transfer(
+    usb_dev: &mut UsbDevice<'_, SimBus>,
+    classes: &mut [&mut dyn UsbClass<SimBus>],
+    setup: [u8; 8],
+    data_out: &[u8],
+) -> Response {
+    let max_packet_size = usize::from(
+        usb_dev
+            .bus()
+            .max_packet_size(EndpointAddress::from_parts(0, UsbDirection::In)),
+    );
+    let length = usize::from(u16::from_le_bytes([setup[6], setup[7]]));
+
+    usb_dev.bus().host_setup(setup);
+    usb_dev.poll(classes);
+
+    if setup[0] & 0x80 != 0 {
+        let mut data = Vec::new();
+        loop {
+            match take_in(usb_dev, classes) {
+                Some(packet) => {
+                    let short = packet.len() < max_packet_size;
+                    data.extend(packet);
+                    if short || data.len() >= length {
+                        break;
+                    }
+                }
+                None if usb_dev.bus().is_endpoint_stalled(0) => return Response::Stall,
+                None => return Response::Timeout,
+            }
+        }
+        // Status stage
+        usb_dev.bus().host_out(0, &[]);
+        usb_dev.poll(classes);
+        Response::Data(data)
+    } else {
+        for chunk in data_out.chunks(max_packet_size) {
+            if usb_dev.bus().is_endpoint_stalled(0) {
+                return Response::Stall;
+            }
+            usb_dev.bus().host_out(0, chunk);
+            usb_dev.poll(classes);
+        }
+        // Status stage
+        match take_in(usb_dev, classes) {
+            Some(_) => {
+                usb_dev.poll(classes);
+                Response::Ack
+            }
+            None if usb_dev.bus().is_endpoint_stalled(0) => Response::Stall,
+            None => Response::Timeout,
+        }
+    }
+}
+
+/// Takes the packet the device has written to interrupt IN endpoint `index`, polling the device
+/// until it does
+///
+/// Reports longer than the endpoint's maximum packet size are returned one packet per call.
+pub fn interrupt_in(
+    usb_dev: &mut UsbDevice<'_, SimBus>,
+    classes: &mut [&mut dyn UsbClass<SimBus>],
+    index: usize,
+) -> Option<Vec<u8>> {
+    for _ in 0..POLL_LIMIT {
+        if let Some(packet) = usb_dev.bus().host_take_in(index) {
+            // Report the IN transfer as complete to the device
+            usb_dev.poll(classes);
+            return Some(packet);
+        }
+        usb_dev.poll(classes);
+    }
+    None
+}
+
+/// Sends `data` to interrupt OUT endpoint `index` and polls the device to receive it
+pub fn interrupt_out(
+    usb_dev: &mut UsbDevice<'_, SimBus>,
+    classes: &mut [&mut dyn UsbClass<SimBus>],
+    index: usize,
+    data: &[u8],
+) {
+    usb_dev.bus().host_out(index, data);
+    usb_dev.poll(classes);
+}
+
+fn take_in(
+    usb_dev: &mut UsbDevice<'_, SimBus>,
+    classes: &mut [&mut dyn UsbClass<SimBus>],
+) -> Option<Vec<u8>> {
+    for _ in 0..POLL_LIMIT {
+        if let Some(packet) = usb_dev.bus().host_take_in(0) {
+            return Some(packet);
+        }
+        if usb_dev.bus().is_endpoint_stalled(0) {
+            return None;
+        }
+        usb_dev.poll(classes);
+    }
+    None
+}
+
+#[cfg(test)]
+mod test {
+    use usb_device::bus::UsbBusAllocator;
+    use usb_device::prelude::*;
+
+    use crate::device::keyboard::{BootKeyboard, BOOT_KEYBOARD_REPORT_DESCRIPTOR};
+    use crate::hid_class::prelude::*;
+    use crate::simulation::{
+        control_transfer, device_builder, interrupt_in, interrupt_out, replay, requests, Compare,
+        Mismatch, Response, SimBus, Trace,
+    };
+
+    #[test]
+    fn replay_keyboard_enumeration() {
+        let usb_alloc = UsbBusAllocator::new(SimBus::new());
+        let mut keyboard = UsbHidClassBuilder::new()
+            .add_interface(BootKeyboard::default_config())
+            .build(&usb_alloc)
+            .unwrap();
+        let mut usb_dev = device_builder(&usb_alloc, 8).build();
+
+        let mut trace = Trace::parse_text(
+            "00 05 07 00 00 00 00 00 => ack   # SET_ADDRESS\n\
+             80 06 00 01 00 00 08 00 => *     # GET_DESCRIPTOR (Device)\n\
+             00 09 01 00 00 00 00 00 => ack   # SET_CONFIGURATION\n\
+             21 0a 00 00 00 00 00 00 => ack   # SET_IDLE\n\
+             81 06 00 22 00 00 ff 00 => *     # GET_DESCRIPTOR (Report)\n\
+             21 0b 00 00 00 00 00 00 => ack   # SET_PROTOCOL (Boot)\n\
+             a1 03 00 00 00 00 01 00 => 00    # GET_PROTOCOL\n\
+             21 09 00 02 00 00 01 00 01 => ack # SET_REPORT (Output)\n\
+             21 02 00 00 00 00 00 00 => ack   # Unsupported class request",
+        )
+        .unwrap();
+        trace.transfers[4].response = Response::Data(BOOT_KEYBOARD_REPORT_DESCRIPTOR.to_vec());
+
+        let report = replay(&mut usb_dev, &mut [&mut keyboard], &trace, Compare::All);
+
+        assert_eq!(report.transfers, 9);
+        assert_eq!(
+            report.mismatches,
+            [Mismatch {
+                index: 8,
+                expected: Response::Ack,
+                actual: Response::Stall,
+            }]
+        );
+        assert_eq!(usb_dev.bus().address(), 7);
+        assert_eq!(usb_dev.state(), UsbDeviceState::Configured);
+        assert_eq!(
+            keyboard
+                .interface::<BootKeyboard<'_, _>, _>()
+                .read_report()
+                .ok(),
+            Some(crate::device::keyboard::KeyboardLedsReport {
+                num_lock: true,
+                ..Default::default()
+            })
+        );
+    }
+
+    #[test]
+    fn exchange_reports_with_keyboard() {
+        use crate::device::keyboard::{BootKeyboardReport, KeyboardLedsReport};
+        use crate::hid_class::descriptor::{DescriptorType, ReportType};
+        use crate::interface::InterfaceClass;
+        use crate::page::Keyboard;
+        use packed_struct::PackedStruct;
+
+        let usb_alloc = UsbBusAllocator::new(SimBus::new());
+        let mut keyboard = UsbHidClassBuilder::new()
+            .add_interface(BootKeyboard::default_config())
+            .build(&usb_alloc)
+            .unwrap();
+        let mut usb_dev = device_builder(&usb_alloc, 8).build();
+
+        let mut transfer = |setup: [u8; 8], data: &[u8]| {
+            control_transfer(&mut usb_dev, &mut [&mut keyboard], setup, data)
+        };
+        assert_eq!(transfer(requests::set_configuration(1), &[]), Response::Ack);
+        assert_eq!(
+            transfer(
+                requests::get_interface_descriptor(0, DescriptorType::Report, 0, 0xFF),
+                &[]
+            ),
+            Response::Data(BOOT_KEYBOARD_REPORT_DESCRIPTOR.to_vec())
+        );
+        assert_eq!(transfer(requests::set_idle(0, 0, 125), &[]), Response::Ack);
+        assert_eq!(
+            transfer(requests::get_idle(0, 0), &[]),
+            Response::Data(vec![125])
+        );
+        assert_eq!(
+            transfer(requests::set_protocol(0, HidProtocol::Boot), &[]),
+            Response::Ack
+        );
+        assert_eq!(
+            transfer(requests::get_protocol(0), &[]),
+            Response::Data(vec![0])
+        );
+        assert_eq!(
+            transfer(requests::set_report(0, ReportType::Output, 0, 1), &[0x02]),
+            Response::Ack
+        );
+
+        let report = BootKeyboardReport::new([Keyboard::A]);
+        let interface = keyboard.interface::<BootKeyboard<'_, _>, _>();
+        assert_eq!(
+            interface.read_report().ok(),
+            Some(KeyboardLedsReport {
+                caps_lock: true,
+                ..Default::default()
+            })
+        );
+        interface.write_report(&report).unwrap();
+        let in_index = interface.in_endpoint_address().index();
+        let out_index = interface.out_endpoint_address().unwrap().index();
+
+        assert_eq!(
+            interrupt_in(&mut usb_dev, &mut [&mut keyboard], in_index),
+            Some(report.pack().unwrap().to_vec())
+        );
+        assert_eq!(
+            interrupt_in(&mut usb_dev, &mut [&mut keyboard], in_index),
+            None
+        );
+
+        interrupt_out(&mut usb_dev, &mut [&mut keyboard], out_index, &[0x01]);
+        assert!(
+            keyboard
+                .interface::<BootKeyboard<'_, _>, _>()
+                .read_report()
+                .unwrap()
+                .num_lock
+        );
+    }
+
+    #[test]
+    fn remote_wakeup_on_report() {
+        use crate::device::keyboard::BootKeyboardReport;
+        use crate::interface::InterfaceClass;
+        use crate::page::Keyboard;
+        use packed_struct::PackedStruct;
+
+        let mut config = BootKeyboard::default_config();
+        config.inner_config = config.inner_config.wake_on_report(true);
+        let usb_alloc = UsbBusAllocator::new(SimBus::new());
+        let mut keyboard = UsbHidClassBuilder::new()
+            .add_interface(config)
+            .build(&usb_alloc)
+            .unwrap();
+        let mut usb_dev = device_builder(&usb_alloc, 8)
+            .supports_remote_wakeup(true)
+            .build();
+        let in_index = keyboard
+            .interface::<BootKeyboard<'_, _>, _>()
+            .in_endpoint_address()
+            .index();
+        let press = |keyboard: &UsbHidClass<_, _>, key| {
+            keyboard
+                .interface::<BootKeyboard<'_, _>, _>()
+                .write_report(&BootKeyboardReport::new([key]))
+                .unwrap();
+        };
+
+        let response = control_transfer(
+            &mut usb_dev,
+            &mut [&mut keyboard],
+            requests::set_configuration(1),
+            &[],
+        );
+        assert_eq!(response, Response::Ack);
+
+        // Reports written while the bus is active are sent without waking the host
+        press(&keyboard, Keyboard::A);
+        assert!(!keyboard.remote_wakeup(&usb_dev));
+        assert!(interrupt_in(&mut usb_dev, &mut [&mut keyboard], in_index).is_some());
+
+        // The host hasn't enabled remote wakeup
+        usb_dev.bus().host_suspend();
+        usb_dev.poll(&mut [&mut keyboard]);
+        assert_eq!(usb_dev.state(), UsbDeviceState::Suspend);
+        press(&keyboard, Keyboard::B);
+        assert!(!keyboard.remote_wakeup(&usb_dev));
+
+        usb_dev.bus().host_resume();
+        usb_dev.poll(&mut [&mut keyboard]);
+        assert!(interrupt_in(&mut usb_dev, &mut [&mut keyboard], in_index).is_some());
+        let response = control_transfer(
+            &mut usb_dev,
+            &mut [&mut keyboard],
+            requests::set_remote_wakeup(),
+            &[],
+        );
+        assert_eq!(response, Response::Ack);
+
+        usb_dev.bus().host_suspend();
+        usb_dev.poll(&mut [&mut keyboard]);
+        assert!(!keyboard.remote_wakeup(&usb_dev));
+        press(&keyboard, Keyboard::C);
+        assert!(keyboard.remote_wakeup(&usb_dev));
+        assert!(!keyboard.remote_wakeup(&usb_dev));
+
+        usb_dev.bus().host_resume();
+        usb_dev.poll(&mut [&mut keyboard]);
+        assert_eq!(
+            interrupt_in(&mut usb_dev, &mut [&mut keyboard], in_index),
+            Some(
+                BootKeyboardReport::new([Keyboard::C])
+                    .pack()
+                    .unwrap()
+                    .to_vec()
+            )
+        );
+    }
+
+    #[test]
+    fn enumerate_composite_device() {
+        use crate::device::keyboard::KeyboardLedsReport;
+        use crate::device::mouse::{WheelMouse, WheelMouseReport, WHEEL_MOUSE_REPORT_DESCRIPTOR};
+        use crate::simulation::{device_builder, SimHost};
+        use packed_struct::PackedStruct;
+
+        let usb_alloc = UsbBusAllocator::new(SimBus::new());
+        let mut composite = UsbHidClassBuilder::new()
+            .add_interface(BootKeyboard::default_config())
+            .add_interface(WheelMouse::default_config())
+            .build(&usb_alloc)
+            .unwrap();
+        let mut usb_dev = device_builder(&usb_alloc, 8).build();
+
+        let host = SimHost::enumerate(&mut usb_dev, &mut [&mut composite]).unwrap();
+        assert_eq!(usb_dev.bus().address(), 1);
+        assert_eq!(usb_dev.state(), UsbDeviceState::Configured);
+        assert_eq!(host.device_descriptor().len(), 18);
+
+        // Interfaces are numbered in the reverse of the order they were added
+        let [mouse, keyboard] = host.interfaces() else {
+            panic!("Expected two HID interfaces");
+        };
+        assert_eq!(mouse.report_descriptor, WHEEL_MOUSE_REPORT_DESCRIPTOR);
+        assert_eq!(keyboard.report_descriptor, BOOT_KEYBOARD_REPORT_DESCRIPTOR);
+        assert_eq!((keyboard.subclass, keyboard.protocol), (1, 1));
+        assert!(keyboard.out_endpoint.is_some());
+        assert_eq!(
+            composite.interface::<BootKeyboard<'_, _>, _>().protocol(),
+            HidProtocol::Report
+        );
+
+        let report = WheelMouseReport {
+            x: 5,
+            ..Default::default()
+        };
+        composite
+            .interface::<WheelMouse<'_, _>, _>()
+            .write_report(&report)
+            .unwrap();
+        assert_eq!(
+            host.read_report(&mut usb_dev, &mut [&mut composite], mouse.number),
+            Some(report.pack().unwrap().to_vec())
+        );
+
+        assert_eq!(
+            host.write_report(
+                &mut usb_dev,
+                &mut [&mut composite],
+                keyboard.number,
+                &[0x04]
+            ),
+            Response::Ack
+        );
+        assert_eq!(
+            composite
+                .interface::<BootKeyboard<'_, _>, _>()
+                .read_report()
+                .ok(),
+            Some(KeyboardLedsReport {
+                scroll_lock: true,
+                ..Default::default()
+            })
+        );
+    }
+}
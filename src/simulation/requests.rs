@@ -0,0 +1,137 @@
+//! Setup packets of standard and HID class requests, for [`control_transfer`](super::control_transfer)
+
+use usb_device::control::Request;
+use usb_device::descriptor::descriptor_type;
+
+use crate::hid_class::descriptor::{DescriptorType, HidProtocol, ReportType};
+use crate::hid_class::HidRequest;
+
+const DEVICE_TO_HOST: u8 = 0x80;
+const CLASS: u8 = 0x20;
+const INTERFACE: u8 = 0x01;
+
+fn setup(request_type: u8, request: u8, value: u16, index: u16, length: u16) -> [u8; 8] {
+    let [value_lsb, value_msb] = value.to_le_bytes();
+    let [index_lsb, index_msb] = index.to_le_bytes();
+    let [length_lsb, length_msb] = length.to_le_bytes();
+    [
+        request_type,
+        request,
+        value_lsb,
+        value_msb,
+        index_lsb,
+        index_msb,
+        length_lsb,
+        length_msb,
+    ]
+}
+
+pub fn set_address(address: u8) -> [u8; 8] {
+    setup(0x00, Request::SET_ADDRESS, address.into(), 0, 0)
+}
+
+pub fn set_configuration(configuration: u8) -> [u8; 8] {
+    setup(0x00, Request::SET_CONFIGURATION, configuration.into(), 0, 0)
+}
+
+/// Allows the device to signal resume while suspended
+pub fn set_remote_wakeup() -> [u8; 8] {
+    setup(
+        0x00,
+        Request::SET_FEATURE,
+        Request::FEATURE_DEVICE_REMOTE_WAKEUP,
+        0,
+        0,
+    )
+}
+
+/// Device descriptor of `length` bytes
+pub fn get_device_descriptor(length: u16) -> [u8; 8] {
+    let value = u16::from(descriptor_type::DEVICE) << 8;
+    setup(DEVICE_TO_HOST, Request::GET_DESCRIPTOR, value, 0, length)
+}
+
+/// Configuration descriptor `index`, with its interface, HID and endpoint descriptors
+pub fn get_configuration_descriptor(index: u8, length: u16) -> [u8; 8] {
+    let value = u16::from(descriptor_type::CONFIGURATION) << 8 | u16::from(index);
+    setup(DEVICE_TO_HOST, Request::GET_DESCRIPTOR, value, 0, length)
+}
+
+/// HID, report or physical descriptor `index` of `interface`
+pub fn get_interface_descriptor(
+    interface: u8,
+    descriptor_type: DescriptorType,
+    index: u8,
+    length: u16,
+) -> [u8; 8] {
+    let value = u16::from(descriptor_type as u8) << 8 | u16::from(index);
+    setup(
+        DEVICE_TO_HOST | INTERFACE,
+        Request::GET_DESCRIPTOR,
+        value,
+        interface.into(),
+        length,
+    )
+}
+
+pub fn get_report(interface: u8, report_type: ReportType, report_id: u8, length: u16) -> [u8; 8] {
+    setup(
+        DEVICE_TO_HOST | CLASS | INTERFACE,
+        HidRequest::GetReport as u8,
+        u16::from(report_type as u8) << 8 | u16::from(report_id),
+        interface.into(),
+        length,
+    )
+}
+
+/// `SET_REPORT` of a `length` byte report, including the report ID if the report has one
+pub fn set_report(interface: u8, report_type: ReportType, report_id: u8, length: u16) -> [u8; 8] {
+    setup(
+        CLASS | INTERFACE,
+        HidRequest::SetReport as u8,
+        u16::from(report_type as u8) << 8 | u16::from(report_id),
+        interface.into(),
+        length,
+    )
+}
+
+pub fn get_idle(interface: u8, report_id: u8) -> [u8; 8] {
+    setup(
+        DEVICE_TO_HOST | CLASS | INTERFACE,
+        HidRequest::GetIdle as u8,
+        report_id.into(),
+        interface.into(),
+        1,
+    )
+}
+
+/// `SET_IDLE` of `duration` in units of 4ms, 0 for indefinite, for `report_id`, 0 for all reports
+pub fn set_idle(interface: u8, report_id: u8, duration: u8) -> [u8; 8] {
+    setup(
+        CLASS | INTERFACE,
+        HidRequest::SetIdle as u8,
+        u16::from(duration) << 8 | u16::from(report_id),
+        interface.into(),
+        0,
+    )
+}
+
+pub fn get_protocol(interface: u8) -> [u8; 8] {
+    setup(
+        DEVICE_TO_HOST | CLASS | INTERFACE,
+        HidRequest::GetProtocol as u8,
+        0,
+        interface.into(),
+        1,
+    )
+}
+
+pub fn set_protocol(interface: u8, protocol: HidProtocol) -> [u8; 8] {
+    setup(
+        CLASS | INTERFACE,
+        HidRequest::SetProtocol as u8,
+        protocol as u16,
+        interface.into(),
+        0,
+    )
+}
@@ -0,0 +1,311 @@
+//! Recorded control transfers
+//!
+//! Traces can be written by hand in a line based text format, or extracted from a pcap capture
+//! made with Linux usbmon, such as one saved by Wireshark.
+//!
+//! Each line of a text trace is a SETUP packet, followed by any OUT data, then `=>` and the
+//! expected response: the IN data, `ack` for a successful transfer without IN data, `stall`, or
+//! `*` to accept any response. Bytes are hexadecimal and text after `#` is ignored.
+//!
+//! ```text
+//! # GET_DESCRIPTOR (Report), truncated to 4 bytes
+//! 81 06 00 22 00 00 04 00 => 05 01 09 06
+//! # SET_REPORT (Output) with one byte of data
+//! 21 09 00 02 00 00 01 00 02 => ack
+//! ```
+
+use std::collections::BTreeMap;
+use std::string::String;
+use std::vec::Vec;
+
+/// Device response to a control transfer
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Response {
+    /// IN data, possibly empty
+    Data(Vec<u8>),
+    /// Status stage of a transfer without IN data
+    Ack,
+    Stall,
+    /// The device did not complete the transfer
+    Timeout,
+    /// Expected response only, any response matches
+    Any,
+}
+
+impl Response {
+    pub fn matches(&self, actual: &Response) -> bool {
+        *self == Response::Any || self == actual
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ControlTransfer {
+    pub setup: [u8; 8],
+    /// Data stage of host to device transfers
+    pub data_out: Vec<u8>,
+    pub response: Response,
+}
+
+impl ControlTransfer {
+    pub fn is_in(&self) -> bool {
+        self.setup[0] & 0x80 != 0
+    }
+
+    /// Request addressed to an interface, rather than the device or an endpoint
+    pub fn is_interface_request(&self) -> bool {
+        self.setup[0] & 0x1F == 0x01
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TraceError {
+    /// Line `line` of a text trace could not be parsed, lines are numbered from 1
+    Syntax { line: usize },
+    /// The capture is not a pcap file of usbmon packets
+    UnsupportedCapture,
+    /// The capture ends part way through a packet
+    Truncated,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Trace {
+    pub transfers: Vec<ControlTransfer>,
+}
+
+impl Trace {
+    pub fn parse_text(text: &str) -> Result<Self, TraceError> {
+        let mut transfers = Vec::new();
+        for (n, line) in text.lines().enumerate() {
+            let syntax = TraceError::Syntax { line: n + 1 };
+            let line = line.split('#').next().unwrap_or_default().trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            let (request, response) = line.split_once("=>").ok_or(syntax)?;
+            let request = parse_hex(request).ok_or(syntax)?;
+            if request.len() < 8 {
+                return Err(syntax);
+            }
+            let response = match response.trim() {
+                "ack" => Response::Ack,
+                "stall" => Response::Stall,
+                "*" => Response::Any,
+                data => Response::Data(parse_hex(data).ok_or(syntax)?),
+            };
+
+            transfers.push(ControlTransfer {
+                setup: request[..8].try_into().unwrap(),
+                data_out: request[8..].to_vec(),
+                response,
+            });
+        }
+        Ok(Self { transfers })
+    }
+
+    pub fn to_text(&self) -> String {
+        let hex = |bytes: &[u8]| {
+            bytes
+                .iter()
+                .map(|b| format!("{b:02x}"))
+                .collect::<Vec<_>>()
+                .join(" ")
+        };
+
+        self.transfers
+            .iter()
+            .map(|t| {
+                let response = match &t.response {
+                    Response::Data(data) => hex(data),
+                    Response::Ack => "ack".into(),
+                    Response::Stall => "stall".into(),
+                    Response::Timeout | Response::Any => "*".into(),
+                };
+                let mut request = t.setup.to_vec();
+                request.extend_from_slice(&t.data_out);
+                format!("{} => {}\n", hex(&request), response)
+            })
+            .collect()
+    }
+
+    /// Extracts the control transfers of device `devnum`, or all devices if `None`, from a
+    /// usbmon pcap capture
+    ///
+    /// Transfers that failed for reasons other than a stall, such as being cancelled, are skipped.
+    /// Responses that were truncated by the capture's snapshot length match any response.
+    pub fn from_usbmon_pcap(capture: &[u8], devnum: Option<u8>) -> Result<Self, TraceError> {
+        const LINKTYPE_USB_LINUX: u32 = 189;
+        const LINKTYPE_USB_LINUX_MMAPPED: u32 = 220;
+        const XFER_CONTROL: u8 = 2;
+        const EPIPE: i32 = -32;
+
+        let header = capture.get(..24).ok_or(TraceError::Truncated)?;
+        let swapped = match u32::from_le_bytes(header[..4].try_into().unwrap()) {
+            0xA1B2_C3D4 | 0xA1B2_3C4D => false,
+            0xD4C3_B2A1 | 0x4D3C_B2A1 => true,
+            _ => return Err(TraceError::UnsupportedCapture),
+        };
+        let pcap_u32 = |bytes: &[u8]| {
+            let bytes = bytes[..4].try_into().unwrap();
+            if swapped {
+                u32::from_be_bytes(bytes)
+            } else {
+                u32::from_le_bytes(bytes)
+            }
+        };
+        let header_len = match pcap_u32(&header[20..]) {
+            LINKTYPE_USB_LINUX => 48,
+            LINKTYPE_USB_LINUX_MMAPPED => 64,
+            _ => return Err(TraceError::UnsupportedCapture),
+        };
+
+        let mut submitted = BTreeMap::new();
+        let mut transfers = Vec::new();
+        let mut records = &capture[24..];
+        while !records.is_empty() {
+            let record = records.get(..16).ok_or(TraceError::Truncated)?;
+            let len = pcap_u32(&record[8..]) as usize;
+            let packet = records.get(16..16 + len).ok_or(TraceError::Truncated)?;
+            records = &records[16 + len..];
+
+            // usbmon headers are in the byte order of the capturing host, assumed little endian
+            let usbmon = packet.get(..header_len).ok_or(TraceError::Truncated)?;
+            let data = &packet[header_len..];
+            let id = u64::from_le_bytes(usbmon[..8].try_into().unwrap());
+            let event = usbmon[8];
+            let setup_present = usbmon[14] == 0;
+            let status = i32::from_le_bytes(usbmon[28..32].try_into().unwrap());
+            let length = u32::from_le_bytes(usbmon[32..36].try_into().unwrap()) as usize;
+            let captured = u32::from_le_bytes(usbmon[36..40].try_into().unwrap()) as usize;
+            let data = &data[..captured.min(data.len())];
+
+            if usbmon[9] != XFER_CONTROL || devnum.is_some_and(|d| d != usbmon[11]) {
+                continue;
+            }
+
+            match event {
+                b'S' if setup_present => {
+                    let setup: [u8; 8] = usbmon[40..48].try_into().unwrap();
+                    let data_out = if setup[0] & 0x80 == 0 {
+                        data.to_vec()
+                    } else {
+                        Vec::new()
+                    };
+                    submitted.insert(id, (setup, data_out));
+                }
+                b'C' => {
+                    let Some((setup, data_out)) = submitted.remove(&id) else {
+                        continue;
+                    };
+                    let response = match status {
+                        0 if setup[0] & 0x80 == 0 => Response::Ack,
+                        0 if data.len() < length => Response::Any,
+                        0 => Response::Data(data.to_vec()),
+                        EPIPE => Response::Stall,
+                        _ => continue,
+                    };
+                    transfers.push(ControlTransfer {
+                        setup,
+                        data_out,
+                        response,
+                    });
+                }
+                _ => {}
+            }
+        }
+
+        Ok(Self { transfers })
+    }
+}
+
+fn parse_hex(s: &str) -> Option<Vec<u8>> {
+    s.split_whitespace()
+        .map(|b| u8::from_str_radix(b, 16).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use std::vec::Vec;
+
+    use crate::simulation::trace::{Response, Trace, TraceError};
+
+    #[test]
+    fn text_round_trip() {
+        let text = "81 06 00 22 00 00 04 00 => 05 01 09 06\n\
+                    21 09 00 02 00 00 01 00 02 => ack\n\
+                    81 06 00 21 00 00 09 00 => stall\n";
+        let trace = Trace::parse_text(text).unwrap();
+
+        assert_eq!(trace.transfers.len(), 3);
+        assert_eq!(trace.transfers[1].data_out, [0x02]);
+        assert_eq!(trace.transfers[2].response, Response::Stall);
+        assert_eq!(trace.to_text(), text);
+    }
+
+    #[test]
+    fn text_syntax_errors() {
+        assert_eq!(
+            Trace::parse_text("# comment\n\n81 06 00 22 => *"),
+            Err(TraceError::Syntax { line: 3 })
+        );
+        assert_eq!(
+            Trace::parse_text("81 06 00 22 00 00 04 00 => 0x05"),
+            Err(TraceError::Syntax { line: 1 })
+        );
+    }
+
+    fn usbmon_packet(
+        id: u64,
+        event: u8,
+        setup: Option<[u8; 8]>,
+        status: i32,
+        data: &[u8],
+    ) -> Vec<u8> {
+        let mut packet = vec![0; 48];
+        packet[..8].copy_from_slice(&id.to_le_bytes());
+        packet[8] = event;
+        packet[9] = 2;
+        packet[11] = 3;
+        packet[14] = if setup.is_some() { 0 } else { b'-' };
+        packet[28..32].copy_from_slice(&status.to_le_bytes());
+        packet[32..36].copy_from_slice(&(data.len() as u32).to_le_bytes());
+        packet[36..40].copy_from_slice(&(data.len() as u32).to_le_bytes());
+        packet[40..48].copy_from_slice(&setup.unwrap_or_default());
+        packet.extend_from_slice(data);
+
+        let mut record = vec![0; 16];
+        record[8..12].copy_from_slice(&(packet.len() as u32).to_le_bytes());
+        record[12..16].copy_from_slice(&(packet.len() as u32).to_le_bytes());
+        record.extend(packet);
+        record
+    }
+
+    #[test]
+    fn usbmon_capture() {
+        let get_descriptor = [0x81, 0x06, 0x00, 0x22, 0x00, 0x00, 0x04, 0x00];
+        let set_idle = [0x21, 0x0A, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00];
+
+        let mut capture = vec![0xD4, 0xC3, 0xB2, 0xA1, 2, 0, 4, 0];
+        capture.extend_from_slice(&[0; 12]);
+        capture.extend_from_slice(&189_u32.to_le_bytes());
+        capture.extend(usbmon_packet(1, b'S', Some(get_descriptor), -115, &[]));
+        capture.extend(usbmon_packet(2, b'S', Some(set_idle), -115, &[]));
+        capture.extend(usbmon_packet(2, b'C', None, -32, &[]));
+        capture.extend(usbmon_packet(1, b'C', None, 0, &[0x05, 0x01, 0x09, 0x06]));
+
+        let trace = Trace::from_usbmon_pcap(&capture, Some(3)).unwrap();
+        assert_eq!(
+            trace.to_text(),
+            "21 0a 00 00 00 00 00 00 => stall\n81 06 00 22 00 00 04 00 => 05 01 09 06\n"
+        );
+        assert!(Trace::from_usbmon_pcap(&capture, Some(4))
+            .unwrap()
+            .transfers
+            .is_empty());
+        assert_eq!(
+            Trace::from_usbmon_pcap(&capture[..30], None),
+            Err(TraceError::Truncated)
+        );
+    }
+}
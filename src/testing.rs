@@ -0,0 +1,199 @@
+//! Test utilities for checking report structs against report descriptors
+//!
+//! A report struct that packs to a different size than its report descriptor declares is
+//! silently truncated or padded by the host. [`assert_report_size`] catches this in a unit test:
+//!
+//! ```
+//! use usbd_human_interface_device::device::mouse::{BootMouseReport, BOOT_MOUSE_REPORT_DESCRIPTOR};
+//! use usbd_human_interface_device::hid_class::descriptor::ReportType;
+//! use usbd_human_interface_device::testing::assert_report_size;
+//!
+//! assert_report_size::<BootMouseReport>(BOOT_MOUSE_REPORT_DESCRIPTOR, ReportType::Input, 0);
+//! ```
+//!
+//! Requires the `std` feature.
+
+use std::string::String;
+
+use packed_struct::PackedStruct;
+
+use crate::hid_class::descriptor::ReportType;
+use crate::hid_class::descriptor_parser::parse_descriptor;
+
+/// Reports summarised from the descriptor under test
+const MAX_REPORTS: usize = 64;
+
+/// Size in bytes of the packed form of `R`
+pub fn packed_report_len<R: PackedStruct>() -> usize {
+    core::mem::size_of::<R::ByteArray>()
+}
+
+/// Checks that `R` packs to the size `descriptor` declares for the report of `report_type` and
+/// `report_id`, excluding the report ID
+///
+/// `report_id` is 0 for descriptors without report IDs.
+pub fn check_report_size<R: PackedStruct>(
+    descriptor: &[u8],
+    report_type: ReportType,
+    report_id: u8,
+) -> Result<(), String> {
+    let summary = parse_descriptor::<MAX_REPORTS>(descriptor)
+        .map_err(|e| format!("Invalid report descriptor: {:?}", e))?;
+    let expected = summary
+        .report_len(report_type, report_id)
+        .ok_or_else(|| format!("No {:?} report with ID {}", report_type, report_id))?;
+    let actual = packed_report_len::<R>();
+    if actual == expected {
+        Ok(())
+    } else {
+        Err(format!(
+            "{:?} report with ID {} is {} bytes, {} packs to {} bytes",
+            report_type,
+            report_id,
+            expected,
+            core::any::type_name::<R>(),
+            actual
+        ))
+    }
+}
+
+/// Panics unless `R` packs to the size `descriptor` declares, see [`check_report_size`]
+#[track_caller]
+pub fn assert_report_size<R: PackedStruct>(
+    descriptor: &[u8],
+    report_type: ReportType,
+    report_id: u8,
+) {
+    if let Err(e) = check_report_size::<R>(descriptor, report_type, report_id) {
+        panic!("{}", e);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::hid_class::descriptor::ReportType::{self, Feature, Input, Output};
+    use crate::testing::*;
+
+    #[test]
+    fn shipped_reports_match_descriptors() {
+        use crate::device::composite_keyboard::*;
+        use crate::device::environmental::*;
+        use crate::device::gaming_mouse::*;
+        use crate::device::multitouch::*;
+        use crate::device::pid::*;
+        use crate::device::power::*;
+        use crate::device::sensor::*;
+
+        assert_report_size::<SystemControlReport>(
+            COMPOSITE_KEYBOARD_REPORT_DESCRIPTOR,
+            Input,
+            SYSTEM_REPORT_ID,
+        );
+        assert_report_size::<GamingMouseReport>(
+            GAMING_MOUSE_REPORT_DESCRIPTOR,
+            Input,
+            GAMING_MOUSE_REPORT_ID,
+        );
+        assert_report_size::<TemperatureReport>(
+            ENVIRONMENTAL_SENSOR_REPORT_DESCRIPTOR,
+            Input,
+            TEMPERATURE_REPORT_ID,
+        );
+        assert_report_size::<HumidityReport>(
+            ENVIRONMENTAL_SENSOR_REPORT_DESCRIPTOR,
+            Input,
+            HUMIDITY_REPORT_ID,
+        );
+        assert_report_size::<MultiTouchReport>(
+            MULTI_TOUCH_REPORT_DESCRIPTOR,
+            Input,
+            MULTI_TOUCH_REPORT_ID,
+        );
+        assert_report_size::<BatteryCapacityReport>(
+            UPS_REPORT_DESCRIPTOR,
+            Feature,
+            BATTERY_CAPACITY_REPORT_ID,
+        );
+        assert_report_size::<PresentStatusReport>(
+            UPS_REPORT_DESCRIPTOR,
+            Input,
+            PRESENT_STATUS_REPORT_ID,
+        );
+        assert_report_size::<RemainingCapacityReport>(
+            UPS_REPORT_DESCRIPTOR,
+            Input,
+            REMAINING_CAPACITY_REPORT_ID,
+        );
+        assert_report_size::<AccelerometerReport>(ACCELEROMETER_REPORT_DESCRIPTOR, Input, 0);
+        assert_report_size::<GyrometerReport>(GYROMETER_REPORT_DESCRIPTOR, Input, 0);
+        assert_report_size::<AmbientLightReport>(AMBIENT_LIGHT_REPORT_DESCRIPTOR, Input, 0);
+        assert_report_size::<SensorProperties>(ACCELEROMETER_REPORT_DESCRIPTOR, Feature, 0);
+        assert_report_size::<ForceFeedbackJoystickReport>(
+            FORCE_FEEDBACK_JOYSTICK_REPORT_DESCRIPTOR,
+            Input,
+            JOYSTICK_REPORT_ID,
+        );
+        assert_report_size::<PidStateReport>(
+            FORCE_FEEDBACK_JOYSTICK_REPORT_DESCRIPTOR,
+            Input,
+            PID_STATE_REPORT_ID,
+        );
+        assert_report_size::<SetEffectReport>(
+            FORCE_FEEDBACK_JOYSTICK_REPORT_DESCRIPTOR,
+            Output,
+            SET_EFFECT_REPORT_ID,
+        );
+        assert_report_size::<SetEnvelopeReport>(
+            FORCE_FEEDBACK_JOYSTICK_REPORT_DESCRIPTOR,
+            Output,
+            SET_ENVELOPE_REPORT_ID,
+        );
+        assert_report_size::<SetConstantForceReport>(
+            FORCE_FEEDBACK_JOYSTICK_REPORT_DESCRIPTOR,
+            Output,
+            SET_CONSTANT_FORCE_REPORT_ID,
+        );
+        assert_report_size::<EffectOperationReport>(
+            FORCE_FEEDBACK_JOYSTICK_REPORT_DESCRIPTOR,
+            Output,
+            EFFECT_OPERATION_REPORT_ID,
+        );
+        assert_report_size::<CreateNewEffectReport>(
+            FORCE_FEEDBACK_JOYSTICK_REPORT_DESCRIPTOR,
+            Feature,
+            CREATE_NEW_EFFECT_REPORT_ID,
+        );
+        assert_report_size::<BlockLoadReport>(
+            FORCE_FEEDBACK_JOYSTICK_REPORT_DESCRIPTOR,
+            Feature,
+            BLOCK_LOAD_REPORT_ID,
+        );
+        assert_report_size::<PidPoolReport>(
+            FORCE_FEEDBACK_JOYSTICK_REPORT_DESCRIPTOR,
+            Feature,
+            PID_POOL_REPORT_ID,
+        );
+    }
+
+    #[test]
+    fn size_mismatch_reported() {
+        use crate::device::mouse::*;
+
+        let ty = ReportType::Input;
+        assert!(
+            check_report_size::<BootMouseReport>(WHEEL_MOUSE_REPORT_DESCRIPTOR, ty, 0)
+                .unwrap_err()
+                .contains("BootMouseReport packs to 3 bytes")
+        );
+        assert!(check_report_size::<BootMouseReport>(BOOT_MOUSE_REPORT_DESCRIPTOR, ty, 1).is_err());
+        assert!(check_report_size::<BootMouseReport>(&[0xC0], ty, 0).is_err());
+    }
+
+    #[test]
+    #[should_panic(expected = "packs to")]
+    fn assert_panics_on_mismatch() {
+        use crate::device::mouse::*;
+
+        assert_report_size::<WheelMouseReport>(BOOT_MOUSE_REPORT_DESCRIPTOR, Input, 0);
+    }
+}